@@ -0,0 +1,232 @@
+//! Watching applied tweaks for changes made outside the app.
+//!
+//! The periodic revalidation (`snapshot_monitor`) only notices a tweak that Windows Update or
+//! another tool reverted at its next pass, up to a quarter of an hour later. This thread asks
+//! Windows to signal a change to any registry key an applied tweak's snapshot records
+//! (`RegNotifyChangeKeyValue`), and polls the startup type of the services the snapshots record
+//! every `POLL_INTERVAL`, since service configuration has no such notification. Either one asks the
+//! monitor for a pass right away (see `SnapshotMonitor::request_pass`), which reports the tweaks
+//! that no longer have the option they were applied with.
+//!
+//! The app's own changes signal too; the pass that follows finds those tweaks at the option their
+//! snapshots record and reports nothing for them.
+
+use crate::error::Result;
+use crate::models::ServiceStartupType;
+use crate::services::{backup_service, registry_service, service_control};
+use crate::state::AppState;
+use std::collections::{BTreeSet, HashMap};
+use std::ptr;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use windows_sys::Win32::Foundation::{
+    CloseHandle, ERROR_SUCCESS, FALSE, HANDLE, TRUE, WAIT_OBJECT_0,
+};
+use windows_sys::Win32::System::Registry::{
+    RegNotifyChangeKeyValue, REG_NOTIFY_CHANGE_LAST_SET, REG_NOTIFY_CHANGE_NAME,
+};
+use windows_sys::Win32::System::Threading::{
+    CreateEventW, WaitForMultipleObjects, MAXIMUM_WAIT_OBJECTS,
+};
+use winreg::RegKey;
+
+/// How often services are polled, and the watched keys brought up to date after a tweak operation
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Wait after a change before asking for a pass, so a burst of writes (an update rewriting a whole
+/// key) makes one pass
+const SETTLE: Duration = Duration::from_secs(2);
+
+/// Most keys one wait can watch
+const MAX_WATCHED_KEYS: usize = MAXIMUM_WAIT_OBJECTS as usize;
+
+/// What the snapshots of the applied tweaks record
+#[derive(Debug, Default)]
+struct Targets {
+    /// (hive, key path), the path lowercased since keys are case-insensitive
+    keys: BTreeSet<(String, String)>,
+    services: BTreeSet<String>,
+}
+
+fn targets() -> Result<Targets> {
+    let mut targets = Targets::default();
+    for tweak_id in backup_service::get_applied_tweaks()? {
+        let snapshot = match backup_service::load_snapshot(&tweak_id) {
+            Ok(Some(snapshot)) => snapshot,
+            Ok(None) => continue,
+            Err(e) => {
+                log::debug!("Not watching '{}': {}", tweak_id, e);
+                continue;
+            }
+        };
+        for registry in snapshot.registry_snapshots {
+            targets
+                .keys
+                .insert((registry.hive, registry.key.to_ascii_lowercase()));
+        }
+        for service in snapshot.service_snapshots {
+            targets.services.insert(service.name);
+        }
+    }
+    Ok(targets)
+}
+
+/// Keys registered for one change notification each. Dropping it closes the keys, which cancels
+/// the notifications.
+#[derive(Default)]
+struct KeyWatch {
+    keys: Vec<RegKey>,
+    events: Vec<HANDLE>,
+}
+
+impl Drop for KeyWatch {
+    fn drop(&mut self) {
+        self.keys.clear();
+        for event in self.events.drain(..) {
+            unsafe {
+                CloseHandle(event);
+            }
+        }
+    }
+}
+
+impl KeyWatch {
+    /// Register the keys that exist; a key a tweak creates is watched once it has been applied and
+    /// the watch is brought up to date
+    fn register(keys: &BTreeSet<(String, String)>) -> Self {
+        let mut watch = KeyWatch::default();
+        for (hive, key_path) in keys {
+            if watch.keys.len() == MAX_WATCHED_KEYS {
+                log::debug!(
+                    "Watching the first {} of {} keys; the rest are left to the periodic revalidation",
+                    MAX_WATCHED_KEYS,
+                    keys.len()
+                );
+                break;
+            }
+            let key = match backup_service::parse_hive(hive)
+                .and_then(|hive| registry_service::open_key_for_notify(&hive, key_path))
+            {
+                Ok(Some(key)) => key,
+                Ok(None) => continue,
+                Err(e) => {
+                    log::debug!("Not watching {}\\{}: {}", hive, key_path, e);
+                    continue;
+                }
+            };
+
+            let event = unsafe { CreateEventW(ptr::null(), FALSE, FALSE, ptr::null()) };
+            if event.is_null() {
+                log::warn!("Failed to create a change notification event");
+                break;
+            }
+            let status = unsafe {
+                RegNotifyChangeKeyValue(
+                    key.raw_handle(),
+                    FALSE,
+                    REG_NOTIFY_CHANGE_NAME | REG_NOTIFY_CHANGE_LAST_SET,
+                    event,
+                    TRUE,
+                )
+            };
+            if status != ERROR_SUCCESS {
+                log::debug!("Failed to watch {}\\{} (error {})", hive, key_path, status);
+                unsafe {
+                    CloseHandle(event);
+                }
+                continue;
+            }
+            watch.keys.push(key);
+            watch.events.push(event);
+        }
+        log::debug!(
+            "Watching {} registry keys of applied tweaks",
+            watch.keys.len()
+        );
+        watch
+    }
+
+    /// Wait up to `timeout` for a watched key to change. Returns whether one did.
+    fn wait(&self, timeout: Duration) -> bool {
+        if self.events.is_empty() {
+            std::thread::sleep(timeout);
+            return false;
+        }
+        let count = self.events.len() as u32;
+        let result = unsafe {
+            WaitForMultipleObjects(
+                count,
+                self.events.as_ptr(),
+                FALSE,
+                timeout.as_millis() as u32,
+            )
+        };
+        (WAIT_OBJECT_0..WAIT_OBJECT_0 + count).contains(&result)
+    }
+}
+
+/// The startup type of each service, None for one that can't be queried
+fn startup_types(services: &BTreeSet<String>) -> HashMap<String, Option<ServiceStartupType>> {
+    services
+        .iter()
+        .map(|name| {
+            let startup_type = service_control::get_service_status(name)
+                .ok()
+                .and_then(|status| status.startup_type);
+            (name.clone(), startup_type)
+        })
+        .collect()
+}
+
+/// Start the watcher thread. Call once `AppState` is managed.
+pub fn spawn(app: AppHandle) {
+    if let Err(e) = std::thread::Builder::new()
+        .name("change-watcher".into())
+        .spawn(move || run(app))
+    {
+        log::warn!("Failed to start the change watcher: {}", e);
+    }
+}
+
+fn run(app: AppHandle) {
+    let state = app.state::<AppState>();
+    let mut watch = KeyWatch::default();
+    let mut services = BTreeSet::new();
+    let mut polled = HashMap::new();
+    // Every tweak operation bumps the status cache's generation when it ends
+    let mut generation = None;
+
+    loop {
+        let current = state.status_cache.generation();
+        if generation != Some(current) {
+            generation = Some(current);
+            let targets = targets().unwrap_or_else(|e| {
+                log::warn!("Failed to read the applied tweaks to watch: {}", e);
+                Targets::default()
+            });
+            watch = KeyWatch::register(&targets.keys);
+            services = targets.services;
+            polled = startup_types(&services);
+        }
+
+        let changed = if watch.wait(POLL_INTERVAL) {
+            log::debug!("A registry key of an applied tweak changed");
+            true
+        } else {
+            let now = startup_types(&services);
+            let changed = now != polled;
+            if changed {
+                log::debug!("The startup type of a service of an applied tweak changed");
+            }
+            polled = now;
+            changed
+        };
+
+        if changed {
+            std::thread::sleep(SETTLE);
+            state.snapshot_monitor.request_pass();
+            // A notification fires once: register the keys again
+            generation = None;
+        }
+    }
+}
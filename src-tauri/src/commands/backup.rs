@@ -1,6 +1,17 @@
-use crate::error::Result;
-use crate::services::backup_service;
+use crate::commands::tweaks::apply::revert_option;
+use crate::debug::emit_event;
+use crate::error::{Error, Result};
+use crate::models::RegistryHive;
+use crate::services::journal_service::{OperationKind, PendingOperation};
+use crate::services::notes_service;
+use crate::services::{backup_service, shell_service, system_info_service, tweak_loader};
+use crate::state::AppState;
 use serde::Serialize;
+use std::path::Path;
+use tauri::State;
+
+/// Event reporting each tweak as `restore_all_to_baseline` works through them
+pub const RESTORE_ALL_PROGRESS_EVENT: &str = "restore-all-progress";
 
 /// Backup information for frontend display
 #[derive(Debug, Clone, Serialize)]
@@ -12,6 +23,8 @@ pub struct BackupInfo {
     pub registry_values_count: usize,
     pub service_snapshots_count: usize,
     pub scheduler_snapshots_count: usize,
+    /// The user's note on the tweak
+    pub note: Option<String>,
 }
 
 /// Check if a tweak has a snapshot (is applied)
@@ -38,11 +51,354 @@ pub fn get_backup_info(tweak_id: String) -> Result<Option<BackupInfo>> {
             registry_values_count: snapshot.registry_snapshots.len(),
             service_snapshots_count: snapshot.service_snapshots.len(),
             scheduler_snapshots_count: snapshot.scheduler_snapshots.len(),
+            note: notes_service::get_note(&tweak_id)?.map(|note| note.text),
         })),
         None => Ok(None),
     }
 }
 
+/// Export a tweak's snapshot as a .reg file that restores the original registry values
+#[tauri::command]
+pub fn export_snapshot_as_reg(tweak_id: String, path: String) -> Result<()> {
+    backup_service::export_snapshot_as_reg(&tweak_id, Path::new(&path))
+}
+
+/// Export the applied tweaks as a PowerShell script and .reg files that reapply them without the
+/// app, written to `dir`
+#[tauri::command]
+pub fn export_applied_tweaks_as_script(dir: String) -> Result<backup_service::ScriptExportReport> {
+    backup_service::export_applied_tweaks_as_script(Path::new(&dir))
+}
+
+/// Where snapshots are stored
+#[tauri::command]
+pub fn get_snapshot_location() -> Result<backup_service::SnapshotLocationInfo> {
+    backup_service::get_snapshot_location()
+}
+
+/// Store snapshots in a new location, optionally moving the existing ones there
+#[tauri::command]
+pub fn set_snapshot_location(
+    state: State<'_, AppState>,
+    location: backup_service::SnapshotLocation,
+    move_existing: Option<bool>,
+) -> Result<backup_service::SnapshotLocationInfo> {
+    let _operation = state.snapshot_monitor.operation();
+    let _stale = state.status_cache.changing_all();
+    backup_service::set_snapshot_location(location, move_existing.unwrap_or(true))
+}
+
+/// A tweak's snapshot history: the original state, then each kept generation, oldest first
+#[tauri::command]
+pub fn list_snapshot_generations(
+    tweak_id: String,
+) -> Result<Vec<backup_service::SnapshotGeneration>> {
+    backup_service::list_snapshot_generations(&tweak_id)
+}
+
+/// How many snapshot generations are kept per tweak, and for how long
+#[tauri::command]
+pub fn get_history_retention() -> backup_service::HistoryRetention {
+    backup_service::get_history_retention()
+}
+
+/// Change the snapshot history retention; existing history is pruned to match right away
+#[tauri::command]
+pub fn set_history_retention(
+    retention: backup_service::HistoryRetention,
+) -> Result<backup_service::HistoryRetention> {
+    backup_service::set_history_retention(retention)
+}
+
+/// Disk used by each applied tweak's snapshots and by the snapshots directory as a whole
+#[tauri::command]
+pub fn get_backup_storage_stats() -> Result<backup_service::BackupStorageStats> {
+    backup_service::get_backup_storage_stats()
+}
+
+/// Prune every tweak's snapshot history to the saved retention (count, age, and total size)
+#[tauri::command]
+pub fn cleanup_old_backups(state: State<'_, AppState>) -> Result<backup_service::CleanupReport> {
+    let _operation = state.snapshot_monitor.operation();
+    let _stale = state.status_cache.changing_all();
+    backup_service::cleanup_old_backups()
+}
+
+/// Export every snapshot (with its file backups) to one compressed archive.
+/// Returns the number of snapshots exported.
+#[tauri::command]
+pub fn export_backup_archive(path: String) -> Result<usize> {
+    backup_service::export_backup_archive(Path::new(&path))
+}
+
+/// Import the snapshots from a backup archive. Tweaks that already have a snapshot here are
+/// skipped unless `overwrite` is set.
+#[tauri::command]
+pub fn import_backup_archive(
+    state: State<'_, AppState>,
+    path: String,
+    overwrite: Option<bool>,
+) -> Result<backup_service::ArchiveImportReport> {
+    let _operation = state.snapshot_monitor.operation();
+    let _stale = state.status_cache.changing_all();
+    backup_service::import_backup_archive(Path::new(&path), overwrite.unwrap_or(false))
+}
+
+/// Check every snapshot's checksum, moving corrupt ones to the `corrupt` folder
+#[tauri::command]
+pub fn verify_snapshots() -> Result<backup_service::SnapshotVerifyReport> {
+    backup_service::verify_all_snapshots()
+}
+
+/// Whether snapshot files are written encrypted
+#[tauri::command]
+pub fn get_snapshot_encryption() -> bool {
+    backup_service::get_snapshot_encryption()
+}
+
+/// Turn snapshot encryption on or off, rewriting the existing snapshot files. Returns the number of
+/// files rewritten.
+#[tauri::command]
+pub fn set_snapshot_encryption(enabled: bool) -> Result<usize> {
+    log::info!("Command: set_snapshot_encryption({})", enabled);
+    backup_service::set_snapshot_encryption(enabled)
+}
+
+/// Whether tweaks reverted outside the app are applied again automatically
+#[tauri::command]
+pub fn get_auto_reapply() -> bool {
+    backup_service::get_auto_reapply()
+}
+
+/// Turn applying tweaks reverted outside the app (by Windows Update or another tool) on or off
+#[tauri::command]
+pub fn set_auto_reapply(enabled: bool) -> Result<()> {
+    log::info!("Command: set_auto_reapply({})", enabled);
+    backup_service::set_auto_reapply(enabled)
+}
+
+/// The baseline captured on first run, or None if there isn't one
+#[tauri::command]
+pub fn get_baseline_info() -> Result<Option<backup_service::BaselineInfo>> {
+    backup_service::get_baseline_info()
+}
+
+/// Capture the baseline again from the current state, replacing the first-run one
+#[tauri::command]
+pub async fn capture_baseline() -> Result<backup_service::BaselineInfo> {
+    log::info!("Command: capture_baseline");
+    backup_service::capture_baseline()
+}
+
+/// Put a registry key (and its subkeys) back to the state recorded in the baseline
+#[tauri::command]
+pub fn restore_key_to_baseline(
+    state: State<'_, AppState>,
+    hive: RegistryHive,
+    key: String,
+) -> Result<backup_service::BaselineRestoreResult> {
+    let _operation = state.snapshot_monitor.operation();
+    let _stale = state.status_cache.changing_all();
+    log::info!(
+        "Command: restore_key_to_baseline({}\\{})",
+        hive.as_str(),
+        key
+    );
+    backup_service::restore_key_to_baseline(hive, &key)
+}
+
+/// Compare a tweak's snapshot with the current state, so a revert can warn about items that
+/// changed since the tweak was applied
+#[tauri::command]
+pub fn diff_snapshot(tweak_id: String) -> Result<backup_service::SnapshotDiff> {
+    backup_service::diff_snapshot(&tweak_id)
+}
+
+/// Applied tweaks still waiting for a reboot; they can't be re-applied or reverted until then
+/// without forcing it
+#[tauri::command]
+pub fn get_pending_reboot_tweaks() -> Result<Vec<String>> {
+    backup_service::get_pending_reboot_tweaks()
+}
+
+/// Restore only some registry values/keys, services, and tasks of a tweak's snapshot; the rest of
+/// the tweak stays applied and the snapshot is kept
+#[tauri::command]
+pub async fn restore_changes(
+    state: State<'_, AppState>,
+    tweak_id: String,
+    selectors: Vec<backup_service::ChangeSelector>,
+) -> Result<backup_service::RestoreResult> {
+    let _operation = state.snapshot_monitor.operation();
+    let _stale = state.status_cache.changing([tweak_id.clone()]);
+    log::info!(
+        "Command: restore_changes({}, {} selectors)",
+        tweak_id,
+        selectors.len()
+    );
+
+    if !system_info_service::get_runtime_context()?.is_admin {
+        return Err(Error::RequiresAdmin);
+    }
+    backup_service::restore_changes(&tweak_id, &selectors)
+}
+
+/// Stage of one tweak in a `restore-all-progress` event
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RestoreAllStage {
+    Started,
+    Finished,
+    Failed,
+}
+
+/// Progress of one tweak while all snapshots are restored
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreAllProgress {
+    pub tweak_id: String,
+    pub tweak_name: String,
+    /// 1-based position of this tweak in the run
+    pub index: usize,
+    pub total: usize,
+    pub stage: RestoreAllStage,
+}
+
+/// Outcome of reverting one tweak during `restore_all_to_baseline`
+#[derive(Debug, Clone, Serialize)]
+pub struct TweakRestoreReport {
+    pub tweak_id: String,
+    pub tweak_name: String,
+    /// Whether everything was restored (and the snapshot released)
+    pub success: bool,
+    pub message: String,
+    /// What couldn't be restored; the snapshot is kept so the revert can be retried
+    pub failures: Vec<String>,
+}
+
+/// Result of `restore_all_to_baseline`, with one report per tweak in the order they were reverted
+#[derive(Debug, Clone, Serialize)]
+pub struct RestoreAllReport {
+    pub success: bool,
+    pub message: String,
+    pub requires_reboot: bool,
+    /// Whether a sign-out is needed (only set when no reboot is, since a reboot covers it)
+    pub requires_logoff: bool,
+    pub restarted_explorer: bool,
+    pub results: Vec<TweakRestoreReport>,
+}
+
+/// Revert every applied tweak to its original state in one pass, most recently applied first (see
+/// `get_applied_tweaks_in_revert_order`). A failed tweak doesn't stop the rest; Explorer is
+/// restarted at most once, at the end.
+#[tauri::command]
+pub async fn restore_all_to_baseline(state: State<'_, AppState>) -> Result<RestoreAllReport> {
+    let _operation = state.snapshot_monitor.operation();
+    let _stale = state.status_cache.changing_all();
+    let tweak_ids = backup_service::get_applied_tweaks_in_revert_order()?;
+    log::info!(
+        "Command: restore_all_to_baseline({} tweaks)",
+        tweak_ids.len()
+    );
+
+    if !system_info_service::get_runtime_context()?.is_admin {
+        return Err(Error::RequiresAdmin);
+    }
+
+    let total = tweak_ids.len();
+    let mut requires_reboot = false;
+    let mut requires_logoff = false;
+    let mut restart_explorer = false;
+    let mut results = Vec::with_capacity(total);
+    let mut journal = PendingOperation::new(OperationKind::RestoreAll);
+
+    for (index, tweak_id) in tweak_ids.into_iter().enumerate() {
+        let tweak_name = tweak_loader::get_tweak(&tweak_id)
+            .ok()
+            .flatten()
+            .map_or_else(|| tweak_id.clone(), |tweak| tweak.name);
+        let progress = |stage| {
+            emit_event(
+                RESTORE_ALL_PROGRESS_EVENT,
+                RestoreAllProgress {
+                    tweak_id: tweak_id.clone(),
+                    tweak_name: tweak_name.clone(),
+                    index: index + 1,
+                    total,
+                    stage,
+                },
+            )
+        };
+        progress(RestoreAllStage::Started);
+
+        let report = match journal.track(&tweak_id, || revert_option(tweak_id.clone())) {
+            Ok((result, wants_explorer_restart)) => {
+                restart_explorer |= wants_explorer_restart;
+                requires_reboot |= result.requires_reboot;
+                requires_logoff |= result.requires_logoff;
+                TweakRestoreReport {
+                    tweak_id: tweak_id.clone(),
+                    tweak_name: tweak_name.clone(),
+                    success: result.success,
+                    failures: result.failures.into_iter().map(|(_, msg)| msg).collect(),
+                    message: result.message,
+                }
+            }
+            Err(e) => {
+                log::warn!("Failed to revert tweak '{}': {}", tweak_id, e);
+                TweakRestoreReport {
+                    tweak_id: tweak_id.clone(),
+                    tweak_name: tweak_name.clone(),
+                    success: false,
+                    message: e.to_string(),
+                    failures: Vec::new(),
+                }
+            }
+        };
+
+        progress(if report.success {
+            RestoreAllStage::Finished
+        } else {
+            RestoreAllStage::Failed
+        });
+        results.push(report);
+    }
+
+    journal.record();
+
+    let restored = results.iter().filter(|r| r.success).count();
+    let message = if restored == total {
+        format!("Restored {} tweaks to their original state", restored)
+    } else {
+        format!(
+            "Restored {}/{} tweaks ({} need attention)",
+            restored,
+            total,
+            total - restored
+        )
+    };
+    log::info!("restore_all_to_baseline completed: {}", message);
+
+    // Once for the whole run rather than once per tweak; a failed restart doesn't undo the reverts
+    let restarted_explorer = restart_explorer
+        && match shell_service::restart_explorer() {
+            Ok(()) => true,
+            Err(e) => {
+                log::warn!("Failed to restart explorer.exe: {}", e);
+                false
+            }
+        };
+
+    Ok(RestoreAllReport {
+        success: restored == total,
+        message,
+        requires_reboot,
+        requires_logoff: requires_logoff && !requires_reboot,
+        restarted_explorer,
+        results,
+    })
+}
+
 /// Validate all snapshots on app startup
 /// Removes stale snapshots where current registry state matches the snapshot state
 /// Returns the number of stale snapshots removed
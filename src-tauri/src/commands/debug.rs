@@ -1,4 +1,10 @@
 use crate::debug::set_debug_enabled;
+use crate::error::Result;
+use crate::metrics::OperationTiming;
+use crate::models::ProcessPrivileges;
+use crate::services::trusted_installer;
+use crate::state::AppState;
+use tauri::State;
 
 /// Enable or disable debug mode
 #[tauri::command]
@@ -9,3 +15,17 @@ pub fn set_debug_mode(enabled: bool) {
         if enabled { "enabled" } else { "disabled" }
     );
 }
+
+/// Phase timings of the last tweak applies and reverts, oldest first, to find slow phases (an
+/// elevated helper that takes long to start, a slow service stop)
+#[tauri::command]
+pub fn get_performance_metrics(state: State<'_, AppState>) -> Vec<OperationTiming> {
+    state.metrics.recent()
+}
+
+/// The app process's token: its privileges, integrity level, and elevation type, so an elevation
+/// failure (e.g. SeDebugPrivilege removed by policy) can be diagnosed without reading the logs
+#[tauri::command]
+pub fn get_process_privileges() -> Result<ProcessPrivileges> {
+    trusted_installer::process_privileges()
+}
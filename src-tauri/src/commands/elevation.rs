@@ -1,10 +1,13 @@
 //! Elevation Commands
 //!
-//! Commands for SYSTEM elevation to modify protected registry keys
-//! and restarting the app with admin privileges.
+//! Commands for SYSTEM elevation to modify protected registry keys, cancelling elevated
+//! operations, reviewing what ran elevated, and restarting the app with admin privileges.
 
-use crate::error::Result;
-use crate::services::trusted_installer;
+use crate::error::{Error, Result};
+use crate::models::ChangeElevation;
+use crate::services::audit::{self, AuditLog};
+use crate::services::relaunch_service::{self, PendingOperation};
+use crate::services::{system_info_service, trusted_installer};
 
 /// Check if SYSTEM elevation is available (i.e., running as admin)
 #[tauri::command]
@@ -12,59 +15,79 @@ pub async fn can_use_system_elevation() -> Result<bool> {
     Ok(trusted_installer::can_use_system_elevation())
 }
 
+/// Start the SYSTEM or TrustedInstaller broker ahead of the first operation that needs it, so that
+/// operation doesn't wait for the TI service or the process start. Called when the user opens a
+/// category with tweaks at that level; an unused broker is closed again after a few minutes idle.
+#[tauri::command]
+pub async fn warm_up_elevation(level: ChangeElevation) -> Result<()> {
+    let level = level.level();
+    if !level.is_elevated() {
+        return Ok(());
+    }
+    if !trusted_installer::can_use_system_elevation() {
+        return Err(Error::RequiresAdmin);
+    }
+    log::debug!("Command: warm_up_elevation({})", level.label());
+    tauri::async_runtime::spawn_blocking(move || trusted_installer::warm_up_session(level))
+        .await
+        .map_err(|e| Error::ServiceControl(format!("warm-up task failed: {}", e)))?
+}
+
+/// Cancel the SYSTEM / TrustedInstaller operations running now, such as a hung elevated command.
+/// Each fails with a `CANCELLED` error; the tweak operation it belongs to handles that like any
+/// other failure.
+#[tauri::command]
+pub fn cancel_elevated_operations() -> Result<()> {
+    log::info!("Command: cancel_elevated_operations");
+    trusted_installer::cancel_elevated_operations();
+    Ok(())
+}
+
+/// Everything run as SYSTEM or TrustedInstaller, newest first, with whether the log's hash chain
+/// is intact
+#[tauri::command]
+pub fn get_elevation_audit_log() -> Result<AuditLog> {
+    audit::read_log()
+}
+
 /// Restart the application with administrator privileges
 /// Uses ShellExecuteW with "runas" verb to trigger UAC prompt
 #[tauri::command]
 pub async fn restart_as_admin(app: tauri::AppHandle) -> Result<()> {
-    use std::ffi::OsStr;
-    use std::os::windows::ffi::OsStrExt;
-    use std::ptr;
-    use windows_sys::Win32::UI::Shell::ShellExecuteW;
-    use windows_sys::Win32::UI::WindowsAndMessaging::SW_SHOWNORMAL;
-
     log::info!("Restart as admin requested");
+    relaunch_service::relaunch_elevated(&[])?;
 
-    // Get current executable path
-    let exe_path = std::env::current_exe().map_err(|e| {
-        crate::error::Error::WindowsApi(format!("Failed to get executable path: {}", e))
-    })?;
-
-    let exe_path_wide: Vec<u16> = OsStr::new(&exe_path)
-        .encode_wide()
-        .chain(std::iter::once(0))
-        .collect();
-
-    let runas: Vec<u16> = OsStr::new("runas")
-        .encode_wide()
-        .chain(std::iter::once(0))
-        .collect();
-
-    // SAFETY: ShellExecuteW is called with valid null-terminated wide strings.
-    // The operation is "runas" which triggers UAC elevation. Return value > 32
-    // indicates success.
-    unsafe {
-        let result = ShellExecuteW(
-            ptr::null_mut(),
-            runas.as_ptr(),
-            exe_path_wide.as_ptr(),
-            ptr::null(),
-            ptr::null(),
-            SW_SHOWNORMAL,
-        );
+    log::info!("New admin instance started, exiting current instance");
+    app.exit(0);
+    Ok(())
+}
 
-        // ShellExecuteW returns a value > 32 on success
-        if result as usize <= 32 {
-            return Err(crate::error::Error::WindowsApi(format!(
-                "Failed to restart as admin, error code: {}",
-                result as usize
-            )));
-        }
+/// Relaunch the application elevated through the UAC prompt, handing the changes the user staged
+/// to the new instance (see `take_resumed_operations`), then exit this one
+#[tauri::command]
+pub async fn relaunch_elevated(
+    app: tauri::AppHandle,
+    pending: Vec<PendingOperation>,
+) -> Result<()> {
+    log::info!(
+        "Command: relaunch_elevated({} staged changes)",
+        pending.len()
+    );
+    if system_info_service::is_running_as_admin() {
+        return Err(Error::ValidationError(
+            "Already running as administrator".to_string(),
+        ));
     }
+    relaunch_service::relaunch_elevated(&pending)?;
 
     log::info!("New admin instance started, exiting current instance");
-
-    // Exit the current (non-admin) instance
     app.exit(0);
-
     Ok(())
 }
+
+/// The staged changes handed over by the instance that relaunched this one; empty after the first
+/// call, or when the app wasn't relaunched
+#[tauri::command]
+pub fn take_resumed_operations() -> Vec<PendingOperation> {
+    relaunch_service::take_resumed_operations()
+}
@@ -1,10 +1,133 @@
-use crate::error::Result;
-use crate::models::SystemInfo;
-use crate::services::system_info_service;
+use crate::error::{Error, Result};
+use crate::models::{HardwareSection, RestorePoint, SystemInfo, ThermalInfo};
+use crate::services::software_service::{self, InstalledSoftware};
+use crate::services::startup_service::{self, StartupChange, StartupItem};
+use crate::services::user_profile_service::{self, UserProfile};
+use crate::services::windows_update_service::{self, UpdateStatus};
+use crate::services::{monitor_service, restore_point_service, system_info_service};
+use crate::state::AppState;
+use tauri::State;
 
-/// Get system information (Windows version, admin status, etc.)
+/// Get system information (Windows version, admin status, etc.). The hardware comes from the
+/// cache, so only the sections past their TTL are queried again.
 #[tauri::command]
-pub async fn get_system_info() -> Result<SystemInfo> {
-    let info = system_info_service::get_system_info()?;
+pub async fn get_system_info(state: State<'_, AppState>) -> Result<SystemInfo> {
+    let (hardware, device) = state.system_info.get();
+    let info = system_info_service::get_system_info(hardware, device)?;
     Ok(info)
 }
+
+/// Get system information after gathering the given hardware sections again (all of them if
+/// `sections` is omitted or empty), e.g. just `disks` after a drive was plugged in
+#[tauri::command]
+pub async fn refresh_system_info(
+    state: State<'_, AppState>,
+    sections: Option<Vec<HardwareSection>>,
+) -> Result<SystemInfo> {
+    let (hardware, device) = state
+        .system_info
+        .refresh(sections.as_deref().unwrap_or_default());
+    let info = system_info_service::get_system_info(hardware, device)?;
+    Ok(info)
+}
+
+/// CPU/GPU temperatures and fan speeds, from a running LibreHardwareMonitor or OpenHardwareMonitor,
+/// else from ACPI thermal zones
+#[tauri::command]
+pub async fn get_thermal_info() -> ThermalInfo {
+    system_info_service::get_thermal_info()
+}
+
+/// Stream live CPU, memory, disk, and GPU usage (and with `thermal`, CPU and GPU temperatures) as
+/// `system://metrics` events every `interval_ms` (default 1000, clamped to 250-60000), replacing a
+/// monitor already running. Returns the interval used.
+#[tauri::command]
+pub fn start_monitoring(interval_ms: Option<u64>, thermal: Option<bool>) -> Result<u64> {
+    monitor_service::start(interval_ms, thermal.unwrap_or(false))
+}
+
+/// Stop the live usage events. Returns false if they weren't running.
+#[tauri::command]
+pub fn stop_monitoring() -> bool {
+    monitor_service::stop()
+}
+
+/// Programs installed on this machine (machine-wide and for the app's account), sorted by name
+#[tauri::command]
+pub async fn get_installed_software() -> Result<Vec<InstalledSoftware>> {
+    software_service::get_installed_software()
+}
+
+/// Programs that start at sign-in (Run keys, Startup folders, logon tasks) with their measured
+/// impact, sorted by name
+#[tauri::command]
+pub async fn get_startup_items() -> Result<Vec<StartupItem>> {
+    startup_service::get_startup_items()
+}
+
+/// Enable or disable the startup entry with `id`, recording its prior state for undo
+#[tauri::command]
+pub async fn set_startup_item_enabled(id: String, enabled: bool) -> Result<()> {
+    log::info!("Command: set_startup_item_enabled('{}', {})", id, enabled);
+    startup_service::set_enabled(&id, enabled)
+}
+
+/// The startup changes that can be undone, newest first
+#[tauri::command]
+pub async fn get_startup_changes() -> Result<Vec<StartupChange>> {
+    startup_service::list_changes()
+}
+
+/// Undo the most recent startup change. Returns it, or null if there's nothing to undo.
+#[tauri::command]
+pub async fn undo_startup_change() -> Result<Option<StartupChange>> {
+    log::info!("Command: undo_startup_change");
+    startup_service::undo_last_change()
+}
+
+/// Windows Update's pending updates (as of its last scan), last install, pause, and pending reboot
+#[tauri::command]
+pub async fn get_update_status() -> Result<UpdateStatus> {
+    windows_update_service::get_update_status()
+}
+
+/// Create a System Restore point with the given description
+#[tauri::command]
+pub async fn create_restore_point(description: String) -> Result<RestorePoint> {
+    log::info!("Command: create_restore_point('{}')", description);
+    if !system_info_service::get_runtime_context()?.is_admin {
+        return Err(Error::RequiresAdmin);
+    }
+    restore_point_service::create_restore_point(&description)
+}
+
+/// List the System Restore points on this machine, oldest first
+#[tauri::command]
+pub async fn list_restore_points() -> Result<Vec<RestorePoint>> {
+    restore_point_service::list_restore_points()
+}
+
+/// User profiles on this machine, for picking the user HKCU tweaks apply to
+#[tauri::command]
+pub async fn list_user_profiles() -> Result<Vec<UserProfile>> {
+    user_profile_service::list_profiles()
+}
+
+/// SID of the user HKCU tweaks apply to, or null for the account the app runs as
+#[tauri::command]
+pub fn get_target_user() -> Option<String> {
+    user_profile_service::target_user()
+}
+
+/// Apply HKCU tweaks to the user with `sid` (null: the account the app runs as) for the rest of
+/// the session, loading their hive if they aren't signed in
+#[tauri::command]
+pub async fn set_target_user(
+    state: State<'_, AppState>,
+    sid: Option<String>,
+) -> Result<Option<UserProfile>> {
+    let _operation = state.snapshot_monitor.operation();
+    let _stale = state.status_cache.changing_all();
+    log::info!("Command: set_target_user({:?})", sid);
+    user_profile_service::set_target_user(sid.as_deref())
+}
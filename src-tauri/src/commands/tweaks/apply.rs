@@ -1,10 +1,19 @@
 //! Apply Commands - Single tweak apply/revert operations
 
-use super::helpers::{apply_all_changes_atomically, run_command, run_powershell_command};
+use super::helpers::{
+    apply_all_changes_atomically, command_error, run_command, run_powershell_command,
+};
 use crate::debug::{emit_debug_log, is_debug_enabled, DebugLevel};
 use crate::error::{Error, Result};
+use crate::metrics::{self, TimedOperation};
 use crate::models::TweakResult;
-use crate::services::{backup_service, system_info_service, tweak_loader};
+use crate::operations;
+use crate::risk_acks::RiskAcknowledgement;
+use crate::services::journal_service::{OperationKind, PendingOperation};
+use crate::services::usage_stats_service::{self, UsageAction};
+use crate::services::{backup_service, shell_service, system_info_service, tweak_loader};
+use crate::state::AppState;
+use tauri::State;
 
 /// Outcome of the automatic rollback that follows a failed apply.
 ///
@@ -16,7 +25,7 @@ use crate::services::{backup_service, system_info_service, tweak_loader};
 /// See `docs/adr/0001-rollback-failure-is-a-first-class-state.md` and
 /// `docs/adr/0002-snapshot-deletion-requires-verification-or-consent.md`.
 #[derive(Debug, PartialEq, Eq)]
-enum RollbackOutcome {
+pub(super) enum RollbackOutcome {
     /// Nothing had been captured, so there was nothing to roll back to.
     NothingToRollBack,
     /// Every captured resource was verifiably restored.
@@ -28,7 +37,9 @@ enum RollbackOutcome {
 /// Classify a rollback attempt. `None` means no rollback was attempted.
 ///
 /// Only `Verified` and `NothingToRollBack` permit releasing a snapshot.
-fn classify_rollback(rollback: Option<Result<backup_service::RestoreResult>>) -> RollbackOutcome {
+pub(super) fn classify_rollback(
+    rollback: Option<Result<backup_service::RestoreResult>>,
+) -> RollbackOutcome {
     match rollback {
         None => RollbackOutcome::NothingToRollBack,
         Some(Ok(result)) if result.success => RollbackOutcome::Verified,
@@ -45,6 +56,18 @@ fn classify_rollback(rollback: Option<Result<backup_service::RestoreResult>>) ->
     }
 }
 
+/// Restart explorer.exe if an operation asked for it, recording the outcome on its result.
+/// A failed restart is logged rather than returned: the change itself went through.
+pub(super) fn restart_explorer_if_requested(result: &mut TweakResult, requested: bool) {
+    if !requested {
+        return;
+    }
+    match shell_service::restart_explorer() {
+        Ok(()) => result.restarted_explorer = true,
+        Err(e) => log::warn!("Failed to restart explorer.exe: {}", e),
+    }
+}
+
 /// Apply a specific option for a tweak
 ///
 /// For toggle tweaks (is_toggle: true):
@@ -53,8 +76,86 @@ fn classify_rollback(rollback: Option<Result<backup_service::RestoreResult>>) ->
 ///
 /// For dropdown tweaks (is_toggle: false):
 /// - option_index corresponds to the options array index
+///
+/// A tweak still waiting for a reboot from its last change is refused unless `force` is set.
+/// With an `operation_id`, `cancel_operation` can stop the apply while it runs; it then fails with
+/// a `CANCELLED` error after rolling back what it changed. A high or critical risk tweak needs the
+/// `risk_ack` token from `acknowledge_risk`, and fails with `RISK_NOT_ACKNOWLEDGED` without one.
 #[tauri::command]
-pub async fn apply_tweak(tweak_id: String, option_index: usize) -> Result<TweakResult> {
+pub async fn apply_tweak(
+    state: State<'_, AppState>,
+    tweak_id: String,
+    option_index: usize,
+    force: Option<bool>,
+    operation_id: Option<String>,
+    risk_ack: Option<String>,
+) -> Result<TweakResult> {
+    let _operation = state.snapshot_monitor.operation();
+    let _stale = state.status_cache.changing([tweak_id.clone()]);
+    let _cancellable = state.operations.begin(operation_id)?;
+    if !force.unwrap_or(false) {
+        backup_service::ensure_no_pending_reboot(&tweak_id)?;
+    }
+    // Last, so an apply refused above leaves the acknowledgement for the next try
+    redeem_risk_acknowledgement(&state, &tweak_id, risk_ack.as_slice())?;
+    let mut journal = PendingOperation::new(OperationKind::Apply);
+    let outcome = journal.track(&tweak_id, || apply_option(tweak_id.clone(), option_index));
+    journal.record();
+    let (mut result, restart_explorer) = outcome?;
+    restart_explorer_if_requested(&mut result, restart_explorer);
+    Ok(result)
+}
+
+/// Acknowledge the risk of applying a high or critical risk tweak. The token returned lets one
+/// `apply_tweak` or `batch_apply_tweaks` apply it within `risk_acks::TOKEN_LIFETIME`.
+#[tauri::command]
+pub fn acknowledge_risk(
+    state: State<'_, AppState>,
+    tweak_id: String,
+) -> Result<RiskAcknowledgement> {
+    let tweak = tweak_loader::get_tweak(&tweak_id)?
+        .ok_or_else(|| Error::NotFound(format!("Tweak '{}'", tweak_id)))?;
+    log::info!(
+        "Command: acknowledge_risk({}, {:?} risk)",
+        tweak_id,
+        tweak.risk_level
+    );
+    state.risk_acks.issue(&tweak_id)
+}
+
+/// Allow applying `tweak_id` if its risk level doesn't need acknowledging, or one of `tokens`
+/// acknowledges it (see `risk_acks`)
+pub(super) fn redeem_risk_acknowledgement(
+    state: &AppState,
+    tweak_id: &str,
+    tokens: &[String],
+) -> Result<()> {
+    let tweak = tweak_loader::get_tweak(tweak_id)?
+        .ok_or_else(|| Error::NotFound(format!("Tweak '{}'", tweak_id)))?;
+    state.risk_acks.redeem(tweak_id, tweak.risk_level, tokens)
+}
+
+/// Cancel the `apply_tweak` or `batch_apply_tweaks` started with `operation_id`. It stops before
+/// its next change or elevated call (a running elevated call is stopped too), rolls back, and fails
+/// with `CANCELLED`. Returns false when no such operation is running.
+#[tauri::command]
+pub fn cancel_operation(state: State<'_, AppState>, operation_id: String) -> Result<bool> {
+    log::info!("Command: cancel_operation({})", operation_id);
+    Ok(state.operations.cancel(&operation_id))
+}
+
+/// [`apply_tweak`] without the Explorer restart, so a batch can restart it once at the end.
+/// Returns the result and whether the applied option asks for Explorer to be restarted. Its phases
+/// are timed (see `metrics`), and it's counted in the usage statistics.
+pub(crate) fn apply_option(tweak_id: String, option_index: usize) -> Result<(TweakResult, bool)> {
+    let timer = metrics::start(&tweak_id, TimedOperation::Apply);
+    let outcome = run_apply(tweak_id.clone(), option_index);
+    timer.finish(outcome.as_ref().is_ok_and(|(result, _)| result.success));
+    usage_stats_service::record(&tweak_id, UsageAction::Apply, &outcome);
+    outcome
+}
+
+fn run_apply(tweak_id: String, option_index: usize) -> Result<(TweakResult, bool)> {
     log::info!(
         "Command: apply_tweak({}, option_index={})",
         tweak_id,
@@ -65,6 +166,7 @@ pub async fn apply_tweak(tweak_id: String, option_index: usize) -> Result<TweakR
         log::error!("Tweak not found: {}", tweak_id);
         Error::NotFound(format!("Tweak '{}'", tweak_id))
     })?;
+    operations::check(&format!("applying '{}'", tweak.name))?;
 
     // Validate option_index
     if option_index >= tweak.options.len() {
@@ -76,7 +178,19 @@ pub async fn apply_tweak(tweak_id: String, option_index: usize) -> Result<TweakR
         )));
     }
 
+    if !tweak.is_available(system_info_service::system_facts()) {
+        return Err(Error::ValidationError(format!(
+            "Tweak '{}' isn't offered on this Windows build or edition",
+            tweak.name
+        )));
+    }
     let option = &tweak.options[option_index];
+    if !option.is_available(system_info_service::system_facts()) {
+        return Err(Error::ValidationError(format!(
+            "Option '{}' of tweak '{}' isn't available on this machine (its conditions don't match)",
+            option.label, tweak.name
+        )));
+    }
     let runtime = system_info_service::get_runtime_context()?;
     let version = runtime.windows_version();
 
@@ -94,19 +208,29 @@ pub async fn apply_tweak(tweak_id: String, option_index: usize) -> Result<TweakR
     }
 
     // Check if already at this option
-    let current_state = backup_service::detect_tweak_state(&tweak, version)?;
+    let current_state = metrics::phase("detection", || {
+        backup_service::detect_tweak_state(&tweak, version)
+    })?;
     if current_state.current_option_index == Some(option_index) {
         log::info!(
             "Tweak '{}' is already at option '{}', skipping",
             tweak.name,
             option.label
         );
-        return Ok(TweakResult {
-            success: true,
-            message: format!("Already at option: {}", option.label),
-            requires_reboot: false,
-            failures: Vec::new(),
-        });
+        return Ok((
+            TweakResult {
+                success: true,
+                message: format!("Already at option: {}", option.label),
+                requires_reboot: false,
+                requires_logoff: false,
+                restarted_explorer: false,
+                failures: Vec::new(),
+                restore_point_sequence: None,
+                results: Vec::new(),
+                rollback: None,
+            },
+            false,
+        ));
     }
 
     if is_debug_enabled() {
@@ -124,13 +248,45 @@ pub async fn apply_tweak(tweak_id: String, option_index: usize) -> Result<TweakR
             "Switching options for '{}': capturing current state for potential rollback",
             tweak.name
         );
-        Some(backup_service::capture_current_state(&tweak, version)?)
+        Some(metrics::phase("snapshot capture", || {
+            backup_service::capture_current_state(&tweak, version)
+        })?)
     } else {
+        None
+    };
+
+    // Write-ahead intent: if the app or machine dies before this apply returns, the next start
+    // offers to roll it back. Written before the first snapshot is saved, so a crash in between
+    // leaves an intent with no snapshot, which rolls back to nothing.
+    let previous_option = if is_switching_options {
+        backup_service::load_snapshot(&tweak_id)?
+            .map(|snapshot| (snapshot.applied_option_index, snapshot.applied_option_label))
+    } else {
+        None
+    };
+    let _intent = backup_service::begin_apply_intent(&backup_service::ApplyIntent {
+        tweak_id: tweak_id.clone(),
+        tweak_name: tweak.name.clone(),
+        option_index,
+        option_label: option.label.clone(),
+        started_at: chrono::Local::now().to_rfc3339(),
+        previous_option,
+        previous_state: pre_apply_state.clone(),
+    })?;
+
+    if !is_switching_options {
         // Capture original state - pass current_option_index so we know if original was unknown
         let original_option_index = current_state.current_option_index;
-        let snapshot =
-            backup_service::capture_snapshot(&tweak, option_index, version, original_option_index)?;
-        backup_service::save_snapshot(&snapshot)?;
+        let snapshot = metrics::phase("snapshot capture", || {
+            let snapshot = backup_service::capture_snapshot(
+                &tweak,
+                option_index,
+                version,
+                original_option_index,
+            )?;
+            backup_service::save_snapshot(&snapshot)?;
+            Ok::<_, Error>(snapshot)
+        })?;
         log::info!(
             "Captured original snapshot for '{}' with {} registry values, {} services (original_option_index={:?})",
             tweak.name,
@@ -138,48 +294,53 @@ pub async fn apply_tweak(tweak_id: String, option_index: usize) -> Result<TweakR
             snapshot.service_snapshots.len(),
             original_option_index
         );
-        None
-    };
+    }
 
-    // Step 2: Run pre_commands if defined (non-reversible, fail-fast)
-    for cmd in &option.pre_commands {
-        if let Err(e) = run_command(cmd, tweak.elevation()) {
-            log::error!("Pre-command failed, aborting: {}", e);
-            if !is_switching_options {
-                if let Err(del_err) = backup_service::delete_snapshot(&tweak_id) {
-                    log::warn!(
-                        "Failed to delete snapshot for '{}' after pre-command failure: {}",
-                        tweak_id,
-                        del_err
-                    );
+    // Steps 2-3: Run pre_commands, then pre_powershell, if defined (non-reversible, fail-fast)
+    metrics::phase("commands", || -> Result<()> {
+        for cmd in &option.pre_commands {
+            if let Err(e) = run_command(
+                cmd,
+                option.command_shell(),
+                tweak.elevation(),
+                option.command_timeout(),
+            ) {
+                log::error!("Pre-command failed, aborting: {}", e);
+                if !is_switching_options {
+                    if let Err(del_err) = backup_service::delete_snapshot(&tweak_id) {
+                        log::warn!(
+                            "Failed to delete snapshot for '{}' after pre-command failure: {}",
+                            tweak_id,
+                            del_err
+                        );
+                    }
                 }
+                return Err(command_error("Pre-command failed", e));
             }
-            return Err(Error::CommandExecution(format!(
-                "Pre-command failed: {}",
-                e
-            )));
         }
-    }
 
-    // Step 3: Run pre_powershell if defined (non-reversible, fail-fast)
-    for ps_cmd in &option.pre_powershell {
-        if let Err(e) = run_powershell_command(ps_cmd, tweak.elevation()) {
-            log::error!("Pre-PowerShell command failed, aborting: {}", e);
-            if !is_switching_options {
-                if let Err(del_err) = backup_service::delete_snapshot(&tweak_id) {
-                    log::warn!(
-                        "Failed to delete snapshot for '{}' after pre-PowerShell failure: {}",
-                        tweak_id,
-                        del_err
-                    );
+        for ps_cmd in &option.pre_powershell {
+            if let Err(e) = run_powershell_command(
+                ps_cmd,
+                option.powershell_engine(),
+                tweak.elevation(),
+                option.command_timeout(),
+            ) {
+                log::error!("Pre-PowerShell command failed, aborting: {}", e);
+                if !is_switching_options {
+                    if let Err(del_err) = backup_service::delete_snapshot(&tweak_id) {
+                        log::warn!(
+                            "Failed to delete snapshot for '{}' after pre-PowerShell failure: {}",
+                            tweak_id,
+                            del_err
+                        );
+                    }
                 }
+                return Err(command_error("Pre-PowerShell failed", e));
             }
-            return Err(Error::CommandExecution(format!(
-                "Pre-PowerShell failed: {}",
-                e
-            )));
         }
-    }
+        Ok(())
+    })?;
 
     // Steps 4-6: Apply all core changes ATOMICALLY
     if let Err(e) = apply_all_changes_atomically(&tweak, option, version) {
@@ -188,16 +349,25 @@ pub async fn apply_tweak(tweak_id: String, option_index: usize) -> Result<TweakR
         // Roll back based on context. The result is deliberately NOT discarded:
         // rollback can itself fail, and when it does the machine is left partly
         // changed with the snapshot as the only remaining route back (ADR-0002).
-        let rollback = match pre_apply_state {
-            Some(ref previous_option_state) => {
-                log::warn!("Rolling back to previous option state (switching options failed)...");
-                Some(backup_service::restore_from_snapshot(previous_option_state))
-            }
-            None => backup_service::load_snapshot(&tweak_id)?.map(|snapshot| {
-                log::warn!("Rolling back ALL changes to original state (first apply failed)...");
-                backup_service::restore_from_snapshot(&snapshot)
-            }),
-        };
+        // A cancelled apply ends up here too, and its rollback must run to the end.
+        let rollback = metrics::phase("rollback", || {
+            operations::uncancellable(|| -> Result<_> {
+                Ok(match pre_apply_state {
+                    Some(ref previous_option_state) => {
+                        log::warn!(
+                            "Rolling back to previous option state (switching options failed)..."
+                        );
+                        Some(backup_service::restore_from_snapshot(previous_option_state))
+                    }
+                    None => backup_service::load_snapshot(&tweak_id)?.map(|snapshot| {
+                        log::warn!(
+                            "Rolling back ALL changes to original state (first apply failed)..."
+                        );
+                        backup_service::restore_from_snapshot(&snapshot)
+                    }),
+                })
+            })
+        })?;
 
         let rollback_failures = match classify_rollback(rollback) {
             RollbackOutcome::NothingToRollBack | RollbackOutcome::Verified => {
@@ -252,71 +422,161 @@ pub async fn apply_tweak(tweak_id: String, option_index: usize) -> Result<TweakR
                 .map(|msg| (tweak_id.clone(), format!("rollback: {}", msg))),
         );
 
-        return Ok(TweakResult {
-            success: false,
-            message: format!(
-                "Apply failed and rollback was incomplete: {} operation(s) could not be \
-                 restored. The snapshot has been kept so you can retry reverting.",
-                incomplete_count
-            ),
-            requires_reboot: false,
-            failures,
-        });
+        return Ok((
+            TweakResult {
+                success: false,
+                message: format!(
+                    "Apply failed and rollback was incomplete: {} operation(s) could not be \
+                     restored. The snapshot has been kept so you can retry reverting.",
+                    incomplete_count
+                ),
+                requires_reboot: false,
+                requires_logoff: false,
+                restarted_explorer: false,
+                failures,
+                restore_point_sequence: None,
+                results: Vec::new(),
+                rollback: None,
+            },
+            false,
+        ));
     }
 
-    // Step 7: If switching options succeeded, update the snapshot metadata
-    if is_switching_options {
+    // Step 7: If switching options succeeded, keep the state it left as a history generation and
+    // update the snapshot metadata. History is a convenience, so failing to record it doesn't fail
+    // the apply.
+    if let Some(previous_option_state) = pre_apply_state {
+        if let Err(e) = backup_service::record_generation(&tweak_id, previous_option_state) {
+            log::warn!(
+                "Failed to record snapshot history for '{}': {}",
+                tweak_id,
+                e
+            );
+        }
         backup_service::update_snapshot_metadata(&tweak_id, option_index, &option.label)?;
     }
 
-    // Step 8: Run post_commands (non-fatal, no rollback)
-    for cmd in &option.post_commands {
-        if let Err(e) = run_command(cmd, tweak.elevation()) {
-            log::warn!("Post-command failed (non-fatal): {}", e);
-        }
-    }
+    // The changes are in, so cancelling now would only skip the follow-up commands: they run
+    // regardless
+    metrics::phase("commands", || {
+        operations::uncancellable(|| {
+            // Step 8: Run post_commands (non-fatal, no rollback)
+            for cmd in &option.post_commands {
+                if let Err(e) = run_command(
+                    cmd,
+                    option.command_shell(),
+                    tweak.elevation(),
+                    option.command_timeout(),
+                ) {
+                    log::warn!("Post-command failed (non-fatal): {}", e);
+                }
+            }
 
-    // Step 9: Run post_powershell (non-fatal, no rollback)
-    for ps_cmd in &option.post_powershell {
-        if let Err(e) = run_powershell_command(ps_cmd, tweak.elevation()) {
-            log::warn!("Post-PowerShell command failed (non-fatal): {}", e);
-        }
+            // Step 9: Run post_powershell (non-fatal, no rollback)
+            for ps_cmd in &option.post_powershell {
+                if let Err(e) = run_powershell_command(
+                    ps_cmd,
+                    option.powershell_engine(),
+                    tweak.elevation(),
+                    option.command_timeout(),
+                ) {
+                    log::warn!("Post-PowerShell command failed (non-fatal): {}", e);
+                }
+            }
+        })
+    });
+
+    let facts = system_info_service::system_facts();
+    let requires_reboot = tweak.requires_reboot || option.requires_reboot(version, facts);
+    let requires_logoff = !requires_reboot && option.requires_logoff(version, facts);
+    let restart_note = restart_note(requires_reboot, requires_logoff);
+    if requires_reboot {
+        mark_reboot_pending(&tweak_id);
     }
 
     log::info!(
         "Successfully applied '{}' → '{}'{}",
         tweak.name,
         option.label,
-        if tweak.requires_reboot {
-            " (reboot required)"
-        } else {
-            ""
-        }
+        restart_note
+            .map(|note| format!(" ({})", note))
+            .unwrap_or_default()
     );
 
     if is_debug_enabled() {
         emit_debug_log(
             DebugLevel::Success,
             &format!("Applied: {} → {}", tweak.name, option.label),
-            if tweak.requires_reboot {
-                Some("Reboot required")
-            } else {
-                None
-            },
+            restart_note,
         );
     }
 
-    Ok(TweakResult {
-        success: true,
-        message: format!("Applied: {} → {}", tweak.name, option.label),
-        requires_reboot: tweak.requires_reboot,
-        failures: Vec::new(),
-    })
+    Ok((
+        TweakResult {
+            success: true,
+            message: format!("Applied: {} → {}", tweak.name, option.label),
+            requires_reboot,
+            requires_logoff,
+            restarted_explorer: false,
+            failures: Vec::new(),
+            restore_point_sequence: None,
+            results: Vec::new(),
+            rollback: None,
+        },
+        option.restart_explorer,
+    ))
+}
+
+/// Record that a tweak waits for a reboot (see `backup::reboot`). The change itself went through,
+/// so a failure is logged rather than returned.
+fn mark_reboot_pending(tweak_id: &str) {
+    if let Err(e) = backup_service::mark_reboot_pending(tweak_id) {
+        log::warn!("Failed to record pending reboot for '{}': {}", tweak_id, e);
+    }
+}
+
+/// Log/debug note for a pending reboot or sign-out
+fn restart_note(requires_reboot: bool, requires_logoff: bool) -> Option<&'static str> {
+    if requires_reboot {
+        Some("Reboot required")
+    } else if requires_logoff {
+        Some("Sign-out required")
+    } else {
+        None
+    }
 }
 
-/// Revert a tweak to its original state (restore from snapshot)
+/// Revert a tweak to its original state (restore from snapshot). Like `apply_tweak`, refused while
+/// the tweak waits for a reboot unless `force` is set.
 #[tauri::command]
-pub async fn revert_tweak(tweak_id: String) -> Result<TweakResult> {
+pub async fn revert_tweak(
+    state: State<'_, AppState>,
+    tweak_id: String,
+    force: Option<bool>,
+) -> Result<TweakResult> {
+    let _operation = state.snapshot_monitor.operation();
+    let _stale = state.status_cache.changing([tweak_id.clone()]);
+    if !force.unwrap_or(false) {
+        backup_service::ensure_no_pending_reboot(&tweak_id)?;
+    }
+    let mut journal = PendingOperation::new(OperationKind::Revert);
+    let outcome = journal.track(&tweak_id, || revert_option(tweak_id.clone()));
+    journal.record();
+    let (mut result, restart_explorer) = outcome?;
+    restart_explorer_if_requested(&mut result, restart_explorer);
+    Ok(result)
+}
+
+/// [`revert_tweak`] without the Explorer restart (see [`apply_option`]).
+pub(crate) fn revert_option(tweak_id: String) -> Result<(TweakResult, bool)> {
+    let timer = metrics::start(&tweak_id, TimedOperation::Revert);
+    let outcome = run_revert(tweak_id.clone());
+    timer.finish(outcome.as_ref().is_ok_and(|(result, _)| result.success));
+    usage_stats_service::record(&tweak_id, UsageAction::Revert, &outcome);
+    outcome
+}
+
+fn run_revert(tweak_id: String) -> Result<(TweakResult, bool)> {
     log::info!("Command: revert_tweak({})", tweak_id);
 
     let tweak = tweak_loader::get_tweak(&tweak_id)?.ok_or_else(|| {
@@ -357,6 +617,17 @@ pub async fn revert_tweak(tweak_id: String) -> Result<TweakResult> {
         );
     }
 
+    // Reverting undoes the applied option's changes, so its restart requirements apply
+    let version = runtime.windows_version();
+    let facts = system_info_service::system_facts();
+    let applied_option = tweak.options.get(snapshot.applied_option_index);
+    let requires_reboot = tweak.requires_reboot
+        || applied_option.is_some_and(|option| option.requires_reboot(version, facts));
+    let requires_logoff = !requires_reboot
+        && applied_option.is_some_and(|option| option.requires_logoff(version, facts));
+    let restart_explorer = applied_option.is_some_and(|option| option.restart_explorer);
+    let restart_note = restart_note(requires_reboot, requires_logoff);
+
     // Restore from snapshot - now returns RestoreResult with failure details
     let restore_result = backup_service::restore_from_snapshot(&snapshot)?;
 
@@ -367,31 +638,33 @@ pub async fn revert_tweak(tweak_id: String) -> Result<TweakResult> {
         log::info!(
             "Successfully reverted '{}' (snapshot deleted){}",
             tweak.name,
-            if tweak.requires_reboot {
-                " - reboot required"
-            } else {
-                ""
-            }
+            restart_note
+                .map(|note| format!(" - {}", note.to_lowercase()))
+                .unwrap_or_default()
         );
 
         if is_debug_enabled() {
             emit_debug_log(
                 DebugLevel::Success,
                 &format!("Reverted: {}", tweak.name),
-                if tweak.requires_reboot {
-                    Some("Reboot required")
-                } else {
-                    None
-                },
+                restart_note,
             );
         }
 
-        Ok(TweakResult {
-            success: true,
-            message: format!("Reverted: {}", tweak.name),
-            requires_reboot: tweak.requires_reboot,
-            failures: Vec::new(),
-        })
+        Ok((
+            TweakResult {
+                success: true,
+                message: format!("Reverted: {}", tweak.name),
+                requires_reboot,
+                requires_logoff,
+                restarted_explorer: false,
+                failures: Vec::new(),
+                restore_point_sequence: None,
+                results: Vec::new(),
+                rollback: None,
+            },
+            restart_explorer,
+        ))
     } else {
         // Partial success - some operations failed but snapshot is kept for retry
         log::warn!(
@@ -428,25 +701,195 @@ pub async fn revert_tweak(tweak_id: String) -> Result<TweakResult> {
 
         // Return partial success with failure details
         // The snapshot is preserved so user can retry
-        Ok(TweakResult {
-            success: false,
-            message: format!(
-                "Partial revert: {} operations failed. Snapshot kept for retry.",
-                failures.len()
-            ),
-            requires_reboot: tweak.requires_reboot,
-            failures,
-        })
+        Ok((
+            TweakResult {
+                success: false,
+                message: format!(
+                    "Partial revert: {} operations failed. Snapshot kept for retry.",
+                    failures.len()
+                ),
+                requires_reboot,
+                requires_logoff,
+                restarted_explorer: false,
+                failures,
+                restore_point_sequence: None,
+                results: Vec::new(),
+                rollback: None,
+            },
+            restart_explorer,
+        ))
     }
 }
 
+/// Return a tweak to one of its snapshot generations (see `list_snapshot_generations`).
+///
+/// Generation 0 is the original state, so it's a plain revert. Any later generation is restored
+/// like an option switch: the state being left is kept as a new generation first, so this can be
+/// undone the same way, and the snapshot stays with its metadata moved to the restored option.
+/// Refused while the tweak waits for a reboot unless `force` is set.
+#[tauri::command]
+pub async fn restore_snapshot_generation(
+    state: State<'_, AppState>,
+    tweak_id: String,
+    generation: u32,
+    force: Option<bool>,
+) -> Result<TweakResult> {
+    let _operation = state.snapshot_monitor.operation();
+    let _stale = state.status_cache.changing([tweak_id.clone()]);
+    if !force.unwrap_or(false) {
+        backup_service::ensure_no_pending_reboot(&tweak_id)?;
+    }
+    let mut journal = PendingOperation::new(OperationKind::RestoreGeneration);
+    let outcome = journal.track(&tweak_id, || {
+        if generation == 0 {
+            revert_option(tweak_id.clone())
+        } else {
+            restore_generation(tweak_id.clone(), generation)
+        }
+    });
+    journal.record();
+    let (mut result, restart_explorer) = outcome?;
+    restart_explorer_if_requested(&mut result, restart_explorer);
+    Ok(result)
+}
+
+fn restore_generation(tweak_id: String, generation: u32) -> Result<(TweakResult, bool)> {
+    log::info!(
+        "Command: restore_snapshot_generation({}, {})",
+        tweak_id,
+        generation
+    );
+
+    let tweak = tweak_loader::get_tweak(&tweak_id)?.ok_or_else(|| {
+        log::error!("Tweak not found: {}", tweak_id);
+        Error::NotFound(format!("Tweak '{}'", tweak_id))
+    })?;
+
+    let runtime = system_info_service::get_runtime_context()?;
+    if tweak.requires_admin && !runtime.is_admin {
+        log::warn!("Tweak '{}' requires admin, but running as user", tweak.name);
+        return Err(Error::RequiresAdmin);
+    }
+
+    let snapshot = backup_service::load_snapshot(&tweak_id)?
+        .ok_or_else(|| Error::BackupFailed("No snapshot found for this tweak".into()))?;
+    let target = backup_service::load_generation(&tweak_id, generation)?;
+
+    // Leaving the applied option and entering the restored one; either may need a restart
+    let version = runtime.windows_version();
+    let facts = system_info_service::system_facts();
+    let options = [
+        tweak.options.get(snapshot.applied_option_index),
+        tweak.options.get(target.applied_option_index),
+    ];
+    let requires_reboot = tweak.requires_reboot
+        || options
+            .iter()
+            .flatten()
+            .any(|option| option.requires_reboot(version, facts));
+    let requires_logoff = !requires_reboot
+        && options
+            .iter()
+            .flatten()
+            .any(|option| option.requires_logoff(version, facts));
+    let restart_explorer = options
+        .iter()
+        .flatten()
+        .any(|option| option.restart_explorer);
+
+    let current_state = backup_service::capture_current_state(&tweak, version)?;
+    let restore_result = backup_service::restore_from_snapshot(&target)?;
+
+    if !restore_result.success {
+        // The snapshot and its history are untouched, so the user can retry or revert
+        log::warn!(
+            "Partial restore of generation {} for '{}': {} failures",
+            generation,
+            tweak.name,
+            restore_result.failures.len()
+        );
+        let failures: Vec<(String, String)> = restore_result
+            .failures
+            .into_iter()
+            .map(|msg| (tweak_id.clone(), msg))
+            .collect();
+        return Ok((
+            TweakResult {
+                success: false,
+                message: format!(
+                    "Partial restore: {} operations failed. Snapshot kept for retry.",
+                    failures.len()
+                ),
+                requires_reboot,
+                requires_logoff,
+                restarted_explorer: false,
+                failures,
+                restore_point_sequence: None,
+                results: Vec::new(),
+                rollback: None,
+            },
+            restart_explorer,
+        ));
+    }
+
+    if let Err(e) = backup_service::record_generation(&tweak_id, current_state) {
+        log::warn!(
+            "Failed to record snapshot history for '{}': {}",
+            tweak_id,
+            e
+        );
+    }
+    backup_service::update_snapshot_metadata(
+        &tweak_id,
+        target.applied_option_index,
+        &target.applied_option_label,
+    )?;
+    if requires_reboot {
+        mark_reboot_pending(&tweak_id);
+    }
+
+    log::info!(
+        "Restored '{}' to generation {} ('{}')",
+        tweak.name,
+        generation,
+        target.applied_option_label
+    );
+    if is_debug_enabled() {
+        emit_debug_log(
+            DebugLevel::Success,
+            &format!("Restored: {} → {}", tweak.name, target.applied_option_label),
+            restart_note(requires_reboot, requires_logoff),
+        );
+    }
+
+    Ok((
+        TweakResult {
+            success: true,
+            message: format!("Restored: {} → {}", tweak.name, target.applied_option_label),
+            requires_reboot,
+            requires_logoff,
+            restarted_explorer: false,
+            failures: Vec::new(),
+            restore_point_sequence: None,
+            results: Vec::new(),
+            rollback: None,
+        },
+        restart_explorer,
+    ))
+}
+
 /// Explicitly accept the current state of a tweak and release its snapshot (ADR-0002 consent).
 ///
 /// Besides a fully-verified revert, this is the only sanctioned way to delete a snapshot. It gives a
 /// tweak stuck in Needs Attention a legitimate way out: the user decides the current (possibly
 /// partially-reverted) state is acceptable and lets the Original State go.
 #[tauri::command]
-pub async fn keep_current_state(tweak_id: String) -> Result<TweakResult> {
+pub async fn keep_current_state(
+    state: State<'_, AppState>,
+    tweak_id: String,
+) -> Result<TweakResult> {
+    let _operation = state.snapshot_monitor.operation();
+    let _stale = state.status_cache.changing([tweak_id.clone()]);
     log::info!("Command: keep_current_state({})", tweak_id);
 
     if !backup_service::snapshot_exists(&tweak_id)? {
@@ -466,10 +909,119 @@ pub async fn keep_current_state(tweak_id: String) -> Result<TweakResult> {
         success: true,
         message: "Current state kept; snapshot released.".to_string(),
         requires_reboot: false,
+        requires_logoff: false,
+        restarted_explorer: false,
+        failures: Vec::new(),
+        restore_point_sequence: None,
+        results: Vec::new(),
+        rollback: None,
+    })
+}
+
+/// Applies cut short by a crash or power loss (see `backup_service::ApplyIntent`), oldest first
+#[tauri::command]
+pub fn get_incomplete_applies() -> Result<Vec<backup_service::ApplyIntent>> {
+    backup_service::incomplete_apply_intents()
+}
+
+/// Roll back an apply that was cut short, to the state before it started: the tweak's original
+/// state on a first apply (releasing the snapshot once verified, ADR-0002), or the state captured
+/// before an option switch. If the rollback is incomplete the intent is kept so it can be retried.
+#[tauri::command]
+pub async fn roll_back_incomplete_apply(
+    state: State<'_, AppState>,
+    tweak_id: String,
+) -> Result<TweakResult> {
+    let _operation = state.snapshot_monitor.operation();
+    let _stale = state.status_cache.changing([tweak_id.clone()]);
+    log::info!("Command: roll_back_incomplete_apply({})", tweak_id);
+
+    let intent = backup_service::incomplete_apply_intents()?
+        .into_iter()
+        .find(|intent| intent.tweak_id == tweak_id)
+        .ok_or_else(|| Error::NotFound(format!("Incomplete apply of '{}'", tweak_id)))?;
+
+    if !system_info_service::get_runtime_context()?.is_admin {
+        return Err(Error::RequiresAdmin);
+    }
+
+    let target = match &intent.previous_state {
+        Some(previous_state) => Some(previous_state.clone()),
+        // No snapshot: the apply died before saving it, so nothing was changed yet
+        None => backup_service::load_snapshot(&tweak_id)?,
+    };
+    let restore = match &target {
+        Some(target) => backup_service::restore_from_snapshot(target)?,
+        None => backup_service::RestoreResult {
+            success: true,
+            failures: Vec::new(),
+        },
+    };
+
+    if !restore.success {
+        log::error!(
+            "Rollback of the incomplete apply of '{}' failed: {} operation(s) not restored",
+            tweak_id,
+            restore.failures.len()
+        );
+        return Ok(TweakResult {
+            success: false,
+            message: format!(
+                "Rollback incomplete: {} operation(s) could not be restored. You can retry it.",
+                restore.failures.len()
+            ),
+            requires_reboot: false,
+            requires_logoff: false,
+            restarted_explorer: false,
+            failures: restore
+                .failures
+                .into_iter()
+                .map(|msg| (tweak_id.clone(), msg))
+                .collect(),
+            restore_point_sequence: None,
+            results: Vec::new(),
+            rollback: None,
+        });
+    }
+
+    match &intent.previous_option {
+        // The switch may have got as far as updating the snapshot; point it back
+        Some((index, label)) if backup_service::snapshot_exists(&tweak_id)? => {
+            backup_service::update_snapshot_metadata(&tweak_id, *index, label)?
+        }
+        Some(_) => {}
+        None => backup_service::delete_snapshot(&tweak_id)?,
+    }
+    backup_service::discard_apply_intent(&tweak_id)?;
+    log::info!(
+        "Rolled back the incomplete apply of '{}' → '{}'",
+        intent.tweak_name,
+        intent.option_label
+    );
+
+    let requires_reboot =
+        tweak_loader::get_tweak(&tweak_id)?.is_some_and(|tweak| tweak.requires_reboot);
+    Ok(TweakResult {
+        success: true,
+        message: format!("Rolled back: {}", intent.tweak_name),
+        requires_reboot,
+        requires_logoff: false,
+        restarted_explorer: false,
         failures: Vec::new(),
+        restore_point_sequence: None,
+        results: Vec::new(),
+        rollback: None,
     })
 }
 
+/// Keep the state an apply that was cut short left behind. The snapshot stays, so the tweak can
+/// still be reverted as usual.
+#[tauri::command]
+pub fn dismiss_incomplete_apply(tweak_id: String) -> Result<()> {
+    log::info!("Command: dismiss_incomplete_apply({})", tweak_id);
+    backup_service::discard_apply_intent(&tweak_id)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
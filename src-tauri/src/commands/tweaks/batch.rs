@@ -1,18 +1,281 @@
 //! Batch Commands - Batch apply/revert operations for multiple tweaks
 
-use super::apply::{apply_tweak, revert_tweak};
-use crate::debug::{emit_debug_log, is_debug_enabled, DebugLevel};
+use super::apply::{
+    apply_option, classify_rollback, redeem_risk_acknowledgement, restart_explorer_if_requested,
+    revert_option, RollbackOutcome,
+};
+use crate::debug::{emit_debug_log, emit_event, is_debug_enabled, DebugLevel};
 use crate::error::{Error, Result};
-use crate::models::TweakResult;
-use crate::services::system_info_service;
+use crate::models::{
+    BatchItemResult, BatchRollbackItem, BatchRollbackReport, TweakResult, TweakSnapshot,
+};
+use crate::services::journal_service::{OperationKind, PendingOperation};
+use crate::services::{backup_service, restore_point_service, system_info_service, tweak_loader};
+use crate::state::AppState;
+use serde::Serialize;
+use tauri::State;
+
+/// Event reporting each tweak as `batch_apply_tweaks` / `batch_revert_tweaks` work through them
+pub const BATCH_PROGRESS_EVENT: &str = "tweak://batch-progress";
+
+/// Stage of one tweak in a `tweak://batch-progress` event
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchStage {
+    Started,
+    Finished,
+    Failed,
+}
+
+/// Progress of one tweak in a batch apply or revert
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchProgress {
+    pub tweak_id: String,
+    pub tweak_name: String,
+    /// 1-based position of this tweak in the batch
+    pub index: usize,
+    pub total: usize,
+    pub stage: BatchStage,
+    /// Failure reason (failed stage only)
+    pub message: Option<String>,
+}
+
+/// Totals of a batch apply or revert, gathered one tweak at a time
+#[derive(Default)]
+struct BatchRun {
+    total: usize,
+    requires_reboot: bool,
+    requires_logoff: bool,
+    restart_explorer: bool,
+    success_count: usize,
+    partial_success_count: usize,
+    failures: Vec<(String, String)>,
+    results: Vec<BatchItemResult>,
+}
+
+impl BatchRun {
+    fn new(total: usize) -> Self {
+        Self {
+            total,
+            ..Default::default()
+        }
+    }
+
+    /// Run one tweak's apply or revert, emitting its progress and recording its outcome
+    fn run(&mut self, tweak_id: &str, operation: impl FnOnce() -> Result<(TweakResult, bool)>) {
+        let (index, total) = (self.results.len() + 1, self.total);
+        let tweak_name = tweak_loader::get_tweak(tweak_id)
+            .ok()
+            .flatten()
+            .map_or_else(|| tweak_id.to_string(), |tweak| tweak.name);
+        let progress = |stage, message| {
+            emit_event(
+                BATCH_PROGRESS_EVENT,
+                BatchProgress {
+                    tweak_id: tweak_id.to_string(),
+                    tweak_name: tweak_name.clone(),
+                    index,
+                    total,
+                    stage,
+                    message,
+                },
+            )
+        };
+        progress(BatchStage::Started, None);
+
+        let item = match operation() {
+            Ok((res, wants_explorer_restart)) => {
+                self.restart_explorer |= wants_explorer_restart;
+                self.requires_reboot |= res.requires_reboot;
+                self.requires_logoff |= res.requires_logoff;
+                if res.success {
+                    self.success_count += 1;
+                } else {
+                    // Partial success - collect the tweak's own failures, or its message if it
+                    // has none
+                    self.partial_success_count += 1;
+                    if res.failures.is_empty() {
+                        self.failures
+                            .push((tweak_id.to_string(), res.message.clone()));
+                    } else {
+                        self.failures.extend(res.failures);
+                    }
+                }
+                BatchItemResult {
+                    tweak_id: tweak_id.to_string(),
+                    success: res.success,
+                    message: res.message,
+                    requires_reboot: res.requires_reboot,
+                    error_code: None,
+                }
+            }
+            Err(e) => {
+                log::warn!("Failed tweak '{}' in batch: {}", tweak_id, e);
+                self.failures.push((tweak_id.to_string(), e.to_string()));
+                BatchItemResult {
+                    tweak_id: tweak_id.to_string(),
+                    success: false,
+                    message: e.to_string(),
+                    requires_reboot: false,
+                    error_code: Some(e.code().to_string()),
+                }
+            }
+        };
+
+        if item.success {
+            progress(BatchStage::Finished, None);
+        } else {
+            progress(BatchStage::Failed, Some(item.message.clone()));
+        }
+        self.results.push(item);
+    }
+
+    /// The batch's result; Explorer is restarted once for the whole batch rather than once per
+    /// tweak
+    fn finish(self, message: String, restore_point_sequence: Option<u32>) -> TweakResult {
+        let mut result = TweakResult {
+            success: self.failures.is_empty(),
+            message,
+            requires_reboot: self.requires_reboot,
+            requires_logoff: self.requires_logoff && !self.requires_reboot,
+            restarted_explorer: false,
+            failures: self.failures,
+            restore_point_sequence,
+            results: self.results,
+            rollback: None,
+        };
+        restart_explorer_if_requested(&mut result, self.restart_explorer);
+        result
+    }
+}
+
+/// A tweak's state before a transactional batch changed it
+struct PreBatchState {
+    tweak_id: String,
+    /// Current values of everything the tweak's options change
+    state: TweakSnapshot,
+    /// Its snapshot as it was, None if it wasn't applied
+    snapshot: Option<TweakSnapshot>,
+}
+
+fn capture_pre_batch_state(tweak_id: &str, version: u32) -> Result<PreBatchState> {
+    let tweak = tweak_loader::get_tweak(tweak_id)?
+        .ok_or_else(|| Error::NotFound(format!("Tweak '{}'", tweak_id)))?;
+    Ok(PreBatchState {
+        tweak_id: tweak_id.to_string(),
+        state: backup_service::capture_current_state(&tweak, version)?,
+        snapshot: backup_service::load_snapshot(tweak_id)?,
+    })
+}
+
+/// Return the tweaks a transactional batch applied before the one at `failed` to their pre-batch
+/// state, newest first. The failed tweak was already rolled back by its own apply.
+fn roll_back_batch(
+    batch: &[(String, usize)],
+    failed: usize,
+    pre_batch: &[PreBatchState],
+    journal: &mut PendingOperation,
+) -> BatchRollbackReport {
+    let failed_tweak_id = batch[failed].0.clone();
+    log::warn!(
+        "Transactional batch stopped at '{}', rolling back {} applied tweak(s)",
+        failed_tweak_id,
+        failed
+    );
+    // A cancelled batch ends up here too, and its rollback must run to the end
+    let items: Vec<BatchRollbackItem> = crate::operations::uncancellable(|| {
+        pre_batch[..failed]
+            .iter()
+            .rev()
+            .map(|pre| roll_back_tweak(pre, journal))
+            .collect()
+    });
+    BatchRollbackReport {
+        failed_tweak_id,
+        items,
+        skipped: batch[failed + 1..]
+            .iter()
+            .map(|(tweak_id, _)| tweak_id.clone())
+            .collect(),
+    }
+}
+
+/// Restore one tweak's pre-batch values. Only a verified restore puts its snapshot back as it was
+/// (ADR-0002); otherwise the snapshot from the apply is kept, as the way back to its original state.
+fn roll_back_tweak(pre: &PreBatchState, journal: &mut PendingOperation) -> BatchRollbackItem {
+    let restore = backup_service::restore_from_snapshot(&pre.state);
+    let failures = match classify_rollback(Some(restore)) {
+        RollbackOutcome::NothingToRollBack | RollbackOutcome::Verified => Vec::new(),
+        RollbackOutcome::Incomplete(failures) => failures,
+    };
+
+    if failures.is_empty() {
+        let snapshot_restored = match &pre.snapshot {
+            Some(snapshot) => backup_service::save_snapshot(snapshot),
+            None => backup_service::delete_snapshot(&pre.tweak_id),
+        };
+        match snapshot_restored {
+            // Nothing left of this tweak's change for the journal to undo
+            Ok(()) => journal.forget(&pre.tweak_id),
+            Err(e) => log::warn!(
+                "Rolled back '{}' but failed to restore its snapshot: {}",
+                pre.tweak_id,
+                e
+            ),
+        }
+        log::info!("Rolled back '{}' to its pre-batch state", pre.tweak_id);
+    } else {
+        log::error!(
+            "Rollback of '{}' was incomplete: {} operation(s) could not be restored",
+            pre.tweak_id,
+            failures.len()
+        );
+    }
+
+    BatchRollbackItem {
+        tweak_id: pre.tweak_id.clone(),
+        restored: failures.is_empty(),
+        failures,
+    }
+}
 
 /// Batch apply multiple tweak options
-/// Input: Vec of (tweak_id, option_index) tuples
+/// Input: Vec of (tweak_id, option_index) tuples. With `create_restore_point`, a System Restore
+/// point is created first and nothing is applied if that fails. A tweak still waiting for a reboot
+/// fails unless it's listed in `force`. Each tweak's progress is emitted as a
+/// `tweak://batch-progress` event and its outcome returned in `results`. With an `operation_id`,
+/// `cancel_operation` rolls back the tweak being applied and fails the rest as `CANCELLED`; tweaks
+/// that already finished stay applied.
+///
+/// With `transactional`, the state of every tweak is captured before any is applied, the batch
+/// stops at the first tweak that fails (or is cancelled), and the tweaks applied before it are
+/// returned to their pre-batch state; `rollback` reports what was undone. Commands run by the
+/// applied options are not undone.
+///
+/// Each high or critical risk tweak needs one of `risk_acks` (from `acknowledge_risk`) issued for
+/// it, and fails with `RISK_NOT_ACKNOWLEDGED` without one.
 #[tauri::command]
-pub async fn batch_apply_tweaks(operations: Vec<(String, usize)>) -> Result<TweakResult> {
+pub async fn batch_apply_tweaks(
+    state: State<'_, AppState>,
+    operations: Vec<(String, usize)>,
+    create_restore_point: Option<bool>,
+    force: Option<Vec<String>>,
+    operation_id: Option<String>,
+    transactional: Option<bool>,
+    risk_acks: Option<Vec<String>>,
+) -> Result<TweakResult> {
+    let _operation = state.snapshot_monitor.operation();
+    let _stale = state
+        .status_cache
+        .changing(operations.iter().map(|(tweak_id, _)| tweak_id.clone()));
+    let _cancellable = state.operations.begin(operation_id)?;
+    let transactional = transactional.unwrap_or(false);
     log::info!(
-        "Command: batch_apply_tweaks({} operations)",
-        operations.len()
+        "Command: batch_apply_tweaks({} operations, restore point: {}, transactional: {})",
+        operations.len(),
+        create_restore_point.unwrap_or(false),
+        transactional
     );
 
     let runtime = system_info_service::get_runtime_context()?;
@@ -22,6 +285,27 @@ pub async fn batch_apply_tweaks(operations: Vec<(String, usize)>) -> Result<Twea
         return Err(Error::RequiresAdmin);
     }
 
+    let restore_point_sequence = if create_restore_point.unwrap_or(false) {
+        let description = format!(
+            "MagicX Toolbox: before applying {} tweaks",
+            operations.len()
+        );
+        Some(restore_point_service::create_restore_point(&description)?.sequence_number)
+    } else {
+        None
+    };
+
+    // Captured before anything is applied, so a failed capture leaves the machine untouched
+    let pre_batch = if transactional {
+        let version = runtime.windows_version();
+        operations
+            .iter()
+            .map(|(tweak_id, _)| capture_pre_batch_state(tweak_id, version))
+            .collect::<Result<Vec<_>>>()?
+    } else {
+        Vec::new()
+    };
+
     if is_debug_enabled() {
         emit_debug_log(
             DebugLevel::Info,
@@ -30,59 +314,53 @@ pub async fn batch_apply_tweaks(operations: Vec<(String, usize)>) -> Result<Twea
         );
     }
 
-    let mut requires_reboot = false;
-    let mut success_count = 0;
-    let mut partial_success_count = 0;
-    let mut failures: Vec<(String, String)> = Vec::new();
+    let mut run = BatchRun::new(operations.len());
+    let mut journal = PendingOperation::new(OperationKind::BatchApply);
 
-    for (tweak_id, option_index) in &operations {
-        let result = Box::pin(apply_tweak(tweak_id.clone(), *option_index)).await;
-
-        match result {
-            Ok(res) => {
-                if res.success {
-                    success_count += 1;
-                } else {
-                    // Partial success - apply rolled back but record failure
-                    partial_success_count += 1;
-                    // Collect inner failures
-                    for (id, msg) in res.failures {
-                        failures.push((id, msg));
-                    }
-                    if failures.is_empty() {
-                        // No inner failures but still failed
-                        failures.push((tweak_id.clone(), res.message));
-                    }
-                }
-                if res.requires_reboot {
-                    requires_reboot = true;
-                }
-            }
-            Err(e) => {
-                let error_msg = e.to_string();
-                log::warn!(
-                    "Failed to apply tweak '{}' option {}: {}",
-                    tweak_id,
-                    option_index,
-                    error_msg
-                );
-                failures.push((tweak_id.clone(), error_msg));
+    let force = force.unwrap_or_default();
+    let risk_acks = risk_acks.unwrap_or_default();
+    let mut failed_at = None;
+    for (position, (tweak_id, option_index)) in operations.iter().enumerate() {
+        run.run(tweak_id, || {
+            if !force.contains(tweak_id) {
+                backup_service::ensure_no_pending_reboot(tweak_id)?;
             }
+            redeem_risk_acknowledgement(&state, tweak_id, &risk_acks)?;
+            journal.track(tweak_id, || apply_option(tweak_id.clone(), *option_index))
+        });
+        if transactional && run.results.last().is_some_and(|item| !item.success) {
+            failed_at = Some(position);
+            break;
         }
     }
 
-    let failure_count = failures.len();
-    let message = if failure_count > 0 {
+    let rollback =
+        failed_at.map(|position| roll_back_batch(&operations, position, &pre_batch, &mut journal));
+
+    journal.record();
+
+    let failure_count = run.failures.len();
+    let message = if let Some(rollback) = &rollback {
+        let restored = rollback.items.iter().filter(|item| item.restored).count();
+        format!(
+            "Batch stopped at '{}': rolled back {}/{} applied tweaks, {} not attempted",
+            rollback.failed_tweak_id,
+            restored,
+            rollback.items.len(),
+            rollback.skipped.len()
+        )
+    } else if failure_count > 0 {
         format!(
             "Applied {}/{} tweaks ({} failed, {} partial)",
-            success_count,
+            run.success_count,
             operations.len(),
             failure_count,
-            partial_success_count
+            run.partial_success_count
         )
     } else {
-        format!("Successfully applied {} tweaks", success_count)
+        format!("Successfully applied {} tweaks", run.success_count)
     };
+    let requires_reboot = run.requires_reboot;
 
     log::info!(
         "Batch apply completed: {}{}",
@@ -106,17 +384,110 @@ pub async fn batch_apply_tweaks(operations: Vec<(String, usize)>) -> Result<Twea
         );
     }
 
-    Ok(TweakResult {
-        success: failure_count == 0,
-        message,
-        requires_reboot,
-        failures,
+    let mut result = run.finish(message, restore_point_sequence);
+    result.rollback = rollback;
+    Ok(result)
+}
+
+/// Result of `reapply_applied_tweaks`
+#[derive(Debug, Clone, Serialize)]
+pub struct ReapplyReport {
+    /// Tweaks that still had the option they were applied with, left as they were
+    pub already_applied: Vec<String>,
+    /// Outcome of the tweaks applied again, as for `batch_apply_tweaks`
+    pub result: TweakResult,
+}
+
+/// Apply every tweak that has a snapshot again with the option its snapshot records: the way back
+/// after a feature update resets settings. Tweaks whose state still matches that option are left
+/// alone and listed in `already_applied`. Progress is emitted as `tweak://batch-progress` for the
+/// tweaks applied again. A tweak still waiting for a reboot fails, as in a batch without `force`.
+///
+/// The options were chosen (and their risk acknowledged) when the tweaks were first applied, so no
+/// risk acknowledgement is needed. With an `operation_id`, `cancel_operation` stops it like a batch.
+#[tauri::command]
+pub async fn reapply_applied_tweaks(
+    state: State<'_, AppState>,
+    operation_id: Option<String>,
+) -> Result<ReapplyReport> {
+    let _operation = state.snapshot_monitor.operation();
+    let tweak_ids = backup_service::get_applied_tweaks()?;
+    let _stale = state.status_cache.changing(tweak_ids.clone());
+    let _cancellable = state.operations.begin(operation_id)?;
+    log::info!(
+        "Command: reapply_applied_tweaks({} tweaks)",
+        tweak_ids.len()
+    );
+
+    let runtime = system_info_service::get_runtime_context()?;
+    if !runtime.is_admin {
+        return Err(Error::RequiresAdmin);
+    }
+    let version = runtime.windows_version();
+
+    // A tweak whose snapshot or state can't be read is attempted anyway, so it's reported
+    let mut already_applied = Vec::new();
+    let mut operations = Vec::new();
+    for tweak_id in tweak_ids {
+        let Some(snapshot) = backup_service::load_snapshot(&tweak_id).ok().flatten() else {
+            continue;
+        };
+        let option_index = snapshot.applied_option_index;
+        let matches = tweak_loader::get_tweak(&tweak_id)
+            .ok()
+            .flatten()
+            .and_then(|tweak| backup_service::detect_tweak_state(&tweak, version).ok())
+            .is_some_and(|detected| detected.current_option_index == Some(option_index));
+        if matches {
+            already_applied.push(tweak_id);
+        } else {
+            operations.push((tweak_id, option_index));
+        }
+    }
+
+    let mut run = BatchRun::new(operations.len());
+    for (tweak_id, option_index) in &operations {
+        run.run(tweak_id, || {
+            backup_service::ensure_no_pending_reboot(tweak_id)?;
+            apply_option(tweak_id.clone(), *option_index)
+        });
+    }
+
+    let failure_count = run.failures.len();
+    let message = if failure_count > 0 {
+        format!(
+            "Re-applied {}/{} tweaks ({} failed, {} partial), {} already applied",
+            run.success_count,
+            operations.len(),
+            failure_count,
+            run.partial_success_count,
+            already_applied.len()
+        )
+    } else {
+        format!(
+            "Re-applied {} tweaks, {} already applied",
+            run.success_count,
+            already_applied.len()
+        )
+    };
+    log::info!("Re-apply completed: {}", message);
+
+    Ok(ReapplyReport {
+        already_applied,
+        result: run.finish(message, None),
     })
 }
 
-/// Batch revert multiple tweaks
+/// Batch revert multiple tweaks. Tweaks still waiting for a reboot fail unless `force` is set.
+/// Progress and per-tweak results are reported as for `batch_apply_tweaks`.
 #[tauri::command]
-pub async fn batch_revert_tweaks(tweak_ids: Vec<String>) -> Result<TweakResult> {
+pub async fn batch_revert_tweaks(
+    state: State<'_, AppState>,
+    tweak_ids: Vec<String>,
+    force: Option<bool>,
+) -> Result<TweakResult> {
+    let _operation = state.snapshot_monitor.operation();
+    let _stale = state.status_cache.changing(tweak_ids.clone());
     log::info!("Command: batch_revert_tweaks({} tweaks)", tweak_ids.len());
 
     let runtime = system_info_service::get_runtime_context()?;
@@ -125,59 +496,33 @@ pub async fn batch_revert_tweaks(tweak_ids: Vec<String>) -> Result<TweakResult>
         return Err(Error::RequiresAdmin);
     }
 
-    let mut requires_reboot = false;
-    let mut success_count = 0;
-    let mut partial_success_count = 0;
-    let mut failures: Vec<(String, String)> = Vec::new();
+    let mut run = BatchRun::new(tweak_ids.len());
+    let mut journal = PendingOperation::new(OperationKind::BatchRevert);
 
+    let force = force.unwrap_or(false);
     for tweak_id in &tweak_ids {
-        let result = Box::pin(revert_tweak(tweak_id.clone())).await;
-
-        match result {
-            Ok(res) => {
-                if res.success {
-                    success_count += 1;
-                } else {
-                    // Partial success - some operations failed, snapshot kept for retry
-                    partial_success_count += 1;
-                    // Collect inner failures
-                    for (id, msg) in res.failures {
-                        failures.push((id, msg));
-                    }
-                    if failures.is_empty() {
-                        // No inner failures but still failed
-                        failures.push((tweak_id.clone(), res.message));
-                    }
-                }
-                if res.requires_reboot {
-                    requires_reboot = true;
-                }
+        run.run(tweak_id, || {
+            if !force {
+                backup_service::ensure_no_pending_reboot(tweak_id)?;
             }
-            Err(e) => {
-                let error_msg = e.to_string();
-                log::warn!("Failed to revert tweak '{}': {}", tweak_id, error_msg);
-                failures.push((tweak_id.clone(), error_msg));
-            }
-        }
+            journal.track(tweak_id, || revert_option(tweak_id.clone()))
+        });
     }
 
-    let failure_count = failures.len();
+    journal.record();
+
+    let failure_count = run.failures.len();
     let message = if failure_count > 0 {
         format!(
             "Reverted {}/{} tweaks ({} failed, {} partial)",
-            success_count,
+            run.success_count,
             tweak_ids.len(),
             failure_count,
-            partial_success_count
+            run.partial_success_count
         )
     } else {
-        format!("Reverted {} tweaks", success_count)
+        format!("Reverted {} tweaks", run.success_count)
     };
 
-    Ok(TweakResult {
-        success: failure_count == 0,
-        message,
-        requires_reboot,
-        failures,
-    })
+    Ok(run.finish(message, None))
 }
@@ -0,0 +1,55 @@
+//! Deferred Commands - Applies queued for the next boot or a set time
+
+use super::apply::redeem_risk_acknowledgement;
+use crate::error::{Error, Result};
+use crate::services::deferred_service::{self, DeferredApply};
+use crate::state::AppState;
+use chrono::{DateTime, Local};
+use tauri::State;
+
+/// Queue option `option_index` of a tweak to be applied at `at` (RFC 3339) or, without it, once
+/// the machine has booted again. A newer request for the same tweak replaces one still waiting.
+/// A high or critical risk tweak takes its `risk_ack` token now, as `apply_tweak` would.
+#[tauri::command]
+pub fn schedule_apply(
+    state: State<'_, AppState>,
+    tweak_id: String,
+    option_index: usize,
+    at: Option<String>,
+    risk_ack: Option<String>,
+) -> Result<DeferredApply> {
+    log::info!(
+        "Command: schedule_apply({}, option_index={}, at={:?})",
+        tweak_id,
+        option_index,
+        at
+    );
+    let at = at
+        .map(|at| {
+            DateTime::parse_from_rfc3339(&at)
+                .map(|at| at.with_timezone(&Local))
+                .map_err(|e| Error::ValidationError(format!("Invalid time '{}': {}", at, e)))
+        })
+        .transpose()?;
+    redeem_risk_acknowledgement(&state, &tweak_id, risk_ack.as_slice())?;
+    deferred_service::queue(&tweak_id, option_index, at)
+}
+
+/// Every queued apply, waiting or finished (with its outcome), oldest first
+#[tauri::command]
+pub fn get_deferred_applies() -> Result<Vec<DeferredApply>> {
+    deferred_service::list()
+}
+
+/// One queued apply, if it's still in the queue
+#[tauri::command]
+pub fn get_deferred_apply(id: String) -> Result<Option<DeferredApply>> {
+    deferred_service::get(&id)
+}
+
+/// Cancel a waiting apply, or dismiss a finished one. Returns false when it isn't in the queue.
+#[tauri::command]
+pub fn cancel_deferred_apply(id: String) -> Result<bool> {
+    log::info!("Command: cancel_deferred_apply({})", id);
+    deferred_service::cancel(&id)
+}
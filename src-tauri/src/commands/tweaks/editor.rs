@@ -0,0 +1,23 @@
+//! Editor Commands - Authoring custom tweaks in `tweaks.d` from the UI
+
+use crate::error::Result;
+use crate::services::tweak_editor_service::{self, DraftReport, TweakDraft};
+
+/// Validate a custom tweak draft in its file, as it would be loaded, without saving it
+#[tauri::command]
+pub fn validate_tweak_draft(draft: TweakDraft) -> Result<DraftReport> {
+    log::debug!("Command: validate_tweak_draft({})", draft.file);
+    tweak_editor_service::validate_draft(&draft)
+}
+
+/// Save a custom tweak draft to its file in `tweaks.d`, unless the file would have errors. The
+/// tweak is loaded at the next launch.
+#[tauri::command]
+pub fn save_custom_tweak(draft: TweakDraft) -> Result<DraftReport> {
+    log::info!(
+        "Command: save_custom_tweak({}, {:?})",
+        draft.file,
+        draft.original_id
+    );
+    tweak_editor_service::save_draft(&draft)
+}
@@ -7,103 +7,156 @@
 //! - Scheduler change application
 //! - Hosts file change application
 //! - Firewall rule change application
+//! - Power plan change application
+//! - BCD store change application
+//! - AppX package change application
+//! - File and folder change application
+//! - Registry ACL change application
+//! - Optional feature change application
+//! - Network adapter property change application
+//! - Group Policy change application and refresh
+//! - Application (winget) install/uninstall
 //! - Atomic change orchestration
 
 use crate::debug::{emit_debug_log, is_debug_enabled, DebugLevel};
 use crate::error::{Error, Result};
+use crate::metrics;
 use crate::models::{
-    RegistryAction, RegistryHive, RegistryValueType, TweakDefinition, TweakOption,
+    ChangeElevation, CommandShell, PolicyScope, RegistryAction, RegistryChange, RegistryHive,
+    RegistryKeyContents, RegistryValueType, SchedulerAction, TweakDefinition, TweakOption,
 };
-use crate::services::elevation::Elevation;
+use crate::operations;
+use crate::services::backup;
+use crate::services::elevation::{Elevation, PowerShellEngine, PowerShellResult, RegistryOp};
+use crate::services::package_service::{AppProgress, AppProgressStage};
 use crate::services::{
-    firewall_service, hosts_service, registry_service, registry_value, scheduler_service,
-    service_control, trusted_installer,
+    appx_service, bcdedit_service, dism_service, file_service, firewall_service, hosts_service,
+    network_service, package_service, policy_service, power_service, registry_acl_service,
+    registry_service, registry_value, scheduler_service, service_control, system_info_service,
+    time_service, trusted_installer,
 };
+use std::time::Duration;
 
 // ============================================================================
 // Command Execution
 // ============================================================================
 
-/// Run a shell command (as user, SYSTEM, or TrustedInstaller)
-pub fn run_command(cmd: &str, elevation: Elevation) -> Result<()> {
-    let label_suffix = if elevation.is_elevated() {
-        format!(" as {}", elevation.label())
-    } else {
-        String::new()
-    };
-    log::info!("Running command{}: {}", label_suffix, cmd);
+/// Run a shell command in `shell` (as user, SYSTEM, or TrustedInstaller). `timeout` replaces the
+/// default limit on an elevated command; a command run as the user has no limit.
+pub fn run_command(
+    cmd: &str,
+    shell: CommandShell,
+    elevation: Elevation,
+    timeout: Option<Duration>,
+) -> Result<()> {
+    if shell != CommandShell::Cmd {
+        return run_powershell_command(cmd, shell.powershell_engine(), elevation, timeout);
+    }
+    operations::check("a command")?;
+    log::info!("Running command{}: {}", elevation_suffix(elevation), cmd);
 
-    match elevation {
+    let result = match elevation {
         Elevation::None => {
             use std::os::windows::process::CommandExt;
             const CREATE_NO_WINDOW: u32 = 0x08000000;
-            let output = std::process::Command::new("cmd")
+            std::process::Command::new("cmd")
                 .raw_arg(format!("/C {}", cmd))
                 .creation_flags(CREATE_NO_WINDOW)
                 .output()
-                .map_err(|e| Error::CommandExecution(e.to_string()))?;
-
-            if !output.status.success() {
-                return Err(Error::CommandExecution(format!(
-                    "Command failed with exit code {}: {}",
-                    output.status.code().unwrap_or(-1),
-                    String::from_utf8_lossy(&output.stderr)
-                )));
-            }
-            Ok(())
+                .map(|output| PowerShellResult::from_output(&output))
+                .map_err(|e| Error::CommandExecution(e.to_string()))
         }
-        // SYSTEM and TrustedInstaller share the same executor signature; only the executor
-        // and the label differ.
-        elevated => {
-            let execute: fn(&str) -> std::result::Result<(), Error> = match elevated {
-                Elevation::TrustedInstaller => trusted_installer::run_command_as_ti,
-                _ => trusted_installer::run_command_as_system,
-            };
-            execute(cmd).map_err(|e| {
-                Error::CommandExecution(format!("{} command failed: {}", elevated.label(), e))
-            })
+        Elevation::System => trusted_installer::run_command_as_system(cmd, timeout),
+        Elevation::TrustedInstaller => trusted_installer::run_command_as_ti(cmd, timeout),
+    };
+    report_output("Command", cmd, elevation, result)
+}
+
+/// Run a PowerShell command in `engine` (as user, SYSTEM, or TrustedInstaller), with `timeout` as
+/// in `run_command`
+pub fn run_powershell_command(
+    cmd: &str,
+    engine: PowerShellEngine,
+    elevation: Elevation,
+    timeout: Option<Duration>,
+) -> Result<()> {
+    operations::check("a PowerShell command")?;
+    log::info!(
+        "Running {}{}: {}",
+        engine.name(),
+        elevation_suffix(elevation),
+        cmd
+    );
+
+    let result = match elevation {
+        Elevation::None => trusted_installer::run_powershell_with(cmd, engine),
+        Elevation::System => trusted_installer::run_powershell_as_system(cmd, engine, timeout),
+        Elevation::TrustedInstaller => {
+            trusted_installer::run_powershell_as_ti(cmd, engine, timeout)
         }
-    }
+    };
+    report_output("PowerShell", cmd, elevation, result)
 }
 
-/// Run a PowerShell command (as user, SYSTEM, or TrustedInstaller)
-pub fn run_powershell_command(cmd: &str, elevation: Elevation) -> Result<()> {
-    let label_suffix = if elevation.is_elevated() {
+fn elevation_suffix(elevation: Elevation) -> String {
+    if elevation.is_elevated() {
         format!(" as {}", elevation.label())
     } else {
         String::new()
+    }
+}
+
+/// Log a command's output, show it in the debug console, and turn a non-zero exit into an error
+/// carrying what the command printed
+fn report_output(
+    what: &str,
+    cmd: &str,
+    elevation: Elevation,
+    result: Result<PowerShellResult>,
+) -> Result<()> {
+    let what = if elevation.is_elevated() {
+        format!("{} ({})", what, elevation.label())
+    } else {
+        what.to_string()
     };
-    log::info!("Running PowerShell{}: {}", label_suffix, cmd);
-
-    match elevation {
-        Elevation::None => match trusted_installer::run_powershell(cmd) {
-            Ok(ps_result) => {
-                if ps_result.exit_code != 0 {
-                    return Err(Error::CommandExecution(format!(
-                        "PowerShell failed with exit code {}: {}",
-                        ps_result.exit_code, ps_result.stderr
-                    )));
-                }
-                if !ps_result.stdout.is_empty() {
-                    log::debug!("PowerShell stdout: {}", ps_result.stdout);
-                }
-                if !ps_result.stderr.is_empty() {
-                    log::debug!("PowerShell stderr: {}", ps_result.stderr);
-                }
-                Ok(())
-            }
-            Err(e) => Err(Error::CommandExecution(format!("PowerShell failed: {}", e))),
-        },
-        // SYSTEM and TrustedInstaller share the same executor signature.
-        elevated => {
-            let execute: fn(&str) -> std::result::Result<(), Error> = match elevated {
-                Elevation::TrustedInstaller => trusted_installer::run_powershell_as_ti,
-                _ => trusted_installer::run_powershell_as_system,
-            };
-            execute(cmd).map_err(|e| {
-                Error::CommandExecution(format!("PowerShell ({}) failed: {}", elevated.label(), e))
-            })
-        }
+    let result = result.map_err(|e| command_error(&format!("{} could not be run", what), e))?;
+
+    let stdout = result.stdout.trim();
+    let stderr = result.stderr.trim();
+    if !stdout.is_empty() {
+        log::debug!("{} stdout: {}", what, stdout);
+    }
+    if !stderr.is_empty() {
+        log::debug!("{} stderr: {}", what, stderr);
+    }
+    if is_debug_enabled() {
+        let level = if result.success {
+            DebugLevel::Info
+        } else {
+            DebugLevel::Error
+        };
+        let output = [("stdout", stdout), ("stderr", stderr)]
+            .iter()
+            .filter(|(_, text)| !text.is_empty())
+            .map(|(stream, text)| format!("[{}] {}", stream, text))
+            .collect::<Vec<_>>()
+            .join(" ");
+        emit_debug_log(
+            level,
+            &format!("{} exited with code {}: {}", what, result.exit_code, cmd),
+            (!output.is_empty()).then_some(output.as_str()),
+        );
+    }
+
+    result.into_success(&what).map(|_| ())
+}
+
+/// A failed command as a `CommandExecution` error with `context`, except that a timeout or a
+/// cancellation is passed on as it is, so the caller can tell them from a command that failed
+pub fn command_error(context: &str, e: Error) -> Error {
+    match e {
+        Error::Timeout(_) | Error::Cancelled(_) => e,
+        e => Error::CommandExecution(format!("{}: {}", context, e)),
     }
 }
 
@@ -125,81 +178,161 @@ pub fn read_registry_value(
     Ok(value)
 }
 
-/// Write a registry value
-fn write_registry_value(
-    hive: &RegistryHive,
-    key: &str,
-    value_name: &str,
-    value_type: &RegistryValueType,
-    value: &serde_json::Value,
-    use_system: bool,
-) -> Result<()> {
-    registry_value::write_registry_json_value(hive, key, value_name, value_type, value, use_system)
+/// The elevation a registry change declares for itself, if it declares one that needs the broker.
+/// Deletes and key creation run in-process unless the change asks otherwise.
+fn declared_registry_elevation(change: &RegistryChange) -> Option<Elevation> {
+    change
+        .elevation
+        .map(ChangeElevation::level)
+        .filter(|level| level.is_elevated() && change.hive.requires_admin())
 }
 
-/// Restore a value (guess type from JSON value)
-fn restore_value(
+/// Read a value for rollback together with its stored type, so it can be written back as the
+/// same type. The declared type is only a fallback for a value that doesn't exist yet.
+fn read_original_value(
     hive: &RegistryHive,
     key: &str,
     value_name: &str,
-    value: &serde_json::Value,
-) -> Result<()> {
-    if let Some(v) = value.as_u64() {
-        if v <= u32::MAX as u64 {
-            registry_service::set_dword(hive, key, value_name, v as u32)?;
-        } else {
-            registry_service::set_qword(hive, key, value_name, v)?;
-        }
-    } else if let Some(v) = value.as_str() {
-        registry_service::set_string(hive, key, value_name, v)?;
-    } else if let Some(arr) = value.as_array() {
-        let binary: Vec<u8> = arr
-            .iter()
-            .filter_map(|v| v.as_u64().map(|u| u as u8))
-            .collect();
-        registry_service::set_binary(hive, key, value_name, &binary)?;
-    }
-    Ok(())
+    declared_type: Option<RegistryValueType>,
+) -> Result<(RegistryValueType, Option<serde_json::Value>)> {
+    let value_type = registry_service::detect_value_type(hive, key, value_name)?
+        .or(declared_type)
+        .unwrap_or(RegistryValueType::Dword);
+    let value = read_registry_value(hive, key, value_name, &value_type)?;
+    Ok((value_type, value))
 }
 
 // ============================================================================
 // Atomic Change Application
 // ============================================================================
 
-/// Apply ALL core changes atomically: registry, services, scheduler, hosts, firewall
-/// If any step fails, caller is responsible for full rollback from snapshot
+/// Apply ALL core changes atomically: registry ACLs, registry, services, scheduler, hosts, firewall,
+/// power plan, BCD, AppX, files, optional features, network adapters, policies, applications
+/// If any step fails, caller is responsible for full rollback from snapshot. A cancelled operation
+/// (see `crate::operations`) fails with `Error::Cancelled` before the next step.
 pub fn apply_all_changes_atomically(
     tweak: &TweakDefinition,
     option: &TweakOption,
     windows_version: u32,
 ) -> Result<()> {
-    // Step 1: Apply registry changes (already has internal rollback on failure)
-    apply_registry_changes(tweak, option, windows_version)?;
+    // Step 1: Apply registry ACL changes first, so the registry writes below can reach keys
+    // that were locked down - fail-fast, return error for full rollback
+    operations::check("the registry ACL changes")?;
+    if let Err(e) = metrics::phase("registry ACLs", || {
+        apply_registry_acl_changes_atomic(option)
+    }) {
+        log::error!("Registry ACL changes failed, need full rollback: {}", e);
+        return Err(e);
+    }
+
+    // Step 2: Apply registry changes, MMCSS, context menu, audio, visual effects, and time values
+    // included (already has internal rollback on failure)
+    operations::check("the registry changes")?;
+    metrics::phase("registry", || {
+        apply_registry_changes(tweak, option, windows_version)
+    })?;
+    if !option.time_changes.is_empty() {
+        // The values are already in place; a stopped service picks them up when it next starts
+        if let Err(e) = time_service::update_config() {
+            log::warn!("Failed to reload Windows Time configuration: {}", e);
+        }
+    }
 
-    // Step 2: Apply service changes - fail-fast, return error for full rollback
-    if let Err(e) = apply_service_changes_atomic(option, tweak.elevation()) {
+    // Step 3: Apply service changes - fail-fast, return error for full rollback
+    operations::check("the service changes")?;
+    if let Err(e) = metrics::phase("services", || {
+        apply_service_changes_atomic(option, tweak.elevation())
+    }) {
         log::error!("Service changes failed, need full rollback: {}", e);
         return Err(e);
     }
 
-    // Step 3: Apply scheduler changes - fail-fast, return error for full rollback
-    if let Err(e) = apply_scheduler_changes_atomic(option, tweak.elevation()) {
+    // Step 4: Apply scheduler changes - fail-fast, return error for full rollback
+    operations::check("the scheduled task changes")?;
+    if let Err(e) = metrics::phase("scheduled tasks", || {
+        apply_scheduler_changes_atomic(option, tweak.elevation())
+    }) {
         log::error!("Scheduler changes failed, need full rollback: {}", e);
         return Err(e);
     }
 
-    // Step 4: Apply hosts file changes - fail-fast, return error for full rollback
-    if let Err(e) = apply_hosts_changes_atomic(option) {
+    // Step 5: Apply hosts file changes - fail-fast, return error for full rollback
+    operations::check("the hosts file changes")?;
+    if let Err(e) = metrics::phase("hosts file", || apply_hosts_changes_atomic(option)) {
         log::error!("Hosts file changes failed, need full rollback: {}", e);
         return Err(e);
     }
 
-    // Step 5: Apply firewall changes - fail-fast, return error for full rollback
-    if let Err(e) = apply_firewall_changes_atomic(option) {
+    // Step 6: Apply firewall changes - fail-fast, return error for full rollback
+    operations::check("the firewall changes")?;
+    if let Err(e) = metrics::phase("firewall", || apply_firewall_changes_atomic(option)) {
         log::error!("Firewall changes failed, need full rollback: {}", e);
         return Err(e);
     }
 
+    // Step 7: Apply power plan changes - fail-fast, return error for full rollback
+    operations::check("the power plan changes")?;
+    if let Err(e) = metrics::phase("power plan", || apply_power_plan_changes_atomic(option)) {
+        log::error!("Power plan changes failed, need full rollback: {}", e);
+        return Err(e);
+    }
+
+    // Step 8: Apply BCD changes - fail-fast, return error for full rollback
+    operations::check("the BCD changes")?;
+    if let Err(e) = metrics::phase("BCD", || apply_bcdedit_changes_atomic(option)) {
+        log::error!("BCD changes failed, need full rollback: {}", e);
+        return Err(e);
+    }
+
+    // Step 9: Apply AppX changes - fail-fast, return error for full rollback
+    operations::check("the AppX changes")?;
+    if let Err(e) = metrics::phase("AppX", || apply_appx_changes_atomic(option)) {
+        log::error!("AppX changes failed, need full rollback: {}", e);
+        return Err(e);
+    }
+
+    // Step 10: Apply file changes - fail-fast, return error for full rollback
+    operations::check("the file changes")?;
+    if let Err(e) = metrics::phase("files", || apply_file_changes_atomic(option)) {
+        log::error!("File changes failed, need full rollback: {}", e);
+        return Err(e);
+    }
+
+    // Step 11: Apply optional feature changes - fail-fast, return error for full rollback
+    operations::check("the optional feature changes")?;
+    if let Err(e) = metrics::phase("optional features", || apply_feature_changes_atomic(option)) {
+        log::error!("Optional feature changes failed, need full rollback: {}", e);
+        return Err(e);
+    }
+
+    // Step 12: Apply network adapter changes - fail-fast, return error for full rollback
+    operations::check("the network adapter changes")?;
+    if let Err(e) = metrics::phase("network adapters", || {
+        apply_netadapter_changes_atomic(option)
+    }) {
+        log::error!("Network adapter changes failed, need full rollback: {}", e);
+        return Err(e);
+    }
+
+    // Step 13: Apply policy changes and refresh Group Policy - fail-fast, return error for full
+    // rollback
+    operations::check("the policy changes")?;
+    if let Err(e) = metrics::phase("policies", || {
+        apply_policy_changes_atomic(option, tweak.requires_system)
+    }) {
+        log::error!("Policy changes failed, need full rollback: {}", e);
+        return Err(e);
+    }
+
+    // Step 14: Apply application changes - fail-fast, return error for full rollback
+    operations::check("the application changes")?;
+    if let Err(e) = metrics::phase("applications", || {
+        apply_app_changes_atomic(&tweak.id, option)
+    }) {
+        log::error!("Application changes failed, need full rollback: {}", e);
+        return Err(e);
+    }
+
     Ok(())
 }
 
@@ -210,270 +343,382 @@ enum RegistryRollback {
         hive: RegistryHive,
         key: String,
         value_name: String,
+        value_type: RegistryValueType,
         original: Option<serde_json::Value>,
     },
-    /// Recreate a key that was deleted.
-    /// NOTE: This is best-effort only - subkeys and values within the deleted key cannot be
-    /// restored. This is acceptable because delete_key is typically used to remove keys that
-    /// were created by the opposite option (e.g., context menu CLSID entries).
-    RecreateKey { hive: RegistryHive, key: String },
+    /// Recreate a key that was deleted, with the subkeys and values read before the delete
+    /// (just the empty key if they couldn't be read)
+    RecreateKey {
+        hive: RegistryHive,
+        key: String,
+        contents: Option<RegistryKeyContents>,
+    },
     /// Delete a key that was created
     DeleteKey { hive: RegistryHive, key: String },
 }
 
-/// Apply all registry changes for an option atomically
-fn apply_registry_changes(
-    tweak: &TweakDefinition,
-    option: &TweakOption,
-    windows_version: u32,
-) -> Result<()> {
-    let mut rollbacks: Vec<RegistryRollback> = Vec::new();
+/// A registry change ready to run: the operation, the level it runs at, and how to undo it
+struct PlannedRegistryChange<'a> {
+    change: &'a RegistryChange,
+    full_path: String,
+    op: RegistryOp,
+    level: Elevation,
+    rollback: Option<RegistryRollback>,
+}
 
-    for change in &option.registry_changes {
-        // Skip if not for this Windows version
-        if !change.applies_to_version(windows_version) {
-            continue;
+/// Work out how to run a registry change and read what it's about to replace, for rollback. None
+/// when the registry already holds what the change would write: a value already set to it, a value
+/// or key already gone, a key that already exists. `skip_validation` changes aren't read, so they
+/// always run.
+fn plan_registry_change<'a>(
+    tweak: &TweakDefinition,
+    change: &'a RegistryChange,
+) -> Result<Option<PlannedRegistryChange<'a>>> {
+    let full_path = format!(
+        "{}\\{}{}",
+        change.hive.as_str(),
+        change.key,
+        if change.value_name.is_empty() {
+            String::new()
+        } else {
+            format!("\\{}", change.value_name)
         }
+    );
+    let skip_validation = if change.skip_validation {
+        " (skip_validation)"
+    } else {
+        ""
+    };
+    // Deletes and key creation run in-process unless the change declares an elevation
+    let declared = declared_registry_elevation(change).unwrap_or(Elevation::None);
+
+    let (op, level, rollback) = match change.action {
+        RegistryAction::Set => {
+            // Set action - write a value
+            let value_type = match &change.value_type {
+                Some(vt) => *vt,
+                None => {
+                    log::error!("Set action requires value_type: {}", full_path);
+                    return Err(Error::ValidationError(
+                        "Set action requires value_type".into(),
+                    ));
+                }
+            };
+            let value = match &change.value {
+                Some(v) => v.clone(),
+                None => {
+                    log::error!("Set action requires value: {}", full_path);
+                    return Err(Error::ValidationError("Set action requires value".into()));
+                }
+            };
 
-        let full_path = format!(
-            "{}\\{}{}",
-            change.hive.as_str(),
-            change.key,
-            if change.value_name.is_empty() {
-                String::new()
+            // A change's own elevation overrides the tweak's; writes follow `requires_system`
+            // otherwise. HKCU is always written in-process (see `write_registry_json_value`).
+            let write_elevation = match change.elevation {
+                Some(elevation) => elevation.level(),
+                None if tweak.requires_system => Elevation::System,
+                None => Elevation::None,
+            };
+            let level = if change.hive.requires_admin() {
+                write_elevation
             } else {
-                format!("\\{}", change.value_name)
-            }
-        );
+                Elevation::None
+            };
 
-        let result = match change.action {
-            RegistryAction::Set => {
-                // Set action - write a value
-                let value_type = match &change.value_type {
-                    Some(vt) => vt,
-                    None => {
-                        log::error!("Set action requires value_type: {}", full_path);
-                        return Err(Error::ValidationError(
-                            "Set action requires value_type".into(),
-                        ));
-                    }
-                };
-                let value = match &change.value {
-                    Some(v) => v,
-                    None => {
-                        log::error!("Set action requires value: {}", full_path);
-                        return Err(Error::ValidationError("Set action requires value".into()));
-                    }
-                };
+            // Read current value for rollback (only for validatable changes)
+            let (original_type, current) = if !change.skip_validation {
+                read_original_value(
+                    &change.hive,
+                    &change.key,
+                    &change.value_name,
+                    Some(value_type),
+                )?
+            } else {
+                (value_type, None)
+            };
+            if original_type == value_type
+                && current.is_some()
+                && registry_value::registry_values_match(
+                    &value_type,
+                    &current,
+                    &Some(value.clone()),
+                )
+                .unwrap_or(false)
+            {
+                log::debug!("{} is already {:?}, skipping", full_path, value);
+                return Ok(None);
+            }
 
-                // Read current value for rollback (only for validatable changes)
-                let current = if !change.skip_validation {
-                    read_registry_value(&change.hive, &change.key, &change.value_name, value_type)?
-                } else {
-                    None
-                };
+            log::debug!(
+                "Setting{} {} = {:?} (was {:?})",
+                skip_validation,
+                full_path,
+                value,
+                current
+            );
 
-                log::debug!(
-                    "Setting{} {} = {:?} (was {:?})",
-                    if change.skip_validation {
-                        " (skip_validation)"
-                    } else {
-                        ""
-                    },
-                    full_path,
-                    value,
-                    current
-                );
+            let rollback = (!change.skip_validation).then(|| RegistryRollback::RestoreValue {
+                hive: change.hive,
+                key: change.key.clone(),
+                value_name: change.value_name.clone(),
+                value_type: original_type,
+                original: current,
+            });
+            let op = RegistryOp::Set {
+                hive: change.hive,
+                key: change.key.clone(),
+                value_name: change.value_name.clone(),
+                value_type,
+                value,
+            };
+            (op, level, rollback)
+        }
 
-                let write_result = write_registry_value(
+        RegistryAction::DeleteValue => {
+            // Read current value for rollback, as whatever type it is stored as
+            let (original_type, current) = if !change.skip_validation {
+                read_original_value(
                     &change.hive,
                     &change.key,
                     &change.value_name,
-                    value_type,
-                    value,
-                    tweak.requires_system,
-                );
+                    change.value_type,
+                )?
+            } else {
+                (RegistryValueType::Dword, None)
+            };
+            if !change.skip_validation && current.is_none() {
+                log::debug!("{} is already absent, skipping", full_path);
+                return Ok(None);
+            }
+            log::debug!("Deleting value{} {}", skip_validation, full_path);
+
+            let rollback = current.is_some().then(|| RegistryRollback::RestoreValue {
+                hive: change.hive,
+                key: change.key.clone(),
+                value_name: change.value_name.clone(),
+                value_type: original_type,
+                original: current,
+            });
+            let op = RegistryOp::DeleteValue {
+                hive: change.hive,
+                key: change.key.clone(),
+                value_name: change.value_name.clone(),
+            };
+            (op, declared, rollback)
+        }
 
-                if write_result.is_ok() && !change.skip_validation {
-                    rollbacks.push(RegistryRollback::RestoreValue {
-                        hive: change.hive,
-                        key: change.key.clone(),
-                        value_name: change.value_name.clone(),
-                        original: current,
-                    });
+        RegistryAction::DeleteKey => {
+            // Check if key exists for rollback tracking, and keep what's in it
+            let key_existed = existing_key(change);
+            if key_existed == Some(false) {
+                log::debug!("{} is already absent, skipping", full_path);
+                return Ok(None);
+            }
+            log::debug!("Deleting key{} {}", skip_validation, full_path);
+
+            let rollback = (key_existed == Some(true)).then(|| {
+                let contents = backup::read_key_contents(&change.hive, &change.key)
+                    .map_err(|e| {
+                        log::warn!(
+                            "Couldn't read {} before deleting it; rollback recreates it empty: {}",
+                            full_path,
+                            e
+                        )
+                    })
+                    .ok();
+                RegistryRollback::RecreateKey {
+                    hive: change.hive,
+                    key: change.key.clone(),
+                    contents,
                 }
+            });
+            let op = RegistryOp::DeleteKey {
+                hive: change.hive,
+                key: change.key.clone(),
+            };
+            (op, declared, rollback)
+        }
 
-                write_result
+        RegistryAction::CreateKey => {
+            // Check if key already exists for rollback
+            let key_existed = existing_key(change);
+            if key_existed == Some(true) {
+                log::debug!("{} already exists, skipping", full_path);
+                return Ok(None);
             }
+            log::debug!("Creating key{} {}", skip_validation, full_path);
+
+            let rollback = (key_existed == Some(false)).then(|| RegistryRollback::DeleteKey {
+                hive: change.hive,
+                key: change.key.clone(),
+            });
+            let op = RegistryOp::CreateKey {
+                hive: change.hive,
+                key: change.key.clone(),
+            };
+            (op, declared, rollback)
+        }
+    };
 
-            RegistryAction::DeleteValue => {
-                log::debug!(
-                    "Deleting value{} {}",
-                    if change.skip_validation {
-                        " (skip_validation)"
-                    } else {
-                        ""
-                    },
-                    full_path
-                );
-
-                // Read current value for rollback
-                let current = if !change.skip_validation {
-                    // Try to detect type and read - use DWORD as default
-                    let value_type = change.value_type.unwrap_or(RegistryValueType::Dword);
-                    read_registry_value(&change.hive, &change.key, &change.value_name, &value_type)?
-                } else {
-                    None
-                };
-
-                let delete_result =
-                    registry_service::delete_value(&change.hive, &change.key, &change.value_name);
+    Ok(Some(PlannedRegistryChange {
+        change,
+        full_path,
+        op,
+        level,
+        rollback,
+    }))
+}
 
-                // Treat not-found as success for delete operations
-                let result = match delete_result {
-                    Err(Error::RegistryKeyNotFound(_)) => Ok(()),
-                    other => other,
-                };
+/// Whether a change's key exists, or None for a `skip_validation` change (not checked) or a key
+/// that couldn't be checked
+fn existing_key(change: &RegistryChange) -> Option<bool> {
+    if change.skip_validation {
+        return None;
+    }
+    registry_service::key_exists(&change.hive, &change.key).ok()
+}
 
-                if result.is_ok() && !change.skip_validation && current.is_some() {
-                    rollbacks.push(RegistryRollback::RestoreValue {
-                        hive: change.hive,
-                        key: change.key.clone(),
-                        value_name: change.value_name.clone(),
-                        original: current,
-                    });
+/// Undo applied registry changes, newest first
+fn roll_back_registry_changes(rollbacks: &[RegistryRollback]) {
+    for rollback in rollbacks.iter().rev() {
+        match rollback {
+            RegistryRollback::RestoreValue {
+                hive,
+                key,
+                value_name,
+                value_type,
+                original,
+            } => {
+                if let Some(val) = original {
+                    let _ = registry_value::write_registry_json_value(
+                        hive, key, value_name, value_type, val, false,
+                    );
+                } else {
+                    let _ = registry_service::delete_value(hive, key, value_name);
                 }
-
-                result
             }
-
-            RegistryAction::DeleteKey => {
-                log::debug!(
-                    "Deleting key{} {}",
-                    if change.skip_validation {
-                        " (skip_validation)"
-                    } else {
-                        ""
-                    },
-                    full_path
-                );
-
-                // Check if key exists for rollback tracking
-                let key_existed = if !change.skip_validation {
-                    registry_service::key_exists(&change.hive, &change.key).unwrap_or(false)
-                } else {
-                    false
-                };
-
-                let delete_result = registry_service::delete_key(&change.hive, &change.key);
-
-                // Treat not-found as success for delete operations
-                let result = match delete_result {
-                    Err(Error::RegistryKeyNotFound(_)) => Ok(()),
-                    other => other,
+            RegistryRollback::RecreateKey {
+                hive,
+                key,
+                contents,
+            } => {
+                let _ = match contents {
+                    Some(contents) => backup::write_key_contents(hive, key, contents, false),
+                    None => registry_service::create_key(hive, key),
                 };
+            }
+            RegistryRollback::DeleteKey { hive, key } => {
+                let _ = registry_service::delete_key(hive, key);
+            }
+        }
+    }
+}
 
-                if result.is_ok() && !change.skip_validation && key_existed {
-                    rollbacks.push(RegistryRollback::RecreateKey {
-                        hive: change.hive,
-                        key: change.key.clone(),
-                    });
-                }
-
-                result
+/// Apply all registry changes for an option atomically.
+///
+/// Every change is planned (and its original read) up front, then consecutive changes at the same
+/// elevation run as one batch: a single broker round trip for SYSTEM / TrustedInstaller instead of
+/// one per change. A batch stops at its first failure; a `skip_validation` failure is logged and
+/// the rest go as a new batch, any other failure rolls back everything applied so far.
+fn apply_registry_changes(
+    tweak: &TweakDefinition,
+    option: &TweakOption,
+    windows_version: u32,
+) -> Result<()> {
+    // ALL_USERS changes become one change per user hive, each with its own rollback; MMCSS,
+    // context menu, audio, visual effects, and time changes are written (and rolled back) as the
+    // registry changes they map to
+    let registry_changes = registry_service::option_registry_changes(option)?;
+
+    let mut planned = Vec::new();
+    let mut already_in_place = 0;
+    for change in &registry_changes {
+        // Skip if not for this Windows version
+        if change.applies_to(windows_version, system_info_service::system_facts()) {
+            match plan_registry_change(tweak, change)? {
+                Some(planned_change) => planned.push(planned_change),
+                None => already_in_place += 1,
             }
+        }
+    }
+    if already_in_place > 0 {
+        log::info!(
+            "{} of {} registry changes already in place, writing {}",
+            already_in_place,
+            already_in_place + planned.len(),
+            planned.len()
+        );
+    }
 
-            RegistryAction::CreateKey => {
-                log::debug!(
-                    "Creating key{} {}",
-                    if change.skip_validation {
-                        " (skip_validation)"
-                    } else {
-                        ""
-                    },
-                    full_path
-                );
+    let mut rollbacks: Vec<RegistryRollback> = Vec::new();
+    let mut next = 0;
+    while next < planned.len() {
+        let level = planned[next].level;
+        let end = planned[next..]
+            .iter()
+            .position(|p| p.level != level)
+            .map_or(planned.len(), |len| next + len);
+        let batch = &mut planned[next..end];
+        let ops: Vec<RegistryOp> = batch.iter().map(|p| p.op.clone()).collect();
 
-                // Check if key already exists for rollback
-                let key_existed = if !change.skip_validation {
-                    registry_service::key_exists(&change.hive, &change.key).unwrap_or(false)
-                } else {
-                    false
-                };
+        let results = match trusted_installer::run_registry_batch(level, &ops) {
+            Ok(results) => results,
+            Err(e) => {
+                // Any of the batch may have run, so undo all of it
+                log::error!(
+                    "Failed a batch of {} registry changes{}: {}",
+                    ops.len(),
+                    elevation_suffix(level),
+                    e
+                );
+                rollbacks.extend(batch.iter_mut().filter_map(|p| p.rollback.take()));
+                roll_back_registry_changes(&rollbacks);
+                return Err(e);
+            }
+        };
+        next += results.len();
 
-                let create_result = registry_service::create_key(&change.hive, &change.key);
+        for (planned_change, result) in batch.iter_mut().zip(results) {
+            let change = planned_change.change;
+            let full_path = &planned_change.full_path;
 
-                if create_result.is_ok() && !change.skip_validation && !key_existed {
-                    rollbacks.push(RegistryRollback::DeleteKey {
-                        hive: change.hive,
-                        key: change.key.clone(),
-                    });
+            // Handle errors
+            if let Err(e) = result {
+                if change.skip_validation {
+                    log::warn!(
+                        "Failed {:?} on {} (skip_validation, continuing): {}",
+                        change.action,
+                        full_path,
+                        e
+                    );
+                    continue;
                 }
 
-                create_result
+                log::error!("Failed {:?} on {}: {}", change.action, full_path, e);
+                roll_back_registry_changes(&rollbacks);
+                return Err(e);
             }
-        };
+            rollbacks.extend(planned_change.rollback.take());
 
-        // Handle errors
-        if let Err(e) = result {
-            if change.skip_validation {
-                log::warn!(
-                    "Failed {:?} on {} (skip_validation, continuing): {}",
-                    change.action,
-                    full_path,
-                    e
+            // Debug logging
+            if is_debug_enabled() {
+                let action_str = match change.action {
+                    RegistryAction::Set => format!("Set {:?}", change.value),
+                    RegistryAction::DeleteValue => "Deleted value".to_string(),
+                    RegistryAction::DeleteKey => "Deleted key".to_string(),
+                    RegistryAction::CreateKey => "Created key".to_string(),
+                };
+                emit_debug_log(
+                    DebugLevel::Info,
+                    &format!(
+                        "{}{} {}",
+                        if change.skip_validation { "[sv] " } else { "" },
+                        action_str,
+                        full_path
+                    ),
+                    None,
                 );
-                continue;
-            }
-
-            log::error!("Failed {:?} on {}: {}", change.action, full_path, e);
-
-            // Rollback applied changes
-            for rollback in rollbacks.iter().rev() {
-                match rollback {
-                    RegistryRollback::RestoreValue {
-                        hive,
-                        key,
-                        value_name,
-                        original,
-                    } => {
-                        if let Some(val) = original {
-                            let _ = restore_value(hive, key, value_name, val);
-                        } else {
-                            let _ = registry_service::delete_value(hive, key, value_name);
-                        }
-                    }
-                    RegistryRollback::RecreateKey { hive, key } => {
-                        // Best effort - just create the key (values are lost)
-                        let _ = registry_service::create_key(hive, key);
-                    }
-                    RegistryRollback::DeleteKey { hive, key } => {
-                        let _ = registry_service::delete_key(hive, key);
-                    }
-                }
             }
-
-            return Err(e);
-        }
-
-        // Debug logging
-        if is_debug_enabled() {
-            let action_str = match change.action {
-                RegistryAction::Set => format!("Set {:?}", change.value),
-                RegistryAction::DeleteValue => "Deleted value".to_string(),
-                RegistryAction::DeleteKey => "Deleted key".to_string(),
-                RegistryAction::CreateKey => "Created key".to_string(),
-            };
-            emit_debug_log(
-                DebugLevel::Info,
-                &format!(
-                    "{}{} {}",
-                    if change.skip_validation { "[sv] " } else { "" },
-                    action_str,
-                    full_path
-                ),
-                None,
-            );
         }
     }
 
@@ -482,8 +727,9 @@ fn apply_registry_changes(
 }
 
 /// Apply all service changes for an option atomically
-fn apply_service_changes_atomic(option: &TweakOption, elevation: Elevation) -> Result<()> {
-    for change in &option.service_changes {
+fn apply_service_changes_atomic(option: &TweakOption, tweak_elevation: Elevation) -> Result<()> {
+    for change in &option.all_service_changes() {
+        let elevation = ChangeElevation::resolve(change.elevation, tweak_elevation);
         let status = match service_control::get_service_status(&change.name) {
             Ok(status) => Some(status),
             Err(e) => {
@@ -496,6 +742,16 @@ fn apply_service_changes_atomic(option: &TweakOption, elevation: Elevation) -> R
             }
         };
 
+        // Components differ between editions; one of their services that isn't installed has
+        // nothing to change
+        if status.as_ref().is_some_and(|s| !s.exists) && option.is_component_service(&change.name) {
+            log::info!(
+                "Service '{}' is not installed, skipping component change",
+                change.name
+            );
+            continue;
+        }
+
         let current_startup = status.as_ref().and_then(|s| s.startup_type);
         let current_state = status.as_ref().map(|s| &s.state);
 
@@ -627,8 +883,9 @@ fn apply_service_changes_atomic(option: &TweakOption, elevation: Elevation) -> R
 }
 
 /// Apply all scheduler changes for an option atomically
-fn apply_scheduler_changes_atomic(option: &TweakOption, elevation: Elevation) -> Result<()> {
+fn apply_scheduler_changes_atomic(option: &TweakOption, tweak_elevation: Elevation) -> Result<()> {
     for change in &option.scheduler_changes {
+        let elevation = ChangeElevation::resolve(change.elevation, tweak_elevation);
         let is_pattern = change.task_name_pattern.is_some();
         let identifier = if let Some(ref pattern) = change.task_name_pattern {
             pattern.clone()
@@ -683,6 +940,15 @@ fn apply_scheduler_changes_atomic(option: &TweakOption, elevation: Elevation) ->
     Ok(())
 }
 
+/// The state a scheduler action leaves a task in
+fn scheduler_target_state(action: SchedulerAction) -> scheduler_service::TaskState {
+    match action {
+        SchedulerAction::Enable => scheduler_service::TaskState::Ready,
+        SchedulerAction::Disable => scheduler_service::TaskState::Disabled,
+        SchedulerAction::Delete => scheduler_service::TaskState::NotFound,
+    }
+}
+
 /// Apply scheduler change for a pattern match
 fn apply_scheduler_pattern(
     change: &crate::models::SchedulerChange,
@@ -699,97 +965,92 @@ fn apply_scheduler_pattern(
         }
     };
 
-    if elevation.is_elevated() {
-        // Resolve matching task names (read-only), then apply each through the typed op below.
-        let tasks = scheduler_service::find_tasks_by_pattern(&change.task_path, pattern)?;
-
-        if tasks.is_empty() {
-            if change.ignore_not_found || change.skip_validation {
-                log::warn!(
-                    "No tasks found matching pattern '{}' in '{}' ({})",
-                    pattern,
-                    change.task_path,
-                    if change.ignore_not_found {
-                        "ignore_not_found"
-                    } else {
-                        "skip_validation"
-                    }
-                );
-                return Ok(());
-            } else {
-                return Err(Error::CommandExecution(format!(
-                    "No tasks found matching pattern '{}' in '{}'",
-                    pattern, change.task_path
-                )));
-            }
+    // When every matching task is already in the target state, there's nothing to send
+    let target = scheduler_target_state(change.action);
+    if let Ok(tasks) = scheduler_service::find_tasks_by_pattern(&change.task_path, pattern) {
+        if !tasks.is_empty()
+            && tasks
+                .iter()
+                .all(|task| backup::task_state_matches(&task.state, &target))
+        {
+            log::info!(
+                "All {} tasks matching '{}' in '{}' already {}, skipping",
+                tasks.len(),
+                pattern,
+                change.task_path,
+                target.as_str()
+            );
+            return Ok(());
         }
+    }
 
-        for task in tasks {
-            let full_path = format!("{}\\{}", change.task_path, task.name);
-            let result = trusted_installer::run_scheduler_op(
-                elevation,
-                &change.task_path,
-                &task.name,
-                change.action,
+    // One typed op for every elevation, like the exact-name path: the matching tasks are listed and
+    // changed together, in-process or inside the broker, in a single round trip.
+    let outcomes = trusted_installer::run_scheduler_pattern(
+        elevation,
+        &change.task_path,
+        pattern,
+        change.action,
+    )?;
+
+    if outcomes.is_empty() {
+        if change.ignore_not_found || change.skip_validation {
+            log::warn!(
+                "No tasks found matching pattern '{}' in '{}' ({})",
+                pattern,
+                change.task_path,
+                if change.ignore_not_found {
+                    "ignore_not_found"
+                } else {
+                    "skip_validation"
+                }
             );
-
-            if let Err(e) = result {
-                if change.skip_validation {
-                    log::warn!(
-                        "Failed to apply scheduler change for '{}' (skip_validation, continuing): {}",
-                        full_path,
-                        e
-                    );
-                    continue;
-                }
-                return Err(Error::CommandExecution(format!(
-                    "Failed to apply scheduler change for '{}': {}",
-                    full_path, e
-                )));
-            }
-
-            if is_debug_enabled() {
-                emit_debug_log(
-                    DebugLevel::Info,
-                    &format!(
-                        "Scheduler{}: {} → {:?}",
-                        flags_str, full_path, change.action
-                    ),
-                    None,
-                );
-            }
+            return Ok(());
         }
-    } else {
-        let (success_count, error_count, errors) = scheduler_service::apply_action_to_pattern(
-            &change.task_path,
-            pattern,
-            change.action,
-            change.ignore_not_found,
-        )?;
-
-        if error_count > 0 {
-            if change.skip_validation {
-                log::warn!(
-                    "Pattern '{}': {} succeeded, {} failed (skip_validation): {:?}",
-                    pattern,
-                    success_count,
-                    error_count,
-                    errors
-                );
-            } else {
-                return Err(Error::CommandExecution(format!(
-                    "Pattern '{}': {} succeeded, {} failed: {:?}",
-                    pattern, success_count, error_count, errors
-                )));
-            }
+        return Err(Error::CommandExecution(format!(
+            "No tasks found matching pattern '{}' in '{}'",
+            pattern, change.task_path
+        )));
+    }
+
+    let errors: Vec<String> = outcomes
+        .iter()
+        .filter_map(|outcome| {
+            let error = outcome.error.as_ref()?;
+            Some(format!("{}\\{}: {}", change.task_path, outcome.name, error))
+        })
+        .collect();
+    if !errors.is_empty() {
+        if change.skip_validation {
+            log::warn!(
+                "Pattern '{}': {} succeeded, {} failed (skip_validation): {:?}",
+                pattern,
+                outcomes.len() - errors.len(),
+                errors.len(),
+                errors
+            );
+        } else {
+            return Err(Error::CommandExecution(format!(
+                "Pattern '{}': {} succeeded, {} failed: {:?}",
+                pattern,
+                outcomes.len() - errors.len(),
+                errors.len(),
+                errors
+            )));
         }
+    }
 
-        if is_debug_enabled() {
+    if is_debug_enabled() {
+        for outcome in outcomes.iter().filter(|outcome| outcome.error.is_none()) {
             emit_debug_log(
                 DebugLevel::Info,
                 &format!(
-                    "Scheduler{}: {}\\[{}] → {:?} ({} tasks)",
-                    flags_str, change.task_path, pattern, change.action, success_count
+                    "Scheduler{}: {}\\{} → {:?} (now {})",
+                    flags_str,
+                    change.task_path,
+                    outcome.name,
+                    change.action,
+                    outcome.state.as_str()
                 ),
                 None,
             );
@@ -815,6 +1076,14 @@ fn apply_scheduler_exact(
     };
     let full_path = format!("{}\\{}", change.task_path, task_name);
 
+    let target = scheduler_target_state(change.action);
+    if scheduler_service::get_task_state(&change.task_path, task_name)
+        .is_ok_and(|current| backup::task_state_matches(&current, &target))
+    {
+        log::info!("Task '{}' already {}, skipping", full_path, target.as_str());
+        return Ok(());
+    }
+
     // One typed op for every elevation: unelevated runs it in-process, SYSTEM/TI run it inside the
     // broker. No schtasks string, so a task name with cmd metacharacters can't be corrupted (C3).
     let result =
@@ -963,21 +1232,572 @@ fn apply_firewall_changes_atomic(option: &TweakOption) -> Result<()> {
     Ok(())
 }
 
+// ============================================================================
+// Power Plan Operations
+// ============================================================================
+
+/// Apply all power plan changes atomically
+fn apply_power_plan_changes_atomic(option: &TweakOption) -> Result<()> {
+    if option.power_plan_changes.is_empty() {
+        return Ok(());
+    }
+
+    log::debug!(
+        "Applying {} power plan changes",
+        option.power_plan_changes.len()
+    );
+
+    // Pin SCHEME_CURRENT to the scheme active before this option runs, so a set_active_scheme
+    // earlier in the list doesn't redirect later setting writes (capture resolves it the same way).
+    let current_scheme = power_service::get_active_scheme()?;
+
+    for change in &option.power_plan_changes {
+        let target = match (&change.scheme, &change.setting) {
+            (_, Some(setting)) => format!(
+                "{}\\{}",
+                change
+                    .scheme
+                    .as_deref()
+                    .unwrap_or(power_service::SCHEME_CURRENT),
+                setting
+            ),
+            (Some(scheme), None) => scheme.clone(),
+            (None, None) => power_service::SCHEME_CURRENT.to_string(),
+        };
+        log::debug!("Power plan change: {} {}", change.action.as_str(), target);
+
+        let result = power_service::apply_power_plan_change(change, &current_scheme);
+
+        if let Err(e) = result {
+            if change.skip_validation {
+                log::warn!(
+                    "Failed to apply power plan change for '{}' (skip_validation, continuing): {}",
+                    target,
+                    e
+                );
+                continue;
+            } else {
+                return Err(Error::CommandExecution(format!(
+                    "Failed to apply power plan change for '{}': {}",
+                    target, e
+                )));
+            }
+        }
+
+        if is_debug_enabled() {
+            let details = match change.action {
+                crate::models::PowerPlanAction::SetActiveScheme => {
+                    format!("activate {}", target)
+                }
+                crate::models::PowerPlanAction::SetSettingValue => format!(
+                    "{} ac={:?} dc={:?}",
+                    target, change.ac_value, change.dc_value
+                ),
+            };
+
+            emit_debug_log(DebugLevel::Info, &format!("Power plan: {}", details), None);
+        }
+    }
+
+    Ok(())
+}
+
+// ============================================================================
+// BCD Operations
+// ============================================================================
+
+/// Apply all BCD changes atomically
+fn apply_bcdedit_changes_atomic(option: &TweakOption) -> Result<()> {
+    if option.bcdedit_changes.is_empty() {
+        return Ok(());
+    }
+
+    log::debug!("Applying {} BCD changes", option.bcdedit_changes.len());
+
+    for change in &option.bcdedit_changes {
+        let identifier = bcdedit_service::resolve_identifier(change.identifier.as_deref());
+        log::debug!(
+            "BCD change: {} {} {}",
+            change.action.as_str(),
+            identifier,
+            change.element
+        );
+
+        let result = bcdedit_service::apply_bcdedit_change(change);
+
+        if let Err(e) = result {
+            if change.skip_validation {
+                log::warn!(
+                    "Failed to apply BCD change for '{} {}' (skip_validation, continuing): {}",
+                    identifier,
+                    change.element,
+                    e
+                );
+                continue;
+            } else {
+                return Err(Error::CommandExecution(format!(
+                    "Failed to apply BCD change for '{} {}': {}",
+                    identifier, change.element, e
+                )));
+            }
+        }
+
+        if is_debug_enabled() {
+            let details = match change.action {
+                crate::models::BcdeditAction::Set => format!(
+                    "{} {} = {}",
+                    identifier,
+                    change.element,
+                    change.value.as_deref().unwrap_or_default()
+                ),
+                crate::models::BcdeditAction::DeleteValue => {
+                    format!("{} {} deleted", identifier, change.element)
+                }
+            };
+
+            emit_debug_log(DebugLevel::Info, &format!("BCD: {}", details), None);
+        }
+    }
+
+    Ok(())
+}
+
+// ============================================================================
+// AppX Operations
+// ============================================================================
+
+/// Apply all AppX changes atomically
+fn apply_appx_changes_atomic(option: &TweakOption) -> Result<()> {
+    if option.appx_changes.is_empty() {
+        return Ok(());
+    }
+
+    log::debug!("Applying {} AppX changes", option.appx_changes.len());
+
+    for change in &option.appx_changes {
+        log::debug!(
+            "AppX change: {} {} ({})",
+            change.action.as_str(),
+            change.package,
+            change.scope.as_str()
+        );
+
+        let result = appx_service::apply_appx_change(change);
+
+        if let Err(e) = result {
+            if change.skip_validation {
+                log::warn!(
+                    "Failed to apply AppX change for '{}' (skip_validation, continuing): {}",
+                    change.package,
+                    e
+                );
+                continue;
+            } else {
+                return Err(Error::CommandExecution(format!(
+                    "Failed to apply AppX change for '{}': {}",
+                    change.package, e
+                )));
+            }
+        }
+
+        if is_debug_enabled() {
+            emit_debug_log(
+                DebugLevel::Info,
+                &format!(
+                    "AppX: {} {} ({})",
+                    change.action.as_str(),
+                    change.package,
+                    change.scope.as_str()
+                ),
+                None,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+// ============================================================================
+// File Operations
+// ============================================================================
+
+/// Apply all file changes atomically
+fn apply_file_changes_atomic(option: &TweakOption) -> Result<()> {
+    if option.file_changes.is_empty() {
+        return Ok(());
+    }
+
+    log::debug!("Applying {} file changes", option.file_changes.len());
+
+    for change in &option.file_changes {
+        log::debug!("File change: {} {}", change.action.as_str(), change.path);
+
+        let result = file_service::apply_file_change(change);
+
+        if let Err(e) = result {
+            if change.skip_validation {
+                log::warn!(
+                    "Failed to apply file change for '{}' (skip_validation, continuing): {}",
+                    change.path,
+                    e
+                );
+                continue;
+            } else {
+                return Err(Error::CommandExecution(format!(
+                    "Failed to apply file change for '{}': {}",
+                    change.path, e
+                )));
+            }
+        }
+
+        if is_debug_enabled() {
+            emit_debug_log(
+                DebugLevel::Info,
+                &format!("File: {} {}", change.action.as_str(), change.path),
+                None,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+// ============================================================================
+// Registry ACL Operations
+// ============================================================================
+
+/// Apply all registry ACL changes atomically
+fn apply_registry_acl_changes_atomic(option: &TweakOption) -> Result<()> {
+    if option.registry_acl_changes.is_empty() {
+        return Ok(());
+    }
+
+    log::debug!(
+        "Applying {} registry ACL changes",
+        option.registry_acl_changes.len()
+    );
+
+    for change in &option.registry_acl_changes {
+        log::debug!(
+            "Registry ACL change: {} {}\\{}",
+            change.action.as_str(),
+            change.hive.as_str(),
+            change.key
+        );
+
+        let result = registry_acl_service::apply_registry_acl_change(change);
+
+        if let Err(e) = result {
+            if change.skip_validation {
+                log::warn!(
+                    "Failed to apply registry ACL change for '{}' (skip_validation, continuing): {}",
+                    change.key,
+                    e
+                );
+                continue;
+            } else {
+                return Err(Error::CommandExecution(format!(
+                    "Failed to apply registry ACL change for '{}': {}",
+                    change.key, e
+                )));
+            }
+        }
+
+        if is_debug_enabled() {
+            emit_debug_log(
+                DebugLevel::Info,
+                &format!(
+                    "Registry ACL: {} {}\\{}",
+                    change.action.as_str(),
+                    change.hive.as_str(),
+                    change.key
+                ),
+                None,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+// ============================================================================
+// Optional Feature Operations
+// ============================================================================
+
+/// Apply all optional feature changes atomically
+fn apply_feature_changes_atomic(option: &TweakOption) -> Result<()> {
+    if option.feature_changes.is_empty() {
+        return Ok(());
+    }
+
+    log::debug!(
+        "Applying {} optional feature changes",
+        option.feature_changes.len()
+    );
+
+    for change in &option.feature_changes {
+        log::debug!(
+            "Optional feature change: {} {}",
+            change.action.as_str(),
+            change.feature
+        );
+
+        let result = dism_service::apply_feature_change(change);
+
+        if let Err(e) = result {
+            if change.skip_validation {
+                log::warn!(
+                    "Failed to apply optional feature change for '{}' (skip_validation, continuing): {}",
+                    change.feature,
+                    e
+                );
+                continue;
+            } else {
+                return Err(Error::CommandExecution(format!(
+                    "Failed to apply optional feature change for '{}': {}",
+                    change.feature, e
+                )));
+            }
+        }
+
+        if is_debug_enabled() {
+            emit_debug_log(
+                DebugLevel::Info,
+                &format!("Feature: {} {}", change.action.as_str(), change.feature),
+                None,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+// ============================================================================
+// Network Adapter Operations
+// ============================================================================
+
+/// Apply all network adapter changes atomically
+fn apply_netadapter_changes_atomic(option: &TweakOption) -> Result<()> {
+    if option.netadapter_changes.is_empty() {
+        return Ok(());
+    }
+
+    log::debug!(
+        "Applying {} network adapter changes",
+        option.netadapter_changes.len()
+    );
+
+    for change in &option.netadapter_changes {
+        let adapter = network_service::resolve_adapter(change.adapter.as_deref());
+        log::debug!(
+            "Network adapter change: {} {} = {}",
+            adapter,
+            change.keyword,
+            change.value
+        );
+
+        let result = network_service::apply_netadapter_change(change);
+
+        if let Err(e) = result {
+            if change.skip_validation {
+                log::warn!(
+                    "Failed to apply network adapter change for '{}' (skip_validation, continuing): {}",
+                    change.keyword,
+                    e
+                );
+                continue;
+            } else {
+                return Err(Error::CommandExecution(format!(
+                    "Failed to apply network adapter change for '{}': {}",
+                    change.keyword, e
+                )));
+            }
+        }
+
+        if is_debug_enabled() {
+            emit_debug_log(
+                DebugLevel::Info,
+                &format!(
+                    "Network adapter: {} {} = {}",
+                    adapter, change.keyword, change.value
+                ),
+                None,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+// ============================================================================
+// Group Policy Operations
+// ============================================================================
+
+/// Apply all policy changes atomically, then refresh Group Policy once per scope touched
+fn apply_policy_changes_atomic(option: &TweakOption, use_system: bool) -> Result<()> {
+    // Browser policies are applied as the policy values they map to
+    let policy_changes = option.all_policy_changes();
+    if policy_changes.is_empty() {
+        return Ok(());
+    }
+
+    log::debug!("Applying {} policy changes", policy_changes.len());
+
+    let mut scopes: Vec<PolicyScope> = Vec::new();
+    for change in &policy_changes {
+        let full_path = format!(
+            "{}\\{}\\{}",
+            change.scope.hive().as_str(),
+            change.key,
+            change.value_name
+        );
+        log::debug!(
+            "Policy change: {} {}{}",
+            change.action.as_str(),
+            full_path,
+            if change.write_pol {
+                " (Registry.pol)"
+            } else {
+                ""
+            }
+        );
+
+        let result = policy_service::apply_policy_change(change, use_system);
+
+        if let Err(e) = result {
+            if change.skip_validation {
+                log::warn!(
+                    "Failed to apply policy change for '{}' (skip_validation, continuing): {}",
+                    full_path,
+                    e
+                );
+                continue;
+            } else {
+                return Err(Error::CommandExecution(format!(
+                    "Failed to apply policy change for '{}': {}",
+                    full_path, e
+                )));
+            }
+        }
+
+        if !scopes.contains(&change.scope) {
+            scopes.push(change.scope);
+        }
+
+        if is_debug_enabled() {
+            emit_debug_log(
+                DebugLevel::Info,
+                &format!("Policy: {} {}", change.action.as_str(), full_path),
+                None,
+            );
+        }
+    }
+
+    // The values are already in place; a failed refresh only delays Group Policy catching up
+    for scope in scopes {
+        if let Err(e) = policy_service::refresh(scope) {
+            log::warn!("Failed to refresh {} policy: {}", scope.as_str(), e);
+        }
+    }
+
+    Ok(())
+}
+
+// ============================================================================
+// Application Operations
+// ============================================================================
+
+/// Apply all application changes atomically, reporting each package's progress to the frontend
+fn apply_app_changes_atomic(tweak_id: &str, option: &TweakOption) -> Result<()> {
+    if option.app_changes.is_empty() {
+        return Ok(());
+    }
+
+    let total = option.app_changes.len();
+    log::debug!("Applying {} application changes", total);
+
+    for (i, change) in option.app_changes.iter().enumerate() {
+        log::debug!(
+            "Application change: {} {}",
+            change.action.as_str(),
+            change.id
+        );
+
+        let progress = AppProgress::new(tweak_id, &change.id, change.action, i + 1, total);
+        progress.emit(AppProgressStage::Started, None);
+
+        let result = package_service::apply_app_change(change);
+
+        if let Err(e) = result {
+            progress.emit(AppProgressStage::Failed, Some(e.to_string()));
+            if change.skip_validation {
+                log::warn!(
+                    "Failed to apply application change for '{}' (skip_validation, continuing): {}",
+                    change.id,
+                    e
+                );
+                continue;
+            } else {
+                return Err(Error::CommandExecution(format!(
+                    "Failed to apply application change for '{}': {}",
+                    change.id, e
+                )));
+            }
+        }
+        progress.emit(AppProgressStage::Finished, None);
+
+        if is_debug_enabled() {
+            emit_debug_log(
+                DebugLevel::Info,
+                &format!("App: {} {}", change.action.as_str(), change.id),
+                None,
+            );
+        }
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn command_returns_error_on_nonzero_exit_code() {
-        let err = run_command("exit /b 7", Elevation::None).unwrap_err();
+        let err = run_command("exit /b 7", CommandShell::Cmd, Elevation::None, None).unwrap_err();
 
         assert!(err.to_string().contains("exit code 7"));
     }
 
     #[test]
     fn powershell_returns_error_on_nonzero_exit_code() {
-        let err = run_powershell_command("exit 7", Elevation::None).unwrap_err();
+        let err =
+            run_powershell_command("exit 7", PowerShellEngine::Windows, Elevation::None, None)
+                .unwrap_err();
 
         assert!(err.to_string().contains("exit code 7"));
     }
+
+    #[test]
+    fn a_command_runs_in_the_options_shell() {
+        // Only PowerShell can read this as exiting with 5; cmd would fail with its own code
+        let err = run_command(
+            "if ($env:SystemRoot) { exit 5 }",
+            CommandShell::Powershell,
+            Elevation::None,
+            None,
+        )
+        .unwrap_err();
+
+        assert!(err.to_string().contains("exit code 5"));
+    }
+
+    #[test]
+    fn a_timeout_is_not_reported_as_a_failed_command() {
+        let timeout = command_error("Pre-command failed", Error::Timeout("slow".to_string()));
+        assert_eq!(timeout.code(), "TIMEOUT");
+
+        let failed = command_error("Pre-command failed", Error::WindowsApi("x".to_string()));
+        assert_eq!(failed.code(), "COMMAND_EXECUTION_FAILED");
+    }
 }
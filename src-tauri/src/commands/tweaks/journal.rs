@@ -0,0 +1,104 @@
+//! Journal Commands - Operation history and undo of the last operation
+
+use super::apply::{apply_option, restart_explorer_if_requested, revert_option};
+use crate::error::{Error, Result};
+use crate::models::TweakResult;
+use crate::services::journal_service::{self, JournalOperation};
+use crate::services::system_info_service;
+use crate::state::AppState;
+use tauri::State;
+
+/// Recorded apply/revert/batch operations, newest first
+#[tauri::command]
+pub fn get_operation_history() -> Result<Vec<JournalOperation>> {
+    journal_service::get_history()
+}
+
+/// Undo the newest operation that hasn't been undone, returning every tweak it changed to the
+/// option it had before (or reverting it, if it wasn't applied). Changes are undone newest first;
+/// any that fail stay on the operation so undoing again retries them.
+#[tauri::command]
+pub async fn undo_last_operation(state: State<'_, AppState>) -> Result<TweakResult> {
+    let _operation = state.snapshot_monitor.operation();
+    let _stale = state.status_cache.changing_all();
+    let operation = journal_service::last_undoable()?
+        .ok_or_else(|| Error::NotFound("No operation to undo".to_string()))?;
+    log::info!(
+        "Command: undo_last_operation(#{} {:?}, {} changes)",
+        operation.id,
+        operation.kind,
+        operation.changes.len()
+    );
+
+    if !system_info_service::get_runtime_context()?.is_admin {
+        return Err(Error::RequiresAdmin);
+    }
+
+    let total = operation.changes.len();
+    let mut requires_reboot = false;
+    let mut requires_logoff = false;
+    let mut restart_explorer = false;
+    let mut failures: Vec<(String, String)> = Vec::new();
+    let mut remaining = Vec::new();
+
+    for change in operation.changes.into_iter().rev() {
+        let result = match change.before {
+            Some(option_index) => apply_option(change.tweak_id.clone(), option_index),
+            None => revert_option(change.tweak_id.clone()),
+        };
+
+        match result {
+            Ok((res, wants_explorer_restart)) => {
+                restart_explorer |= wants_explorer_restart;
+                requires_reboot |= res.requires_reboot;
+                requires_logoff |= res.requires_logoff;
+                if !res.success {
+                    if res.failures.is_empty() {
+                        failures.push((change.tweak_id.clone(), res.message));
+                    } else {
+                        failures.extend(res.failures);
+                    }
+                    remaining.push(change);
+                }
+            }
+            Err(e) => {
+                log::warn!("Failed to undo change to '{}': {}", change.tweak_id, e);
+                failures.push((change.tweak_id.clone(), e.to_string()));
+                remaining.push(change);
+            }
+        }
+    }
+
+    // Back in the order they were made
+    remaining.reverse();
+    let undone = total - remaining.len();
+    if let Err(e) = journal_service::finish_undo(operation.id, remaining) {
+        log::warn!("Failed to update the operation journal: {}", e);
+    }
+
+    let message = if failures.is_empty() {
+        format!("Undid {} tweak changes", undone)
+    } else {
+        format!(
+            "Undid {}/{} tweak changes ({} failed)",
+            undone,
+            total,
+            total - undone
+        )
+    };
+    log::info!("undo_last_operation completed: {}", message);
+
+    let mut result = TweakResult {
+        success: failures.is_empty(),
+        message,
+        requires_reboot,
+        requires_logoff: requires_logoff && !requires_reboot,
+        restarted_explorer: false,
+        failures,
+        restore_point_sequence: None,
+        results: Vec::new(),
+        rollback: None,
+    };
+    restart_explorer_if_requested(&mut result, restart_explorer);
+    Ok(result)
+}
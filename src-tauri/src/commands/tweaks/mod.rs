@@ -4,9 +4,19 @@
 //! - `query`: Status and listing commands
 //! - `apply`: Apply/revert single tweak commands
 //! - `batch`: Batch operations
+//! - `deferred`: Applies queued for the next boot or a set time
+//! - `editor`: Authoring custom tweaks in `tweaks.d`
+//! - `journal`: Operation history and undo
+//! - `notes`: The user's notes on tweaks
+//! - `stats`: Local, opt-in usage statistics
 //! - `helpers`: Internal helper functions for registry, services, scheduler
 
 pub mod apply;
 pub mod batch;
+pub mod deferred;
+pub mod editor;
 pub(crate) mod helpers;
+pub mod journal;
+pub mod notes;
 pub mod query;
+pub mod stats;
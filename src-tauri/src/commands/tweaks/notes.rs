@@ -0,0 +1,26 @@
+//! Notes Commands - The user's notes on tweaks
+
+use crate::error::{Error, Result};
+use crate::services::notes_service::{self, TweakNote, TweakNotes};
+use crate::services::tweak_loader;
+
+/// Every note, by tweak ID
+#[tauri::command]
+pub fn get_tweak_notes() -> Result<TweakNotes> {
+    notes_service::get_notes()
+}
+
+/// Set the note on a tweak, e.g. why it was applied; an empty note removes it. Returns the note
+/// kept.
+#[tauri::command]
+pub fn set_tweak_note(tweak_id: String, note: String) -> Result<Option<TweakNote>> {
+    log::info!(
+        "Command: set_tweak_note({}, {} chars)",
+        tweak_id,
+        note.len()
+    );
+    if tweak_loader::get_tweak(&tweak_id)?.is_none() {
+        return Err(Error::NotFound(format!("Tweak '{}'", tweak_id)));
+    }
+    notes_service::set_note(&tweak_id, &note)
+}
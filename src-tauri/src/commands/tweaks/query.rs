@@ -1,9 +1,17 @@
 //! Query Commands - Status and listing operations for tweaks
 
 use crate::error::Result;
-use crate::models::{CategoryDefinition, TweakDefinition, TweakInspection, TweakStatus};
+use crate::models::tweak_validation::is_tweak_tag;
+use crate::models::{
+    CategoryDefinition, TweakDefinition, TweakInspection, TweakSearchFilters, TweakSnapshot,
+    TweakState, TweakStatus, TweakStatusDetail,
+};
+use crate::services::profile_template_service::{self, BuiltinProfile};
+use crate::services::tweak_loader::CustomTweaksReport;
 use crate::services::{backup_service, system_info_service, tweak_loader};
-use rayon::prelude::*;
+use crate::state::AppState;
+use std::collections::HashSet;
+use tauri::State;
 
 /// Get all available categories (auto-discovered from YAML files)
 #[tauri::command]
@@ -27,22 +35,192 @@ pub async fn get_available_tweaks() -> Result<Vec<&'static TweakDefinition>> {
     Ok(tweaks)
 }
 
+/// Get the tweaks available on this Windows version that carry `tag`, from every category, by name
+#[tauri::command]
+pub async fn get_tweaks_by_tag(tag: String) -> Result<Vec<&'static TweakDefinition>> {
+    log::debug!("Command: get_tweaks_by_tag({})", tag);
+    if !is_tweak_tag(&tag) {
+        return Err(crate::error::Error::ValidationError(format!(
+            "Unknown tweak tag '{}'",
+            tag
+        )));
+    }
+    let version = system_info_service::get_windows_info()?.version_number();
+    let mut tweaks: Vec<&'static TweakDefinition> = tweak_loader::get_tweaks_for_version(version)?
+        .into_iter()
+        .filter(|tweak| tweak.tags.contains(&tag))
+        .collect();
+    tweaks.sort_by(|a, b| a.name.cmp(&b.name));
+    log::debug!("Returning {} tweaks tagged '{}'", tweaks.len(), tag);
+    Ok(tweaks)
+}
+
+/// Get the built-in profile templates (Gaming Desktop, Developer Laptop, ...), generated from the
+/// tags of the tweaks available on this Windows version
+#[tauri::command]
+pub async fn get_builtin_profiles() -> Result<Vec<BuiltinProfile>> {
+    log::debug!("Command: get_builtin_profiles");
+    let version = system_info_service::get_windows_info()?.version_number();
+    let tweaks = tweak_loader::get_tweaks_for_version(version)?;
+    Ok(profile_template_service::builtin_profiles(&tweaks))
+}
+
+/// What loading the user's `tweaks.d` folder found: the custom tweaks each file added, or the
+/// errors that kept it out
+#[tauri::command]
+pub async fn get_custom_tweaks() -> Result<&'static CustomTweaksReport> {
+    log::debug!("Command: get_custom_tweaks");
+    Ok(tweak_loader::custom_tweaks_report())
+}
+
+/// The JSON Schema of the tweak YAML format, for editors and validating tweak files
+#[tauri::command]
+pub async fn get_tweak_schema() -> Result<serde_json::Value> {
+    log::debug!("Command: get_tweak_schema");
+    Ok(tweak_loader::tweak_schema())
+}
+
+/// Search the tweaks available on this Windows version. Every word of `query` has to appear in the
+/// tweak's name, description, or info (ignoring case; an empty query matches every tweak), and the
+/// tweak has to pass `filters`. Tweaks with more of the words in their name come first, then by name.
+#[tauri::command]
+pub async fn search_tweaks(
+    query: String,
+    filters: Option<TweakSearchFilters>,
+) -> Result<Vec<&'static TweakDefinition>> {
+    log::debug!("Command: search_tweaks({:?})", query);
+    let filters = filters.unwrap_or_default();
+    let version = system_info_service::get_windows_info()?.version_number();
+    // Snapshots are only listed when the applied-state filter needs them
+    let applied: HashSet<String> = if filters.applied.is_some() {
+        backup_service::get_applied_tweaks()?.into_iter().collect()
+    } else {
+        HashSet::new()
+    };
+    let terms: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+
+    let mut found: Vec<(usize, &'static TweakDefinition)> =
+        tweak_loader::get_tweaks_for_version(version)?
+            .into_iter()
+            .filter(|tweak| passes_filters(tweak, &filters, &applied))
+            .filter_map(|tweak| name_hits(tweak, &terms).map(|hits| (hits, tweak)))
+            .collect();
+    found.sort_by(|(a_hits, a), (b_hits, b)| b_hits.cmp(a_hits).then_with(|| a.name.cmp(&b.name)));
+
+    log::debug!("Search matched {} tweaks", found.len());
+    Ok(found.into_iter().map(|(_, tweak)| tweak).collect())
+}
+
+/// Whether `tweak` passes every filter that is set. `applied` holds the tweaks with a snapshot.
+fn passes_filters(
+    tweak: &TweakDefinition,
+    filters: &TweakSearchFilters,
+    applied: &HashSet<String>,
+) -> bool {
+    (filters.risk_levels.is_empty() || filters.risk_levels.contains(&tweak.risk_level))
+        && (filters.category_ids.is_empty() || filters.category_ids.contains(&tweak.category_id))
+        && filters
+            .requires_reboot
+            .is_none_or(|wanted| tweak.requires_reboot == wanted)
+        && (filters.elevations.is_empty()
+            || filters.elevations.contains(&tweak.declared_elevation()))
+        && filters
+            .applied
+            .is_none_or(|wanted| applied.contains(&tweak.id) == wanted)
+}
+
+/// How many of the (lowercased) search `terms` appear in the tweak's name, or None when one of them
+/// appears nowhere in its name, description, or info
+fn name_hits(tweak: &TweakDefinition, terms: &[String]) -> Option<usize> {
+    let name = tweak.name.to_lowercase();
+    let rest = format!(
+        "{}\n{}",
+        tweak.description.to_lowercase(),
+        tweak.info.as_deref().unwrap_or_default().to_lowercase()
+    );
+    let mut hits = 0;
+    for term in terms {
+        if name.contains(term.as_str()) {
+            hits += 1;
+        } else if !rest.contains(term.as_str()) {
+            return None;
+        }
+    }
+    Some(hits)
+}
+
 /// Get status of a specific tweak
 /// Returns current_option_index = None if system state doesn't match any defined option
+/// A status cached since the last operation that could change it is returned as it is.
 #[tauri::command]
-pub async fn get_tweak_status(tweak_id: String) -> Result<TweakStatus> {
+pub async fn get_tweak_status(state: State<'_, AppState>, tweak_id: String) -> Result<TweakStatus> {
     log::trace!("Command: get_tweak_status({})", tweak_id);
+    if let Some(status) = state.status_cache.cached(&tweak_id) {
+        return Ok(status);
+    }
     let tweak = tweak_loader::get_tweak(&tweak_id)?
         .ok_or_else(|| crate::error::Error::WindowsApi(format!("Tweak not found: {}", tweak_id)))?;
 
     let windows_info = system_info_service::get_windows_info()?;
     let version = windows_info.version_number();
+    let generation = state.status_cache.generation();
 
     // Detect current state by matching against all options
-    let state = backup_service::detect_tweak_state(&tweak, version)?;
+    let detected = backup_service::detect_tweak_state(&tweak, version)?;
 
     // Get snapshot info if exists (for last_applied timestamp and original_option_index)
     let snapshot = backup_service::load_snapshot(&tweak_id)?;
+    let status = status_from(&tweak, detected, snapshot);
+    state.status_cache.record(vec![status.clone()], generation);
+
+    log::trace!(
+        "Tweak {} status: current_option={:?}, has_snapshot={}, original_option={:?}, inferred={}",
+        tweak_id,
+        status.current_option_index,
+        status.has_backup,
+        status.snapshot_original_option_index,
+        status.status_inferred
+    );
+    Ok(status)
+}
+
+/// Get a tweak's status with the reason for it: each option's per-change comparison with the current
+/// state (registry values expected vs actual, service startup types, task states, ...), and for a
+/// custom state the option it is closest to
+#[tauri::command]
+pub async fn get_tweak_status_detail(tweak_id: String) -> Result<TweakStatusDetail> {
+    log::debug!("Command: get_tweak_status_detail({})", tweak_id);
+    let tweak = tweak_loader::get_tweak(&tweak_id)?
+        .ok_or_else(|| crate::error::Error::NotFound(format!("Tweak '{}'", tweak_id)))?;
+    let version = system_info_service::get_windows_info()?.version_number();
+
+    let state = backup_service::detect_tweak_state(&tweak, version)?;
+    let inspection =
+        backup_service::inspect_tweak(&tweak, version, state.current_option_index, None)?;
+    let closest_option_index = match state.current_option_index {
+        Some(_) => None,
+        None => inspection
+            .options
+            .iter()
+            .filter(|option| option.matched_count + option.mismatched_count > 0)
+            .min_by_key(|option| (option.mismatched_count, option.option_index))
+            .map(|option| option.option_index),
+    };
+    let snapshot = backup_service::load_snapshot(&tweak_id)?;
+
+    Ok(TweakStatusDetail {
+        status: status_from(&tweak, state, snapshot),
+        inspection,
+        closest_option_index,
+    })
+}
+
+/// Build a tweak's status from its detected state and its snapshot (if it has one)
+pub(crate) fn status_from(
+    tweak: &TweakDefinition,
+    state: TweakState,
+    snapshot: Option<TweakSnapshot>,
+) -> TweakStatus {
     let last_applied = snapshot.as_ref().map(|s| s.created_at.clone());
     let needs_attention = snapshot
         .as_ref()
@@ -55,17 +233,8 @@ pub async fn get_tweak_status(tweak_id: String) -> Result<TweakStatus> {
     // Get original_option_index from snapshot: Some(Some(i)) if matched, Some(None) if unknown
     let snapshot_original_option_index = snapshot.map(|s| s.original_option_index);
 
-    log::trace!(
-        "Tweak {} status: current_option={:?}, has_snapshot={}, original_option={:?}, inferred={}",
-        tweak_id,
-        state.current_option_index,
-        state.has_snapshot,
-        snapshot_original_option_index,
-        state.status_inferred
-    );
-
-    Ok(TweakStatus {
-        tweak_id,
+    TweakStatus {
+        tweak_id: tweak.id.clone(),
         is_applied: state.current_option_index == Some(0),
         last_applied,
         has_backup: state.has_snapshot,
@@ -75,74 +244,62 @@ pub async fn get_tweak_status(tweak_id: String) -> Result<TweakStatus> {
         error: None,
         needs_attention,
         unrestorable_resources,
-    })
+        unavailable_options: tweak.unavailable_options(system_info_service::system_facts()),
+    }
 }
 
-/// Get status of all tweaks (parallelized for performance)
+/// Get status of all tweaks. Statuses cached since the last operation that could change them are
+/// reused; the rest are detected in parallel (see `status_cache`).
 #[tauri::command]
-pub async fn get_all_tweak_statuses() -> Result<Vec<TweakStatus>> {
+pub async fn get_all_tweak_statuses(state: State<'_, AppState>) -> Result<Vec<TweakStatus>> {
     log::debug!("Command: get_all_tweak_statuses");
-    let windows_info = system_info_service::get_windows_info()?;
-    let version = windows_info.version_number();
+    let version = system_info_service::get_windows_info()?.version_number();
+    let tweaks = tweak_loader::get_tweaks_for_version(version)?;
+    let statuses = state.status_cache.statuses(tweaks, version, false);
+    log::debug!("Returning {} tweak statuses", statuses.len());
+    Ok(statuses)
+}
 
+/// Get status of all tweaks like `get_all_tweak_statuses`, detecting every one of them again with
+/// `force` (for changes made outside the app). A status that changed is also reported with a
+/// `tweak://status-changed` event.
+#[tauri::command]
+pub async fn refresh_tweak_statuses(
+    state: State<'_, AppState>,
+    force: Option<bool>,
+) -> Result<Vec<TweakStatus>> {
+    let force = force.unwrap_or(false);
+    log::debug!("Command: refresh_tweak_statuses(force={})", force);
+    let version = system_info_service::get_windows_info()?.version_number();
     let tweaks = tweak_loader::get_tweaks_for_version(version)?;
+    Ok(state.status_cache.statuses(tweaks, version, force))
+}
 
-    // Use rayon parallel iterator for concurrent status detection
-    // This is a CPU-bound + IO-bound task that benefits from parallelization
-    let statuses: Vec<TweakStatus> = tweaks
-        .into_par_iter()
-        .map(|tweak| {
-            let id = tweak.id.clone();
-            match backup_service::detect_tweak_state(tweak, version) {
-                Ok(state) => {
-                    let snapshot = backup_service::load_snapshot(&id).ok().flatten();
-                    let last_applied = snapshot.as_ref().map(|s| s.created_at.clone());
-                    let needs_attention = snapshot
-                        .as_ref()
-                        .map(|s| s.needs_attention)
-                        .unwrap_or(false);
-                    let unrestorable_resources = snapshot
-                        .as_ref()
-                        .map(|s| s.unrestorable_resources.clone())
-                        .unwrap_or_default();
-                    let snapshot_original_option_index = snapshot.map(|s| s.original_option_index);
-
-                    TweakStatus {
-                        tweak_id: id,
-                        is_applied: state.current_option_index == Some(0),
-                        last_applied,
-                        has_backup: state.has_snapshot,
-                        current_option_index: state.current_option_index,
-                        snapshot_original_option_index,
-                        status_inferred: state.status_inferred,
-                        error: None,
-                        needs_attention,
-                        unrestorable_resources,
-                    }
-                }
-                Err(e) => {
-                    log::warn!("Failed to detect state for tweak {}: {}", id, e);
-                    // Return tweak with error state instead of dropping it
-                    // This ensures frontend sees all tweaks and can show error indicator
-                    TweakStatus {
-                        tweak_id: id,
-                        is_applied: false,
-                        last_applied: None,
-                        has_backup: false,
-                        current_option_index: None,
-                        snapshot_original_option_index: None,
-                        status_inferred: false,
-                        error: Some(format!("State detection failed: {}", e)),
-                        needs_attention: false,
-                        unrestorable_resources: Vec::new(),
-                    }
-                }
+/// Detect a tweak's status. A tweak whose detection fails gets a status with the error instead,
+/// so the frontend still sees it and can show an error indicator.
+pub(crate) fn detect_status(tweak: &TweakDefinition, version: u32) -> TweakStatus {
+    match backup_service::detect_tweak_state(tweak, version) {
+        Ok(state) => {
+            let snapshot = backup_service::load_snapshot(&tweak.id).ok().flatten();
+            status_from(tweak, state, snapshot)
+        }
+        Err(e) => {
+            log::warn!("Failed to detect state for tweak {}: {}", tweak.id, e);
+            TweakStatus {
+                tweak_id: tweak.id.clone(),
+                is_applied: false,
+                last_applied: None,
+                has_backup: false,
+                current_option_index: None,
+                snapshot_original_option_index: None,
+                status_inferred: false,
+                error: Some(format!("State detection failed: {}", e)),
+                needs_attention: false,
+                unrestorable_resources: Vec::new(),
+                unavailable_options: tweak.unavailable_options(system_info_service::system_facts()),
             }
-        })
-        .collect();
-
-    log::debug!("Returning {} tweak statuses", statuses.len());
-    Ok(statuses)
+        }
+    }
 }
 
 /// Inspect a tweak to find detailed mismatches (for "Custom Configuration" analysis)
@@ -169,3 +326,60 @@ pub async fn get_tweak_inspection(tweak_id: String) -> Result<TweakInspection> {
 
     Ok(result)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ChangeElevation, RiskLevel};
+
+    fn tweak(id: &str, name: &str, description: &str) -> TweakDefinition {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": name,
+            "description": description,
+            "info": "Applies to the Start menu",
+            "risk_level": "low",
+            "requires_admin": true,
+            "options": [],
+            "category_id": "ui",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn every_term_must_appear_and_name_hits_rank_higher() {
+        let t = tweak("web_search", "Disable Web Search", "Stops Bing results");
+        let terms =
+            |q: &str| -> Vec<String> { q.split_whitespace().map(str::to_lowercase).collect() };
+        assert_eq!(name_hits(&t, &terms("web bing")), Some(1));
+        assert_eq!(name_hits(&t, &terms("SEARCH start")), Some(1));
+        assert_eq!(name_hits(&t, &terms("web cortana")), None);
+        assert_eq!(name_hits(&t, &terms("")), Some(0));
+    }
+
+    #[test]
+    fn filters_combine_and_empty_ones_match_everything() {
+        let t = tweak("web_search", "Disable Web Search", "Stops Bing results");
+        let applied: HashSet<String> = ["web_search".to_string()].into();
+        assert!(passes_filters(
+            &t,
+            &TweakSearchFilters::default(),
+            &HashSet::new()
+        ));
+
+        let filters = TweakSearchFilters {
+            risk_levels: vec![RiskLevel::Low, RiskLevel::Medium],
+            elevations: vec![ChangeElevation::Admin],
+            applied: Some(true),
+            ..Default::default()
+        };
+        assert!(passes_filters(&t, &filters, &applied));
+        assert!(!passes_filters(&t, &filters, &HashSet::new()));
+
+        let filters = TweakSearchFilters {
+            category_ids: vec!["privacy".to_string()],
+            ..Default::default()
+        };
+        assert!(!passes_filters(&t, &filters, &applied));
+    }
+}
@@ -0,0 +1,34 @@
+//! Stats Commands - Local, opt-in usage statistics of tweaks
+
+use crate::error::Result;
+use crate::services::backup_service;
+use crate::services::usage_stats_service::{self, TweakStatsReport};
+use std::path::Path;
+
+/// How often each tweak was applied, reverted, and failed, and whether counting is on
+#[tauri::command]
+pub fn get_tweak_stats() -> Result<TweakStatsReport> {
+    usage_stats_service::get_stats()
+}
+
+/// Turn counting applies and reverts on or off (off until the user opts in)
+#[tauri::command]
+pub fn set_usage_stats_enabled(enabled: bool) -> Result<()> {
+    log::info!("Command: set_usage_stats_enabled({})", enabled);
+    backup_service::set_usage_stats_enabled(enabled)
+}
+
+/// Forget every count
+#[tauri::command]
+pub fn reset_tweak_stats() -> Result<()> {
+    log::info!("Command: reset_tweak_stats");
+    usage_stats_service::reset_stats()
+}
+
+/// Write the counts of the built-in tweaks, without times or error messages, to `path` for the
+/// user to share. Returns the number of tweaks exported.
+#[tauri::command]
+pub fn export_tweak_stats(path: String) -> Result<usize> {
+    log::info!("Command: export_tweak_stats({})", path);
+    usage_stats_service::export_stats(Path::new(&path))
+}
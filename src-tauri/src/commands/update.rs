@@ -1,5 +1,7 @@
-//! Update commands for checking and installing app updates from GitHub Releases
+//! Update commands for checking and installing app updates from GitHub Releases, and tweak packs
+//! from the channel the user configures (see `tweak_pack_service`)
 
+use crate::services::tweak_pack_service::{self, PackManifest, TweakPackSettings, TweakPackUpdate};
 use crate::Error;
 use serde::{Deserialize, Serialize};
 use std::io::Read;
@@ -300,6 +302,32 @@ pub fn install_update(download_url: String, asset_name: String) -> Result<(), Er
     }
 }
 
+/// Get the tweak pack channel's URL and public key
+#[tauri::command]
+pub fn get_tweak_pack_settings() -> Result<TweakPackSettings, Error> {
+    tweak_pack_service::get_settings()
+}
+
+/// Set the tweak pack channel's URL (https) and Ed25519 public key (hex). Returns what was saved.
+#[tauri::command]
+pub fn set_tweak_pack_settings(settings: TweakPackSettings) -> Result<TweakPackSettings, Error> {
+    log::info!("Setting the tweak pack channel to {:?}", settings.url);
+    tweak_pack_service::set_settings(settings)
+}
+
+/// Check the tweak pack channel for a pack newer than the one installed or staged
+#[tauri::command]
+pub fn check_for_tweak_updates() -> Result<TweakPackUpdate, Error> {
+    tweak_pack_service::check_for_updates()
+}
+
+/// Download the channel's tweak pack, verify its signature and hashes, validate its tweaks, and
+/// stage it. It takes effect at the next launch.
+#[tauri::command]
+pub fn install_tweak_pack() -> Result<PackManifest, Error> {
+    tweak_pack_service::install()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
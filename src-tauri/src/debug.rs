@@ -6,7 +6,8 @@ use tauri::{AppHandle, Emitter};
 /// Global debug mode flag
 static DEBUG_ENABLED: AtomicBool = AtomicBool::new(false);
 
-/// The handle used to emit debug events to the frontend, set once during setup.
+/// The handle used to emit debug events (and other backend events, see [`emit_event`]) to the
+/// frontend, set once during setup.
 ///
 /// This is held here rather than threaded through the apply chain as a parameter.
 /// It was previously passed down through 11 signatures and ~13 call sites purely to
@@ -78,6 +79,20 @@ pub fn emit_debug_log(level: DebugLevel, message: &str, context: Option<&str>) {
     let _ = app.emit("debug-log", entry);
 }
 
+/// The registered handle, for state the apply chain records into (see `metrics`). None in tests.
+pub(crate) fn app_handle() -> Option<&'static AppHandle> {
+    DEBUG_APP.get()
+}
+
+/// Send an event to the frontend regardless of debug mode (e.g. progress of long-running changes).
+///
+/// Like [`emit_debug_log`], a silent no-op when no handle has been registered.
+pub fn emit_event<S: Serialize + Clone>(event: &str, payload: S) {
+    if let Some(app) = DEBUG_APP.get() {
+        let _ = app.emit(event, payload);
+    }
+}
+
 /// Convenience macros for debug logging
 #[macro_export]
 macro_rules! debug_info {
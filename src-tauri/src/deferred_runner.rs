@@ -0,0 +1,110 @@
+//! Running deferred applies (see `deferred_service`) once they're due.
+//!
+//! The runner checks the queue at launch and then every `CHECK_INTERVAL`, so a next-boot apply
+//! runs as soon as the app starts after the reboot and a timed one within `CHECK_INTERVAL` of its
+//! time. Each due apply runs like `apply_tweak`: as a tweak operation, recorded in the journal,
+//! and refused while the tweak waits for a reboot. Its risk was acknowledged when it was queued.
+//! An apply that needs administrator rights waits for a launch that has them rather than failing.
+//! The outcome is recorded on the entry and reported with `tweak://deferred-apply`.
+
+use crate::commands::tweaks::apply::apply_option;
+use crate::debug::emit_event;
+use crate::error::Error;
+use crate::services::deferred_service::{self, DeferredApply};
+use crate::services::journal_service::{OperationKind, PendingOperation};
+use crate::services::{backup_service, shell_service};
+use crate::state::AppState;
+use std::collections::HashSet;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+/// Event carrying a [`DeferredApply`] that just ran, with its outcome
+pub const DEFERRED_APPLY_EVENT: &str = "tweak://deferred-apply";
+
+/// How often the queue is checked for applies that came due
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Run the due applies until the app exits. Spawn it with `tauri::async_runtime::spawn` once
+/// `AppState` is managed.
+pub async fn run(app: AppHandle) {
+    let _ = tauri::async_runtime::spawn_blocking(deferred_service::sync_startup_task).await;
+    // Applies already reported as waiting for administrator rights
+    let mut waiting = HashSet::new();
+    loop {
+        let handle = app.clone();
+        match tauri::async_runtime::spawn_blocking(move || {
+            let mut waiting = waiting;
+            run_due(&handle, &mut waiting);
+            waiting
+        })
+        .await
+        {
+            Ok(next) => waiting = next,
+            Err(e) => {
+                log::warn!("Deferred apply task failed: {}", e);
+                waiting = HashSet::new();
+            }
+        }
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
+
+fn run_due(app: &AppHandle, waiting: &mut HashSet<String>) {
+    let due = match deferred_service::due() {
+        Ok(due) => due,
+        Err(e) => {
+            log::warn!("Failed to read the deferred apply queue: {}", e);
+            return;
+        }
+    };
+    for entry in due {
+        let (success, message) = match apply(app, &entry) {
+            Ok(message) => (true, message),
+            Err(Error::RequiresAdmin) => {
+                if waiting.insert(entry.id.clone()) {
+                    log::info!(
+                        "Deferred apply of '{}' waits for the app to run as administrator",
+                        entry.tweak_id
+                    );
+                }
+                continue;
+            }
+            Err(e) => (false, e.to_string()),
+        };
+        log::info!(
+            "Deferred apply of '{}' option {}: {}",
+            entry.tweak_id,
+            entry.option_index,
+            message
+        );
+        match deferred_service::finish(&entry.id, success, message) {
+            Ok(Some(finished)) => emit_event(DEFERRED_APPLY_EVENT, finished),
+            Ok(None) => {}
+            Err(e) => log::warn!("Failed to record deferred apply {}: {}", entry.id, e),
+        }
+    }
+}
+
+/// Apply a due entry as a tweak operation. Returns the apply's message.
+fn apply(app: &AppHandle, entry: &DeferredApply) -> Result<String, Error> {
+    let state = app.state::<AppState>();
+    let _operation = state.snapshot_monitor.operation();
+    let _stale = state.status_cache.changing([entry.tweak_id.clone()]);
+    backup_service::ensure_no_pending_reboot(&entry.tweak_id)?;
+
+    let mut journal = PendingOperation::new(OperationKind::Apply);
+    let outcome = journal.track(&entry.tweak_id, || {
+        apply_option(entry.tweak_id.clone(), entry.option_index)
+    });
+    journal.record();
+    let (result, restart_explorer) = outcome?;
+    if !result.success {
+        return Err(Error::CommandExecution(result.message));
+    }
+    if restart_explorer {
+        if let Err(e) = shell_service::restart_explorer() {
+            log::warn!("Failed to restart explorer.exe: {}", e);
+        }
+    }
+    Ok(result.message)
+}
@@ -38,6 +38,18 @@ pub enum Error {
 
     #[error("Validation failed: {0}")]
     ValidationError(String),
+
+    #[error("Reboot pending: {0}")]
+    RebootPending(String),
+
+    #[error("Timed out: {0}")]
+    Timeout(String),
+
+    #[error("Cancelled: {0}")]
+    Cancelled(String),
+
+    #[error("Risk not acknowledged: {0}")]
+    RiskNotAcknowledged(String),
 }
 
 impl Error {
@@ -57,6 +69,10 @@ impl Error {
             Error::CommandExecution(_) => "COMMAND_EXECUTION_FAILED",
             Error::NotFound(_) => "NOT_FOUND",
             Error::ValidationError(_) => "VALIDATION_FAILED",
+            Error::RebootPending(_) => "REBOOT_PENDING",
+            Error::Timeout(_) => "TIMEOUT",
+            Error::Cancelled(_) => "CANCELLED",
+            Error::RiskNotAcknowledged(_) => "RISK_NOT_ACKNOWLEDGED",
         }
     }
 }
@@ -1,9 +1,18 @@
+mod change_watcher;
 mod commands;
 pub mod debug;
+mod deferred_runner;
 mod error;
+mod metrics;
 mod models;
+mod operations;
+mod risk_acks;
 mod services;
 mod setup;
+mod snapshot_monitor;
+mod state;
+mod status_cache;
+mod system_info_cache;
 mod window_watchdog;
 
 /// Generated tweak data compiled from YAML files at build time.
@@ -17,23 +26,31 @@ pub use error::Error;
 pub use models::*;
 use tauri_plugin_log::{Target, TargetKind};
 
-/// If this process was launched as `--broker <request-file> <response-file>`, run the elevated
-/// effect broker and return its exit code; returns `None` for a normal launch (start the GUI).
+/// If this process was launched as `--broker <request-file> <response-file>` or
+/// `--broker-session <pipe>`, run the elevated effect broker and return its exit code; returns
+/// `None` for a normal launch (start the GUI).
 ///
-/// The main app spawns the app binary with this flag under a SYSTEM/TrustedInstaller token; the
-/// child executes typed operations against the effect services and writes a typed response. No
-/// shell is involved. Must be checked before any GUI/Tauri initialization.
+/// The main app spawns the app binary with one of these flags under a SYSTEM/TrustedInstaller
+/// token; the child executes typed operations against the effect services and sends back typed
+/// responses (one request per process, or every request of a session over the pipe). No shell is
+/// involved. Must be checked before any GUI/Tauri initialization.
 pub fn run_broker_if_requested() -> Option<i32> {
     let args: Vec<String> = std::env::args().collect();
+    if let Some(pos) = args.iter().position(|a| a == "--broker-session") {
+        let pipe = args.get(pos + 1)?;
+        services::elevation::kill_children_on_exit();
+        return Some(services::elevation::run_broker_session(pipe));
+    }
     let pos = args.iter().position(|a| a == "--broker")?;
     let req = args.get(pos + 1)?;
     let resp = args.get(pos + 2)?;
+    services::elevation::kill_children_on_exit();
     Some(services::elevation::run_broker(req, resp))
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    tauri::Builder::default()
+    let app = tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_prevent_default::debug())
         .plugin(tauri_plugin_dialog::init())
@@ -87,6 +104,7 @@ pub fn run() {
                 })
                 .build(),
         )
+        .manage(state::AppState::default())
         .setup(|app| {
             log::info!("Application starting...");
             log::debug!("Debug logging enabled");
@@ -106,35 +124,120 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             commands::general::show_main_window,
             commands::system::get_system_info,
+            commands::system::refresh_system_info,
+            commands::system::get_thermal_info,
+            commands::system::start_monitoring,
+            commands::system::stop_monitoring,
+            commands::system::get_installed_software,
+            commands::system::get_startup_items,
+            commands::system::set_startup_item_enabled,
+            commands::system::get_startup_changes,
+            commands::system::undo_startup_change,
+            commands::system::get_update_status,
+            commands::system::create_restore_point,
+            commands::system::list_restore_points,
+            commands::system::list_user_profiles,
+            commands::system::get_target_user,
+            commands::system::set_target_user,
             // Tweak query commands
             commands::tweaks::query::get_categories,
             commands::tweaks::query::get_available_tweaks,
+            commands::tweaks::query::search_tweaks,
+            commands::tweaks::query::get_tweaks_by_tag,
+            commands::tweaks::query::get_builtin_profiles,
+            commands::tweaks::query::get_custom_tweaks,
+            commands::tweaks::query::get_tweak_schema,
+            commands::tweaks::editor::validate_tweak_draft,
+            commands::tweaks::editor::save_custom_tweak,
             commands::tweaks::query::get_tweak_status,
             commands::tweaks::query::get_all_tweak_statuses,
+            commands::tweaks::query::refresh_tweak_statuses,
             commands::tweaks::query::get_tweak_inspection,
+            commands::tweaks::query::get_tweak_status_detail,
             // Tweak apply commands
             commands::tweaks::apply::apply_tweak,
+            commands::tweaks::apply::cancel_operation,
+            commands::tweaks::apply::acknowledge_risk,
             commands::tweaks::apply::revert_tweak,
             commands::tweaks::apply::keep_current_state,
+            commands::tweaks::apply::restore_snapshot_generation,
+            commands::tweaks::apply::get_incomplete_applies,
+            commands::tweaks::apply::roll_back_incomplete_apply,
+            commands::tweaks::apply::dismiss_incomplete_apply,
             // Tweak batch commands
             commands::tweaks::batch::batch_apply_tweaks,
             commands::tweaks::batch::batch_revert_tweaks,
+            commands::tweaks::batch::reapply_applied_tweaks,
+            // Deferred apply commands
+            commands::tweaks::deferred::schedule_apply,
+            commands::tweaks::deferred::get_deferred_applies,
+            commands::tweaks::deferred::get_deferred_apply,
+            commands::tweaks::deferred::cancel_deferred_apply,
+            commands::tweaks::journal::get_operation_history,
+            commands::tweaks::journal::undo_last_operation,
+            commands::tweaks::notes::get_tweak_notes,
+            commands::tweaks::notes::set_tweak_note,
+            commands::tweaks::stats::get_tweak_stats,
+            commands::tweaks::stats::set_usage_stats_enabled,
+            commands::tweaks::stats::reset_tweak_stats,
+            commands::tweaks::stats::export_tweak_stats,
             commands::debug::set_debug_mode,
+            commands::debug::get_performance_metrics,
+            commands::debug::get_process_privileges,
             // Backup commands
             commands::backup::has_backup,
             commands::backup::list_backups,
             commands::backup::get_backup_info,
+            commands::backup::export_snapshot_as_reg,
+            commands::backup::export_applied_tweaks_as_script,
+            commands::backup::export_backup_archive,
+            commands::backup::import_backup_archive,
+            commands::backup::get_snapshot_location,
+            commands::backup::set_snapshot_location,
+            commands::backup::list_snapshot_generations,
+            commands::backup::get_history_retention,
+            commands::backup::set_history_retention,
+            commands::backup::get_backup_storage_stats,
+            commands::backup::cleanup_old_backups,
+            commands::backup::restore_all_to_baseline,
             commands::backup::validate_snapshots,
+            commands::backup::verify_snapshots,
+            commands::backup::get_snapshot_encryption,
+            commands::backup::set_snapshot_encryption,
+            commands::backup::get_auto_reapply,
+            commands::backup::set_auto_reapply,
+            commands::backup::get_baseline_info,
+            commands::backup::capture_baseline,
+            commands::backup::restore_key_to_baseline,
+            commands::backup::diff_snapshot,
+            commands::backup::restore_changes,
+            commands::backup::get_pending_reboot_tweaks,
             // Elevation commands
             commands::elevation::can_use_system_elevation,
+            commands::elevation::cancel_elevated_operations,
+            commands::elevation::warm_up_elevation,
+            commands::elevation::get_elevation_audit_log,
             commands::elevation::restart_as_admin,
+            commands::elevation::relaunch_elevated,
+            commands::elevation::take_resumed_operations,
             // Update commands
             commands::update::check_for_update,
             commands::update::install_update,
+            commands::update::get_tweak_pack_settings,
+            commands::update::set_tweak_pack_settings,
+            commands::update::check_for_tweak_updates,
+            commands::update::install_tweak_pack,
         ])
-        .run(tauri::generate_context!())
+        .build(tauri::generate_context!())
         .unwrap_or_else(|e| {
             log::error!("Failed to run Tauri application: {:?}", e);
             std::process::exit(1);
         });
+
+    app.run(|_app, event| {
+        if let tauri::RunEvent::Exit = event {
+            // A user can't sign in while their hive is loaded by the app
+            services::user_profile_service::release_target_user();
+        }
+    });
 }
@@ -2,8 +2,8 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
-    // If launched as the elevated effect broker (`--broker <req> <resp>`), run it and exit before
-    // any GUI initialization.
+    // If launched as the elevated effect broker (`--broker <req> <resp>` or `--broker-session
+    // <pipe>`), run it and exit before any GUI initialization.
     if let Some(code) = app_lib::run_broker_if_requested() {
         std::process::exit(code);
     }
@@ -0,0 +1,212 @@
+//! Timing of tweak operations.
+//!
+//! Applying or reverting a tweak runs up to a dozen phases, several of them through an elevated
+//! helper that can take seconds to start. To find the slow ones, `apply_option` and
+//! `revert_option` time each phase (snapshot capture, registry, services, scheduled tasks,
+//! commands, ...) and the last `CAPACITY` operations are kept in `AppState` for
+//! `get_performance_metrics`. With debug mode on, each operation's timings also go to the debug log.
+//!
+//! Like cancellation (see `operations`), the operation being timed is kept per thread, so the
+//! phases deep in the apply helpers can record themselves without every call passing it down.
+
+use crate::debug::{emit_debug_log, is_debug_enabled, DebugLevel};
+use crate::state::AppState;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Instant;
+use tauri::Manager;
+
+/// Operations kept
+pub const CAPACITY: usize = 100;
+
+thread_local! {
+    /// Phases recorded so far by the operation being timed on this thread, if one is
+    static CURRENT: RefCell<Option<Vec<PhaseTiming>>> = const { RefCell::new(None) };
+}
+
+/// What was timed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimedOperation {
+    Apply,
+    Revert,
+}
+
+/// How long one phase of an operation took
+#[derive(Debug, Clone, Serialize)]
+pub struct PhaseTiming {
+    pub phase: &'static str,
+    pub duration_ms: f64,
+}
+
+/// One timed apply or revert
+#[derive(Debug, Clone, Serialize)]
+pub struct OperationTiming {
+    pub tweak_id: String,
+    pub operation: TimedOperation,
+    /// When it started (RFC 3339)
+    pub started_at: String,
+    pub total_ms: f64,
+    pub success: bool,
+    /// In the order they ran
+    pub phases: Vec<PhaseTiming>,
+}
+
+/// The metrics' part of `AppState`: the last `CAPACITY` operations, oldest first
+#[derive(Debug, Default)]
+pub struct PerformanceMetrics {
+    recent: Mutex<VecDeque<OperationTiming>>,
+}
+
+impl PerformanceMetrics {
+    fn lock(&self) -> MutexGuard<'_, VecDeque<OperationTiming>> {
+        self.recent.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn record(&self, timing: OperationTiming) {
+        let mut recent = self.lock();
+        if recent.len() == CAPACITY {
+            recent.pop_front();
+        }
+        recent.push_back(timing);
+    }
+
+    /// The operations kept, oldest first
+    pub fn recent(&self) -> Vec<OperationTiming> {
+        self.lock().iter().cloned().collect()
+    }
+}
+
+/// Times an operation on this thread until [`finish`](Self::finish)ed
+pub struct Timer {
+    tweak_id: String,
+    operation: TimedOperation,
+    started: Instant,
+    started_at: String,
+    /// Phases of an operation this one runs inside, if any
+    outer: Option<Vec<PhaseTiming>>,
+}
+
+/// Start timing `operation` of `tweak_id` on this thread
+pub fn start(tweak_id: &str, operation: TimedOperation) -> Timer {
+    Timer {
+        tweak_id: tweak_id.to_string(),
+        operation,
+        started: Instant::now(),
+        started_at: chrono::Local::now().to_rfc3339(),
+        outer: CURRENT.with(|current| current.borrow_mut().replace(Vec::new())),
+    }
+}
+
+impl Timer {
+    /// Stop timing and keep the result (see the module docs)
+    pub fn finish(mut self, success: bool) {
+        let timing = self.take(success);
+        if is_debug_enabled() {
+            let phases: Vec<String> = timing
+                .phases
+                .iter()
+                .map(|phase| format!("{} {:.0} ms", phase.phase, phase.duration_ms))
+                .collect();
+            emit_debug_log(
+                DebugLevel::Info,
+                &format!(
+                    "{:?} '{}' took {:.0} ms",
+                    timing.operation, timing.tweak_id, timing.total_ms
+                ),
+                Some(&phases.join(", ")),
+            );
+        }
+        if let Some(state) = crate::debug::app_handle().and_then(|app| app.try_state::<AppState>())
+        {
+            state.metrics.record(timing);
+        }
+    }
+
+    fn take(&mut self, success: bool) -> OperationTiming {
+        let phases = CURRENT
+            .with(|current| std::mem::replace(&mut *current.borrow_mut(), self.outer.take()));
+        OperationTiming {
+            tweak_id: std::mem::take(&mut self.tweak_id),
+            operation: self.operation,
+            started_at: std::mem::take(&mut self.started_at),
+            total_ms: millis(self.started),
+            success,
+            phases: phases.unwrap_or_default(),
+        }
+    }
+}
+
+fn millis(since: Instant) -> f64 {
+    since.elapsed().as_secs_f64() * 1000.0
+}
+
+/// Times phase `name` of the operation timed on this thread until dropped. Without one it records
+/// nothing.
+pub struct Phase {
+    name: &'static str,
+    started: Instant,
+}
+
+impl Drop for Phase {
+    fn drop(&mut self) {
+        CURRENT.with(|current| {
+            if let Some(phases) = current.borrow_mut().as_mut() {
+                phases.push(PhaseTiming {
+                    phase: self.name,
+                    duration_ms: millis(self.started),
+                });
+            }
+        });
+    }
+}
+
+/// Start timing phase `name`; it ends when the returned guard drops
+pub fn timed(name: &'static str) -> Phase {
+    Phase {
+        name,
+        started: Instant::now(),
+    }
+}
+
+/// Run `f` as phase `name` of the operation timed on this thread
+pub fn phase<T>(name: &'static str, f: impl FnOnce() -> T) -> T {
+    let _phase = timed(name);
+    f()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phases_go_to_the_innermost_operation() {
+        let mut outer = start("outer", TimedOperation::Apply);
+        phase("registry", || ());
+        let mut inner = start("inner", TimedOperation::Revert);
+        phase("services", || ());
+        let inner = inner.take(true);
+        phase("commands", || ());
+        let outer = outer.take(false);
+
+        let names =
+            |timing: &OperationTiming| timing.phases.iter().map(|p| p.phase).collect::<Vec<_>>();
+        assert_eq!(names(&inner), ["services"]);
+        assert_eq!(names(&outer), ["registry", "commands"]);
+        assert_eq!(phase("untimed", || 7), 7);
+        assert!(CURRENT.with(|current| current.borrow().is_none()));
+    }
+
+    #[test]
+    fn only_the_last_operations_are_kept() {
+        let metrics = PerformanceMetrics::default();
+        for n in 0..CAPACITY + 5 {
+            metrics.record(start(&n.to_string(), TimedOperation::Apply).take(true));
+        }
+        let recent = metrics.recent();
+        assert_eq!(recent.len(), CAPACITY);
+        assert_eq!(recent[0].tweak_id, "5");
+    }
+}
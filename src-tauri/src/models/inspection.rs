@@ -55,6 +55,124 @@ pub struct FirewallMismatch {
     pub skip_validation: bool,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PowerPlanMismatch {
+    pub action: String,
+    pub scheme: String,
+    pub subgroup: Option<String>,
+    pub setting: Option<String>,
+    pub expected_value: Option<serde_json::Value>,
+    pub actual_value: Option<serde_json::Value>,
+    pub description: String,
+    pub is_match: bool,
+    pub skip_validation: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BcdeditMismatch {
+    pub identifier: String,
+    pub element: String,
+    pub expected_value: Option<String>,
+    pub actual_value: Option<String>,
+    pub description: String,
+    pub is_match: bool,
+    pub skip_validation: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppxMismatch {
+    pub package: String,
+    pub action: String,
+    pub scope: String,
+    pub expected_present: bool,
+    pub actual_present: bool,
+    pub description: String,
+    pub is_match: bool,
+    pub skip_validation: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileMismatch {
+    pub path: String,
+    pub action: String,
+    pub destination: Option<String>,
+    /// State the option expects, e.g. `absent` or `written`
+    pub expected_state: String,
+    /// State found on disk, in the same vocabulary as `expected_state`
+    pub actual_state: String,
+    pub description: String,
+    pub is_match: bool,
+    pub skip_validation: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistryAclMismatch {
+    pub hive: String,
+    pub key: String,
+    pub action: String,
+    pub trustee: String,
+    /// State the option expects, e.g. `owner` or `full_control`
+    pub expected_state: String,
+    /// State found on the key, in the same vocabulary as `expected_state`
+    pub actual_state: String,
+    pub description: String,
+    pub is_match: bool,
+    pub skip_validation: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeatureMismatch {
+    pub feature: String,
+    pub action: String,
+    pub expected_enabled: bool,
+    /// None if the feature isn't available on this Windows edition
+    pub actual_enabled: Option<bool>,
+    pub description: String,
+    pub is_match: bool,
+    pub skip_validation: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetAdapterMismatch {
+    /// Adapter name, or the change's adapter pattern when no adapter has the property
+    pub adapter: String,
+    pub keyword: String,
+    pub expected_value: String,
+    /// None if the driver default is in effect or no adapter has the property
+    pub actual_value: Option<String>,
+    pub description: String,
+    pub is_match: bool,
+    pub skip_validation: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyMismatch {
+    pub scope: String,
+    pub key: String,
+    pub value_name: String,
+    pub action: String,
+    pub expected_value: Option<serde_json::Value>,
+    /// None if the value doesn't exist
+    pub actual_value: Option<serde_json::Value>,
+    pub value_type: Option<String>,
+    /// Whether Registry.pol holds the expected record (None if the change doesn't write it)
+    pub pol_match: Option<bool>,
+    pub description: String,
+    pub is_match: bool,
+    pub skip_validation: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppMismatch {
+    pub id: String,
+    pub action: String,
+    pub expected_installed: bool,
+    pub actual_installed: bool,
+    pub description: String,
+    pub is_match: bool,
+    pub skip_validation: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OptionInspection {
     pub option_index: usize,
@@ -68,7 +186,31 @@ pub struct OptionInspection {
     pub hosts_results: Vec<HostsMismatch>,
     #[serde(default)]
     pub firewall_results: Vec<FirewallMismatch>,
+    #[serde(default)]
+    pub power_plan_results: Vec<PowerPlanMismatch>,
+    #[serde(default)]
+    pub bcdedit_results: Vec<BcdeditMismatch>,
+    #[serde(default)]
+    pub appx_results: Vec<AppxMismatch>,
+    #[serde(default)]
+    pub file_results: Vec<FileMismatch>,
+    #[serde(default)]
+    pub registry_acl_results: Vec<RegistryAclMismatch>,
+    #[serde(default)]
+    pub feature_results: Vec<FeatureMismatch>,
+    #[serde(default)]
+    pub netadapter_results: Vec<NetAdapterMismatch>,
+    #[serde(default)]
+    pub policy_results: Vec<PolicyMismatch>,
+    #[serde(default)]
+    pub app_results: Vec<AppMismatch>,
     pub all_match: bool,
+    /// Validatable (non-`skip_validation`) changes that match the current state
+    #[serde(default)]
+    pub matched_count: usize,
+    /// Validatable changes that don't
+    #[serde(default)]
+    pub mismatched_count: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -78,3 +220,14 @@ pub struct TweakInspection {
     /// Index of the option that fully matches, if any
     pub matched_option_index: Option<usize>,
 }
+
+/// Why a tweak has the status it has: the status itself and every option's per-change comparison
+/// with the current state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TweakStatusDetail {
+    pub status: crate::models::TweakStatus,
+    pub inspection: TweakInspection,
+    /// When no option matches (a custom state), the one with the fewest mismatched changes, i.e. the
+    /// option the current state is closest to. None when an option matches or none can be checked.
+    pub closest_option_index: Option<usize>,
+}
@@ -1,8 +1,12 @@
 pub mod inspection;
 pub mod system;
 pub mod tweak;
+pub mod tweak_components;
+pub mod tweak_fragments;
 pub mod tweak_schema;
 pub mod tweak_snapshot;
+pub mod tweak_template;
+pub mod tweak_validation;
 
 pub use inspection::*;
 pub use system::*;
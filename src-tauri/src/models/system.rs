@@ -1,3 +1,4 @@
+use super::{WindowsBuild, WindowsEdition};
 use serde::{Deserialize, Serialize};
 
 /// Windows version information
@@ -118,6 +119,43 @@ pub struct NetworkInfo {
     pub ip_address: String,
     /// DHCP Enabled
     pub dhcp_enabled: bool,
+    /// What the adapter connects over
+    #[serde(default)]
+    pub medium: NetworkMedium,
+    /// Link speed in Mbps (the faster of receive and transmit), None while it has no link
+    #[serde(default)]
+    pub link_speed_mbps: Option<u64>,
+    /// DNS servers in use (set by hand or by DHCP), IPv4 first, in the order they're tried
+    #[serde(default)]
+    pub dns_servers: Vec<String>,
+    /// The network a connected Wi-Fi adapter is on
+    #[serde(default)]
+    pub wifi: Option<WifiInfo>,
+}
+
+/// What a network adapter connects over (its NDIS physical medium)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum NetworkMedium {
+    Ethernet,
+    Wifi,
+    /// Mobile broadband
+    Cellular,
+    Bluetooth,
+    /// Anything else the driver reports (e.g. a modem or a fibre link)
+    Other,
+    /// The driver doesn't say (most virtual adapters)
+    #[default]
+    Unknown,
+}
+
+/// A Wi-Fi connection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WifiInfo {
+    /// Network name
+    pub ssid: String,
+    /// Signal quality, 0-100
+    pub signal_percent: u32,
 }
 
 /// Disk/Storage information
@@ -160,6 +198,105 @@ pub struct HardwareInfo {
     pub total_storage_gb: f64,
 }
 
+/// A part of the hardware info gathered on its own (see `refresh_system_info`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HardwareSection {
+    Cpu,
+    Gpu,
+    Monitors,
+    Memory,
+    Motherboard,
+    Disks,
+    Network,
+    /// The device (manufacturer, model, form factor)
+    Device,
+}
+
+impl HardwareSection {
+    pub const ALL: [HardwareSection; 8] = [
+        HardwareSection::Cpu,
+        HardwareSection::Gpu,
+        HardwareSection::Monitors,
+        HardwareSection::Memory,
+        HardwareSection::Motherboard,
+        HardwareSection::Disks,
+        HardwareSection::Network,
+        HardwareSection::Device,
+    ];
+}
+
+/// Battery and power plan status
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct PowerInfo {
+    /// Whether the machine has a system battery
+    pub has_battery: bool,
+    /// Whether it's running on AC power, or None if Windows can't tell
+    pub on_ac_power: Option<bool>,
+    /// Whether the battery is charging
+    pub charging: bool,
+    /// Battery charge (0-100), or None without a battery or if it's unknown
+    pub charge_percent: Option<u8>,
+    /// Capacity the batteries were designed for, in mWh
+    pub design_capacity_mwh: Option<u32>,
+    /// Capacity the batteries hold when fully charged now, in mWh
+    pub full_charge_capacity_mwh: Option<u32>,
+    /// Full charge capacity as a percentage of the design capacity (battery wear)
+    pub health_percent: Option<f64>,
+    /// GUID of the active power plan
+    pub active_plan_guid: Option<String>,
+    /// Name of the active power plan (e.g. "Balanced")
+    pub active_plan_name: Option<String>,
+}
+
+/// Where temperature and fan readings come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ThermalSource {
+    /// The WMI provider of a running LibreHardwareMonitor
+    LibreHardwareMonitor,
+    /// The WMI provider of a running OpenHardwareMonitor
+    OpenHardwareMonitor,
+    /// ACPI thermal zones (MSAcpi_ThermalZoneTemperature); no fans, and no GPU
+    Acpi,
+    /// Nothing could be read
+    #[default]
+    None,
+}
+
+/// What a sensor measures
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SensorKind {
+    /// In °C
+    Temperature,
+    /// In RPM
+    Fan,
+}
+
+/// One temperature or fan sensor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThermalSensor {
+    /// Hardware the sensor is on (e.g. "AMD Ryzen 7 5800X", or the ACPI zone)
+    pub hardware: String,
+    /// Sensor name (e.g. "CPU Package", "Fan #1")
+    pub name: String,
+    pub kind: SensorKind,
+    pub value: f64,
+}
+
+/// Temperatures and fan speeds
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ThermalInfo {
+    pub source: ThermalSource,
+    /// CPU temperature in °C (the package sensor, or the hottest core); from ACPI, the hottest
+    /// thermal zone
+    pub cpu_celsius: Option<f64>,
+    /// GPU temperature in °C (the hottest GPU core)
+    pub gpu_celsius: Option<f64>,
+    pub sensors: Vec<ThermalSensor>,
+}
+
 /// System information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SystemInfo {
@@ -170,4 +307,108 @@ pub struct SystemInfo {
     pub hardware: HardwareInfo,
     /// Device information (manufacturer, model)
     pub device: DeviceInfo,
+    /// Battery and power plan status
+    #[serde(default)]
+    pub power: PowerInfo,
+    /// Whether Windows is waiting for a reboot (servicing, updates, or queued file renames)
+    #[serde(default)]
+    pub reboot_pending: bool,
+}
+
+/// A System Restore point (an instance of the `SystemRestore` WMI class)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestorePoint {
+    /// Sequence number Windows uses to identify the point
+    pub sequence_number: u32,
+    pub description: String,
+    /// Creation time (ISO 8601, UTC)
+    pub created_at: String,
+    /// `RestorePointType` (e.g. 12 = settings modified, 0 = application install)
+    pub restore_point_type: u32,
+}
+
+/// Hardware, edition, and build facts that tweak `conditions` (and the `${gpu_vendor}` template) use
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SystemFacts {
+    /// Whether the machine reports itself as a laptop (PCSystemType = Mobile)
+    pub is_laptop: bool,
+    /// Whether the machine has a system battery
+    pub has_battery: bool,
+    /// Whether any physical disk is an SSD (including NVMe)
+    pub has_ssd: bool,
+    /// Edition family, or None if the EditionID isn't recognized
+    pub edition: Option<WindowsEdition>,
+    /// Installed physical memory in GB
+    pub ram_gb: f64,
+    /// Build and cumulative update revision, or build 0 if they can't be read
+    pub build: WindowsBuild,
+    /// Primary GPU vendor: "nvidia", "amd", "intel", or "unknown"
+    pub gpu_vendor: String,
+}
+
+/// The app process's token, for diagnosing elevation failures from the UI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessPrivileges {
+    /// How UAC issued the token
+    pub elevation_type: TokenElevationType,
+    /// Whether the token is elevated (running as administrator)
+    pub is_elevated: bool,
+    /// Mandatory integrity level
+    pub integrity_level: IntegrityLevel,
+    /// Every privilege the token holds, enabled or not
+    pub privileges: Vec<TokenPrivilege>,
+    /// Privileges SYSTEM / TrustedInstaller elevation needs that the token doesn't hold, such as
+    /// SeDebugPrivilege removed by group policy
+    pub missing_for_elevation: Vec<String>,
+}
+
+/// How UAC issued a token (`TOKEN_ELEVATION_TYPE`)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenElevationType {
+    /// No split token: UAC is off, or the account isn't an administrator's
+    Default,
+    /// The elevated half of an administrator's split token
+    Full,
+    /// The filtered half of an administrator's split token (not elevated)
+    Limited,
+}
+
+/// Mandatory integrity level of a token, from its integrity SID's RID
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IntegrityLevel {
+    Untrusted,
+    Low,
+    Medium,
+    MediumPlus,
+    High,
+    System,
+    Protected,
+}
+
+impl IntegrityLevel {
+    /// The level an integrity RID (`SECURITY_MANDATORY_*_RID`) falls in
+    pub fn from_rid(rid: u32) -> Self {
+        match rid {
+            0..0x1000 => IntegrityLevel::Untrusted,
+            0x1000..0x2000 => IntegrityLevel::Low,
+            0x2000..0x2100 => IntegrityLevel::Medium,
+            0x2100..0x3000 => IntegrityLevel::MediumPlus,
+            0x3000..0x4000 => IntegrityLevel::High,
+            0x4000..0x5000 => IntegrityLevel::System,
+            _ => IntegrityLevel::Protected,
+        }
+    }
+}
+
+/// A privilege held by a token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenPrivilege {
+    /// Privilege name (e.g. "SeDebugPrivilege")
+    pub name: String,
+    /// Whether it's enabled now
+    pub enabled: bool,
+    /// Whether it's enabled when the token is created
+    pub enabled_by_default: bool,
 }
@@ -11,6 +11,7 @@
 //! the runtime-only impls for those types, plus the runtime-only status/result types
 //! (`TweakState`, `TweakResult`, `TweakStatus`) that build.rs never sees.
 
+use crate::models::SystemFacts;
 use serde::{Deserialize, Serialize};
 
 // Re-export the shared schema types so they remain reachable as `models::tweak::*` (and, via
@@ -38,8 +39,16 @@ impl RegistryHive {
         match self {
             RegistryHive::Hkcu => "HKCU",
             RegistryHive::Hklm => "HKLM",
+            RegistryHive::Hkcr => "HKCR",
+            RegistryHive::Hku => "HKU",
+            RegistryHive::AllUsers => "ALL_USERS",
         }
     }
+
+    /// Whether writing under this hive needs administrator privileges (all but the user's own)
+    pub fn requires_admin(&self) -> bool {
+        !matches!(self, RegistryHive::Hkcu)
+    }
 }
 
 impl RegistryValueType {
@@ -111,6 +120,38 @@ impl SchedulerAction {
     }
 }
 
+impl ChangeElevation {
+    /// The level a change declaring this runs at
+    pub fn level(self) -> crate::services::elevation::Elevation {
+        use crate::services::elevation::Elevation;
+        match self {
+            ChangeElevation::User | ChangeElevation::Admin => Elevation::None,
+            ChangeElevation::System => Elevation::System,
+            ChangeElevation::Ti => Elevation::TrustedInstaller,
+        }
+    }
+
+    /// The level a change runs at: its own `elevation` if it declares one, the tweak's otherwise
+    pub fn resolve(
+        declared: Option<ChangeElevation>,
+        tweak: crate::services::elevation::Elevation,
+    ) -> crate::services::elevation::Elevation {
+        declared.map_or(tweak, ChangeElevation::level)
+    }
+}
+
+impl CommandShell {
+    /// The PowerShell that scripts run in alongside commands in this shell: PowerShell 7 for
+    /// `pwsh`, Windows PowerShell otherwise
+    pub fn powershell_engine(self) -> crate::services::elevation::PowerShellEngine {
+        use crate::services::elevation::PowerShellEngine;
+        match self {
+            CommandShell::Pwsh => PowerShellEngine::Core,
+            CommandShell::Cmd | CommandShell::Powershell => PowerShellEngine::Windows,
+        }
+    }
+}
+
 impl RegistryChange {
     /// Check if this registry change applies to a given Windows version
     pub fn applies_to_version(&self, version: u32) -> bool {
@@ -120,6 +161,57 @@ impl RegistryChange {
             Some(versions) => versions.contains(&version),
         }
     }
+
+    /// Check if this registry change applies to a given Windows version and machine
+    pub fn applies_to(&self, version: u32, facts: &SystemFacts) -> bool {
+        self.applies_to_version(version)
+            && self
+                .conditions
+                .as_ref()
+                .is_none_or(|conditions| conditions.matches(facts))
+    }
+}
+
+impl Conditions {
+    /// Whether every guard that is set holds for this machine. An edition guard never holds when
+    /// the edition is unknown.
+    pub fn matches(&self, facts: &SystemFacts) -> bool {
+        self.is_laptop
+            .is_none_or(|laptop| laptop == facts.is_laptop)
+            && self
+                .has_battery
+                .is_none_or(|battery| battery == facts.has_battery)
+            && self.has_ssd.is_none_or(|ssd| ssd == facts.has_ssd)
+            && edition_matches(self.edition.as_deref(), facts)
+            && self
+                .min_ram_gb
+                .is_none_or(|min| facts.ram_gb >= f64::from(min))
+            && facts.build.within(self.min_build, self.max_build)
+    }
+}
+
+/// Whether this machine's edition is one of `editions` (always, if there's no list)
+fn edition_matches(editions: Option<&[WindowsEdition]>, facts: &SystemFacts) -> bool {
+    editions.is_none_or(|editions| {
+        facts
+            .edition
+            .is_some_and(|edition| editions.contains(&edition))
+    })
+}
+
+impl WindowsBuild {
+    /// Whether this (the machine's) build is within `min..=max`. A bound without a revision
+    /// compares builds only, so it covers every cumulative update of its build.
+    pub fn within(self, min: Option<WindowsBuild>, max: Option<WindowsBuild>) -> bool {
+        let compared_to = |bound: WindowsBuild| {
+            (
+                self.build,
+                bound.revision.map(|_| self.revision.unwrap_or(0)),
+            )
+        };
+        min.is_none_or(|min| compared_to(min) >= (min.build, min.revision))
+            && max.is_none_or(|max| compared_to(max) <= (max.build, max.revision))
+    }
 }
 
 impl HostsAction {
@@ -170,17 +262,841 @@ impl FirewallOperation {
     }
 }
 
+impl PowerPlanAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PowerPlanAction::SetActiveScheme => "set_active_scheme",
+            PowerPlanAction::SetSettingValue => "set_setting_value",
+        }
+    }
+}
+
+impl BcdeditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BcdeditAction::Set => "set",
+            BcdeditAction::DeleteValue => "delete_value",
+        }
+    }
+}
+
+impl AppxAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AppxAction::Remove => "remove",
+            AppxAction::RemoveProvisioned => "remove_provisioned",
+            AppxAction::Reinstall => "reinstall",
+        }
+    }
+}
+
+impl AppxScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AppxScope::CurrentUser => "current_user",
+            AppxScope::AllUsers => "all_users",
+        }
+    }
+}
+
+impl FileAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileAction::Copy => "copy",
+            FileAction::Delete => "delete",
+            FileAction::Rename => "rename",
+            FileAction::SetAttributes => "set_attributes",
+            FileAction::WriteContent => "write_content",
+        }
+    }
+}
+
+impl RegistryAclAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RegistryAclAction::TakeOwnership => "take_ownership",
+            RegistryAclAction::Grant => "grant",
+            RegistryAclAction::Revoke => "revoke",
+        }
+    }
+}
+
+impl RegistryAccess {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RegistryAccess::FullControl => "full_control",
+            RegistryAccess::Read => "read",
+        }
+    }
+}
+
+impl FeatureAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FeatureAction::Enable => "enable",
+            FeatureAction::Disable => "disable",
+        }
+    }
+}
+
+impl PolicyScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PolicyScope::Machine => "machine",
+            PolicyScope::User => "user",
+        }
+    }
+
+    /// Hive the scope's policy values live in
+    pub fn hive(&self) -> RegistryHive {
+        match self {
+            PolicyScope::Machine => RegistryHive::Hklm,
+            PolicyScope::User => RegistryHive::Hkcu,
+        }
+    }
+}
+
+impl PolicyAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PolicyAction::Set => "set",
+            PolicyAction::DeleteValue => "delete_value",
+        }
+    }
+}
+
+impl PolicyChange {
+    /// The plain registry change this policy writes, so capture, restore, and detection of the
+    /// value itself go through the registry paths.
+    pub fn to_registry_change(&self) -> RegistryChange {
+        RegistryChange {
+            hive: self.scope.hive(),
+            key: self.key.clone(),
+            value_name: self.value_name.clone(),
+            action: match self.action {
+                PolicyAction::Set => RegistryAction::Set,
+                PolicyAction::DeleteValue => RegistryAction::DeleteValue,
+            },
+            value_type: self.value_type,
+            value: self.value.clone(),
+            windows_versions: None,
+            conditions: None,
+            requires_reboot: false,
+            requires_logoff: false,
+            skip_validation: self.skip_validation,
+            elevation: None,
+        }
+    }
+}
+
+impl Browser {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Browser::Edge => "edge",
+            Browser::Chrome => "chrome",
+            Browser::Firefox => "firefox",
+        }
+    }
+
+    /// Key the browser reads its policies from (under the policy scope's hive)
+    pub fn policy_key(&self) -> &'static str {
+        match self {
+            Browser::Edge => "SOFTWARE\\Policies\\Microsoft\\Edge",
+            Browser::Chrome => "SOFTWARE\\Policies\\Google\\Chrome",
+            Browser::Firefox => "SOFTWARE\\Policies\\Mozilla\\Firefox",
+        }
+    }
+}
+
+impl BrowserPolicyChange {
+    /// The Group Policy values this policy maps to, so apply, capture, restore, and detection go
+    /// through the policy paths. Booleans and integers are DWORDs and strings REG_SZ under the
+    /// browser's key; a list is a subkey named after the policy with one REG_SZ per item ("1",
+    /// "2", ...); an object is a subkey with a value (or list subkey) per field.
+    pub fn to_policy_changes(&self) -> Vec<PolicyChange> {
+        let key = self.browser.policy_key().to_string();
+        let mut changes = Vec::new();
+        match self.action {
+            PolicyAction::Set => {
+                if let Some(value) = &self.value {
+                    self.push_values(&mut changes, &key, &self.policy, value);
+                }
+            }
+            PolicyAction::DeleteValue => changes.push(PolicyChange {
+                scope: self.scope,
+                key,
+                value_name: self.policy.clone(),
+                action: PolicyAction::DeleteValue,
+                value_type: None,
+                value: None,
+                write_pol: false,
+                skip_validation: self.skip_validation,
+            }),
+        }
+        changes
+    }
+
+    fn push_values(
+        &self,
+        changes: &mut Vec<PolicyChange>,
+        key: &str,
+        name: &str,
+        value: &serde_json::Value,
+    ) {
+        let set = |key: &str, value_name: &str, value_type, value| PolicyChange {
+            scope: self.scope,
+            key: key.to_string(),
+            value_name: value_name.to_string(),
+            action: PolicyAction::Set,
+            value_type: Some(value_type),
+            value: Some(value),
+            write_pol: false,
+            skip_validation: self.skip_validation,
+        };
+
+        match value {
+            serde_json::Value::Bool(enabled) => changes.push(set(
+                key,
+                name,
+                RegistryValueType::Dword,
+                serde_json::json!(u32::from(*enabled)),
+            )),
+            serde_json::Value::Number(_) => {
+                changes.push(set(key, name, RegistryValueType::Dword, value.clone()))
+            }
+            serde_json::Value::String(_) => {
+                changes.push(set(key, name, RegistryValueType::String, value.clone()))
+            }
+            serde_json::Value::Array(items) => {
+                let list_key = format!("{}\\{}", key, name);
+                for (index, item) in items.iter().enumerate() {
+                    let item = match item {
+                        serde_json::Value::String(text) => text.clone(),
+                        other => other.to_string(),
+                    };
+                    changes.push(set(
+                        &list_key,
+                        &(index + 1).to_string(),
+                        RegistryValueType::String,
+                        serde_json::json!(item),
+                    ));
+                }
+            }
+            serde_json::Value::Object(fields) => {
+                let object_key = format!("{}\\{}", key, name);
+                for (field, value) in fields {
+                    self.push_values(changes, &object_key, field, value);
+                }
+            }
+            serde_json::Value::Null => {}
+        }
+    }
+}
+
+impl MmcssSchedulingCategory {
+    /// Registry spelling of the category ("Scheduling Category" value)
+    pub fn as_registry_str(&self) -> &'static str {
+        match self {
+            MmcssSchedulingCategory::Low => "Low",
+            MmcssSchedulingCategory::Medium => "Medium",
+            MmcssSchedulingCategory::High => "High",
+        }
+    }
+}
+
+impl MmcssIoPriority {
+    /// Registry spelling of the priority ("SFIO Priority" value)
+    pub fn as_registry_str(&self) -> &'static str {
+        match self {
+            MmcssIoPriority::Idle => "Idle",
+            MmcssIoPriority::Low => "Low",
+            MmcssIoPriority::Normal => "Normal",
+            MmcssIoPriority::High => "High",
+        }
+    }
+}
+
+/// Parent key of the MMCSS task keys (under HKLM)
+pub const MMCSS_TASKS_KEY: &str =
+    "SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion\\Multimedia\\SystemProfile\\Tasks";
+
+impl MmcssChange {
+    /// Registry key of this task (under HKLM)
+    pub fn key(&self) -> String {
+        format!("{}\\{}", MMCSS_TASKS_KEY, self.task)
+    }
+
+    /// The registry values this change writes, one per field that is set, so capture, restore,
+    /// and detection go through the registry paths.
+    pub fn to_registry_changes(&self) -> Vec<RegistryChange> {
+        let dword = |value_name: &str, value: Option<u32>| {
+            value.map(|v| (value_name, RegistryValueType::Dword, serde_json::json!(v)))
+        };
+        let string = |value_name: &str, value: Option<&str>| {
+            value.map(|v| (value_name, RegistryValueType::String, serde_json::json!(v)))
+        };
+        let values = [
+            dword("Priority", self.priority),
+            dword("GPU Priority", self.gpu_priority),
+            string(
+                "Scheduling Category",
+                self.scheduling_category
+                    .map(|category| category.as_registry_str()),
+            ),
+            string(
+                "SFIO Priority",
+                self.sfio_priority
+                    .map(|priority| priority.as_registry_str()),
+            ),
+            string(
+                "Background Only",
+                self.background_only
+                    .map(|background| if background { "True" } else { "False" }),
+            ),
+            dword("Clock Rate", self.clock_rate),
+            dword("Affinity", self.affinity),
+        ];
+
+        let key = self.key();
+        values
+            .into_iter()
+            .flatten()
+            .map(|(value_name, value_type, value)| RegistryChange {
+                hive: RegistryHive::Hklm,
+                key: key.clone(),
+                value_name: value_name.to_string(),
+                action: RegistryAction::Set,
+                value_type: Some(value_type),
+                value: Some(value),
+                windows_versions: None,
+                conditions: None,
+                requires_reboot: false,
+                requires_logoff: false,
+                skip_validation: self.skip_validation,
+                elevation: None,
+            })
+            .collect()
+    }
+}
+
+impl ContextMenuScope {
+    /// Hive the entry is registered in. Context menu changes never go through the HKCR merge
+    /// view, where a write lands in HKCU or HKLM depending on which one already has the key.
+    pub fn hive(&self) -> RegistryHive {
+        match self {
+            ContextMenuScope::User => RegistryHive::Hkcu,
+            ContextMenuScope::Machine => RegistryHive::Hklm,
+        }
+    }
+}
+
+/// CLSID whose empty `InprocServer32` override brings back the classic context menu on Windows 11
+pub const CLASSIC_CONTEXT_MENU_CLSID: &str = "{86ca1aa0-34aa-4e8b-a509-50c905bae2a2}";
+
+impl ContextMenuChange {
+    /// Registry key of the entry (under [`ContextMenuScope::hive`])
+    pub fn key(&self) -> String {
+        match self.kind {
+            ContextMenuKind::Verb => {
+                format!("SOFTWARE\\Classes\\{}\\shell\\{}", self.target, self.name)
+            }
+            ContextMenuKind::Handler => format!(
+                "SOFTWARE\\Classes\\{}\\shellex\\ContextMenuHandlers\\{}",
+                self.target, self.name
+            ),
+            ContextMenuKind::ClassicMenu => {
+                format!("SOFTWARE\\Classes\\CLSID\\{}", CLASSIC_CONTEXT_MENU_CLSID)
+            }
+        }
+    }
+
+    /// The registry changes this entry maps to, so capture, restore, and detection go through the
+    /// registry paths. An add creates the entry key first, so revert removes the whole key when it
+    /// didn't exist; a remove deletes the key, and revert recreates it with its values.
+    pub fn to_registry_changes(&self) -> Vec<RegistryChange> {
+        let hive = self.scope.hive();
+        let key = self.key();
+        // The classic menu override only exists on Windows 11
+        let windows_versions = (self.kind == ContextMenuKind::ClassicMenu).then(|| vec![11]);
+        let change =
+            |key: String, action: RegistryAction, value_name: &str, value: Option<&str>| {
+                RegistryChange {
+                    hive,
+                    key,
+                    value_name: value_name.to_string(),
+                    action,
+                    value_type: value.map(|_| RegistryValueType::String),
+                    value: value.map(|v| serde_json::json!(v)),
+                    windows_versions: windows_versions.clone(),
+                    conditions: None,
+                    requires_reboot: false,
+                    requires_logoff: false,
+                    skip_validation: self.skip_validation,
+                    elevation: None,
+                }
+            };
+        let set = |key: &str, value_name: &str, value: &str| {
+            change(
+                key.to_string(),
+                RegistryAction::Set,
+                value_name,
+                Some(value),
+            )
+        };
+
+        if self.action == ContextMenuAction::Remove {
+            return vec![change(key, RegistryAction::DeleteKey, "", None)];
+        }
+
+        let mut changes = vec![change(key.clone(), RegistryAction::CreateKey, "", None)];
+        match self.kind {
+            ContextMenuKind::Verb => {
+                if let Some(label) = &self.label {
+                    changes.push(set(&key, "", label));
+                }
+                if let Some(icon) = &self.icon {
+                    changes.push(set(&key, "Icon", icon));
+                }
+                if self.extended {
+                    changes.push(set(&key, "Extended", ""));
+                }
+                if let Some(command) = &self.command {
+                    changes.push(set(&format!("{}\\command", key), "", command));
+                }
+            }
+            ContextMenuKind::Handler => {
+                if let Some(clsid) = &self.clsid {
+                    changes.push(set(&key, "", clsid));
+                }
+            }
+            ContextMenuKind::ClassicMenu => {
+                changes.push(set(&format!("{}\\InprocServer32", key), "", ""));
+            }
+        }
+        changes
+    }
+}
+
+/// Windows Time service parameters key (under HKLM): `NtpServer` and `Type`
+pub const W32TIME_PARAMETERS_KEY: &str = "SYSTEM\\CurrentControlSet\\Services\\W32Time\\Parameters";
+/// Windows Time NTP client key (under HKLM): `SpecialPollInterval`
+pub const W32TIME_NTP_CLIENT_KEY: &str =
+    "SYSTEM\\CurrentControlSet\\Services\\W32Time\\TimeProviders\\NtpClient";
+
+/// NTP server flags w32tm writes by default: `0x1` (poll every `SpecialPollInterval` seconds)
+/// and `0x8` (client mode)
+const NTP_SERVER_FLAGS: &str = "0x9";
+
+impl TimeSyncType {
+    /// Registry spelling of the sync type (`Type` value)
+    pub fn as_registry_str(&self) -> &'static str {
+        match self {
+            TimeSyncType::Ntp => "NTP",
+            TimeSyncType::Nt5ds => "NT5DS",
+            TimeSyncType::AllSync => "AllSync",
+            TimeSyncType::NoSync => "NoSync",
+        }
+    }
+}
+
+impl TimeChange {
+    /// `NtpServer` value for the configured servers: space-separated, each with
+    /// [`NTP_SERVER_FLAGS`] unless it already names its own flags ("host,0x8")
+    pub fn ntp_server_value(&self) -> Option<String> {
+        self.ntp_servers.as_ref().map(|servers| {
+            servers
+                .iter()
+                .map(|server| {
+                    if server.contains(',') {
+                        server.clone()
+                    } else {
+                        format!("{},{}", server, NTP_SERVER_FLAGS)
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+    }
+
+    /// The registry values this change writes, one per field that is set, so capture, restore,
+    /// and detection go through the registry paths. The service is told to reload them by
+    /// `time_service::update_config`.
+    pub fn to_registry_changes(&self) -> Vec<RegistryChange> {
+        let values = [
+            self.ntp_server_value().map(|servers| {
+                (
+                    W32TIME_PARAMETERS_KEY,
+                    "NtpServer",
+                    RegistryValueType::String,
+                    serde_json::json!(servers),
+                )
+            }),
+            self.sync_type.map(|sync_type| {
+                (
+                    W32TIME_PARAMETERS_KEY,
+                    "Type",
+                    RegistryValueType::String,
+                    serde_json::json!(sync_type.as_registry_str()),
+                )
+            }),
+            self.sync_interval.map(|seconds| {
+                (
+                    W32TIME_NTP_CLIENT_KEY,
+                    "SpecialPollInterval",
+                    RegistryValueType::Dword,
+                    serde_json::json!(seconds),
+                )
+            }),
+        ];
+
+        values
+            .into_iter()
+            .flatten()
+            .map(|(key, value_name, value_type, value)| RegistryChange {
+                hive: RegistryHive::Hklm,
+                key: key.to_string(),
+                value_name: value_name.to_string(),
+                action: RegistryAction::Set,
+                value_type: Some(value_type),
+                value: Some(value),
+                windows_versions: None,
+                conditions: None,
+                requires_reboot: false,
+                requires_logoff: false,
+                skip_validation: self.skip_validation,
+                elevation: None,
+            })
+            .collect()
+    }
+}
+
+impl ComponentChange {
+    /// The service changes this change maps to, in the order they're applied. Disabling stops
+    /// dependents first: the services of dependent components, then the component's own services
+    /// in reverse start order. Enabling restores the default startup types in start order and
+    /// starts the automatic ones.
+    pub fn to_service_changes(&self) -> Vec<ServiceChange> {
+        let change = |name: &str, startup: ServiceStartupType, start_service: bool| ServiceChange {
+            name: name.to_string(),
+            startup,
+            stop_service: false,
+            start_service,
+            skip_validation: self.skip_validation,
+            elevation: None,
+        };
+
+        if self.enabled {
+            return self
+                .component
+                .services()
+                .iter()
+                .map(|(name, startup)| {
+                    change(name, *startup, *startup == ServiceStartupType::Automatic)
+                })
+                .collect();
+        }
+
+        self.component
+            .dependents()
+            .iter()
+            .chain(std::iter::once(&self.component))
+            .flat_map(|component| component.services().iter().rev())
+            .map(|(name, _)| change(name, ServiceStartupType::Disabled, false))
+            .collect()
+    }
+}
+
+impl StateProbes {
+    /// The registry probes as changes for capture only: `delete_value` for a value and
+    /// `delete_key` for a key, whose snapshots keep everything revert needs to put them back.
+    /// Never applied.
+    pub fn to_registry_changes(&self) -> Vec<RegistryChange> {
+        self.registry
+            .iter()
+            .map(|probe| RegistryChange {
+                hive: probe.hive,
+                key: probe.key.clone(),
+                value_name: probe.value_name.clone().unwrap_or_default(),
+                action: if probe.value_name.is_some() {
+                    RegistryAction::DeleteValue
+                } else {
+                    RegistryAction::DeleteKey
+                },
+                value_type: None,
+                value: None,
+                windows_versions: None,
+                conditions: None,
+                requires_reboot: false,
+                requires_logoff: false,
+                skip_validation: true,
+                elevation: None,
+            })
+            .collect()
+    }
+
+    /// The file probes as `delete` changes for capture only, whose snapshots back up the content.
+    /// Never applied.
+    pub fn to_file_changes(&self) -> Vec<FileChange> {
+        self.files
+            .iter()
+            .map(|path| FileChange {
+                path: path.clone(),
+                action: FileAction::Delete,
+                destination: None,
+                attributes: None,
+                content: None,
+                skip_backup: false,
+                skip_validation: true,
+            })
+            .collect()
+    }
+}
+
+/// Key (under HKCU) of `UserPreferencesMask`
+pub const USER_PREFERENCES_MASK_KEY: &str = "Control Panel\\Desktop";
+/// REG_BINARY value holding one bit per Performance Options effect
+pub const USER_PREFERENCES_MASK_VALUE: &str = "UserPreferencesMask";
+
+const EXPLORER_ADVANCED_KEY: &str =
+    "Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\Advanced";
+
+impl VisualEffect {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VisualEffect::MenuAnimation => "menu_animation",
+            VisualEffect::ComboBoxAnimation => "combo_box_animation",
+            VisualEffect::SmoothScrolling => "smooth_scrolling",
+            VisualEffect::SelectionFade => "selection_fade",
+            VisualEffect::TooltipAnimation => "tooltip_animation",
+            VisualEffect::CursorShadow => "cursor_shadow",
+            VisualEffect::WindowShadows => "window_shadows",
+            VisualEffect::ControlAnimations => "control_animations",
+            VisualEffect::WindowAnimations => "window_animations",
+            VisualEffect::TaskbarAnimations => "taskbar_animations",
+            VisualEffect::IconShadows => "icon_shadows",
+            VisualEffect::Transparency => "transparency",
+            VisualEffect::FontSmoothing => "font_smoothing",
+        }
+    }
+
+    /// Byte index and bit of this effect in `UserPreferencesMask`, or `None` for effects stored in
+    /// a registry value of their own
+    pub fn mask_bit(&self) -> Option<(usize, u8)> {
+        match self {
+            VisualEffect::MenuAnimation => Some((0, 0x02)),
+            VisualEffect::ComboBoxAnimation => Some((0, 0x04)),
+            VisualEffect::SmoothScrolling => Some((0, 0x08)),
+            VisualEffect::SelectionFade => Some((1, 0x04)),
+            VisualEffect::TooltipAnimation => Some((1, 0x08)),
+            VisualEffect::CursorShadow => Some((1, 0x20)),
+            VisualEffect::WindowShadows => Some((2, 0x04)),
+            VisualEffect::ControlAnimations => Some((4, 0x02)),
+            _ => None,
+        }
+    }
+
+    /// Whether Windows only picks the effect up at sign-in. Transparency applies right away.
+    pub fn requires_logoff(&self) -> bool {
+        *self != VisualEffect::Transparency
+    }
+}
+
+impl VisualEffectsChange {
+    /// The registry value an effect stored outside `UserPreferencesMask` maps to. Mask effects
+    /// depend on the current mask, so `visual_effects_service` combines them into one mask write.
+    pub fn to_registry_change(&self) -> Option<RegistryChange> {
+        let dword = serde_json::json!(u32::from(self.enabled));
+        let (key, value_name, value_type, value) = match self.effect {
+            VisualEffect::WindowAnimations => (
+                "Control Panel\\Desktop\\WindowMetrics",
+                "MinAnimate",
+                RegistryValueType::String,
+                serde_json::json!(if self.enabled { "1" } else { "0" }),
+            ),
+            VisualEffect::TaskbarAnimations => (
+                EXPLORER_ADVANCED_KEY,
+                "TaskbarAnimations",
+                RegistryValueType::Dword,
+                dword,
+            ),
+            VisualEffect::IconShadows => (
+                EXPLORER_ADVANCED_KEY,
+                "ListviewShadow",
+                RegistryValueType::Dword,
+                dword,
+            ),
+            VisualEffect::Transparency => (
+                "Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize",
+                "EnableTransparency",
+                RegistryValueType::Dword,
+                dword,
+            ),
+            VisualEffect::FontSmoothing => (
+                USER_PREFERENCES_MASK_KEY,
+                "FontSmoothing",
+                RegistryValueType::String,
+                serde_json::json!(if self.enabled { "2" } else { "0" }),
+            ),
+            _ => return None,
+        };
+
+        Some(RegistryChange {
+            hive: RegistryHive::Hkcu,
+            key: key.to_string(),
+            value_name: value_name.to_string(),
+            action: RegistryAction::Set,
+            value_type: Some(value_type),
+            value: Some(value),
+            windows_versions: None,
+            conditions: None,
+            requires_reboot: false,
+            requires_logoff: false,
+            skip_validation: self.skip_validation,
+            elevation: None,
+        })
+    }
+}
+
+impl AppAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AppAction::Install => "install",
+            AppAction::Uninstall => "uninstall",
+        }
+    }
+}
+
 impl TweakOption {
+    /// The option's registry changes followed by those its MMCSS, context menu, visual effects, and
+    /// time changes map to. Audio changes and `UserPreferencesMask` effects depend on the machine, so
+    /// they're added by `registry_service::option_registry_changes`.
+    pub fn all_registry_changes(&self) -> Vec<RegistryChange> {
+        self.registry_changes
+            .iter()
+            .cloned()
+            .chain(
+                self.mmcss_changes
+                    .iter()
+                    .flat_map(MmcssChange::to_registry_changes),
+            )
+            .chain(
+                self.context_menu_changes
+                    .iter()
+                    .flat_map(ContextMenuChange::to_registry_changes),
+            )
+            .chain(
+                self.visual_effects_changes
+                    .iter()
+                    .filter_map(VisualEffectsChange::to_registry_change),
+            )
+            .chain(
+                self.time_changes
+                    .iter()
+                    .flat_map(TimeChange::to_registry_changes),
+            )
+            .collect()
+    }
+
+    /// The option's service changes followed by those its component changes map to
+    pub fn all_service_changes(&self) -> Vec<ServiceChange> {
+        self.service_changes
+            .iter()
+            .cloned()
+            .chain(
+                self.component_changes
+                    .iter()
+                    .flat_map(ComponentChange::to_service_changes),
+            )
+            .collect()
+    }
+
+    /// Whether `service_name` belongs to a component this option changes. A component's service
+    /// that isn't installed is skipped on apply, and counts as disabled when detecting status.
+    pub fn is_component_service(&self, service_name: &str) -> bool {
+        self.component_changes.iter().any(|change| {
+            change.component.has_service(service_name)
+                || (!change.enabled
+                    && change
+                        .component
+                        .dependents()
+                        .iter()
+                        .any(|dependent| dependent.has_service(service_name)))
+        })
+    }
+
+    /// The option's policy changes followed by those its browser policy changes map to
+    pub fn all_policy_changes(&self) -> Vec<PolicyChange> {
+        self.policy_changes
+            .iter()
+            .cloned()
+            .chain(
+                self.browser_policy_changes
+                    .iter()
+                    .flat_map(BrowserPolicyChange::to_policy_changes),
+            )
+            .collect()
+    }
+
+    /// Whether applying or reverting this option needs a reboot: the option's own flag or that of
+    /// any registry change that applies on this machine
+    pub fn requires_reboot(&self, version: u32, facts: &SystemFacts) -> bool {
+        self.requires_reboot
+            || self
+                .registry_changes
+                .iter()
+                .any(|change| change.requires_reboot && change.applies_to(version, facts))
+    }
+
+    /// Whether applying or reverting this option needs a sign-out (see [`Self::requires_reboot`]);
+    /// also true for visual effects Windows only reads at sign-in
+    pub fn requires_logoff(&self, version: u32, facts: &SystemFacts) -> bool {
+        self.requires_logoff
+            || self
+                .registry_changes
+                .iter()
+                .any(|change| change.requires_logoff && change.applies_to(version, facts))
+            || self
+                .visual_effects_changes
+                .iter()
+                .any(|change| change.effect.requires_logoff())
+    }
+
+    /// Check if this option can be applied on this machine (its conditions, if any, hold)
+    pub fn is_available(&self, facts: &SystemFacts) -> bool {
+        self.conditions
+            .as_ref()
+            .is_none_or(|conditions| conditions.matches(facts))
+    }
+
     /// Check if this option has any effective changes for the given Windows version
     pub fn has_changes_for_version(&self, version: u32) -> bool {
         let has_registry = self
             .registry_changes
             .iter()
             .any(|c| c.applies_to_version(version));
-        let has_services = !self.service_changes.is_empty();
+        let has_services = !self.service_changes.is_empty() || !self.component_changes.is_empty();
         let has_scheduler = !self.scheduler_changes.is_empty();
         let has_hosts = !self.hosts_changes.is_empty();
         let has_firewall = !self.firewall_changes.is_empty();
+        let has_power_plan = !self.power_plan_changes.is_empty();
+        let has_bcdedit = !self.bcdedit_changes.is_empty();
+        let has_appx = !self.appx_changes.is_empty();
+        let has_files = !self.file_changes.is_empty();
+        let has_registry_acls = !self.registry_acl_changes.is_empty();
+        let has_features = !self.feature_changes.is_empty();
+        let has_netadapters = !self.netadapter_changes.is_empty();
+        let has_policies =
+            !self.policy_changes.is_empty() || !self.browser_policy_changes.is_empty();
+        let has_apps = !self.app_changes.is_empty();
+        let has_mmcss = !self.mmcss_changes.is_empty();
+        let has_audio = !self.audio_changes.is_empty();
+        let has_visual_effects = !self.visual_effects_changes.is_empty();
+        let has_time = !self.time_changes.is_empty();
+        let has_context_menu = self
+            .context_menu_changes
+            .iter()
+            .flat_map(ContextMenuChange::to_registry_changes)
+            .any(|c| c.applies_to_version(version));
         let has_commands = !self.pre_commands.is_empty() || !self.post_commands.is_empty();
         let has_powershell = !self.pre_powershell.is_empty() || !self.post_powershell.is_empty();
         has_registry
@@ -188,9 +1104,40 @@ impl TweakOption {
             || has_scheduler
             || has_hosts
             || has_firewall
+            || has_power_plan
+            || has_bcdedit
+            || has_appx
+            || has_files
+            || has_registry_acls
+            || has_features
+            || has_netadapters
+            || has_policies
+            || has_apps
+            || has_mmcss
+            || has_context_menu
+            || has_audio
+            || has_visual_effects
+            || has_time
             || has_commands
             || has_powershell
     }
+
+    /// The interpreter this option's `pre_commands` / `post_commands` run in
+    pub fn command_shell(&self) -> CommandShell {
+        self.shell.unwrap_or_default()
+    }
+
+    /// The PowerShell this option's `pre_powershell` / `post_powershell` scripts run in
+    pub fn powershell_engine(&self) -> crate::services::elevation::PowerShellEngine {
+        self.command_shell().powershell_engine()
+    }
+
+    /// How long each of this option's elevated commands may run (the elevated-process default
+    /// when unset)
+    pub fn command_timeout(&self) -> Option<std::time::Duration> {
+        self.command_timeout_secs
+            .map(|secs| std::time::Duration::from_secs(u64::from(secs)))
+    }
 }
 
 impl TweakDefinition {
@@ -202,10 +1149,65 @@ impl TweakDefinition {
             .any(|opt| opt.has_changes_for_version(version))
     }
 
+    /// Whether this machine's build and edition are ones the tweak is offered on
+    pub fn is_available(&self, facts: &SystemFacts) -> bool {
+        facts.build.within(self.min_build, self.max_build)
+            && edition_matches(self.editions.as_deref(), facts)
+    }
+
+    /// Check if this tweak is offered on a given Windows version and machine
+    pub fn applies_to(&self, version: u32, facts: &SystemFacts) -> bool {
+        self.applies_to_version(version) && self.is_available(facts)
+    }
+
+    /// Indexes of options whose conditions don't hold on this machine
+    pub fn unavailable_options(&self, facts: &SystemFacts) -> Vec<usize> {
+        self.options
+            .iter()
+            .enumerate()
+            .filter(|(_, option)| !option.is_available(facts))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
     /// The privilege level this tweak's operations run at, derived from its declared flags.
     pub fn elevation(&self) -> crate::services::elevation::Elevation {
         crate::services::elevation::Elevation::from_flags(self.requires_system, self.requires_ti)
     }
+
+    /// The privilege this tweak declares, as the UI names it (a change may declare more)
+    pub fn declared_elevation(&self) -> ChangeElevation {
+        if self.requires_ti {
+            ChangeElevation::Ti
+        } else if self.requires_system {
+            ChangeElevation::System
+        } else if self.requires_admin {
+            ChangeElevation::Admin
+        } else {
+            ChangeElevation::User
+        }
+    }
+}
+
+/// Filters of `search_tweaks`. A filter left out (or empty) matches every tweak; a tweak has to
+/// match all of the others.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TweakSearchFilters {
+    /// Any of these risk levels
+    #[serde(default)]
+    pub risk_levels: Vec<RiskLevel>,
+    /// Any of these categories
+    #[serde(default)]
+    pub category_ids: Vec<String>,
+    /// Whether the tweak itself declares it needs a reboot
+    #[serde(default)]
+    pub requires_reboot: Option<bool>,
+    /// Any of these privilege levels, as the tweak declares it (see `declared_elevation`)
+    #[serde(default)]
+    pub elevations: Vec<ChangeElevation>,
+    /// Whether the tweak is applied by the app (has a snapshot)
+    #[serde(default)]
+    pub applied: Option<bool>,
 }
 
 // ============================================================================
@@ -235,13 +1237,64 @@ pub struct TweakResult {
     pub success: bool,
     pub message: String,
     pub requires_reboot: bool,
+    /// Whether a sign-out is needed (only set when no reboot is, since a reboot covers it)
+    #[serde(default)]
+    pub requires_logoff: bool,
+    /// Whether explorer.exe was restarted to pick up the change
+    #[serde(default)]
+    pub restarted_explorer: bool,
     /// List of (tweak_id, error_message) for failed operations in batch mode
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub failures: Vec<(String, String)>,
+    /// Sequence number of the System Restore point created before a batch apply, if one was
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restore_point_sequence: Option<u32>,
+    /// Outcome of each tweak in batch mode, in the order they ran
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub results: Vec<BatchItemResult>,
+    /// What a transactional batch apply undid after one of its tweaks failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rollback: Option<BatchRollbackReport>,
 }
 
-/// Status of a specific tweak (returned to frontend)
+/// Rollback of a transactional batch apply: the tweaks applied before the one that failed are
+/// returned to the state they were in before the batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchRollbackReport {
+    /// Tweak whose failure stopped the batch
+    pub failed_tweak_id: String,
+    /// Tweaks rolled back, in the order they were undone (newest first)
+    pub items: Vec<BatchRollbackItem>,
+    /// Tweaks the batch never reached
+    pub skipped: Vec<String>,
+}
+
+/// Rollback of one tweak of a transactional batch
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchRollbackItem {
+    pub tweak_id: String,
+    /// Whether every value was verifiably restored
+    pub restored: bool,
+    /// Values that could not be restored; the tweak keeps its snapshot so it can still be reverted
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failures: Vec<String>,
+}
+
+/// Outcome of one tweak in a batch apply or revert
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchItemResult {
+    pub tweak_id: String,
+    pub success: bool,
+    pub message: String,
+    pub requires_reboot: bool,
+    /// Code of the error that stopped the tweak (see `Error::code`), e.g. "REBOOT_PENDING"; `None`
+    /// if it succeeded, or failed and was rolled back without one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<String>,
+}
+
+/// Status of a specific tweak (returned to frontend)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TweakStatus {
     pub tweak_id: String,
     /// Whether the tweak has been applied by this app (has snapshot)
@@ -274,6 +1327,9 @@ pub struct TweakStatus {
     /// Resources a partial revert could not restore (empty unless `needs_attention`).
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub unrestorable_resources: Vec<String>,
+    /// Options that can't be applied here because their `conditions` don't hold
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unavailable_options: Vec<usize>,
 }
 
 // ============================================================================
@@ -293,10 +1349,433 @@ mod tests {
             value_type: Some(RegistryValueType::Dword),
             value: Some(serde_json::json!(value)),
             windows_versions,
+            conditions: None,
+            requires_reboot: false,
+            requires_logoff: false,
             skip_validation: false,
+            elevation: None,
         }
     }
 
+    fn desktop_pro() -> SystemFacts {
+        SystemFacts {
+            is_laptop: false,
+            has_battery: false,
+            has_ssd: true,
+            edition: Some(WindowsEdition::Pro),
+            ram_gb: 16.0,
+            build: WindowsBuild {
+                build: 22631,
+                revision: Some(4037),
+            },
+            gpu_vendor: "nvidia".to_string(),
+        }
+    }
+
+    fn build(text: &str) -> WindowsBuild {
+        serde_json::from_value(serde_json::json!(text)).unwrap()
+    }
+
+    #[test]
+    fn test_change_elevation_overrides_the_tweaks() {
+        use crate::services::elevation::Elevation;
+
+        assert_eq!(
+            ChangeElevation::resolve(None, Elevation::TrustedInstaller),
+            Elevation::TrustedInstaller
+        );
+        assert_eq!(
+            ChangeElevation::resolve(Some(ChangeElevation::Admin), Elevation::TrustedInstaller),
+            Elevation::None
+        );
+        assert_eq!(
+            ChangeElevation::resolve(Some(ChangeElevation::Ti), Elevation::None),
+            Elevation::TrustedInstaller
+        );
+
+        let change: ServiceChange = serde_json::from_str(
+            r#"{"name":"WaaSMedicSvc","startup":"disabled","elevation":"ti"}"#,
+        )
+        .unwrap();
+        assert_eq!(change.elevation, Some(ChangeElevation::Ti));
+    }
+
+    #[test]
+    fn test_conditions_match_only_when_every_guard_holds() {
+        let facts = desktop_pro();
+        assert!(Conditions::default().matches(&facts));
+        assert!(Conditions {
+            is_laptop: Some(false),
+            has_battery: Some(false),
+            has_ssd: Some(true),
+            edition: Some(vec![WindowsEdition::Pro, WindowsEdition::Enterprise]),
+            min_ram_gb: Some(16),
+            min_build: Some(build("22631.2428")),
+            max_build: Some(build("22631")),
+        }
+        .matches(&facts));
+        assert!(!Conditions {
+            is_laptop: Some(true),
+            ..Default::default()
+        }
+        .matches(&facts));
+        assert!(!Conditions {
+            has_battery: Some(true),
+            ..Default::default()
+        }
+        .matches(&facts));
+        assert!(!Conditions {
+            min_ram_gb: Some(32),
+            ..Default::default()
+        }
+        .matches(&facts));
+    }
+
+    #[test]
+    fn test_build_ranges_compare_revisions_only_when_the_bound_has_one() {
+        let current = desktop_pro().build;
+        assert!(current.within(Some(build("22631")), Some(build("22631"))));
+        assert!(current.within(Some(build("22631.4037")), None));
+        assert!(!current.within(Some(build("22631.4038")), None));
+        assert!(!current.within(None, Some(build("22631.3000"))));
+        assert!(!current.within(Some(build("26100")), None));
+        assert!(current.within(None, Some(build("26100.1"))));
+
+        let unknown = WindowsBuild::default();
+        assert!(!unknown.within(Some(build("19041")), None));
+        assert_eq!(
+            serde_json::to_value(build("22631.2428")).unwrap(),
+            serde_json::json!("22631.2428")
+        );
+        assert_eq!(
+            serde_json::from_value::<WindowsBuild>(serde_json::json!(19045)).unwrap(),
+            build("19045")
+        );
+        assert!(serde_json::from_value::<WindowsBuild>(serde_json::json!("23H2")).is_err());
+    }
+
+    #[test]
+    fn test_edition_condition_fails_for_unknown_edition() {
+        let facts = SystemFacts {
+            edition: None,
+            ..desktop_pro()
+        };
+        let conditions = Conditions {
+            edition: Some(vec![WindowsEdition::Pro]),
+            ..Default::default()
+        };
+        assert!(!conditions.matches(&facts));
+    }
+
+    #[test]
+    fn test_registry_change_applies_to_checks_version_and_conditions() {
+        let mut change = make_registry_change(1, Some(vec![11]));
+        change.conditions = Some(Conditions {
+            has_ssd: Some(true),
+            ..Default::default()
+        });
+        assert!(change.applies_to(11, &desktop_pro()));
+        assert!(!change.applies_to(10, &desktop_pro()));
+        let hdd_only = SystemFacts {
+            has_ssd: false,
+            ..desktop_pro()
+        };
+        assert!(!change.applies_to(11, &hdd_only));
+    }
+
+    #[test]
+    fn test_change_restart_flags_count_only_where_the_change_applies() {
+        let mut win10_only = make_registry_change(1, Some(vec![10]));
+        win10_only.requires_reboot = true;
+        let mut everywhere = make_registry_change(1, None);
+        everywhere.requires_logoff = true;
+        let option = TweakOption {
+            registry_changes: vec![win10_only, everywhere],
+            ..serde_json::from_value(serde_json::json!({ "label": "On" })).unwrap()
+        };
+
+        assert!(option.requires_reboot(10, &desktop_pro()));
+        assert!(!option.requires_reboot(11, &desktop_pro()));
+        assert!(option.requires_logoff(11, &desktop_pro()));
+
+        let flagged: TweakOption =
+            serde_json::from_value(serde_json::json!({ "label": "On", "requires_reboot": true }))
+                .unwrap();
+        assert!(flagged.requires_reboot(11, &desktop_pro()));
+        assert!(!flagged.requires_logoff(11, &desktop_pro()));
+    }
+
+    #[test]
+    fn test_state_probes_capture_values_keys_and_files_without_applying_them() {
+        let option: TweakOption = serde_json::from_value(serde_json::json!({
+            "label": "On",
+            "post_powershell": ["Set-Something"],
+            "state_probes": {
+                "registry": [
+                    { "hive": "HKLM", "key": "SOFTWARE\\Test", "value_name": "Flag" },
+                    { "hive": "HKCU", "key": "Software\\Test" },
+                ],
+                "files": ["%TEMP%\\test.txt"],
+            },
+        }))
+        .unwrap();
+
+        let registry = option.state_probes.to_registry_changes();
+        assert_eq!(registry[0].action, RegistryAction::DeleteValue);
+        assert_eq!(registry[0].value_name, "Flag");
+        assert_eq!(registry[1].action, RegistryAction::DeleteKey);
+        assert!(registry.iter().all(|change| change.skip_validation));
+
+        let files = option.state_probes.to_file_changes();
+        assert_eq!(files[0].action, FileAction::Delete);
+        assert!(!files[0].skip_backup);
+        assert!(option.state_probes.services.is_empty());
+        assert!(option.all_registry_changes().is_empty());
+    }
+
+    #[test]
+    fn test_mmcss_change_writes_only_the_fields_that_are_set() {
+        let change: MmcssChange = serde_json::from_value(serde_json::json!({
+            "task": "Games",
+            "priority": 6,
+            "gpu_priority": 8,
+            "scheduling_category": "high",
+            "background_only": false,
+        }))
+        .unwrap();
+
+        let values: Vec<_> = change
+            .to_registry_changes()
+            .into_iter()
+            .map(|c| {
+                assert_eq!(c.hive, RegistryHive::Hklm);
+                assert_eq!(c.key, format!("{}\\Games", MMCSS_TASKS_KEY));
+                (c.value_name, c.value_type.unwrap(), c.value.unwrap())
+            })
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                (
+                    "Priority".to_string(),
+                    RegistryValueType::Dword,
+                    serde_json::json!(6)
+                ),
+                (
+                    "GPU Priority".to_string(),
+                    RegistryValueType::Dword,
+                    serde_json::json!(8)
+                ),
+                (
+                    "Scheduling Category".to_string(),
+                    RegistryValueType::String,
+                    serde_json::json!("High")
+                ),
+                (
+                    "Background Only".to_string(),
+                    RegistryValueType::String,
+                    serde_json::json!("False")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_context_menu_changes_map_to_explicit_hives() {
+        let verb: ContextMenuChange = serde_json::from_value(serde_json::json!({
+            "action": "add",
+            "target": "Directory\\Background",
+            "name": "OpenTerminal",
+            "label": "Open Terminal here",
+            "command": "wt.exe -d \"%V\"",
+        }))
+        .unwrap();
+        let changes = verb.to_registry_changes();
+        let key = "SOFTWARE\\Classes\\Directory\\Background\\shell\\OpenTerminal";
+        assert!(changes.iter().all(|c| c.hive == RegistryHive::Hkcu));
+        assert_eq!(changes[0].action, RegistryAction::CreateKey);
+        assert_eq!(changes[0].key, key);
+        assert_eq!(changes[1].key, key);
+        assert_eq!(
+            changes[1].value,
+            Some(serde_json::json!("Open Terminal here"))
+        );
+        assert_eq!(changes[2].key, format!("{}\\command", key));
+        assert_eq!(changes.len(), 3);
+
+        let classic: ContextMenuChange = serde_json::from_value(serde_json::json!({
+            "action": "remove",
+            "kind": "classic_menu",
+        }))
+        .unwrap();
+        let changes = classic.to_registry_changes();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].action, RegistryAction::DeleteKey);
+        assert_eq!(
+            changes[0].key,
+            format!("SOFTWARE\\Classes\\CLSID\\{}", CLASSIC_CONTEXT_MENU_CLSID)
+        );
+        assert!(!changes[0].applies_to_version(10));
+    }
+
+    #[test]
+    fn test_visual_effects_map_to_mask_bits_or_their_own_values() {
+        let option: TweakOption = serde_json::from_value(serde_json::json!({
+            "label": "Best performance",
+            "visual_effects_changes": [
+                { "effect": "menu_animation", "enabled": false },
+                { "effect": "transparency", "enabled": false },
+            ],
+        }))
+        .unwrap();
+
+        // Mask effects aren't registry changes of their own
+        assert_eq!(
+            option.visual_effects_changes[0].effect.mask_bit(),
+            Some((0, 0x02))
+        );
+        assert!(option.visual_effects_changes[0]
+            .to_registry_change()
+            .is_none());
+
+        let changes = option.all_registry_changes();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].hive, RegistryHive::Hkcu);
+        assert_eq!(changes[0].value_name, "EnableTransparency");
+        assert_eq!(changes[0].value, Some(serde_json::json!(0)));
+        assert!(option.requires_logoff(11, &desktop_pro()));
+    }
+
+    #[test]
+    fn test_browser_policies_map_to_policy_values() {
+        let option: TweakOption = serde_json::from_value(serde_json::json!({
+            "label": "On",
+            "browser_policy_changes": [
+                { "browser": "edge", "policy": "ShowRecommendationsEnabled", "value": false },
+                {
+                    "browser": "chrome",
+                    "policy": "ExtensionInstallBlocklist",
+                    "value": ["*"],
+                    "scope": "user",
+                },
+                {
+                    "browser": "firefox",
+                    "policy": "DNSOverHTTPS",
+                    "value": { "Enabled": true, "ProviderURL": "https://dns.example/dns-query" },
+                },
+            ],
+        }))
+        .unwrap();
+
+        let values: Vec<_> = option
+            .all_policy_changes()
+            .into_iter()
+            .map(|c| (c.scope, c.key, c.value_name, c.value.unwrap()))
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                (
+                    PolicyScope::Machine,
+                    "SOFTWARE\\Policies\\Microsoft\\Edge".to_string(),
+                    "ShowRecommendationsEnabled".to_string(),
+                    serde_json::json!(0)
+                ),
+                (
+                    PolicyScope::User,
+                    "SOFTWARE\\Policies\\Google\\Chrome\\ExtensionInstallBlocklist".to_string(),
+                    "1".to_string(),
+                    serde_json::json!("*")
+                ),
+                (
+                    PolicyScope::Machine,
+                    "SOFTWARE\\Policies\\Mozilla\\Firefox\\DNSOverHTTPS".to_string(),
+                    "Enabled".to_string(),
+                    serde_json::json!(1)
+                ),
+                (
+                    PolicyScope::Machine,
+                    "SOFTWARE\\Policies\\Mozilla\\Firefox\\DNSOverHTTPS".to_string(),
+                    "ProviderURL".to_string(),
+                    serde_json::json!("https://dns.example/dns-query")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_time_change_writes_w32time_values() {
+        let change: TimeChange = serde_json::from_value(serde_json::json!({
+            "ntp_servers": ["time.cloudflare.com", "pool.ntp.org,0x8"],
+            "sync_type": "ntp",
+            "sync_interval": 3600,
+        }))
+        .unwrap();
+
+        let values: Vec<_> = change
+            .to_registry_changes()
+            .into_iter()
+            .map(|c| {
+                assert_eq!(c.hive, RegistryHive::Hklm);
+                (c.key, c.value_name, c.value.unwrap())
+            })
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                (
+                    W32TIME_PARAMETERS_KEY.to_string(),
+                    "NtpServer".to_string(),
+                    serde_json::json!("time.cloudflare.com,0x9 pool.ntp.org,0x8")
+                ),
+                (
+                    W32TIME_PARAMETERS_KEY.to_string(),
+                    "Type".to_string(),
+                    serde_json::json!("NTP")
+                ),
+                (
+                    W32TIME_NTP_CLIENT_KEY.to_string(),
+                    "SpecialPollInterval".to_string(),
+                    serde_json::json!(3600)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_component_change_stops_dependents_first() {
+        let disable: ComponentChange = serde_json::from_value(serde_json::json!({
+            "component": "print_spooler",
+            "enabled": false,
+        }))
+        .unwrap();
+        let changes = disable.to_service_changes();
+        assert_eq!(
+            changes.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(),
+            vec!["Fax", "PrintNotify", "Spooler"]
+        );
+        assert!(changes
+            .iter()
+            .all(|c| c.startup == ServiceStartupType::Disabled));
+
+        let enable = ComponentChange {
+            enabled: true,
+            ..disable
+        };
+        let changes: Vec<_> = enable
+            .to_service_changes()
+            .into_iter()
+            .map(|c| (c.name, c.startup, c.start_service))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                ("Spooler".to_string(), ServiceStartupType::Automatic, true),
+                ("PrintNotify".to_string(), ServiceStartupType::Manual, false),
+            ]
+        );
+    }
+
     #[test]
     fn test_registry_change_applies_to_version() {
         let change = make_registry_change(1, None);
@@ -0,0 +1,52 @@
+//! Services making up each optional component of `component_changes`.
+//!
+//! Shared by `build.rs` (which rejects conflicting changes to a component and its services) and
+//! the runtime (which expands a component change into service changes). Like `tweak_template.rs`,
+//! this file must stay free of crate-internal imports other than the schema so build.rs can
+//! include it via `#[path]`.
+
+use super::tweak_schema::{LegacyComponent, ServiceStartupType};
+
+impl LegacyComponent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LegacyComponent::PrintSpooler => "print_spooler",
+            LegacyComponent::Fax => "fax",
+            LegacyComponent::Xbox => "xbox",
+        }
+    }
+
+    /// The component's services in start order (each after the services it depends on), with
+    /// the startup type Windows installs them with
+    pub fn services(&self) -> &'static [(&'static str, ServiceStartupType)] {
+        match self {
+            LegacyComponent::PrintSpooler => &[
+                ("Spooler", ServiceStartupType::Automatic),
+                ("PrintNotify", ServiceStartupType::Manual),
+            ],
+            LegacyComponent::Fax => &[("Fax", ServiceStartupType::Manual)],
+            LegacyComponent::Xbox => &[
+                ("XboxGipSvc", ServiceStartupType::Manual),
+                ("XblAuthManager", ServiceStartupType::Manual),
+                ("XblGameSave", ServiceStartupType::Manual),
+                ("XboxNetApiSvc", ServiceStartupType::Manual),
+            ],
+        }
+    }
+
+    /// Components with a service that depends on one of this component's services. They can't
+    /// run without it, so disabling this component disables them too.
+    pub fn dependents(&self) -> &'static [LegacyComponent] {
+        match self {
+            LegacyComponent::PrintSpooler => &[LegacyComponent::Fax],
+            LegacyComponent::Fax | LegacyComponent::Xbox => &[],
+        }
+    }
+
+    /// Whether `service_name` is one of the component's services
+    pub fn has_service(&self, service_name: &str) -> bool {
+        self.services()
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case(service_name))
+    }
+}
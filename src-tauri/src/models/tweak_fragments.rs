@@ -0,0 +1,232 @@
+//! Change lists shared between built-in tweaks.
+//!
+//! `tweaks/_fragments.yaml` names lists of changes (each a mapping of `*_changes` lists, as an
+//! option has them). An option lists the fragments it uses under `include`, and each fragment's
+//! changes are put before the option's own, in `include` order. The includes are resolved as
+//! the file is parsed, so the generated definitions stay flat and the runtime never sees them.
+//!
+//! Shared by `build.rs` and the debug hot reload; like `tweak_template.rs`, this file must stay
+//! free of crate-internal imports so build.rs can include it via `#[path]`.
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_yaml_bw::{Mapping, Value};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// File in `tweaks/` that holds the fragments; it isn't a tweak file
+pub const FRAGMENTS_FILE: &str = "_fragments.yaml";
+
+/// Key of an option listing the fragments it includes
+const INCLUDE: &str = "include";
+
+/// The fragments tweaks can include. The default has none, so any `include` is an error.
+#[derive(Debug, Default)]
+pub struct Fragments {
+    fragments: BTreeMap<String, Mapping>,
+    /// Fragments included so far, to report the ones no tweak uses
+    used: RefCell<BTreeSet<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FragmentsFile {
+    fragments: BTreeMap<String, Mapping>,
+}
+
+impl Fragments {
+    /// Parse the fragments file. Every key of a fragment must be a list of changes.
+    pub fn from_yaml(content: &str) -> Result<Self, String> {
+        let file: FragmentsFile = serde_yaml_bw::from_str(content).map_err(|e| e.to_string())?;
+        for (name, fragment) in &file.fragments {
+            if fragment.is_empty() {
+                return Err(format!("fragment '{}' has no changes", name));
+            }
+            for (key, changes) in fragment {
+                let key = key.as_str().unwrap_or_default();
+                if !key.ends_with("_changes") || changes.as_sequence().is_none() {
+                    return Err(format!(
+                        "fragment '{}': '{}' isn't a list of changes (e.g. registry_changes)",
+                        name, key
+                    ));
+                }
+            }
+        }
+        Ok(Fragments {
+            fragments: file.fragments,
+            used: RefCell::default(),
+        })
+    }
+
+    /// Parse a tweak file, with the includes of its options resolved
+    pub fn parse<T: DeserializeOwned>(&self, content: &str) -> Result<T, String> {
+        let mut value: Value = serde_yaml_bw::from_str(content).map_err(|e| e.to_string())?;
+        if !self.resolve(&mut value)? {
+            // Parsed from the text again, so errors keep their line numbers
+            return serde_yaml_bw::from_str(content).map_err(|e| e.to_string());
+        }
+        serde_yaml_bw::from_value(value).map_err(|e| e.to_string())
+    }
+
+    /// Names of the fragments no parsed file has included
+    pub fn unused(&self) -> Vec<&str> {
+        let used = self.used.borrow();
+        self.fragments
+            .keys()
+            .filter(|name| !used.contains(*name))
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Resolve the includes of every option of every tweak in a tweak file. Returns whether there
+    /// were any.
+    fn resolve(&self, file: &mut Value) -> Result<bool, String> {
+        let tweaks = file
+            .as_mapping_mut()
+            .and_then(|file| file.get_mut("tweaks"));
+        let Some(tweaks) = tweaks.and_then(Value::as_sequence_mut) else {
+            return Ok(false);
+        };
+        let mut resolved = false;
+        for tweak in tweaks.iter_mut() {
+            let id = tweak
+                .get("id")
+                .and_then(Value::as_str)
+                .unwrap_or("?")
+                .to_string();
+            let options = tweak
+                .as_mapping_mut()
+                .and_then(|tweak| tweak.get_mut("options"));
+            let Some(options) = options.and_then(Value::as_sequence_mut) else {
+                continue;
+            };
+            for option in options.iter_mut().filter_map(Value::as_mapping_mut) {
+                let label = option
+                    .get("label")
+                    .and_then(Value::as_str)
+                    .unwrap_or("?")
+                    .to_string();
+                resolved |= self
+                    .resolve_option(option)
+                    .map_err(|e| format!("{}: option '{}': {}", id, label, e))?;
+            }
+        }
+        Ok(resolved)
+    }
+
+    /// Put the changes of the fragments an option includes before its own
+    fn resolve_option(&self, option: &mut Mapping) -> Result<bool, String> {
+        let Some(include) = option.shift_remove(INCLUDE) else {
+            return Ok(false);
+        };
+        let names: Vec<String> = serde_yaml_bw::from_value(include)
+            .map_err(|_| "'include' must be a list of fragment names".to_string())?;
+
+        let mut included: Vec<(Value, Vec<Value>)> = Vec::new();
+        for (i, name) in names.iter().enumerate() {
+            if names[..i].contains(name) {
+                return Err(format!("includes fragment '{}' twice", name));
+            }
+            let fragment = self.fragments.get(name).ok_or_else(|| {
+                format!(
+                    "includes '{}', which isn't a fragment in {}",
+                    name, FRAGMENTS_FILE
+                )
+            })?;
+            self.used.borrow_mut().insert(name.clone());
+            for (key, changes) in fragment {
+                let changes = changes.as_sequence().map(|s| s.elements.clone());
+                match included.iter_mut().find(|(k, _)| k == key) {
+                    Some((_, list)) => list.extend(changes.unwrap_or_default()),
+                    None => included.push((key.clone(), changes.unwrap_or_default())),
+                }
+            }
+        }
+
+        for (key, mut changes) in included {
+            if let Some(own) = option.get(&key) {
+                let own = own.as_sequence().ok_or_else(|| {
+                    format!("'{}' must be a list", key.as_str().unwrap_or_default())
+                })?;
+                changes.extend(own.iter().cloned());
+            }
+            option.insert(key, Value::from(changes));
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAGMENTS: &str = "fragments:\n\
+        \x20 accel_off:\n\
+        \x20   registry_changes:\n\
+        \x20     - value_name: MouseSpeed\n\
+        \x20     - value_name: MouseThreshold1\n\
+        \x20 default_curves:\n\
+        \x20   registry_changes:\n\
+        \x20     - value_name: SmoothMouseXCurve\n\
+        \x20   service_changes:\n\
+        \x20     - name: MouClass\n";
+
+    fn option(fragments: &Fragments, option: &str) -> Result<Value, String> {
+        let mut file: Value = serde_yaml_bw::from_str(&format!(
+            "tweaks:\n  - id: mouse\n    options:\n      - {}\n",
+            option
+        ))
+        .unwrap();
+        fragments.resolve(&mut file)?;
+        Ok(file["tweaks"][0]["options"][0].clone())
+    }
+
+    fn names(option: &Value, key: &str) -> Vec<String> {
+        option[key]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .map(|change| {
+                let name = change.get("value_name").or_else(|| change.get("name"));
+                name.and_then(Value::as_str).unwrap().to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn included_changes_come_before_the_options_own() {
+        let fragments = Fragments::from_yaml(FRAGMENTS).unwrap();
+        let resolved = option(
+            &fragments,
+            "{label: On, include: [accel_off, default_curves], registry_changes: [{value_name: Own}]}",
+        )
+        .unwrap();
+
+        assert!(resolved.get(INCLUDE).is_none());
+        assert_eq!(
+            names(&resolved, "registry_changes"),
+            ["MouseSpeed", "MouseThreshold1", "SmoothMouseXCurve", "Own"]
+        );
+        assert_eq!(names(&resolved, "service_changes"), ["MouClass"]);
+        assert!(fragments.unused().is_empty());
+    }
+
+    #[test]
+    fn unknown_or_repeated_fragments_are_errors() {
+        let fragments = Fragments::from_yaml(FRAGMENTS).unwrap();
+        assert!(option(&fragments, "{label: On, include: [missing]}").is_err());
+        assert!(option(&fragments, "{label: On, include: [accel_off, accel_off]}").is_err());
+        assert!(option(&fragments, "{label: On, include: accel_off}").is_err());
+        assert_eq!(fragments.unused(), ["default_curves"]);
+
+        // Custom tweaks have no fragments
+        assert!(option(&Fragments::default(), "{label: On, include: [accel_off]}").is_err());
+    }
+
+    #[test]
+    fn fragments_hold_only_change_lists() {
+        assert!(Fragments::from_yaml("fragments:\n  bad:\n    label: On\n").is_err());
+        assert!(Fragments::from_yaml("fragments:\n  bad:\n    registry_changes: On\n").is_err());
+        assert!(Fragments::from_yaml("fragments:\n  empty: {}\n").is_err());
+    }
+}
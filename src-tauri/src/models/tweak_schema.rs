@@ -1,16 +1,20 @@
 //! Shared YAML-schema type definitions for the tweak system.
 //!
 //! These are the pure data definitions consumed by BOTH `build.rs` (build-time YAML
-//! parsing/validation and JSON generation) and the runtime crate (`models::tweak`,
-//! deserializing the embedded JSON). Previously these ~19 types were hand-mirrored
+//! parsing/validation and data generation) and the runtime crate (`models::tweak`,
+//! decoding the embedded MessagePack). Previously these ~19 types were hand-mirrored
 //! in both places and could drift silently; now there is exactly one definition of
 //! each, and a field rename is a compile error on both sides.
 //!
-//! This file contains ONLY type definitions (derives + serde attributes + fields).
+//! This file contains ONLY type definitions (derives + serde attributes + fields), plus the
+//! serde and schema impls of `WindowsBuild`, which both sides parse. The
+//! `JsonSchema` derives give build.rs the JSON Schema of the tweak file format, so field docs here
+//! are what editors show for the YAML.
 //! `impl` blocks stay with their respective consumers: runtime-only impls remain in
 //! `models/tweak.rs`, build-only impls (validation, `requires_admin`, etc.) remain
 //! in `build.rs`.
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 // ============================================================================
@@ -18,7 +22,7 @@ use serde::{Deserialize, Serialize};
 // ============================================================================
 
 /// Risk level for a tweak indicating potential impact
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum RiskLevel {
     /// Safe to apply/revert without issues
@@ -32,16 +36,25 @@ pub enum RiskLevel {
 }
 
 /// Registry hive types
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 pub enum RegistryHive {
     #[serde(rename = "HKCU")]
     Hkcu,
     #[serde(rename = "HKLM")]
     Hklm,
+    #[serde(rename = "HKCR")]
+    Hkcr,
+    /// HKEY_USERS; the key starts with a user SID or `.DEFAULT`
+    #[serde(rename = "HKU")]
+    Hku,
+    /// Every loaded user account hive; the key is relative to each user's hive.
+    /// Expanded into one HKU change per user before anything touches the registry.
+    #[serde(rename = "ALL_USERS")]
+    AllUsers,
 }
 
 /// Registry value types
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 pub enum RegistryValueType {
     #[serde(rename = "REG_DWORD")]
     Dword,
@@ -58,7 +71,7 @@ pub enum RegistryValueType {
 }
 
 /// Action to perform on a registry key/value
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Default)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Default, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum RegistryAction {
     /// Set a registry value (default behavior)
@@ -73,7 +86,7 @@ pub enum RegistryAction {
 }
 
 /// Windows service startup type
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum ServiceStartupType {
     /// Service is disabled (cannot be started)
@@ -89,7 +102,7 @@ pub enum ServiceStartupType {
 }
 
 /// Action to perform on a scheduled task
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum SchedulerAction {
     /// Enable a disabled scheduled task
@@ -100,12 +113,40 @@ pub enum SchedulerAction {
     Delete,
 }
 
+/// Privilege a single registry, service, or scheduler change runs at, in place of the tweak's
+/// `requires_system` / `requires_ti`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeElevation {
+    /// In the app's process, which needs no administrator rights for this change
+    User,
+    /// In the app's process, which must be running as administrator
+    Admin,
+    /// As SYSTEM, through the elevated broker
+    System,
+    /// As TrustedInstaller, through the elevated broker
+    Ti,
+}
+
+/// Interpreter an option's commands run in
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CommandShell {
+    /// `cmd.exe /c`
+    #[default]
+    Cmd,
+    /// Windows PowerShell 5.1 (`powershell.exe`)
+    Powershell,
+    /// PowerShell 7+ (`pwsh.exe`), which must be installed
+    Pwsh,
+}
+
 // ============================================================================
 // CORE STRUCTURES
 // ============================================================================
 
 /// Category definition from YAML header
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct CategoryDefinition {
     pub id: String,
@@ -116,8 +157,129 @@ pub struct CategoryDefinition {
     pub order: i32,
 }
 
+/// Windows edition family, matched by `conditions.edition`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+pub enum WindowsEdition {
+    Home,
+    Pro,
+    Education,
+    Enterprise,
+    Server,
+}
+
+/// A Windows build, optionally down to the revision a cumulative update brings. Written as `22631`,
+/// or `"22631.2428"` for that update and later ones.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "BuildSpec", into = "BuildSpec")]
+pub struct WindowsBuild {
+    /// `CurrentBuildNumber`, e.g. 22631
+    pub build: u32,
+    /// `UBR`, the cumulative update's revision; None covers every revision of `build`
+    pub revision: Option<u32>,
+}
+
+/// How a [`WindowsBuild`] is written: a build number, or a `"build.revision"` string
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum BuildSpec {
+    Build(u32),
+    Text(String),
+}
+
+// Parsing is shared too: build.rs reads builds from YAML, the runtime from the embedded data
+impl TryFrom<BuildSpec> for WindowsBuild {
+    type Error = String;
+
+    fn try_from(spec: BuildSpec) -> Result<Self, String> {
+        let text = match spec {
+            BuildSpec::Build(build) => {
+                return Ok(WindowsBuild {
+                    build,
+                    revision: None,
+                })
+            }
+            BuildSpec::Text(text) => text,
+        };
+        let parsed = match text.split_once('.') {
+            None => text.parse().ok().map(|build| WindowsBuild {
+                build,
+                revision: None,
+            }),
+            Some((build, revision)) => {
+                build
+                    .parse()
+                    .ok()
+                    .zip(revision.parse().ok())
+                    .map(|(build, revision)| WindowsBuild {
+                        build,
+                        revision: Some(revision),
+                    })
+            }
+        };
+        parsed.ok_or_else(|| {
+            format!(
+                "invalid build '{}': expected a build number or \"build.revision\" (e.g. \"22631.2428\")",
+                text
+            )
+        })
+    }
+}
+
+impl From<WindowsBuild> for BuildSpec {
+    fn from(build: WindowsBuild) -> Self {
+        match build.revision {
+            None => BuildSpec::Build(build.build),
+            Some(revision) => BuildSpec::Text(format!("{}.{}", build.build, revision)),
+        }
+    }
+}
+
+impl JsonSchema for WindowsBuild {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "WindowsBuild".into()
+    }
+
+    fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "description": "A Windows build (22631), or a build and cumulative update revision (\"22631.2428\")",
+            "oneOf": [
+                { "type": "integer", "minimum": 0 },
+                { "type": "string", "pattern": "^[0-9]+(\\.[0-9]+)?$" }
+            ]
+        })
+    }
+}
+
+/// Hardware, edition, and build guards on an option or a registry change; every field that is set
+/// must hold on this machine for it to apply
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Conditions {
+    /// Only on laptops (true) or only on non-laptops (false)
+    #[serde(default)]
+    pub is_laptop: Option<bool>,
+    /// Only with (true) or without (false) a system battery
+    #[serde(default)]
+    pub has_battery: Option<bool>,
+    /// Only with (true) or without (false) at least one SSD
+    #[serde(default)]
+    pub has_ssd: Option<bool>,
+    /// Only on these editions
+    #[serde(default)]
+    pub edition: Option<Vec<WindowsEdition>>,
+    /// Only with at least this much installed memory
+    #[serde(default)]
+    pub min_ram_gb: Option<u32>,
+    /// Only on this build or later; with a revision, from that cumulative update on
+    #[serde(default)]
+    pub min_build: Option<WindowsBuild>,
+    /// Only up to this build; without a revision, up to every revision of it
+    #[serde(default)]
+    pub max_build: Option<WindowsBuild>,
+}
+
 /// Single registry modification within an option
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct RegistryChange {
     pub hive: RegistryHive,
@@ -138,13 +300,25 @@ pub struct RegistryChange {
     /// Optional Windows version filter [10], [11], or [10, 11]
     #[serde(default)]
     pub windows_versions: Option<Vec<u32>>,
+    /// Optional hardware/edition guards; the change is skipped where they don't hold
+    #[serde(default)]
+    pub conditions: Option<Conditions>,
+    /// If true, this change only takes effect after a reboot (counted only where it applies)
+    #[serde(default)]
+    pub requires_reboot: bool,
+    /// If true, this change only takes effect after signing out and back in
+    #[serde(default)]
+    pub requires_logoff: bool,
     /// If true, skip this change for tweak status validation and ignore failures during apply
     #[serde(default)]
     pub skip_validation: bool,
+    /// Privilege this change runs at; defaults to the tweak's
+    #[serde(default)]
+    pub elevation: Option<ChangeElevation>,
 }
 
 /// Single service modification within an option
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct ServiceChange {
     /// Service name (e.g., "DiagTrack", "Spooler")
@@ -160,10 +334,13 @@ pub struct ServiceChange {
     /// If true, skip this change for tweak status validation and ignore failures during apply
     #[serde(default)]
     pub skip_validation: bool,
+    /// Privilege this change runs at; defaults to the tweak's
+    #[serde(default)]
+    pub elevation: Option<ChangeElevation>,
 }
 
 /// Single scheduled task modification within an option
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct SchedulerChange {
     /// Task path (e.g., "\\Microsoft\\Windows\\Customer Experience Improvement Program")
@@ -183,10 +360,13 @@ pub struct SchedulerChange {
     /// If true, don't error if task/path not found (useful for optional tasks)
     #[serde(default)]
     pub ignore_not_found: bool,
+    /// Privilege this change runs at; defaults to the tweak's
+    #[serde(default)]
+    pub elevation: Option<ChangeElevation>,
 }
 
 /// Action to perform on a hosts file entry
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum HostsAction {
     /// Add entry (or ensure it exists)
@@ -196,7 +376,7 @@ pub enum HostsAction {
 }
 
 /// Single hosts file modification within an option
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct HostsChange {
     /// IP address to map (e.g., "127.0.0.1", "0.0.0.0")
@@ -214,7 +394,7 @@ pub struct HostsChange {
 }
 
 /// Direction for firewall rules
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum FirewallDirection {
     /// Inbound traffic
@@ -224,7 +404,7 @@ pub enum FirewallDirection {
 }
 
 /// Action for firewall rules
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum FirewallRuleAction {
     /// Block traffic
@@ -234,7 +414,7 @@ pub enum FirewallRuleAction {
 }
 
 /// Protocol for firewall rules
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum FirewallProtocol {
     /// Any protocol
@@ -250,7 +430,7 @@ pub enum FirewallProtocol {
 }
 
 /// Firewall change operation type
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum FirewallOperation {
     /// Create a new firewall rule
@@ -260,7 +440,7 @@ pub enum FirewallOperation {
 }
 
 /// Single firewall rule modification within an option
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct FirewallChange {
     /// Unique rule name (e.g., "Block DiagTrack Telemetry")
@@ -299,12 +479,681 @@ pub struct FirewallChange {
     pub skip_validation: bool,
 }
 
+/// Action to perform on the power plan configuration
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerPlanAction {
+    /// Make a power scheme the active scheme
+    SetActiveScheme,
+    /// Set the AC and/or DC value index of a power setting within a scheme
+    SetSettingValue,
+}
+
+/// Single power plan modification within an option
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PowerPlanChange {
+    /// Action to perform: set_active_scheme or set_setting_value
+    pub action: PowerPlanAction,
+    /// Scheme GUID or alias (SCHEME_MIN, SCHEME_MAX, SCHEME_BALANCED, SCHEME_CURRENT).
+    /// Required for set_active_scheme; defaults to SCHEME_CURRENT for set_setting_value.
+    #[serde(default)]
+    pub scheme: Option<String>,
+    /// Subgroup GUID (required for set_setting_value)
+    #[serde(default)]
+    pub subgroup: Option<String>,
+    /// Setting GUID (required for set_setting_value)
+    #[serde(default)]
+    pub setting: Option<String>,
+    /// Value index while on AC power (set_setting_value)
+    #[serde(default)]
+    pub ac_value: Option<u32>,
+    /// Value index while on battery (set_setting_value)
+    #[serde(default)]
+    pub dc_value: Option<u32>,
+    /// If true, skip this change for tweak status validation and ignore failures during apply
+    #[serde(default)]
+    pub skip_validation: bool,
+}
+
+/// Action to perform on a BCD element
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Default, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BcdeditAction {
+    /// Set an element's value (`bcdedit /set`, default behavior)
+    #[default]
+    Set,
+    /// Remove an element so the boot loader falls back to its default (`bcdedit /deletevalue`)
+    DeleteValue,
+}
+
+/// Single BCD store modification within an option
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct BcdeditChange {
+    /// BCD entry identifier (e.g., "{current}", "{default}", or a GUID). Defaults to {current}.
+    #[serde(default)]
+    pub identifier: Option<String>,
+    /// Element name (e.g., "useplatformclock", "disabledynamictick")
+    pub element: String,
+    /// Action to perform: set or delete_value
+    #[serde(default)]
+    pub action: BcdeditAction,
+    /// Value to set (required for set, e.g. "yes", "no", "enhanced")
+    #[serde(default)]
+    pub value: Option<String>,
+    /// If true, skip this change for tweak status validation and ignore failures during apply
+    #[serde(default)]
+    pub skip_validation: bool,
+}
+
+/// Action to perform on an AppX (UWP) package
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AppxAction {
+    /// Uninstall the package (`Remove-AppxPackage`)
+    Remove,
+    /// Remove the package from the system image so new user accounts don't get it
+    /// (`Remove-AppxProvisionedPackage`)
+    RemoveProvisioned,
+    /// Re-register a package whose files are still on disk (`Add-AppxPackage -Register`)
+    Reinstall,
+}
+
+/// Which user accounts an AppX change applies to
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Default, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AppxScope {
+    /// Only the user running the app (default)
+    #[default]
+    CurrentUser,
+    /// Every user account on the machine (requires admin)
+    AllUsers,
+}
+
+/// Single AppX package modification within an option
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AppxChange {
+    /// Package name as reported by Get-AppxPackage (e.g., "Microsoft.BingNews"); `*` wildcards allowed
+    pub package: String,
+    /// Action to perform: remove, remove_provisioned, or reinstall
+    pub action: AppxAction,
+    /// Users affected by remove (current_user or all_users)
+    #[serde(default)]
+    pub scope: AppxScope,
+    /// If true, skip this change for tweak status validation and ignore failures during apply
+    #[serde(default)]
+    pub skip_validation: bool,
+}
+
+/// Action to perform on a file or folder
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FileAction {
+    /// Copy `path` to `destination` (overwrites an existing destination)
+    Copy,
+    /// Delete the file or folder at `path` (folders recursively)
+    Delete,
+    /// Rename/move `path` to `destination` (destination must not exist)
+    Rename,
+    /// Change the read-only/hidden/system attributes of `path`
+    SetAttributes,
+    /// Write `content` (UTF-8) to the file at `path`, replacing any existing content
+    WriteContent,
+}
+
+/// File attribute flags for set_attributes. Omitted flags are left unchanged.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct FileAttributes {
+    #[serde(default)]
+    pub readonly: Option<bool>,
+    #[serde(default)]
+    pub hidden: Option<bool>,
+    #[serde(default)]
+    pub system: Option<bool>,
+}
+
+/// Single file or folder modification within an option
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct FileChange {
+    /// Target path; environment variables such as %APPDATA% are expanded
+    pub path: String,
+    /// Action to perform: copy, delete, rename, set_attributes, or write_content
+    pub action: FileAction,
+    /// Destination path (required for copy and rename)
+    #[serde(default)]
+    pub destination: Option<String>,
+    /// Attribute flags to set (required for set_attributes)
+    #[serde(default)]
+    pub attributes: Option<FileAttributes>,
+    /// File content to write (required for write_content)
+    #[serde(default)]
+    pub content: Option<String>,
+    /// If true, don't copy the original into the snapshot before changing it (e.g. when clearing
+    /// caches). Revert then cannot bring the original content back.
+    #[serde(default)]
+    pub skip_backup: bool,
+    /// If true, skip this change for tweak status validation and ignore failures during apply
+    #[serde(default)]
+    pub skip_validation: bool,
+}
+
+/// Action to perform on a registry key's security descriptor
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RegistryAclAction {
+    /// Make the trustee the owner of the key
+    TakeOwnership,
+    /// Add an explicit allow entry for the trustee
+    Grant,
+    /// Remove every explicit entry for the trustee
+    Revoke,
+}
+
+/// Access level granted by a registry ACL change
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Default, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RegistryAccess {
+    /// KEY_ALL_ACCESS (default)
+    #[default]
+    FullControl,
+    /// KEY_READ
+    Read,
+}
+
+/// Single registry key ownership/permission modification within an option
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RegistryAclChange {
+    /// Registry hive (HKCU or HKLM)
+    pub hive: RegistryHive,
+    /// Registry key path (without hive)
+    pub key: String,
+    /// Action to perform: take_ownership, grant, or revoke
+    pub action: RegistryAclAction,
+    /// Account the change is about: a SID (`S-1-5-32-544`), an SDDL alias (`BA`), or an account
+    /// name. Defaults to the Administrators group for take_ownership and grant.
+    #[serde(default)]
+    pub trustee: Option<String>,
+    /// Access to grant (grant only; defaults to full_control)
+    #[serde(default)]
+    pub access: Option<RegistryAccess>,
+    /// If true, skip this change for tweak status validation and ignore failures during apply
+    #[serde(default)]
+    pub skip_validation: bool,
+}
+
+/// Action to perform on a Windows optional feature
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FeatureAction {
+    /// Turn the feature on (`Enable-WindowsOptionalFeature`)
+    Enable,
+    /// Turn the feature off (`Disable-WindowsOptionalFeature`)
+    Disable,
+}
+
+/// Single Windows optional feature modification within an option
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct FeatureChange {
+    /// Feature name as reported by Get-WindowsOptionalFeature (e.g., "Microsoft-Hyper-V")
+    pub feature: String,
+    /// Action to perform: enable or disable
+    pub action: FeatureAction,
+    /// If true, skip this change for tweak status validation and ignore failures during apply
+    #[serde(default)]
+    pub skip_validation: bool,
+}
+
+/// Single network adapter advanced property modification within an option
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct NetAdapterChange {
+    /// Adapter name as shown by Get-NetAdapter (e.g., "Ethernet"); `*` wildcards allowed.
+    /// Defaults to every physical adapter.
+    #[serde(default)]
+    pub adapter: Option<String>,
+    /// Advanced property registry keyword (e.g., "*RSS", "*InterruptModeration")
+    pub keyword: String,
+    /// Registry value to set (e.g., "0" to disable a boolean property)
+    pub value: String,
+    /// If true, skip this change for tweak status validation and ignore failures during apply
+    #[serde(default)]
+    pub skip_validation: bool,
+}
+
+/// Which half of Group Policy a policy change belongs to
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Default, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyScope {
+    /// Computer Configuration (HKLM, refreshed with `gpupdate /target:computer`)
+    #[default]
+    Machine,
+    /// User Configuration (HKCU, refreshed with `gpupdate /target:user`)
+    User,
+}
+
+/// Action to perform on a policy value
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Default, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyAction {
+    /// Set the policy value (default behavior)
+    #[default]
+    Set,
+    /// Delete the policy value (in Registry.pol: record it as deleted)
+    DeleteValue,
+}
+
+/// Single Group Policy registry value modification within an option
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyChange {
+    /// Machine or user policy; selects the hive, the Registry.pol file, and the gpupdate target
+    pub scope: PolicyScope,
+    /// Policy key path without hive (e.g., "SOFTWARE\\Policies\\Microsoft\\Windows\\DataCollection")
+    pub key: String,
+    /// Value name (policies always use named values)
+    pub value_name: String,
+    /// Action to perform: set or delete_value
+    #[serde(default)]
+    pub action: PolicyAction,
+    /// Value type - required for "set" action
+    #[serde(default)]
+    pub value_type: Option<RegistryValueType>,
+    /// Target value - required for "set" action
+    #[serde(default)]
+    pub value: Option<serde_json::Value>,
+    /// If true, also record the value in the local Group Policy object (Registry.pol), so Windows
+    /// re-applies it on every policy refresh and it shows up in policy tools
+    #[serde(default)]
+    pub write_pol: bool,
+    /// If true, skip this change for tweak status validation and ignore failures during apply
+    #[serde(default)]
+    pub skip_validation: bool,
+}
+
+/// Browser whose enterprise policies a browser policy change sets
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Browser {
+    /// Microsoft Edge (`SOFTWARE\Policies\Microsoft\Edge`)
+    Edge,
+    /// Google Chrome (`SOFTWARE\Policies\Google\Chrome`)
+    Chrome,
+    /// Mozilla Firefox (`SOFTWARE\Policies\Mozilla\Firefox`)
+    Firefox,
+}
+
+/// Single browser enterprise policy within an option, named the way the browser documents it
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct BrowserPolicyChange {
+    /// Browser the policy belongs to: edge, chrome, or firefox
+    pub browser: Browser,
+    /// Policy name from the browser's policy list (e.g., "DnsOverHttpsMode", "DisableTelemetry")
+    pub policy: String,
+    /// Machine (default) or user policy
+    #[serde(default)]
+    pub scope: PolicyScope,
+    /// Action to perform: set or delete_value
+    #[serde(default)]
+    pub action: PolicyAction,
+    /// Policy value - required for "set" action: a boolean, integer, string, list of strings, or
+    /// (for object policies) an object of those
+    #[serde(default)]
+    pub value: Option<serde_json::Value>,
+    /// If true, skip this change for tweak status validation and ignore failures during apply
+    #[serde(default)]
+    pub skip_validation: bool,
+}
+
+/// Action to perform on an application package
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AppAction {
+    /// Install the package (`winget install`); a no-op if it is already installed
+    Install,
+    /// Uninstall the package (`winget uninstall`); a no-op if it isn't installed
+    Uninstall,
+}
+
+/// Single application install or uninstall (through winget) within an option
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AppChange {
+    /// winget package identifier, matched exactly (e.g., "Microsoft.OneDrive")
+    pub id: String,
+    /// Action to perform: install or uninstall
+    pub action: AppAction,
+    /// winget source ("winget" or "msstore"). Defaults to "winget" for installs; uninstalls match
+    /// the installed package from any source.
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Exact version to install (install only; defaults to the latest)
+    #[serde(default)]
+    pub version: Option<String>,
+    /// If true, skip this change for tweak status validation and ignore failures during apply
+    #[serde(default)]
+    pub skip_validation: bool,
+}
+
+/// MMCSS scheduling category of a multimedia task
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MmcssSchedulingCategory {
+    Low,
+    Medium,
+    High,
+}
+
+/// MMCSS scheduled I/O priority of a multimedia task
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MmcssIoPriority {
+    Idle,
+    Low,
+    Normal,
+    High,
+}
+
+/// Settings of one Multimedia Class Scheduler (MMCSS) task within an option, i.e. the values
+/// under `HKLM\SOFTWARE\Microsoft\Windows NT\CurrentVersion\Multimedia\SystemProfile\Tasks\<task>`.
+/// Fields left unset are not touched.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MmcssChange {
+    /// Task key name (e.g., "Games", "Pro Audio")
+    pub task: String,
+    /// Thread priority, 1 (lowest) to 8 (highest)
+    #[serde(default)]
+    pub priority: Option<u32>,
+    /// GPU priority, 0 to 31
+    #[serde(default)]
+    pub gpu_priority: Option<u32>,
+    /// Scheduling category: low, medium, or high
+    #[serde(default)]
+    pub scheduling_category: Option<MmcssSchedulingCategory>,
+    /// Scheduled I/O priority: idle, low, normal, or high
+    #[serde(default)]
+    pub sfio_priority: Option<MmcssIoPriority>,
+    /// Whether the task only runs in the background
+    #[serde(default)]
+    pub background_only: Option<bool>,
+    /// Timer resolution in 100-nanosecond units (10000 = 1 ms)
+    #[serde(default)]
+    pub clock_rate: Option<u32>,
+    /// Processor affinity mask (0 = all processors)
+    #[serde(default)]
+    pub affinity: Option<u32>,
+    /// If true, skip this change for tweak status validation and ignore failures during apply
+    #[serde(default)]
+    pub skip_validation: bool,
+}
+
+/// Where a context menu change is registered
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Default, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextMenuScope {
+    /// Current user only (HKCU\SOFTWARE\Classes)
+    #[default]
+    User,
+    /// All users (HKLM\SOFTWARE\Classes)
+    Machine,
+}
+
+/// Kind of context menu entry
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Default, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextMenuKind {
+    /// Static verb (`<target>\shell\<name>`) that runs a command
+    #[default]
+    Verb,
+    /// Shell extension handler (`<target>\shellex\ContextMenuHandlers\<name>`) naming a CLSID
+    Handler,
+    /// Windows 11 classic context menu override (`CLSID\{86ca1aa0-...}\InprocServer32`)
+    ClassicMenu,
+}
+
+/// Action to perform on a context menu entry
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextMenuAction {
+    /// Register the entry (for classic_menu: turn the classic menu on)
+    Add,
+    /// Delete the entry's key (for classic_menu: turn the classic menu off)
+    Remove,
+}
+
+/// Single shell context menu modification within an option
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ContextMenuChange {
+    /// Action to perform: add or remove
+    pub action: ContextMenuAction,
+    /// Entry kind: verb (default), handler, or classic_menu
+    #[serde(default)]
+    pub kind: ContextMenuKind,
+    /// Per-user (default) or machine-wide registration
+    #[serde(default)]
+    pub scope: ContextMenuScope,
+    /// Class the entry belongs to (e.g., "*", "Directory", "Directory\\Background", ".txt");
+    /// unused for classic_menu
+    #[serde(default)]
+    pub target: String,
+    /// Verb or handler key name (e.g., "OpenWithCode"); unused for classic_menu
+    #[serde(default)]
+    pub name: String,
+    /// Menu text (verb add)
+    #[serde(default)]
+    pub label: Option<String>,
+    /// Command line the verb runs (verb add, required)
+    #[serde(default)]
+    pub command: Option<String>,
+    /// Icon resource (verb add, e.g., "C:\\Tools\\app.exe,0")
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// If true, the verb only shows with Shift held (verb add)
+    #[serde(default)]
+    pub extended: bool,
+    /// Handler CLSID, e.g. "{...}" (handler add, required)
+    #[serde(default)]
+    pub clsid: Option<String>,
+    /// If true, skip this change for tweak status validation and ignore failures during apply
+    #[serde(default)]
+    pub skip_validation: bool,
+}
+
+/// Windows sound scheme
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SoundScheme {
+    /// "No Sounds": every system sound is silenced
+    None,
+    /// "Windows Default"
+    Default,
+}
+
+/// What Windows does to other sounds when it detects communications activity (a call)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+pub enum CommunicationsDucking {
+    #[serde(rename = "mute_others")]
+    MuteOthers,
+    #[serde(rename = "reduce_80")]
+    Reduce80,
+    #[serde(rename = "reduce_50")]
+    Reduce50,
+    #[serde(rename = "do_nothing")]
+    DoNothing,
+}
+
+/// Audio settings within an option; fields left unset are not touched
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AudioChange {
+    /// Sound scheme of the current user: none or default
+    #[serde(default)]
+    pub sound_scheme: Option<SoundScheme>,
+    /// Whether the startup sound plays at sign-in
+    #[serde(default)]
+    pub startup_sound: Option<bool>,
+    /// Communications activity ducking: mute_others, reduce_80, reduce_50, or do_nothing
+    #[serde(default)]
+    pub communications_ducking: Option<CommunicationsDucking>,
+    /// Whether audio enhancements are enabled on every playback device
+    #[serde(default)]
+    pub audio_enhancements: Option<bool>,
+    /// If true, skip this change for tweak status validation and ignore failures during apply
+    #[serde(default)]
+    pub skip_validation: bool,
+}
+
+/// A visual effect from the Performance Options dialog (and the transparency and font smoothing
+/// settings next to it)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VisualEffect {
+    /// Fade or slide menus into view
+    MenuAnimation,
+    /// Slide open combo boxes
+    ComboBoxAnimation,
+    /// Smooth-scroll list boxes
+    SmoothScrolling,
+    /// Fade out menu items after clicking
+    SelectionFade,
+    /// Fade or slide tooltips into view
+    TooltipAnimation,
+    /// Show shadows under the mouse pointer
+    CursorShadow,
+    /// Show shadows under windows
+    WindowShadows,
+    /// Animate controls and elements inside windows
+    ControlAnimations,
+    /// Animate windows when minimizing and maximizing
+    WindowAnimations,
+    /// Animations in the taskbar
+    TaskbarAnimations,
+    /// Use drop shadows for icon labels on the desktop
+    IconShadows,
+    /// Transparency effects (Personalization > Colors)
+    Transparency,
+    /// Smooth edges of screen fonts
+    FontSmoothing,
+}
+
+/// Turn one visual effect on or off
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct VisualEffectsChange {
+    /// The effect to change
+    pub effect: VisualEffect,
+    /// Whether the effect is on
+    pub enabled: bool,
+    /// If true, skip this change for tweak status validation and ignore failures during apply
+    #[serde(default)]
+    pub skip_validation: bool,
+}
+
+/// Where the Windows Time service syncs the clock from (the w32time `Type` value)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeSyncType {
+    /// The configured NTP servers
+    Ntp,
+    /// The domain hierarchy
+    Nt5ds,
+    /// The domain hierarchy and the configured NTP servers
+    AllSync,
+    /// No syncing
+    NoSync,
+}
+
+/// Windows Time service (w32time) settings within an option; fields left unset are not touched
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TimeChange {
+    /// NTP servers, in order of preference (e.g., ["time.cloudflare.com", "pool.ntp.org"])
+    #[serde(default)]
+    pub ntp_servers: Option<Vec<String>>,
+    /// Sync source: ntp, nt5ds, all_sync, or no_sync
+    #[serde(default)]
+    pub sync_type: Option<TimeSyncType>,
+    /// Seconds between syncs with the NTP servers
+    #[serde(default)]
+    pub sync_interval: Option<u32>,
+    /// If true, skip this change for tweak status validation and ignore failures during apply
+    #[serde(default)]
+    pub skip_validation: bool,
+}
+
+/// Optional Windows component made up of services
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LegacyComponent {
+    /// Print Spooler and Printer Extensions and Notifications
+    PrintSpooler,
+    /// Fax service
+    Fax,
+    /// Xbox Live and Xbox accessory services
+    Xbox,
+}
+
+/// Turn an optional component's services on or off as a bundle within an option
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ComponentChange {
+    /// Component to change
+    pub component: LegacyComponent,
+    /// true restores the services' default startup types, false disables and stops them
+    /// (along with the services of components that depend on this one)
+    pub enabled: bool,
+    /// If true, skip this change for tweak status validation and ignore failures during apply
+    #[serde(default)]
+    pub skip_validation: bool,
+}
+
+/// Registry location captured by a state probe: one value, or a whole key when `value_name` is
+/// omitted
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RegistryProbe {
+    pub hive: RegistryHive,
+    pub key: String,
+    /// Value to capture (empty string targets the default value); omit to capture the key with
+    /// every subkey and value under it
+    #[serde(default)]
+    pub value_name: Option<String>,
+}
+
+/// State an option's commands may change. Commands can't be undone, so the backup service
+/// captures these before apply and restores them on revert, which makes command-heavy tweaks at
+/// least partly revertible. Nothing here is changed on apply.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct StateProbes {
+    /// Registry values and keys to capture
+    #[serde(default)]
+    pub registry: Vec<RegistryProbe>,
+    /// Files and folders to back up; environment variables such as %APPDATA% are expanded
+    #[serde(default)]
+    pub files: Vec<String>,
+    /// Services whose startup type and running state to capture
+    #[serde(default)]
+    pub services: Vec<String>,
+}
+
 /// A single option within a tweak - contains all changes for that state
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct TweakOption {
     /// Display label (e.g., "Enabled", "Disabled", "4MB")
     pub label: String,
+    /// Optional hardware/edition guards; the option can't be applied where they don't hold
+    #[serde(default)]
+    pub conditions: Option<Conditions>,
     /// Registry modifications for this option
     #[serde(default)]
     pub registry_changes: Vec<RegistryChange>,
@@ -320,6 +1169,54 @@ pub struct TweakOption {
     /// Firewall rule modifications for this option
     #[serde(default)]
     pub firewall_changes: Vec<FirewallChange>,
+    /// Power plan modifications for this option
+    #[serde(default)]
+    pub power_plan_changes: Vec<PowerPlanChange>,
+    /// BCD store modifications for this option
+    #[serde(default)]
+    pub bcdedit_changes: Vec<BcdeditChange>,
+    /// AppX (UWP) package modifications for this option
+    #[serde(default)]
+    pub appx_changes: Vec<AppxChange>,
+    /// File and folder modifications for this option
+    #[serde(default)]
+    pub file_changes: Vec<FileChange>,
+    /// Registry key ownership and permission modifications for this option
+    #[serde(default)]
+    pub registry_acl_changes: Vec<RegistryAclChange>,
+    /// Windows optional feature modifications for this option
+    #[serde(default)]
+    pub feature_changes: Vec<FeatureChange>,
+    /// Network adapter advanced property modifications for this option
+    #[serde(default)]
+    pub netadapter_changes: Vec<NetAdapterChange>,
+    /// Group Policy registry value modifications for this option
+    #[serde(default)]
+    pub policy_changes: Vec<PolicyChange>,
+    /// Application install/uninstall (winget) modifications for this option
+    #[serde(default)]
+    pub app_changes: Vec<AppChange>,
+    /// Multimedia Class Scheduler task modifications for this option
+    #[serde(default)]
+    pub mmcss_changes: Vec<MmcssChange>,
+    /// Shell context menu modifications for this option
+    #[serde(default)]
+    pub context_menu_changes: Vec<ContextMenuChange>,
+    /// Audio setting modifications for this option
+    #[serde(default)]
+    pub audio_changes: Vec<AudioChange>,
+    /// Visual effect modifications for this option
+    #[serde(default)]
+    pub visual_effects_changes: Vec<VisualEffectsChange>,
+    /// Browser enterprise policy modifications for this option
+    #[serde(default)]
+    pub browser_policy_changes: Vec<BrowserPolicyChange>,
+    /// Windows Time service modifications for this option
+    #[serde(default)]
+    pub time_changes: Vec<TimeChange>,
+    /// Optional component (service bundle) modifications for this option
+    #[serde(default)]
+    pub component_changes: Vec<ComponentChange>,
     /// Shell commands (cmd.exe) to run BEFORE applying changes
     #[serde(default)]
     pub pre_commands: Vec<String>,
@@ -332,6 +1229,30 @@ pub struct TweakOption {
     /// PowerShell commands to run AFTER applying changes (after post_commands)
     #[serde(default)]
     pub post_powershell: Vec<String>,
+    /// Interpreter for `pre_commands` / `post_commands` (default: cmd). With `pwsh`, the
+    /// `pre_powershell` / `post_powershell` scripts also run in PowerShell 7 instead of Windows
+    /// PowerShell.
+    #[serde(default)]
+    pub shell: Option<CommandShell>,
+    /// How long each of the option's commands may run, in seconds, when the tweak runs them as
+    /// SYSTEM or TrustedInstaller (default: the 30-second elevated-process timeout). For commands
+    /// known to run long, such as DISM.
+    #[serde(default)]
+    pub command_timeout_secs: Option<u32>,
+    /// State the option's commands change, captured so revert can restore it
+    #[serde(default)]
+    pub state_probes: StateProbes,
+    /// If true, switching to or reverting this option needs a reboot (in addition to the
+    /// tweak-level `requires_reboot`)
+    #[serde(default)]
+    pub requires_reboot: bool,
+    /// If true, switching to or reverting this option needs a sign-out
+    #[serde(default)]
+    pub requires_logoff: bool,
+    /// If true, explorer.exe is restarted after this option is applied or reverted, for
+    /// settings Explorer only reads at startup
+    #[serde(default)]
+    pub restart_explorer: bool,
     /// If true, treat missing registry keys/values as matching this option.
     /// Used for tweaks that modify registry entries which may not exist on all Windows editions.
     /// When a registry key/value doesn't exist and this flag is set, the option is considered
@@ -353,7 +1274,7 @@ pub struct TweakOption {
 }
 
 /// Complete tweak definition with category assignment
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct TweakDefinition {
     pub id: String,
@@ -380,4 +1301,28 @@ pub struct TweakDefinition {
     /// Category this tweak belongs to
     #[serde(default)]
     pub category_id: String,
+    /// Loaded from the user's `tweaks.d` directory rather than compiled in
+    #[serde(default)]
+    pub custom: bool,
+    /// Kept so it can be reverted, but no longer recommended
+    #[serde(default)]
+    pub deprecated: bool,
+    /// The tweak to use instead of this deprecated one
+    #[serde(default)]
+    pub superseded_by: Option<String>,
+    /// Earlier IDs of the tweak. Snapshots and notes stored under one are moved to `id`.
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Only offered from this build on
+    #[serde(default)]
+    pub min_build: Option<WindowsBuild>,
+    /// Only offered up to this build
+    #[serde(default)]
+    pub max_build: Option<WindowsBuild>,
+    /// Only offered on these editions
+    #[serde(default)]
+    pub editions: Option<Vec<WindowsEdition>>,
+    /// Groups across categories the tweak belongs to (e.g. `privacy`, `gaming`)
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
@@ -3,12 +3,14 @@
 //! Snapshot-based storage for registry/service state before tweak application.
 //! Used for atomic rollback to the exact state before any changes were made.
 
+use crate::models::ChangeElevation;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 /// Current on-disk snapshot schema version. Additive fields guarded by `#[serde(default)]` don't
-/// require a bump; bump only when the meaning of an existing field changes. Snapshots written
-/// before versioning existed deserialize as 0.
+/// require a bump; bump only when the meaning of an existing field changes, and add the upgrade
+/// step to `MIGRATIONS` in `backup::storage`. Snapshots written before versioning existed
+/// deserialize as 0.
 pub const SNAPSHOT_SCHEMA_VERSION: u32 = 1;
 
 /// Snapshot of a single registry value before modification
@@ -26,6 +28,40 @@ pub struct RegistrySnapshot {
     pub value: Option<Value>,
     /// Whether the value existed before modification
     pub existed: bool,
+    /// True for a key-level snapshot (delete_key / create_key), where `existed` refers to the key
+    /// itself and `value_name` is unused
+    #[serde(default)]
+    pub key_level: bool,
+    /// Everything under a key that a delete_key removed, so revert recreates it with its values
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_contents: Option<RegistryKeyContents>,
+    /// The change's own elevation, if it declared one; restoring follows `requires_system` otherwise
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elevation: Option<ChangeElevation>,
+}
+
+/// The subkeys and values under a registry key, captured before the key is deleted
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RegistryKeyContents {
+    /// Subkey paths relative to the key, parents before their children
+    #[serde(default)]
+    pub subkeys: Vec<String>,
+    /// Values of the key and of every subkey
+    #[serde(default)]
+    pub values: Vec<RegistryKeyValue>,
+}
+
+/// One value inside a captured registry key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistryKeyValue {
+    /// Subkey path relative to the captured key ("" for the key itself)
+    #[serde(default)]
+    pub subkey: String,
+    /// Value name ("" for the default value)
+    pub name: String,
+    /// Value type (REG_DWORD, REG_SZ, etc.)
+    pub value_type: String,
+    pub value: Value,
 }
 
 /// Snapshot of a service's state before modification
@@ -37,6 +73,9 @@ pub struct ServiceSnapshot {
     pub startup_type: String,
     /// Whether the service was running before modification
     pub was_running: bool,
+    /// The change's own elevation, if it declared one; restoring follows `requires_system` otherwise
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elevation: Option<ChangeElevation>,
 }
 
 /// Snapshot of a scheduled task's state before modification
@@ -48,6 +87,12 @@ pub struct SchedulerSnapshot {
     pub task_name: String,
     /// Task state before modification ("Ready", "Disabled", "NotFound")
     pub original_state: String,
+    /// Task definition XML, captured for tasks the change deletes so revert can register them again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub definition_xml: Option<String>,
+    /// The change's own elevation, if it declared one; restoring follows `requires_system` otherwise
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elevation: Option<ChangeElevation>,
 }
 
 /// Snapshot of a hosts file entry before modification
@@ -70,6 +115,164 @@ pub struct FirewallSnapshot {
     pub existed: bool,
 }
 
+/// Snapshot of power plan state before modification. A snapshot with no `subgroup`/`setting`
+/// records the previously active scheme; otherwise it records one setting's value indices.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PowerPlanSnapshot {
+    /// Resolved scheme GUID (aliases such as SCHEME_CURRENT are resolved at capture time)
+    pub scheme: String,
+    /// Subgroup GUID - None for an active-scheme snapshot
+    #[serde(default)]
+    pub subgroup: Option<String>,
+    /// Setting GUID - None for an active-scheme snapshot
+    #[serde(default)]
+    pub setting: Option<String>,
+    /// AC value index before modification
+    #[serde(default)]
+    pub ac_value: Option<u32>,
+    /// DC value index before modification
+    #[serde(default)]
+    pub dc_value: Option<u32>,
+}
+
+/// Snapshot of a BCD element before modification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BcdeditSnapshot {
+    /// BCD entry identifier (e.g., "{current}")
+    pub identifier: String,
+    /// Element name
+    pub element: String,
+    /// Element value before modification (None if the element was not set)
+    pub value: Option<String>,
+    /// Whether the element was set before modification
+    pub existed: bool,
+}
+
+/// An installed AppX package recorded in a snapshot
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AppxPackageRecord {
+    /// Full package name (e.g., "Microsoft.BingNews_4.55.0.0_x64__8wekyb3d8bbwe")
+    pub full_name: String,
+    /// Install folder containing AppxManifest.xml, used to re-register the package on revert
+    #[serde(default)]
+    pub install_location: Option<String>,
+}
+
+/// Snapshot of AppX package state before modification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppxSnapshot {
+    /// Package name (or wildcard pattern) as written in the change
+    pub package: String,
+    /// Scope the installed list was read in ("current_user" or "all_users")
+    pub scope: String,
+    /// Packages matching `package` that were installed before modification
+    #[serde(default)]
+    pub installed: Vec<AppxPackageRecord>,
+    /// Provisioned package names matching `package` before modification.
+    /// None when the change doesn't touch provisioning (not captured).
+    #[serde(default)]
+    pub provisioned: Option<Vec<String>>,
+}
+
+/// Snapshot of a file or folder before modification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileSnapshot {
+    /// Expanded path that was captured
+    pub path: String,
+    /// Whether anything existed at `path` before modification
+    pub existed: bool,
+    /// Whether `path` was a folder
+    #[serde(default)]
+    pub is_dir: bool,
+    /// Copy of the original, relative to the snapshots directory (None if nothing was backed up)
+    #[serde(default)]
+    pub backup_path: Option<String>,
+    /// Original file attributes (captured for set_attributes)
+    #[serde(default)]
+    pub attributes: Option<u32>,
+    /// For a rename: where `path` was moved to, so revert can move it back
+    #[serde(default)]
+    pub moved_to: Option<String>,
+}
+
+/// Snapshot of a Windows optional feature before modification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeatureSnapshot {
+    /// Feature name as written in the change
+    pub feature: String,
+    /// Whether the feature was enabled (or pending enable) before modification.
+    /// None if the feature isn't available on this Windows edition.
+    #[serde(default)]
+    pub enabled: Option<bool>,
+}
+
+/// Snapshot of one adapter's advanced property before modification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetAdapterSnapshot {
+    /// Adapter name
+    pub adapter: String,
+    /// Advanced property registry keyword
+    pub keyword: String,
+    /// Registry value before modification (None if the driver default was in effect)
+    #[serde(default)]
+    pub value: Option<String>,
+}
+
+/// One value record in a Registry.pol file
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PolicyFileEntry {
+    /// Key path without hive
+    pub key: String,
+    /// Value name; a `**del.` prefix marks the value as deleted by policy
+    pub value_name: String,
+    /// Raw registry type (REG_SZ = 1, REG_DWORD = 4, ...)
+    pub value_type: u32,
+    /// Raw value data
+    #[serde(default)]
+    pub data: Vec<u8>,
+}
+
+/// Snapshot of a policy value's Registry.pol record before modification.
+/// The registry value itself is captured in `registry_snapshots`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicySnapshot {
+    /// Policy scope ("machine" or "user"), used to pick the Registry.pol file and gpupdate target
+    pub scope: String,
+    /// Policy key path
+    pub key: String,
+    /// Value name
+    pub value_name: String,
+    /// Whether the Registry.pol record was captured (only for changes with write_pol)
+    #[serde(default)]
+    pub pol_captured: bool,
+    /// Registry.pol record for this value before modification (None if there was none)
+    #[serde(default)]
+    pub pol_entry: Option<PolicyFileEntry>,
+}
+
+/// Snapshot of an application package's install state before modification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppSnapshot {
+    /// winget package identifier
+    pub id: String,
+    /// winget source the package was installed from or would be reinstalled from
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Whether the package was installed
+    pub installed: bool,
+}
+
+/// Snapshot of a registry key's security descriptor before modification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistryAclSnapshot {
+    /// Registry hive (HKCU or HKLM)
+    pub hive: String,
+    /// Registry key path
+    pub key: String,
+    /// Owner and DACL in SDDL form (None if the key didn't exist)
+    pub sddl: Option<String>,
+}
+
 /// Complete snapshot of system state before applying a tweak option
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TweakSnapshot {
@@ -100,6 +303,11 @@ pub struct TweakSnapshot {
     /// Human-readable descriptions of the resources a partial revert could not restore.
     #[serde(default)]
     pub unrestorable_resources: Vec<String>,
+    /// Boot time (RFC 3339) of the session the applied option was set in, when it needs a reboot
+    /// to take effect. While the machine hasn't rebooted since, detection can't be trusted for the
+    /// reboot-dependent values, so re-applying or reverting is blocked (see `backup::reboot`).
+    #[serde(default)]
+    pub pending_reboot_boot: Option<String>,
     /// Whether SYSTEM elevation was used for this tweak
     #[serde(default)]
     pub requires_system: bool,
@@ -121,6 +329,33 @@ pub struct TweakSnapshot {
     /// Firewall rules captured before changes
     #[serde(default)]
     pub firewall_snapshots: Vec<FirewallSnapshot>,
+    /// Power plan state captured before changes
+    #[serde(default)]
+    pub power_plan_snapshots: Vec<PowerPlanSnapshot>,
+    /// BCD elements captured before changes
+    #[serde(default)]
+    pub bcdedit_snapshots: Vec<BcdeditSnapshot>,
+    /// AppX packages captured before changes
+    #[serde(default)]
+    pub appx_snapshots: Vec<AppxSnapshot>,
+    /// Files and folders captured before changes
+    #[serde(default)]
+    pub file_snapshots: Vec<FileSnapshot>,
+    /// Registry key security descriptors captured before changes
+    #[serde(default)]
+    pub registry_acl_snapshots: Vec<RegistryAclSnapshot>,
+    /// Windows optional features captured before changes
+    #[serde(default)]
+    pub feature_snapshots: Vec<FeatureSnapshot>,
+    /// Network adapter properties captured before changes
+    #[serde(default)]
+    pub netadapter_snapshots: Vec<NetAdapterSnapshot>,
+    /// Group Policy records captured before changes
+    #[serde(default)]
+    pub policy_snapshots: Vec<PolicySnapshot>,
+    /// Application install states captured before changes
+    #[serde(default)]
+    pub app_snapshots: Vec<AppSnapshot>,
 }
 
 impl TweakSnapshot {
@@ -145,6 +380,7 @@ impl TweakSnapshot {
             machine_guid: crate::services::system_info_service::machine_guid(),
             needs_attention: false,
             unrestorable_resources: Vec::new(),
+            pending_reboot_boot: None,
             requires_system,
             original_option_index,
             registry_snapshots: Vec::new(),
@@ -152,6 +388,15 @@ impl TweakSnapshot {
             scheduler_snapshots: Vec::new(),
             hosts_snapshots: Vec::new(),
             firewall_snapshots: Vec::new(),
+            power_plan_snapshots: Vec::new(),
+            bcdedit_snapshots: Vec::new(),
+            appx_snapshots: Vec::new(),
+            file_snapshots: Vec::new(),
+            registry_acl_snapshots: Vec::new(),
+            feature_snapshots: Vec::new(),
+            netadapter_snapshots: Vec::new(),
+            policy_snapshots: Vec::new(),
+            app_snapshots: Vec::new(),
         }
     }
 
@@ -179,6 +424,51 @@ impl TweakSnapshot {
     pub fn add_firewall_snapshot(&mut self, snapshot: FirewallSnapshot) {
         self.firewall_snapshots.push(snapshot);
     }
+
+    /// Add a power plan snapshot
+    pub fn add_power_plan_snapshot(&mut self, snapshot: PowerPlanSnapshot) {
+        self.power_plan_snapshots.push(snapshot);
+    }
+
+    /// Add a BCD element snapshot
+    pub fn add_bcdedit_snapshot(&mut self, snapshot: BcdeditSnapshot) {
+        self.bcdedit_snapshots.push(snapshot);
+    }
+
+    /// Add an AppX package snapshot
+    pub fn add_appx_snapshot(&mut self, snapshot: AppxSnapshot) {
+        self.appx_snapshots.push(snapshot);
+    }
+
+    /// Add a file snapshot
+    pub fn add_file_snapshot(&mut self, snapshot: FileSnapshot) {
+        self.file_snapshots.push(snapshot);
+    }
+
+    /// Add a registry ACL snapshot
+    pub fn add_registry_acl_snapshot(&mut self, snapshot: RegistryAclSnapshot) {
+        self.registry_acl_snapshots.push(snapshot);
+    }
+
+    /// Add an optional feature snapshot
+    pub fn add_feature_snapshot(&mut self, snapshot: FeatureSnapshot) {
+        self.feature_snapshots.push(snapshot);
+    }
+
+    /// Add a network adapter property snapshot
+    pub fn add_netadapter_snapshot(&mut self, snapshot: NetAdapterSnapshot) {
+        self.netadapter_snapshots.push(snapshot);
+    }
+
+    /// Add a policy snapshot
+    pub fn add_policy_snapshot(&mut self, snapshot: PolicySnapshot) {
+        self.policy_snapshots.push(snapshot);
+    }
+
+    /// Add an application snapshot
+    pub fn add_app_snapshot(&mut self, snapshot: AppSnapshot) {
+        self.app_snapshots.push(snapshot);
+    }
 }
 
 #[cfg(test)]
@@ -209,6 +499,40 @@ mod tests {
         assert_eq!(s.tweak_id, "t");
     }
 
+    #[test]
+    fn an_older_key_snapshot_loads_as_a_value_snapshot_and_contents_round_trip() {
+        let legacy: RegistrySnapshot = serde_json::from_str(
+            r#"{"hive": "HKCU", "key": "Software\\X", "value_name": "", "value_type": null,
+                "value": null, "existed": true}"#,
+        )
+        .unwrap();
+        assert!(!legacy.key_level);
+        assert!(legacy.key_contents.is_none());
+
+        let snapshot = RegistrySnapshot {
+            key_level: true,
+            key_contents: Some(RegistryKeyContents {
+                subkeys: vec!["command".to_string()],
+                values: vec![RegistryKeyValue {
+                    subkey: "command".to_string(),
+                    name: String::new(),
+                    value_type: "REG_SZ".to_string(),
+                    value: serde_json::json!("notepad.exe \"%1\""),
+                }],
+            }),
+            ..legacy
+        };
+        let back: RegistrySnapshot =
+            serde_json::from_str(&serde_json::to_string(&snapshot).unwrap()).unwrap();
+        assert!(back.key_level);
+        let contents = back.key_contents.unwrap();
+        assert_eq!(contents.subkeys, ["command"]);
+        assert_eq!(
+            contents.values[0].value,
+            serde_json::json!("notepad.exe \"%1\"")
+        );
+    }
+
     #[test]
     fn schema_version_and_machine_guid_round_trip() {
         let mut s = TweakSnapshot::new("t", "T", 0, "opt", 11, false, None);
@@ -0,0 +1,207 @@
+//! `${variable}` templating for strings inside tweak options.
+//!
+//! Shared by `build.rs` (which rejects unknown variables and malformed placeholders) and the
+//! runtime `tweak_loader` (which substitutes this machine's values when the tweaks are loaded).
+//! Like `tweak_schema.rs`, this file must stay free of crate-internal imports so build.rs can
+//! include it via `#[path]`. `$${` writes a literal `${`.
+
+use std::fmt;
+
+/// Variables a tweak may reference, with what each expands to
+pub const TEMPLATE_VARIABLES: &[(&str, &str)] = &[
+    ("username", "Account name of the current user"),
+    ("sid", "SID of the current user (e.g. S-1-5-21-...-1001)"),
+    (
+        "user_profile",
+        "Current user's profile folder (%USERPROFILE%)",
+    ),
+    (
+        "app_data",
+        "Current user's roaming AppData folder (%APPDATA%)",
+    ),
+    (
+        "local_app_data",
+        "Current user's local AppData folder (%LOCALAPPDATA%)",
+    ),
+    ("program_files", "Program Files folder (%ProgramFiles%)"),
+    (
+        "program_files_x86",
+        "32-bit Program Files folder (%ProgramFiles(x86)%)",
+    ),
+    ("program_data", "ProgramData folder (%ProgramData%)"),
+    ("system_root", "Windows folder (%SystemRoot%)"),
+    ("system_drive", "System drive (%SystemDrive%, e.g. C:)"),
+    (
+        "gpu_vendor",
+        "Primary GPU vendor: nvidia, amd, intel, or unknown",
+    ),
+];
+
+/// Whether `name` is one of [`TEMPLATE_VARIABLES`]
+pub fn is_template_variable(name: &str) -> bool {
+    TEMPLATE_VARIABLES.iter().any(|(known, _)| *known == name)
+}
+
+/// Why a template string couldn't be expanded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateError {
+    /// A `${` with no closing `}`
+    Unterminated,
+    /// `${}` or a name with characters other than lowercase letters, digits, and `_`
+    InvalidName(String),
+    /// A variable the resolver has no value for
+    Unresolved(String),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unterminated => write!(f, "unterminated `${{` (missing `}}`)"),
+            Self::InvalidName(name) => write!(f, "invalid variable name `${{{}}}`", name),
+            Self::Unresolved(name) => write!(f, "unknown variable `${{{}}}`", name),
+        }
+    }
+}
+
+/// Replace every `${name}` in `text` with `resolve(name)`. Text without a `$` is returned as is.
+pub fn expand_template(
+    text: &str,
+    mut resolve: impl FnMut(&str) -> Option<String>,
+) -> Result<String, TemplateError> {
+    if !text.contains('$') {
+        return Ok(text.to_string());
+    }
+
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        if let Some(escaped) = after.strip_prefix("${") {
+            expanded.push_str("${");
+            rest = escaped;
+        } else if let Some(placeholder) = after.strip_prefix('{') {
+            let end = placeholder.find('}').ok_or(TemplateError::Unterminated)?;
+            let name = &placeholder[..end];
+            let valid = !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+            if !valid {
+                return Err(TemplateError::InvalidName(name.to_string()));
+            }
+            let value = resolve(name).ok_or_else(|| TemplateError::Unresolved(name.to_string()))?;
+            expanded.push_str(&value);
+            rest = &placeholder[end + 1..];
+        } else {
+            // A lone `$` (e.g. `$env:TEMP` in a PowerShell command) is plain text
+            expanded.push('$');
+            rest = after;
+        }
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Expand every string inside a JSON value in place
+pub fn expand_json_strings(
+    value: &mut serde_json::Value,
+    resolve: &mut impl FnMut(&str) -> Option<String>,
+) -> Result<(), TemplateError> {
+    match value {
+        serde_json::Value::String(text) => {
+            if text.contains("${") {
+                *text = expand_template(text, &mut *resolve)?;
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                expand_json_strings(item, resolve)?;
+            }
+        }
+        serde_json::Value::Object(fields) => {
+            for field in fields.values_mut() {
+                expand_json_strings(field, resolve)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(name: &str) -> Option<String> {
+        match name {
+            "username" => Some("alice".to_string()),
+            "sid" => Some("S-1-5-21-1-2-3-1001".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn placeholders_are_replaced_and_other_text_is_kept() {
+        assert_eq!(
+            expand_template("C:\\Users\\${username}\\AppData", resolve).unwrap(),
+            "C:\\Users\\alice\\AppData"
+        );
+        assert_eq!(
+            expand_template("${sid}\\Software\\${username}", resolve).unwrap(),
+            "S-1-5-21-1-2-3-1001\\Software\\alice"
+        );
+        assert_eq!(
+            expand_template("no variables", resolve).unwrap(),
+            "no variables"
+        );
+    }
+
+    #[test]
+    fn a_lone_dollar_and_the_escape_are_literal() {
+        assert_eq!(
+            expand_template("Remove-Item $env:TEMP\\x", resolve).unwrap(),
+            "Remove-Item $env:TEMP\\x"
+        );
+        assert_eq!(
+            expand_template("$${username} is ${username}", resolve).unwrap(),
+            "${username} is alice"
+        );
+    }
+
+    #[test]
+    fn malformed_and_unknown_placeholders_are_errors() {
+        assert_eq!(
+            expand_template("${username", resolve),
+            Err(TemplateError::Unterminated)
+        );
+        assert_eq!(
+            expand_template("${}", resolve),
+            Err(TemplateError::InvalidName(String::new()))
+        );
+        assert_eq!(
+            expand_template("${User Name}", resolve),
+            Err(TemplateError::InvalidName("User Name".to_string()))
+        );
+        assert_eq!(
+            expand_template("${gpu}", resolve),
+            Err(TemplateError::Unresolved("gpu".to_string()))
+        );
+    }
+
+    #[test]
+    fn strings_nested_in_json_are_expanded() {
+        let mut value = serde_json::json!({
+            "key": "Software\\${username}",
+            "items": ["${sid}", 1, null],
+        });
+        expand_json_strings(&mut value, &mut resolve).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "key": "Software\\alice",
+                "items": ["S-1-5-21-1-2-3-1001", 1, null],
+            })
+        );
+    }
+}
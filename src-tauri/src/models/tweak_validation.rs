@@ -0,0 +1,3388 @@
+//! The tweak YAML format as authored, and the validation every tweak file passes.
+//!
+//! build.rs includes this file via `#[path]` to check `tweaks/*.yaml` before compiling them in, and
+//! `tweak_loader` uses it on the user's `tweaks.d` files at startup, so a custom tweak is held to
+//! exactly the rules of a compiled one. Both validate a parsed file with
+//! `ValidationContext::validate_file` and convert its tweaks with
+//! `TweakDefinitionRaw::into_definition`.
+//!
+//! Errors reject the tweaks: they fail the build, or leave a `tweaks.d` file unloaded. Warnings
+//! are only reported.
+
+use super::tweak_schema::*;
+use super::tweak_template::{expand_json_strings, is_template_variable, TEMPLATE_VARIABLES};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// Raw tweak definition as loaded from YAML
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct TweakDefinitionRaw {
+    /// Unique snake_case ID
+    id: String,
+    name: String,
+    description: String,
+    /// Longer explanation shown in the tweak's details
+    #[serde(default)]
+    info: Option<String>,
+    risk_level: RiskLevel,
+    /// If true, the app must run as administrator (inferred for HKLM and other system changes)
+    #[serde(default)]
+    requires_admin: bool,
+    /// If true, run as SYSTEM
+    #[serde(default)]
+    requires_system: bool,
+    /// If true, run as TrustedInstaller (for protected services like WaaSMedicSvc)
+    #[serde(default)]
+    requires_ti: bool,
+    /// If true, every option takes effect after a restart
+    #[serde(default)]
+    requires_reboot: bool,
+    /// Show a dropdown even with only 2 options
+    #[serde(default)]
+    force_dropdown: bool,
+    /// The states the tweak can be switched between; at least 2
+    options: Vec<TweakOption>,
+    /// IDs of tweaks that change some of the same settings to other values on purpose. Without
+    /// this, two tweaks changing one setting differently is an error.
+    #[serde(default)]
+    overlaps_with: Vec<String>,
+    /// Kept so it can be reverted, but no longer recommended
+    #[serde(default)]
+    deprecated: bool,
+    /// The tweak to use instead; needs `deprecated`
+    #[serde(default)]
+    superseded_by: Option<String>,
+    /// Earlier IDs of the tweak, so snapshots stored under them still find it after a rename
+    #[serde(default)]
+    aliases: Vec<String>,
+    /// Only offered from this build on (`22631`, or `"22631.2428"` from that cumulative update on)
+    #[serde(default)]
+    min_build: Option<WindowsBuild>,
+    /// Only offered up to this build; without a revision, up to every revision of it
+    #[serde(default)]
+    max_build: Option<WindowsBuild>,
+    /// Only offered on these editions
+    #[serde(default)]
+    editions: Option<Vec<WindowsEdition>>,
+    /// Groups across categories the tweak belongs to, each one of the declared tweak tags
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// YAML file structure with category and tweaks
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct TweakFile {
+    pub(crate) category: CategoryDefinition,
+    pub(crate) tweaks: Vec<TweakDefinitionRaw>,
+}
+
+// ============================================================================
+// Validation Engine
+// ============================================================================
+// Type-driven validation that catches errors before a tweak is offered.
+// Adding new fields to structs automatically gets basic validation via serde.
+// Custom rules here validate semantic correctness beyond type checking.
+
+/// Validation context tracking state across multiple files
+pub(crate) struct ValidationContext {
+    /// All tweak IDs seen so far (for duplicate detection)
+    pub(crate) seen_tweak_ids: HashSet<String>,
+    /// All category IDs seen (with file name for error reporting)
+    pub(crate) seen_category_ids: HashMap<String, String>,
+    /// Collected validation errors (fatal - the tweaks are rejected)
+    pub(crate) errors: Vec<String>,
+    /// Collected warnings (non-fatal - just report)
+    pub(crate) warnings: Vec<String>,
+    /// The tweaks changing each setting seen so far, by `target_changes` key (for conflicts)
+    targets: HashMap<String, Vec<TargetChange>>,
+    /// `overlaps_with` pairs that allowed a conflict (for finding stale entries)
+    used_overlaps: HashSet<(String, String)>,
+    /// Each tweak's `overlaps_with`, with its file
+    declared_overlaps: Vec<(String, String, String)>,
+    /// Each `superseded_by`, with its file and tweak (checked once every tweak is seen)
+    superseded: Vec<(String, String, String)>,
+}
+
+/// The IDs and settings a `ValidationContext` has seen, to put back with `restore_claims` when a
+/// file is rejected
+#[allow(dead_code)]
+pub(crate) struct Claims {
+    seen_tweak_ids: HashSet<String>,
+    seen_category_ids: HashMap<String, String>,
+    targets: HashMap<String, Vec<TargetChange>>,
+    declared_overlaps: Vec<(String, String, String)>,
+}
+
+/// One tweak's changes to one setting
+#[derive(Debug, Clone)]
+struct TargetChange {
+    tweak_id: String,
+    /// The setting as the tweak spells it, for messages
+    label: String,
+    /// What its options set the setting to, as text
+    values: BTreeSet<String>,
+}
+
+impl ValidationContext {
+    pub(crate) fn new() -> Self {
+        Self {
+            seen_tweak_ids: HashSet::new(),
+            seen_category_ids: HashMap::new(),
+            errors: Vec::new(),
+            warnings: Vec::new(),
+            targets: HashMap::new(),
+            used_overlaps: HashSet::new(),
+            declared_overlaps: Vec::new(),
+            superseded: Vec::new(),
+        }
+    }
+
+    /// Record the settings `tweak_id` changes, with an error for each earlier tweak changing one
+    /// of them to a different value unless either lists the other in `overlaps_with`
+    fn check_conflicts(
+        &mut self,
+        file: &str,
+        tweak_id: &str,
+        options: &[TweakOption],
+        overlaps_with: &[String],
+    ) {
+        for id in overlaps_with {
+            self.declared_overlaps
+                .push((file.to_string(), tweak_id.to_string(), id.clone()));
+        }
+        // One error per conflicting tweak, naming the first setting they share
+        let mut conflicts: Vec<(String, String)> = Vec::new();
+        for (key, change) in target_changes(tweak_id, options) {
+            for other in self.targets.get(&key).into_iter().flatten() {
+                if other.tweak_id == tweak_id || other.values.union(&change.values).count() < 2 {
+                    continue;
+                }
+                let pair = overlap_pair(tweak_id, &other.tweak_id);
+                let declared = self
+                    .declared_overlaps
+                    .iter()
+                    .any(|(_, owner, id)| overlap_pair(owner, id) == pair);
+                if declared {
+                    self.used_overlaps.insert(pair);
+                } else if !conflicts.iter().any(|(id, _)| *id == other.tweak_id) {
+                    conflicts.push((other.tweak_id.clone(), change.label.clone()));
+                }
+            }
+            self.targets.entry(key).or_default().push(change);
+        }
+        for (other, label) in conflicts {
+            self.tweak_error(
+                file,
+                tweak_id,
+                format!(
+                    "changes {} to other values than tweak '{}' does, so each one's state would follow whichever was applied last; list '{}' in overlaps_with if that's intended",
+                    label, other, other
+                ),
+            );
+        }
+    }
+
+    /// Check what refers to other tweaks: a `superseded_by` has to name one, and an `overlaps_with`
+    /// entry that allowed no conflict is warned about. Call once every file is validated.
+    pub(crate) fn check_references(&mut self) {
+        for (file, tweak_id, target) in std::mem::take(&mut self.superseded) {
+            if !self.seen_tweak_ids.contains(&target) {
+                self.tweak_error(
+                    &file,
+                    &tweak_id,
+                    format!("superseded_by names '{}', which isn't a tweak", target),
+                );
+            }
+        }
+
+        let unused: Vec<_> = self
+            .declared_overlaps
+            .iter()
+            .filter(|(_, owner, id)| !self.used_overlaps.contains(&overlap_pair(owner, id)))
+            .cloned()
+            .collect();
+        for (file, owner, id) in unused {
+            self.tweak_warning(
+                &file,
+                &owner,
+                format!(
+                    "overlaps_with lists '{}', but the two don't change any setting to different values",
+                    id
+                ),
+            );
+        }
+    }
+
+    /// Check if there are any errors
+    pub(crate) fn has_errors(&self) -> bool {
+        !self.errors.is_empty()
+    }
+}
+
+/// For the runtime loader, which checks custom tweaks against the compiled ones and leaves out a
+/// rejected file's tweaks. build.rs only validates the compiled files.
+#[allow(dead_code)]
+impl ValidationContext {
+    /// What has been seen so far
+    pub(crate) fn claims(&self) -> Claims {
+        Claims {
+            seen_tweak_ids: self.seen_tweak_ids.clone(),
+            seen_category_ids: self.seen_category_ids.clone(),
+            targets: self.targets.clone(),
+            declared_overlaps: self.declared_overlaps.clone(),
+        }
+    }
+
+    /// Forget what was seen after `claims` was taken
+    pub(crate) fn restore_claims(&mut self, claims: Claims) {
+        self.seen_tweak_ids = claims.seen_tweak_ids;
+        self.seen_category_ids = claims.seen_category_ids;
+        self.targets = claims.targets;
+        self.declared_overlaps = claims.declared_overlaps;
+    }
+
+    /// Claim the ID and settings of an already validated tweak (a compiled one, when checking
+    /// custom tweaks), so later tweaks are checked against it
+    pub(crate) fn claim_tweak(&mut self, tweak: &TweakDefinition) {
+        self.seen_tweak_ids.insert(tweak.id.clone());
+        self.seen_tweak_ids.extend(tweak.aliases.iter().cloned());
+        for (key, change) in target_changes(&tweak.id, &tweak.options) {
+            self.targets.entry(key).or_default().push(change);
+        }
+    }
+}
+
+/// Check a `min_build`/`max_build` pair: Windows 10 or later builds, in order. `fields` is what
+/// the field names are prefixed with in messages (e.g. "option 'On': conditions.").
+fn validate_build_range(
+    ctx: &mut ValidationContext,
+    file: &str,
+    tweak_id: &str,
+    fields: &str,
+    min: Option<WindowsBuild>,
+    max: Option<WindowsBuild>,
+) {
+    for (name, bound) in [("min_build", min), ("max_build", max)] {
+        if bound.is_some_and(|bound| bound.build < FIRST_WINDOWS_10_BUILD) {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!(
+                    "{}{} must be a Windows 10 or later build ({} or higher)",
+                    fields, name, FIRST_WINDOWS_10_BUILD
+                ),
+            );
+        }
+    }
+    if let (Some(min), Some(max)) = (min, max) {
+        let (first, last) = (
+            (min.build, min.revision.unwrap_or(0)),
+            (max.build, max.revision.unwrap_or(u32::MAX)),
+        );
+        if first > last {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!(
+                    "{0}min_build is after {0}max_build (it would never match)",
+                    fields
+                ),
+            );
+        }
+    }
+}
+
+/// Validate tweak ID format (snake_case convention)
+fn is_valid_tweak_id(id: &str) -> bool {
+    if id.is_empty() {
+        return false;
+    }
+    // Must start with lowercase letter or underscore
+    let mut chars = id.chars();
+    let first = chars.next().unwrap();
+    if !first.is_ascii_lowercase() && first != '_' {
+        return false;
+    }
+    // Rest must be lowercase, digits, or underscore
+    chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// Valid Windows versions for filtering
+const VALID_WINDOWS_VERSIONS: &[u32] = &[10, 11];
+
+/// Tags a tweak may carry, with what each groups. Tags build views across categories
+/// (`get_tweaks_by_tag`), so a new one is declared here rather than made up by one tweak.
+pub(crate) const TWEAK_TAGS: &[(&str, &str)] = &[
+    (
+        "telemetry",
+        "Stops diagnostic, usage, or error data being sent to Microsoft",
+    ),
+    ("privacy", "Limits what Windows records about you or shares"),
+    ("ads", "Removes ads, suggestions, and promoted content"),
+    ("ai", "Turns off AI features such as Copilot and Recall"),
+    (
+        "gaming",
+        "Affects game latency, frame pacing, or game features",
+    ),
+    ("battery", "Affects battery life or power draw"),
+    ("visuals", "Changes how Windows looks or animates"),
+];
+
+/// Whether `tag` is one of [`TWEAK_TAGS`]
+pub(crate) fn is_tweak_tag(tag: &str) -> bool {
+    TWEAK_TAGS.iter().any(|(known, _)| *known == tag)
+}
+
+/// Windows 10's first release; no build the app runs on is older
+const FIRST_WINDOWS_10_BUILD: u32 = 10240;
+
+/// Power scheme aliases accepted in place of a scheme GUID (mirrors `power_service`)
+const POWER_SCHEME_ALIASES: &[&str] = &[
+    "SCHEME_MIN",
+    "SCHEME_MAX",
+    "SCHEME_BALANCED",
+    "SCHEME_CURRENT",
+];
+
+/// The 32 hex digits of a GUID (`xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx`, braces optional), or None
+/// if it's malformed. `power_service::parse_guid` reads GUIDs through it, so the runtime parses
+/// exactly the GUIDs validation accepts.
+pub(crate) fn guid_digits(value: &str) -> Option<String> {
+    let trimmed = value.trim().trim_start_matches('{').trim_end_matches('}');
+    let groups: Vec<&str> = trimmed.split('-').collect();
+    let well_formed = groups.len() == 5
+        && groups
+            .iter()
+            .zip([8, 4, 4, 4, 12])
+            .all(|(group, len)| group.len() == len && group.chars().all(|c| c.is_ascii_hexdigit()));
+    well_formed.then(|| groups.concat())
+}
+
+/// Validate GUID format (see [`guid_digits`])
+fn is_valid_guid(value: &str) -> bool {
+    guid_digits(value).is_some()
+}
+
+/// Well-known BCD entry identifiers accepted in place of a `{GUID}`
+const BCD_WELL_KNOWN_IDENTIFIERS: &[&str] = &[
+    "{current}",
+    "{default}",
+    "{bootmgr}",
+    "{fwbootmgr}",
+    "{globalsettings}",
+    "{bootloadersettings}",
+    "{resumeloadersettings}",
+    "{hypervisorsettings}",
+    "{dbgsettings}",
+    "{emssettings}",
+    "{badmemory}",
+    "{memdiag}",
+];
+
+impl Conditions {
+    /// Validate that every guard that is set can actually hold
+    fn validate(&self, ctx: &mut ValidationContext, file: &str, tweak_id: &str, location: &str) {
+        if *self == Conditions::default() {
+            ctx.tweak_warning(
+                file,
+                tweak_id,
+                format!("{}: conditions has no guards set; remove it", location),
+            );
+        }
+
+        if self
+            .edition
+            .as_ref()
+            .is_some_and(|editions| editions.is_empty())
+        {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!(
+                    "{}: conditions.edition cannot be empty (it would never match); omit it to allow every edition",
+                    location
+                ),
+            );
+        }
+
+        if self.min_ram_gb == Some(0) {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!("{}: conditions.min_ram_gb must be greater than 0", location),
+            );
+        }
+
+        validate_build_range(
+            ctx,
+            file,
+            tweak_id,
+            &format!("{}: conditions.", location),
+            self.min_build,
+            self.max_build,
+        );
+    }
+}
+
+impl RegistryChange {
+    /// Whether this change writes the `UserPreferencesMask` blob that `visual_effects_changes`
+    /// manages bit by bit
+    fn writes_preferences_mask(&self) -> bool {
+        matches!(self.hive, RegistryHive::Hkcu | RegistryHive::AllUsers)
+            && self
+                .key
+                .trim_matches('\\')
+                .eq_ignore_ascii_case("Control Panel\\Desktop")
+            && self.value_name.eq_ignore_ascii_case("UserPreferencesMask")
+    }
+
+    /// Validate registry change semantic correctness
+    fn validate(
+        &self,
+        ctx: &mut ValidationContext,
+        file: &str,
+        tweak_id: &str,
+        option_label: &str,
+    ) {
+        let location = format!(
+            "option '{}' registry change '{}'",
+            option_label, self.value_name
+        );
+
+        // Validate key is not empty
+        if self.key.trim().is_empty() {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!("{}: registry key cannot be empty", location),
+            );
+        } else {
+            self.validate_hive_key(ctx, file, tweak_id, &location);
+        }
+
+        // Action-specific validation
+        match self.action {
+            RegistryAction::Set => {
+                // Set action requires value_type and value
+                if self.value_type.is_none() {
+                    ctx.tweak_error(
+                        file,
+                        tweak_id,
+                        format!("{}: 'set' action requires value_type", location),
+                    );
+                }
+                if self.value.is_none() {
+                    ctx.tweak_error(
+                        file,
+                        tweak_id,
+                        format!("{}: 'set' action requires value", location),
+                    );
+                }
+                // Validate value_name (empty string targets default value)
+                if self.value_name.is_empty() {
+                    ctx.tweak_warning(
+                        file,
+                        tweak_id,
+                        format!(
+                            "{}: value_name is empty (targeting default value)",
+                            location
+                        ),
+                    );
+                } else if self.value_name.trim().is_empty() {
+                    ctx.tweak_error(
+                        file,
+                        tweak_id,
+                        format!(
+                            "{}: value_name is whitespace-only (use empty string for default value)",
+                            location
+                        ),
+                    );
+                }
+                // Validate value matches value_type (if both present)
+                if self.value_type.is_some() && self.value.is_some() {
+                    self.validate_value_type(ctx, file, tweak_id, &location);
+                }
+            }
+            RegistryAction::DeleteValue => {
+                // DeleteValue requires value_name
+                if self.value_name.is_empty() {
+                    ctx.tweak_error(
+                        file,
+                        tweak_id,
+                        format!("{}: 'delete_value' action requires value_name", location),
+                    );
+                } else if self.value_name.trim().is_empty() {
+                    ctx.tweak_error(
+                        file,
+                        tweak_id,
+                        format!("{}: value_name is whitespace-only", location),
+                    );
+                }
+                // Warn if value_type/value are provided (they're ignored)
+                if self.value_type.is_some() || self.value.is_some() {
+                    ctx.tweak_warning(
+                        file,
+                        tweak_id,
+                        format!(
+                            "{}: value_type and value are ignored for 'delete_value' action",
+                            location
+                        ),
+                    );
+                }
+            }
+            RegistryAction::DeleteKey => {
+                // DeleteKey only needs the key path
+                // Warn if value_name/value_type/value are provided
+                if !self.value_name.is_empty() {
+                    ctx.tweak_warning(
+                        file,
+                        tweak_id,
+                        format!(
+                            "{}: value_name is ignored for 'delete_key' action",
+                            location
+                        ),
+                    );
+                }
+                if self.value_type.is_some() || self.value.is_some() {
+                    ctx.tweak_warning(
+                        file,
+                        tweak_id,
+                        format!(
+                            "{}: value_type and value are ignored for 'delete_key' action",
+                            location
+                        ),
+                    );
+                }
+            }
+            RegistryAction::CreateKey => {
+                // CreateKey only needs the key path
+                // Warn if value_name/value_type/value are provided
+                if !self.value_name.is_empty() {
+                    ctx.tweak_warning(
+                        file,
+                        tweak_id,
+                        format!(
+                            "{}: value_name is ignored for 'create_key' action",
+                            location
+                        ),
+                    );
+                }
+                if self.value_type.is_some() || self.value.is_some() {
+                    ctx.tweak_warning(
+                        file,
+                        tweak_id,
+                        format!(
+                            "{}: value_type and value are ignored for 'create_key' action",
+                            location
+                        ),
+                    );
+                }
+            }
+        }
+
+        // Validate Windows versions (applies to all actions)
+        if let Some(versions) = &self.windows_versions {
+            for v in versions {
+                if !VALID_WINDOWS_VERSIONS.contains(v) {
+                    ctx.tweak_error(
+                        file,
+                        tweak_id,
+                        format!(
+                            "{}: invalid windows_version {}, must be one of {:?}",
+                            location, v, VALID_WINDOWS_VERSIONS
+                        ),
+                    );
+                }
+            }
+        }
+
+        if let Some(conditions) = &self.conditions {
+            conditions.validate(ctx, file, tweak_id, &location);
+        }
+    }
+
+    /// Check if this registry change targets a hive other than HKCU (requires admin)
+    fn requires_admin(&self) -> bool {
+        !matches!(self.hive, RegistryHive::Hkcu)
+    }
+
+    /// Validate the key against its hive: HKU keys start with a user hive root, ALL_USERS keys
+    /// are relative to each user's hive
+    fn validate_hive_key(
+        &self,
+        ctx: &mut ValidationContext,
+        file: &str,
+        tweak_id: &str,
+        location: &str,
+    ) {
+        let mut segments = self.key.trim_matches('\\').split('\\');
+        let first = segments.next().unwrap_or("");
+        let is_user_root = first.eq_ignore_ascii_case(".DEFAULT")
+            || first.starts_with("S-1-")
+            || first == "${sid}";
+
+        match self.hive {
+            RegistryHive::Hku if !is_user_root => ctx.tweak_error(
+                file,
+                tweak_id,
+                format!(
+                    "{}: HKU key must start with a user SID or .DEFAULT (use ALL_USERS to target every user)",
+                    location
+                ),
+            ),
+            // Deleting or replacing a whole user hive root is never what a tweak means
+            RegistryHive::Hku if segments.next().is_none() => ctx.tweak_error(
+                file,
+                tweak_id,
+                format!(
+                    "{}: HKU key must name a key inside the user hive, not the hive root",
+                    location
+                ),
+            ),
+            RegistryHive::AllUsers if is_user_root => ctx.tweak_error(
+                file,
+                tweak_id,
+                format!(
+                    "{}: ALL_USERS key is relative to each user's hive and must not start with a SID or .DEFAULT",
+                    location
+                ),
+            ),
+            _ => {}
+        }
+    }
+
+    /// Validate that the value matches the declared value_type
+    fn validate_value_type(
+        &self,
+        ctx: &mut ValidationContext,
+        file: &str,
+        tweak_id: &str,
+        location: &str,
+    ) {
+        let value_type = match &self.value_type {
+            Some(vt) => vt,
+            None => return,
+        };
+        let value = match &self.value {
+            Some(v) => v,
+            None => return,
+        };
+
+        match value_type {
+            RegistryValueType::Dword => {
+                if !value.is_u64() && !value.is_i64() {
+                    ctx.tweak_error(
+                        file,
+                        tweak_id,
+                        format!(
+                            "{}: REG_DWORD requires integer value, got {}",
+                            location,
+                            value_type_name(value)
+                        ),
+                    );
+                } else if let Some(n) = value.as_u64() {
+                    if n > u32::MAX as u64 {
+                        ctx.tweak_error(
+                            file,
+                            tweak_id,
+                            format!(
+                                "{}: REG_DWORD value {} exceeds u32::MAX ({})",
+                                location,
+                                n,
+                                u32::MAX
+                            ),
+                        );
+                    }
+                } else if let Some(n) = value.as_i64() {
+                    if n < 0 || n > u32::MAX as i64 {
+                        ctx.tweak_error(
+                            file,
+                            tweak_id,
+                            format!(
+                                "{}: REG_DWORD value {} out of range (0..{})",
+                                location,
+                                n,
+                                u32::MAX
+                            ),
+                        );
+                    }
+                }
+            }
+            RegistryValueType::Qword => {
+                if !value.is_u64() && !value.is_i64() {
+                    ctx.tweak_error(
+                        file,
+                        tweak_id,
+                        format!(
+                            "{}: REG_QWORD requires integer value, got {}",
+                            location,
+                            value_type_name(value)
+                        ),
+                    );
+                } else if let Some(n) = value.as_i64() {
+                    // REG_QWORD is unsigned (0 to u64::MAX), negative values are invalid
+                    if n < 0 {
+                        ctx.tweak_error(
+                            file,
+                            tweak_id,
+                            format!(
+                                "{}: REG_QWORD value {} is negative; must be in range 0..{}",
+                                location,
+                                n,
+                                u64::MAX
+                            ),
+                        );
+                    }
+                }
+            }
+            RegistryValueType::String | RegistryValueType::ExpandString => {
+                if !value.is_string() {
+                    ctx.tweak_error(
+                        file,
+                        tweak_id,
+                        format!(
+                            "{}: {} requires string value, got {}",
+                            location,
+                            if matches!(value_type, RegistryValueType::String) {
+                                "REG_SZ"
+                            } else {
+                                "REG_EXPAND_SZ"
+                            },
+                            value_type_name(value)
+                        ),
+                    );
+                }
+            }
+            RegistryValueType::MultiString => {
+                if !value.is_array() {
+                    ctx.tweak_error(
+                        file,
+                        tweak_id,
+                        format!(
+                            "{}: REG_MULTI_SZ requires array of strings, got {}",
+                            location,
+                            value_type_name(value)
+                        ),
+                    );
+                } else if let Some(arr) = value.as_array() {
+                    for (i, item) in arr.iter().enumerate() {
+                        match item.as_str() {
+                            None => ctx.tweak_error(
+                                file,
+                                tweak_id,
+                                format!(
+                                    "{}: REG_MULTI_SZ array item [{}] must be string, got {}",
+                                    location,
+                                    i,
+                                    value_type_name(item)
+                                ),
+                            ),
+                            // An empty string ends the list in REG_MULTI_SZ data, so it can't be stored
+                            Some(s) if s.is_empty() || s.contains('\0') => ctx.tweak_error(
+                                file,
+                                tweak_id,
+                                format!(
+                                    "{}: REG_MULTI_SZ array item [{}] must be non-empty and contain no NUL characters",
+                                    location, i
+                                ),
+                            ),
+                            Some(_) => {}
+                        }
+                    }
+                }
+            }
+            RegistryValueType::Binary => {
+                // Binary can be array of integers (bytes) or hex string
+                if value.is_array() {
+                    if let Some(arr) = value.as_array() {
+                        for (i, item) in arr.iter().enumerate() {
+                            if let Some(n) = item.as_u64() {
+                                if n > 255 {
+                                    ctx.tweak_error(
+                                        file,
+                                        tweak_id,
+                                        format!(
+                                            "{}: REG_BINARY array item [{}] value {} exceeds byte range (0-255)",
+                                            location, i, n
+                                        ),
+                                    );
+                                }
+                            } else {
+                                ctx.tweak_error(
+                                    file,
+                                    tweak_id,
+                                    format!(
+                                        "{}: REG_BINARY array item [{}] must be integer (0-255), got {}",
+                                        location,
+                                        i,
+                                        value_type_name(item)
+                                    ),
+                                );
+                            }
+                        }
+                    }
+                } else if !value.is_string() {
+                    ctx.tweak_error(
+                        file,
+                        tweak_id,
+                        format!(
+                            "{}: REG_BINARY requires array of bytes or hex string, got {}",
+                            location,
+                            value_type_name(value)
+                        ),
+                    );
+                }
+            }
+        }
+    }
+}
+
+impl ServiceChange {
+    /// Validate service change semantic correctness
+    fn validate(
+        &self,
+        ctx: &mut ValidationContext,
+        file: &str,
+        tweak_id: &str,
+        option_label: &str,
+    ) {
+        let location = format!("option '{}' service change", option_label);
+
+        // Validate service name is not empty
+        if self.name.trim().is_empty() {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!("{}: service name cannot be empty", location),
+            );
+        }
+    }
+}
+
+impl SchedulerChange {
+    /// Validate scheduler change semantic correctness
+    fn validate(
+        &self,
+        ctx: &mut ValidationContext,
+        file: &str,
+        tweak_id: &str,
+        option_label: &str,
+    ) {
+        let location = format!("option '{}' scheduler change", option_label);
+
+        // Validate task_path is not empty
+        if self.task_path.trim().is_empty() {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!("{}: task_path cannot be empty", location),
+            );
+        }
+
+        // Validate mutual exclusivity: task_name XOR task_name_pattern
+        match (&self.task_name, &self.task_name_pattern) {
+            (None, None) => {
+                ctx.tweak_error(
+                    file,
+                    tweak_id,
+                    format!(
+                        "{}: must specify either 'task_name' or 'task_name_pattern'",
+                        location
+                    ),
+                );
+            }
+            (Some(_), Some(_)) => {
+                ctx.tweak_error(
+                    file,
+                    tweak_id,
+                    format!(
+                        "{}: cannot specify both 'task_name' and 'task_name_pattern' (mutually exclusive)",
+                        location
+                    ),
+                );
+            }
+            (Some(name), None) => {
+                // Validate task_name is not empty
+                if name.trim().is_empty() {
+                    ctx.tweak_error(
+                        file,
+                        tweak_id,
+                        format!("{}: task_name cannot be empty", location),
+                    );
+                }
+            }
+            (None, Some(pattern)) => {
+                // Validate task_name_pattern is not empty
+                if pattern.trim().is_empty() {
+                    ctx.tweak_error(
+                        file,
+                        tweak_id,
+                        format!("{}: task_name_pattern cannot be empty", location),
+                    );
+                }
+                // Validate task_name_pattern is valid regex
+                else if let Err(regex_err) = regex_lite::Regex::new(pattern) {
+                    ctx.tweak_error(
+                        file,
+                        tweak_id,
+                        format!(
+                            "{}: invalid regex pattern '{}' in task_name_pattern: {}",
+                            location, pattern, regex_err
+                        ),
+                    );
+                }
+            }
+        }
+    }
+}
+
+impl HostsChange {
+    /// Validate hosts change semantic correctness
+    fn validate(
+        &self,
+        ctx: &mut ValidationContext,
+        file: &str,
+        tweak_id: &str,
+        option_label: &str,
+    ) {
+        let location = format!("option '{}' hosts change", option_label);
+
+        if self.ip.trim().is_empty() {
+            ctx.tweak_error(file, tweak_id, format!("{}: ip cannot be empty", location));
+        }
+        if self.domain.trim().is_empty() {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!("{}: domain cannot be empty", location),
+            );
+        }
+    }
+}
+
+impl FirewallChange {
+    /// Validate firewall change semantic correctness
+    fn validate(
+        &self,
+        ctx: &mut ValidationContext,
+        file: &str,
+        tweak_id: &str,
+        option_label: &str,
+    ) {
+        let location = format!("option '{}' firewall change '{}'", option_label, self.name);
+
+        if self.name.trim().is_empty() {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!("{}: firewall rule name cannot be empty", location),
+            );
+        }
+
+        // Create operation requires direction and action
+        if matches!(self.operation, FirewallOperation::Create) {
+            if self.direction.is_none() {
+                ctx.tweak_error(
+                    file,
+                    tweak_id,
+                    format!("{}: 'create' operation requires 'direction'", location),
+                );
+            }
+            if self.action.is_none() {
+                ctx.tweak_error(
+                    file,
+                    tweak_id,
+                    format!("{}: 'create' operation requires 'action'", location),
+                );
+            }
+        }
+    }
+}
+
+impl PowerPlanChange {
+    /// Validate power plan change semantic correctness
+    fn validate(
+        &self,
+        ctx: &mut ValidationContext,
+        file: &str,
+        tweak_id: &str,
+        option_label: &str,
+    ) {
+        let location = format!(
+            "option '{}' power plan change '{}'",
+            option_label,
+            self.setting
+                .as_deref()
+                .or(self.scheme.as_deref())
+                .unwrap_or("?")
+        );
+
+        if let Some(scheme) = &self.scheme {
+            let is_alias = POWER_SCHEME_ALIASES
+                .iter()
+                .any(|alias| alias.eq_ignore_ascii_case(scheme.trim()));
+            if !is_alias && !is_valid_guid(scheme) {
+                ctx.tweak_error(
+                    file,
+                    tweak_id,
+                    format!(
+                        "{}: scheme '{}' is neither a GUID nor one of {:?}",
+                        location, scheme, POWER_SCHEME_ALIASES
+                    ),
+                );
+            }
+        }
+
+        match self.action {
+            PowerPlanAction::SetActiveScheme => {
+                match &self.scheme {
+                    None => ctx.tweak_error(
+                        file,
+                        tweak_id,
+                        format!("{}: 'set_active_scheme' requires 'scheme'", location),
+                    ),
+                    Some(scheme) if scheme.trim().eq_ignore_ascii_case("SCHEME_CURRENT") => ctx
+                        .tweak_error(
+                            file,
+                            tweak_id,
+                            format!(
+                                "{}: 'set_active_scheme' to SCHEME_CURRENT is a no-op",
+                                location
+                            ),
+                        ),
+                    Some(_) => {}
+                }
+                if self.subgroup.is_some()
+                    || self.setting.is_some()
+                    || self.ac_value.is_some()
+                    || self.dc_value.is_some()
+                {
+                    ctx.tweak_error(
+                        file,
+                        tweak_id,
+                        format!(
+                            "{}: 'set_active_scheme' takes only 'scheme' (subgroup/setting/ac_value/dc_value are for 'set_setting_value')",
+                            location
+                        ),
+                    );
+                }
+            }
+            PowerPlanAction::SetSettingValue => {
+                for (field, value) in [("subgroup", &self.subgroup), ("setting", &self.setting)] {
+                    match value {
+                        None => ctx.tweak_error(
+                            file,
+                            tweak_id,
+                            format!("{}: 'set_setting_value' requires '{}'", location, field),
+                        ),
+                        Some(guid) if !is_valid_guid(guid) => ctx.tweak_error(
+                            file,
+                            tweak_id,
+                            format!("{}: {} '{}' is not a valid GUID", location, field, guid),
+                        ),
+                        Some(_) => {}
+                    }
+                }
+                if self.ac_value.is_none() && self.dc_value.is_none() {
+                    ctx.tweak_error(
+                        file,
+                        tweak_id,
+                        format!(
+                            "{}: 'set_setting_value' requires 'ac_value' and/or 'dc_value'",
+                            location
+                        ),
+                    );
+                }
+            }
+        }
+    }
+}
+
+impl BcdeditChange {
+    /// Validate BCD change semantic correctness
+    fn validate(
+        &self,
+        ctx: &mut ValidationContext,
+        file: &str,
+        tweak_id: &str,
+        option_label: &str,
+    ) {
+        let location = format!("option '{}' BCD change '{}'", option_label, self.element);
+
+        if let Some(identifier) = &self.identifier {
+            let is_well_known = BCD_WELL_KNOWN_IDENTIFIERS
+                .iter()
+                .any(|known| known.eq_ignore_ascii_case(identifier.trim()));
+            let is_guid = identifier.starts_with('{')
+                && identifier.ends_with('}')
+                && is_valid_guid(identifier);
+            if !is_well_known && !is_guid {
+                ctx.tweak_error(
+                    file,
+                    tweak_id,
+                    format!(
+                        "{}: identifier '{}' is neither a {{GUID}} nor one of {:?}",
+                        location, identifier, BCD_WELL_KNOWN_IDENTIFIERS
+                    ),
+                );
+            }
+        }
+
+        // Element names are single alphanumeric words (e.g. useplatformclock); the custom:<hex>
+        // form addresses an element by its raw type.
+        let element_ok = match self.element.split_once(':') {
+            Some((prefix, hex)) => {
+                prefix.eq_ignore_ascii_case("custom")
+                    && !hex.is_empty()
+                    && hex.chars().all(|c| c.is_ascii_hexdigit())
+            }
+            None => {
+                !self.element.is_empty() && self.element.chars().all(|c| c.is_ascii_alphanumeric())
+            }
+        };
+        if !element_ok {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!(
+                    "{}: element must be an alphanumeric name (e.g. 'useplatformclock') or 'custom:<hex>'",
+                    location
+                ),
+            );
+        }
+
+        match self.action {
+            BcdeditAction::Set => match &self.value {
+                None => ctx.tweak_error(
+                    file,
+                    tweak_id,
+                    format!(
+                        "{}: 'set' requires 'value' (use 'action: delete_value' to remove the element)",
+                        location
+                    ),
+                ),
+                Some(value) if value.trim().is_empty() || value.chars().any(char::is_control) => {
+                    ctx.tweak_error(
+                        file,
+                        tweak_id,
+                        format!("{}: value must be non-empty and single-line", location),
+                    )
+                }
+                Some(_) => {}
+            },
+            BcdeditAction::DeleteValue => {
+                if self.value.is_some() {
+                    ctx.tweak_error(
+                        file,
+                        tweak_id,
+                        format!("{}: 'delete_value' does not take a 'value'", location),
+                    );
+                }
+            }
+        }
+    }
+}
+
+impl AppxChange {
+    /// Validate AppX change semantic correctness
+    fn validate(
+        &self,
+        ctx: &mut ValidationContext,
+        file: &str,
+        tweak_id: &str,
+        option_label: &str,
+    ) {
+        let location = format!("option '{}' AppX change '{}'", option_label, self.package);
+
+        // Package names are dotted identifiers (Microsoft.BingNews); `*` is the only wildcard
+        let name_ok = !self.package.is_empty()
+            && self
+                .package
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '*'));
+        if !name_ok {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!(
+                    "{}: package must be a package name (letters, digits, '.', '-', '_', '*')",
+                    location
+                ),
+            );
+        } else if self.package.chars().all(|c| c == '*' || c == '.') {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!("{}: package pattern would match every package", location),
+            );
+        }
+
+        if self.action == AppxAction::Reinstall && self.scope == AppxScope::AllUsers {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!(
+                    "{}: 'reinstall' registers the package for the current user only; remove 'scope: all_users'",
+                    location
+                ),
+            );
+        }
+    }
+}
+
+/// Whether a file change path is absolute: a drive path (`C:\...`) or one rooted at an environment
+/// variable (`%ProgramData%\...`) or a folder template variable (`${local_app_data}\...`).
+/// Relative paths would resolve against the app's working folder.
+fn is_absolute_file_path(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    let drive = bytes.len() >= 3
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && matches!(bytes[2], b'\\' | b'/');
+    let env_rooted = path.starts_with('%') && path[1..].contains('%');
+    let template_rooted = [
+        "${user_profile}",
+        "${app_data}",
+        "${local_app_data}",
+        "${program_files}",
+        "${program_files_x86}",
+        "${program_data}",
+        "${system_root}",
+        "${system_drive}",
+    ]
+    .iter()
+    .any(|root| path.starts_with(root));
+    drive || env_rooted || template_rooted
+}
+
+/// Whether a path names a whole drive (`C:\`), which no file change may copy over, delete, or rename.
+fn is_drive_root(path: &str) -> bool {
+    let trimmed = path.trim_end_matches(['\\', '/']);
+    trimmed.len() == 2 && trimmed.as_bytes()[0].is_ascii_alphabetic() && trimmed.ends_with(':')
+}
+
+impl FileChange {
+    /// Validate file change semantic correctness
+    fn validate(
+        &self,
+        ctx: &mut ValidationContext,
+        file: &str,
+        tweak_id: &str,
+        option_label: &str,
+    ) {
+        let location = format!("option '{}' file change '{}'", option_label, self.path);
+
+        let mut paths = vec![("path", self.path.as_str())];
+        if let Some(destination) = &self.destination {
+            paths.push(("destination", destination.as_str()));
+        }
+        for (field, path) in paths {
+            if !is_absolute_file_path(path) {
+                ctx.tweak_error(
+                    file,
+                    tweak_id,
+                    format!(
+                        "{}: {} must be absolute (C:\\... or %VARIABLE%\\...)",
+                        location, field
+                    ),
+                );
+            } else if is_drive_root(path) {
+                ctx.tweak_error(
+                    file,
+                    tweak_id,
+                    format!("{}: {} cannot be a drive root", location, field),
+                );
+            }
+        }
+
+        let needs_destination = matches!(self.action, FileAction::Copy | FileAction::Rename);
+        if needs_destination && self.destination.is_none() {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!("{}: copy and rename require 'destination'", location),
+            );
+        } else if !needs_destination && self.destination.is_some() {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!(
+                    "{}: 'destination' is only used by copy and rename",
+                    location
+                ),
+            );
+        }
+
+        match (&self.attributes, self.action) {
+            (None, FileAction::SetAttributes) => ctx.tweak_error(
+                file,
+                tweak_id,
+                format!("{}: 'set_attributes' requires 'attributes'", location),
+            ),
+            (Some(attributes), FileAction::SetAttributes) => {
+                if attributes == &FileAttributes::default() {
+                    ctx.tweak_error(
+                        file,
+                        tweak_id,
+                        format!(
+                            "{}: 'attributes' must set at least one of readonly, hidden, system",
+                            location
+                        ),
+                    );
+                }
+            }
+            (Some(_), _) => ctx.tweak_error(
+                file,
+                tweak_id,
+                format!("{}: 'attributes' is only used by set_attributes", location),
+            ),
+            (None, _) => {}
+        }
+
+        match (&self.content, self.action) {
+            (None, FileAction::WriteContent) => ctx.tweak_error(
+                file,
+                tweak_id,
+                format!("{}: 'write_content' requires 'content'", location),
+            ),
+            (Some(_), FileAction::WriteContent) | (None, _) => {}
+            (Some(_), _) => ctx.tweak_error(
+                file,
+                tweak_id,
+                format!("{}: 'content' is only used by write_content", location),
+            ),
+        }
+
+        if self.skip_backup && matches!(self.action, FileAction::Rename | FileAction::SetAttributes)
+        {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!(
+                    "{}: 'skip_backup' only applies to copy, delete, and write_content",
+                    location
+                ),
+            );
+        }
+    }
+}
+
+impl RegistryAclChange {
+    /// Validate registry ACL change semantic correctness
+    fn validate(
+        &self,
+        ctx: &mut ValidationContext,
+        file: &str,
+        tweak_id: &str,
+        option_label: &str,
+    ) {
+        let location = format!(
+            "option '{}' registry ACL change '{}'",
+            option_label, self.key
+        );
+
+        // An empty key would target the hive root
+        if self.key.trim().trim_matches('\\').is_empty() {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!("{}: registry key cannot be empty", location),
+            );
+        }
+
+        // Permissions are set on one concrete key
+        if self.hive == RegistryHive::AllUsers {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!(
+                    "{}: ALL_USERS is not supported for registry ACL changes (use HKU with a SID)",
+                    location
+                ),
+            );
+        }
+
+        match &self.trustee {
+            Some(trustee) if trustee.trim().is_empty() => ctx.tweak_error(
+                file,
+                tweak_id,
+                format!("{}: trustee cannot be empty", location),
+            ),
+            None if self.action == RegistryAclAction::Revoke => ctx.tweak_error(
+                file,
+                tweak_id,
+                format!(
+                    "{}: 'revoke' requires 'trustee' (there is no default account to revoke)",
+                    location
+                ),
+            ),
+            _ => {}
+        }
+
+        if self.access.is_some() && self.action != RegistryAclAction::Grant {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!("{}: 'access' is only used by grant", location),
+            );
+        }
+    }
+}
+
+impl FeatureChange {
+    /// Validate optional feature change semantic correctness
+    fn validate(
+        &self,
+        ctx: &mut ValidationContext,
+        file: &str,
+        tweak_id: &str,
+        option_label: &str,
+    ) {
+        let location = format!(
+            "option '{}' optional feature change '{}'",
+            option_label, self.feature
+        );
+
+        // Feature names are identifiers like Microsoft-Hyper-V or NetFx3; no wildcards, since the
+        // snapshot records one feature per change
+        let name_ok = !self.feature.is_empty()
+            && self
+                .feature
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'));
+        if !name_ok {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!(
+                    "{}: feature must be a feature name (letters, digits, '.', '-', '_')",
+                    location
+                ),
+            );
+        }
+    }
+}
+
+impl NetAdapterChange {
+    /// Validate network adapter change semantic correctness
+    fn validate(
+        &self,
+        ctx: &mut ValidationContext,
+        file: &str,
+        tweak_id: &str,
+        option_label: &str,
+    ) {
+        let location = format!(
+            "option '{}' network adapter change '{}'",
+            option_label, self.keyword
+        );
+
+        if let Some(adapter) = &self.adapter {
+            if adapter.trim().is_empty() {
+                ctx.tweak_error(
+                    file,
+                    tweak_id,
+                    format!(
+                        "{}: adapter cannot be empty (omit it to target every physical adapter)",
+                        location
+                    ),
+                );
+            }
+        }
+
+        // Registry keywords are identifiers; standardized ones start with '*' (*RSS, *EEE)
+        let bare = self.keyword.strip_prefix('*').unwrap_or(&self.keyword);
+        let keyword_ok =
+            !bare.is_empty() && bare.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !keyword_ok {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!(
+                    "{}: keyword must be an advanced property registry keyword (e.g. *RSS), not a display name",
+                    location
+                ),
+            );
+        }
+
+        if self.value.trim().is_empty() || self.value.chars().any(char::is_control) {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!("{}: value must be non-empty and single-line", location),
+            );
+        }
+    }
+}
+
+/// winget sources an application change may name
+const APP_SOURCES: &[&str] = &["winget", "msstore"];
+
+/// Longest `command_timeout_secs` an option may set (one hour)
+const MAX_COMMAND_TIMEOUT_SECS: u32 = 3600;
+
+impl AppChange {
+    fn validate(
+        &self,
+        ctx: &mut ValidationContext,
+        file: &str,
+        tweak_id: &str,
+        option_label: &str,
+    ) {
+        let location = format!("option '{}' app change '{}'", option_label, self.id);
+
+        // winget identifiers: Publisher.Package (e.g. Microsoft.VCRedist.2015+.x64) or a Store ID
+        let id_ok = !self.id.is_empty()
+            && self
+                .id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '+'));
+        if !id_ok {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!(
+                    "{}: id must be a winget package identifier (e.g. Microsoft.OneDrive), not a display name",
+                    location
+                ),
+            );
+        }
+
+        if let Some(source) = &self.source {
+            if !APP_SOURCES.contains(&source.as_str()) {
+                ctx.tweak_error(
+                    file,
+                    tweak_id,
+                    format!(
+                        "{}: unknown source '{}' (expected one of: {})",
+                        location,
+                        source,
+                        APP_SOURCES.join(", ")
+                    ),
+                );
+            }
+        }
+
+        if let Some(version) = &self.version {
+            if self.action != AppAction::Install {
+                ctx.tweak_error(
+                    file,
+                    tweak_id,
+                    format!("{}: version is only valid for 'install'", location),
+                );
+            } else if version.trim().is_empty() || version.chars().any(char::is_whitespace) {
+                ctx.tweak_error(
+                    file,
+                    tweak_id,
+                    format!("{}: version must be a single non-empty token", location),
+                );
+            }
+        }
+    }
+}
+
+/// Policy roots Group Policy owns under both HKLM and HKCU
+const POLICY_KEY_PREFIXES: &[&str] = &[
+    "SOFTWARE\\Policies\\",
+    "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Policies\\",
+];
+
+impl PolicyChange {
+    fn validate(
+        &self,
+        ctx: &mut ValidationContext,
+        file: &str,
+        tweak_id: &str,
+        option_label: &str,
+    ) {
+        let location = format!(
+            "option '{}' policy change '{}'",
+            option_label, self.value_name
+        );
+
+        let key_upper = self.key.to_ascii_uppercase();
+        if !POLICY_KEY_PREFIXES
+            .iter()
+            .any(|prefix| key_upper.starts_with(&prefix.to_ascii_uppercase()))
+        {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!(
+                    "{}: key must be under SOFTWARE\\Policies or SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Policies (use registry_changes for other keys)",
+                    location
+                ),
+            );
+        }
+
+        if self.value_name.trim().is_empty() {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!("{}: value_name cannot be empty", location),
+            );
+        } else if self.value_name.starts_with("**") {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!(
+                    "{}: value_name cannot start with '**' (reserved for Registry.pol directives; use action 'delete_value' instead of '**del.')",
+                    location
+                ),
+            );
+        }
+
+        match self.action {
+            PolicyAction::Set => {
+                if self.value_type.is_none() {
+                    ctx.tweak_error(
+                        file,
+                        tweak_id,
+                        format!("{}: 'set' action requires value_type", location),
+                    );
+                }
+                if self.value.is_none() {
+                    ctx.tweak_error(
+                        file,
+                        tweak_id,
+                        format!("{}: 'set' action requires value", location),
+                    );
+                }
+                if self.value_type.is_some() && self.value.is_some() {
+                    // Same value/type rules as a plain registry change
+                    let as_registry = RegistryChange {
+                        hive: RegistryHive::Hklm,
+                        key: self.key.clone(),
+                        value_name: self.value_name.clone(),
+                        action: RegistryAction::Set,
+                        value_type: self.value_type,
+                        value: self.value.clone(),
+                        windows_versions: None,
+                        conditions: None,
+                        requires_reboot: false,
+                        requires_logoff: false,
+                        skip_validation: self.skip_validation,
+                        elevation: None,
+                    };
+                    as_registry.validate_value_type(ctx, file, tweak_id, &location);
+                }
+            }
+            PolicyAction::DeleteValue => {
+                if self.value_type.is_some() || self.value.is_some() {
+                    ctx.tweak_error(
+                        file,
+                        tweak_id,
+                        format!(
+                            "{}: 'delete_value' action does not take value_type or value",
+                            location
+                        ),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Shape of a browser policy's value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BrowserPolicyKind {
+    Boolean,
+    Integer,
+    String,
+    /// List of strings, stored as a subkey with values "1", "2", ...
+    List,
+    /// Object of booleans, integers, strings, and lists (Firefox), stored as a subkey
+    Object,
+}
+
+impl BrowserPolicyKind {
+    fn describe(&self) -> &'static str {
+        match self {
+            BrowserPolicyKind::Boolean => "a boolean",
+            BrowserPolicyKind::Integer => "an integer",
+            BrowserPolicyKind::String => "a string",
+            BrowserPolicyKind::List => "a list of strings",
+            BrowserPolicyKind::Object => "an object",
+        }
+    }
+
+    fn matches(&self, value: &serde_json::Value) -> bool {
+        let is_list = |value: &serde_json::Value| {
+            value
+                .as_array()
+                .is_some_and(|items| items.iter().all(serde_json::Value::is_string))
+        };
+        match self {
+            BrowserPolicyKind::Boolean => value.is_boolean(),
+            BrowserPolicyKind::Integer => value.as_u64().is_some_and(|n| n <= u32::MAX as u64),
+            BrowserPolicyKind::String => value.is_string(),
+            BrowserPolicyKind::List => is_list(value),
+            BrowserPolicyKind::Object => value.as_object().is_some_and(|fields| {
+                !fields.is_empty()
+                    && fields.values().all(|field| {
+                        field.is_boolean()
+                            || field.as_u64().is_some_and(|n| n <= u32::MAX as u64)
+                            || field.is_string()
+                            || is_list(field)
+                    })
+            }),
+        }
+    }
+}
+
+/// Edge policies `browser_policy_changes` may set: a curated subset of the Edge policy list
+/// (telemetry, sponsored content, DNS over HTTPS, sidebar and shopping features, startup). Add a
+/// policy here, with the shape its documentation gives, before using it in a tweak.
+const EDGE_POLICIES: &[(&str, BrowserPolicyKind)] = &[
+    (
+        "AddressBarMicrosoftSearchInBingProviderEnabled",
+        BrowserPolicyKind::Boolean,
+    ),
+    ("AutofillAddressEnabled", BrowserPolicyKind::Boolean),
+    ("AutofillCreditCardEnabled", BrowserPolicyKind::Boolean),
+    ("BackgroundModeEnabled", BrowserPolicyKind::Boolean),
+    ("BuiltInDnsClientEnabled", BrowserPolicyKind::Boolean),
+    ("ConfigureDoNotTrack", BrowserPolicyKind::Boolean),
+    ("DefaultBrowserSettingEnabled", BrowserPolicyKind::Boolean),
+    ("DiagnosticData", BrowserPolicyKind::Integer),
+    ("DnsOverHttpsMode", BrowserPolicyKind::String),
+    ("DnsOverHttpsTemplates", BrowserPolicyKind::String),
+    ("EdgeCollectionsEnabled", BrowserPolicyKind::Boolean),
+    ("EdgeFollowEnabled", BrowserPolicyKind::Boolean),
+    ("EdgeShoppingAssistantEnabled", BrowserPolicyKind::Boolean),
+    ("ExtensionInstallBlocklist", BrowserPolicyKind::List),
+    ("ExtensionInstallForcelist", BrowserPolicyKind::List),
+    (
+        "HardwareAccelerationModeEnabled",
+        BrowserPolicyKind::Boolean,
+    ),
+    ("HideFirstRunExperience", BrowserPolicyKind::Boolean),
+    ("HomepageLocation", BrowserPolicyKind::String),
+    ("HubsSidebarEnabled", BrowserPolicyKind::Boolean),
+    ("MetricsReportingEnabled", BrowserPolicyKind::Boolean),
+    (
+        "NewTabPageAllowedBackgroundTypes",
+        BrowserPolicyKind::Integer,
+    ),
+    ("NewTabPageContentEnabled", BrowserPolicyKind::Boolean),
+    ("NewTabPageHideDefaultTopSites", BrowserPolicyKind::Boolean),
+    ("NewTabPageQuickLinksEnabled", BrowserPolicyKind::Boolean),
+    ("PasswordManagerEnabled", BrowserPolicyKind::Boolean),
+    (
+        "PersonalizationReportingEnabled",
+        BrowserPolicyKind::Boolean,
+    ),
+    ("PromotionalTabsEnabled", BrowserPolicyKind::Boolean),
+    ("RestoreOnStartup", BrowserPolicyKind::Integer),
+    ("RestoreOnStartupURLs", BrowserPolicyKind::List),
+    ("SearchSuggestEnabled", BrowserPolicyKind::Boolean),
+    ("ShowHomeButton", BrowserPolicyKind::Boolean),
+    ("ShowMicrosoftRewards", BrowserPolicyKind::Boolean),
+    ("ShowRecommendationsEnabled", BrowserPolicyKind::Boolean),
+    ("SmartScreenEnabled", BrowserPolicyKind::Boolean),
+    (
+        "SpotlightExperiencesAndRecommendationsEnabled",
+        BrowserPolicyKind::Boolean,
+    ),
+    ("StartupBoostEnabled", BrowserPolicyKind::Boolean),
+    ("TrackingPrevention", BrowserPolicyKind::Integer),
+    ("TyposquattingCheckerEnabled", BrowserPolicyKind::Boolean),
+    ("UserFeedbackAllowed", BrowserPolicyKind::Boolean),
+    ("WebWidgetAllowed", BrowserPolicyKind::Boolean),
+];
+
+/// Chrome policies `browser_policy_changes` may set (see [`EDGE_POLICIES`])
+const CHROME_POLICIES: &[(&str, BrowserPolicyKind)] = &[
+    ("AlternateErrorPagesEnabled", BrowserPolicyKind::Boolean),
+    ("AutofillAddressEnabled", BrowserPolicyKind::Boolean),
+    ("AutofillCreditCardEnabled", BrowserPolicyKind::Boolean),
+    ("BackgroundModeEnabled", BrowserPolicyKind::Boolean),
+    ("BlockThirdPartyCookies", BrowserPolicyKind::Boolean),
+    ("BrowserSignin", BrowserPolicyKind::Integer),
+    ("BuiltInDnsClientEnabled", BrowserPolicyKind::Boolean),
+    ("DefaultBrowserSettingEnabled", BrowserPolicyKind::Boolean),
+    ("DnsOverHttpsMode", BrowserPolicyKind::String),
+    ("DnsOverHttpsTemplates", BrowserPolicyKind::String),
+    ("ExtensionInstallBlocklist", BrowserPolicyKind::List),
+    ("ExtensionInstallForcelist", BrowserPolicyKind::List),
+    (
+        "HardwareAccelerationModeEnabled",
+        BrowserPolicyKind::Boolean,
+    ),
+    ("HomepageLocation", BrowserPolicyKind::String),
+    ("MetricsReportingEnabled", BrowserPolicyKind::Boolean),
+    ("NetworkPredictionOptions", BrowserPolicyKind::Integer),
+    ("PasswordManagerEnabled", BrowserPolicyKind::Boolean),
+    (
+        "PrivacySandboxAdMeasurementEnabled",
+        BrowserPolicyKind::Boolean,
+    ),
+    ("PrivacySandboxAdTopicsEnabled", BrowserPolicyKind::Boolean),
+    ("PrivacySandboxPromptEnabled", BrowserPolicyKind::Boolean),
+    (
+        "PrivacySandboxSiteEnabledAdsEnabled",
+        BrowserPolicyKind::Boolean,
+    ),
+    ("PromotionsEnabled", BrowserPolicyKind::Boolean),
+    ("RestoreOnStartup", BrowserPolicyKind::Integer),
+    ("RestoreOnStartupURLs", BrowserPolicyKind::List),
+    (
+        "SafeBrowsingExtendedReportingEnabled",
+        BrowserPolicyKind::Boolean,
+    ),
+    ("SafeBrowsingProtectionLevel", BrowserPolicyKind::Integer),
+    ("SearchSuggestEnabled", BrowserPolicyKind::Boolean),
+    ("ShowHomeButton", BrowserPolicyKind::Boolean),
+    ("SpellCheckServiceEnabled", BrowserPolicyKind::Boolean),
+    ("SyncDisabled", BrowserPolicyKind::Boolean),
+    (
+        "UrlKeyedAnonymizedDataCollectionEnabled",
+        BrowserPolicyKind::Boolean,
+    ),
+    ("UserFeedbackAllowed", BrowserPolicyKind::Boolean),
+];
+
+/// Firefox policies `browser_policy_changes` may set (see [`EDGE_POLICIES`])
+const FIREFOX_POLICIES: &[(&str, BrowserPolicyKind)] = &[
+    ("CaptivePortal", BrowserPolicyKind::Boolean),
+    ("DisableAppUpdate", BrowserPolicyKind::Boolean),
+    ("DisableDefaultBrowserAgent", BrowserPolicyKind::Boolean),
+    ("DisableFeedbackCommands", BrowserPolicyKind::Boolean),
+    ("DisableFirefoxAccounts", BrowserPolicyKind::Boolean),
+    ("DisableFirefoxStudies", BrowserPolicyKind::Boolean),
+    ("DisableFormHistory", BrowserPolicyKind::Boolean),
+    ("DisablePocket", BrowserPolicyKind::Boolean),
+    ("DisableTelemetry", BrowserPolicyKind::Boolean),
+    ("DNSOverHTTPS", BrowserPolicyKind::Object),
+    ("DontCheckDefaultBrowser", BrowserPolicyKind::Boolean),
+    ("EnableTrackingProtection", BrowserPolicyKind::Object),
+    ("FirefoxHome", BrowserPolicyKind::Object),
+    ("FirefoxSuggest", BrowserPolicyKind::Object),
+    ("HardwareAcceleration", BrowserPolicyKind::Boolean),
+    ("Homepage", BrowserPolicyKind::Object),
+    ("NetworkPrediction", BrowserPolicyKind::Boolean),
+    ("OfferToSaveLogins", BrowserPolicyKind::Boolean),
+    ("PasswordManagerEnabled", BrowserPolicyKind::Boolean),
+    ("PromptForDownloadLocation", BrowserPolicyKind::Boolean),
+    ("SearchSuggestEnabled", BrowserPolicyKind::Boolean),
+    ("UserMessaging", BrowserPolicyKind::Object),
+];
+
+impl Browser {
+    fn known_policies(&self) -> &'static [(&'static str, BrowserPolicyKind)] {
+        match self {
+            Browser::Edge => EDGE_POLICIES,
+            Browser::Chrome => CHROME_POLICIES,
+            Browser::Firefox => FIREFOX_POLICIES,
+        }
+    }
+}
+
+impl BrowserPolicyChange {
+    fn validate(
+        &self,
+        ctx: &mut ValidationContext,
+        file: &str,
+        tweak_id: &str,
+        option_label: &str,
+    ) {
+        let location = format!(
+            "option '{}' {:?} policy '{}'",
+            option_label, self.browser, self.policy
+        );
+
+        let known = self.browser.known_policies();
+        let Some(&(_, kind)) = known.iter().find(|(name, _)| *name == self.policy) else {
+            let hint = known
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(&self.policy))
+                .map(|(name, _)| format!(" (did you mean '{}'?)", name))
+                .unwrap_or_else(|| {
+                    " (add it to the known browser policies in build.rs if the browser documents it)"
+                        .to_string()
+                });
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!("{}: unknown policy{}", location, hint),
+            );
+            return;
+        };
+
+        match self.action {
+            PolicyAction::Set => match &self.value {
+                Some(value) if kind.matches(value) => {}
+                Some(_) => ctx.tweak_error(
+                    file,
+                    tweak_id,
+                    format!("{}: value must be {}", location, kind.describe()),
+                ),
+                None => ctx.tweak_error(
+                    file,
+                    tweak_id,
+                    format!("{}: 'set' action requires value", location),
+                ),
+            },
+            PolicyAction::DeleteValue => {
+                if self.value.is_some() {
+                    ctx.tweak_error(
+                        file,
+                        tweak_id,
+                        format!("{}: 'delete_value' action cannot have value", location),
+                    );
+                }
+                if matches!(kind, BrowserPolicyKind::List | BrowserPolicyKind::Object) {
+                    ctx.tweak_error(
+                        file,
+                        tweak_id,
+                        format!(
+                            "{}: only boolean, integer, and string policies can be deleted",
+                            location
+                        ),
+                    );
+                }
+            }
+        }
+    }
+}
+
+impl MmcssChange {
+    fn validate(
+        &self,
+        ctx: &mut ValidationContext,
+        file: &str,
+        tweak_id: &str,
+        option_label: &str,
+    ) {
+        let location = format!("option '{}' MMCSS change '{}'", option_label, self.task);
+
+        if self.task.trim().is_empty() {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!("{}: task cannot be empty", location),
+            );
+        } else if self.task.contains('\\') || self.task.trim() != self.task {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!(
+                    "{}: task must be a single key name under SystemProfile\\Tasks (e.g. \"Games\"), without backslashes or surrounding whitespace",
+                    location
+                ),
+            );
+        }
+
+        if let Some(priority) = self.priority {
+            if !(1..=8).contains(&priority) {
+                ctx.tweak_error(
+                    file,
+                    tweak_id,
+                    format!("{}: priority must be 1-8, got {}", location, priority),
+                );
+            }
+        }
+
+        if let Some(gpu_priority) = self.gpu_priority {
+            if gpu_priority > 31 {
+                ctx.tweak_error(
+                    file,
+                    tweak_id,
+                    format!(
+                        "{}: gpu_priority must be 0-31, got {}",
+                        location, gpu_priority
+                    ),
+                );
+            }
+        }
+
+        if self.clock_rate == Some(0) {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!(
+                    "{}: clock_rate must be greater than 0 (100-ns units, 10000 = 1 ms)",
+                    location
+                ),
+            );
+        }
+
+        let sets_anything = self.priority.is_some()
+            || self.gpu_priority.is_some()
+            || self.scheduling_category.is_some()
+            || self.sfio_priority.is_some()
+            || self.background_only.is_some()
+            || self.clock_rate.is_some()
+            || self.affinity.is_some();
+        if !sets_anything {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!(
+                    "{}: sets nothing (expected at least one of priority, gpu_priority, scheduling_category, sfio_priority, background_only, clock_rate, affinity)",
+                    location
+                ),
+            );
+        }
+    }
+}
+
+impl ContextMenuChange {
+    fn validate(
+        &self,
+        ctx: &mut ValidationContext,
+        file: &str,
+        tweak_id: &str,
+        option_label: &str,
+    ) {
+        let location = match self.kind {
+            ContextMenuKind::ClassicMenu => {
+                format!("option '{}' classic context menu change", option_label)
+            }
+            _ => format!(
+                "option '{}' context menu change '{}\\{}'",
+                option_label, self.target, self.name
+            ),
+        };
+
+        if self.kind == ContextMenuKind::ClassicMenu {
+            if !self.target.is_empty() || !self.name.is_empty() {
+                ctx.tweak_error(
+                    file,
+                    tweak_id,
+                    format!("{}: classic_menu does not take target or name", location),
+                );
+            }
+            if self.scope == ContextMenuScope::Machine {
+                ctx.tweak_warning(
+                    file,
+                    tweak_id,
+                    format!(
+                        "{}: the classic menu override is normally per-user (scope: user)",
+                        location
+                    ),
+                );
+            }
+        } else {
+            if self.target.trim().is_empty()
+                || self.target.starts_with('\\')
+                || self.target.ends_with('\\')
+            {
+                ctx.tweak_error(
+                    file,
+                    tweak_id,
+                    format!(
+                        "{}: target must be a class name such as \"*\", \"Directory\", or \".txt\" (without leading or trailing backslashes)",
+                        location
+                    ),
+                );
+            }
+            if self.name.trim().is_empty() || self.name.contains('\\') {
+                ctx.tweak_error(
+                    file,
+                    tweak_id,
+                    format!(
+                        "{}: name must be a single key name without backslashes",
+                        location
+                    ),
+                );
+            }
+        }
+
+        // Fields each action/kind combination accepts
+        let (verb_fields, clsid) = match (self.action, self.kind) {
+            (ContextMenuAction::Add, ContextMenuKind::Verb) => (true, false),
+            (ContextMenuAction::Add, ContextMenuKind::Handler) => (false, true),
+            _ => (false, false),
+        };
+        let has_verb_fields =
+            self.label.is_some() || self.command.is_some() || self.icon.is_some() || self.extended;
+        if has_verb_fields && !verb_fields {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!(
+                    "{}: label, command, icon, and extended are only valid when adding a verb",
+                    location
+                ),
+            );
+        }
+        if self.clsid.is_some() && !clsid {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!("{}: clsid is only valid when adding a handler", location),
+            );
+        }
+
+        if verb_fields && self.command.as_deref().is_none_or(|c| c.trim().is_empty()) {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!("{}: adding a verb requires a command", location),
+            );
+        }
+        if clsid {
+            match &self.clsid {
+                Some(clsid)
+                    if clsid.starts_with('{') && clsid.ends_with('}') && is_valid_guid(clsid) => {}
+                Some(clsid) => ctx.tweak_error(
+                    file,
+                    tweak_id,
+                    format!("{}: clsid '{}' is not a {{GUID}}", location, clsid),
+                ),
+                None => ctx.tweak_error(
+                    file,
+                    tweak_id,
+                    format!("{}: adding a handler requires a clsid", location),
+                ),
+            }
+        }
+    }
+}
+
+impl AudioChange {
+    /// Names of the settings this change sets
+    fn set_fields(&self) -> Vec<&'static str> {
+        [
+            ("sound_scheme", self.sound_scheme.is_some()),
+            ("startup_sound", self.startup_sound.is_some()),
+            (
+                "communications_ducking",
+                self.communications_ducking.is_some(),
+            ),
+            ("audio_enhancements", self.audio_enhancements.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(name, set)| set.then_some(name))
+        .collect()
+    }
+
+    fn validate(
+        &self,
+        ctx: &mut ValidationContext,
+        file: &str,
+        tweak_id: &str,
+        option_label: &str,
+    ) {
+        if self.set_fields().is_empty() {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!(
+                    "option '{}' audio change sets nothing (expected at least one of sound_scheme, startup_sound, communications_ducking, audio_enhancements)",
+                    option_label
+                ),
+            );
+        }
+    }
+
+    /// Whether this change writes machine-wide values (startup sound, device enhancements)
+    fn requires_admin(&self) -> bool {
+        self.startup_sound.is_some() || self.audio_enhancements.is_some()
+    }
+}
+
+impl TimeChange {
+    /// Names of the settings this change sets
+    fn set_fields(&self) -> Vec<&'static str> {
+        [
+            ("ntp_servers", self.ntp_servers.is_some()),
+            ("sync_type", self.sync_type.is_some()),
+            ("sync_interval", self.sync_interval.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(name, set)| set.then_some(name))
+        .collect()
+    }
+
+    fn validate(
+        &self,
+        ctx: &mut ValidationContext,
+        file: &str,
+        tweak_id: &str,
+        option_label: &str,
+    ) {
+        let location = format!("option '{}' time change", option_label);
+
+        if self.set_fields().is_empty() {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!(
+                    "{} sets nothing (expected at least one of ntp_servers, sync_type, sync_interval)",
+                    location
+                ),
+            );
+        }
+
+        if let Some(servers) = &self.ntp_servers {
+            if servers.is_empty() {
+                ctx.tweak_error(
+                    file,
+                    tweak_id,
+                    format!("{}: ntp_servers cannot be empty", location),
+                );
+            }
+            for server in servers {
+                let host = server.split(',').next().unwrap_or_default();
+                if host.is_empty() || server.chars().any(char::is_whitespace) {
+                    ctx.tweak_error(
+                        file,
+                        tweak_id,
+                        format!(
+                            "{}: NTP server '{}' must be a host name without spaces (optionally with flags, e.g. \"pool.ntp.org,0x9\")",
+                            location, server
+                        ),
+                    );
+                }
+            }
+            if matches!(
+                self.sync_type,
+                Some(TimeSyncType::Nt5ds | TimeSyncType::NoSync)
+            ) {
+                ctx.tweak_warning(
+                    file,
+                    tweak_id,
+                    format!(
+                        "{}: ntp_servers are only used when sync_type is ntp or all_sync",
+                        location
+                    ),
+                );
+            }
+        }
+
+        if self.sync_interval == Some(0) {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!("{}: sync_interval must be greater than 0 seconds", location),
+            );
+        }
+    }
+}
+
+impl StateProbes {
+    /// Validate that every probe names something; probes only matter for options with commands
+    fn validate(
+        &self,
+        ctx: &mut ValidationContext,
+        file: &str,
+        tweak_id: &str,
+        option: &TweakOption,
+    ) {
+        if self.registry.is_empty() && self.files.is_empty() && self.services.is_empty() {
+            return;
+        }
+        let location = format!("option '{}' state_probes", option.label);
+        let has_commands = !option.pre_commands.is_empty()
+            || !option.post_commands.is_empty()
+            || !option.pre_powershell.is_empty()
+            || !option.post_powershell.is_empty();
+        if !has_commands {
+            ctx.tweak_warning(
+                file,
+                tweak_id,
+                format!(
+                    "{}: the option runs no commands; its other changes are captured on their own",
+                    location
+                ),
+            );
+        }
+
+        for probe in &self.registry {
+            if probe.key.trim().is_empty() {
+                ctx.tweak_error(
+                    file,
+                    tweak_id,
+                    format!("{}: registry probe key cannot be empty", location),
+                );
+            }
+        }
+        for path in &self.files {
+            if path.trim().is_empty() {
+                ctx.tweak_error(
+                    file,
+                    tweak_id,
+                    format!("{}: file probe path cannot be empty", location),
+                );
+            }
+        }
+        for name in &self.services {
+            if name.trim().is_empty() {
+                ctx.tweak_error(
+                    file,
+                    tweak_id,
+                    format!("{}: service probe name cannot be empty", location),
+                );
+            }
+        }
+    }
+}
+
+impl TweakOption {
+    /// Validate option semantic correctness
+    fn validate(&self, ctx: &mut ValidationContext, file: &str, tweak_id: &str) {
+        // Validate option label is not empty or whitespace
+        if self.label.trim().is_empty() {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                "option label cannot be empty or whitespace-only".to_string(),
+            );
+        }
+
+        if let Some(conditions) = &self.conditions {
+            conditions.validate(ctx, file, tweak_id, &format!("option '{}'", self.label));
+        }
+
+        // Validate ${variable} placeholders in every string of the option
+        if let Ok(mut option) = serde_json::to_value(self) {
+            let mut resolve = |name: &str| is_template_variable(name).then(String::new);
+            if let Err(e) = expand_json_strings(&mut option, &mut resolve) {
+                let known: Vec<&str> = TEMPLATE_VARIABLES.iter().map(|(name, _)| *name).collect();
+                ctx.tweak_error(
+                    file,
+                    tweak_id,
+                    format!(
+                        "option '{}': {} (known variables: {}; write `$${{` for a literal `${{`)",
+                        self.label,
+                        e,
+                        known.join(", ")
+                    ),
+                );
+            }
+        }
+
+        // Validate all registry changes
+        for change in &self.registry_changes {
+            change.validate(ctx, file, tweak_id, &self.label);
+        }
+
+        // Validate all service changes
+        for change in &self.service_changes {
+            change.validate(ctx, file, tweak_id, &self.label);
+        }
+
+        // Validate all scheduler changes
+        for change in &self.scheduler_changes {
+            change.validate(ctx, file, tweak_id, &self.label);
+        }
+
+        // Validate all hosts changes
+        for change in &self.hosts_changes {
+            change.validate(ctx, file, tweak_id, &self.label);
+        }
+
+        // Validate all firewall changes
+        for change in &self.firewall_changes {
+            change.validate(ctx, file, tweak_id, &self.label);
+        }
+
+        // Validate all power plan changes
+        for change in &self.power_plan_changes {
+            change.validate(ctx, file, tweak_id, &self.label);
+        }
+
+        // Validate all BCD changes
+        for change in &self.bcdedit_changes {
+            change.validate(ctx, file, tweak_id, &self.label);
+        }
+
+        // Validate all AppX changes
+        for change in &self.appx_changes {
+            change.validate(ctx, file, tweak_id, &self.label);
+        }
+
+        // Validate all file changes
+        for change in &self.file_changes {
+            change.validate(ctx, file, tweak_id, &self.label);
+        }
+
+        // Validate all registry ACL changes
+        for change in &self.registry_acl_changes {
+            change.validate(ctx, file, tweak_id, &self.label);
+        }
+
+        // Validate all optional feature changes
+        for change in &self.feature_changes {
+            change.validate(ctx, file, tweak_id, &self.label);
+        }
+
+        // Validate all network adapter changes
+        for change in &self.netadapter_changes {
+            change.validate(ctx, file, tweak_id, &self.label);
+        }
+
+        // Validate all policy changes
+        for change in &self.policy_changes {
+            change.validate(ctx, file, tweak_id, &self.label);
+        }
+
+        // Validate all browser policy changes; each policy may be set only once per option
+        let mut seen_browser_policies = HashSet::new();
+        for change in &self.browser_policy_changes {
+            change.validate(ctx, file, tweak_id, &self.label);
+            if !seen_browser_policies.insert((change.browser, change.scope, change.policy.as_str()))
+            {
+                ctx.tweak_error(
+                    file,
+                    tweak_id,
+                    format!(
+                        "option '{}': {:?} policy '{}' is listed more than once",
+                        self.label, change.browser, change.policy
+                    ),
+                );
+            }
+        }
+
+        // Validate all application changes
+        for change in &self.app_changes {
+            change.validate(ctx, file, tweak_id, &self.label);
+        }
+
+        // Validate all MMCSS changes; each task may appear only once per option
+        let mut seen_tasks = HashSet::new();
+        for change in &self.mmcss_changes {
+            change.validate(ctx, file, tweak_id, &self.label);
+            if !seen_tasks.insert(change.task.to_ascii_lowercase()) {
+                ctx.tweak_error(
+                    file,
+                    tweak_id,
+                    format!(
+                        "option '{}': MMCSS task '{}' is listed more than once (merge the changes)",
+                        self.label, change.task
+                    ),
+                );
+            }
+        }
+
+        // Validate all context menu changes
+        for change in &self.context_menu_changes {
+            change.validate(ctx, file, tweak_id, &self.label);
+        }
+
+        // Validate all audio changes; each setting may be set only once per option
+        let mut seen_audio_fields = HashSet::new();
+        for change in &self.audio_changes {
+            change.validate(ctx, file, tweak_id, &self.label);
+            for field in change.set_fields() {
+                if !seen_audio_fields.insert(field) {
+                    ctx.tweak_error(
+                        file,
+                        tweak_id,
+                        format!(
+                            "option '{}': audio setting '{}' is set more than once",
+                            self.label, field
+                        ),
+                    );
+                }
+            }
+        }
+
+        // Validate all visual effects changes; each effect may be listed only once per option
+        let mut seen_effects = HashSet::new();
+        for change in &self.visual_effects_changes {
+            if !seen_effects.insert(change.effect) {
+                ctx.tweak_error(
+                    file,
+                    tweak_id,
+                    format!(
+                        "option '{}': visual effect {} is listed more than once",
+                        self.label,
+                        serde_json::json!(change.effect)
+                    ),
+                );
+            }
+        }
+        if self
+            .registry_changes
+            .iter()
+            .any(RegistryChange::writes_preferences_mask)
+        {
+            let message = if self.visual_effects_changes.is_empty() {
+                "writes UserPreferencesMask as a whole blob, which also overwrites unrelated settings; use visual_effects_changes to set individual effects"
+            } else {
+                "writes UserPreferencesMask in registry_changes as well as through visual_effects_changes; one overwrites the other"
+            };
+            ctx.tweak_warning(
+                file,
+                tweak_id,
+                format!("option '{}' {}", self.label, message),
+            );
+        }
+
+        // Validate all time changes; each setting may be set only once per option
+        let mut seen_time_fields = HashSet::new();
+        for change in &self.time_changes {
+            change.validate(ctx, file, tweak_id, &self.label);
+            for field in change.set_fields() {
+                if !seen_time_fields.insert(field) {
+                    ctx.tweak_error(
+                        file,
+                        tweak_id,
+                        format!(
+                            "option '{}': time setting '{}' is set more than once",
+                            self.label, field
+                        ),
+                    );
+                }
+            }
+        }
+
+        // Validate component changes: each component once, not combined with a conflicting change
+        // to one of its services or to a component it depends on
+        let mut seen_components = HashSet::new();
+        for change in &self.component_changes {
+            let component = change.component.as_str();
+            if !seen_components.insert(change.component) {
+                ctx.tweak_error(
+                    file,
+                    tweak_id,
+                    format!(
+                        "option '{}': component '{}' is listed more than once",
+                        self.label, component
+                    ),
+                );
+            }
+            for service in &self.service_changes {
+                if change.component.has_service(&service.name) {
+                    ctx.tweak_error(
+                        file,
+                        tweak_id,
+                        format!(
+                            "option '{}': service '{}' is part of component '{}'; change it through either service_changes or component_changes, not both",
+                            self.label, service.name, component
+                        ),
+                    );
+                }
+            }
+            for other in &self.component_changes {
+                if other.enabled || !other.component.dependents().contains(&change.component) {
+                    continue;
+                }
+                if change.enabled {
+                    ctx.tweak_error(
+                        file,
+                        tweak_id,
+                        format!(
+                            "option '{}': component '{}' can't be enabled while '{}', which it depends on, is disabled",
+                            self.label,
+                            component,
+                            other.component.as_str()
+                        ),
+                    );
+                } else {
+                    ctx.tweak_warning(
+                        file,
+                        tweak_id,
+                        format!(
+                            "option '{}': disabling component '{}' already disables '{}'",
+                            self.label,
+                            other.component.as_str(),
+                            component
+                        ),
+                    );
+                }
+            }
+        }
+
+        self.state_probes.validate(ctx, file, tweak_id, self);
+
+        if let Some(secs) = self.command_timeout_secs {
+            if secs == 0 || secs > MAX_COMMAND_TIMEOUT_SECS {
+                ctx.tweak_error(
+                    file,
+                    tweak_id,
+                    format!(
+                        "option '{}': command_timeout_secs must be between 1 and {}, got {}",
+                        self.label, MAX_COMMAND_TIMEOUT_SECS, secs
+                    ),
+                );
+            }
+            let has_commands = !self.pre_commands.is_empty()
+                || !self.post_commands.is_empty()
+                || !self.pre_powershell.is_empty()
+                || !self.post_powershell.is_empty();
+            if !has_commands {
+                ctx.tweak_warning(
+                    file,
+                    tweak_id,
+                    format!(
+                        "option '{}': command_timeout_secs has no effect on an option without commands",
+                        self.label
+                    ),
+                );
+            }
+        }
+
+        if let Some(shell) = self.shell {
+            let has_commands = !self.pre_commands.is_empty() || !self.post_commands.is_empty();
+            let has_powershell =
+                !self.pre_powershell.is_empty() || !self.post_powershell.is_empty();
+            // Only pwsh changes how pre/post_powershell run; the other shells affect commands only
+            let affects_scripts = shell == CommandShell::Pwsh && has_powershell;
+            if !has_commands && !affects_scripts {
+                ctx.tweak_warning(
+                    file,
+                    tweak_id,
+                    format!(
+                        "option '{}': shell has no effect without pre_commands / post_commands{}",
+                        self.label,
+                        if shell == CommandShell::Pwsh {
+                            " or PowerShell scripts"
+                        } else {
+                            ""
+                        }
+                    ),
+                );
+            } else if shell == CommandShell::Cmd {
+                ctx.tweak_warning(
+                    file,
+                    tweak_id,
+                    format!(
+                        "option '{}': shell: cmd is the default and can be removed",
+                        self.label
+                    ),
+                );
+            }
+        }
+
+        // Check for empty option (no changes at all)
+        let has_any_changes = !self.registry_changes.is_empty()
+            || !self.service_changes.is_empty()
+            || !self.scheduler_changes.is_empty()
+            || !self.hosts_changes.is_empty()
+            || !self.firewall_changes.is_empty()
+            || !self.power_plan_changes.is_empty()
+            || !self.bcdedit_changes.is_empty()
+            || !self.appx_changes.is_empty()
+            || !self.file_changes.is_empty()
+            || !self.registry_acl_changes.is_empty()
+            || !self.feature_changes.is_empty()
+            || !self.netadapter_changes.is_empty()
+            || !self.policy_changes.is_empty()
+            || !self.app_changes.is_empty()
+            || !self.mmcss_changes.is_empty()
+            || !self.context_menu_changes.is_empty()
+            || !self.audio_changes.is_empty()
+            || !self.visual_effects_changes.is_empty()
+            || !self.browser_policy_changes.is_empty()
+            || !self.time_changes.is_empty()
+            || !self.component_changes.is_empty()
+            || !self.pre_commands.is_empty()
+            || !self.post_commands.is_empty()
+            || !self.pre_powershell.is_empty()
+            || !self.post_powershell.is_empty();
+
+        if !has_any_changes {
+            ctx.tweak_error(
+                file,
+                tweak_id,
+                format!(
+                    "option '{}' has no changes (registry, service, scheduler, hosts, firewall, power plan, BCD, AppX, file, registry ACL, optional feature, network adapter, policy, app, MMCSS, context menu, audio, visual effects, browser policy, time, component, or commands)",
+                    self.label
+                ),
+            );
+        }
+    }
+
+    /// Check if this option requires admin privileges (any registry change outside HKCU; any
+    /// registry ACL change, since ownership and descriptor restore need admin-only privileges; any
+    /// optional feature change, since DISM only services the running image when elevated; any
+    /// network adapter change, since adapter properties live in the driver's HKLM key; any
+    /// machine policy or Registry.pol write, since both live under admin-only locations; any
+    /// application change, since most installers and uninstallers work machine-wide; any MMCSS
+    /// change, since the task keys live in HKLM; any machine-wide context menu change; any startup
+    /// sound or audio enhancement change, since both live in HKLM; any machine browser policy; any
+    /// time change, since w32time's settings live in HKLM; or any component change, since it
+    /// reconfigures services)
+    fn requires_admin(&self) -> bool {
+        self.registry_changes.iter().any(|r| r.requires_admin())
+            || !self.registry_acl_changes.is_empty()
+            || !self.feature_changes.is_empty()
+            || !self.netadapter_changes.is_empty()
+            || self
+                .policy_changes
+                .iter()
+                .any(|p| p.scope == PolicyScope::Machine || p.write_pol)
+            || !self.app_changes.is_empty()
+            || !self.mmcss_changes.is_empty()
+            || self
+                .context_menu_changes
+                .iter()
+                .any(|c| c.scope == ContextMenuScope::Machine)
+            || self.audio_changes.iter().any(AudioChange::requires_admin)
+            || self
+                .browser_policy_changes
+                .iter()
+                .any(|b| b.scope == PolicyScope::Machine)
+            || !self.time_changes.is_empty()
+            || !self.component_changes.is_empty()
+    }
+
+    /// Elevations the option's registry, service, and scheduler changes declare for themselves
+    fn declared_elevations(&self) -> impl Iterator<Item = ChangeElevation> + '_ {
+        self.registry_changes
+            .iter()
+            .filter_map(|c| c.elevation)
+            .chain(self.service_changes.iter().filter_map(|c| c.elevation))
+            .chain(self.scheduler_changes.iter().filter_map(|c| c.elevation))
+    }
+}
+
+impl TweakDefinitionRaw {
+    /// The final definition, in `category_id`, with the permissions its changes need inferred
+    pub(crate) fn into_definition(self, category_id: &str) -> TweakDefinition {
+        let requires_ti = self.requires_ti;
+        let requires_system = self.requires_system || requires_ti;
+        // A change that runs elevated needs the app elevated, whatever the tweak declares
+        let requires_admin = self.requires_admin
+            || requires_system
+            || self
+                .options
+                .iter()
+                .flat_map(TweakOption::declared_elevations)
+                .any(|elevation| elevation != ChangeElevation::User);
+
+        TweakDefinition {
+            id: self.id,
+            name: self.name,
+            description: self.description,
+            info: self.info,
+            risk_level: self.risk_level,
+            requires_admin,
+            requires_system,
+            requires_ti,
+            requires_reboot: self.requires_reboot,
+            force_dropdown: self.force_dropdown,
+            options: self.options,
+            category_id: category_id.to_string(),
+            custom: false,
+            deprecated: self.deprecated,
+            superseded_by: self.superseded_by,
+            aliases: self.aliases,
+            min_build: self.min_build,
+            max_build: self.max_build,
+            editions: self.editions,
+            tags: self.tags,
+        }
+    }
+
+    /// The elevation the tweak's changes run at when they don't declare one
+    fn elevation(&self) -> ChangeElevation {
+        if self.requires_ti {
+            ChangeElevation::Ti
+        } else if self.requires_system {
+            ChangeElevation::System
+        } else if self.requires_admin {
+            ChangeElevation::Admin
+        } else {
+            ChangeElevation::User
+        }
+    }
+
+    /// Check the `elevation` an option's changes declare against what each change needs
+    fn validate_change_elevations(
+        &self,
+        ctx: &mut ValidationContext,
+        file: &str,
+        option: &TweakOption,
+    ) {
+        let tweak_elevation = self.elevation();
+        let redundant = |kind: &str, target: &str| {
+            format!(
+                "option '{}': {} '{}' declares elevation '{}', which is already the tweak's",
+                option.label,
+                kind,
+                target,
+                elevation_name(tweak_elevation)
+            )
+        };
+
+        for change in &option.registry_changes {
+            let target = format!("{}\\{}", change.hive.as_str(), change.key);
+            match change.elevation {
+                None => {}
+                Some(ChangeElevation::User) if change.requires_admin() => ctx.tweak_error(
+                    file,
+                    &self.id,
+                    format!(
+                        "option '{}': registry change '{}' declares elevation 'user', but {} needs administrator rights",
+                        option.label,
+                        target,
+                        change.hive.as_str()
+                    ),
+                ),
+                Some(ChangeElevation::System | ChangeElevation::Ti)
+                    if change.hive == RegistryHive::Hkcu =>
+                {
+                    ctx.tweak_warning(
+                        file,
+                        &self.id,
+                        format!(
+                            "option '{}': registry change '{}' declares an elevated level, but HKCU changes are always written as the user",
+                            option.label, target
+                        ),
+                    )
+                }
+                Some(elevation) if elevation == tweak_elevation => {
+                    ctx.tweak_warning(file, &self.id, redundant("registry change", &target))
+                }
+                Some(_) => {}
+            }
+        }
+
+        for change in &option.service_changes {
+            match change.elevation {
+                Some(ChangeElevation::User) => ctx.tweak_error(
+                    file,
+                    &self.id,
+                    format!(
+                        "option '{}': service change '{}' declares elevation 'user', but changing a service needs administrator rights",
+                        option.label, change.name
+                    ),
+                ),
+                Some(elevation) if elevation == tweak_elevation => {
+                    ctx.tweak_warning(file, &self.id, redundant("service change", &change.name))
+                }
+                _ => {}
+            }
+        }
+
+        for change in &option.scheduler_changes {
+            let target = format!(
+                "{}\\{}",
+                change.task_path.trim_end_matches('\\'),
+                change
+                    .task_name
+                    .as_deref()
+                    .or(change.task_name_pattern.as_deref())
+                    .unwrap_or_default()
+            );
+            if change.elevation == Some(tweak_elevation) {
+                ctx.tweak_warning(file, &self.id, redundant("scheduler change", &target));
+            }
+        }
+    }
+
+    /// Validate tweak definition semantic correctness
+    pub(crate) fn validate(&self, ctx: &mut ValidationContext, file: &str) {
+        // Validate tweak ID format (snake_case)
+        if !is_valid_tweak_id(&self.id) {
+            ctx.tweak_error(
+                file,
+                &self.id,
+                "tweak ID must be snake_case (lowercase letters, digits, underscores only)"
+                    .to_string(),
+            );
+        }
+
+        // Check for duplicate ID
+        if ctx.seen_tweak_ids.contains(&self.id) {
+            ctx.tweak_error(
+                file,
+                &self.id,
+                "duplicate tweak ID (already defined in another file)".to_string(),
+            );
+        } else {
+            ctx.seen_tweak_ids.insert(self.id.clone());
+        }
+
+        // Aliases share the ID namespace, so an old ID resolves to exactly one tweak
+        for alias in &self.aliases {
+            if !is_valid_tweak_id(alias) || *alias == self.id {
+                ctx.tweak_error(
+                    file,
+                    &self.id,
+                    format!(
+                        "alias '{}' must be a snake_case ID other than the tweak's",
+                        alias
+                    ),
+                );
+            } else if !ctx.seen_tweak_ids.insert(alias.clone()) {
+                ctx.tweak_error(
+                    file,
+                    &self.id,
+                    format!("alias '{}' is already a tweak ID or alias", alias),
+                );
+            }
+        }
+        if let Some(target) = &self.superseded_by {
+            if !self.deprecated {
+                ctx.tweak_error(
+                    file,
+                    &self.id,
+                    "superseded_by needs deprecated: true".to_string(),
+                );
+            } else if *target == self.id {
+                ctx.tweak_error(
+                    file,
+                    &self.id,
+                    "superseded_by names the tweak itself".to_string(),
+                );
+            } else {
+                ctx.superseded
+                    .push((file.to_string(), self.id.clone(), target.clone()));
+            }
+        }
+
+        if self
+            .editions
+            .as_ref()
+            .is_some_and(|editions| editions.is_empty())
+        {
+            ctx.tweak_error(
+                file,
+                &self.id,
+                "editions cannot be empty (it would never match); omit it to allow every edition"
+                    .to_string(),
+            );
+        }
+        validate_build_range(ctx, file, &self.id, "", self.min_build, self.max_build);
+
+        let mut seen_tags = HashSet::new();
+        for tag in &self.tags {
+            if !is_tweak_tag(tag) {
+                let known: Vec<&str> = TWEAK_TAGS.iter().map(|(name, _)| *name).collect();
+                ctx.tweak_error(
+                    file,
+                    &self.id,
+                    format!(
+                        "unknown tag '{}' (declared tags: {})",
+                        tag,
+                        known.join(", ")
+                    ),
+                );
+            } else if !seen_tags.insert(tag) {
+                ctx.tweak_warning(file, &self.id, format!("tag '{}' is listed twice", tag));
+            }
+        }
+
+        // Validate option count (minimum 2 required)
+        if self.options.len() < 2 {
+            ctx.tweak_error(
+                file,
+                &self.id,
+                format!("must have at least 2 options, found {}", self.options.len()),
+            );
+            return; // Can't validate further without proper options
+        }
+
+        // Warn if force_dropdown used with 3+ options (unnecessary)
+        if self.force_dropdown && self.options.len() > 2 {
+            ctx.tweak_warning(
+                file,
+                &self.id,
+                "force_dropdown is unnecessary for 3+ options (already defaults to dropdown)"
+                    .to_string(),
+            );
+        }
+
+        // Check for duplicate option labels within this tweak
+        let mut seen_labels: HashSet<String> = HashSet::new();
+        for option in &self.options {
+            let label_lower = option.label.to_lowercase();
+            if seen_labels.contains(&label_lower) {
+                ctx.tweak_error(
+                    file,
+                    &self.id,
+                    format!(
+                        "duplicate option label '{}' (case-insensitive)",
+                        option.label
+                    ),
+                );
+            } else {
+                seen_labels.insert(label_lower);
+            }
+        }
+
+        // Validate each option
+        for option in &self.options {
+            option.validate(ctx, file, &self.id);
+            self.validate_change_elevations(ctx, file, option);
+
+            // Commands run as the user have no time limit to change
+            if option.command_timeout_secs.is_some() && !self.requires_system && !self.requires_ti {
+                ctx.tweak_warning(
+                    file,
+                    &self.id,
+                    format!(
+                        "option '{}': command_timeout_secs only applies to commands run as SYSTEM or TrustedInstaller (requires_system / requires_ti)",
+                        option.label
+                    ),
+                );
+            }
+
+            // A tweak-level reboot already covers every option and change
+            let change_requires_reboot = option.registry_changes.iter().any(|c| c.requires_reboot);
+            if self.requires_reboot && (option.requires_reboot || change_requires_reboot) {
+                ctx.tweak_warning(
+                    file,
+                    &self.id,
+                    format!(
+                        "option '{}' declares requires_reboot, which is redundant because the tweak already sets requires_reboot",
+                        option.label
+                    ),
+                );
+            }
+        }
+
+        // Check if any option requires admin but tweak doesn't declare it
+        let any_requires_admin = self.options.iter().any(|o| o.requires_admin());
+        if any_requires_admin && !self.requires_admin && !self.requires_system && !self.requires_ti
+        {
+            ctx.tweak_warning(
+                file,
+                &self.id,
+                "contains HKLM/HKCR/HKU registry, registry ACL, optional feature, network adapter, admin-only policy, or application changes but requires_admin is false (should be true)"
+                    .to_string(),
+            );
+        }
+
+        for id in &self.overlaps_with {
+            if *id == self.id || !is_valid_tweak_id(id) {
+                ctx.tweak_error(
+                    file,
+                    &self.id,
+                    format!("overlaps_with entry '{}' isn't another tweak's ID", id),
+                );
+            }
+        }
+        ctx.check_conflicts(file, &self.id, &self.options, &self.overlaps_with);
+    }
+}
+
+/// A pair of tweak IDs in a fixed order
+fn overlap_pair(a: &str, b: &str) -> (String, String) {
+    if a <= b {
+        (a.to_string(), b.to_string())
+    } else {
+        (b.to_string(), a.to_string())
+    }
+}
+
+/// The YAML spelling of a registry hive
+fn hive_name(hive: RegistryHive) -> &'static str {
+    match hive {
+        RegistryHive::Hkcu => "HKCU",
+        RegistryHive::Hklm => "HKLM",
+        RegistryHive::Hkcr => "HKCR",
+        RegistryHive::Hku => "HKU",
+        RegistryHive::AllUsers => "ALL_USERS",
+    }
+}
+
+/// The registry values, services, and scheduled tasks `options` change, by a key that ignores
+/// case as Windows does, with what the options set each to
+fn target_changes(tweak_id: &str, options: &[TweakOption]) -> BTreeMap<String, TargetChange> {
+    let mut targets = BTreeMap::new();
+    let mut add = |label: String, value: String| {
+        targets
+            .entry(label.to_lowercase())
+            .or_insert_with(|| TargetChange {
+                tweak_id: tweak_id.to_string(),
+                label,
+                values: BTreeSet::new(),
+            })
+            .values
+            .insert(value);
+    };
+    for option in options {
+        for change in &option.registry_changes {
+            let label = format!(
+                "registry value {}\\{}\\{}",
+                hive_name(change.hive),
+                change.key.trim_end_matches('\\'),
+                change.value_name
+            );
+            let value = match &change.value {
+                Some(value) => format!("{:?} {}", change.action, value),
+                None => format!("{:?}", change.action),
+            };
+            add(label, value);
+        }
+        for change in &option.service_changes {
+            add(
+                format!("service {}", change.name),
+                format!("{:?}", change.startup),
+            );
+        }
+        for change in &option.scheduler_changes {
+            let name = change
+                .task_name
+                .as_deref()
+                .or(change.task_name_pattern.as_deref())
+                .unwrap_or_default();
+            add(
+                format!(
+                    "scheduled task {}\\{}",
+                    change.task_path.trim_end_matches('\\'),
+                    name
+                ),
+                format!("{:?}", change.action),
+            );
+        }
+    }
+    targets
+}
+
+/// The YAML spelling of a change elevation
+fn elevation_name(elevation: ChangeElevation) -> &'static str {
+    match elevation {
+        ChangeElevation::User => "user",
+        ChangeElevation::Admin => "admin",
+        ChangeElevation::System => "system",
+        ChangeElevation::Ti => "ti",
+    }
+}
+
+/// Get human-readable name for JSON value type
+fn value_type_name(value: &serde_json::Value) -> &'static str {
+    match value {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "boolean",
+        serde_json::Value::Number(_) => "number",
+        serde_json::Value::String(_) => "string",
+        serde_json::Value::Array(_) => "array",
+        serde_json::Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CATEGORY: &str = r#"
+category:
+  id: test_tweaks
+  name: "Test"
+  description: "Tweaks for tests"
+  icon: "mdi:flask"
+  order: 1
+"#;
+
+    /// A tweak with two options that each set a DWORD under HKCU to `values`
+    fn tweak(id: &str, values: [&str; 2]) -> String {
+        format!(
+            r#"
+  - id: {}
+    name: "Test tweak"
+    description: "A tweak for tests"
+    risk_level: low
+    options:
+      - label: "On"
+        registry_changes:
+          - hive: HKCU
+            key: "Software\\Test"
+            value_name: "Value"
+            value_type: "REG_DWORD"
+            value: {}
+      - label: "Off"
+        registry_changes:
+          - hive: HKCU
+            key: "Software\\Test"
+            value_name: "Value"
+            value_type: "REG_DWORD"
+            value: {}
+"#,
+            id, values[0], values[1]
+        )
+    }
+
+    fn file(tweaks: &[String]) -> TweakFile {
+        let yaml = format!("{}tweaks:{}", CATEGORY, tweaks.concat());
+        serde_yaml_bw::from_str(&yaml).expect("test YAML should parse")
+    }
+
+    fn validate(tweaks: &[String]) -> ValidationContext {
+        let mut ctx = ValidationContext::new();
+        ctx.validate_file("test.yaml", &file(tweaks));
+        ctx
+    }
+
+    #[test]
+    fn a_valid_file_has_no_errors() {
+        let ctx = validate(&[tweak("show_seconds", ["1", "0"])]);
+        assert!(!ctx.has_errors(), "{:?}", ctx.errors);
+    }
+
+    #[test]
+    fn tweak_ids_are_snake_case() {
+        assert!(is_valid_tweak_id("show_seconds_2"));
+        assert!(is_valid_tweak_id("_private"));
+        for id in ["", "ShowSeconds", "show-seconds", "2fast", "show seconds"] {
+            assert!(!is_valid_tweak_id(id), "{:?}", id);
+        }
+        assert!(validate(&[tweak("ShowSeconds", ["1", "0"])]).has_errors());
+    }
+
+    #[test]
+    fn a_tweak_id_is_claimed_once_across_files() {
+        let mut ctx = ValidationContext::new();
+        assert!(ctx.validate_file("a.yaml", &file(&[tweak("show_seconds", ["1", "0"])])));
+
+        let mut other = file(&[tweak("show_seconds", ["1", "0"])]);
+        other.category.id = "other_tweaks".to_string();
+        assert!(!ctx.validate_file("b.yaml", &other));
+        assert!(
+            ctx.errors[0].contains("duplicate tweak ID"),
+            "{:?}",
+            ctx.errors
+        );
+    }
+
+    #[test]
+    fn a_tweak_needs_two_differently_labelled_options() {
+        let one_option = tweak("one_option", ["1", "0"]);
+        let one_option = &one_option[..one_option.find("      - label: \"Off\"").unwrap()];
+        let ctx = validate(&[one_option.to_string()]);
+        assert!(
+            ctx.errors[0].contains("at least 2 options"),
+            "{:?}",
+            ctx.errors
+        );
+
+        let same_labels = tweak("same_labels", ["1", "0"]).replace("\"Off\"", "\"on\"");
+        let ctx = validate(&[same_labels]);
+        assert!(
+            ctx.errors
+                .iter()
+                .any(|e| e.contains("duplicate option label")),
+            "{:?}",
+            ctx.errors
+        );
+    }
+
+    #[test]
+    fn registry_values_match_their_type() {
+        let ctx = validate(&[tweak("string_dword", ["\"1\"", "0"])]);
+        assert!(
+            ctx.errors[0].contains("REG_DWORD requires integer value, got string"),
+            "{:?}",
+            ctx.errors
+        );
+
+        let ctx = validate(&[tweak("huge_dword", ["4294967296", "0"])]);
+        assert!(
+            ctx.errors[0].contains("exceeds u32::MAX"),
+            "{:?}",
+            ctx.errors
+        );
+    }
+
+    #[test]
+    fn scheduler_task_patterns_are_valid_regex() {
+        let scheduled = |pattern: &str| {
+            tweak("scheduled", ["1", "0"]).replace(
+                "      - label: \"Off\"",
+                &format!(
+                    "        scheduler_changes:\n          - task_path: \"\\\\Test\"\n            task_name_pattern: \"{}\"\n            action: disable\n      - label: \"Off\"",
+                    pattern
+                ),
+            )
+        };
+        let ctx = validate(&[scheduled("Refresh|Reboot")]);
+        assert!(!ctx.has_errors(), "{:?}", ctx.errors);
+
+        let ctx = validate(&[scheduled("Refresh(")]);
+        assert!(
+            ctx.errors
+                .iter()
+                .any(|e| e.contains("invalid regex pattern")),
+            "{:?}",
+            ctx.errors
+        );
+    }
+
+    #[test]
+    fn tweaks_changing_a_setting_differently_conflict_unless_allowed() {
+        let ctx = validate(&[tweak("first", ["1", "0"]), tweak("second", ["2", "0"])]);
+        assert_eq!(ctx.errors.len(), 1, "{:?}", ctx.errors);
+        assert!(ctx.errors[0].contains("Tweak 'second'") && ctx.errors[0].contains("'first'"));
+
+        // The same single value isn't a conflict
+        let ctx = validate(&[tweak("first", ["1", "1"]), tweak("second", ["1", "1"])]);
+        assert!(!ctx.has_errors(), "{:?}", ctx.errors);
+
+        // Either side may allow it
+        let allowing = |id: &str, other: &str| {
+            tweak(id, ["2", "0"]).replace(
+                "    options:",
+                &format!("    overlaps_with: [{}]\n    options:", other),
+            )
+        };
+        for tweaks in [
+            [tweak("first", ["1", "0"]), allowing("second", "first")],
+            [allowing("first", "second"), tweak("second", ["2", "0"])],
+        ] {
+            let mut ctx = validate(&tweaks);
+            ctx.check_references();
+            assert!(!ctx.has_errors(), "{:?}", ctx.errors);
+            assert!(ctx.warnings.is_empty(), "{:?}", ctx.warnings);
+        }
+
+        let mut ctx = validate(&[allowing("first", "second")]);
+        ctx.check_references();
+        assert!(ctx
+            .warnings
+            .iter()
+            .any(|w| w.contains("overlaps_with lists 'second'")));
+    }
+
+    #[test]
+    fn into_definition_infers_admin_from_elevated_changes() {
+        let raw = file(&[tweak("as_system", ["1", "0"]).replace(
+            "    risk_level: low",
+            "    risk_level: low\n    requires_system: true",
+        )])
+        .tweaks
+        .remove(0);
+        let definition = raw.into_definition("test_tweaks");
+        assert!(definition.requires_admin && definition.requires_system);
+        assert!(!definition.custom);
+        assert_eq!(definition.category_id, "test_tweaks");
+    }
+
+    #[test]
+    fn tags_must_be_declared() {
+        let with_tags = |tags: &str| {
+            tweak("show_seconds", ["1", "0"]).replace(
+                "    risk_level: low",
+                &format!("    risk_level: low\n    tags: {}", tags),
+            )
+        };
+        let ctx = validate(&[with_tags("[privacy, telemetry]")]);
+        assert!(!ctx.has_errors(), "{:?}", ctx.errors);
+        assert!(validate(&[with_tags("[privacy, speed]")]).has_errors());
+
+        let ctx = validate(&[with_tags("[ads, ads]")]);
+        assert!(!ctx.has_errors() && !ctx.warnings.is_empty());
+    }
+
+    #[test]
+    fn build_ranges_are_real_builds_in_order() {
+        let with_range = |fields: &str| {
+            tweak("new_feature", ["1", "0"]).replace(
+                "    risk_level: low",
+                &format!("    risk_level: low\n{}", fields),
+            )
+        };
+        let ctx = validate(&[with_range(
+            "    min_build: \"22631.2428\"\n    max_build: 26100\n    editions: [Pro, Enterprise]",
+        )]);
+        assert!(!ctx.has_errors(), "{:?}", ctx.errors);
+
+        assert!(validate(&[with_range("    min_build: 26100\n    max_build: 22631")]).has_errors());
+        assert!(validate(&[with_range("    min_build: 9600")]).has_errors());
+        assert!(validate(&[with_range("    editions: []")]).has_errors());
+        // The same build, with a revision past the one the bound covers
+        assert!(validate(&[with_range(
+            "    min_build: \"22631.3000\"\n    max_build: \"22631.2428\""
+        )])
+        .has_errors());
+    }
+}
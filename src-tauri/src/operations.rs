@@ -0,0 +1,155 @@
+//! Cancellation of running tweak operations.
+//!
+//! `apply_tweak` and `batch_apply_tweaks` take an operation ID from the caller and register it here
+//! for as long as they run, so `cancel_operation` can stop them part-way. Cancelling only sets a
+//! flag: the operation notices it between changes and before each elevated call (an elevated call
+//! already running is stopped too, see `WaitLimit`), fails with `Error::Cancelled`, and rolls back
+//! what it changed like after any other failure.
+//!
+//! The flag of the running operation is kept per thread, so the effect services can check it
+//! without every call passing it down. Commands run an operation start to finish on one thread.
+
+use crate::error::{Error, Result};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+thread_local! {
+    /// Cancellation flag of the operation running on this thread, if it can be cancelled
+    static CURRENT: RefCell<Option<Arc<AtomicBool>>> = const { RefCell::new(None) };
+}
+
+/// The registry's part of `AppState`: cancellation flags of the operations running now, by ID
+#[derive(Debug, Default)]
+pub struct Operations {
+    running: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+/// Registers an operation and makes it the current one on this thread until dropped
+pub struct OperationScope<'a> {
+    operations: &'a Operations,
+    id: Option<String>,
+    previous: Option<Arc<AtomicBool>>,
+}
+
+impl Drop for OperationScope<'_> {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+        if let Some(id) = &self.id {
+            self.operations.lock().remove(id);
+        }
+    }
+}
+
+impl Operations {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Arc<AtomicBool>>> {
+        self.running.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Run the rest of this thread's work as operation `id`. Without an ID the operation can't be
+    /// cancelled. An ID that is already running is refused.
+    pub fn begin(&self, id: Option<String>) -> Result<OperationScope<'_>> {
+        let flag = Arc::new(AtomicBool::new(false));
+        if let Some(id) = &id {
+            let mut running = self.lock();
+            if running.contains_key(id) {
+                return Err(Error::ValidationError(format!(
+                    "Operation '{}' is already running",
+                    id
+                )));
+            }
+            running.insert(id.clone(), Arc::clone(&flag));
+        }
+        let previous = CURRENT.with(|current| current.borrow_mut().replace(flag));
+        Ok(OperationScope {
+            operations: self,
+            id,
+            previous,
+        })
+    }
+
+    /// Ask operation `id` to stop. Returns false when no operation with that ID is running (it may
+    /// just have finished).
+    pub fn cancel(&self, id: &str) -> bool {
+        match self.lock().get(id) {
+            Some(flag) => {
+                flag.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// The cancellation flag of the operation running on this thread, for work that outlives the
+/// thread-local (an elevated call's watchdog)
+pub fn current_flag() -> Option<Arc<AtomicBool>> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+/// Whether the operation running on this thread has been cancelled
+pub fn is_cancelled() -> bool {
+    CURRENT.with(|current| {
+        current
+            .borrow()
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::SeqCst))
+    })
+}
+
+/// Fail with `Error::Cancelled` if the operation running on this thread has been cancelled.
+/// `before` names what was about to run, for the error message.
+pub fn check(before: &str) -> Result<()> {
+    if is_cancelled() {
+        log::info!("Operation cancelled before {}", before);
+        return Err(Error::Cancelled(format!(
+            "Operation was cancelled before {}",
+            before
+        )));
+    }
+    Ok(())
+}
+
+/// Run `f` where cancellation is not honoured: a rollback, or the follow-up work of changes that
+/// are already in place, which must run to the end even when the operation was cancelled
+pub fn uncancellable<T>(f: impl FnOnce() -> T) -> T {
+    struct Restore(Option<Arc<AtomicBool>>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let flag = self.0.take();
+            CURRENT.with(|current| *current.borrow_mut() = flag);
+        }
+    }
+    let _restore = Restore(CURRENT.with(|current| current.borrow_mut().take()));
+    f()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancel_reaches_the_running_operation_until_it_ends() {
+        let operations = Operations::default();
+        {
+            let _scope = operations.begin(Some("op-1".to_string())).unwrap();
+            assert!(check("the first change").is_ok());
+            assert!(operations.cancel("op-1"));
+            assert!(matches!(check("the next change"), Err(Error::Cancelled(_))));
+            assert!(uncancellable(|| check("the rollback")).is_ok());
+            assert!(is_cancelled());
+        }
+        assert!(!is_cancelled());
+        assert!(!operations.cancel("op-1"));
+    }
+
+    #[test]
+    fn a_running_id_cannot_be_reused() {
+        let operations = Operations::default();
+        let _scope = operations.begin(Some("op-2".to_string())).unwrap();
+        assert!(operations.begin(Some("op-2".to_string())).is_err());
+        assert!(operations.begin(None).is_ok());
+    }
+}
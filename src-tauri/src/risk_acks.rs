@@ -0,0 +1,137 @@
+//! Acknowledgement of high and critical risk tweaks before they're applied.
+//!
+//! The confirmation the UI shows for a risky tweak is only a UI convention: a scripted call, or a
+//! frontend bug that skips the dialog, would apply the tweak all the same. So applying a tweak whose
+//! `risk_level` is high or critical also takes a token from `acknowledge_risk`. A token is issued
+//! for one tweak, expires after `TOKEN_LIFETIME`, and is used up by the apply it is given to,
+//! whether that apply succeeds or not.
+
+use crate::error::{Error, Result};
+use crate::models::RiskLevel;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// How long an acknowledgement stays valid
+pub const TOKEN_LIFETIME: Duration = Duration::from_secs(5 * 60);
+
+/// Whether applying a tweak of this risk level takes an acknowledgement
+pub fn requires_acknowledgement(risk_level: RiskLevel) -> bool {
+    matches!(risk_level, RiskLevel::High | RiskLevel::Critical)
+}
+
+/// A token returned by `acknowledge_risk`
+#[derive(Debug, Clone, Serialize)]
+pub struct RiskAcknowledgement {
+    pub tweak_id: String,
+    pub token: String,
+    /// Seconds until the token expires
+    pub expires_in_secs: u64,
+}
+
+/// The acknowledgements' part of `AppState`: tokens issued and not used yet
+#[derive(Debug, Default)]
+pub struct RiskAcknowledgements {
+    issued: Mutex<HashMap<String, Issued>>,
+}
+
+#[derive(Debug)]
+struct Issued {
+    tweak_id: String,
+    expires: Instant,
+}
+
+impl RiskAcknowledgements {
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Issued>> {
+        self.issued.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Issue a token for applying `tweak_id`
+    pub fn issue(&self, tweak_id: &str) -> Result<RiskAcknowledgement> {
+        let mut bytes = [0u8; 16];
+        getrandom::getrandom(&mut bytes)
+            .map_err(|e| Error::WindowsApi(format!("Failed to generate a token: {}", e)))?;
+        let token: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+        let now = Instant::now();
+        let mut issued = self.lock();
+        issued.retain(|_, entry| entry.expires > now);
+        issued.insert(
+            token.clone(),
+            Issued {
+                tweak_id: tweak_id.to_string(),
+                expires: now + TOKEN_LIFETIME,
+            },
+        );
+        Ok(RiskAcknowledgement {
+            tweak_id: tweak_id.to_string(),
+            token,
+            expires_in_secs: TOKEN_LIFETIME.as_secs(),
+        })
+    }
+
+    /// Allow applying `tweak_id`: without a risk that needs acknowledging, always; otherwise only
+    /// with one of `tokens` issued for it and not expired, which is used up
+    pub fn redeem(&self, tweak_id: &str, risk_level: RiskLevel, tokens: &[String]) -> Result<()> {
+        if !requires_acknowledgement(risk_level) {
+            return Ok(());
+        }
+        let now = Instant::now();
+        let mut issued = self.lock();
+        let token = tokens.iter().find(|token| {
+            issued
+                .get(*token)
+                .is_some_and(|entry| entry.tweak_id == tweak_id && entry.expires > now)
+        });
+        match token {
+            Some(token) => {
+                issued.remove(token);
+                Ok(())
+            }
+            None => {
+                log::warn!(
+                    "Refused to apply '{}' ({:?} risk) without a valid acknowledgement",
+                    tweak_id,
+                    risk_level
+                );
+                Err(Error::RiskNotAcknowledged(format!(
+                    "'{}' is a {:?} risk tweak; acknowledge the risk before applying it",
+                    tweak_id, risk_level
+                )))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_token_applies_its_own_tweak_once() {
+        let acks = RiskAcknowledgements::default();
+        let token = acks.issue("risky").unwrap().token;
+
+        assert!(acks
+            .redeem("other", RiskLevel::High, &[token.clone()])
+            .is_err());
+        assert!(acks
+            .redeem("risky", RiskLevel::High, &[token.clone()])
+            .is_ok());
+        assert!(matches!(
+            acks.redeem("risky", RiskLevel::High, &[token]),
+            Err(Error::RiskNotAcknowledged(_))
+        ));
+        assert!(acks.redeem("safe", RiskLevel::Medium, &[]).is_ok());
+    }
+
+    #[test]
+    fn an_expired_token_is_refused() {
+        let acks = RiskAcknowledgements::default();
+        let token = acks.issue("risky").unwrap().token;
+        acks.lock().get_mut(&token).unwrap().expires = Instant::now();
+
+        assert!(acks.redeem("risky", RiskLevel::Critical, &[token]).is_err());
+    }
+}
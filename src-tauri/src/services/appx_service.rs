@@ -0,0 +1,167 @@
+//! AppX (UWP) package service for removing, deprovisioning, and re-registering packages.
+//!
+//! Uses the Appx PowerShell module (`Get-AppxPackage`, `Remove-AppxPackage`,
+//! `Get-AppxProvisionedPackage`, ...) and reads its output as JSON, so nothing depends on
+//! localized text. `all_users` scope and provisioning changes require administrator privileges.
+
+use crate::error::Error;
+use crate::models::{AppxAction, AppxChange, AppxPackageRecord, AppxScope};
+use crate::services::powershell::{ps_quote, run_script};
+use serde::Deserialize;
+
+/// Shape of one `Get-AppxPackage` result after `Select-Object PackageFullName, InstallLocation`.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RawPackage {
+    package_full_name: String,
+    #[serde(default)]
+    install_location: Option<String>,
+}
+
+/// `-AllUsers` switch for the given scope (empty for the current user).
+fn all_users_switch(scope: AppxScope) -> &'static str {
+    match scope {
+        AppxScope::CurrentUser => "",
+        AppxScope::AllUsers => " -AllUsers",
+    }
+}
+
+/// List installed packages whose name matches `package` (wildcards allowed).
+pub fn list_installed(package: &str, scope: AppxScope) -> Result<Vec<AppxPackageRecord>, Error> {
+    let script = format!(
+        "ConvertTo-Json -Compress -InputObject @(Get-AppxPackage -Name {}{} | Select-Object PackageFullName, InstallLocation)",
+        ps_quote(package),
+        all_users_switch(scope)
+    );
+    parse_package_list(&run_script(&script)?)
+}
+
+/// List provisioned package names whose display name matches `package` (wildcards allowed).
+pub fn list_provisioned(package: &str) -> Result<Vec<String>, Error> {
+    let script = format!(
+        "ConvertTo-Json -Compress -InputObject @(Get-AppxProvisionedPackage -Online | Where-Object DisplayName -like {} | ForEach-Object PackageName)",
+        ps_quote(package)
+    );
+    parse_name_list(&run_script(&script)?)
+}
+
+/// Uninstall every package matching `package` in the given scope.
+pub fn remove_package(package: &str, scope: AppxScope) -> Result<(), Error> {
+    let switch = all_users_switch(scope);
+    let script = format!(
+        "Get-AppxPackage -Name {}{} | Remove-AppxPackage{}",
+        ps_quote(package),
+        switch,
+        switch
+    );
+    run_script(&script)?;
+    log::info!("Removed AppX package '{}' ({})", package, scope.as_str());
+    Ok(())
+}
+
+/// Remove every provisioned package matching `package` from the system image.
+pub fn remove_provisioned(package: &str) -> Result<(), Error> {
+    let script = format!(
+        "Get-AppxProvisionedPackage -Online | Where-Object DisplayName -like {} | Remove-AppxProvisionedPackage -Online | Out-Null",
+        ps_quote(package)
+    );
+    run_script(&script)?;
+    log::info!("Removed provisioned AppX package '{}'", package);
+    Ok(())
+}
+
+/// Re-register a package for the current user from its install folder.
+pub fn register_package(install_location: &str) -> Result<(), Error> {
+    let script = format!(
+        "Add-AppxPackage -DisableDevelopmentMode -Register (Join-Path {} 'AppxManifest.xml')",
+        ps_quote(install_location)
+    );
+    run_script(&script)?;
+    log::info!("Registered AppX package from '{}'", install_location);
+    Ok(())
+}
+
+/// Re-register every package matching `package` for the current user, using the files of any
+/// user's (or the staged) copy. Fails if no copy of the package is left on disk.
+pub fn reinstall_package(package: &str) -> Result<(), Error> {
+    let script = format!(
+        "$pkgs = @(Get-AppxPackage -AllUsers -Name {} | Where-Object InstallLocation | Sort-Object PackageFullName -Unique); \
+         if ($pkgs.Count -eq 0) {{ throw 'No installed or staged copy of the package was found' }}; \
+         foreach ($p in $pkgs) {{ Add-AppxPackage -DisableDevelopmentMode -Register (Join-Path $p.InstallLocation 'AppxManifest.xml') }}",
+        ps_quote(package)
+    );
+    run_script(&script)?;
+    log::info!("Re-registered AppX package '{}'", package);
+    Ok(())
+}
+
+/// Apply an AppX change
+pub fn apply_appx_change(change: &AppxChange) -> Result<(), Error> {
+    match change.action {
+        AppxAction::Remove => remove_package(&change.package, change.scope),
+        AppxAction::RemoveProvisioned => remove_provisioned(&change.package),
+        AppxAction::Reinstall => reinstall_package(&change.package),
+    }
+}
+
+/// Parse the JSON array emitted by [`list_installed`].
+fn parse_package_list(json: &str) -> Result<Vec<AppxPackageRecord>, Error> {
+    let json = json.trim();
+    if json.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let raw: Vec<RawPackage> = serde_json::from_str(json)
+        .map_err(|e| Error::CommandExecution(format!("Unexpected AppX package list: {}", e)))?;
+    Ok(raw
+        .into_iter()
+        .map(|p| AppxPackageRecord {
+            full_name: p.package_full_name,
+            install_location: p.install_location.filter(|l| !l.is_empty()),
+        })
+        .collect())
+}
+
+/// Parse the JSON string array emitted by [`list_provisioned`].
+fn parse_name_list(json: &str) -> Result<Vec<String>, Error> {
+    let json = json.trim();
+    if json.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    serde_json::from_str(json)
+        .map_err(|e| Error::CommandExecution(format!("Unexpected AppX provisioned list: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_package_list_parses_with_and_without_install_locations() {
+        let json = r#"[{"PackageFullName":"Microsoft.BingNews_4.55.0.0_x64__8wekyb3d8bbwe","InstallLocation":"C:\\Program Files\\WindowsApps\\Microsoft.BingNews_4.55.0.0_x64__8wekyb3d8bbwe"},{"PackageFullName":"Staged_1.0.0.0_x64__abc","InstallLocation":null}]"#;
+        let packages = parse_package_list(json).unwrap();
+        assert_eq!(packages.len(), 2);
+        assert_eq!(
+            packages[0].full_name,
+            "Microsoft.BingNews_4.55.0.0_x64__8wekyb3d8bbwe"
+        );
+        assert!(packages[0].install_location.is_some());
+        assert_eq!(packages[1].install_location, None);
+    }
+
+    #[test]
+    fn empty_output_is_an_empty_list() {
+        assert!(parse_package_list("[]").unwrap().is_empty());
+        assert!(parse_package_list("\r\n").unwrap().is_empty());
+        assert!(parse_name_list("[]").unwrap().is_empty());
+    }
+
+    #[test]
+    #[ignore = "queries the live Appx packages through PowerShell — run with --ignored"]
+    fn a_package_that_is_not_installed_lists_nothing() {
+        let packages =
+            list_installed("MagicXToolboxTest.DoesNotExist", AppxScope::CurrentUser).unwrap();
+        assert!(packages.is_empty());
+    }
+}
@@ -0,0 +1,196 @@
+//! Audio settings (sound scheme, startup sound, communications ducking, audio enhancements).
+//!
+//! Every setting an [`AudioChange`] covers lives in the registry, so a change is turned into the
+//! registry changes it makes and goes through the registry apply, snapshot, and detection paths.
+//! Some of those values are only known at run time: the sound scheme touches one value per sound
+//! event, and audio enhancements one per playback device.
+
+use crate::error::Error;
+use crate::models::{
+    AudioChange, CommunicationsDucking, RegistryAction, RegistryChange, RegistryHive,
+    RegistryValueType, SoundScheme,
+};
+use crate::services::registry_service;
+
+const SCHEMES_KEY: &str = "AppEvents\\Schemes";
+const SCHEME_APPS_KEY: &str = "AppEvents\\Schemes\\Apps";
+const DUCKING_KEY: &str = "Software\\Microsoft\\Multimedia\\Audio";
+const BOOT_ANIMATION_KEY: &str =
+    "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Authentication\\LogonUI\\BootAnimation";
+const RENDER_DEVICES_KEY: &str =
+    "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\MMDevices\\Audio\\Render";
+/// `PKEY_AudioEndpoint_Disable_SysFx`: 1 turns a device's enhancements off
+const DISABLE_SYSFX_VALUE: &str = "{1da5d803-d492-4edd-8c23-e0c0ffee7f0e},5";
+
+fn set_value(
+    hive: RegistryHive,
+    key: String,
+    value_name: &str,
+    value_type: RegistryValueType,
+    value: serde_json::Value,
+    skip_validation: bool,
+) -> RegistryChange {
+    RegistryChange {
+        hive,
+        key,
+        value_name: value_name.to_string(),
+        action: RegistryAction::Set,
+        value_type: Some(value_type),
+        value: Some(value),
+        windows_versions: None,
+        conditions: None,
+        requires_reboot: false,
+        requires_logoff: false,
+        skip_validation,
+        elevation: None,
+    }
+}
+
+/// `UserDuckingPreference` value for a ducking setting
+fn ducking_preference(ducking: CommunicationsDucking) -> u32 {
+    match ducking {
+        CommunicationsDucking::MuteOthers => 0,
+        CommunicationsDucking::Reduce80 => 1,
+        CommunicationsDucking::Reduce50 => 2,
+        CommunicationsDucking::DoNothing => 3,
+    }
+}
+
+/// The registry changes an audio change makes on this machine
+pub fn to_registry_changes(change: &AudioChange) -> Result<Vec<RegistryChange>, Error> {
+    let skip = change.skip_validation;
+    let mut changes = Vec::new();
+
+    if let Some(scheme) = change.sound_scheme {
+        changes.extend(sound_scheme_changes(scheme, skip)?);
+    }
+
+    if let Some(enabled) = change.startup_sound {
+        changes.push(set_value(
+            RegistryHive::Hklm,
+            BOOT_ANIMATION_KEY.to_string(),
+            "DisableStartupSound",
+            RegistryValueType::Dword,
+            serde_json::json!(u32::from(!enabled)),
+            skip,
+        ));
+    }
+
+    if let Some(ducking) = change.communications_ducking {
+        changes.push(set_value(
+            RegistryHive::Hkcu,
+            DUCKING_KEY.to_string(),
+            "UserDuckingPreference",
+            RegistryValueType::Dword,
+            serde_json::json!(ducking_preference(ducking)),
+            skip,
+        ));
+    }
+
+    if let Some(enabled) = change.audio_enhancements {
+        for fx_key in playback_fx_keys()? {
+            changes.push(set_value(
+                RegistryHive::Hklm,
+                fx_key,
+                DISABLE_SYSFX_VALUE,
+                RegistryValueType::Dword,
+                serde_json::json!(u32::from(!enabled)),
+                skip,
+            ));
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Select a sound scheme the way the Sound control panel does: name it under `AppEvents\Schemes`
+/// and copy its sound into every event's `.Current` key (empty for "No Sounds", the event's
+/// `.Default` sound for "Windows Default").
+fn sound_scheme_changes(
+    scheme: SoundScheme,
+    skip_validation: bool,
+) -> Result<Vec<RegistryChange>, Error> {
+    let hive = RegistryHive::Hkcu;
+    let scheme_name = match scheme {
+        SoundScheme::None => ".None",
+        SoundScheme::Default => ".Default",
+    };
+    let mut changes = vec![set_value(
+        hive,
+        SCHEMES_KEY.to_string(),
+        "",
+        RegistryValueType::String,
+        serde_json::json!(scheme_name),
+        skip_validation,
+    )];
+
+    for app in registry_service::list_subkeys(&hive, SCHEME_APPS_KEY)? {
+        let app_key = format!("{}\\{}", SCHEME_APPS_KEY, app);
+        for event in registry_service::list_subkeys(&hive, &app_key)? {
+            let event_key = format!("{}\\{}", app_key, event);
+            let current_key = format!("{}\\.Current", event_key);
+            // Only events that have a sound slot; others aren't part of any scheme
+            if !registry_service::key_exists(&hive, &current_key)? {
+                continue;
+            }
+
+            let sound = match scheme {
+                SoundScheme::None => String::new(),
+                SoundScheme::Default => {
+                    let default_key = format!("{}\\.Default", event_key);
+                    match registry_service::read_string(&hive, &default_key, "") {
+                        Ok(sound) => sound.unwrap_or_default(),
+                        Err(Error::RegistryKeyNotFound(_)) => String::new(),
+                        Err(e) => return Err(e),
+                    }
+                }
+            };
+            changes.push(set_value(
+                hive,
+                current_key,
+                "",
+                RegistryValueType::ExpandString,
+                serde_json::json!(sound),
+                skip_validation,
+            ));
+        }
+    }
+
+    Ok(changes)
+}
+
+/// `FxProperties` keys (under HKLM) of every playback device that has audio effects
+fn playback_fx_keys() -> Result<Vec<String>, Error> {
+    let hive = RegistryHive::Hklm;
+    let mut keys = Vec::new();
+    for device in registry_service::list_subkeys(&hive, RENDER_DEVICES_KEY)? {
+        let fx_key = format!("{}\\{}\\FxProperties", RENDER_DEVICES_KEY, device);
+        if registry_service::key_exists(&hive, &fx_key)? {
+            keys.push(fx_key);
+        }
+    }
+    Ok(keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ducking_and_startup_sound_map_to_fixed_values() {
+        let change: AudioChange = serde_json::from_value(serde_json::json!({
+            "startup_sound": false,
+            "communications_ducking": "do_nothing",
+        }))
+        .unwrap();
+        let changes = to_registry_changes(&change).unwrap();
+
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].hive, RegistryHive::Hklm);
+        assert_eq!(changes[0].value_name, "DisableStartupSound");
+        assert_eq!(changes[0].value, Some(serde_json::json!(1)));
+        assert_eq!(changes[1].hive, RegistryHive::Hkcu);
+        assert_eq!(changes[1].value_name, "UserDuckingPreference");
+        assert_eq!(changes[1].value, Some(serde_json::json!(3)));
+    }
+}
@@ -0,0 +1,358 @@
+//! Elevation audit log: a record of everything run as SYSTEM or TrustedInstaller.
+//!
+//! Every operation the elevated broker runs is appended as one JSON line to
+//! `%ProgramData%\MagicX Toolbox\audit\elevation.log`: its level, what ran (the command line for
+//! commands and scripts, the typed operation otherwise), its exit code, and how long it took. The
+//! file is only ever appended to, and is shared by every account on the machine like the storage
+//! settings.
+//!
+//! Entries form a hash chain: each holds the SHA-256 of the entry before it and its own hash over
+//! both, so editing, removing, or reordering an entry breaks the chain there, which reading the log
+//! reports. The last entry's sequence number and hash are also kept in the registry
+//! (`HKLM\SOFTWARE\MagicX Toolbox`, `AuditHead`), which only administrators can write, so cutting
+//! entries off the end, or deleting the file, shows as a head mismatch rather than a shorter log
+//! that still verifies. That makes tampering evident rather than impossible; an administrator able
+//! to rewrite both the file and the value can recompute every hash.
+
+use crate::error::Error;
+use crate::models::RegistryHive;
+use crate::services::backup;
+use crate::services::elevation::Elevation;
+use crate::services::registry_service;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// The log file, relative to the ProgramData app folder
+const AUDIT_FILE: &str = "audit\\elevation.log";
+
+/// `prev_hash` of the first entry
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Where the last entry's `"<seq> <hash>"` is kept, outside the log file
+const HEAD_KEY: &str = "SOFTWARE\\MagicX Toolbox";
+const HEAD_VALUE: &str = "AuditHead";
+
+/// The last entry written (sequence number and hash), read from the file on first use. Also
+/// serializes appends, so the chain stays linear across threads.
+static HEAD: Mutex<Option<(u64, String)>> = Mutex::new(None);
+
+/// One elevated operation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Increasing from 1, without gaps
+    pub seq: u64,
+    /// When it finished (RFC 3339)
+    pub timestamp: String,
+    /// "SYSTEM" or "TrustedInstaller"
+    pub level: String,
+    /// What ran
+    pub command: String,
+    /// Exit code of a command or script; 0 for another operation that succeeded; None when it
+    /// failed without one (the reason is in `error`)
+    pub exit_code: Option<i32>,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+    /// Hash of the entry before this one
+    pub prev_hash: String,
+    /// Hash of this entry's fields, `prev_hash` included
+    pub hash: String,
+}
+
+impl AuditEntry {
+    fn compute_hash(&self) -> String {
+        let fields = (
+            self.seq,
+            &self.timestamp,
+            &self.level,
+            &self.command,
+            self.exit_code,
+            &self.error,
+            self.duration_ms,
+            &self.prev_hash,
+        );
+        let canonical = serde_json::to_vec(&fields).unwrap_or_default();
+        format!("{:x}", Sha256::digest(&canonical))
+    }
+}
+
+/// How the log's last entry compares with the head kept in the registry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HeadCheck {
+    /// The last entry is the recorded head (or the log is empty and no head was recorded)
+    Matches,
+    /// The log ends before the recorded head: entries were cut off the end, or the file deleted
+    Truncated,
+    /// The entry in the head's place differs from it, or entries follow it that were never
+    /// recorded
+    Mismatch,
+    /// The log has entries but no head was recorded
+    Missing,
+}
+
+/// The log as read back, with the result of checking its chain
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditLog {
+    /// Newest first
+    pub entries: Vec<AuditEntry>,
+    /// Whether every entry is intact and in its place, and the last one is the recorded head
+    pub intact: bool,
+    /// Line (from 1) where the chain first breaks, if it does
+    pub broken_at_line: Option<usize>,
+    pub head: HeadCheck,
+}
+
+fn audit_path() -> Result<PathBuf, Error> {
+    backup::program_data_file(AUDIT_FILE)
+}
+
+/// Parse a recorded head, `"<seq> <hash>"`
+fn parse_head(head: &str) -> Option<(u64, String)> {
+    let (seq, hash) = head.split_once(' ')?;
+    Some((seq.parse().ok()?, hash.to_string()))
+}
+
+fn read_head() -> Result<Option<String>, Error> {
+    registry_service::read_string(&RegistryHive::Hklm, HEAD_KEY, HEAD_VALUE)
+}
+
+fn write_head(seq: u64, hash: &str) -> Result<(), Error> {
+    registry_service::set_string(
+        &RegistryHive::Hklm,
+        HEAD_KEY,
+        HEAD_VALUE,
+        &format!("{} {}", seq, hash),
+    )
+    .map_err(|e| Error::BackupFailed(format!("Failed to record the audit log head: {}", e)))
+}
+
+/// Parse the log's contents and check the chain, then check the last entry against the recorded
+/// head. Lines that aren't entries are skipped, and break the chain like a modified entry would.
+fn verify(content: &str, head: Option<&str>) -> AuditLog {
+    let mut entries = Vec::new();
+    let mut broken_at_line = None;
+    let mut prev = (0, GENESIS_HASH.to_string());
+    for (index, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = serde_json::from_str::<AuditEntry>(line).ok();
+        let linked = entry.as_ref().is_some_and(|entry| {
+            entry.seq == prev.0 + 1
+                && entry.prev_hash == prev.1
+                && entry.hash == entry.compute_hash()
+        });
+        if !linked && broken_at_line.is_none() {
+            broken_at_line = Some(index + 1);
+        }
+        if let Some(entry) = entry {
+            prev = (entry.seq, entry.hash.clone());
+            entries.push(entry);
+        }
+    }
+
+    let last = entries.last();
+    let head = match head.map(parse_head) {
+        None if last.is_none() => HeadCheck::Matches,
+        None => HeadCheck::Missing,
+        Some(Some((seq, _))) if last.map_or(0, |e| e.seq) < seq => HeadCheck::Truncated,
+        Some(Some((seq, hash))) if last.is_some_and(|e| e.seq == seq && e.hash == hash) => {
+            HeadCheck::Matches
+        }
+        Some(_) => HeadCheck::Mismatch,
+    };
+
+    entries.reverse();
+    AuditLog {
+        intact: broken_at_line.is_none() && head == HeadCheck::Matches,
+        broken_at_line,
+        head,
+        entries,
+    }
+}
+
+fn read_content() -> Result<String, Error> {
+    match fs::read_to_string(audit_path()?) {
+        Ok(content) => Ok(content),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(Error::BackupFailed(format!(
+            "Failed to read the elevation audit log: {}",
+            e
+        ))),
+    }
+}
+
+/// The log, newest first, and whether its chain is intact and ends at the recorded head
+pub fn read_log() -> Result<AuditLog, Error> {
+    Ok(verify(&read_content()?, read_head()?.as_deref()))
+}
+
+fn append(
+    level: Elevation,
+    command: &str,
+    exit_code: Option<i32>,
+    error: Option<String>,
+    duration: Duration,
+) -> Result<(), Error> {
+    let mut head = HEAD.lock().unwrap_or_else(|e| e.into_inner());
+    if head.is_none() {
+        // Continue from the last entry that parses, even after a break, so new entries chain on.
+        // When the file ends before the recorded head, continue from the head instead: the next
+        // entry then doesn't link to the file's last one, so the truncation stays evident.
+        let last = verify(&read_content()?, None).entries.into_iter().next();
+        let last = last.map_or((0, GENESIS_HASH.to_string()), |e| (e.seq, e.hash));
+        let recorded = read_head()?.as_deref().and_then(parse_head);
+        *head = Some(match recorded {
+            Some(recorded) if recorded.0 >= last.0 => recorded,
+            _ => last,
+        });
+    }
+    let (seq, prev_hash) = head.clone().unwrap_or((0, GENESIS_HASH.to_string()));
+
+    let mut entry = AuditEntry {
+        seq: seq + 1,
+        timestamp: chrono::Local::now().to_rfc3339(),
+        level: level.label().to_string(),
+        command: command.to_string(),
+        exit_code,
+        error,
+        duration_ms: duration.as_millis() as u64,
+        prev_hash,
+        hash: String::new(),
+    };
+    entry.hash = entry.compute_hash();
+
+    let path = audit_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| {
+            Error::BackupFailed(format!("Failed to create {}: {}", dir.display(), e))
+        })?;
+    }
+    let mut line = serde_json::to_string(&entry)
+        .map_err(|e| Error::BackupFailed(format!("Failed to serialize audit entry: {}", e)))?;
+    line.push('\n');
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .map_err(|e| {
+            Error::BackupFailed(format!("Failed to write the elevation audit log: {}", e))
+        })?;
+
+    let written = head.insert((entry.seq, entry.hash));
+    write_head(written.0, &written.1)
+}
+
+/// Record an elevated operation. The operation has already run, so a failure to record it is
+/// logged rather than failing it.
+pub fn record(
+    level: Elevation,
+    command: &str,
+    exit_code: Option<i32>,
+    error: Option<String>,
+    duration: Duration,
+) {
+    if let Err(e) = append(level, command, exit_code, error, duration) {
+        log::warn!("Failed to record '{}' in the audit log: {}", command, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain(commands: &[&str]) -> Vec<AuditEntry> {
+        let mut prev = (0, GENESIS_HASH.to_string());
+        commands
+            .iter()
+            .map(|command| {
+                let mut entry = AuditEntry {
+                    seq: prev.0 + 1,
+                    timestamp: "2026-01-01T10:00:00+00:00".to_string(),
+                    level: "SYSTEM".to_string(),
+                    command: command.to_string(),
+                    exit_code: Some(0),
+                    error: None,
+                    duration_ms: 12,
+                    prev_hash: prev.1.clone(),
+                    hash: String::new(),
+                };
+                entry.hash = entry.compute_hash();
+                prev = (entry.seq, entry.hash.clone());
+                entry
+            })
+            .collect()
+    }
+
+    fn to_lines(entries: &[AuditEntry]) -> String {
+        entries
+            .iter()
+            .map(|entry| serde_json::to_string(entry).unwrap() + "\n")
+            .collect()
+    }
+
+    fn head_of(entries: &[AuditEntry]) -> String {
+        let last = entries.last().unwrap();
+        format!("{} {}", last.seq, last.hash)
+    }
+
+    #[test]
+    fn an_untouched_log_verifies() {
+        let entries = chain(&["a", "b", "c"]);
+        let log = verify(&to_lines(&entries), Some(head_of(&entries).as_str()));
+        assert!(log.intact);
+        assert_eq!(log.head, HeadCheck::Matches);
+        assert_eq!(log.entries.len(), 3);
+        assert_eq!(log.entries[0].command, "c");
+    }
+
+    #[test]
+    fn edited_removed_or_reordered_entries_break_the_chain() {
+        let mut edited = chain(&["a", "b", "c"]);
+        edited[1].command = "something else".to_string();
+        assert_eq!(verify(&to_lines(&edited), None).broken_at_line, Some(2));
+
+        let mut removed = chain(&["a", "b", "c"]);
+        removed.remove(1);
+        assert_eq!(verify(&to_lines(&removed), None).broken_at_line, Some(2));
+
+        let mut reordered = chain(&["a", "b", "c"]);
+        reordered.swap(0, 2);
+        assert_eq!(verify(&to_lines(&reordered), None).broken_at_line, Some(1));
+
+        let garbage = to_lines(&chain(&["a"])) + "not an entry\n";
+        assert_eq!(verify(&garbage, None).broken_at_line, Some(2));
+    }
+
+    #[test]
+    fn entries_cut_off_the_end_or_rewritten_miss_the_recorded_head() {
+        let entries = chain(&["a", "b", "c"]);
+        let head = head_of(&entries);
+
+        let truncated = verify(&to_lines(&entries[..2]), Some(head.as_str()));
+        assert_eq!(truncated.broken_at_line, None);
+        assert_eq!(truncated.head, HeadCheck::Truncated);
+        assert!(!truncated.intact);
+        assert_eq!(verify("", Some(head.as_str())).head, HeadCheck::Truncated);
+
+        let rewritten = chain(&["a", "b", "forged"]);
+        assert_eq!(
+            verify(&to_lines(&rewritten), Some(head.as_str())).head,
+            HeadCheck::Mismatch
+        );
+
+        let unrecorded = verify(&to_lines(&entries), Some(head_of(&entries[..2]).as_str()));
+        assert_eq!(unrecorded.head, HeadCheck::Mismatch);
+
+        let missing = verify(&to_lines(&entries), None);
+        assert_eq!(missing.head, HeadCheck::Missing);
+        assert!(!missing.intact);
+        assert!(verify("", None).intact);
+    }
+}
@@ -0,0 +1,354 @@
+//! Backup archives: every snapshot, with its file backups, in one compressed file.
+//!
+//! An archive lets the backup state move with the user, e.g. when reinstalling the app or switching
+//! from the portable build to the installed one. It's a gzip-compressed JSON document with a format
+//! marker and a layout version, so an archive from a newer app is rejected instead of half-read.
+//! Snapshots are kept as raw JSON and parsed on import, so an older snapshot inside an archive loads
+//! the same way an older snapshot file does. A tweak's earlier generations travel with it as files,
+//! next to its file backups. Encrypted snapshots and generations are decrypted on export, since the
+//! key stays on this machine. The user's tweak notes travel with the archive too.
+
+use crate::error::Error;
+use crate::services::notes_service::{self, TweakNotes};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+use super::encryption;
+use super::integrity;
+use super::rename::migrate_renamed_tweaks;
+use super::storage::{
+    file_backups_dir_name, get_applied_tweaks, get_snapshot_path, get_snapshots_dir,
+    history_dir_name, save_snapshot, snapshot_exists, snapshot_from_json,
+};
+
+/// Value of the `format` field that marks a file as a backup archive
+const ARCHIVE_FORMAT: &str = "magicx-backup";
+
+/// Current archive layout. Bump it when the layout changes and keep reading the older ones.
+pub const ARCHIVE_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BackupArchive {
+    format: String,
+    version: u32,
+    app_version: String,
+    created_at: String,
+    /// MachineGuid of the machine the archive was made on
+    machine_guid: Option<String>,
+    /// Snapshot files as stored on disk
+    snapshots: Vec<serde_json::Value>,
+    /// Everything in the snapshots' file-backup and history folders
+    files: Vec<ArchivedFile>,
+    /// The user's notes, by tweak ID (absent from archives made before notes existed)
+    #[serde(default)]
+    notes: TweakNotes,
+}
+
+/// One entry of a file-backup or history folder, with its path relative to the snapshots directory
+#[derive(Debug, Serialize, Deserialize)]
+struct ArchivedFile {
+    /// `/`-separated, starting with the tweak's `{tweak_id}.files` or `{tweak_id}.history` folder
+    path: String,
+    /// File contents, or None for a directory
+    data: Option<Vec<u8>>,
+}
+
+/// Outcome of `import_backup_archive`
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchiveImportReport {
+    /// Tweaks whose snapshots were imported
+    pub imported: Vec<String>,
+    /// Tweaks skipped because a snapshot for them already exists here
+    pub skipped: Vec<String>,
+    /// Whether the archive was made on a different machine
+    pub from_other_machine: bool,
+    /// Notes added for tweaks that had none here
+    pub notes_imported: usize,
+}
+
+/// Write every snapshot and its file backups to `path`. Returns the number of snapshots exported.
+pub fn export_backup_archive(path: &Path) -> Result<usize, Error> {
+    let dir = get_snapshots_dir()?;
+    let mut archive = BackupArchive {
+        format: ARCHIVE_FORMAT.to_string(),
+        version: ARCHIVE_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: chrono::Local::now().to_rfc3339(),
+        machine_guid: crate::services::system_info_service::machine_guid(),
+        snapshots: Vec::new(),
+        files: Vec::new(),
+        notes: notes_service::get_notes()?,
+    };
+
+    let mut tweak_ids = get_applied_tweaks()?;
+    tweak_ids.sort();
+    for tweak_id in &tweak_ids {
+        let content = fs::read(get_snapshot_path(tweak_id)?)
+            .map_err(|e| Error::BackupFailed(format!("Failed to read snapshot: {}", e)))?;
+        // The checksum is dropped here and recomputed when the snapshot is saved on import
+        let verified = integrity::verify(&content).map_err(|reason| {
+            Error::BackupFailed(format!(
+                "Snapshot for '{}' is corrupt: {}",
+                tweak_id, reason
+            ))
+        })?;
+        archive.snapshots.push(verified.json);
+
+        for folder in [file_backups_dir_name(tweak_id), history_dir_name(tweak_id)] {
+            if dir.join(&folder).is_dir() {
+                collect_files(&dir, &folder, &mut archive.files)?;
+            }
+        }
+    }
+
+    let file = fs::File::create(path)
+        .map_err(|e| Error::BackupFailed(format!("Failed to create {}: {}", path.display(), e)))?;
+    write_archive(&archive, file)?;
+
+    log::info!(
+        "Exported {} snapshots ({} backup entries) to {}",
+        archive.snapshots.len(),
+        archive.files.len(),
+        path.display()
+    );
+    Ok(archive.snapshots.len())
+}
+
+/// Restore the snapshots (and their file backups) from an archive made by
+/// `export_backup_archive`. A tweak that already has a snapshot here is skipped unless
+/// `overwrite` is set, since that snapshot holds this machine's current original state.
+pub fn import_backup_archive(path: &Path, overwrite: bool) -> Result<ArchiveImportReport, Error> {
+    let file = fs::File::open(path)
+        .map_err(|e| Error::BackupFailed(format!("Failed to open {}: {}", path.display(), e)))?;
+    let archive = read_archive(file)?;
+
+    // Parse everything before touching the snapshots directory, so a bad archive changes nothing
+    let snapshots = archive
+        .snapshots
+        .into_iter()
+        .map(snapshot_from_json)
+        .collect::<Result<Vec<_>, _>>()?;
+    for file in &archive.files {
+        safe_relative_path(&file.path)?;
+    }
+
+    let from_other_machine = match (
+        archive.machine_guid.as_deref(),
+        crate::services::system_info_service::machine_guid(),
+    ) {
+        (Some(archived), Some(current)) => archived != current,
+        _ => false,
+    };
+    if from_other_machine {
+        log::warn!("Importing a backup archive made on a different machine");
+    }
+
+    let dir = get_snapshots_dir()?;
+    let mut report = ArchiveImportReport {
+        imported: Vec::new(),
+        skipped: Vec::new(),
+        from_other_machine,
+        notes_imported: 0,
+    };
+    for snapshot in snapshots {
+        let tweak_id = snapshot.tweak_id.clone();
+        if snapshot_exists(&tweak_id)? && !overwrite {
+            log::info!("Skipping '{}': a snapshot already exists", tweak_id);
+            report.skipped.push(tweak_id);
+            continue;
+        }
+
+        // File backups first, so the snapshot never points at backups that aren't there yet
+        for folder in [
+            file_backups_dir_name(&tweak_id),
+            history_dir_name(&tweak_id),
+        ] {
+            if dir.join(&folder).exists() {
+                fs::remove_dir_all(dir.join(&folder)).map_err(|e| {
+                    Error::BackupFailed(format!("Failed to replace {}: {}", folder, e))
+                })?;
+            }
+            for file in archive
+                .files
+                .iter()
+                .filter(|file| belongs_to(&file.path, &folder))
+            {
+                write_archived_file(&dir, file)?;
+            }
+        }
+
+        save_snapshot(&snapshot)?;
+        report.imported.push(tweak_id);
+    }
+    report.notes_imported = notes_service::merge_notes(archive.notes)?;
+    // An archive from before a tweak was renamed has it under the old ID
+    if let Err(e) = migrate_renamed_tweaks() {
+        log::warn!("Failed to move imported snapshots of renamed tweaks: {}", e);
+    }
+
+    log::info!(
+        "Imported {} snapshots from {} ({} skipped, {} notes)",
+        report.imported.len(),
+        path.display(),
+        report.skipped.len(),
+        report.notes_imported
+    );
+    Ok(report)
+}
+
+fn write_archive(archive: &BackupArchive, writer: impl Write) -> Result<(), Error> {
+    let mut encoder = GzEncoder::new(writer, Compression::default());
+    serde_json::to_writer(&mut encoder, archive)
+        .map_err(|e| Error::BackupFailed(format!("Failed to write archive: {}", e)))?;
+    encoder
+        .finish()
+        .map_err(|e| Error::BackupFailed(format!("Failed to write archive: {}", e)))?;
+    Ok(())
+}
+
+fn read_archive(reader: impl Read) -> Result<BackupArchive, Error> {
+    let mut json = String::new();
+    GzDecoder::new(reader)
+        .read_to_string(&mut json)
+        .map_err(|e| Error::BackupFailed(format!("Not a backup archive: {}", e)))?;
+
+    // Check the marker and version before the full parse, so a newer layout gets a clear error
+    let header: serde_json::Value = serde_json::from_str(&json)
+        .map_err(|e| Error::BackupFailed(format!("Corrupt backup archive: {}", e)))?;
+    if header.get("format").and_then(|f| f.as_str()) != Some(ARCHIVE_FORMAT) {
+        return Err(Error::BackupFailed("Not a backup archive".to_string()));
+    }
+    let version = header.get("version").and_then(|v| v.as_u64()).unwrap_or(0);
+    if version == 0 || version > u64::from(ARCHIVE_VERSION) {
+        return Err(Error::BackupFailed(format!(
+            "Backup archive version {} is not supported (this app reads up to version {})",
+            version, ARCHIVE_VERSION
+        )));
+    }
+
+    serde_json::from_value(header)
+        .map_err(|e| Error::BackupFailed(format!("Corrupt backup archive: {}", e)))
+}
+
+/// Add `rel` (a directory under `root`) and everything below it to `files`
+fn collect_files(root: &Path, rel: &str, files: &mut Vec<ArchivedFile>) -> Result<(), Error> {
+    files.push(ArchivedFile {
+        path: rel.to_string(),
+        data: None,
+    });
+    let entries = fs::read_dir(root.join(rel))
+        .map_err(|e| Error::BackupFailed(format!("Failed to read {}: {}", rel, e)))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| Error::BackupFailed(e.to_string()))?;
+        let child = format!("{}/{}", rel, entry.file_name().to_string_lossy());
+        if entry.path().is_dir() {
+            collect_files(root, &child, files)?;
+        } else {
+            let data = fs::read(entry.path())
+                .map_err(|e| Error::BackupFailed(format!("Failed to read {}: {}", child, e)))?;
+            let data = encryption::open(&data)
+                .map_err(|reason| {
+                    Error::BackupFailed(format!("{} can't be decrypted ({})", child, reason))
+                })?
+                .into_owned();
+            files.push(ArchivedFile {
+                path: child,
+                data: Some(data),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn write_archived_file(root: &Path, file: &ArchivedFile) -> Result<(), Error> {
+    let target = root.join(safe_relative_path(&file.path)?);
+    let result = match &file.data {
+        None => fs::create_dir_all(&target),
+        Some(data) => target
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&target, data)),
+    };
+    result.map_err(|e| Error::BackupFailed(format!("Failed to restore {}: {}", file.path, e)))
+}
+
+/// Whether `path` is the `backups_dir` folder or inside it
+fn belongs_to(path: &str, backups_dir: &str) -> bool {
+    path.strip_prefix(backups_dir)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// An archive path as a relative path that stays inside the snapshots directory
+fn safe_relative_path(path: &str) -> Result<PathBuf, Error> {
+    let relative = PathBuf::from(path.replace('/', "\\"));
+    let is_safe = !path.is_empty()
+        && relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+    if is_safe {
+        Ok(relative)
+    } else {
+        Err(Error::BackupFailed(format!(
+            "Backup archive contains an unsafe path: {}",
+            path
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TweakSnapshot;
+
+    fn archive_with_version(version: u32) -> BackupArchive {
+        let snapshot = TweakSnapshot::new("test_tweak", "Test Tweak", 1, "Off", 11, false, None);
+        BackupArchive {
+            format: ARCHIVE_FORMAT.to_string(),
+            version,
+            app_version: "3.0.0".to_string(),
+            created_at: "2026-01-01T00:00:00+00:00".to_string(),
+            machine_guid: None,
+            snapshots: vec![serde_json::to_value(snapshot).unwrap()],
+            files: vec![ArchivedFile {
+                path: "test_tweak.files/20260101/0".to_string(),
+                data: Some(b"hosts".to_vec()),
+            }],
+            notes: TweakNotes::new(),
+        }
+    }
+
+    #[test]
+    fn archive_round_trips_through_gzip() {
+        let mut bytes = Vec::new();
+        write_archive(&archive_with_version(ARCHIVE_VERSION), &mut bytes).unwrap();
+        assert_eq!(&bytes[..2], &[0x1f, 0x8b], "archive should be gzip");
+
+        let archive = read_archive(bytes.as_slice()).unwrap();
+        assert_eq!(archive.version, ARCHIVE_VERSION);
+        assert_eq!(archive.snapshots[0]["tweak_id"], "test_tweak");
+        assert_eq!(archive.files[0].data.as_deref(), Some(&b"hosts"[..]));
+    }
+
+    #[test]
+    fn newer_archive_versions_are_rejected() {
+        let mut bytes = Vec::new();
+        write_archive(&archive_with_version(ARCHIVE_VERSION + 1), &mut bytes).unwrap();
+        let err = read_archive(bytes.as_slice()).unwrap_err();
+        assert!(err.to_string().contains("not supported"), "{}", err);
+    }
+
+    #[test]
+    fn paths_outside_the_snapshots_directory_are_rejected() {
+        assert!(safe_relative_path("tweak.files/1/0").is_ok());
+        assert!(safe_relative_path("tweak.files/../../evil").is_err());
+        assert!(safe_relative_path("C:/Windows/evil").is_err());
+        assert!(safe_relative_path("").is_err());
+        assert!(belongs_to("tweak.files/1/0", "tweak.files"));
+        assert!(!belongs_to("tweak.files2/0", "tweak.files"));
+    }
+}
@@ -0,0 +1,413 @@
+//! Baseline: the state of everything the compiled tweaks touch, captured once on first run.
+//!
+//! A tweak's snapshot holds the state from just before the app applied it, which is already
+//! changed when another tool (or an earlier install) set the same thing. The baseline is captured
+//! on the first launch, before this app changes anything, and records every registry value and
+//! key, service, and scheduled task any tweak references, so a key can be put back to how it was
+//! found (`restore_key_to_baseline`) whatever has changed it since.
+//!
+//! It's stored as `baseline/baseline.json` in the snapshots directory, with a checksum like the
+//! snapshots, so it moves with them. Items that can't be read are left out rather than failing the
+//! capture.
+
+use crate::error::Error;
+use crate::models::{
+    PolicyChange, RegistryAction, RegistryChange, RegistryHive, RegistrySnapshot, SchedulerAction,
+    SchedulerSnapshot, ServiceSnapshot, SystemFacts, TweakOption,
+};
+use crate::services::elevation::Elevation;
+use crate::services::{registry_service, scheduler_service, system_info_service, tweak_loader};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use super::capture::{capture_registry_state, capture_scheduler_state, capture_service_state};
+use super::integrity;
+use super::restore::restore_one_registry;
+use super::storage::get_snapshots_dir;
+
+/// Folder (in the snapshots directory) holding the baseline
+const BASELINE_DIR: &str = "baseline";
+
+const BASELINE_FILE: &str = "baseline.json";
+
+/// The state captured on first run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemBaseline {
+    /// When it was captured (RFC 3339)
+    pub created_at: String,
+    pub windows_version: u32,
+    #[serde(default)]
+    pub registry: Vec<RegistrySnapshot>,
+    #[serde(default)]
+    pub services: Vec<ServiceSnapshot>,
+    #[serde(default)]
+    pub tasks: Vec<SchedulerSnapshot>,
+}
+
+/// Summary of the baseline, for the UI
+#[derive(Debug, Clone, Serialize)]
+pub struct BaselineInfo {
+    pub created_at: String,
+    pub windows_version: u32,
+    pub registry_count: usize,
+    pub service_count: usize,
+    pub task_count: usize,
+}
+
+impl From<&SystemBaseline> for BaselineInfo {
+    fn from(baseline: &SystemBaseline) -> Self {
+        Self {
+            created_at: baseline.created_at.clone(),
+            windows_version: baseline.windows_version,
+            registry_count: baseline.registry.len(),
+            service_count: baseline.services.len(),
+            task_count: baseline.tasks.len(),
+        }
+    }
+}
+
+/// Result of `restore_key_to_baseline`
+#[derive(Debug, Clone, Serialize)]
+pub struct BaselineRestoreResult {
+    /// Keys and values put back, as `HIVE\key` or `HIVE\key\value`
+    pub restored: Vec<String>,
+    pub failures: Vec<String>,
+}
+
+fn baseline_path() -> Result<PathBuf, Error> {
+    Ok(get_snapshots_dir()?.join(BASELINE_DIR).join(BASELINE_FILE))
+}
+
+/// The baseline, or None if it hasn't been captured
+pub fn load_baseline() -> Result<Option<SystemBaseline>, Error> {
+    let path = baseline_path()?;
+    let content = match fs::read(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(Error::BackupFailed(format!(
+                "Failed to read baseline: {}",
+                e
+            )))
+        }
+    };
+    // Unlike a snapshot, a corrupt baseline can't simply be captured again (the state it recorded
+    // is gone), so it's reported rather than quarantined
+    let verified = integrity::verify(&content).map_err(|reason| {
+        Error::BackupFailed(format!(
+            "Baseline {} is corrupt ({})",
+            path.display(),
+            reason
+        ))
+    })?;
+    serde_json::from_value(verified.json)
+        .map(Some)
+        .map_err(|e| Error::BackupFailed(format!("Failed to parse baseline: {}", e)))
+}
+
+/// Summary of the baseline, or None if it hasn't been captured
+pub fn get_baseline_info() -> Result<Option<BaselineInfo>, Error> {
+    Ok(load_baseline()?.as_ref().map(BaselineInfo::from))
+}
+
+/// Capture the baseline if there isn't one yet (the first run)
+pub fn ensure_baseline() -> Result<(), Error> {
+    if baseline_path()?.exists() {
+        return Ok(());
+    }
+    log::info!("No baseline yet; capturing the original state of everything tweaks reference");
+    capture_baseline().map(|_| ())
+}
+
+/// Capture the current state of everything the tweaks reference as the baseline, replacing any
+/// earlier one
+pub fn capture_baseline() -> Result<BaselineInfo, Error> {
+    let version = system_info_service::get_runtime_context()?.windows_version();
+    let tweaks = tweak_loader::get_tweaks_for_version(version)?;
+    let options: Vec<&TweakOption> = tweaks.iter().flat_map(|tweak| &tweak.options).collect();
+
+    let registry_changes =
+        referenced_registry_changes(&options, version, system_info_service::system_facts());
+    let service_names = referenced_services(&options);
+    // Services are only queried elevated when the normal query falls short, so the most
+    // privileged level any tweak runs at is enough for all of them
+    let service_elevation = Elevation::from_flags(
+        tweaks.iter().any(|tweak| tweak.requires_system),
+        tweaks.iter().any(|tweak| tweak.requires_ti),
+    );
+    let tasks = referenced_tasks(&options);
+
+    let ((registry, services), tasks) = rayon::join(
+        || {
+            rayon::join(
+                || {
+                    capture_each(&registry_changes, |change| {
+                        capture_registry_state(change).map_err(|e| {
+                            format!(
+                                "{}\\{}\\{}: {}",
+                                change.hive.as_str(),
+                                change.key,
+                                change.value_name,
+                                e
+                            )
+                        })
+                    })
+                },
+                || {
+                    capture_each(&service_names, |name| {
+                        capture_service_state(name, service_elevation)
+                            .map_err(|e| format!("service {}: {}", name, e))
+                    })
+                },
+            )
+        },
+        || {
+            capture_each(&tasks, |(task_path, task_name, deleted)| {
+                capture_scheduler_state(task_path, task_name, *deleted)
+                    .map_err(|e| format!("task {}\\{}: {}", task_path, task_name, e))
+            })
+        },
+    );
+
+    let baseline = SystemBaseline {
+        created_at: chrono::Local::now().to_rfc3339(),
+        windows_version: version,
+        registry,
+        services,
+        tasks,
+    };
+    let path = baseline_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| {
+            Error::BackupFailed(format!("Failed to create {}: {}", dir.display(), e))
+        })?;
+    }
+    fs::write(&path, integrity::to_file_contents(&baseline)?)
+        .map_err(|e| Error::BackupFailed(format!("Failed to write baseline: {}", e)))?;
+
+    let info = BaselineInfo::from(&baseline);
+    log::info!(
+        "Captured baseline: {} registry entries, {} services, {} tasks",
+        info.registry_count,
+        info.service_count,
+        info.task_count
+    );
+    Ok(info)
+}
+
+/// Put a registry key back to its baseline: the key itself if it was captured, then every
+/// captured value in it or its subkeys. One failure doesn't stop the rest.
+pub fn restore_key_to_baseline(
+    hive: RegistryHive,
+    key: &str,
+) -> Result<BaselineRestoreResult, Error> {
+    let baseline = load_baseline()?
+        .ok_or_else(|| Error::NotFound("No baseline has been captured".to_string()))?;
+
+    let key = key.trim_matches('\\').to_lowercase();
+    let subkey_prefix = format!("{}\\", key);
+    let mut entries: Vec<&RegistrySnapshot> = baseline
+        .registry
+        .iter()
+        .filter(|reg| reg.hive == hive.as_str())
+        .filter(|reg| {
+            let captured = reg.key.to_lowercase();
+            captured == key || captured.starts_with(&subkey_prefix)
+        })
+        .collect();
+    if entries.is_empty() {
+        return Err(Error::NotFound(format!(
+            "The baseline has nothing under {}\\{}",
+            hive.as_str(),
+            key
+        )));
+    }
+    // Keys before values, parents before subkeys
+    entries.sort_by_key(|reg| (!reg.key_level, reg.key.len()));
+
+    let mut result = BaselineRestoreResult {
+        restored: Vec::new(),
+        failures: Vec::new(),
+    };
+    for reg in entries {
+        let label = if reg.key_level {
+            format!("{}\\{}", reg.hive, reg.key)
+        } else {
+            format!("{}\\{}\\{}", reg.hive, reg.key, reg.value_name)
+        };
+        match restore_one_registry(reg, true) {
+            Ok(()) => result.restored.push(label),
+            Err(e) => result.failures.push(format!("{}: {}", label, e)),
+        }
+    }
+
+    log::info!(
+        "Restored {}\\{} to baseline: {} restored, {} failed",
+        hive.as_str(),
+        key,
+        result.restored.len(),
+        result.failures.len()
+    );
+    Ok(result)
+}
+
+/// Capture each item in parallel, leaving out (with a debug log) the ones that can't be read
+fn capture_each<T: Sync, S: Send>(
+    items: &[T],
+    capture: impl Fn(&T) -> Result<S, String> + Sync,
+) -> Vec<S> {
+    items
+        .par_iter()
+        .filter_map(|item| {
+            capture(item)
+                .inspect_err(|e| log::debug!("Baseline: skipping {}", e))
+                .ok()
+        })
+        .collect()
+}
+
+/// Every registry value and key the options reference on this machine, once each. Key-level
+/// references are captured as `delete_key` so the baseline keeps the key's contents.
+fn referenced_registry_changes(
+    options: &[&TweakOption],
+    version: u32,
+    facts: &SystemFacts,
+) -> Vec<RegistryChange> {
+    let mut unique: HashMap<String, RegistryChange> = HashMap::new();
+    for option in options {
+        let changes = registry_service::option_registry_changes(option).unwrap_or_else(|e| {
+            log::debug!(
+                "Baseline: skipping registry changes of '{}': {}",
+                option.label,
+                e
+            );
+            Vec::new()
+        });
+        let policies = option.all_policy_changes();
+        let probes =
+            registry_service::expand_registry_changes(&option.state_probes.to_registry_changes())
+                .unwrap_or_default();
+
+        for change in changes
+            .into_iter()
+            .chain(policies.iter().map(PolicyChange::to_registry_change))
+            .chain(probes)
+        {
+            if !change.applies_to(version, facts) {
+                continue;
+            }
+            let (id, change) = match change.action {
+                RegistryAction::DeleteKey | RegistryAction::CreateKey => (
+                    format!("{}\\{}\\[key]", change.hive.as_str(), change.key),
+                    RegistryChange {
+                        action: RegistryAction::DeleteKey,
+                        ..change
+                    },
+                ),
+                RegistryAction::Set | RegistryAction::DeleteValue => (
+                    format!(
+                        "{}\\{}\\{}",
+                        change.hive.as_str(),
+                        change.key,
+                        change.value_name
+                    ),
+                    change,
+                ),
+            };
+            unique.entry(id.to_lowercase()).or_insert(change);
+        }
+    }
+    unique.into_values().collect()
+}
+
+/// Every service the options reference, once each
+fn referenced_services(options: &[&TweakOption]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for option in options {
+        let service_names = option.all_service_changes().into_iter().map(|sc| sc.name);
+        for name in service_names.chain(option.state_probes.services.iter().cloned()) {
+            if !names.iter().any(|seen| seen.eq_ignore_ascii_case(&name)) {
+                names.push(name);
+            }
+        }
+    }
+    names
+}
+
+/// Every scheduled task the options reference (patterns resolved to the tasks they match now),
+/// once each, flagged when any change deletes it so its definition is captured too
+fn referenced_tasks(options: &[&TweakOption]) -> Vec<(String, String, bool)> {
+    let mut tasks: Vec<(String, String, bool)> = Vec::new();
+    for change in options.iter().flat_map(|option| &option.scheduler_changes) {
+        let deleted = change.action == SchedulerAction::Delete;
+        let names: Vec<String> = if let Some(pattern) = &change.task_name_pattern {
+            match scheduler_service::find_tasks_by_pattern(&change.task_path, pattern) {
+                Ok(found) => found.into_iter().map(|task| task.name).collect(),
+                Err(e) => {
+                    log::debug!(
+                        "Baseline: skipping tasks matching '{}' in '{}': {}",
+                        pattern,
+                        change.task_path,
+                        e
+                    );
+                    Vec::new()
+                }
+            }
+        } else {
+            change.task_name.iter().cloned().collect()
+        };
+
+        for name in names {
+            match tasks
+                .iter_mut()
+                .find(|(path, seen, _)| *path == change.task_path && *seen == name)
+            {
+                Some(task) => task.2 |= deleted,
+                None => tasks.push((change.task_path.clone(), name, deleted)),
+            }
+        }
+    }
+    tasks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn references_are_listed_once_and_keys_keep_their_contents() {
+        let options: Vec<TweakOption> = serde_json::from_value(serde_json::json!([
+            {
+                "label": "On",
+                "registry_changes": [
+                    { "hive": "HKCU", "key": "Software\\MagicXBaseline", "action": "create_key" },
+                    { "hive": "HKCU", "key": "Software\\MagicXBaseline", "value_name": "A",
+                      "value_type": "REG_DWORD", "value": 1 },
+                ],
+                "service_changes": [{ "name": "DiagTrack", "startup": "disabled" }],
+            },
+            {
+                "label": "Off",
+                "registry_changes": [
+                    { "hive": "HKCU", "key": "Software\\MagicXBaseline", "value_name": "A",
+                      "action": "delete_value" },
+                ],
+                "service_changes": [{ "name": "diagtrack", "startup": "automatic" }],
+            },
+        ]))
+        .unwrap();
+        let options: Vec<&TweakOption> = options.iter().collect();
+
+        let mut changes = referenced_registry_changes(&options, 11, &SystemFacts::default());
+        changes.sort_by_key(|change| change.value_name.clone());
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].action, RegistryAction::DeleteKey);
+        assert_eq!(changes[1].value_name, "A");
+
+        assert_eq!(referenced_services(&options), vec!["DiagTrack".to_string()]);
+    }
+}
@@ -1,19 +1,40 @@
 //! Snapshot Capture Operations
 //!
 //! Functions for capturing system state before applying tweaks:
-//! - Registry value snapshots
+//! - Registry value snapshots (and the full contents of keys about to be deleted)
 //! - Service state snapshots
 //! - Scheduled task state snapshots
+//! - Power plan state snapshots
+//! - BCD element snapshots
+//! - AppX package snapshots
+//! - File and folder snapshots (originals backed up next to the snapshot)
+//! - Registry key security descriptor snapshots
+//! - Windows optional feature snapshots
+//! - Network adapter advanced property snapshots
+//! - Application (winget package) install state snapshots
+//! - Group Policy Registry.pol record snapshots (policy values themselves go through registry)
 
 use crate::error::Error;
 use crate::models::{
-    FirewallSnapshot, HostsSnapshot, RegistryAction, RegistryHive, RegistrySnapshot,
-    RegistryValueType, SchedulerSnapshot, ServiceSnapshot, TweakDefinition, TweakSnapshot,
+    AppAction, AppChange, AppSnapshot, AppxAction, AppxChange, AppxScope, AppxSnapshot,
+    BcdeditSnapshot, ChangeElevation, FeatureChange, FeatureSnapshot, FileAction, FileChange,
+    FileSnapshot, FirewallSnapshot, HostsSnapshot, NetAdapterChange, NetAdapterSnapshot,
+    PolicyChange, PolicyFileEntry, PolicyScope, PolicySnapshot, PowerPlanAction, PowerPlanChange,
+    PowerPlanSnapshot, RegistryAclChange, RegistryAclSnapshot, RegistryAction, RegistryChange,
+    RegistryHive, RegistryKeyContents, RegistryKeyValue, RegistrySnapshot, RegistryValueType,
+    SchedulerAction, SchedulerSnapshot, ServiceSnapshot, TweakDefinition, TweakOption,
+    TweakSnapshot,
 };
+use crate::services::elevation::{self, Elevation};
 use crate::services::{
-    firewall_service, hosts_service, registry_service, scheduler_service, service_control,
+    appx_service, bcdedit_service, dism_service, file_service, firewall_service, hosts_service,
+    network_service, package_service, policy_service, power_service, registry_acl_service,
+    registry_service, scheduler_service, service_control, system_info_service,
 };
 use rayon::prelude::*;
+use scheduler_service::TaskState;
+
+use super::storage;
 
 /// Capture complete state before applying a tweak option (parallelized)
 pub fn capture_snapshot(
@@ -45,27 +66,115 @@ pub fn capture_snapshot(
         original_option_index,
     );
 
-    // Parallel capture: registry, services, scheduler, hosts, and firewall run concurrently
-    let ((registry_result, (services_result, scheduler_result)), (hosts_result, firewall_result)) =
-        rayon::join(
-            || {
-                rayon::join(
-                    || capture_registry_snapshots(&option.registry_changes, windows_version),
-                    || {
-                        rayon::join(
-                            || capture_service_snapshots(&option.service_changes),
-                            || capture_scheduler_snapshots(&option.scheduler_changes),
-                        )
-                    },
-                )
-            },
-            || {
-                rayon::join(
-                    || capture_hosts_snapshots(&option.hosts_changes),
-                    || capture_firewall_snapshots(&option.firewall_changes),
-                )
-            },
-        );
+    // Policy (including browser policy), MMCSS, context menu, audio, visual effects, and time values
+    // are plain registry values, captured alongside the option's own (with ALL_USERS changes expanded
+    // to each user's hive)
+    let policy_changes = option.all_policy_changes();
+    // State probes are captured like the changes they stand for (nothing applies them), so revert
+    // restores what the option's commands changed
+    let probes = &option.state_probes;
+    let registry_changes: Vec<RegistryChange> = registry_service::option_registry_changes(option)?
+        .into_iter()
+        .chain(policy_changes.iter().map(PolicyChange::to_registry_change))
+        .chain(registry_service::expand_registry_changes(
+            &probes.to_registry_changes(),
+        )?)
+        .collect();
+    let services: Vec<(String, Option<ChangeElevation>)> = option
+        .all_service_changes()
+        .into_iter()
+        .map(|sc| (sc.name, sc.elevation))
+        .chain(probes.services.iter().map(|name| (name.clone(), None)))
+        .collect();
+    let file_changes: Vec<FileChange> = option
+        .file_changes
+        .iter()
+        .cloned()
+        .chain(probes.to_file_changes())
+        .collect();
+
+    // Parallel capture: registry, policies, registry ACLs, services, scheduler, hosts, firewall,
+    // power plan, BCD, AppX, files, optional features, and network adapters run concurrently
+    let (
+        (
+            ((registry_result, policy_result), registry_acl_result),
+            (services_result, scheduler_result),
+        ),
+        (
+            (hosts_result, firewall_result),
+            (
+                (power_plan_result, bcdedit_result),
+                ((appx_result, file_result), (feature_result, (netadapter_result, app_result))),
+            ),
+        ),
+    ) = rayon::join(
+        || {
+            rayon::join(
+                || {
+                    rayon::join(
+                        || {
+                            rayon::join(
+                                || capture_registry_snapshots(&registry_changes, windows_version),
+                                || capture_policy_snapshots(&policy_changes),
+                            )
+                        },
+                        || capture_registry_acl_snapshots(&option.registry_acl_changes),
+                    )
+                },
+                || {
+                    rayon::join(
+                        || capture_service_snapshots(&services, tweak),
+                        || capture_scheduler_snapshots(&option.scheduler_changes),
+                    )
+                },
+            )
+        },
+        || {
+            rayon::join(
+                || {
+                    rayon::join(
+                        || capture_hosts_snapshots(&option.hosts_changes),
+                        || capture_firewall_snapshots(&option.firewall_changes),
+                    )
+                },
+                || {
+                    rayon::join(
+                        || {
+                            rayon::join(
+                                || capture_power_plan_snapshots(&option.power_plan_changes),
+                                || capture_bcdedit_snapshots(&option.bcdedit_changes),
+                            )
+                        },
+                        || {
+                            rayon::join(
+                                || {
+                                    rayon::join(
+                                        || capture_appx_snapshots(&option.appx_changes),
+                                        || capture_file_snapshots(&tweak.id, &file_changes),
+                                    )
+                                },
+                                || {
+                                    rayon::join(
+                                        || capture_feature_snapshots(&option.feature_changes),
+                                        || {
+                                            rayon::join(
+                                                || {
+                                                    capture_netadapter_snapshots(
+                                                        &option.netadapter_changes,
+                                                    )
+                                                },
+                                                || capture_app_snapshots(&option.app_changes),
+                                            )
+                                        },
+                                    )
+                                },
+                            )
+                        },
+                    )
+                },
+            )
+        },
+    );
 
     // Add captured snapshots to the result
     for reg_snapshot in registry_result? {
@@ -79,6 +188,14 @@ pub fn capture_snapshot(
         snapshot.add_registry_snapshot(reg_snapshot);
     }
 
+    for policy_snapshot in policy_result? {
+        snapshot.add_policy_snapshot(policy_snapshot);
+    }
+
+    for acl_snapshot in registry_acl_result? {
+        snapshot.add_registry_acl_snapshot(acl_snapshot);
+    }
+
     for service_snapshot in services_result? {
         snapshot.add_service_snapshot(service_snapshot);
     }
@@ -95,19 +212,66 @@ pub fn capture_snapshot(
         snapshot.add_firewall_snapshot(firewall_snapshot);
     }
 
+    for power_plan_snapshot in power_plan_result? {
+        snapshot.add_power_plan_snapshot(power_plan_snapshot);
+    }
+
+    for bcdedit_snapshot in bcdedit_result? {
+        snapshot.add_bcdedit_snapshot(bcdedit_snapshot);
+    }
+
+    for appx_snapshot in appx_result? {
+        snapshot.add_appx_snapshot(appx_snapshot);
+    }
+
+    for file_snapshot in file_result? {
+        snapshot.add_file_snapshot(file_snapshot);
+    }
+
+    for feature_snapshot in feature_result? {
+        snapshot.add_feature_snapshot(feature_snapshot);
+    }
+
+    for netadapter_snapshot in netadapter_result? {
+        snapshot.add_netadapter_snapshot(netadapter_snapshot);
+    }
+
+    for app_snapshot in app_result? {
+        snapshot.add_app_snapshot(app_snapshot);
+    }
+
     log::info!(
-        "Captured {} registry, {} services, {} tasks, {} hosts, {} firewall for '{}'",
+        "Captured {} registry, {} policies, {} registry ACLs, {} services, {} tasks, {} hosts, {} firewall, {} power plan, {} BCD, {} AppX, {} files, {} features, {} adapter properties, {} apps for '{}'",
         snapshot.registry_snapshots.len(),
+        snapshot.policy_snapshots.len(),
+        snapshot.registry_acl_snapshots.len(),
         snapshot.service_snapshots.len(),
         snapshot.scheduler_snapshots.len(),
         snapshot.hosts_snapshots.len(),
         snapshot.firewall_snapshots.len(),
+        snapshot.power_plan_snapshots.len(),
+        snapshot.bcdedit_snapshots.len(),
+        snapshot.appx_snapshots.len(),
+        snapshot.file_snapshots.len(),
+        snapshot.feature_snapshots.len(),
+        snapshot.netadapter_snapshots.len(),
+        snapshot.app_snapshots.len(),
         tweak.name
     );
 
     Ok(snapshot)
 }
 
+/// Snapshot what a registry change targets: its value, or for a key-level change, its key
+pub(crate) fn capture_registry_state(
+    change: &crate::models::RegistryChange,
+) -> Result<RegistrySnapshot, Error> {
+    match change.action {
+        RegistryAction::Set | RegistryAction::DeleteValue => capture_value_snapshot(change),
+        RegistryAction::DeleteKey | RegistryAction::CreateKey => capture_key_snapshot(change),
+    }
+}
+
 /// Snapshot the current value at a registry change's target, recording it with the value's ACTUAL
 /// type. `value_type` is optional for delete/create actions, so we detect the stored type rather
 /// than guessing DWORD — a wrong guess made a non-DWORD value fail to read and aborted the capture.
@@ -133,12 +297,21 @@ fn capture_value_snapshot(
         },
         value,
         existed,
+        key_level: false,
+        key_contents: None,
+        elevation: change.elevation,
     })
 }
 
-/// Snapshot a key-level change (DeleteKey / CreateKey): record only whether the key already exists.
+/// Snapshot a key-level change (DeleteKey / CreateKey): record whether the key already exists
+/// and, for a key about to be deleted, everything under it so revert can recreate it.
 fn capture_key_snapshot(change: &crate::models::RegistryChange) -> Result<RegistrySnapshot, Error> {
     let existed = registry_service::key_exists(&change.hive, &change.key)?;
+    let key_contents = if existed && change.action == RegistryAction::DeleteKey {
+        Some(read_key_contents(&change.hive, &change.key)?)
+    } else {
+        None
+    };
 
     Ok(RegistrySnapshot {
         hive: change.hive.as_str().to_string(),
@@ -147,9 +320,54 @@ fn capture_key_snapshot(change: &crate::models::RegistryChange) -> Result<Regist
         value_type: None,
         value: None,
         existed,
+        key_level: true,
+        key_contents,
+        elevation: change.elevation,
     })
 }
 
+/// Read every subkey and value under a registry key, with each value's actual type
+pub fn read_key_contents(hive: &RegistryHive, key: &str) -> Result<RegistryKeyContents, Error> {
+    let mut contents = RegistryKeyContents::default();
+    // Walk breadth-first so parents are listed before their children
+    let mut pending = vec![String::new()];
+    while !pending.is_empty() {
+        let mut next = Vec::new();
+        for subkey in pending {
+            let path = if subkey.is_empty() {
+                key.to_string()
+            } else {
+                format!("{}\\{}", key, subkey)
+            };
+
+            for name in registry_service::list_value_names(hive, &path)? {
+                let value_type = registry_service::detect_value_type(hive, &path, &name)?
+                    .unwrap_or(RegistryValueType::Binary);
+                if let (Some(value), true) = read_registry_value(hive, &path, &name, &value_type)? {
+                    contents.values.push(RegistryKeyValue {
+                        subkey: subkey.clone(),
+                        name,
+                        value_type: value_type.as_str().to_string(),
+                        value,
+                    });
+                }
+            }
+
+            for child in registry_service::list_subkeys(hive, &path)? {
+                let child = if subkey.is_empty() {
+                    child
+                } else {
+                    format!("{}\\{}", subkey, child)
+                };
+                contents.subkeys.push(child.clone());
+                next.push(child);
+            }
+        }
+        pending = next;
+    }
+    Ok(contents)
+}
+
 /// Capture registry values in parallel
 fn capture_registry_snapshots(
     registry_changes: &[crate::models::RegistryChange],
@@ -157,24 +375,35 @@ fn capture_registry_snapshots(
 ) -> Result<Vec<RegistrySnapshot>, Error> {
     registry_changes
         .par_iter()
-        .filter(|change| change.applies_to_version(windows_version))
-        .map(|change| match change.action {
-            RegistryAction::Set | RegistryAction::DeleteValue => capture_value_snapshot(change),
-            RegistryAction::DeleteKey | RegistryAction::CreateKey => capture_key_snapshot(change),
-        })
+        .filter(|change| change.applies_to(windows_version, system_info_service::system_facts()))
+        .map(capture_registry_state)
         .collect()
 }
 
 /// Capture service states in parallel
 fn capture_service_snapshots(
-    service_changes: &[crate::models::ServiceChange],
+    services: &[(String, Option<ChangeElevation>)],
+    tweak: &TweakDefinition,
 ) -> Result<Vec<ServiceSnapshot>, Error> {
-    service_changes
+    services
         .par_iter()
-        .map(|sc| capture_service_state(&sc.name))
+        .map(|(name, elevation)| capture_declared_service_state(name, *elevation, tweak))
         .collect()
 }
 
+/// Capture a service's state at the elevation its change declares (the tweak's otherwise), and
+/// record that elevation so revert restores it the same way
+fn capture_declared_service_state(
+    name: &str,
+    elevation: Option<ChangeElevation>,
+    tweak: &TweakDefinition,
+) -> Result<ServiceSnapshot, Error> {
+    let mut snapshot =
+        capture_service_state(name, ChangeElevation::resolve(elevation, tweak.elevation()))?;
+    snapshot.elevation = elevation;
+    Ok(snapshot)
+}
+
 /// Capture scheduler task states (mixed parallel/sequential due to pattern matching)
 fn capture_scheduler_snapshots(
     scheduler_changes: &[crate::models::SchedulerChange],
@@ -207,6 +436,7 @@ fn capture_scheduler_snapshots(
             }
 
             // Capture matching tasks in parallel
+            let deleted = task_change.action == SchedulerAction::Delete;
             let task_snapshots: Vec<SchedulerSnapshot> = matching_tasks
                 .par_iter()
                 .map(|task| {
@@ -216,17 +446,25 @@ fn capture_scheduler_snapshots(
                         task.name,
                         task.state.as_str()
                     );
-                    SchedulerSnapshot {
-                        task_path: task_change.task_path.clone(),
-                        task_name: task.name.clone(),
-                        original_state: task.state.as_str().to_string(),
-                    }
+                    let mut snapshot = scheduler_snapshot(
+                        &task_change.task_path,
+                        &task.name,
+                        &task.state,
+                        deleted,
+                    )?;
+                    snapshot.elevation = task_change.elevation;
+                    Ok(snapshot)
                 })
-                .collect();
+                .collect::<Result<_, Error>>()?;
             snapshots.extend(task_snapshots);
         } else if let Some(ref task_name) = task_change.task_name {
             // Exact task name: capture single task state
-            let task_snapshot = capture_scheduler_state(&task_change.task_path, task_name)?;
+            let mut task_snapshot = capture_scheduler_state(
+                &task_change.task_path,
+                task_name,
+                task_change.action == SchedulerAction::Delete,
+            )?;
+            task_snapshot.elevation = task_change.elevation;
             snapshots.push(task_snapshot);
         } else {
             log::warn!("Scheduler change has neither task_name nor task_name_pattern, skipping");
@@ -269,6 +507,291 @@ fn capture_firewall_snapshots(
         .collect()
 }
 
+/// Capture power plan states. `SCHEME_CURRENT` is resolved once, up front, to the scheme that is
+/// active now — the same scheme the apply resolves it to.
+fn capture_power_plan_snapshots(
+    power_plan_changes: &[PowerPlanChange],
+) -> Result<Vec<PowerPlanSnapshot>, Error> {
+    if power_plan_changes.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let current_scheme = power_service::get_active_scheme()?;
+    power_plan_changes
+        .iter()
+        .map(|change| capture_power_plan_state(change, &current_scheme))
+        .collect()
+}
+
+/// Capture the state one power plan change would overwrite: the active scheme for
+/// `set_active_scheme`, or the setting's AC/DC value indices for `set_setting_value`.
+fn capture_power_plan_state(
+    change: &PowerPlanChange,
+    current_scheme: &str,
+) -> Result<PowerPlanSnapshot, Error> {
+    match change.action {
+        PowerPlanAction::SetActiveScheme => Ok(PowerPlanSnapshot {
+            scheme: current_scheme.to_string(),
+            subgroup: None,
+            setting: None,
+            ac_value: None,
+            dc_value: None,
+        }),
+        PowerPlanAction::SetSettingValue => {
+            let scheme = power_service::resolve_scheme(change.scheme.as_deref(), current_scheme)?;
+            let (subgroup, setting) = power_service::setting_target(change)?;
+            let values = power_service::read_setting(&scheme, subgroup, setting)?;
+            Ok(PowerPlanSnapshot {
+                scheme,
+                subgroup: Some(power_service::normalize_guid(subgroup)?),
+                setting: Some(power_service::normalize_guid(setting)?),
+                ac_value: Some(values.ac),
+                dc_value: Some(values.dc),
+            })
+        }
+    }
+}
+
+/// Capture BCD element states
+fn capture_bcdedit_snapshots(
+    bcdedit_changes: &[crate::models::BcdeditChange],
+) -> Result<Vec<BcdeditSnapshot>, Error> {
+    bcdedit_changes
+        .iter()
+        .map(|change| {
+            capture_bcdedit_state(
+                bcdedit_service::resolve_identifier(change.identifier.as_deref()),
+                &change.element,
+            )
+        })
+        .collect()
+}
+
+/// Capture one BCD element. Boolean values are stored normalized so restore can pass them back
+/// to `bcdedit /set` regardless of the display language they were read in.
+fn capture_bcdedit_state(identifier: &str, element: &str) -> Result<BcdeditSnapshot, Error> {
+    let value = bcdedit_service::get_value(identifier, element)?
+        .map(|v| bcdedit_service::normalize_value(&v));
+
+    Ok(BcdeditSnapshot {
+        identifier: identifier.to_string(),
+        element: element.to_string(),
+        existed: value.is_some(),
+        value,
+    })
+}
+
+/// Capture AppX package states
+fn capture_appx_snapshots(appx_changes: &[AppxChange]) -> Result<Vec<AppxSnapshot>, Error> {
+    appx_changes.iter().map(capture_appx_state).collect()
+}
+
+/// Capture the packages one AppX change could affect: the installed copies in the scope the
+/// change works in (reinstall only ever registers for the current user), plus the provisioned
+/// copies when the change deprovisions.
+fn capture_appx_state(change: &AppxChange) -> Result<AppxSnapshot, Error> {
+    let scope = match change.action {
+        AppxAction::Reinstall => AppxScope::CurrentUser,
+        AppxAction::Remove | AppxAction::RemoveProvisioned => change.scope,
+    };
+    let installed = appx_service::list_installed(&change.package, scope)?;
+    let provisioned = match change.action {
+        AppxAction::RemoveProvisioned => Some(appx_service::list_provisioned(&change.package)?),
+        AppxAction::Remove | AppxAction::Reinstall => None,
+    };
+
+    Ok(AppxSnapshot {
+        package: change.package.clone(),
+        scope: scope.as_str().to_string(),
+        installed,
+        provisioned,
+    })
+}
+
+/// Capture file and folder states, copying originals into the tweak's file-backup folder. Each
+/// capture gets its own subfolder so a later capture never overwrites an earlier snapshot's backups.
+fn capture_file_snapshots(
+    tweak_id: &str,
+    file_changes: &[FileChange],
+) -> Result<Vec<FileSnapshot>, Error> {
+    if file_changes.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let capture_dir = format!(
+        "{}/{}",
+        storage::file_backups_dir_name(tweak_id),
+        chrono::Local::now().format("%Y%m%d%H%M%S%f")
+    );
+    file_changes
+        .iter()
+        .enumerate()
+        .map(|(i, change)| capture_file_state(change, &format!("{}/{}", capture_dir, i)))
+        .collect()
+}
+
+/// Capture the path one file change overwrites: the destination for copy, the source otherwise.
+/// Content is backed up only for actions that destroy it (copy, delete, write_content).
+fn capture_file_state(change: &FileChange, backup_rel: &str) -> Result<FileSnapshot, Error> {
+    let path = file_service::expand_path(&change.path);
+    let (target, moved_to) = match change.action {
+        FileAction::Copy => (file_service::destination(change)?, None),
+        FileAction::Rename => (path, Some(file_service::destination(change)?)),
+        FileAction::Delete | FileAction::SetAttributes | FileAction::WriteContent => (path, None),
+    };
+
+    let existed = file_service::path_exists(&target);
+    let destroys_content = matches!(
+        change.action,
+        FileAction::Copy | FileAction::Delete | FileAction::WriteContent
+    );
+    let backup_path = if existed && destroys_content && !change.skip_backup {
+        let backup = storage::get_snapshots_dir()?.join(backup_rel);
+        file_service::copy_path(std::path::Path::new(&target), &backup)?;
+        Some(backup_rel.to_string())
+    } else {
+        None
+    };
+    let attributes = if existed && change.action == FileAction::SetAttributes {
+        Some(file_service::get_attributes(&target)?)
+    } else {
+        None
+    };
+
+    Ok(FileSnapshot {
+        is_dir: file_service::is_dir(&target),
+        path: target,
+        existed,
+        backup_path,
+        attributes,
+        moved_to,
+    })
+}
+
+/// Capture the security descriptor of every key an ACL change touches, once per key.
+fn capture_registry_acl_snapshots(
+    acl_changes: &[RegistryAclChange],
+) -> Result<Vec<RegistryAclSnapshot>, Error> {
+    let mut seen = std::collections::HashSet::new();
+    acl_changes
+        .iter()
+        .filter(|change| {
+            seen.insert(format!("{}\\{}", change.hive.as_str(), change.key).to_lowercase())
+        })
+        .map(|change| {
+            Ok(RegistryAclSnapshot {
+                hive: change.hive.as_str().to_string(),
+                key: change.key.clone(),
+                sddl: registry_acl_service::get_sddl(change.hive, &change.key)?,
+            })
+        })
+        .collect()
+}
+
+/// Capture optional feature states, once per feature
+fn capture_feature_snapshots(
+    feature_changes: &[FeatureChange],
+) -> Result<Vec<FeatureSnapshot>, Error> {
+    let mut seen = std::collections::HashSet::new();
+    feature_changes
+        .iter()
+        .filter(|change| seen.insert(change.feature.to_lowercase()))
+        .map(|change| {
+            Ok(FeatureSnapshot {
+                feature: change.feature.clone(),
+                enabled: dism_service::is_enabled(&change.feature)?,
+            })
+        })
+        .collect()
+}
+
+/// Capture network adapter property states, once per (adapter, keyword) pair
+fn capture_netadapter_snapshots(
+    netadapter_changes: &[NetAdapterChange],
+) -> Result<Vec<NetAdapterSnapshot>, Error> {
+    let mut seen = std::collections::HashSet::new();
+    let mut snapshots = Vec::new();
+
+    for change in netadapter_changes {
+        let adapter = network_service::resolve_adapter(change.adapter.as_deref());
+        for property in network_service::get_properties(adapter, &change.keyword)? {
+            if seen.insert(format!("{}|{}", property.name, change.keyword).to_lowercase()) {
+                snapshots.push(NetAdapterSnapshot {
+                    adapter: property.name,
+                    keyword: change.keyword.clone(),
+                    value: property.value,
+                });
+            }
+        }
+    }
+
+    Ok(snapshots)
+}
+
+/// Capture application install states, once per package
+fn capture_app_snapshots(app_changes: &[AppChange]) -> Result<Vec<AppSnapshot>, Error> {
+    let mut seen = std::collections::HashSet::new();
+    app_changes
+        .iter()
+        .filter(|change| seen.insert(change.id.to_lowercase()))
+        .map(|change| {
+            Ok(AppSnapshot {
+                id: change.id.clone(),
+                source: change.source.clone(),
+                installed: package_service::is_installed(&change.id)?,
+            })
+        })
+        .collect()
+}
+
+/// Capture the Registry.pol records policy changes will write, once per value.
+/// Changes without `write_pol` get a row with `pol_captured: false`, so restore still knows
+/// which scopes to refresh.
+fn capture_policy_snapshots(policy_changes: &[PolicyChange]) -> Result<Vec<PolicySnapshot>, Error> {
+    let mut pol_files: std::collections::HashMap<PolicyScope, Vec<PolicyFileEntry>> =
+        std::collections::HashMap::new();
+    let mut seen = std::collections::HashSet::new();
+    let mut snapshots = Vec::new();
+
+    for change in policy_changes {
+        let id = format!(
+            "{}|{}|{}",
+            change.scope.as_str(),
+            change.key,
+            change.value_name
+        )
+        .to_lowercase();
+        if !seen.insert(id) {
+            continue;
+        }
+
+        let pol_entry = if change.write_pol {
+            let entries = match pol_files.entry(change.scope) {
+                std::collections::hash_map::Entry::Occupied(e) => e.into_mut(),
+                std::collections::hash_map::Entry::Vacant(e) => {
+                    e.insert(policy_service::read_pol(change.scope)?)
+                }
+            };
+            entries
+                .iter()
+                .find(|entry| policy_service::is_record_for(entry, &change.key, &change.value_name))
+                .cloned()
+        } else {
+            None
+        };
+
+        snapshots.push(PolicySnapshot {
+            scope: change.scope.as_str().to_string(),
+            key: change.key.clone(),
+            value_name: change.value_name.clone(),
+            pol_captured: change.write_pol,
+            pol_entry,
+        });
+    }
+
+    Ok(snapshots)
+}
+
 /// Capture CURRENT system state for ALL items across ALL options of a tweak (parallelized).
 /// Used for rollback when switching between options - restores to the state
 /// BEFORE the current apply operation started (not the original pre-tweak state).
@@ -293,38 +816,130 @@ pub fn capture_current_state(
         None, // Not relevant for temporary rollback snapshots
     );
 
+    // ALL_USERS changes are expanded to each user's hive, browser policies to the policy values
+    // they write, and policy, MMCSS, context menu, audio, visual effects, and time values are
+    // captured as registry values; keep them alive for the references below. State probes are
+    // captured like the registry and file changes they stand for.
+    let expanded_registry_changes: Vec<Vec<RegistryChange>> = tweak
+        .options
+        .iter()
+        .map(|option| {
+            let mut changes = registry_service::option_registry_changes(option)?;
+            changes.extend(registry_service::expand_registry_changes(
+                &option.state_probes.to_registry_changes(),
+            )?);
+            Ok::<_, Error>(changes)
+        })
+        .collect::<Result<_, _>>()?;
+    let option_file_changes: Vec<Vec<FileChange>> = tweak
+        .options
+        .iter()
+        .map(|option| {
+            option
+                .file_changes
+                .iter()
+                .cloned()
+                .chain(option.state_probes.to_file_changes())
+                .collect()
+        })
+        .collect();
+    let option_policy_changes: Vec<Vec<PolicyChange>> = tweak
+        .options
+        .iter()
+        .map(TweakOption::all_policy_changes)
+        .collect();
+    let policy_registry_changes: Vec<RegistryChange> = option_policy_changes
+        .iter()
+        .flatten()
+        .map(PolicyChange::to_registry_change)
+        .collect();
+
     // Collect unique items across all options first
     use std::collections::{HashMap, HashSet};
     let mut unique_registry: HashMap<String, &crate::models::RegistryChange> = HashMap::new();
-    let mut unique_services: HashSet<String> = HashSet::new();
-    let mut unique_tasks: Vec<(&str, &str)> = Vec::new(); // (path, name)
-    let mut unique_task_patterns: Vec<(&str, &str)> = Vec::new(); // (path, pattern)
+    // In first-seen order, so components' services are restored in reverse of how they're stopped
+    // (name, elevation the change declares)
+    let mut unique_services: Vec<(String, Option<ChangeElevation>)> = Vec::new();
+    // (path, name, deleted, elevation)
+    let mut unique_tasks: Vec<(&str, &str, bool, Option<ChangeElevation>)> = Vec::new();
+    // (path, pattern, deleted, elevation)
+    let mut unique_task_patterns: Vec<(&str, &str, bool, Option<ChangeElevation>)> = Vec::new();
     let mut unique_hosts: HashMap<String, (&str, &str)> = HashMap::new(); // key -> (ip, domain)
     let mut unique_firewall: HashSet<String> = HashSet::new();
+    let mut unique_power_plan: HashMap<String, &PowerPlanChange> = HashMap::new();
+    let mut unique_bcdedit: HashMap<String, (&str, &str)> = HashMap::new(); // key -> (id, element)
+    let mut unique_appx: HashMap<String, &AppxChange> = HashMap::new();
+    let mut unique_files: HashMap<String, &FileChange> = HashMap::new();
+    let mut unique_registry_acls: HashMap<String, &RegistryAclChange> = HashMap::new();
+    let mut unique_features: HashMap<String, &FeatureChange> = HashMap::new();
+    let mut unique_netadapters: HashMap<String, &NetAdapterChange> = HashMap::new();
+    let mut unique_policies: HashMap<String, &PolicyChange> = HashMap::new();
+    let mut unique_apps: HashMap<String, &AppChange> = HashMap::new();
 
-    for option in &tweak.options {
-        for change in &option.registry_changes {
-            if !change.applies_to_version(windows_version) {
+    for (((option, registry_changes), policy_changes), file_changes) in tweak
+        .options
+        .iter()
+        .zip(&expanded_registry_changes)
+        .zip(&option_policy_changes)
+        .zip(&option_file_changes)
+    {
+        for change in registry_changes {
+            if !change.applies_to(windows_version, system_info_service::system_facts()) {
                 continue;
             }
-            let key_id = format!(
-                "{}\\{}\\{}",
-                change.hive.as_str(),
-                change.key,
-                change.value_name
-            );
+            let key_id = match change.action {
+                // Key-level snapshots get their own id, so they don't collide with the key's
+                // default value. A delete_key wins over a create_key of the same key, since only
+                // its snapshot keeps the key's contents.
+                RegistryAction::DeleteKey | RegistryAction::CreateKey => {
+                    let key_id = format!("{}\\{}\\[key]", change.hive.as_str(), change.key);
+                    if change.action == RegistryAction::DeleteKey {
+                        unique_registry.insert(key_id, change);
+                        continue;
+                    }
+                    key_id
+                }
+                RegistryAction::Set | RegistryAction::DeleteValue => format!(
+                    "{}\\{}\\{}",
+                    change.hive.as_str(),
+                    change.key,
+                    change.value_name
+                ),
+            };
             unique_registry.entry(key_id).or_insert(change);
         }
 
-        for sc in &option.service_changes {
-            unique_services.insert(sc.name.clone());
+        let services = option
+            .all_service_changes()
+            .into_iter()
+            .map(|sc| (sc.name, sc.elevation));
+        let probes = option
+            .state_probes
+            .services
+            .iter()
+            .map(|name| (name.clone(), None));
+        for (name, elevation) in services.chain(probes) {
+            if !unique_services.iter().any(|(seen, _)| *seen == name) {
+                unique_services.push((name, elevation));
+            }
         }
 
         for task_change in &option.scheduler_changes {
+            let deleted = task_change.action == SchedulerAction::Delete;
             if let Some(ref pattern) = task_change.task_name_pattern {
-                unique_task_patterns.push((&task_change.task_path, pattern));
+                unique_task_patterns.push((
+                    &task_change.task_path,
+                    pattern,
+                    deleted,
+                    task_change.elevation,
+                ));
             } else if let Some(ref task_name) = task_change.task_name {
-                unique_tasks.push((&task_change.task_path, task_name));
+                unique_tasks.push((
+                    &task_change.task_path,
+                    task_name,
+                    deleted,
+                    task_change.elevation,
+                ));
             }
         }
 
@@ -336,119 +951,353 @@ pub fn capture_current_state(
         for fc in &option.firewall_changes {
             unique_firewall.insert(fc.name.clone());
         }
+
+        for pc in &option.power_plan_changes {
+            // Every set_active_scheme captures the same thing (the active scheme), so they share
+            // one key; settings are keyed by their (scheme, subgroup, setting) triple.
+            let key = match pc.action {
+                PowerPlanAction::SetActiveScheme => "active_scheme".to_string(),
+                PowerPlanAction::SetSettingValue => format!(
+                    "{}|{}|{}",
+                    pc.scheme
+                        .as_deref()
+                        .unwrap_or(power_service::SCHEME_CURRENT),
+                    pc.subgroup.as_deref().unwrap_or_default(),
+                    pc.setting.as_deref().unwrap_or_default()
+                )
+                .to_lowercase(),
+            };
+            unique_power_plan.entry(key).or_insert(pc);
+        }
+
+        for bc in &option.bcdedit_changes {
+            let identifier = bcdedit_service::resolve_identifier(bc.identifier.as_deref());
+            let key = format!("{}|{}", identifier, bc.element).to_lowercase();
+            unique_bcdedit
+                .entry(key)
+                .or_insert((identifier, &bc.element));
+        }
+
+        for ac in &option.appx_changes {
+            // Keyed by what the capture reads, so e.g. a remove and a reinstall of the same
+            // package in the current-user scope share one snapshot.
+            let key = format!(
+                "{}|{}|{}",
+                ac.package,
+                ac.scope.as_str(),
+                ac.action == AppxAction::RemoveProvisioned
+            )
+            .to_lowercase();
+            unique_appx.entry(key).or_insert(ac);
+        }
+
+        for fc in file_changes {
+            let key = format!(
+                "{}|{}|{}",
+                fc.action.as_str(),
+                fc.path,
+                fc.destination.as_deref().unwrap_or_default()
+            )
+            .to_lowercase();
+            unique_files.entry(key).or_insert(fc);
+        }
+
+        for rc in &option.registry_acl_changes {
+            let key = format!("{}\\{}", rc.hive.as_str(), rc.key).to_lowercase();
+            unique_registry_acls.entry(key).or_insert(rc);
+        }
+
+        for fc in &option.feature_changes {
+            unique_features
+                .entry(fc.feature.to_lowercase())
+                .or_insert(fc);
+        }
+
+        for nc in &option.netadapter_changes {
+            let key = format!(
+                "{}|{}",
+                network_service::resolve_adapter(nc.adapter.as_deref()),
+                nc.keyword
+            )
+            .to_lowercase();
+            unique_netadapters.entry(key).or_insert(nc);
+        }
+
+        for pc in policy_changes {
+            let key = format!("{}|{}|{}", pc.scope.as_str(), pc.key, pc.value_name).to_lowercase();
+            // Prefer a change that writes Registry.pol, so its record gets captured
+            let slot = unique_policies.entry(key).or_insert(pc);
+            if pc.write_pol {
+                *slot = pc;
+            }
+        }
+
+        for ac in &option.app_changes {
+            // Prefer an install, so a revert reinstalls from the source it names
+            let slot = unique_apps.entry(ac.id.to_lowercase()).or_insert(ac);
+            if ac.action == AppAction::Install {
+                *slot = ac;
+            }
+        }
+    }
+
+    for change in &policy_registry_changes {
+        let key_id = format!(
+            "{}\\{}\\{}",
+            change.hive.as_str(),
+            change.key,
+            change.value_name
+        );
+        unique_registry.entry(key_id).or_insert(change);
     }
 
     // Capture all categories in parallel
     let registry_changes: Vec<_> = unique_registry.values().cloned().collect();
-    let service_names: Vec<_> = unique_services.iter().cloned().collect();
+    let services = unique_services;
     let hosts_entries: Vec<_> = unique_hosts.values().cloned().collect();
     let firewall_names: Vec<_> = unique_firewall.iter().cloned().collect();
+    let power_plan_changes: Vec<PowerPlanChange> =
+        unique_power_plan.values().map(|&pc| pc.clone()).collect();
+    let bcdedit_elements: Vec<_> = unique_bcdedit.values().cloned().collect();
+    let appx_changes: Vec<AppxChange> = unique_appx.values().map(|&ac| ac.clone()).collect();
+    let file_changes: Vec<FileChange> = unique_files.values().map(|&fc| fc.clone()).collect();
+    let registry_acl_changes: Vec<RegistryAclChange> = unique_registry_acls
+        .values()
+        .map(|&rc| rc.clone())
+        .collect();
+    let feature_changes: Vec<FeatureChange> =
+        unique_features.values().map(|&fc| fc.clone()).collect();
+    let netadapter_changes: Vec<NetAdapterChange> =
+        unique_netadapters.values().map(|&nc| nc.clone()).collect();
+    let policy_changes: Vec<PolicyChange> =
+        unique_policies.values().map(|&pc| pc.clone()).collect();
+    let app_changes: Vec<AppChange> = unique_apps.values().map(|&ac| ac.clone()).collect();
 
-    let ((registry_result, (services_result, scheduler_result)), (hosts_result, firewall_result)) =
-        rayon::join(
-            || {
-                rayon::join(
-                    || {
-                        // Parallel registry capture (same value-detection as capture_snapshot).
-                        registry_changes
-                            .par_iter()
-                            .map(|&change| capture_value_snapshot(change))
-                            .collect::<Result<Vec<_>, Error>>()
-                    },
-                    || {
-                        rayon::join(
-                            || {
-                                // Parallel service capture
-                                service_names
-                                    .par_iter()
-                                    .map(|name| capture_service_state(name))
-                                    .collect::<Result<Vec<_>, Error>>()
-                            },
-                            || {
-                                // Scheduler capture
-                                let mut snapshots = Vec::new();
-                                let mut captured_tasks_set: HashSet<String> = HashSet::new();
-
-                                for (task_path, pattern) in &unique_task_patterns {
-                                    // Propagate a read failure rather than silently dropping these
-                                    // tasks from the rollback snapshot.
-                                    let matching_tasks = scheduler_service::find_tasks_by_pattern(
-                                        task_path, pattern,
-                                    )?;
-                                    for task in matching_tasks {
-                                        let task_id = format!("{}\\{}", task_path, task.name);
-                                        if !captured_tasks_set.contains(&task_id) {
-                                            captured_tasks_set.insert(task_id);
-                                            snapshots.push(SchedulerSnapshot {
-                                                task_path: task_path.to_string(),
-                                                task_name: task.name.clone(),
-                                                original_state: task.state.as_str().to_string(),
-                                            });
-                                        }
+    let (
+        (
+            ((registry_result, policy_result), registry_acl_result),
+            (services_result, scheduler_result),
+        ),
+        (
+            (hosts_result, firewall_result),
+            (
+                (power_plan_result, bcdedit_result),
+                ((appx_result, file_result), (feature_result, (netadapter_result, app_result))),
+            ),
+        ),
+    ) = rayon::join(
+        || {
+            rayon::join(
+                || {
+                    rayon::join(
+                        || {
+                            rayon::join(
+                                || {
+                                    // Parallel registry capture (same as capture_snapshot)
+                                    registry_changes
+                                        .par_iter()
+                                        .map(|&change| capture_registry_state(change))
+                                        .collect::<Result<Vec<_>, Error>>()
+                                },
+                                // Policy Registry.pol capture
+                                || capture_policy_snapshots(&policy_changes),
+                            )
+                        },
+                        // Registry ACL capture
+                        || capture_registry_acl_snapshots(&registry_acl_changes),
+                    )
+                },
+                || {
+                    rayon::join(
+                        || {
+                            // Parallel service capture
+                            services
+                                .par_iter()
+                                .map(|(name, elevation)| {
+                                    capture_declared_service_state(name, *elevation, tweak)
+                                })
+                                .collect::<Result<Vec<_>, Error>>()
+                        },
+                        || {
+                            // Scheduler capture. A task reached through several changes (names
+                            // and patterns, across options) is captured once, with its definition
+                            // if any of them deletes it.
+                            // (path, name, state if already known, deleted, elevation)
+                            type Task = (
+                                String,
+                                String,
+                                Option<TaskState>,
+                                bool,
+                                Option<ChangeElevation>,
+                            );
+                            let mut tasks: Vec<Task> = Vec::new();
+                            let mut add_task =
+                                |path: &str, name: String, state, deleted: bool, elevation| {
+                                    match tasks.iter_mut().find(|t| t.0 == path && t.1 == name) {
+                                        Some(task) => task.3 |= deleted,
+                                        None => tasks.push((
+                                            path.to_string(),
+                                            name,
+                                            state,
+                                            deleted,
+                                            elevation,
+                                        )),
                                     }
+                                };
+
+                            for (task_path, pattern, deleted, elevation) in &unique_task_patterns {
+                                // Propagate a read failure rather than silently dropping these
+                                // tasks from the rollback snapshot.
+                                let matching_tasks =
+                                    scheduler_service::find_tasks_by_pattern(task_path, pattern)?;
+                                for task in matching_tasks {
+                                    add_task(
+                                        task_path,
+                                        task.name,
+                                        Some(task.state),
+                                        *deleted,
+                                        *elevation,
+                                    );
                                 }
+                            }
+                            for (task_path, task_name, deleted, elevation) in &unique_tasks {
+                                add_task(
+                                    task_path,
+                                    task_name.to_string(),
+                                    None,
+                                    *deleted,
+                                    *elevation,
+                                );
+                            }
 
-                                for (task_path, task_name) in &unique_tasks {
-                                    let task_id = format!("{}\\{}", task_path, task_name);
-                                    if !captured_tasks_set.contains(&task_id) {
-                                        captured_tasks_set.insert(task_id);
-                                        match capture_scheduler_state(task_path, task_name) {
-                                            Ok(task_snapshot) => snapshots.push(task_snapshot),
-                                            Err(e) => {
-                                                log::debug!(
-                                                "Could not capture state for task {}\\{}: {} (may not exist)",
-                                                task_path,
-                                                task_name,
-                                                e
-                                            );
-                                            }
-                                        }
+                            let mut snapshots = Vec::new();
+                            for (task_path, task_name, state, deleted, elevation) in tasks {
+                                if let Some(state) = state {
+                                    let mut task_snapshot = scheduler_snapshot(
+                                        &task_path, &task_name, &state, deleted,
+                                    )?;
+                                    task_snapshot.elevation = elevation;
+                                    snapshots.push(task_snapshot);
+                                    continue;
+                                }
+                                match capture_scheduler_state(&task_path, &task_name, deleted) {
+                                    Ok(mut task_snapshot) => {
+                                        task_snapshot.elevation = elevation;
+                                        snapshots.push(task_snapshot)
+                                    }
+                                    Err(e) => {
+                                        log::debug!(
+                                            "Could not capture state for task {}\\{}: {} (may not exist)",
+                                            task_path,
+                                            task_name,
+                                            e
+                                        );
                                     }
                                 }
+                            }
 
-                                Ok::<_, Error>(snapshots)
-                            },
-                        )
-                    },
-                )
-            },
-            || {
-                rayon::join(
-                    || {
-                        // Hosts capture
-                        hosts_entries
-                            .iter()
-                            .map(|(ip, domain)| {
-                                let existed = hosts_service::entry_exists(ip, domain)?;
-                                Ok(HostsSnapshot {
-                                    ip: ip.to_string(),
-                                    domain: domain.to_string(),
-                                    existed,
+                            Ok::<_, Error>(snapshots)
+                        },
+                    )
+                },
+            )
+        },
+        || {
+            rayon::join(
+                || {
+                    rayon::join(
+                        || {
+                            // Hosts capture
+                            hosts_entries
+                                .iter()
+                                .map(|(ip, domain)| {
+                                    let existed = hosts_service::entry_exists(ip, domain)?;
+                                    Ok(HostsSnapshot {
+                                        ip: ip.to_string(),
+                                        domain: domain.to_string(),
+                                        existed,
+                                    })
                                 })
-                            })
-                            .collect::<Result<Vec<_>, Error>>()
-                    },
-                    || {
-                        // Firewall capture
-                        firewall_names
-                            .iter()
-                            .map(|name| {
-                                let existed = firewall_service::rule_exists(name)?;
-                                Ok(FirewallSnapshot {
-                                    name: name.clone(),
-                                    existed,
+                                .collect::<Result<Vec<_>, Error>>()
+                        },
+                        || {
+                            // Firewall capture
+                            firewall_names
+                                .iter()
+                                .map(|name| {
+                                    let existed = firewall_service::rule_exists(name)?;
+                                    Ok(FirewallSnapshot {
+                                        name: name.clone(),
+                                        existed,
+                                    })
                                 })
-                            })
-                            .collect::<Result<Vec<_>, Error>>()
-                    },
-                )
-            },
-        );
+                                .collect::<Result<Vec<_>, Error>>()
+                        },
+                    )
+                },
+                || {
+                    rayon::join(
+                        || {
+                            rayon::join(
+                                // Power plan capture
+                                || capture_power_plan_snapshots(&power_plan_changes),
+                                || {
+                                    // BCD capture
+                                    bcdedit_elements
+                                        .iter()
+                                        .map(|(identifier, element)| {
+                                            capture_bcdedit_state(identifier, element)
+                                        })
+                                        .collect::<Result<Vec<_>, Error>>()
+                                },
+                            )
+                        },
+                        || {
+                            rayon::join(
+                                || {
+                                    rayon::join(
+                                        // AppX capture
+                                        || capture_appx_snapshots(&appx_changes),
+                                        // File capture
+                                        || capture_file_snapshots(&tweak.id, &file_changes),
+                                    )
+                                },
+                                || {
+                                    rayon::join(
+                                        // Optional feature capture
+                                        || capture_feature_snapshots(&feature_changes),
+                                        || {
+                                            rayon::join(
+                                                // Network adapter capture
+                                                || {
+                                                    capture_netadapter_snapshots(
+                                                        &netadapter_changes,
+                                                    )
+                                                },
+                                                // Application capture
+                                                || capture_app_snapshots(&app_changes),
+                                            )
+                                        },
+                                    )
+                                },
+                            )
+                        },
+                    )
+                },
+            )
+        },
+    );
 
     // Add results to snapshot
     for reg in registry_result? {
         snapshot.add_registry_snapshot(reg);
     }
+    for policy in policy_result? {
+        snapshot.add_policy_snapshot(policy);
+    }
+    for acl in registry_acl_result? {
+        snapshot.add_registry_acl_snapshot(acl);
+    }
     for svc in services_result? {
         snapshot.add_service_snapshot(svc);
     }
@@ -461,23 +1310,80 @@ pub fn capture_current_state(
     for fw in firewall_result? {
         snapshot.add_firewall_snapshot(fw);
     }
+    for pp in power_plan_result? {
+        snapshot.add_power_plan_snapshot(pp);
+    }
+    for bcd in bcdedit_result? {
+        snapshot.add_bcdedit_snapshot(bcd);
+    }
+    for appx in appx_result? {
+        snapshot.add_appx_snapshot(appx);
+    }
+    for file in file_result? {
+        snapshot.add_file_snapshot(file);
+    }
+    for feature in feature_result? {
+        snapshot.add_feature_snapshot(feature);
+    }
+    for netadapter in netadapter_result? {
+        snapshot.add_netadapter_snapshot(netadapter);
+    }
+    for app in app_result? {
+        snapshot.add_app_snapshot(app);
+    }
 
     log::info!(
-        "Captured current state: {} registry, {} services, {} tasks, {} hosts, {} firewall for '{}'",
+        "Captured current state: {} registry, {} policies, {} registry ACLs, {} services, {} tasks, {} hosts, {} firewall, {} power plan, {} BCD, {} AppX, {} files, {} features, {} adapter properties, {} apps for '{}'",
         snapshot.registry_snapshots.len(),
+        snapshot.policy_snapshots.len(),
+        snapshot.registry_acl_snapshots.len(),
         snapshot.service_snapshots.len(),
         snapshot.scheduler_snapshots.len(),
         snapshot.hosts_snapshots.len(),
         snapshot.firewall_snapshots.len(),
+        snapshot.power_plan_snapshots.len(),
+        snapshot.bcdedit_snapshots.len(),
+        snapshot.appx_snapshots.len(),
+        snapshot.file_snapshots.len(),
+        snapshot.feature_snapshots.len(),
+        snapshot.netadapter_snapshots.len(),
+        snapshot.app_snapshots.len(),
         tweak.name
     );
 
     Ok(snapshot)
 }
 
-/// Capture current service state
-pub(crate) fn capture_service_state(service_name: &str) -> Result<ServiceSnapshot, Error> {
-    let status = service_control::get_service_status(service_name)?;
+/// Capture current service state. Protected services can refuse the query, or their startup type,
+/// to an administrator; for a tweak that runs elevated they're queried again at its elevation, so
+/// the snapshot records what revert has to restore rather than "unknown".
+pub(crate) fn capture_service_state(
+    service_name: &str,
+    elevation: Elevation,
+) -> Result<ServiceSnapshot, Error> {
+    let status = match service_control::get_service_status(service_name) {
+        Ok(status) if status.exists && status.startup_type.is_none() && elevation.is_elevated() => {
+            elevation::run_service_query(elevation, service_name).unwrap_or_else(|e| {
+                log::warn!(
+                    "Failed to query service '{}' as {}: {}",
+                    service_name,
+                    elevation.label(),
+                    e
+                );
+                status
+            })
+        }
+        Err(e) if elevation.is_elevated() => {
+            log::debug!(
+                "Querying service '{}' as {} ({})",
+                service_name,
+                elevation.label(),
+                e
+            );
+            elevation::run_service_query(elevation, service_name)?
+        }
+        result => result?,
+    };
     let startup_type = status
         .startup_type
         .map(|t| format!("{:?}", t).to_lowercase())
@@ -487,20 +1393,40 @@ pub(crate) fn capture_service_state(service_name: &str) -> Result<ServiceSnapsho
         name: service_name.to_string(),
         startup_type,
         was_running: status.state == service_control::ServiceState::Running,
+        elevation: None,
     })
 }
 
-/// Capture current scheduled task state
+/// Capture current scheduled task state. `deleted` marks a task the change deletes; its
+/// definition is captured too, so revert can register it again.
 pub(crate) fn capture_scheduler_state(
     task_path: &str,
     task_name: &str,
+    deleted: bool,
 ) -> Result<SchedulerSnapshot, Error> {
     let state = scheduler_service::get_task_state(task_path, task_name)?;
+    scheduler_snapshot(task_path, task_name, &state, deleted)
+}
+
+/// Snapshot of a task whose state is already known
+fn scheduler_snapshot(
+    task_path: &str,
+    task_name: &str,
+    state: &TaskState,
+    deleted: bool,
+) -> Result<SchedulerSnapshot, Error> {
+    let definition_xml = if deleted && *state != TaskState::NotFound {
+        scheduler_service::get_task_xml(task_path, task_name)?
+    } else {
+        None
+    };
 
     Ok(SchedulerSnapshot {
         task_path: task_path.to_string(),
         task_name: task_name.to_string(),
         original_state: state.as_str().to_string(),
+        definition_xml,
+        elevation: None,
     })
 }
 
@@ -604,7 +1530,11 @@ mod capture_value_tests {
             value_type: None,
             value: None,
             windows_versions: None,
+            conditions: None,
+            requires_reboot: false,
+            requires_logoff: false,
             skip_validation: false,
+            elevation: None,
         };
 
         let snap = capture_value_snapshot(&change)
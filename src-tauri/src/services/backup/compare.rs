@@ -17,13 +17,23 @@
 
 use crate::error::Error;
 use crate::models::inspection::{
-    FirewallMismatch, HostsMismatch, RegistryMismatch, SchedulerMismatch, ServiceMismatch,
+    AppMismatch, AppxMismatch, BcdeditMismatch, FeatureMismatch, FileMismatch, FirewallMismatch,
+    HostsMismatch, NetAdapterMismatch, PolicyMismatch, PowerPlanMismatch, RegistryAclMismatch,
+    RegistryMismatch, SchedulerMismatch, ServiceMismatch,
+};
+use crate::models::tweak::{
+    AppAction, AppxAction, AppxScope, BcdeditAction, FeatureAction, FileAction, FirewallOperation,
+    HostsAction, PolicyAction, PowerPlanAction, RegistryAccess, RegistryAclAction, SchedulerAction,
+};
+use crate::models::{
+    RegistryAction, RegistryHive, RegistryValueType, ServiceStartupType, TweakOption,
+    USER_PREFERENCES_MASK_KEY, USER_PREFERENCES_MASK_VALUE,
 };
-use crate::models::tweak::{FirewallOperation, HostsAction, SchedulerAction};
-use crate::models::{RegistryAction, TweakOption};
 use crate::services::{
-    firewall_service, hosts_service, registry_service, registry_value, scheduler_service,
-    service_control,
+    appx_service, bcdedit_service, dism_service, file_service, firewall_service, hosts_service,
+    network_service, package_service, policy_service, power_service, registry_acl_service,
+    registry_service, registry_value, scheduler_service, service_control, system_info_service,
+    visual_effects_service,
 };
 
 use super::capture::read_registry_value;
@@ -36,6 +46,15 @@ pub struct OptionComparison {
     pub scheduler: Vec<SchedulerMismatch>,
     pub hosts: Vec<HostsMismatch>,
     pub firewall: Vec<FirewallMismatch>,
+    pub power_plan: Vec<PowerPlanMismatch>,
+    pub bcdedit: Vec<BcdeditMismatch>,
+    pub appx: Vec<AppxMismatch>,
+    pub file: Vec<FileMismatch>,
+    pub registry_acl: Vec<RegistryAclMismatch>,
+    pub feature: Vec<FeatureMismatch>,
+    pub netadapter: Vec<NetAdapterMismatch>,
+    pub policy: Vec<PolicyMismatch>,
+    pub app: Vec<AppMismatch>,
     /// True if any validatable item matched only because a `*_missing_is_match` flag treated a
     /// missing item as a match (rather than an actual-value match). Drives `status_inferred`.
     pub inferred: bool,
@@ -46,7 +65,20 @@ impl OptionComparison {
     /// item AND every validatable item matches. An option with zero validatable items cannot be the
     /// current state — there is nothing to confirm — so it does not match.
     pub fn all_match(&self) -> bool {
-        let validatable: Vec<bool> = std::iter::empty()
+        let validatable = self.validatable();
+        !validatable.is_empty() && validatable.iter().all(|&m| m)
+    }
+
+    /// How many validatable items match and how many don't
+    pub fn match_counts(&self) -> (usize, usize) {
+        let validatable = self.validatable();
+        let matched = validatable.iter().filter(|&&m| m).count();
+        (matched, validatable.len() - matched)
+    }
+
+    /// Whether each validatable (non-`skip_validation`) item matches
+    fn validatable(&self) -> Vec<bool> {
+        std::iter::empty()
             .chain(
                 self.registry
                     .iter()
@@ -77,9 +109,61 @@ impl OptionComparison {
                     .filter(|f| !f.skip_validation)
                     .map(|f| f.is_match),
             )
-            .collect();
-
-        !validatable.is_empty() && validatable.iter().all(|&m| m)
+            .chain(
+                self.power_plan
+                    .iter()
+                    .filter(|p| !p.skip_validation)
+                    .map(|p| p.is_match),
+            )
+            .chain(
+                self.bcdedit
+                    .iter()
+                    .filter(|b| !b.skip_validation)
+                    .map(|b| b.is_match),
+            )
+            .chain(
+                self.appx
+                    .iter()
+                    .filter(|a| !a.skip_validation)
+                    .map(|a| a.is_match),
+            )
+            .chain(
+                self.file
+                    .iter()
+                    .filter(|f| !f.skip_validation)
+                    .map(|f| f.is_match),
+            )
+            .chain(
+                self.registry_acl
+                    .iter()
+                    .filter(|r| !r.skip_validation)
+                    .map(|r| r.is_match),
+            )
+            .chain(
+                self.feature
+                    .iter()
+                    .filter(|f| !f.skip_validation)
+                    .map(|f| f.is_match),
+            )
+            .chain(
+                self.netadapter
+                    .iter()
+                    .filter(|n| !n.skip_validation)
+                    .map(|n| n.is_match),
+            )
+            .chain(
+                self.policy
+                    .iter()
+                    .filter(|p| !p.skip_validation)
+                    .map(|p| p.is_match),
+            )
+            .chain(
+                self.app
+                    .iter()
+                    .filter(|a| !a.skip_validation)
+                    .map(|a| a.is_match),
+            )
+            .collect()
     }
 }
 
@@ -94,12 +178,30 @@ pub fn compare_option(
     let scheduler = compare_scheduler(option, &mut inferred)?;
     let hosts = compare_hosts(option)?;
     let firewall = compare_firewall(option)?;
+    let power_plan = compare_power_plan(option)?;
+    let bcdedit = compare_bcdedit(option)?;
+    let appx = compare_appx(option)?;
+    let file = compare_files(option)?;
+    let registry_acl = compare_registry_acl(option)?;
+    let feature = compare_features(option)?;
+    let netadapter = compare_netadapters(option)?;
+    let policy = compare_policies(option)?;
+    let app = compare_apps(option)?;
     Ok(OptionComparison {
         registry,
         service,
         scheduler,
         hosts,
         firewall,
+        power_plan,
+        bcdedit,
+        appx,
+        file,
+        registry_acl,
+        feature,
+        netadapter,
+        policy,
+        app,
         inferred,
     })
 }
@@ -118,9 +220,20 @@ fn compare_registry(
 ) -> Result<Vec<RegistryMismatch>, Error> {
     let missing_is_match = option.registry_missing_is_match;
     let mut results = Vec::new();
+    // ALL_USERS changes are compared (and reported) per user hive; MMCSS, context menu, audio,
+    // visual effects, and time changes are reported as the registry values they write
+    let registry_changes = registry_service::option_registry_changes(option)?;
+    let has_mask_effects = option
+        .visual_effects_changes
+        .iter()
+        .any(|change| change.effect.mask_bit().is_some());
 
-    for change in &option.registry_changes {
-        if !change.applies_to_version(windows_version) {
+    for change in &registry_changes {
+        if !change.applies_to(windows_version, system_info_service::system_facts()) {
+            continue;
+        }
+        // Compared bit by bit in compare_visual_effects_mask
+        if has_mask_effects && visual_effects_service::is_preferences_mask(change) {
             continue;
         }
 
@@ -226,6 +339,62 @@ fn compare_registry(
         results.push(mismatch);
     }
 
+    if has_mask_effects {
+        results.extend(compare_visual_effects_mask(
+            option,
+            missing_is_match,
+            inferred,
+        )?);
+    }
+
+    Ok(results)
+}
+
+/// One entry per visual effect stored in `UserPreferencesMask`, comparing only that effect's bit:
+/// the other bits belong to unrelated settings and don't count.
+fn compare_visual_effects_mask(
+    option: &TweakOption,
+    missing_is_match: bool,
+    inferred: &mut bool,
+) -> Result<Vec<RegistryMismatch>, Error> {
+    let mask = visual_effects_service::read_preferences_mask()?;
+    let mut results = Vec::new();
+
+    for change in &option.visual_effects_changes {
+        let Some((byte, bit)) = change.effect.mask_bit() else {
+            continue;
+        };
+        let actual = mask
+            .as_deref()
+            .map(|mask| visual_effects_service::mask_bit_is_set(mask, (byte, bit)));
+        let is_match = match actual {
+            Some(actual) => actual == change.enabled,
+            None if missing_is_match => {
+                note_inferred(inferred, change.skip_validation);
+                true
+            }
+            None => false,
+        };
+
+        results.push(RegistryMismatch {
+            hive: RegistryHive::Hkcu.as_str().to_string(),
+            key: USER_PREFERENCES_MASK_KEY.to_string(),
+            value_name: USER_PREFERENCES_MASK_VALUE.to_string(),
+            expected_value: Some(serde_json::json!(change.enabled)),
+            actual_value: actual.map(|actual| serde_json::json!(actual)),
+            value_type: Some(RegistryValueType::Binary.as_str().to_string()),
+            description: format!(
+                "Turn {} {} (byte {}, bit 0x{:02X})",
+                change.effect.as_str(),
+                if change.enabled { "on" } else { "off" },
+                byte,
+                bit
+            ),
+            is_match,
+            skip_validation: change.skip_validation,
+        });
+    }
+
     Ok(results)
 }
 
@@ -236,13 +405,18 @@ fn compare_service(
     let missing_is_match = option.service_missing_is_match;
     let mut results = Vec::new();
 
-    for change in &option.service_changes {
+    for change in &option.all_service_changes() {
         // A query failure propagates (as it did in detection); a service that genuinely does not
         // exist is Ok(status) with exists == false, which the missing_is_match flag can absorb.
         let status = service_control::get_service_status(&change.name)?;
 
         let is_match = if !status.exists {
-            if missing_is_match {
+            // A component's service that isn't installed is as off as a disabled one
+            if change.startup == ServiceStartupType::Disabled
+                && option.is_component_service(&change.name)
+            {
+                true
+            } else if missing_is_match {
                 note_inferred(inferred, change.skip_validation);
                 true
             } else {
@@ -403,6 +577,488 @@ fn compare_firewall(option: &TweakOption) -> Result<Vec<FirewallMismatch>, Error
     Ok(results)
 }
 
+fn compare_power_plan(option: &TweakOption) -> Result<Vec<PowerPlanMismatch>, Error> {
+    if option.power_plan_changes.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let active_scheme = power_service::get_active_scheme()?;
+    let mut results = Vec::new();
+
+    for change in &option.power_plan_changes {
+        // SCHEME_CURRENT resolves against the scheme active now, as it did during apply.
+        let scheme = power_service::resolve_scheme(change.scheme.as_deref(), &active_scheme)?;
+
+        let mismatch = match change.action {
+            PowerPlanAction::SetActiveScheme => PowerPlanMismatch {
+                action: change.action.as_str().to_string(),
+                scheme: scheme.clone(),
+                subgroup: None,
+                setting: None,
+                expected_value: Some(serde_json::json!(scheme)),
+                actual_value: Some(serde_json::json!(active_scheme)),
+                description: format!("Activate power scheme {}", scheme),
+                is_match: scheme == active_scheme,
+                skip_validation: change.skip_validation,
+            },
+            PowerPlanAction::SetSettingValue => {
+                let (subgroup, setting) = match power_service::setting_target(change) {
+                    Ok(target) => target,
+                    Err(_) => continue, // Invalid config: nothing to compare.
+                };
+
+                // A setting this machine does not have reads as absent (a mismatch), not an error.
+                let current = match power_service::read_setting(&scheme, subgroup, setting) {
+                    Ok(values) => Some(values),
+                    Err(Error::NotFound(_)) => None,
+                    Err(e) => return Err(e),
+                };
+
+                let is_match = current.is_some_and(|values| {
+                    change.ac_value.is_none_or(|ac| ac == values.ac)
+                        && change.dc_value.is_none_or(|dc| dc == values.dc)
+                });
+
+                PowerPlanMismatch {
+                    action: change.action.as_str().to_string(),
+                    scheme,
+                    subgroup: Some(subgroup.to_string()),
+                    setting: Some(setting.to_string()),
+                    expected_value: Some(
+                        serde_json::json!({ "ac": change.ac_value, "dc": change.dc_value }),
+                    ),
+                    actual_value: current
+                        .map(|values| serde_json::json!({ "ac": values.ac, "dc": values.dc })),
+                    description: format!("Set power setting {}", setting),
+                    is_match,
+                    skip_validation: change.skip_validation,
+                }
+            }
+        };
+
+        results.push(mismatch);
+    }
+
+    Ok(results)
+}
+
+fn compare_bcdedit(option: &TweakOption) -> Result<Vec<BcdeditMismatch>, Error> {
+    let mut results = Vec::new();
+
+    for change in &option.bcdedit_changes {
+        let identifier = bcdedit_service::resolve_identifier(change.identifier.as_deref());
+        let actual = bcdedit_service::get_value(identifier, &change.element)?;
+        let (is_match, description) = match change.action {
+            BcdeditAction::Set => {
+                let expected = change.value.as_deref().unwrap_or_default();
+                (
+                    actual
+                        .as_deref()
+                        .is_some_and(|a| bcdedit_service::values_equal(expected, a)),
+                    format!("Set BCD {} {} = {}", identifier, change.element, expected),
+                )
+            }
+            BcdeditAction::DeleteValue => (
+                actual.is_none(),
+                format!("Delete BCD {} {}", identifier, change.element),
+            ),
+        };
+        results.push(BcdeditMismatch {
+            identifier: identifier.to_string(),
+            element: change.element.clone(),
+            expected_value: change.value.clone(),
+            actual_value: actual,
+            description,
+            is_match,
+            skip_validation: change.skip_validation,
+        });
+    }
+
+    Ok(results)
+}
+
+fn compare_appx(option: &TweakOption) -> Result<Vec<AppxMismatch>, Error> {
+    let mut results = Vec::new();
+
+    for change in &option.appx_changes {
+        let (scope, expected_present, actual_present, description) = match change.action {
+            AppxAction::Remove => (
+                change.scope,
+                false,
+                !appx_service::list_installed(&change.package, change.scope)?.is_empty(),
+                format!("Remove AppX package '{}'", change.package),
+            ),
+            AppxAction::RemoveProvisioned => (
+                change.scope,
+                false,
+                !appx_service::list_provisioned(&change.package)?.is_empty(),
+                format!("Deprovision AppX package '{}'", change.package),
+            ),
+            AppxAction::Reinstall => (
+                AppxScope::CurrentUser,
+                true,
+                !appx_service::list_installed(&change.package, AppxScope::CurrentUser)?.is_empty(),
+                format!("Reinstall AppX package '{}'", change.package),
+            ),
+        };
+        results.push(AppxMismatch {
+            package: change.package.clone(),
+            action: change.action.as_str().to_string(),
+            scope: scope.as_str().to_string(),
+            expected_present,
+            actual_present,
+            description,
+            is_match: expected_present == actual_present,
+            skip_validation: change.skip_validation,
+        });
+    }
+
+    Ok(results)
+}
+
+fn compare_files(option: &TweakOption) -> Result<Vec<FileMismatch>, Error> {
+    let mut results = Vec::new();
+
+    for change in &option.file_changes {
+        let path = file_service::expand_path(&change.path);
+        let destination = change.destination.as_deref().map(file_service::expand_path);
+        let (expected_state, actual_state, description) = match change.action {
+            FileAction::Copy => {
+                let dest = file_service::destination(change)?;
+                let actual = if !file_service::path_exists(&dest) {
+                    "missing"
+                } else if file_service::is_dir(&path) {
+                    // Folder copies are checked for presence only
+                    "copied"
+                } else if file_service::files_equal(&path, &dest)? {
+                    "copied"
+                } else {
+                    "different"
+                };
+                ("copied", actual, format!("Copy '{}' to '{}'", path, dest))
+            }
+            FileAction::Delete => (
+                "absent",
+                if file_service::path_exists(&path) {
+                    "present"
+                } else {
+                    "absent"
+                },
+                format!("Delete '{}'", path),
+            ),
+            FileAction::Rename => {
+                let dest = file_service::destination(change)?;
+                let actual =
+                    if !file_service::path_exists(&path) && file_service::path_exists(&dest) {
+                        "renamed"
+                    } else {
+                        "not renamed"
+                    };
+                (
+                    "renamed",
+                    actual,
+                    format!("Rename '{}' to '{}'", path, dest),
+                )
+            }
+            FileAction::SetAttributes => {
+                let actual = if !file_service::path_exists(&path) {
+                    "missing"
+                } else {
+                    let current = file_service::get_attributes(&path)?;
+                    let flags = change.attributes.clone().unwrap_or_default();
+                    if file_service::apply_attribute_flags(current, &flags) == current {
+                        "set"
+                    } else {
+                        "different"
+                    }
+                };
+                ("set", actual, format!("Set attributes of '{}'", path))
+            }
+            FileAction::WriteContent => {
+                let content = change.content.as_deref().unwrap_or_default();
+                let actual = if !file_service::path_exists(&path) {
+                    "missing"
+                } else if file_service::content_equals(&path, content.as_bytes())? {
+                    "written"
+                } else {
+                    "different"
+                };
+                ("written", actual, format!("Write content to '{}'", path))
+            }
+        };
+        results.push(FileMismatch {
+            path,
+            action: change.action.as_str().to_string(),
+            destination,
+            expected_state: expected_state.to_string(),
+            actual_state: actual_state.to_string(),
+            description,
+            is_match: expected_state == actual_state,
+            skip_validation: change.skip_validation,
+        });
+    }
+
+    Ok(results)
+}
+
+/// Label for the access a mask covers, in `RegistryAccess` terms.
+fn access_label(mask: u32) -> &'static str {
+    let covers = |access| {
+        let required = registry_acl_service::access_mask(access);
+        mask & required == required
+    };
+    if covers(RegistryAccess::FullControl) {
+        RegistryAccess::FullControl.as_str()
+    } else if covers(RegistryAccess::Read) {
+        RegistryAccess::Read.as_str()
+    } else {
+        "none"
+    }
+}
+
+fn compare_registry_acl(option: &TweakOption) -> Result<Vec<RegistryAclMismatch>, Error> {
+    let mut results = Vec::new();
+
+    for change in &option.registry_acl_changes {
+        let trustee = match change.action {
+            RegistryAclAction::Revoke => change.trustee.as_deref().unwrap_or_default(),
+            _ => registry_acl_service::resolve_trustee(change.trustee.as_deref()),
+        };
+        let state = registry_acl_service::trustee_state(change.hive, &change.key, trustee)?;
+        let path = format!("{}\\{}", change.hive.as_str(), change.key);
+
+        let (expected_state, actual_state, is_match, description) = match change.action {
+            RegistryAclAction::TakeOwnership => {
+                let is_owner = state.as_ref().is_some_and(|s| s.is_owner);
+                let actual = match &state {
+                    None => "missing",
+                    Some(_) if is_owner => "owner",
+                    Some(_) => "not owner",
+                };
+                (
+                    "owner",
+                    actual,
+                    is_owner,
+                    format!("Take ownership of {} for '{}'", path, trustee),
+                )
+            }
+            RegistryAclAction::Grant => {
+                let access = change.access.unwrap_or_default();
+                let required = registry_acl_service::access_mask(access);
+                let mask = state.as_ref().map(|s| s.explicit_allow_mask);
+                (
+                    access.as_str(),
+                    mask.map_or("missing", access_label),
+                    mask.is_some_and(|m| m & required == required),
+                    format!("Grant {} on {} to '{}'", access.as_str(), path, trustee),
+                )
+            }
+            RegistryAclAction::Revoke => {
+                let actual = match &state {
+                    None => "missing",
+                    Some(s) if s.has_explicit_entries => "explicit entries",
+                    Some(_) => "no explicit entries",
+                };
+                (
+                    "no explicit entries",
+                    actual,
+                    state.as_ref().is_some_and(|s| !s.has_explicit_entries),
+                    format!("Revoke explicit access on {} from '{}'", path, trustee),
+                )
+            }
+        };
+        results.push(RegistryAclMismatch {
+            hive: change.hive.as_str().to_string(),
+            key: change.key.clone(),
+            action: change.action.as_str().to_string(),
+            trustee: trustee.to_string(),
+            expected_state: expected_state.to_string(),
+            actual_state: actual_state.to_string(),
+            description,
+            is_match,
+            skip_validation: change.skip_validation,
+        });
+    }
+
+    Ok(results)
+}
+
+fn compare_features(option: &TweakOption) -> Result<Vec<FeatureMismatch>, Error> {
+    let mut results = Vec::new();
+
+    for change in &option.feature_changes {
+        let expected_enabled = change.action == FeatureAction::Enable;
+        let actual_enabled = dism_service::is_enabled(&change.feature)?;
+        // A feature this edition doesn't have can't be on, so it satisfies a disable
+        let is_match = actual_enabled.unwrap_or(false) == expected_enabled;
+        let description = match change.action {
+            FeatureAction::Enable => format!("Enable optional feature '{}'", change.feature),
+            FeatureAction::Disable => format!("Disable optional feature '{}'", change.feature),
+        };
+        results.push(FeatureMismatch {
+            feature: change.feature.clone(),
+            action: change.action.as_str().to_string(),
+            expected_enabled,
+            actual_enabled,
+            description,
+            is_match,
+            skip_validation: change.skip_validation,
+        });
+    }
+
+    Ok(results)
+}
+
+fn compare_netadapters(option: &TweakOption) -> Result<Vec<NetAdapterMismatch>, Error> {
+    let mut results = Vec::new();
+
+    for change in &option.netadapter_changes {
+        let adapter = network_service::resolve_adapter(change.adapter.as_deref());
+        let properties = network_service::get_properties(adapter, &change.keyword)?;
+
+        if properties.is_empty() {
+            // Nothing to set means nothing to confirm: report it rather than match vacuously
+            results.push(NetAdapterMismatch {
+                adapter: adapter.to_string(),
+                keyword: change.keyword.clone(),
+                expected_value: change.value.clone(),
+                actual_value: None,
+                description: format!(
+                    "No physical adapter matching '{}' has property '{}'",
+                    adapter, change.keyword
+                ),
+                is_match: false,
+                skip_validation: change.skip_validation,
+            });
+            continue;
+        }
+
+        for property in properties {
+            let is_match = property.value.as_deref() == Some(change.value.as_str());
+            results.push(NetAdapterMismatch {
+                description: format!(
+                    "Set {} = {} on adapter '{}'",
+                    change.keyword, change.value, property.name
+                ),
+                adapter: property.name,
+                keyword: change.keyword.clone(),
+                expected_value: change.value.clone(),
+                actual_value: property.value,
+                is_match,
+                skip_validation: change.skip_validation,
+            });
+        }
+    }
+
+    Ok(results)
+}
+
+fn compare_policies(option: &TweakOption) -> Result<Vec<PolicyMismatch>, Error> {
+    let mut results = Vec::new();
+
+    // Browser policies are compared as the policy values they write
+    for change in &option.all_policy_changes() {
+        let hive = change.scope.hive();
+
+        let (expected_value, actual_value, value_type, value_match) = match change.action {
+            PolicyAction::Set => {
+                let (value_type, expected_val) = match (&change.value_type, &change.value) {
+                    (Some(vt), Some(v)) => (vt, v),
+                    _ => continue, // Invalid config: nothing to compare.
+                };
+                let (current_val, existed) =
+                    read_registry_value(&hive, &change.key, &change.value_name, value_type)?;
+                let value_match = existed
+                    && registry_value::registry_values_match(
+                        value_type,
+                        &current_val,
+                        &Some(expected_val.clone()),
+                    )
+                    .unwrap_or(false);
+                (
+                    Some(expected_val.clone()),
+                    if existed { current_val } else { None },
+                    Some(value_type.as_str().to_string()),
+                    value_match,
+                )
+            }
+            PolicyAction::DeleteValue => {
+                let exists = registry_service::value_exists(&hive, &change.key, &change.value_name)
+                    .unwrap_or(false);
+                (
+                    None,
+                    exists.then(|| serde_json::json!("Exists")),
+                    None,
+                    !exists,
+                )
+            }
+        };
+
+        let pol_match = if change.write_pol {
+            let expected = policy_service::entry_for_change(change)?;
+            Some(
+                policy_service::get_entry(change.scope, &change.key, &change.value_name)?
+                    == Some(expected),
+            )
+        } else {
+            None
+        };
+
+        let description = match change.action {
+            PolicyAction::Set => format!(
+                "Set {} policy {} to {:?}",
+                change.scope.as_str(),
+                change.value_name,
+                expected_value.as_ref().unwrap_or(&serde_json::Value::Null)
+            ),
+            PolicyAction::DeleteValue => format!(
+                "Delete {} policy {}",
+                change.scope.as_str(),
+                change.value_name
+            ),
+        };
+
+        results.push(PolicyMismatch {
+            scope: change.scope.as_str().to_string(),
+            key: change.key.clone(),
+            value_name: change.value_name.clone(),
+            action: change.action.as_str().to_string(),
+            expected_value,
+            actual_value,
+            value_type,
+            pol_match,
+            description,
+            is_match: value_match && pol_match.unwrap_or(true),
+            skip_validation: change.skip_validation,
+        });
+    }
+
+    Ok(results)
+}
+
+fn compare_apps(option: &TweakOption) -> Result<Vec<AppMismatch>, Error> {
+    let mut results = Vec::new();
+
+    for change in &option.app_changes {
+        let expected_installed = change.action == AppAction::Install;
+        let actual_installed = package_service::is_installed(&change.id)?;
+        results.push(AppMismatch {
+            id: change.id.clone(),
+            action: change.action.as_str().to_string(),
+            expected_installed,
+            actual_installed,
+            description: match change.action {
+                AppAction::Install => format!("Install {}", change.id),
+                AppAction::Uninstall => format!("Uninstall {}", change.id),
+            },
+            is_match: actual_installed == expected_installed,
+            skip_validation: change.skip_validation,
+        });
+    }
+
+    Ok(results)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -428,6 +1084,15 @@ mod tests {
             scheduler: vec![],
             hosts: vec![],
             firewall: vec![],
+            power_plan: vec![],
+            bcdedit: vec![],
+            appx: vec![],
+            file: vec![],
+            registry_acl: vec![],
+            feature: vec![],
+            netadapter: vec![],
+            policy: vec![],
+            app: vec![],
             inferred: false,
         }
     }
@@ -455,4 +1120,10 @@ mod tests {
         assert!(!comparison(vec![]).all_match());
         assert!(!comparison(vec![reg(false, true)]).all_match());
     }
+
+    #[test]
+    fn match_counts_leave_out_skip_validation_items() {
+        let counts = comparison(vec![reg(true, false), reg(false, false), reg(false, true)]);
+        assert_eq!(counts.match_counts(), (1, 1));
+    }
 }
@@ -6,15 +6,20 @@
 //! - Migration utilities for old backup formats
 
 use crate::error::Error;
-use crate::models::{RegistryValueType, TweakDefinition, TweakSnapshot, TweakState};
+use crate::models::{AppxScope, RegistryValueType, TweakDefinition, TweakSnapshot, TweakState};
 use crate::services::{
-    firewall_service, hosts_service, registry_value, scheduler_service, service_control,
+    appx_service, bcdedit_service, dism_service, file_service, firewall_service, hosts_service,
+    network_service, package_service, policy_service, power_service, registry_acl_service,
+    registry_service, registry_value, scheduler_service, service_control, system_info_service,
 };
 use rayon::prelude::*;
 
 use super::capture::read_registry_value;
-use super::helpers::{parse_hive, parse_value_type, task_state_matches};
-use super::storage::{delete_snapshot, get_applied_tweaks, load_snapshot, snapshot_exists};
+use super::helpers::{parse_hive, parse_policy_scope, parse_value_type, task_state_matches};
+use super::storage::{
+    delete_snapshot, get_applied_tweaks, get_snapshots_dir, has_incomplete_apply, load_snapshot,
+    snapshot_exists,
+};
 
 // ============================================================================
 // State Detection
@@ -33,8 +38,13 @@ pub fn detect_tweak_state(
         None
     };
 
-    // Try to match current state against each option (shared comparison core).
+    // Try to match current state against each option (shared comparison core). Options whose
+    // conditions don't hold on this machine can't be applied, so they are never reported as current.
+    let facts = system_info_service::system_facts();
     for (index, option) in tweak.options.iter().enumerate() {
+        if !option.is_available(facts) {
+            continue;
+        }
         let comparison = super::compare::compare_option(option, windows_version)?;
         if comparison.all_match() {
             return Ok(TweakState {
@@ -72,6 +82,14 @@ pub fn validate_all_snapshots() -> Result<u32, Error> {
     let stale_tweaks: Vec<String> = applied_tweaks
         .par_iter()
         .filter_map(|tweak_id| {
+            // Cut short mid-apply: the snapshot is what a rollback needs, whatever the state says
+            if has_incomplete_apply(tweak_id) {
+                log::info!(
+                    "Keeping snapshot for '{}': its apply never completed",
+                    tweak_id
+                );
+                return None;
+            }
             match load_snapshot(tweak_id) {
                 Ok(Some(snapshot)) => {
                     // A snapshot is stale if current state matches the original snapshot state
@@ -125,7 +143,16 @@ fn snapshot_matches_current_state(snapshot: &TweakSnapshot) -> Result<bool, Erro
         || !snapshot.service_snapshots.is_empty()
         || !snapshot.scheduler_snapshots.is_empty()
         || !snapshot.hosts_snapshots.is_empty()
-        || !snapshot.firewall_snapshots.is_empty();
+        || !snapshot.firewall_snapshots.is_empty()
+        || !snapshot.power_plan_snapshots.is_empty()
+        || !snapshot.bcdedit_snapshots.is_empty()
+        || !snapshot.appx_snapshots.is_empty()
+        || !snapshot.file_snapshots.is_empty()
+        || !snapshot.registry_acl_snapshots.is_empty()
+        || !snapshot.feature_snapshots.is_empty()
+        || !snapshot.netadapter_snapshots.is_empty()
+        || !snapshot.policy_snapshots.is_empty()
+        || !snapshot.app_snapshots.is_empty();
 
     if !has_any_snapshot {
         return Ok(false);
@@ -135,7 +162,16 @@ fn snapshot_matches_current_state(snapshot: &TweakSnapshot) -> Result<bool, Erro
         && service_snapshots_match(snapshot)?
         && scheduler_snapshots_match(snapshot)?
         && hosts_snapshots_match(snapshot)?
-        && firewall_snapshots_match(snapshot)?)
+        && firewall_snapshots_match(snapshot)?
+        && power_plan_snapshots_match(snapshot)?
+        && bcdedit_snapshots_match(snapshot)?
+        && appx_snapshots_match(snapshot)?
+        && file_snapshots_match(snapshot)?
+        && registry_acl_snapshots_match(snapshot)?
+        && feature_snapshots_match(snapshot)?
+        && netadapter_snapshots_match(snapshot)?
+        && policy_snapshots_match(snapshot)?
+        && app_snapshots_match(snapshot)?)
 }
 
 fn registry_snapshots_match(snapshot: &TweakSnapshot) -> Result<bool, Error> {
@@ -144,12 +180,13 @@ fn registry_snapshots_match(snapshot: &TweakSnapshot) -> Result<bool, Error> {
         .par_iter()
         .map(|reg| {
             let hive = parse_hive(&reg.hive)?;
-            let value_type = reg
-                .value_type
-                .as_ref()
-                .map(|t| parse_value_type(t))
-                .transpose()?
-                .unwrap_or(RegistryValueType::Dword);
+            // A snapshot of an absent value has no type; read whatever is there now as its own
+            // type (a REG_MULTI_SZ read as DWORD is an error, not a mismatch).
+            let value_type = match reg.value_type.as_deref() {
+                Some(t) => parse_value_type(t)?,
+                None => registry_service::detect_value_type(&hive, &reg.key, &reg.value_name)?
+                    .unwrap_or(RegistryValueType::Dword),
+            };
 
             let (current_value, current_exists) =
                 read_registry_value(&hive, &reg.key, &reg.value_name, &value_type)?;
@@ -227,6 +264,156 @@ fn firewall_snapshots_match(snapshot: &TweakSnapshot) -> Result<bool, Error> {
     Ok(true)
 }
 
+fn power_plan_snapshots_match(snapshot: &TweakSnapshot) -> Result<bool, Error> {
+    if snapshot.power_plan_snapshots.is_empty() {
+        return Ok(true);
+    }
+
+    let active_scheme = power_service::get_active_scheme()?;
+    for power_plan in &snapshot.power_plan_snapshots {
+        let matches = match (&power_plan.subgroup, &power_plan.setting) {
+            (Some(subgroup), Some(setting)) => {
+                let current = power_service::read_setting(&power_plan.scheme, subgroup, setting)?;
+                power_plan.ac_value.is_none_or(|ac| ac == current.ac)
+                    && power_plan.dc_value.is_none_or(|dc| dc == current.dc)
+            }
+            _ => active_scheme == power_plan.scheme,
+        };
+
+        if !matches {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+fn bcdedit_snapshots_match(snapshot: &TweakSnapshot) -> Result<bool, Error> {
+    for bcd in &snapshot.bcdedit_snapshots {
+        let current = bcdedit_service::get_value(&bcd.identifier, &bcd.element)?;
+        let matches = match (&bcd.value, &current) {
+            (Some(expected), Some(actual)) => bcdedit_service::values_equal(expected, actual),
+            (None, None) => true,
+            _ => false,
+        };
+
+        if !matches {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+fn appx_snapshots_match(snapshot: &TweakSnapshot) -> Result<bool, Error> {
+    for appx in &snapshot.appx_snapshots {
+        let scope = match appx.scope.as_str() {
+            "all_users" => AppxScope::AllUsers,
+            _ => AppxScope::CurrentUser,
+        };
+        let installed = !appx_service::list_installed(&appx.package, scope)?.is_empty();
+        if installed != !appx.installed.is_empty() {
+            return Ok(false);
+        }
+
+        if let Some(provisioned) = &appx.provisioned {
+            let currently_provisioned = !appx_service::list_provisioned(&appx.package)?.is_empty();
+            if currently_provisioned != !provisioned.is_empty() {
+                return Ok(false);
+            }
+        }
+    }
+
+    Ok(true)
+}
+
+fn file_snapshots_match(snapshot: &TweakSnapshot) -> Result<bool, Error> {
+    for file in &snapshot.file_snapshots {
+        if let Some(moved_to) = &file.moved_to {
+            // A rename is undone when the original name is back and the new one is gone
+            if !file_service::path_exists(&file.path) || file_service::path_exists(moved_to) {
+                return Ok(false);
+            }
+            continue;
+        }
+
+        if file_service::path_exists(&file.path) != file.existed {
+            return Ok(false);
+        }
+
+        if let Some(backup_rel) = file.backup_path.as_ref().filter(|_| !file.is_dir) {
+            let backup = get_snapshots_dir()?.join(backup_rel);
+            if !file_service::files_equal(&file.path, &backup.to_string_lossy())? {
+                return Ok(false);
+            }
+        }
+
+        if let Some(attributes) = file.attributes {
+            if file_service::get_attributes(&file.path)? != attributes {
+                return Ok(false);
+            }
+        }
+    }
+
+    Ok(true)
+}
+
+fn registry_acl_snapshots_match(snapshot: &TweakSnapshot) -> Result<bool, Error> {
+    for acl in &snapshot.registry_acl_snapshots {
+        let hive = parse_hive(&acl.hive)?;
+        if registry_acl_service::get_sddl(hive, &acl.key)? != acl.sddl {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+fn feature_snapshots_match(snapshot: &TweakSnapshot) -> Result<bool, Error> {
+    for feature in &snapshot.feature_snapshots {
+        if dism_service::is_enabled(&feature.feature)? != feature.enabled {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+fn netadapter_snapshots_match(snapshot: &TweakSnapshot) -> Result<bool, Error> {
+    for netadapter in &snapshot.netadapter_snapshots {
+        let current = network_service::get_properties(&netadapter.adapter, &netadapter.keyword)?;
+        let matches = current
+            .iter()
+            .any(|p| p.name == netadapter.adapter && p.value == netadapter.value);
+        if !matches {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+fn policy_snapshots_match(snapshot: &TweakSnapshot) -> Result<bool, Error> {
+    for policy in snapshot.policy_snapshots.iter().filter(|p| p.pol_captured) {
+        let scope = parse_policy_scope(&policy.scope)?;
+        if policy_service::get_entry(scope, &policy.key, &policy.value_name)? != policy.pol_entry {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+fn app_snapshots_match(snapshot: &TweakSnapshot) -> Result<bool, Error> {
+    for app in &snapshot.app_snapshots {
+        if package_service::is_installed(&app.id)? != app.installed {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
 fn all_match(results: Vec<Result<bool, Error>>) -> Result<bool, Error> {
     for result in results {
         if !result? {
@@ -249,6 +436,7 @@ mod tests {
             name: "__mgx_missing_service_for_stale_snapshot_test__".to_string(),
             startup_type: "manual".to_string(),
             was_running: false,
+            elevation: None,
         });
 
         let matches = snapshot_matches_current_state(&snapshot).unwrap();
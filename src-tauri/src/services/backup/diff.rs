@@ -0,0 +1,340 @@
+//! Snapshot vs live state, item by item.
+//!
+//! Reverting writes the snapshot's original state back over whatever is there now. When something
+//! other than the tweak changed an item since it was applied (the user, another tool, Windows),
+//! that change is lost, so the UI shows this diff first. Each registry value or key, service, and
+//! scheduled task in the snapshot is classified against its original state and against what the
+//! applied option set it to.
+
+use crate::error::Error;
+use crate::models::{
+    PolicyChange, RegistryAction, RegistryChange, RegistrySnapshot, RegistryValueType,
+    SchedulerAction, SchedulerSnapshot, ServiceSnapshot, TweakOption,
+};
+use crate::services::{
+    registry_service, registry_value, scheduler_service, service_control, tweak_loader,
+};
+use rayon::prelude::*;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+use super::capture::read_registry_value;
+use super::helpers::{parse_hive, parse_value_type, task_state_matches};
+use super::storage::load_snapshot;
+
+/// How an item's current state relates to the snapshot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffStatus {
+    /// What the tweak set; reverting restores the original as expected
+    Matching,
+    /// Already back to the original; reverting changes nothing here
+    Original,
+    /// Neither the original nor what the tweak set (or, where the tweak's target isn't known,
+    /// such as a state probe, not the original); reverting overwrites it
+    ChangedExternally,
+    /// The tweak left it in place but it's gone now
+    Missing,
+}
+
+/// One item of the diff
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffEntry {
+    /// `HIVE\key\value`, `HIVE\key`, a service name, or `\path\task`
+    pub item: String,
+    /// State in the snapshot (None: absent)
+    pub original: Option<Value>,
+    /// State now (None: absent)
+    pub current: Option<Value>,
+    pub status: DiffStatus,
+}
+
+/// Result of `diff_snapshot`
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotDiff {
+    pub tweak_id: String,
+    /// Label of the option the snapshot was taken for
+    pub option_label: String,
+    pub registry: Vec<DiffEntry>,
+    pub services: Vec<DiffEntry>,
+    pub tasks: Vec<DiffEntry>,
+    /// Number of entries a revert would overwrite or can't find
+    pub changed_externally: usize,
+    pub missing: usize,
+}
+
+/// Compare a tweak's snapshot with the current registry, service, and scheduled task state
+pub fn diff_snapshot(tweak_id: &str) -> Result<SnapshotDiff, Error> {
+    let snapshot = load_snapshot(tweak_id)?
+        .ok_or_else(|| Error::NotFound(format!("No snapshot for tweak '{}'", tweak_id)))?;
+    // Without the definition (e.g. a tweak removed from this version) nothing has a known target
+    let tweak = tweak_loader::get_tweak(tweak_id)?;
+    let option = tweak
+        .as_ref()
+        .and_then(|tweak| tweak.options.get(snapshot.applied_option_index));
+
+    let registry_targets = match option {
+        Some(option) => registry_targets(option)?,
+        None => HashMap::new(),
+    };
+    let registry = snapshot
+        .registry_snapshots
+        .par_iter()
+        .map(|reg| diff_registry(reg, registry_targets.get(&registry_id(reg))))
+        .collect::<Result<Vec<_>, Error>>()?;
+    let services = snapshot
+        .service_snapshots
+        .iter()
+        .map(|service| diff_service(service, option))
+        .collect::<Result<Vec<_>, Error>>()?;
+    let tasks = snapshot
+        .scheduler_snapshots
+        .iter()
+        .map(|task| diff_task(task, option))
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let count = |status| {
+        registry
+            .iter()
+            .chain(&services)
+            .chain(&tasks)
+            .filter(|entry| entry.status == status)
+            .count()
+    };
+    Ok(SnapshotDiff {
+        tweak_id: snapshot.tweak_id.clone(),
+        option_label: snapshot.applied_option_label.clone(),
+        changed_externally: count(DiffStatus::ChangedExternally),
+        missing: count(DiffStatus::Missing),
+        registry,
+        services,
+        tasks,
+    })
+}
+
+/// Classify an item: `is_applied` is None when the tweak's target for it isn't known, and `gone`
+/// means the tweak left it in place but it no longer exists
+fn classify(is_original: bool, is_applied: Option<bool>, gone: bool) -> DiffStatus {
+    if is_original {
+        DiffStatus::Original
+    } else if is_applied == Some(true) {
+        DiffStatus::Matching
+    } else if gone {
+        DiffStatus::Missing
+    } else {
+        DiffStatus::ChangedExternally
+    }
+}
+
+fn registry_id_of(hive: &str, key: &str, value_name: Option<&str>) -> String {
+    match value_name {
+        Some(value_name) => format!("{}\\{}\\{}", hive, key, value_name),
+        None => format!("{}\\{}\\[key]", hive, key),
+    }
+    .to_lowercase()
+}
+
+fn registry_id(reg: &RegistrySnapshot) -> String {
+    registry_id_of(
+        &reg.hive,
+        &reg.key,
+        (!reg.key_level).then_some(reg.value_name.as_str()),
+    )
+}
+
+/// The applied option's registry changes (including policy values), by what they target
+fn registry_targets(option: &TweakOption) -> Result<HashMap<String, RegistryChange>, Error> {
+    let changes = registry_service::option_registry_changes(option)?
+        .into_iter()
+        .chain(
+            option
+                .all_policy_changes()
+                .iter()
+                .map(PolicyChange::to_registry_change),
+        );
+    Ok(changes
+        .map(|change| {
+            let key_level = matches!(
+                change.action,
+                RegistryAction::DeleteKey | RegistryAction::CreateKey
+            );
+            let id = registry_id_of(
+                change.hive.as_str(),
+                &change.key,
+                (!key_level).then_some(change.value_name.as_str()),
+            );
+            (id, change)
+        })
+        .collect())
+}
+
+fn exists_marker(exists: bool) -> Option<Value> {
+    exists.then(|| Value::from("Exists"))
+}
+
+fn diff_registry(
+    reg: &RegistrySnapshot,
+    target: Option<&RegistryChange>,
+) -> Result<DiffEntry, Error> {
+    let hive = parse_hive(&reg.hive)?;
+
+    if reg.key_level {
+        let exists = registry_service::key_exists(&hive, &reg.key)?;
+        let status = classify(
+            exists == reg.existed,
+            target.map(|change| exists == (change.action == RegistryAction::CreateKey)),
+            !exists && target.is_some_and(|change| change.action == RegistryAction::CreateKey),
+        );
+        return Ok(DiffEntry {
+            item: format!("{}\\{}", reg.hive, reg.key),
+            original: exists_marker(reg.existed),
+            current: exists_marker(exists),
+            status,
+        });
+    }
+
+    // Same type handling as detection: an absent original has no type, so read what's there now
+    // as its own type
+    let value_type = match reg.value_type.as_deref() {
+        Some(t) => parse_value_type(t)?,
+        None => registry_service::detect_value_type(&hive, &reg.key, &reg.value_name)?
+            .unwrap_or(RegistryValueType::Dword),
+    };
+    let (current, exists) = read_registry_value(&hive, &reg.key, &reg.value_name, &value_type)?;
+    let current = if exists { current } else { None };
+
+    let is_original = match (reg.existed, exists) {
+        (false, false) => true,
+        (true, true) => registry_value::registry_values_match(&value_type, &current, &reg.value)?,
+        _ => false,
+    };
+    let is_applied = target.map(|change| match (change.action, &change.value) {
+        (RegistryAction::Set, Some(expected)) => {
+            exists
+                && registry_value::registry_values_match(
+                    change.value_type.as_ref().unwrap_or(&value_type),
+                    &current,
+                    &Some(expected.clone()),
+                )
+                .unwrap_or(false)
+        }
+        _ => !exists,
+    });
+    let gone = !exists && target.is_some_and(|change| change.action == RegistryAction::Set);
+
+    Ok(DiffEntry {
+        item: format!("{}\\{}\\{}", reg.hive, reg.key, reg.value_name),
+        original: if reg.existed { reg.value.clone() } else { None },
+        current,
+        status: classify(is_original, is_applied, gone),
+    })
+}
+
+fn diff_service(
+    service: &ServiceSnapshot,
+    option: Option<&TweakOption>,
+) -> Result<DiffEntry, Error> {
+    let status = service_control::get_service_status(&service.name)?;
+    let current = status.startup_type.map(|startup| startup.as_str());
+    let target = option.and_then(|option| {
+        option
+            .all_service_changes()
+            .into_iter()
+            .find(|change| change.name.eq_ignore_ascii_case(&service.name))
+            .map(|change| change.startup)
+    });
+
+    Ok(DiffEntry {
+        item: service.name.clone(),
+        original: Some(Value::from(service.startup_type.clone())),
+        current: current.map(Value::from),
+        status: classify(
+            status.exists && current == Some(service.startup_type.as_str()),
+            target.map(|startup| status.startup_type == Some(startup)),
+            !status.exists,
+        ),
+    })
+}
+
+fn diff_task(task: &SchedulerSnapshot, option: Option<&TweakOption>) -> Result<DiffEntry, Error> {
+    use scheduler_service::TaskState;
+
+    let current = scheduler_service::get_task_state(&task.task_path, &task.task_name)?;
+    // `from_str` only knows the states a task can be in
+    let original = if task.original_state.eq_ignore_ascii_case("NotFound") {
+        TaskState::NotFound
+    } else {
+        TaskState::from_str(&task.original_state)
+    };
+    let target = option.and_then(|option| {
+        option
+            .scheduler_changes
+            .iter()
+            .find(|change| {
+                change.task_path.eq_ignore_ascii_case(&task.task_path)
+                    && match (&change.task_name, &change.task_name_pattern) {
+                        (Some(name), _) => name.eq_ignore_ascii_case(&task.task_name),
+                        (None, Some(pattern)) => regex::Regex::new(pattern)
+                            .is_ok_and(|pattern| pattern.is_match(&task.task_name)),
+                        (None, None) => false,
+                    }
+            })
+            .map(|change| change.action)
+    });
+    let expected = |action| match action {
+        SchedulerAction::Enable => TaskState::Ready,
+        SchedulerAction::Disable => TaskState::Disabled,
+        SchedulerAction::Delete => TaskState::NotFound,
+    };
+
+    Ok(DiffEntry {
+        item: format!(
+            "{}\\{}",
+            task.task_path.trim_end_matches('\\'),
+            task.task_name
+        ),
+        original: Some(Value::from(task.original_state.clone())),
+        current: Some(Value::from(current.as_str())),
+        status: classify(
+            task_state_matches(&current, &original),
+            target.map(|action| task_state_matches(&current, &expected(action))),
+            current == TaskState::NotFound
+                && target.is_some_and(|action| action != SchedulerAction::Delete),
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_original_state_wins_over_the_applied_one() {
+        // A tweak that set an item to what it already was
+        assert_eq!(classify(true, Some(true), false), DiffStatus::Original);
+        assert_eq!(classify(false, Some(true), false), DiffStatus::Matching);
+    }
+
+    #[test]
+    fn anything_else_is_an_external_change_or_missing() {
+        assert_eq!(
+            classify(false, Some(false), false),
+            DiffStatus::ChangedExternally
+        );
+        assert_eq!(classify(false, None, false), DiffStatus::ChangedExternally);
+        assert_eq!(classify(false, Some(false), true), DiffStatus::Missing);
+    }
+
+    #[test]
+    fn registry_ids_of_values_and_keys_do_not_collide() {
+        assert_ne!(
+            registry_id_of("HKCU", "Software\\Test", Some("")),
+            registry_id_of("HKCU", "Software\\Test", None)
+        );
+        assert_eq!(
+            registry_id_of("HKCU", "Software\\Test", Some("Value")),
+            registry_id_of("HKCU", "SOFTWARE\\TEST", Some("value"))
+        );
+    }
+}
@@ -0,0 +1,352 @@
+//! Optional encryption of snapshot files.
+//!
+//! Snapshots record registry data, user-specific paths, and SIDs. With encryption turned on (saved
+//! in `storage.json`), snapshot, history, and baseline files are written as AES-256-GCM ciphertext
+//! instead of JSON. The key is random, created the first time encryption is turned on, and kept in
+//! `%ProgramData%\MagicX Toolbox\snapshot.key` wrapped by DPAPI with machine scope: every account on
+//! this machine could unwrap it (the app runs elevated under whichever user started it), a copy taken
+//! to another machine can't. So the key file itself is readable by Administrators and SYSTEM only
+//! (`protected_dir::PRIVATE_FILE_DACL`), unlike the rest of the folder, which Users may read; a key
+//! file made before that is restricted the next time it's loaded elevated. It's written through a
+//! temp file, so a failed write can't leave a torn key behind.
+//!
+//! An encrypted file starts with a marker, so reads handle encrypted and plain files alike and the
+//! setting only decides how files are written. Turning it on or off rewrites the existing files.
+//! Archives hold the plain JSON, since the key doesn't leave the machine.
+
+use crate::error::Error;
+use crate::services::{protected_dir, system_info_service};
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use std::borrow::Cow;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use windows_sys::Win32::Foundation::LocalFree;
+use windows_sys::Win32::Security::Cryptography::{
+    CryptProtectData, CryptUnprotectData, CRYPTPROTECT_LOCAL_MACHINE, CRYPTPROTECT_UI_FORBIDDEN,
+    CRYPT_INTEGER_BLOB,
+};
+
+use super::location::{self, StorageSettings};
+use super::storage::get_snapshots_dir;
+
+/// Start of an encrypted file; the last byte is the format version
+const MAGIC: &[u8] = b"MGXENC\x01";
+
+const NONCE_LEN: usize = 12;
+
+/// File (in the ProgramData app folder) holding the DPAPI-wrapped key
+const KEY_FILE: &str = "snapshot.key";
+
+/// Extra DPAPI entropy, so the key blob only unwraps for this app's calls
+const DPAPI_ENTROPY: &[u8] = b"MagicX Toolbox snapshot key";
+
+/// Unwrapped key, loaded on first use. A mutex rather than a lock-free cache so two threads can't
+/// both create a key.
+static KEY_CACHE: Mutex<Option<Key<Aes256Gcm>>> = Mutex::new(None);
+
+/// Whether new snapshot files are written encrypted
+pub fn get_snapshot_encryption() -> bool {
+    location::read_storage_settings().encrypt_snapshots
+}
+
+/// Turn encryption on or off and rewrite the existing snapshot, history, and baseline files to
+/// match. Returns the number of files rewritten; files that can't be read are left as they are.
+pub fn set_snapshot_encryption(enabled: bool) -> Result<usize, Error> {
+    if enabled {
+        // Fail before anything is switched if the key can't be created
+        key(true)?;
+    }
+    location::write_storage_settings(&StorageSettings {
+        encrypt_snapshots: enabled,
+        ..location::read_storage_settings()
+    })?;
+
+    let dir = get_snapshots_dir()?;
+    let mut rewritten = 0;
+    for path in snapshot_files(&dir)? {
+        match rewrite(&path, enabled) {
+            Ok(true) => rewritten += 1,
+            Ok(false) => {}
+            Err(e) => log::warn!("Failed to rewrite {}: {}", path.display(), e),
+        }
+    }
+
+    log::info!(
+        "Snapshot encryption turned {}; {} files rewritten",
+        if enabled { "on" } else { "off" },
+        rewritten
+    );
+    Ok(rewritten)
+}
+
+/// Encrypt a file's contents if encryption is on; otherwise return them as they are
+pub(super) fn seal(plain: Vec<u8>) -> Result<Vec<u8>, Error> {
+    if !get_snapshot_encryption() {
+        return Ok(plain);
+    }
+    encrypt_with(&key(true)?, &plain)
+}
+
+/// The plain contents of a file, decrypting it if it's encrypted. The error is the reason it
+/// couldn't be decrypted.
+pub(super) fn open(content: &[u8]) -> Result<Cow<'_, [u8]>, String> {
+    if !content.starts_with(MAGIC) {
+        return Ok(Cow::Borrowed(content));
+    }
+    let key = key(false).map_err(|e| e.to_string())?;
+    decrypt_with(&key, content).map(Cow::Owned)
+}
+
+fn encrypt_with(key: &Key<Aes256Gcm>, plain: &[u8]) -> Result<Vec<u8>, Error> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = Aes256Gcm::new(key)
+        .encrypt(&nonce, plain)
+        .map_err(|_| Error::BackupFailed("Failed to encrypt snapshot".to_string()))?;
+
+    let mut sealed = Vec::with_capacity(MAGIC.len() + NONCE_LEN + ciphertext.len());
+    sealed.extend_from_slice(MAGIC);
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+fn decrypt_with(key: &Key<Aes256Gcm>, sealed: &[u8]) -> Result<Vec<u8>, String> {
+    let body = &sealed[MAGIC.len()..];
+    if body.len() < NONCE_LEN {
+        return Err("truncated".to_string());
+    }
+    let (nonce, ciphertext) = body.split_at(NONCE_LEN);
+    Aes256Gcm::new(key)
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "wrong key or modified ciphertext".to_string())
+}
+
+/// The snapshot key, loaded from the key file; with `create`, a new key is made (and saved) when
+/// there's no key file yet
+fn key(create: bool) -> Result<Key<Aes256Gcm>, Error> {
+    let mut cache = KEY_CACHE
+        .lock()
+        .map_err(|_| Error::BackupFailed("Snapshot key lock poisoned".to_string()))?;
+    if let Some(key) = *cache {
+        return Ok(key);
+    }
+
+    let path = location::program_data_file(KEY_FILE)?;
+    let key = if path.exists() {
+        let blob = fs::read(&path).map_err(|e| {
+            Error::BackupFailed(format!("Failed to read {}: {}", path.display(), e))
+        })?;
+        let bytes = dpapi_unprotect(&blob)?;
+        if bytes.len() != 32 {
+            return Err(Error::BackupFailed(format!(
+                "{} does not hold a valid key",
+                path.display()
+            )));
+        }
+        // A key file made before it was kept private
+        if system_info_service::is_running_as_admin() {
+            if let Err(e) = protected_dir::make_private(&path) {
+                log::warn!("{}", e);
+            }
+        }
+        *Key::<Aes256Gcm>::from_slice(&bytes)
+    } else if create {
+        let key = Aes256Gcm::generate_key(OsRng);
+        write_key_file(&path, &dpapi_protect(&key)?)?;
+        log::info!("Created snapshot encryption key at {}", path.display());
+        key
+    } else {
+        return Err(Error::BackupFailed(format!(
+            "The snapshot encryption key ({}) is missing",
+            path.display()
+        )));
+    };
+
+    *cache = Some(key);
+    Ok(key)
+}
+
+/// Write the wrapped key to a temp file readable by Administrators and SYSTEM only, then rename it
+/// to `path`
+fn write_key_file(path: &Path, wrapped: &[u8]) -> Result<(), Error> {
+    let dir = path
+        .parent()
+        .ok_or_else(|| Error::BackupFailed(format!("Invalid key path {}", path.display())))?;
+    fs::create_dir_all(dir)
+        .map_err(|e| Error::BackupFailed(format!("Failed to create {}: {}", dir.display(), e)))?;
+
+    let mut tmp = tempfile::NamedTempFile::new_in(dir)
+        .map_err(|e| Error::BackupFailed(format!("Failed to create temp key file: {}", e)))?;
+    protected_dir::make_private(tmp.path())?;
+    tmp.write_all(wrapped)
+        .and_then(|()| tmp.as_file().sync_all())
+        .map_err(|e| Error::BackupFailed(format!("Failed to write {}: {}", path.display(), e)))?;
+    tmp.persist(path)
+        .map_err(|e| Error::BackupFailed(format!("Failed to write {}: {}", path.display(), e)))?;
+    Ok(())
+}
+
+fn blob(data: &[u8]) -> CRYPT_INTEGER_BLOB {
+    CRYPT_INTEGER_BLOB {
+        cbData: data.len() as u32,
+        pbData: data.as_ptr() as *mut u8,
+    }
+}
+
+/// Copy a blob DPAPI allocated into a Vec and free it
+///
+/// # Safety
+/// `out` must have been filled by a successful `CryptProtectData`/`CryptUnprotectData` call.
+unsafe fn take_blob(out: CRYPT_INTEGER_BLOB) -> Vec<u8> {
+    let data = std::slice::from_raw_parts(out.pbData, out.cbData as usize).to_vec();
+    LocalFree(out.pbData.cast());
+    data
+}
+
+fn dpapi_protect(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let input = blob(data);
+    let entropy = blob(DPAPI_ENTROPY);
+    let mut out = blob(&[]);
+    // SAFETY: the input and entropy blobs point into slices that outlive the call, and DPAPI only
+    // reads them; `out` is freed by `take_blob` after a successful call.
+    let ok = unsafe {
+        CryptProtectData(
+            &input,
+            std::ptr::null(),
+            &entropy,
+            std::ptr::null(),
+            std::ptr::null(),
+            CRYPTPROTECT_LOCAL_MACHINE | CRYPTPROTECT_UI_FORBIDDEN,
+            &mut out,
+        )
+    };
+    if ok == 0 {
+        return Err(Error::BackupFailed(format!(
+            "Failed to protect the snapshot key: {}",
+            std::io::Error::last_os_error()
+        )));
+    }
+    // SAFETY: the call succeeded
+    Ok(unsafe { take_blob(out) })
+}
+
+fn dpapi_unprotect(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let input = blob(data);
+    let entropy = blob(DPAPI_ENTROPY);
+    let mut out = blob(&[]);
+    // SAFETY: as in `dpapi_protect`
+    let ok = unsafe {
+        CryptUnprotectData(
+            &input,
+            std::ptr::null_mut(),
+            &entropy,
+            std::ptr::null(),
+            std::ptr::null(),
+            CRYPTPROTECT_UI_FORBIDDEN,
+            &mut out,
+        )
+    };
+    if ok == 0 {
+        return Err(Error::BackupFailed(format!(
+            "Failed to unlock the snapshot key (was it made on another machine?): {}",
+            std::io::Error::last_os_error()
+        )));
+    }
+    // SAFETY: the call succeeded
+    Ok(unsafe { take_blob(out) })
+}
+
+/// Snapshot, history generation, and baseline files (quarantined files and file backups are left
+/// alone)
+fn snapshot_files(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let json_files_in = |dir: &Path| -> Vec<PathBuf> {
+        fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "json"))
+            .collect()
+    };
+
+    let mut files = json_files_in(dir);
+    let entries = fs::read_dir(dir)
+        .map_err(|e| Error::BackupFailed(format!("Failed to read snapshots: {}", e)))?;
+    for entry in entries.filter_map(Result::ok) {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.ends_with(".history") || name == "baseline" {
+            files.extend(json_files_in(&entry.path()));
+        }
+    }
+    Ok(files)
+}
+
+/// Rewrite one file encrypted or plain; false if it already was
+fn rewrite(path: &Path, encrypt: bool) -> Result<bool, Error> {
+    let content = fs::read(path)
+        .map_err(|e| Error::BackupFailed(format!("Failed to read {}: {}", path.display(), e)))?;
+    if content.starts_with(MAGIC) == encrypt {
+        return Ok(false);
+    }
+    let plain = open(&content).map_err(|reason| {
+        Error::BackupFailed(format!(
+            "{} can't be decrypted ({})",
+            path.display(),
+            reason
+        ))
+    })?;
+    let new_content = if encrypt {
+        encrypt_with(&key(true)?, &plain)?
+    } else {
+        plain.into_owned()
+    };
+
+    // Atomic, like every snapshot write
+    let dir = path
+        .parent()
+        .ok_or_else(|| Error::BackupFailed(format!("Invalid snapshot path {}", path.display())))?;
+    let mut tmp = tempfile::NamedTempFile::new_in(dir)
+        .map_err(|e| Error::BackupFailed(format!("Failed to create temp file: {}", e)))?;
+    tmp.write_all(&new_content)
+        .map_err(|e| Error::BackupFailed(format!("Failed to write {}: {}", path.display(), e)))?;
+    tmp.persist(path)
+        .map_err(|e| Error::BackupFailed(format!("Failed to replace {}: {}", path.display(), e)))?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_encrypted_file_decrypts_to_its_contents() {
+        let key = Aes256Gcm::generate_key(OsRng);
+        let sealed = encrypt_with(&key, b"{\"tweak_id\": \"t\"}").unwrap();
+        assert!(sealed.starts_with(MAGIC));
+        assert!(!sealed.windows(8).any(|w| w == b"tweak_id"));
+        assert_eq!(
+            decrypt_with(&key, &sealed).unwrap(),
+            b"{\"tweak_id\": \"t\"}"
+        );
+    }
+
+    #[test]
+    fn a_modified_or_foreign_file_does_not_decrypt() {
+        let key = Aes256Gcm::generate_key(OsRng);
+        let mut sealed = encrypt_with(&key, b"{}").unwrap();
+        assert!(decrypt_with(&Aes256Gcm::generate_key(OsRng), &sealed).is_err());
+
+        let last = sealed.len() - 1;
+        sealed[last] ^= 1;
+        assert!(decrypt_with(&key, &sealed).is_err());
+        assert!(decrypt_with(&key, MAGIC).is_err());
+    }
+
+    #[test]
+    fn a_plain_file_is_read_as_it_is() {
+        let content = b"{\"tweak_id\": \"t\"}";
+        assert!(matches!(open(content), Ok(Cow::Borrowed(c)) if c == content));
+    }
+}
@@ -0,0 +1,344 @@
+//! Export of a snapshot's registry values as a `.reg` file.
+//!
+//! The file describes the registry as it was before the tweak was applied, so importing it
+//! (double-click, or `reg import`) puts the values back without the app — on this machine, or on
+//! another one with the same tweak applied. Values and keys the tweak created become deletions
+//! (`"name"=-`, `[-key]`); keys it deleted are recreated with their captured contents. Only the
+//! registry portion of the snapshot (which includes policy values) can be expressed this way.
+
+use crate::error::Error;
+use crate::models::{RegistryHive, RegistryKeyContents, RegistrySnapshot, TweakSnapshot};
+use crate::services::registry_value::{parse_registry_value, RegistryValue};
+use std::fs;
+use std::path::Path;
+
+use super::helpers::{parse_hive, parse_value_type};
+use super::storage::load_snapshot;
+
+/// First line of every version 5 `.reg` file
+pub(super) const REG_FILE_HEADER: &str = "Windows Registry Editor Version 5.00";
+
+/// Write the registry portion of a tweak's snapshot to `path` as a `.reg` file
+pub fn export_snapshot_as_reg(tweak_id: &str, path: &Path) -> Result<(), Error> {
+    let snapshot = load_snapshot(tweak_id)?
+        .ok_or_else(|| Error::NotFound(format!("No snapshot for tweak '{}'", tweak_id)))?;
+    let text = snapshot_to_reg(&snapshot)?;
+    write_reg_file(path, &text)?;
+
+    log::info!(
+        "Exported {} registry snapshots of tweak '{}' to {}",
+        snapshot.registry_snapshots.len(),
+        tweak_id,
+        path.display()
+    );
+    Ok(())
+}
+
+/// Write `.reg` text to `path`
+pub(super) fn write_reg_file(path: &Path, text: &str) -> Result<(), Error> {
+    // Regedit reads version 5 files as UTF-16LE; a BOM-less UTF-8 file would garble non-ASCII
+    // paths and values
+    let bytes: Vec<u8> = [0xFF, 0xFE]
+        .into_iter()
+        .chain(text.encode_utf16().flat_map(u16::to_le_bytes))
+        .collect();
+    fs::write(path, bytes)
+        .map_err(|e| Error::BackupFailed(format!("Failed to write {}: {}", path.display(), e)))
+}
+
+/// Render the registry portion of a snapshot as `.reg` text (CRLF line endings)
+pub(crate) fn snapshot_to_reg(snapshot: &TweakSnapshot) -> Result<String, Error> {
+    let mut writer = RegWriter::default();
+    writer.line(REG_FILE_HEADER);
+    writer.line("");
+    writer.line(&format!(
+        "; Original state of \"{}\" ({}), captured {}",
+        snapshot.tweak_name, snapshot.tweak_id, snapshot.created_at
+    ));
+
+    for reg in &snapshot.registry_snapshots {
+        let root = key_path(&parse_hive(&reg.hive)?, &reg.key);
+        if reg.key_level {
+            write_key_snapshot(&mut writer, &root, reg)?;
+        } else if reg.existed {
+            let value = reg_value(reg.value_type.as_deref(), reg.value.as_ref())?;
+            writer.value(&root, &reg.value_name, &value);
+        } else {
+            writer.value(&root, &reg.value_name, "-");
+        }
+    }
+
+    writer.line("");
+    Ok(writer.text)
+}
+
+/// A key-level snapshot: a key the tweak created is deleted, a key it deleted is recreated
+fn write_key_snapshot(
+    writer: &mut RegWriter,
+    root: &str,
+    reg: &RegistrySnapshot,
+) -> Result<(), Error> {
+    if !reg.existed {
+        writer.delete_key(root);
+        return Ok(());
+    }
+    match &reg.key_contents {
+        Some(contents) => write_key_contents(writer, root, contents),
+        // The key existed and was kept (create_key), so there's nothing to put back
+        None => Ok(()),
+    }
+}
+
+fn write_key_contents(
+    writer: &mut RegWriter,
+    root: &str,
+    contents: &RegistryKeyContents,
+) -> Result<(), Error> {
+    writer.key(root);
+    for subkey in &contents.subkeys {
+        writer.key(&format!("{}\\{}", root, subkey));
+    }
+    for value in &contents.values {
+        let key = if value.subkey.is_empty() {
+            root.to_string()
+        } else {
+            format!("{}\\{}", root, value.subkey)
+        };
+        let data = reg_value(Some(&value.value_type), Some(&value.value))?;
+        writer.value(&key, &value.name, &data);
+    }
+    Ok(())
+}
+
+pub(super) fn key_path(hive: &RegistryHive, key: &str) -> String {
+    let root = match hive {
+        RegistryHive::Hkcu => "HKEY_CURRENT_USER",
+        RegistryHive::Hklm => "HKEY_LOCAL_MACHINE",
+        RegistryHive::Hkcr => "HKEY_CLASSES_ROOT",
+        RegistryHive::Hku | RegistryHive::AllUsers => "HKEY_USERS",
+    };
+    if key.is_empty() {
+        root.to_string()
+    } else {
+        format!("{}\\{}", root, key)
+    }
+}
+
+/// The data half of a `name=data` line
+fn reg_value(value_type: Option<&str>, value: Option<&serde_json::Value>) -> Result<String, Error> {
+    let (Some(value_type), Some(value)) = (value_type, value) else {
+        return Err(Error::BackupFailed(
+            "Snapshot of an existing value has no type or data".to_string(),
+        ));
+    };
+    let parsed = parse_registry_value(&parse_value_type(value_type)?, value)?;
+    Ok(reg_data(parsed))
+}
+
+/// A parsed value as the data half of a `name=data` line
+pub(super) fn reg_data(parsed: RegistryValue) -> String {
+    match parsed {
+        RegistryValue::Dword(n) => format!("dword:{:08x}", n),
+        RegistryValue::Qword(n) => format!("hex(b):{}", hex_bytes(&n.to_le_bytes())),
+        // A quoted string can't hold a line break, so those are written as raw REG_SZ data
+        RegistryValue::String(s) if s.contains(['\r', '\n']) => {
+            format!("hex(1):{}", hex_bytes(&utf16_bytes(&[s.as_str()], false)))
+        }
+        RegistryValue::String(s) => quoted(&s),
+        RegistryValue::ExpandString(s) => {
+            format!("hex(2):{}", hex_bytes(&utf16_bytes(&[s.as_str()], false)))
+        }
+        RegistryValue::MultiString(items) => {
+            let items: Vec<&str> = items.iter().map(String::as_str).collect();
+            format!("hex(7):{}", hex_bytes(&utf16_bytes(&items, true)))
+        }
+        RegistryValue::Binary(bytes) => format!("hex:{}", hex_bytes(&bytes)),
+    }
+}
+
+/// UTF-16LE bytes of each string with its terminating NUL, plus the list's final NUL if `multi`
+fn utf16_bytes(items: &[&str], multi: bool) -> Vec<u8> {
+    let mut units: Vec<u16> = Vec::new();
+    for item in items {
+        units.extend(item.encode_utf16());
+        units.push(0);
+    }
+    if multi {
+        units.push(0);
+    }
+    units.into_iter().flat_map(u16::to_le_bytes).collect()
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn quoted(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Accumulates `.reg` lines, opening a `[key]` section only when the key changes
+#[derive(Default)]
+pub(super) struct RegWriter {
+    pub(super) text: String,
+    current_key: Option<String>,
+}
+
+impl RegWriter {
+    pub(super) fn line(&mut self, line: &str) {
+        self.text.push_str(line);
+        self.text.push_str("\r\n");
+    }
+
+    pub(super) fn key(&mut self, key: &str) {
+        if self.current_key.as_deref() != Some(key) {
+            self.line("");
+            self.line(&format!("[{}]", key));
+            self.current_key = Some(key.to_string());
+        }
+    }
+
+    pub(super) fn delete_key(&mut self, key: &str) {
+        self.line("");
+        self.line(&format!("[-{}]", key));
+        self.current_key = None;
+    }
+
+    pub(super) fn value(&mut self, key: &str, name: &str, data: &str) {
+        self.key(key);
+        let name = if name.is_empty() {
+            "@".to_string()
+        } else {
+            quoted(name)
+        };
+        self.line(&format!("{}={}", name, data));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::RegistryKeyValue;
+
+    fn value_snapshot(
+        key: &str,
+        name: &str,
+        value_type: &str,
+        value: Option<serde_json::Value>,
+    ) -> RegistrySnapshot {
+        RegistrySnapshot {
+            hive: "HKCU".to_string(),
+            key: key.to_string(),
+            value_name: name.to_string(),
+            value_type: Some(value_type.to_string()),
+            existed: value.is_some(),
+            value,
+            key_level: false,
+            key_contents: None,
+            elevation: None,
+        }
+    }
+
+    #[test]
+    fn values_are_grouped_by_key_and_missing_ones_deleted() {
+        let mut snapshot = TweakSnapshot::new("test_tweak", "Test Tweak", 0, "On", 11, false, None);
+        snapshot.registry_snapshots = vec![
+            value_snapshot(
+                "Software\\Test",
+                "Count",
+                "REG_DWORD",
+                Some(serde_json::json!(26)),
+            ),
+            value_snapshot(
+                "Software\\Test",
+                "Path",
+                "REG_SZ",
+                Some(serde_json::json!("C:\\Users \"x\"")),
+            ),
+            value_snapshot("Software\\Test", "New", "REG_DWORD", None),
+            value_snapshot(
+                "Software\\Other",
+                "",
+                "REG_MULTI_SZ",
+                Some(serde_json::json!(["a", "b"])),
+            ),
+        ];
+
+        let text = snapshot_to_reg(&snapshot).unwrap();
+        let body: Vec<&str> = text.split("\r\n").skip(3).collect();
+        assert!(text.starts_with("Windows Registry Editor Version 5.00\r\n"));
+        assert_eq!(
+            body,
+            vec![
+                "",
+                "[HKEY_CURRENT_USER\\Software\\Test]",
+                "\"Count\"=dword:0000001a",
+                "\"Path\"=\"C:\\\\Users \\\"x\\\"\"",
+                "\"New\"=-",
+                "",
+                "[HKEY_CURRENT_USER\\Software\\Other]",
+                "@=hex(7):61,00,00,00,62,00,00,00,00,00",
+                "",
+                "",
+            ]
+        );
+    }
+
+    #[test]
+    fn created_keys_are_deleted_and_deleted_keys_recreated() {
+        let mut snapshot = TweakSnapshot::new("test_tweak", "Test Tweak", 0, "On", 11, false, None);
+        snapshot.registry_snapshots = vec![
+            RegistrySnapshot {
+                hive: "HKLM".to_string(),
+                key: "SOFTWARE\\Created".to_string(),
+                value_name: String::new(),
+                value_type: None,
+                value: None,
+                existed: false,
+                key_level: true,
+                key_contents: None,
+                elevation: None,
+            },
+            RegistrySnapshot {
+                hive: "HKCR".to_string(),
+                key: "Directory\\shell\\Deleted".to_string(),
+                value_name: String::new(),
+                value_type: None,
+                value: None,
+                existed: true,
+                key_level: true,
+                key_contents: Some(RegistryKeyContents {
+                    subkeys: vec!["command".to_string()],
+                    values: vec![RegistryKeyValue {
+                        subkey: "command".to_string(),
+                        name: String::new(),
+                        value_type: "REG_EXPAND_SZ".to_string(),
+                        value: serde_json::json!("%1"),
+                    }],
+                }),
+                elevation: None,
+            },
+        ];
+
+        let text = snapshot_to_reg(&snapshot).unwrap();
+        let body: Vec<&str> = text.split("\r\n").skip(3).collect();
+        assert_eq!(
+            body,
+            vec![
+                "",
+                "[-HKEY_LOCAL_MACHINE\\SOFTWARE\\Created]",
+                "",
+                "[HKEY_CLASSES_ROOT\\Directory\\shell\\Deleted]",
+                "",
+                "[HKEY_CLASSES_ROOT\\Directory\\shell\\Deleted\\command]",
+                "@=hex(2):25,00,31,00,00,00",
+                "",
+                "",
+            ]
+        );
+    }
+}
@@ -3,18 +3,33 @@
 //! Utility functions for parsing registry types and comparing scheduler task states.
 
 use crate::error::Error;
-use crate::models::{RegistryHive, RegistryValueType};
+use crate::models::{PolicyScope, RegistryHive, RegistryValueType};
 use crate::services::scheduler_service;
 
-/// Parse hive string to RegistryHive enum
+/// Parse hive string to RegistryHive enum. Snapshots only ever record concrete hives, since
+/// ALL_USERS changes are expanded to HKU before capture.
 pub fn parse_hive(hive: &str) -> Result<RegistryHive, Error> {
     match hive {
         "HKCU" => Ok(RegistryHive::Hkcu),
         "HKLM" => Ok(RegistryHive::Hklm),
+        "HKCR" => Ok(RegistryHive::Hkcr),
+        "HKU" => Ok(RegistryHive::Hku),
         _ => Err(Error::BackupFailed(format!("Unknown hive: {}", hive))),
     }
 }
 
+/// Parse policy scope string to PolicyScope enum
+pub fn parse_policy_scope(scope: &str) -> Result<PolicyScope, Error> {
+    match scope {
+        "machine" => Ok(PolicyScope::Machine),
+        "user" => Ok(PolicyScope::User),
+        _ => Err(Error::BackupFailed(format!(
+            "Unknown policy scope: {}",
+            scope
+        ))),
+    }
+}
+
 /// Parse value type string to RegistryValueType enum
 pub fn parse_value_type(value_type: &str) -> Result<RegistryValueType, Error> {
     match value_type {
@@ -54,9 +69,22 @@ mod tests {
     fn test_parse_hive() {
         assert!(matches!(parse_hive("HKCU"), Ok(RegistryHive::Hkcu)));
         assert!(matches!(parse_hive("HKLM"), Ok(RegistryHive::Hklm)));
+        assert!(matches!(parse_hive("HKCR"), Ok(RegistryHive::Hkcr)));
+        assert!(matches!(parse_hive("HKU"), Ok(RegistryHive::Hku)));
+        assert!(parse_hive("ALL_USERS").is_err());
         assert!(parse_hive("INVALID").is_err());
     }
 
+    #[test]
+    fn test_parse_policy_scope() {
+        assert!(matches!(
+            parse_policy_scope("machine"),
+            Ok(PolicyScope::Machine)
+        ));
+        assert!(matches!(parse_policy_scope("user"), Ok(PolicyScope::User)));
+        assert!(parse_policy_scope("HKLM").is_err());
+    }
+
     #[test]
     fn test_parse_value_type() {
         assert!(matches!(
@@ -0,0 +1,462 @@
+//! Earlier generations of a tweak's snapshot.
+//!
+//! The snapshot file itself always holds the original state (generation 0) and is never replaced
+//! while the tweak is applied. Each switch to another option first captures the state being left
+//! and, once the switch succeeds, keeps it as the next generation in `{tweak_id}.history/{n}.json`,
+//! so the user can step back to any option the tweak was at before, not only to the original.
+//! How many generations are kept, for how long, and how much disk all of them may take, is set by
+//! `HistoryRetention` (saved with the snapshot location in `storage.json`); generation 0 is never
+//! pruned. A generation's file backups live in their own capture folder under `{tweak_id}.files`,
+//! which is removed with it.
+
+use crate::error::Error;
+use crate::models::TweakSnapshot;
+use chrono::{DateTime, Duration, FixedOffset};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::integrity;
+use super::location::{read_storage_settings, write_storage_settings, StorageSettings};
+use super::storage::{
+    file_backups_dir_name, get_applied_tweaks, get_snapshots_dir, history_dir_name, load_snapshot,
+    snapshot_from_json, write_snapshot_file,
+};
+use super::usage::path_size;
+
+/// How much history is kept
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistoryRetention {
+    /// Generations kept besides the original; 0 turns history off
+    pub max_generations: u32,
+    /// Generations older than this many days are dropped; None keeps them regardless of age
+    pub max_age_days: Option<u32>,
+    /// Size (MB) the history of all tweaks together is kept under by dropping the oldest
+    /// generations first; None for no cap
+    pub max_total_mb: Option<u32>,
+}
+
+impl Default for HistoryRetention {
+    fn default() -> Self {
+        Self {
+            max_generations: 10,
+            max_age_days: None,
+            max_total_mb: None,
+        }
+    }
+}
+
+/// What a cleanup removed
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CleanupReport {
+    pub generations_removed: u32,
+    pub bytes_freed: u64,
+}
+
+/// A kept generation, with the disk space deleting it frees
+struct KeptGeneration {
+    tweak_id: String,
+    generation: u32,
+    created: Option<DateTime<FixedOffset>>,
+    snapshot: TweakSnapshot,
+    size: u64,
+}
+
+/// One entry of a tweak's history, for the UI
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotGeneration {
+    /// 0 for the original state, then 1, 2, ... in the order they were captured
+    pub generation: u32,
+    pub created_at: String,
+    /// Option the tweak was at in this state (for the original: None if it matched no option)
+    pub option_index: Option<usize>,
+    /// Label of that option (None for the original, which the UI labels itself)
+    pub option_label: Option<String>,
+}
+
+fn history_dir(tweak_id: &str) -> Result<PathBuf, Error> {
+    Ok(get_snapshots_dir()?.join(history_dir_name(tweak_id)))
+}
+
+/// Generation numbers in a history folder, oldest first
+fn generation_numbers(dir: &Path) -> Result<Vec<u32>, Error> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut numbers: Vec<u32> = fs::read_dir(dir)
+        .map_err(|e| Error::BackupFailed(format!("Failed to read snapshot history: {}", e)))?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .strip_suffix(".json")
+                .and_then(|n| n.parse().ok())
+        })
+        .collect();
+    numbers.sort_unstable();
+    Ok(numbers)
+}
+
+/// Read one generation file. A corrupt one is quarantined (like a corrupt snapshot) and read as
+/// missing.
+fn read_generation(tweak_id: &str, path: &Path) -> Result<Option<TweakSnapshot>, Error> {
+    let content = fs::read(path)
+        .map_err(|e| Error::BackupFailed(format!("Failed to read snapshot history: {}", e)))?;
+    match integrity::verify(&content) {
+        Ok(verified) => snapshot_from_json(verified.json).map(Some),
+        Err(reason) => {
+            integrity::quarantine(path, tweak_id, &reason)?;
+            Ok(None)
+        }
+    }
+}
+
+/// Keep `state` (captured just before the tweak left its current option) as the tweak's next
+/// generation, labelled with that option. Returns the new generation number, or None when history
+/// is turned off.
+pub fn record_generation(tweak_id: &str, mut state: TweakSnapshot) -> Result<Option<u32>, Error> {
+    let retention = get_history_retention();
+    if retention.max_generations == 0 {
+        remove_file_backups(tweak_id, &state);
+        return Ok(None);
+    }
+
+    let current = load_snapshot(tweak_id)?
+        .ok_or_else(|| Error::NotFound(format!("Snapshot for tweak '{}'", tweak_id)))?;
+    state.applied_option_index = current.applied_option_index;
+    state.applied_option_label = current.applied_option_label;
+    state.original_option_index = current.original_option_index;
+
+    let dir = history_dir(tweak_id)?;
+    fs::create_dir_all(&dir)
+        .map_err(|e| Error::BackupFailed(format!("Failed to create snapshot history: {}", e)))?;
+    let generation = generation_numbers(&dir)?.last().map_or(1, |last| last + 1);
+    write_snapshot_file(&dir.join(format!("{}.json", generation)), &state)?;
+    log::info!(
+        "Recorded generation {} of '{}' (option '{}')",
+        generation,
+        tweak_id,
+        state.applied_option_label
+    );
+
+    prune_history(tweak_id, &retention)?;
+    prune_to_size_cap(&retention)?;
+    Ok(Some(generation))
+}
+
+/// The original state followed by every kept generation, oldest first. Empty if the tweak has no
+/// snapshot.
+pub fn list_snapshot_generations(tweak_id: &str) -> Result<Vec<SnapshotGeneration>, Error> {
+    let Some(original) = load_snapshot(tweak_id)? else {
+        return Ok(Vec::new());
+    };
+    let mut generations = vec![SnapshotGeneration {
+        generation: 0,
+        created_at: original.created_at,
+        option_index: original.original_option_index,
+        option_label: None,
+    }];
+
+    let dir = history_dir(tweak_id)?;
+    for generation in generation_numbers(&dir)? {
+        if let Some(snapshot) =
+            read_generation(tweak_id, &dir.join(format!("{}.json", generation)))?
+        {
+            generations.push(SnapshotGeneration {
+                generation,
+                created_at: snapshot.created_at,
+                option_index: Some(snapshot.applied_option_index),
+                option_label: Some(snapshot.applied_option_label),
+            });
+        }
+    }
+    Ok(generations)
+}
+
+/// Load a kept generation (1 or later; generation 0 is the snapshot itself)
+pub fn load_generation(tweak_id: &str, generation: u32) -> Result<TweakSnapshot, Error> {
+    let path = history_dir(tweak_id)?.join(format!("{}.json", generation));
+    if generation == 0 || !path.exists() {
+        return Err(Error::NotFound(format!(
+            "Generation {} of tweak '{}'",
+            generation, tweak_id
+        )));
+    }
+    read_generation(tweak_id, &path)?.ok_or_else(|| {
+        Error::BackupFailed(format!(
+            "Generation {} of tweak '{}' was corrupt and has been moved to the corrupt folder",
+            generation, tweak_id
+        ))
+    })
+}
+
+/// The saved retention (the default if none is saved)
+pub fn get_history_retention() -> HistoryRetention {
+    read_storage_settings().history
+}
+
+/// Save a new retention and apply it to every tweak's history right away
+pub fn set_history_retention(retention: HistoryRetention) -> Result<HistoryRetention, Error> {
+    write_storage_settings(&StorageSettings {
+        history: retention.clone(),
+        ..read_storage_settings()
+    })?;
+    log::info!("Snapshot history retention set to {:?}", retention);
+    cleanup_old_backups()?;
+    Ok(retention)
+}
+
+/// Prune every tweak's history to the saved retention: by count and age per tweak, then the oldest
+/// generations of any tweak until the whole history fits the size cap
+pub fn cleanup_old_backups() -> Result<CleanupReport, Error> {
+    let retention = get_history_retention();
+    let mut report = CleanupReport::default();
+    for tweak_id in get_applied_tweaks()? {
+        match prune_history(&tweak_id, &retention) {
+            Ok(pruned) => report.add(pruned),
+            Err(e) => log::warn!("Failed to prune snapshot history of '{}': {}", tweak_id, e),
+        }
+    }
+    report.add(prune_to_size_cap(&retention)?);
+    if report.generations_removed > 0 {
+        log::info!(
+            "Cleaned up {} snapshot generations ({} bytes)",
+            report.generations_removed,
+            report.bytes_freed
+        );
+    }
+    Ok(report)
+}
+
+impl CleanupReport {
+    fn add(&mut self, other: CleanupReport) {
+        self.generations_removed += other.generations_removed;
+        self.bytes_freed += other.bytes_freed;
+    }
+}
+
+/// A tweak's kept generations, oldest first
+fn kept_generations(tweak_id: &str) -> Result<Vec<KeptGeneration>, Error> {
+    let dir = history_dir(tweak_id)?;
+    let mut generations = Vec::new();
+    for generation in generation_numbers(&dir)? {
+        let path = dir.join(format!("{}.json", generation));
+        if let Some(snapshot) = read_generation(tweak_id, &path)? {
+            let size = path_size(&path)
+                + capture_dirs(tweak_id, &snapshot)
+                    .iter()
+                    .map(|dir| path_size(dir))
+                    .sum::<u64>();
+            generations.push(KeptGeneration {
+                tweak_id: tweak_id.to_string(),
+                generation,
+                created: DateTime::parse_from_rfc3339(&snapshot.created_at).ok(),
+                snapshot,
+                size,
+            });
+        }
+    }
+    Ok(generations)
+}
+
+/// Delete a generation with its file backups
+fn delete_generation(kept: &KeptGeneration) -> Result<(), Error> {
+    let path = history_dir(&kept.tweak_id)?.join(format!("{}.json", kept.generation));
+    fs::remove_file(path).map_err(|e| {
+        Error::BackupFailed(format!("Failed to delete old snapshot generation: {}", e))
+    })?;
+    remove_file_backups(&kept.tweak_id, &kept.snapshot);
+    log::debug!(
+        "Pruned generation {} of '{}'",
+        kept.generation,
+        kept.tweak_id
+    );
+    Ok(())
+}
+
+/// Delete the generations `retention` no longer keeps by count or age
+fn prune_history(tweak_id: &str, retention: &HistoryRetention) -> Result<CleanupReport, Error> {
+    let generations = kept_generations(tweak_id)?;
+    let dated: Vec<(u32, Option<DateTime<FixedOffset>>)> = generations
+        .iter()
+        .map(|kept| (kept.generation, kept.created))
+        .collect();
+    let expired = generations_to_prune(&dated, retention, chrono::Local::now().into());
+
+    let mut report = CleanupReport::default();
+    for kept in generations
+        .iter()
+        .filter(|kept| expired.contains(&kept.generation))
+    {
+        delete_generation(kept)?;
+        report.generations_removed += 1;
+        report.bytes_freed += kept.size;
+    }
+    Ok(report)
+}
+
+/// Delete the oldest generations, of whichever tweak, until all history fits `max_total_mb`
+fn prune_to_size_cap(retention: &HistoryRetention) -> Result<CleanupReport, Error> {
+    let mut report = CleanupReport::default();
+    let Some(max_total_mb) = retention.max_total_mb else {
+        return Ok(report);
+    };
+
+    let mut generations = Vec::new();
+    for tweak_id in get_applied_tweaks()? {
+        generations.extend(kept_generations(&tweak_id)?);
+    }
+    // Undated generations sort first, so they're the first to go
+    generations.sort_by_key(|kept| kept.created);
+    let sizes: Vec<u64> = generations.iter().map(|kept| kept.size).collect();
+    let excess = oldest_over_size_cap(&sizes, u64::from(max_total_mb) * 1024 * 1024);
+
+    for kept in &generations[..excess] {
+        delete_generation(kept)?;
+        report.generations_removed += 1;
+        report.bytes_freed += kept.size;
+    }
+    Ok(report)
+}
+
+/// How many of `sizes` (oldest first) have to go for the rest to fit in `cap` bytes
+fn oldest_over_size_cap(sizes: &[u64], cap: u64) -> usize {
+    let mut total: u64 = sizes.iter().sum();
+    let mut excess = 0;
+    while total > cap && excess < sizes.len() {
+        total -= sizes[excess];
+        excess += 1;
+    }
+    excess
+}
+
+/// Generations (oldest first) that `retention` drops: all but the newest `max_generations`, and
+/// any older than `max_age_days`. A generation with an unreadable date is only dropped by count.
+fn generations_to_prune(
+    generations: &[(u32, Option<DateTime<FixedOffset>>)],
+    retention: &HistoryRetention,
+    now: DateTime<FixedOffset>,
+) -> Vec<u32> {
+    let keep_from = generations
+        .len()
+        .saturating_sub(retention.max_generations as usize);
+    let cutoff = retention
+        .max_age_days
+        .map(|days| now - Duration::days(i64::from(days)));
+
+    generations
+        .iter()
+        .enumerate()
+        .filter(|(i, (_, created))| {
+            *i < keep_from
+                || matches!((created, cutoff), (Some(created), Some(cutoff)) if *created < cutoff)
+        })
+        .map(|(_, (generation, _))| *generation)
+        .collect()
+}
+
+/// The capture folders holding a generation's file backups. Each capture has its own folder, so
+/// these never include the original snapshot's backups.
+fn capture_dirs(tweak_id: &str, snapshot: &TweakSnapshot) -> Vec<PathBuf> {
+    let backups_prefix = format!("{}/", file_backups_dir_name(tweak_id));
+    let dirs: BTreeSet<&str> = snapshot
+        .file_snapshots
+        .iter()
+        .filter_map(|file| file.backup_path.as_deref())
+        .filter(|path| path.starts_with(&backups_prefix))
+        .filter_map(|path| path.rsplit_once('/').map(|(dir, _)| dir))
+        .filter(|dir| dir.len() > backups_prefix.len())
+        .collect();
+    let Ok(root) = get_snapshots_dir() else {
+        return Vec::new();
+    };
+    dirs.into_iter().map(|dir| root.join(dir)).collect()
+}
+
+/// Remove the capture folders holding a generation's file backups. Leftovers only cost disk space,
+/// so failures are logged.
+fn remove_file_backups(tweak_id: &str, snapshot: &TweakSnapshot) {
+    for path in capture_dirs(tweak_id, snapshot) {
+        if path.exists() {
+            if let Err(e) = fs::remove_dir_all(&path) {
+                log::warn!("Failed to delete file backups {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::backup::storage::{delete_snapshot, save_snapshot};
+
+    fn at(s: &str) -> Option<DateTime<FixedOffset>> {
+        Some(DateTime::parse_from_rfc3339(s).unwrap())
+    }
+
+    #[test]
+    fn the_oldest_generations_beyond_the_limit_are_pruned() {
+        let generations: Vec<_> = (1..=5)
+            .map(|n| (n, at(&format!("2026-01-0{}T00:00:00+00:00", n))))
+            .collect();
+        let retention = HistoryRetention {
+            max_generations: 3,
+            ..HistoryRetention::default()
+        };
+        let now = at("2026-01-10T00:00:00+00:00").unwrap();
+        assert_eq!(
+            generations_to_prune(&generations, &retention, now),
+            vec![1, 2]
+        );
+    }
+
+    #[test]
+    fn generations_past_the_age_limit_are_pruned_unless_their_date_is_unknown() {
+        let generations = vec![
+            (1, at("2026-01-01T00:00:00+00:00")),
+            (2, None),
+            (3, at("2026-01-08T00:00:00+00:00")),
+        ];
+        let retention = HistoryRetention {
+            max_generations: 10,
+            max_age_days: Some(5),
+            max_total_mb: None,
+        };
+        let now = at("2026-01-10T00:00:00+00:00").unwrap();
+        assert_eq!(generations_to_prune(&generations, &retention, now), vec![1]);
+    }
+
+    #[test]
+    fn the_oldest_generations_go_until_the_rest_fit_the_size_cap() {
+        assert_eq!(oldest_over_size_cap(&[40, 30, 20, 10], 100), 0);
+        assert_eq!(oldest_over_size_cap(&[40, 30, 20, 10], 35), 2);
+        assert_eq!(oldest_over_size_cap(&[40, 30, 20, 10], 0), 4);
+        assert_eq!(oldest_over_size_cap(&[], 0), 0);
+    }
+
+    #[test]
+    fn generations_are_recorded_listed_and_removed_with_the_snapshot() {
+        let id = format!("__history_test_{}", std::process::id());
+        save_snapshot(&TweakSnapshot::new(&id, "T", 1, "Off", 11, false, Some(0))).unwrap();
+
+        let state = TweakSnapshot::new(&id, "T", usize::MAX, "_current_state_", 11, false, None);
+        let generation = record_generation(&id, state).unwrap();
+        if get_history_retention().max_generations > 0 {
+            assert_eq!(generation, Some(1));
+            let listed = list_snapshot_generations(&id).unwrap();
+            assert_eq!(listed.len(), 2);
+            assert_eq!(listed[0].generation, 0);
+            assert_eq!(listed[0].option_index, Some(0));
+            assert_eq!(listed[1].option_index, Some(1));
+            assert_eq!(listed[1].option_label.as_deref(), Some("Off"));
+            assert_eq!(load_generation(&id, 1).unwrap().applied_option_index, 1);
+        }
+
+        delete_snapshot(&id).unwrap();
+        assert!(!history_dir(&id).unwrap().exists());
+        assert!(list_snapshot_generations(&id).unwrap().is_empty());
+    }
+}
@@ -49,6 +49,7 @@ fn inspect_option(
 ) -> Result<OptionInspection, Error> {
     let comparison = super::compare::compare_option(option, windows_version)?;
     let all_match = comparison.all_match();
+    let (matched_count, mismatched_count) = comparison.match_counts();
 
     Ok(OptionInspection {
         option_index: index,
@@ -60,6 +61,17 @@ fn inspect_option(
         scheduler_results: comparison.scheduler,
         hosts_results: comparison.hosts,
         firewall_results: comparison.firewall,
+        power_plan_results: comparison.power_plan,
+        bcdedit_results: comparison.bcdedit,
+        appx_results: comparison.appx,
+        file_results: comparison.file,
+        registry_acl_results: comparison.registry_acl,
+        feature_results: comparison.feature,
+        netadapter_results: comparison.netadapter,
+        policy_results: comparison.policy,
+        app_results: comparison.app,
         all_match,
+        matched_count,
+        mismatched_count,
     })
 }
@@ -0,0 +1,230 @@
+//! Snapshot integrity: a SHA-256 checksum in every snapshot file, and quarantine for files that
+//! fail it.
+//!
+//! The checksum covers the snapshot's JSON without the `checksum` field, serialized compactly with
+//! sorted keys, so it doesn't depend on the pretty-printing or field order of the file. Snapshots
+//! written before checksums existed have none and are accepted as they are; they get one the next
+//! time they're saved. A file that isn't valid JSON or doesn't match its checksum is moved to
+//! `corrupt/` inside the snapshots directory rather than failing every operation that lists
+//! snapshots; the tweak then shows as not applied, and the file is kept for manual recovery. The
+//! checksum is taken over the plain JSON, so it's checked the same way for encrypted files (see
+//! `encryption`), which are decrypted first.
+
+use crate::error::Error;
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::encryption;
+use super::storage::{get_applied_tweaks, get_snapshot_path, get_snapshots_dir};
+
+/// Top-level field holding the checksum
+const CHECKSUM_FIELD: &str = "checksum";
+
+/// Folder (in the snapshots directory) corrupt snapshots are moved to
+const CORRUPT_DIR: &str = "corrupt";
+
+/// Why a snapshot file failed verification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Corruption {
+    InvalidJson(String),
+    ChecksumMismatch,
+    Undecryptable(String),
+}
+
+impl std::fmt::Display for Corruption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidJson(e) => write!(f, "not valid JSON ({})", e),
+            Self::ChecksumMismatch => write!(f, "checksum mismatch"),
+            Self::Undecryptable(e) => write!(f, "can't be decrypted ({})", e),
+        }
+    }
+}
+
+/// A verified snapshot file
+pub(crate) struct VerifiedJson {
+    /// The snapshot's JSON, without the checksum field
+    pub json: Value,
+    /// Whether the file had a checksum (false for snapshots written before checksums existed)
+    pub had_checksum: bool,
+}
+
+/// A snapshot that was moved out of the way
+#[derive(Debug, Clone, Serialize)]
+pub struct QuarantinedSnapshot {
+    pub tweak_id: String,
+    pub reason: String,
+    /// Where the file was moved to
+    pub moved_to: String,
+}
+
+/// Result of `verify_all_snapshots`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SnapshotVerifyReport {
+    /// Snapshots whose checksum matched
+    pub valid: Vec<String>,
+    /// Snapshots without a checksum (written by an older version)
+    pub unchecked: Vec<String>,
+    /// Snapshots that failed verification and were moved to `corrupt/`
+    pub quarantined: Vec<QuarantinedSnapshot>,
+}
+
+fn checksum_of(json: &Value) -> String {
+    // serde_json's Value keeps object keys sorted, so this form is canonical
+    let canonical = serde_json::to_vec(json).unwrap_or_default();
+    format!("{:x}", Sha256::digest(&canonical))
+}
+
+/// Serialize a snapshot to the pretty-printed JSON stored on disk, with its checksum
+fn to_checked_json(snapshot: &impl Serialize) -> Result<String, Error> {
+    let mut json = serde_json::to_value(snapshot)
+        .map_err(|e| Error::BackupFailed(format!("Failed to serialize snapshot: {}", e)))?;
+    let checksum = checksum_of(&json);
+    if let Some(fields) = json.as_object_mut() {
+        fields.insert(CHECKSUM_FIELD.to_string(), Value::from(checksum));
+    }
+    serde_json::to_string_pretty(&json)
+        .map_err(|e| Error::BackupFailed(format!("Failed to serialize snapshot: {}", e)))
+}
+
+/// The contents of a snapshot file: its checked JSON, encrypted if encryption is on
+pub(crate) fn to_file_contents(snapshot: &impl Serialize) -> Result<Vec<u8>, Error> {
+    encryption::seal(to_checked_json(snapshot)?.into_bytes())
+}
+
+/// Parse a snapshot file's contents (decrypting them if needed) and check its checksum, if it has
+/// one
+pub(crate) fn verify(content: &[u8]) -> Result<VerifiedJson, Corruption> {
+    let content = encryption::open(content).map_err(Corruption::Undecryptable)?;
+    let mut json: Value =
+        serde_json::from_slice(&content).map_err(|e| Corruption::InvalidJson(e.to_string()))?;
+    let stored = json
+        .as_object_mut()
+        .and_then(|fields| fields.remove(CHECKSUM_FIELD));
+
+    match stored {
+        None => Ok(VerifiedJson {
+            json,
+            had_checksum: false,
+        }),
+        Some(stored) if stored.as_str() == Some(checksum_of(&json).as_str()) => Ok(VerifiedJson {
+            json,
+            had_checksum: true,
+        }),
+        Some(_) => Err(Corruption::ChecksumMismatch),
+    }
+}
+
+/// Move a corrupt snapshot file to `corrupt/`, named after the tweak and the time it was found
+pub(crate) fn quarantine(
+    path: &Path,
+    tweak_id: &str,
+    reason: &Corruption,
+) -> Result<QuarantinedSnapshot, Error> {
+    let dir = get_snapshots_dir()?.join(CORRUPT_DIR);
+    fs::create_dir_all(&dir)
+        .map_err(|e| Error::BackupFailed(format!("Failed to create {}: {}", dir.display(), e)))?;
+    let target: PathBuf = dir.join(format!(
+        "{}-{}.json",
+        tweak_id,
+        chrono::Local::now().format("%Y%m%d%H%M%S%3f")
+    ));
+    fs::rename(path, &target).map_err(|e| {
+        Error::BackupFailed(format!(
+            "Failed to quarantine corrupt snapshot {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+
+    log::warn!(
+        "Snapshot for tweak '{}' is corrupt ({}); moved it to {}",
+        tweak_id,
+        reason,
+        target.display()
+    );
+    Ok(QuarantinedSnapshot {
+        tweak_id: tweak_id.to_string(),
+        reason: reason.to_string(),
+        moved_to: target.display().to_string(),
+    })
+}
+
+/// Check every snapshot file, quarantining the ones that fail
+pub fn verify_all_snapshots() -> Result<SnapshotVerifyReport, Error> {
+    let mut report = SnapshotVerifyReport::default();
+    let mut tweak_ids = get_applied_tweaks()?;
+    tweak_ids.sort();
+
+    for tweak_id in tweak_ids {
+        let path = get_snapshot_path(&tweak_id)?;
+        let content = match fs::read(&path) {
+            Ok(content) => content,
+            Err(e) => {
+                log::warn!("Failed to read snapshot for '{}': {}", tweak_id, e);
+                continue;
+            }
+        };
+        match verify(&content) {
+            Ok(verified) if verified.had_checksum => report.valid.push(tweak_id),
+            Ok(_) => report.unchecked.push(tweak_id),
+            Err(reason) => report
+                .quarantined
+                .push(quarantine(&path, &tweak_id, &reason)?),
+        }
+    }
+
+    log::info!(
+        "Verified snapshots: {} valid, {} without checksum, {} quarantined",
+        report.valid.len(),
+        report.unchecked.len(),
+        report.quarantined.len()
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TweakSnapshot;
+
+    #[test]
+    fn a_checked_snapshot_verifies_and_drops_the_checksum_field() {
+        let snapshot = TweakSnapshot::new("t", "T", 0, "opt", 11, false, None);
+        let content = to_checked_json(&snapshot).unwrap();
+        assert!(content.contains("\"checksum\""));
+
+        let verified = verify(content.as_bytes()).unwrap();
+        assert!(verified.had_checksum);
+        assert!(verified.json.get(CHECKSUM_FIELD).is_none());
+        assert_eq!(verified.json["tweak_id"], "t");
+    }
+
+    #[test]
+    fn an_edited_snapshot_fails_its_checksum() {
+        let snapshot = TweakSnapshot::new("t", "T", 0, "opt", 11, false, None);
+        let content = to_checked_json(&snapshot)
+            .unwrap()
+            .replace("\"applied_option_index\": 0", "\"applied_option_index\": 1");
+        assert!(matches!(
+            verify(content.as_bytes()),
+            Err(Corruption::ChecksumMismatch)
+        ));
+        assert!(matches!(
+            verify(b"{\"tweak_id\": \"t\""),
+            Err(Corruption::InvalidJson(_))
+        ));
+    }
+
+    #[test]
+    fn a_snapshot_without_a_checksum_is_accepted_as_unchecked() {
+        let content =
+            serde_json::to_string(&TweakSnapshot::new("t", "T", 0, "opt", 11, false, None))
+                .unwrap();
+        let verified = verify(content.as_bytes()).unwrap();
+        assert!(!verified.had_checksum);
+    }
+}
@@ -0,0 +1,339 @@
+//! Where snapshots are stored.
+//!
+//! Snapshots default to `snapshots\` next to the executable, which keeps the portable build
+//! self-contained. An installed copy under Program Files usually can't write there, so when the
+//! executable's folder isn't writable they go to `%ProgramData%\MagicX Toolbox\snapshots` instead.
+//! The user can also choose a location (ProgramData, AppData, or a custom folder); that choice is
+//! saved in `%ProgramData%\MagicX Toolbox\storage.json`, which is read before anything else so it
+//! applies from the first snapshot access. The same file holds the snapshot history retention (see
+//! `history`), whether snapshots are encrypted (see `encryption`), and whether tweaks reverted
+//! outside the app are applied again (see `snapshot_monitor`), since they belong to the machine's
+//! snapshots rather than to one user's UI settings. The ProgramData app folder is made writable by
+//! Administrators and SYSTEM only (see `protected_dir`), since the elevated app acts on what's in it.
+
+use crate::error::Error;
+use crate::services::{file_service, protected_dir};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Once, RwLock};
+
+use super::history::HistoryRetention;
+
+const SNAPSHOTS_DIR: &str = "snapshots";
+
+/// Folder under %ProgramData% / %APPDATA% for the app's data
+const APP_DATA_FOLDER: &str = "MagicX Toolbox";
+
+/// File (in the ProgramData app folder) recording the chosen location, history retention,
+/// encryption, and auto-reapply settings
+const STORAGE_SETTINGS_FILE: &str = "storage.json";
+
+/// Resolved snapshots directory, cached after the first lookup and replaced on a move
+static SNAPSHOTS_DIR_CACHE: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// A place snapshots can be kept
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SnapshotLocation {
+    /// `snapshots\` next to the executable
+    Portable,
+    /// `%ProgramData%\MagicX Toolbox\snapshots`, shared by every account on the machine
+    ProgramData,
+    /// `%APPDATA%\MagicX Toolbox\snapshots`, in the current user's profile
+    AppData,
+    /// A folder the user picked
+    Custom { path: String },
+}
+
+/// The location in use, for the settings UI
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotLocationInfo {
+    pub location: SnapshotLocation,
+    /// Resolved snapshots directory
+    pub path: String,
+    /// False when the location was picked automatically (no saved choice)
+    pub configured: bool,
+}
+
+/// Contents of `storage.json`
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(super) struct StorageSettings {
+    /// None until the user picks a location
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<SnapshotLocation>,
+    #[serde(default)]
+    pub history: HistoryRetention,
+    /// Write snapshot files encrypted
+    #[serde(default)]
+    pub encrypt_snapshots: bool,
+    /// Apply a tweak again when something outside the app reverts it
+    #[serde(default)]
+    pub auto_reapply: bool,
+    /// Count applies and reverts per tweak (see `usage_stats_service`)
+    #[serde(default)]
+    pub usage_stats: bool,
+}
+
+fn exe_dir() -> Result<PathBuf, Error> {
+    Ok(std::env::current_exe()
+        .map_err(|e| Error::BackupFailed(format!("Failed to get executable path: {}", e)))?
+        .parent()
+        .ok_or_else(|| Error::BackupFailed("Failed to get executable directory".into()))?
+        .to_path_buf())
+}
+
+fn env_dir(var: &str) -> Result<PathBuf, Error> {
+    std::env::var_os(var)
+        .map(PathBuf::from)
+        .ok_or_else(|| Error::BackupFailed(format!("%{}% is not set", var)))
+}
+
+/// The ProgramData app folder. The first call creates it for Administrators and SYSTEM only if it
+/// doesn't exist (or restricts it, when elevated); a failure is logged, and writing in it fails
+/// later where it matters.
+fn program_data_dir() -> Result<PathBuf, Error> {
+    static SECURED: Once = Once::new();
+    let dir = env_dir("ProgramData")?.join(APP_DATA_FOLDER);
+    SECURED.call_once(|| {
+        if let Err(e) = protected_dir::ensure(&dir) {
+            log::warn!("{}", e);
+        }
+    });
+    Ok(dir)
+}
+
+/// A file in the ProgramData app folder, which is the same for every account on the machine
+pub(crate) fn program_data_file(name: &str) -> Result<PathBuf, Error> {
+    Ok(program_data_dir()?.join(name))
+}
+
+fn settings_path() -> Result<PathBuf, Error> {
+    program_data_file(STORAGE_SETTINGS_FILE)
+}
+
+/// The snapshots directory for a location
+fn resolve(location: &SnapshotLocation) -> Result<PathBuf, Error> {
+    Ok(match location {
+        SnapshotLocation::Portable => exe_dir()?.join(SNAPSHOTS_DIR),
+        SnapshotLocation::ProgramData => program_data_dir()?.join(SNAPSHOTS_DIR),
+        SnapshotLocation::AppData => env_dir("APPDATA")?
+            .join(APP_DATA_FOLDER)
+            .join(SNAPSHOTS_DIR),
+        SnapshotLocation::Custom { path } => {
+            let path = PathBuf::from(path);
+            if !path.is_absolute() {
+                return Err(Error::ValidationError(format!(
+                    "Snapshot folder must be an absolute path: {}",
+                    path.display()
+                )));
+            }
+            path
+        }
+    })
+}
+
+/// The saved settings, or the defaults if there are none. An unreadable settings file is ignored
+/// (with a warning) rather than blocking every snapshot operation.
+pub(super) fn read_storage_settings() -> StorageSettings {
+    let Some(path) = settings_path().ok() else {
+        return StorageSettings::default();
+    };
+    let Ok(content) = fs::read_to_string(&path) else {
+        return StorageSettings::default();
+    };
+    serde_json::from_str(&content).unwrap_or_else(|e| {
+        log::warn!("Ignoring unreadable {}: {}", path.display(), e);
+        StorageSettings::default()
+    })
+}
+
+pub(super) fn write_storage_settings(settings: &StorageSettings) -> Result<(), Error> {
+    let path = settings_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| {
+            Error::BackupFailed(format!("Failed to create {}: {}", parent.display(), e))
+        })?;
+    }
+    let json = serde_json::to_string_pretty(settings)
+        .map_err(|e| Error::BackupFailed(format!("Failed to serialize storage settings: {}", e)))?;
+    fs::write(&path, json)
+        .map_err(|e| Error::BackupFailed(format!("Failed to write {}: {}", path.display(), e)))
+}
+
+/// Whether files can be created in `dir` (creating it if needed)
+fn is_writable(dir: &Path) -> bool {
+    fs::create_dir_all(dir).is_ok() && tempfile::tempfile_in(dir).is_ok()
+}
+
+/// The location used when none is saved: next to the executable if that's writable, otherwise
+/// ProgramData
+fn automatic_location() -> SnapshotLocation {
+    match exe_dir() {
+        Ok(dir) if is_writable(&dir.join(SNAPSHOTS_DIR)) => SnapshotLocation::Portable,
+        _ => {
+            log::info!("Executable folder is not writable; storing snapshots in ProgramData");
+            SnapshotLocation::ProgramData
+        }
+    }
+}
+
+fn current() -> (SnapshotLocation, bool) {
+    match read_storage_settings().location {
+        Some(location) => (location, true),
+        None => (automatic_location(), false),
+    }
+}
+
+/// The snapshots directory in use, created if it doesn't exist yet
+pub fn snapshots_dir() -> Result<PathBuf, Error> {
+    if let Some(dir) = SNAPSHOTS_DIR_CACHE
+        .read()
+        .ok()
+        .and_then(|cache| cache.clone())
+    {
+        return Ok(dir);
+    }
+
+    let (location, _) = current();
+    let dir = resolve(&location)?;
+    if !dir.exists() {
+        fs::create_dir_all(&dir).map_err(|e| {
+            Error::BackupFailed(format!("Failed to create snapshots directory: {}", e))
+        })?;
+        log::debug!("Created snapshots directory at {:?}", dir);
+    }
+
+    if let Ok(mut cache) = SNAPSHOTS_DIR_CACHE.write() {
+        *cache = Some(dir.clone());
+    }
+    Ok(dir)
+}
+
+/// The location in use and where it points
+pub fn get_snapshot_location() -> Result<SnapshotLocationInfo, Error> {
+    let (location, configured) = current();
+    Ok(SnapshotLocationInfo {
+        path: snapshots_dir()?.display().to_string(),
+        location,
+        configured,
+    })
+}
+
+/// Switch to a new location, moving the existing snapshots (and their file backups) there when
+/// `move_existing` is set. Nothing is switched if the new folder isn't writable or already holds a
+/// snapshot with the same name, and a failed copy removes what it copied.
+pub fn set_snapshot_location(
+    location: SnapshotLocation,
+    move_existing: bool,
+) -> Result<SnapshotLocationInfo, Error> {
+    let old_dir = snapshots_dir()?;
+    let new_dir = resolve(&location)?;
+    if !is_writable(&new_dir) {
+        return Err(Error::BackupFailed(format!(
+            "Can't write to {}",
+            new_dir.display()
+        )));
+    }
+
+    let same_dir = fs::canonicalize(&old_dir).ok() == fs::canonicalize(&new_dir).ok();
+    let entries: Vec<PathBuf> = if move_existing && !same_dir {
+        fs::read_dir(&old_dir)
+            .map_err(|e| Error::BackupFailed(format!("Failed to read snapshots: {}", e)))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    if let Some(clash) = entries
+        .iter()
+        .filter_map(|entry| entry.file_name())
+        .find(|name| new_dir.join(name).exists())
+    {
+        return Err(Error::BackupFailed(format!(
+            "{} already contains {}",
+            new_dir.display(),
+            clash.to_string_lossy()
+        )));
+    }
+
+    let mut copied: Vec<PathBuf> = Vec::new();
+    for entry in &entries {
+        let Some(name) = entry.file_name() else {
+            continue;
+        };
+        let target = new_dir.join(name);
+        if let Err(e) = file_service::copy_path(entry, &target) {
+            for path in copied.iter().chain([&target]) {
+                let _ = file_service::remove_path(&path.to_string_lossy());
+            }
+            return Err(e);
+        }
+        copied.push(target);
+    }
+
+    write_storage_settings(&StorageSettings {
+        location: Some(location.clone()),
+        ..read_storage_settings()
+    })?;
+    if let Ok(mut cache) = SNAPSHOTS_DIR_CACHE.write() {
+        *cache = Some(new_dir.clone());
+    }
+
+    // The copies are in place and in use, so a leftover original is only disk usage
+    for entry in &entries {
+        if let Err(e) = file_service::remove_path(&entry.to_string_lossy()) {
+            log::warn!(
+                "Failed to remove {} after moving it: {}",
+                entry.display(),
+                e
+            );
+        }
+    }
+
+    log::info!(
+        "Snapshots location set to {:?} ({}), {} entries moved",
+        location,
+        new_dir.display(),
+        entries.len()
+    );
+    Ok(SnapshotLocationInfo {
+        location,
+        path: new_dir.display().to_string(),
+        configured: true,
+    })
+}
+
+/// Whether a tweak reverted outside the app (by Windows Update or another tool) is applied again
+pub fn get_auto_reapply() -> bool {
+    read_storage_settings().auto_reapply
+}
+
+/// Turn applying reverted tweaks again on or off
+pub fn set_auto_reapply(enabled: bool) -> Result<(), Error> {
+    write_storage_settings(&StorageSettings {
+        auto_reapply: enabled,
+        ..read_storage_settings()
+    })?;
+    log::info!(
+        "Auto-reapply of externally reverted tweaks set to {}",
+        enabled
+    );
+    Ok(())
+}
+
+/// Whether applies and reverts are counted in the usage statistics (off unless the user opts in)
+pub fn get_usage_stats_enabled() -> bool {
+    read_storage_settings().usage_stats
+}
+
+/// Turn counting applies and reverts on or off. Counts already kept stay until they're reset.
+pub fn set_usage_stats_enabled(enabled: bool) -> Result<(), Error> {
+    write_storage_settings(&StorageSettings {
+        usage_stats: enabled,
+        ..read_storage_settings()
+    })?;
+    log::info!("Usage statistics set to {}", enabled);
+    Ok(())
+}
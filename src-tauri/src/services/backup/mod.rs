@@ -5,31 +5,87 @@
 //!
 //! ## Module Organization
 //!
-//! - `storage`: File I/O for snapshot persistence
+//! - `storage`: File I/O for snapshot persistence, and the intent records of applies in progress
+//! - `location`: Which directory snapshots are stored in
+//! - `history`: Earlier generations of each snapshot, and their retention
 //! - `capture`: State capture before applying tweaks
 //! - `restore`: Atomic restore with rollback support
 //! - `detection`: State detection and snapshot validation
 //! - `inspection`: Per-item mismatch report for the UI
+//! - `diff`: Snapshot vs live state, for the warning before a revert
 //! - `compare`: The shared option-vs-current comparison core (detection + inspection)
+//! - `export`: `.reg` export of a snapshot's registry values
+//! - `script`: Export of the applied tweaks as a PowerShell script and `.reg` files
+//! - `archive`: Export/import of all snapshots as one compressed archive
+//! - `integrity`: Snapshot checksums and quarantine of corrupt snapshots
+//! - `encryption`: Optional AES-GCM encryption of snapshot files, keyed through DPAPI
+//! - `baseline`: State of everything the tweaks reference, captured on first run
+//! - `reboot`: Which applied tweaks are still waiting for a reboot
+//! - `rename`: Moving the snapshots of a renamed tweak from its old ID (an alias) to the new one
+//! - `usage`: Disk used by each tweak's snapshots and by the snapshots directory
 //! - `helpers`: Parsing and comparison utilities
 
 #[cfg(test)]
 mod roundtrip_tests;
 
+mod archive;
+mod baseline;
 mod capture;
 mod compare;
 mod detection;
+mod diff;
+mod encryption;
+mod export;
 mod helpers;
+mod history;
 pub mod inspection;
+mod integrity;
+mod location;
+mod reboot;
+mod rename;
 pub mod restore;
+mod script;
 pub mod storage;
+mod usage;
 
 // Re-export public items from submodules
-pub use capture::{capture_current_state, capture_snapshot, read_registry_value};
+pub use archive::{export_backup_archive, import_backup_archive, ArchiveImportReport};
+pub use baseline::{
+    capture_baseline, ensure_baseline, get_baseline_info, load_baseline, restore_key_to_baseline,
+    BaselineInfo, BaselineRestoreResult, SystemBaseline,
+};
+pub use capture::{
+    capture_current_state, capture_snapshot, read_key_contents, read_registry_value,
+};
 pub use detection::{detect_tweak_state, validate_all_snapshots};
+pub use diff::{diff_snapshot, DiffEntry, DiffStatus, SnapshotDiff};
+pub use encryption::{get_snapshot_encryption, set_snapshot_encryption};
+pub use export::export_snapshot_as_reg;
+pub(crate) use helpers::{parse_hive, task_state_matches};
+pub use history::{
+    cleanup_old_backups, get_history_retention, list_snapshot_generations, load_generation,
+    record_generation, set_history_retention, CleanupReport, HistoryRetention, SnapshotGeneration,
+};
 pub use inspection::inspect_tweak;
-pub use restore::{restore_from_snapshot, RestoreResult};
+pub use integrity::{verify_all_snapshots, QuarantinedSnapshot, SnapshotVerifyReport};
+pub(crate) use location::program_data_file;
+pub use location::{
+    get_auto_reapply, get_snapshot_location, get_usage_stats_enabled, set_auto_reapply,
+    set_snapshot_location, set_usage_stats_enabled, SnapshotLocation, SnapshotLocationInfo,
+};
+pub(crate) use reboot::same_boot;
+pub use reboot::{
+    ensure_no_pending_reboot, get_pending_reboot_tweaks, is_reboot_pending, mark_reboot_pending,
+};
+pub use rename::migrate_renamed_tweaks;
+pub use restore::{
+    restore_changes, restore_from_snapshot, write_key_contents, ChangeSelector, RestoreResult,
+};
+pub use script::{export_applied_tweaks_as_script, PartialScriptExport, ScriptExportReport};
 pub use storage::{
-    delete_snapshot, get_applied_tweaks, load_snapshot, mark_needs_attention, save_snapshot,
-    snapshot_exists, update_snapshot_metadata,
+    begin_apply_intent, delete_snapshot, discard_apply_intent, get_applied_tweaks,
+    get_applied_tweaks_in_revert_order, has_incomplete_apply, incomplete_apply_intents,
+    load_snapshot, mark_needs_attention, save_snapshot, snapshot_exists, update_snapshot_metadata,
+    ApplyIntent,
 };
+pub use usage::{get_backup_storage_stats, BackupStorageStats, TweakStorageUsage};
@@ -0,0 +1,84 @@
+//! Pending reboot tracking.
+//!
+//! Some options only take effect after a reboot, and until then the values detection reads may not
+//! be what Windows acts on (or Windows rewrites them on the way down). Changing such a tweak again
+//! before rebooting, or reverting it, stacks a second change on state that hasn't settled, which
+//! is how detection ends up confused. So an apply that needs a reboot records the current boot in
+//! the snapshot, and re-applying or reverting the tweak is refused (unless forced) until the
+//! machine has booted again.
+
+use crate::error::Error;
+use crate::models::TweakSnapshot;
+use crate::services::system_info_service;
+use chrono::{DateTime, Local};
+
+use super::storage::{get_applied_tweaks, load_snapshot, save_snapshot};
+
+/// Boot times computed in the same boot drift by milliseconds (and by clock adjustments); a
+/// reboot moves it by at least the time the machine was off and booting
+const SAME_BOOT_TOLERANCE_SECS: i64 = 60;
+
+/// Whether a boot time recorded earlier (RFC 3339) is `current`'s boot
+pub(crate) fn same_boot(recorded: &str, current: DateTime<Local>) -> bool {
+    DateTime::parse_from_rfc3339(recorded).is_ok_and(|recorded| {
+        (current.fixed_offset() - recorded).num_seconds().abs() <= SAME_BOOT_TOLERANCE_SECS
+    })
+}
+
+/// Whether the snapshot's applied option is still waiting for a reboot
+pub fn is_reboot_pending(snapshot: &TweakSnapshot) -> bool {
+    snapshot
+        .pending_reboot_boot
+        .as_deref()
+        .is_some_and(|boot| same_boot(boot, system_info_service::boot_time()))
+}
+
+/// Record on a tweak's snapshot that its applied option needs a reboot
+pub fn mark_reboot_pending(tweak_id: &str) -> Result<(), Error> {
+    if let Some(mut snapshot) = load_snapshot(tweak_id)? {
+        snapshot.pending_reboot_boot = Some(system_info_service::boot_time().to_rfc3339());
+        save_snapshot(&snapshot)?;
+        log::debug!("Marked tweak '{}' as waiting for a reboot", tweak_id);
+    }
+    Ok(())
+}
+
+/// Tweaks whose applied option is still waiting for a reboot
+pub fn get_pending_reboot_tweaks() -> Result<Vec<String>, Error> {
+    Ok(get_applied_tweaks()?
+        .into_iter()
+        .filter(|tweak_id| {
+            load_snapshot(tweak_id)
+                .ok()
+                .flatten()
+                .is_some_and(|snapshot| is_reboot_pending(&snapshot))
+        })
+        .collect())
+}
+
+/// Refuse to change a tweak that's still waiting for a reboot; pass `force` to change it anyway
+pub fn ensure_no_pending_reboot(tweak_id: &str) -> Result<(), Error> {
+    match load_snapshot(tweak_id)? {
+        Some(snapshot) if is_reboot_pending(&snapshot) => Err(Error::RebootPending(format!(
+            "'{}' needs a reboot before it can be changed again",
+            snapshot.tweak_name
+        ))),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_boot_time_within_the_tolerance_is_the_same_boot() {
+        let boot = Local::now();
+        let recorded = (boot - chrono::Duration::seconds(2)).to_rfc3339();
+        assert!(same_boot(&recorded, boot));
+
+        let earlier = (boot - chrono::Duration::minutes(10)).to_rfc3339();
+        assert!(!same_boot(&earlier, boot));
+        assert!(!same_boot("not a time", boot));
+    }
+}
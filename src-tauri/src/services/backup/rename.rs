@@ -0,0 +1,171 @@
+//! Moving what's stored under a renamed tweak's earlier ID to its current one.
+//!
+//! A tweak renamed in the YAML lists its old ID in `aliases`. Its snapshot, file backups, and
+//! history were stored under the old ID, and would otherwise look like the snapshot of a tweak that
+//! no longer exists. At startup (and after an archive import) each one is moved to the current ID,
+//! with the tweak ID and the file backup paths inside rewritten, and so are the tweak's note and
+//! usage counts.
+
+use crate::error::Error;
+use crate::models::TweakSnapshot;
+use crate::services::{notes_service, tweak_loader, usage_stats_service};
+use std::fs;
+use std::path::Path;
+
+use super::integrity;
+use super::storage::{
+    file_backups_dir_name, get_applied_tweaks, get_snapshot_path, get_snapshots_dir,
+    history_dir_name, load_snapshot, save_snapshot, snapshot_exists, snapshot_from_json,
+    write_snapshot_file,
+};
+
+/// Move the snapshots stored under an alias to the tweak's current ID. Returns how many moved.
+pub fn migrate_renamed_tweaks() -> Result<u32, Error> {
+    let mut moved = 0;
+    for old_id in get_applied_tweaks()? {
+        let Some(new_id) = tweak_loader::renamed_to(&old_id) else {
+            continue;
+        };
+        match move_snapshot(&old_id, new_id) {
+            Ok(true) => moved += 1,
+            Ok(false) => {}
+            Err(e) => log::warn!(
+                "Failed to move the snapshot of '{}' to '{}': {}",
+                old_id,
+                new_id,
+                e
+            ),
+        }
+    }
+    if let Err(e) = notes_service::rename_notes(tweak_loader::renamed_to) {
+        log::warn!("Failed to move the notes of renamed tweaks: {}", e);
+    }
+    if let Err(e) = usage_stats_service::rename_stats(tweak_loader::renamed_to) {
+        log::warn!("Failed to move the usage counts of renamed tweaks: {}", e);
+    }
+    Ok(moved)
+}
+
+/// Move one tweak's snapshot, file backups, and history. Returns false if there's nothing to move
+/// or the new ID already has a snapshot, which is kept.
+fn move_snapshot(old_id: &str, new_id: &str) -> Result<bool, Error> {
+    if snapshot_exists(new_id)? {
+        log::warn!(
+            "Keeping the snapshot of '{}' under its old ID: '{}' has one of its own",
+            old_id,
+            new_id
+        );
+        return Ok(false);
+    }
+    let Some(mut snapshot) = load_snapshot(old_id)? else {
+        return Ok(false);
+    };
+
+    let dir = get_snapshots_dir()?;
+    for (old, new) in [
+        (file_backups_dir_name(old_id), file_backups_dir_name(new_id)),
+        (history_dir_name(old_id), history_dir_name(new_id)),
+    ] {
+        if dir.join(&old).exists() {
+            fs::rename(dir.join(&old), dir.join(&new)).map_err(|e| {
+                Error::BackupFailed(format!("Failed to move {} to {}: {}", old, new, e))
+            })?;
+        }
+    }
+    rename_generations(&dir.join(history_dir_name(new_id)), old_id, new_id)?;
+
+    // The snapshot goes last: until it's written under the new ID, the old one is still the record
+    rename_in_snapshot(&mut snapshot, old_id, new_id);
+    save_snapshot(&snapshot)?;
+    fs::remove_file(get_snapshot_path(old_id)?).map_err(|e| {
+        Error::BackupFailed(format!(
+            "Failed to remove the snapshot of '{}': {}",
+            old_id, e
+        ))
+    })?;
+    log::info!(
+        "Moved the snapshot of '{}' to its new ID '{}'",
+        old_id,
+        new_id
+    );
+    Ok(true)
+}
+
+/// Rewrite the generations in a (moved) history folder for the new ID
+fn rename_generations(history: &Path, old_id: &str, new_id: &str) -> Result<(), Error> {
+    if !history.exists() {
+        return Ok(());
+    }
+    let entries = fs::read_dir(history)
+        .map_err(|e| Error::BackupFailed(format!("Failed to read snapshot history: {}", e)))?;
+    for path in entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+    {
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let content = fs::read(&path)
+            .map_err(|e| Error::BackupFailed(format!("Failed to read snapshot history: {}", e)))?;
+        let mut generation = match integrity::verify(&content) {
+            Ok(verified) => snapshot_from_json(verified.json)?,
+            Err(reason) => {
+                integrity::quarantine(&path, new_id, &reason)?;
+                continue;
+            }
+        };
+        rename_in_snapshot(&mut generation, old_id, new_id);
+        write_snapshot_file(&path, &generation)?;
+    }
+    Ok(())
+}
+
+/// Point a snapshot at the new ID, and its file backups at the moved folder
+fn rename_in_snapshot(snapshot: &mut TweakSnapshot, old_id: &str, new_id: &str) {
+    let old_prefix = format!("{}/", file_backups_dir_name(old_id));
+    let new_prefix = format!("{}/", file_backups_dir_name(new_id));
+    snapshot.tweak_id = new_id.to_string();
+    for file in &mut snapshot.file_snapshots {
+        if let Some(rest) = file
+            .backup_path
+            .as_deref()
+            .and_then(|path| path.strip_prefix(&old_prefix))
+        {
+            file.backup_path = Some(format!("{}{}", new_prefix, rest));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::FileSnapshot;
+
+    #[test]
+    fn renaming_rewrites_the_id_and_file_backup_paths() {
+        let file = |backup_path: &str| FileSnapshot {
+            path: r"C:\Windows\System32\drivers\etc\hosts".to_string(),
+            existed: true,
+            is_dir: false,
+            backup_path: Some(backup_path.to_string()),
+            attributes: None,
+            moved_to: None,
+        };
+        let mut snapshot = TweakSnapshot::new("old_name", "Tweak", 1, "On", 11, false, Some(0));
+        snapshot.file_snapshots.push(file("old_name.files/2/hosts"));
+        snapshot
+            .file_snapshots
+            .push(file("elsewhere/old_name.files/hosts"));
+
+        rename_in_snapshot(&mut snapshot, "old_name", "new_name");
+        assert_eq!(snapshot.tweak_id, "new_name");
+        assert_eq!(
+            snapshot.file_snapshots[0].backup_path.as_deref(),
+            Some("new_name.files/2/hosts")
+        );
+        assert_eq!(
+            snapshot.file_snapshots[1].backup_path.as_deref(),
+            Some("elsewhere/old_name.files/hosts")
+        );
+    }
+}
@@ -3,22 +3,42 @@
 //! Functions for restoring system state from snapshots:
 //! - Atomic restore with rollback on failure
 //! - Registry value restoration (normal and elevated)
+//! - Registry key recreation (with the subkeys and values captured before a delete_key)
 //! - Service and scheduler state restoration (with SYSTEM elevation support)
+//! - Power plan restoration (settings first, then the previously active scheme)
+//! - BCD element restoration
+//! - AppX package re-registration
+//! - File and folder restoration from the snapshot's file backups
+//! - Windows optional feature restoration
+//! - Network adapter advanced property restoration
+//! - Group Policy Registry.pol restoration and policy refresh
+//! - Application (winget package) reinstall/uninstall
+//! - Registry key security descriptor restoration (last, after the values it guarded)
+//! - Selective restore of chosen registry values/keys, services, and tasks (`restore_changes`)
 
 use crate::error::Error;
+use crate::metrics;
 use crate::models::{
-    FirewallSnapshot, HostsSnapshot, RegistryHive, RegistrySnapshot, SchedulerAction,
-    SchedulerSnapshot, ServiceSnapshot, TweakSnapshot,
+    AppAction, AppSnapshot, AppxScope, AppxSnapshot, BcdeditSnapshot, ChangeElevation,
+    FeatureSnapshot, FileSnapshot, FirewallSnapshot, HostsSnapshot, NetAdapterSnapshot,
+    PolicyScope, PolicySnapshot, PowerPlanSnapshot, RegistryAclSnapshot, RegistryHive,
+    RegistryKeyContents, RegistrySnapshot, SchedulerAction, SchedulerSnapshot, ServiceSnapshot,
+    TweakSnapshot,
 };
+use crate::services::package_service::{AppProgress, AppProgressStage};
 use crate::services::{
-    firewall_service, hosts_service, registry_service, registry_value, service_control,
+    appx_service, bcdedit_service, dism_service, file_service, firewall_service, hosts_service,
+    network_service, package_service, policy_service, power_service, registry_acl_service,
+    registry_service, registry_value, scheduler_service, service_control, time_service,
     trusted_installer,
 };
 
-use super::helpers::{parse_hive, parse_value_type};
+use super::helpers::{parse_hive, parse_policy_scope, parse_value_type};
+use super::storage;
+use serde::{Deserialize, Serialize};
 
 /// Result of a restore operation with detailed failure information
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct RestoreResult {
     /// Whether all restore operations succeeded
     pub success: bool,
@@ -48,8 +68,9 @@ pub fn restore_from_snapshot(snapshot: &TweakSnapshot) -> Result<RestoreResult,
     let mut failures: Vec<String> = Vec::new();
 
     // Phase 1: Restore registry values
+    let phase = metrics::timed("registry");
     for reg in &snapshot.registry_snapshots {
-        if let Err(e) = restore_one_registry(reg, snapshot.requires_system) {
+        if let Err(e) = restore_one_registry(reg, uses_system(reg.elevation, snapshot)) {
             let msg = format!(
                 "Registry '{}\\{}\\{}': {}",
                 reg.hive, reg.key, reg.value_name, e
@@ -58,24 +79,42 @@ pub fn restore_from_snapshot(snapshot: &TweakSnapshot) -> Result<RestoreResult,
             failures.push(msg);
         }
     }
+    // Windows Time only reads its registry values when told to; like a policy refresh, a failed
+    // reload isn't a restore failure, since the values themselves are back
+    if snapshot.registry_snapshots.iter().any(|reg| {
+        reg.hive == RegistryHive::Hklm.as_str() && time_service::is_w32time_key(&reg.key)
+    }) {
+        if let Err(e) = time_service::update_config() {
+            log::warn!("Failed to reload Windows Time configuration: {}", e);
+        }
+    }
+    drop(phase);
 
-    // Phase 2: Restore service states
-    for svc in &snapshot.service_snapshots {
-        if let Err(e) = restore_service_state(svc, snapshot.requires_system) {
+    // Phase 2: Restore service states, last changed first, so services come back before the
+    // ones that depend on them
+    let phase = metrics::timed("services");
+    for svc in snapshot.service_snapshots.iter().rev() {
+        if let Err(e) = restore_service_state(svc, uses_system(svc.elevation, snapshot)) {
             let msg = format!("Service '{}': {}", svc.name, e);
             log::error!("Failed to restore service: {}", msg);
             failures.push(msg);
         }
     }
+    drop(phase);
 
     // Phase 3: Restore scheduled task states (with SYSTEM elevation if needed)
+    let phase = metrics::timed("scheduled tasks");
     for task in &snapshot.scheduler_snapshots {
-        if let Err(e) = restore_scheduler_state(task, snapshot.requires_system) {
+        if let Err(e) = restore_scheduler_state(task, uses_system(task.elevation, snapshot)) {
             let msg = format!("Task '{}\\{}': {}", task.task_path, task.task_name, e);
             log::error!("Failed to restore task: {}", msg);
             failures.push(msg);
         }
     }
+    drop(phase);
+
+    // Phases 4-14 are timed together as the rarer kinds of change
+    let phase = metrics::timed("other changes");
 
     // Phase 4: Restore hosts file entries (collect failures)
     for host in &snapshot.hosts_snapshots {
@@ -95,32 +134,307 @@ pub fn restore_from_snapshot(snapshot: &TweakSnapshot) -> Result<RestoreResult,
         }
     }
 
+    // Phase 6: Restore power plan state (collect failures). Settings go first so that restoring
+    // the previously active scheme last commits them.
+    let (scheme_snapshots, setting_snapshots): (Vec<_>, Vec<_>) = snapshot
+        .power_plan_snapshots
+        .iter()
+        .partition(|pp| pp.setting.is_none());
+    for pp in setting_snapshots.into_iter().chain(scheme_snapshots) {
+        if let Err(e) = restore_power_plan_state(pp) {
+            let msg = format!("Power plan '{}': {}", power_plan_label(pp), e);
+            log::error!("Failed to restore power plan: {}", msg);
+            failures.push(msg);
+        }
+    }
+
+    // Phase 7: Restore BCD elements (collect failures)
+    for bcd in &snapshot.bcdedit_snapshots {
+        if let Err(e) = restore_bcdedit_state(bcd) {
+            let msg = format!("BCD '{} {}': {}", bcd.identifier, bcd.element, e);
+            log::error!("Failed to restore BCD element: {}", msg);
+            failures.push(msg);
+        }
+    }
+
+    // Phase 8: Restore AppX packages (collect failures)
+    for appx in &snapshot.appx_snapshots {
+        if let Err(e) = restore_appx_state(appx) {
+            let msg = format!("AppX '{}': {}", appx.package, e);
+            log::error!("Failed to restore AppX package: {}", msg);
+            failures.push(msg);
+        }
+    }
+
+    // Phase 9: Restore files and folders (collect failures). Reverse order, so a path touched by
+    // several changes ends up as the earliest snapshot recorded it.
+    for file in snapshot.file_snapshots.iter().rev() {
+        if let Err(e) = restore_file_state(file) {
+            let msg = format!("File '{}': {}", file.path, e);
+            log::error!("Failed to restore file: {}", msg);
+            failures.push(msg);
+        }
+    }
+
+    // Phase 10: Restore optional features (collect failures)
+    for feature in &snapshot.feature_snapshots {
+        if let Err(e) = restore_feature_state(feature) {
+            let msg = format!("Feature '{}': {}", feature.feature, e);
+            log::error!("Failed to restore optional feature: {}", msg);
+            failures.push(msg);
+        }
+    }
+
+    // Phase 11: Restore network adapter properties (collect failures)
+    for netadapter in &snapshot.netadapter_snapshots {
+        if let Err(e) = restore_netadapter_state(netadapter) {
+            let msg = format!(
+                "Adapter '{}' {}: {}",
+                netadapter.adapter, netadapter.keyword, e
+            );
+            log::error!("Failed to restore network adapter property: {}", msg);
+            failures.push(msg);
+        }
+    }
+
+    // Phase 12: Restore Registry.pol records (collect failures), then refresh Group Policy for
+    // every scope the tweak touched. The policy values themselves were restored in phase 1.
+    let mut policy_scopes: Vec<PolicyScope> = Vec::new();
+    for policy in &snapshot.policy_snapshots {
+        match restore_policy_state(policy) {
+            Ok(scope) => {
+                if !policy_scopes.contains(&scope) {
+                    policy_scopes.push(scope);
+                }
+            }
+            Err(e) => {
+                let msg = format!("Policy '{}\\{}': {}", policy.key, policy.value_name, e);
+                log::error!("Failed to restore policy: {}", msg);
+                failures.push(msg);
+            }
+        }
+    }
+    for scope in policy_scopes {
+        // The restored values are already in the registry; a failed refresh only delays
+        // Group Policy catching up, so it isn't a restore failure.
+        if let Err(e) = policy_service::refresh(scope) {
+            log::warn!("Failed to refresh {} policy: {}", scope.as_str(), e);
+        }
+    }
+
+    // Phase 13: Restore application install states (collect failures)
+    let app_total = snapshot.app_snapshots.len();
+    for (i, app) in snapshot.app_snapshots.iter().enumerate() {
+        if let Err(e) = restore_app_state(app, &snapshot.tweak_id, i + 1, app_total) {
+            let msg = format!("App '{}': {}", app.id, e);
+            log::error!("Failed to restore application: {}", msg);
+            failures.push(msg);
+        }
+    }
+
+    // Phase 14: Restore registry key security descriptors (collect failures). Last, because the
+    // registry values restored in phase 1 may only be writable with the access the tweak granted.
+    for acl in &snapshot.registry_acl_snapshots {
+        if let Err(e) = restore_registry_acl_state(acl) {
+            let msg = format!("Registry ACL '{}\\{}': {}", acl.hive, acl.key, e);
+            log::error!("Failed to restore registry ACL: {}", msg);
+            failures.push(msg);
+        }
+    }
+    drop(phase);
+
     let success = failures.is_empty();
 
     if success {
         log::info!(
-            "Successfully restored {} registry, {} services, {} tasks, {} hosts, {} firewall",
+            "Successfully restored {} registry, {} services, {} tasks, {} hosts, {} firewall, {} power plan, {} BCD, {} AppX, {} files, {} features, {} adapter properties, {} policies, {} apps, {} registry ACLs",
             snapshot.registry_snapshots.len(),
             snapshot.service_snapshots.len(),
             snapshot.scheduler_snapshots.len(),
             snapshot.hosts_snapshots.len(),
-            snapshot.firewall_snapshots.len()
+            snapshot.firewall_snapshots.len(),
+            snapshot.power_plan_snapshots.len(),
+            snapshot.bcdedit_snapshots.len(),
+            snapshot.appx_snapshots.len(),
+            snapshot.file_snapshots.len(),
+            snapshot.feature_snapshots.len(),
+            snapshot.netadapter_snapshots.len(),
+            snapshot.policy_snapshots.len(),
+            snapshot.app_snapshots.len(),
+            snapshot.registry_acl_snapshots.len()
         );
     } else {
         log::warn!(
-            "Restore completed with {} failures out of {} registry, {} services, {} tasks, {} hosts, {} firewall",
+            "Restore completed with {} failures out of {} registry, {} services, {} tasks, {} hosts, {} firewall, {} power plan, {} BCD, {} AppX, {} files, {} features, {} adapter properties, {} policies, {} apps, {} registry ACLs",
             failures.len(),
             snapshot.registry_snapshots.len(),
             snapshot.service_snapshots.len(),
             snapshot.scheduler_snapshots.len(),
             snapshot.hosts_snapshots.len(),
-            snapshot.firewall_snapshots.len()
+            snapshot.firewall_snapshots.len(),
+            snapshot.power_plan_snapshots.len(),
+            snapshot.bcdedit_snapshots.len(),
+            snapshot.appx_snapshots.len(),
+            snapshot.file_snapshots.len(),
+            snapshot.feature_snapshots.len(),
+            snapshot.netadapter_snapshots.len(),
+            snapshot.policy_snapshots.len(),
+            snapshot.app_snapshots.len(),
+            snapshot.registry_acl_snapshots.len()
         );
     }
 
     Ok(RestoreResult { success, failures })
 }
 
+/// Picks items out of a snapshot for `restore_changes`. Names are matched case-insensitively, like
+/// Windows does.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ChangeSelector {
+    /// A registry value, or with no `value_name`, a key-level snapshot (a deleted or created key)
+    Registry {
+        hive: String,
+        key: String,
+        #[serde(default)]
+        value_name: Option<String>,
+    },
+    Service {
+        name: String,
+    },
+    Task {
+        task_path: String,
+        task_name: String,
+    },
+}
+
+impl ChangeSelector {
+    fn matches_registry(&self, reg: &RegistrySnapshot) -> bool {
+        match self {
+            Self::Registry {
+                hive,
+                key,
+                value_name,
+            } => {
+                hive.eq_ignore_ascii_case(&reg.hive)
+                    && key.trim_matches('\\').eq_ignore_ascii_case(&reg.key)
+                    && match value_name {
+                        Some(value_name) => {
+                            !reg.key_level && value_name.eq_ignore_ascii_case(&reg.value_name)
+                        }
+                        None => reg.key_level,
+                    }
+            }
+            _ => false,
+        }
+    }
+
+    fn matches_service(&self, svc: &ServiceSnapshot) -> bool {
+        matches!(self, Self::Service { name } if name.eq_ignore_ascii_case(&svc.name))
+    }
+
+    fn matches_task(&self, task: &SchedulerSnapshot) -> bool {
+        match self {
+            Self::Task {
+                task_path,
+                task_name,
+            } => {
+                task_path
+                    .trim_end_matches('\\')
+                    .eq_ignore_ascii_case(task.task_path.trim_end_matches('\\'))
+                    && task_name.eq_ignore_ascii_case(&task.task_name)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Restore only the chosen registry values/keys, services, and scheduled tasks of a tweak's
+/// snapshot, e.g. one value Windows Update put back while the rest of the tweak should stay. Every
+/// selector has to match something in the snapshot. Like a full restore, every item is attempted
+/// and failures are collected; the snapshot itself is left as it is.
+pub fn restore_changes(
+    tweak_id: &str,
+    selectors: &[ChangeSelector],
+) -> Result<RestoreResult, Error> {
+    let snapshot = storage::load_snapshot(tweak_id)?
+        .ok_or_else(|| Error::NotFound(format!("No snapshot for tweak '{}'", tweak_id)))?;
+
+    let registry: Vec<&RegistrySnapshot> = snapshot
+        .registry_snapshots
+        .iter()
+        .filter(|reg| selectors.iter().any(|s| s.matches_registry(reg)))
+        .collect();
+    let services: Vec<&ServiceSnapshot> = snapshot
+        .service_snapshots
+        .iter()
+        .filter(|svc| selectors.iter().any(|s| s.matches_service(svc)))
+        .collect();
+    let tasks: Vec<&SchedulerSnapshot> = snapshot
+        .scheduler_snapshots
+        .iter()
+        .filter(|task| selectors.iter().any(|s| s.matches_task(task)))
+        .collect();
+
+    if let Some(unmatched) = selectors.iter().find(|selector| {
+        !registry.iter().any(|reg| selector.matches_registry(reg))
+            && !services.iter().any(|svc| selector.matches_service(svc))
+            && !tasks.iter().any(|task| selector.matches_task(task))
+    }) {
+        return Err(Error::ValidationError(format!(
+            "The snapshot of '{}' has nothing matching {:?}",
+            tweak_id, unmatched
+        )));
+    }
+
+    log::info!(
+        "Restoring {} registry, {} services, {} tasks of the snapshot for '{}'",
+        registry.len(),
+        services.len(),
+        tasks.len(),
+        snapshot.tweak_name
+    );
+
+    // Same order and failure handling as `restore_from_snapshot`
+    let mut failures: Vec<String> = Vec::new();
+    for reg in &registry {
+        if let Err(e) = restore_one_registry(reg, uses_system(reg.elevation, snapshot)) {
+            failures.push(format!(
+                "Registry '{}\\{}\\{}': {}",
+                reg.hive, reg.key, reg.value_name, e
+            ));
+        }
+    }
+    if registry.iter().any(|reg| {
+        reg.hive == RegistryHive::Hklm.as_str() && time_service::is_w32time_key(&reg.key)
+    }) {
+        if let Err(e) = time_service::update_config() {
+            log::warn!("Failed to reload Windows Time configuration: {}", e);
+        }
+    }
+    for svc in services.iter().rev() {
+        if let Err(e) = restore_service_state(svc, uses_system(svc.elevation, snapshot)) {
+            failures.push(format!("Service '{}': {}", svc.name, e));
+        }
+    }
+    for task in &tasks {
+        if let Err(e) = restore_scheduler_state(task, uses_system(task.elevation, snapshot)) {
+            failures.push(format!(
+                "Task '{}\\{}': {}",
+                task.task_path, task.task_name, e
+            ));
+        }
+    }
+
+    for failure in &failures {
+        log::error!("Failed to restore {}", failure);
+    }
+    Ok(RestoreResult {
+        success: failures.is_empty(),
+        failures,
+    })
+}
+
 #[derive(Clone)]
 struct RegistryRestoreOp {
     hive: RegistryHive,
@@ -131,9 +445,20 @@ struct RegistryRestoreOp {
     existed: bool,
 }
 
-/// Restore a single registry value from its snapshot.
-fn restore_one_registry(reg: &RegistrySnapshot, use_system: bool) -> Result<(), Error> {
+/// Whether an entry is restored elevated: as its change declared, or as the whole tweak was
+/// applied. TrustedInstaller-level changes are restored as SYSTEM, like TI tweaks.
+fn uses_system(declared: Option<ChangeElevation>, snapshot: &TweakSnapshot) -> bool {
+    declared.map_or(snapshot.requires_system, |elevation| {
+        elevation.level().is_elevated()
+    })
+}
+
+/// Restore a single registry value (or, for a key-level snapshot, key) from its snapshot.
+pub(crate) fn restore_one_registry(reg: &RegistrySnapshot, use_system: bool) -> Result<(), Error> {
     let hive = parse_hive(&reg.hive)?;
+    if reg.key_level {
+        return restore_registry_key(reg, hive, use_system);
+    }
     let op = RegistryRestoreOp {
         hive,
         key: reg.key.clone(),
@@ -194,6 +519,83 @@ fn execute_registry_restore(op: &RegistryRestoreOp, use_system: bool) -> Result<
     }
 }
 
+/// Restore a key-level snapshot: delete a key the tweak created, or recreate a key it deleted
+/// together with its captured subkeys and values.
+fn restore_registry_key(
+    reg: &RegistrySnapshot,
+    hive: RegistryHive,
+    use_system: bool,
+) -> Result<(), Error> {
+    let elevated = use_system && hive.requires_admin();
+    if !reg.existed {
+        log::debug!(
+            "Deleting key {}\\{} (didn't exist originally)",
+            hive.as_str(),
+            reg.key
+        );
+        if elevated {
+            return trusted_installer::delete_registry_key_as_system(hive, &reg.key);
+        }
+        return match registry_service::delete_key(&hive, &reg.key) {
+            Err(Error::RegistryKeyNotFound(_)) => Ok(()),
+            other => other,
+        };
+    }
+
+    match &reg.key_contents {
+        Some(contents) => write_key_contents(&hive, &reg.key, contents, use_system),
+        None if elevated => trusted_installer::create_registry_key_as_system(hive, &reg.key),
+        None => registry_service::create_key(&hive, &reg.key),
+    }
+}
+
+/// Recreate a registry key with the subkeys and values captured by
+/// [`super::capture::read_key_contents`]. Existing values with the same names are overwritten.
+pub fn write_key_contents(
+    hive: &RegistryHive,
+    key: &str,
+    contents: &RegistryKeyContents,
+    use_system: bool,
+) -> Result<(), Error> {
+    log::debug!(
+        "Recreating key {}\\{} ({} subkeys, {} values)",
+        hive.as_str(),
+        key,
+        contents.subkeys.len(),
+        contents.values.len()
+    );
+    let elevated = use_system && hive.requires_admin();
+    let path_of = |subkey: &str| {
+        if subkey.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}\\{}", key, subkey)
+        }
+    };
+
+    for path in std::iter::once(key.to_string()).chain(contents.subkeys.iter().map(|s| path_of(s)))
+    {
+        if elevated {
+            trusted_installer::create_registry_key_as_system(*hive, &path)?;
+        } else {
+            registry_service::create_key(hive, &path)?;
+        }
+    }
+
+    for value in &contents.values {
+        let value_type = parse_value_type(&value.value_type)?;
+        registry_value::write_registry_json_value(
+            hive,
+            &path_of(&value.subkey),
+            &value.name,
+            &value_type,
+            &value.value,
+            use_system,
+        )?;
+    }
+    Ok(())
+}
+
 fn restore_registry_normal(
     hive: &RegistryHive,
     key: &str,
@@ -281,6 +683,25 @@ fn restore_scheduler_state(snapshot: &SchedulerSnapshot, use_system: bool) -> Re
         trusted_installer::Elevation::None
     };
 
+    // A task the change deleted is registered again from its captured definition first
+    let existed = matches!(
+        snapshot.original_state.as_str(),
+        "Ready" | "Running" | "Disabled"
+    );
+    if let (true, Some(xml)) = (existed, &snapshot.definition_xml) {
+        if scheduler_service::get_task_state(&snapshot.task_path, &snapshot.task_name)?
+            == scheduler_service::TaskState::NotFound
+        {
+            trusted_installer::run_scheduler_register(
+                level,
+                &snapshot.task_path,
+                &snapshot.task_name,
+                xml,
+            )?;
+            log::info!("Re-registered deleted scheduled task: {}", task_path);
+        }
+    }
+
     match snapshot.original_state.as_str() {
         "Ready" | "Running" => {
             // Task was enabled — re-enable it.
@@ -303,10 +724,9 @@ fn restore_scheduler_state(snapshot: &SchedulerSnapshot, use_system: bool) -> Re
             log::info!("Disabled scheduled task: {}", task_path);
         }
         "NotFound" => {
-            // Task didn't exist before - we can't restore a deleted task
-            // This is expected if the tweak was a "delete" action
+            // Task didn't exist before the tweak - nothing to restore
             log::info!(
-                "Task '{}' was not found before tweak, cannot restore (expected for delete actions)",
+                "Task '{}' was not found before tweak, nothing to restore",
                 task_path
             );
         }
@@ -374,13 +794,305 @@ fn restore_firewall_state(snapshot: &FirewallSnapshot) -> Result<(), Error> {
     Ok(())
 }
 
+/// Human-readable name of a power plan snapshot for failure messages.
+fn power_plan_label(snapshot: &PowerPlanSnapshot) -> String {
+    match &snapshot.setting {
+        Some(setting) => format!("{}\\{}", snapshot.scheme, setting),
+        None => format!("active scheme {}", snapshot.scheme),
+    }
+}
+
+fn restore_power_plan_state(snapshot: &PowerPlanSnapshot) -> Result<(), Error> {
+    match (&snapshot.subgroup, &snapshot.setting) {
+        (Some(subgroup), Some(setting)) => {
+            power_service::write_setting(
+                &snapshot.scheme,
+                subgroup,
+                setting,
+                snapshot.ac_value,
+                snapshot.dc_value,
+            )?;
+            log::info!(
+                "Restored power setting {} (ac={:?}, dc={:?})",
+                power_plan_label(snapshot),
+                snapshot.ac_value,
+                snapshot.dc_value
+            );
+        }
+        _ => {
+            if power_service::get_active_scheme()? != snapshot.scheme {
+                power_service::set_active_scheme(&snapshot.scheme)?;
+                log::info!("Restored active power scheme: {}", snapshot.scheme);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn restore_bcdedit_state(snapshot: &BcdeditSnapshot) -> Result<(), Error> {
+    match (&snapshot.value, snapshot.existed) {
+        (Some(value), true) => {
+            let current = bcdedit_service::get_value(&snapshot.identifier, &snapshot.element)?;
+            if !current.is_some_and(|c| bcdedit_service::values_equal(value, &c)) {
+                bcdedit_service::set_value(&snapshot.identifier, &snapshot.element, value)?;
+                log::info!(
+                    "Restored BCD element {} {} = {}",
+                    snapshot.identifier,
+                    snapshot.element,
+                    value
+                );
+            }
+        }
+        _ => {
+            // Element wasn't set before - remove it so the boot loader default applies again
+            bcdedit_service::delete_value(&snapshot.identifier, &snapshot.element)?;
+        }
+    }
+    Ok(())
+}
+
+/// Restores what it can, then fails if the package is still missing somewhere revert can't reach:
+/// other users' accounts (registration only reaches the current user) or the Windows image
+/// (re-provisioning needs the original package file, which the snapshot can't hold). The revert
+/// then reports the tweak as not restored instead of quietly leaving it half-reverted.
+fn restore_appx_state(snapshot: &AppxSnapshot) -> Result<(), Error> {
+    let scope = match snapshot.scope.as_str() {
+        "all_users" => AppxScope::AllUsers,
+        _ => AppxScope::CurrentUser,
+    };
+    let current = appx_service::list_installed(&snapshot.package, scope)?;
+    let mut unrestored = Vec::new();
+
+    if snapshot.installed.is_empty() {
+        // Package wasn't installed before - remove it if it is now (e.g. reverting a reinstall)
+        if !current.is_empty() {
+            appx_service::remove_package(&snapshot.package, scope)?;
+        }
+    } else {
+        for record in &snapshot.installed {
+            if current.iter().any(|c| c.full_name == record.full_name) {
+                continue;
+            }
+            let location = record.install_location.as_deref().ok_or_else(|| {
+                Error::CommandExecution(format!(
+                    "'{}' has no recorded install location to re-register from",
+                    record.full_name
+                ))
+            })?;
+            appx_service::register_package(location)?;
+            if scope == AppxScope::AllUsers {
+                unrestored.push(format!(
+                    "'{}' was re-registered for the current user only; other users must reinstall it",
+                    record.full_name
+                ));
+            }
+        }
+    }
+
+    if let Some(provisioned) = snapshot.provisioned.as_ref().filter(|p| !p.is_empty()) {
+        let currently_provisioned = appx_service::list_provisioned(&snapshot.package)?;
+        for name in provisioned {
+            if !currently_provisioned.contains(name) {
+                unrestored.push(format!(
+                    "provisioned package '{}' was removed and can't be re-provisioned without the original package file",
+                    name
+                ));
+            }
+        }
+    }
+
+    if !unrestored.is_empty() {
+        return Err(Error::CommandExecution(unrestored.join("; ")));
+    }
+
+    Ok(())
+}
+
+fn restore_file_state(snapshot: &FileSnapshot) -> Result<(), Error> {
+    if let Some(moved_to) = &snapshot.moved_to {
+        // Renamed away - move it back unless something already took its place
+        if !file_service::path_exists(&snapshot.path) && file_service::path_exists(moved_to) {
+            std::fs::rename(moved_to, &snapshot.path).map_err(|e| {
+                Error::CommandExecution(format!("Failed to rename '{}' back: {}", moved_to, e))
+            })?;
+            log::info!("Renamed '{}' back to '{}'", moved_to, snapshot.path);
+        }
+        return Ok(());
+    }
+
+    if !snapshot.existed {
+        // Path didn't exist before - remove whatever the tweak created
+        if file_service::path_exists(&snapshot.path) {
+            file_service::remove_path(&snapshot.path)?;
+            log::info!("Deleted '{}' (didn't exist originally)", snapshot.path);
+        }
+        return Ok(());
+    }
+
+    if let Some(backup_rel) = &snapshot.backup_path {
+        let backup = storage::get_snapshots_dir()?.join(backup_rel);
+        if !backup.exists() {
+            return Err(Error::NotFound(format!(
+                "File backup '{}' is missing",
+                backup.display()
+            )));
+        }
+        file_service::remove_path(&snapshot.path)?;
+        file_service::copy_path(&backup, std::path::Path::new(&snapshot.path))?;
+        log::info!("Restored '{}' from backup", snapshot.path);
+    } else if !file_service::path_exists(&snapshot.path) {
+        log::warn!(
+            "'{}' existed before but is now missing; it was not backed up (skip_backup)",
+            snapshot.path
+        );
+        return Ok(());
+    }
+
+    if let Some(attributes) = snapshot.attributes {
+        file_service::set_attributes(&snapshot.path, attributes)?;
+        log::info!("Restored attributes of '{}'", snapshot.path);
+    }
+    Ok(())
+}
+
+fn restore_feature_state(snapshot: &FeatureSnapshot) -> Result<(), Error> {
+    let Some(was_enabled) = snapshot.enabled else {
+        // Feature didn't exist on this edition, so the tweak couldn't have changed it
+        return Ok(());
+    };
+
+    match dism_service::is_enabled(&snapshot.feature)? {
+        Some(enabled) if enabled == was_enabled => Ok(()),
+        Some(_) if was_enabled => dism_service::enable_feature(&snapshot.feature),
+        Some(_) => dism_service::disable_feature(&snapshot.feature),
+        None => Err(Error::NotFound(format!(
+            "Optional feature '{}' is no longer available",
+            snapshot.feature
+        ))),
+    }
+}
+
+fn restore_netadapter_state(snapshot: &NetAdapterSnapshot) -> Result<(), Error> {
+    let current = network_service::get_properties(&snapshot.adapter, &snapshot.keyword)?;
+    let Some(current) = current.into_iter().find(|p| p.name == snapshot.adapter) else {
+        return Err(Error::NotFound(format!(
+            "Adapter '{}' no longer has property '{}'",
+            snapshot.adapter, snapshot.keyword
+        )));
+    };
+
+    if current.value == snapshot.value {
+        return Ok(());
+    }
+    match &snapshot.value {
+        Some(value) => network_service::set_property(&snapshot.adapter, &snapshot.keyword, value),
+        None => network_service::reset_property(&snapshot.adapter, &snapshot.keyword),
+    }
+}
+
+/// Put a policy value's Registry.pol record back, returning the scope to refresh.
+fn restore_policy_state(snapshot: &PolicySnapshot) -> Result<PolicyScope, Error> {
+    let scope = parse_policy_scope(&snapshot.scope)?;
+
+    if snapshot.pol_captured {
+        let current = policy_service::get_entry(scope, &snapshot.key, &snapshot.value_name)?;
+        if current != snapshot.pol_entry {
+            policy_service::set_entry(
+                scope,
+                &snapshot.key,
+                &snapshot.value_name,
+                snapshot.pol_entry.clone(),
+            )?;
+        }
+    }
+    Ok(scope)
+}
+
+fn restore_app_state(
+    snapshot: &AppSnapshot,
+    tweak_id: &str,
+    index: usize,
+    total: usize,
+) -> Result<(), Error> {
+    if package_service::is_installed(&snapshot.id)? == snapshot.installed {
+        return Ok(());
+    }
+
+    let action = if snapshot.installed {
+        AppAction::Install
+    } else {
+        AppAction::Uninstall
+    };
+    let progress = AppProgress::new(tweak_id, &snapshot.id, action, index, total);
+    progress.emit(AppProgressStage::Started, None);
+
+    let result = match action {
+        AppAction::Install => {
+            package_service::install(&snapshot.id, snapshot.source.as_deref(), None)
+        }
+        AppAction::Uninstall => package_service::uninstall(&snapshot.id, None),
+    };
+    match &result {
+        Ok(_) => progress.emit(AppProgressStage::Finished, None),
+        Err(e) => progress.emit(AppProgressStage::Failed, Some(e.to_string())),
+    }
+    result.map(|_| ())
+}
+
+fn restore_registry_acl_state(snapshot: &RegistryAclSnapshot) -> Result<(), Error> {
+    let Some(sddl) = &snapshot.sddl else {
+        // Key didn't exist before - its descriptor goes away with the key itself
+        return Ok(());
+    };
+    let hive = parse_hive(&snapshot.hive)?;
+
+    if registry_acl_service::get_sddl(hive, &snapshot.key)?.as_deref() != Some(sddl.as_str()) {
+        registry_acl_service::set_sddl(hive, &snapshot.key, sddl)?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn change_selectors_tell_values_from_keys() {
+        let value: ChangeSelector = serde_json::from_value(serde_json::json!({
+            "kind": "registry",
+            "hive": "hkcu",
+            "key": "software\\x",
+            "value_name": "v",
+        }))
+        .unwrap();
+        let key: ChangeSelector = serde_json::from_value(serde_json::json!({
+            "kind": "registry",
+            "hive": "HKCU",
+            "key": "Software\\X",
+        }))
+        .unwrap();
+        let mut reg = RegistrySnapshot {
+            hive: "HKCU".to_string(),
+            key: "Software\\X".to_string(),
+            value_name: "V".to_string(),
+            value_type: Some("REG_DWORD".to_string()),
+            value: Some(serde_json::json!(1)),
+            existed: true,
+            key_level: false,
+            key_contents: None,
+            elevation: None,
+        };
+
+        assert!(value.matches_registry(&reg));
+        assert!(!key.matches_registry(&reg));
+        reg.key_level = true;
+        assert!(!value.matches_registry(&reg));
+        assert!(key.matches_registry(&reg));
+    }
+
     #[test]
     fn a_failed_phase_does_not_abort_the_remaining_phases() {
-        // ADR-0001: rollback attempts all five phases and collects failures. A registry restore that
+        // ADR-0001: rollback attempts every phase and collects failures. A registry restore that
         // fails must not abandon the service phase — previously the registry phase rolled back and
         // returned Err, abandoning services / scheduler / hosts / firewall entirely.
         let mut snap = TweakSnapshot::new("__wp4_test", "T", 0, "opt", 11, false, None);
@@ -393,12 +1105,16 @@ mod tests {
             value_type: Some("REG_DWORD".to_string()),
             value: Some(serde_json::json!(1)),
             existed: true,
+            key_level: false,
+            key_contents: None,
+            elevation: None,
         });
         // A service op for a service that does not exist — this later phase must still be attempted.
         snap.service_snapshots.push(ServiceSnapshot {
             name: "MagicXNoSuchService_wp4".to_string(),
             startup_type: "manual".to_string(),
             was_running: false,
+            elevation: None,
         });
 
         let result = restore_from_snapshot(&snap).unwrap();
@@ -18,6 +18,7 @@ use crate::models::{
     RegistryAction, RegistryChange, RegistryHive, RegistryValueType, RiskLevel, TweakDefinition,
     TweakOption,
 };
+use crate::services::backup::storage::snapshot_from_json;
 use crate::services::backup::{
     capture_snapshot, delete_snapshot, detect_tweak_state, restore_from_snapshot, save_snapshot,
 };
@@ -73,22 +74,57 @@ fn dword_change(key: &str, name: &str, value: u32) -> RegistryChange {
         value_type: Some(RegistryValueType::Dword),
         value: Some(serde_json::json!(value)),
         windows_versions: None,
+        conditions: None,
+        requires_reboot: false,
+        requires_logoff: false,
         skip_validation: false,
+        elevation: None,
+    }
+}
+
+fn multi_string_change(key: &str, name: &str, value: &[&str]) -> RegistryChange {
+    RegistryChange {
+        value_type: Some(RegistryValueType::MultiString),
+        value: Some(serde_json::json!(value)),
+        ..dword_change(key, name, 0)
     }
 }
 
 fn option(label: &str, registry_changes: Vec<RegistryChange>) -> TweakOption {
     TweakOption {
         label: label.to_string(),
+        conditions: None,
         registry_changes,
         service_changes: Vec::new(),
         scheduler_changes: Vec::new(),
         hosts_changes: Vec::new(),
         firewall_changes: Vec::new(),
+        power_plan_changes: Vec::new(),
+        bcdedit_changes: Vec::new(),
+        appx_changes: Vec::new(),
+        file_changes: Vec::new(),
+        registry_acl_changes: Vec::new(),
+        feature_changes: Vec::new(),
+        netadapter_changes: Vec::new(),
+        policy_changes: Vec::new(),
+        app_changes: Vec::new(),
+        mmcss_changes: Vec::new(),
+        context_menu_changes: Vec::new(),
+        audio_changes: Vec::new(),
+        visual_effects_changes: Vec::new(),
+        browser_policy_changes: Vec::new(),
+        time_changes: Vec::new(),
+        component_changes: Vec::new(),
         pre_commands: Vec::new(),
         post_commands: Vec::new(),
         pre_powershell: Vec::new(),
         post_powershell: Vec::new(),
+        shell: None,
+        command_timeout_secs: None,
+        state_probes: StateProbes::default(),
+        requires_reboot: false,
+        requires_logoff: false,
+        restart_explorer: false,
         registry_missing_is_match: false,
         service_missing_is_match: false,
         scheduler_missing_is_match: false,
@@ -109,6 +145,14 @@ fn tweak(id: &str, options: Vec<TweakOption>) -> TweakDefinition {
         force_dropdown: false,
         options,
         category_id: "test".to_string(),
+        custom: false,
+        deprecated: false,
+        superseded_by: None,
+        aliases: Vec::new(),
+        min_build: None,
+        max_build: None,
+        editions: None,
+        tags: Vec::new(),
     }
 }
 
@@ -263,6 +307,51 @@ fn switching_options_leaves_the_original_snapshot_intact() {
     );
 }
 
+#[test]
+fn a_value_already_in_place_is_left_alone_and_the_rest_applied() {
+    let s = Scratch::new("rt_partly_in_place");
+    let t = tweak(
+        &s.tweak_id,
+        vec![
+            option(
+                "On",
+                vec![
+                    dword_change(&s.key, "Kept", 1),
+                    dword_change(&s.key, "Added", 1),
+                ],
+            ),
+            option("Off", vec![dword_change(&s.key, "Kept", 0)]),
+        ],
+    );
+
+    // One of the option's values is already what it writes, so only the other one is written
+    registry_service::set_dword(&RegistryHive::Hkcu, &s.key, "Kept", 1).expect("seed");
+
+    let snapshot = capture_snapshot(&t, 0, 11, None).expect("capture");
+    save_snapshot(&snapshot).expect("save");
+    apply(&t, 0);
+    assert_eq!(
+        detect_tweak_state(&t, 11).unwrap().current_option_index,
+        Some(0)
+    );
+
+    let result = restore_from_snapshot(&snapshot).expect("restore");
+    assert!(
+        result.success,
+        "restore reported failures: {:?}",
+        result.failures
+    );
+    assert_eq!(
+        registry_service::read_dword(&RegistryHive::Hkcu, &s.key, "Kept").unwrap(),
+        Some(1),
+        "revert changed a value the tweak found already in place"
+    );
+    assert!(
+        !registry_service::value_exists(&RegistryHive::Hkcu, &s.key, "Added").unwrap_or(false),
+        "revert left behind the value the tweak added"
+    );
+}
+
 #[test]
 fn a_key_created_by_the_tweak_is_removed_by_revert() {
     let s = Scratch::new("rt_created_key");
@@ -292,3 +381,85 @@ fn a_key_created_by_the_tweak_is_removed_by_revert() {
         "revert left behind a value in a key the tweak created"
     );
 }
+
+#[test]
+fn a_multi_string_value_is_applied_detected_and_restored() {
+    let s = Scratch::new("rt_multi_string");
+    let t = tweak(
+        &s.tweak_id,
+        vec![
+            option("On", vec![multi_string_change(&s.key, "List", &["a", "b"])]),
+            // An empty list must still be told apart from an absent value
+            option("Off", vec![multi_string_change(&s.key, "List", &[])]),
+        ],
+    );
+
+    let original = vec!["original".to_string()];
+    registry_service::set_multi_string(&RegistryHive::Hkcu, &s.key, "List", &original)
+        .expect("seed");
+    assert_eq!(
+        detect_tweak_state(&t, 11).unwrap().current_option_index,
+        None
+    );
+
+    let snapshot = capture_snapshot(&t, 0, 11, None).expect("capture");
+    save_snapshot(&snapshot).expect("save");
+    apply(&t, 0);
+    assert_eq!(
+        registry_service::read_multi_string(&RegistryHive::Hkcu, &s.key, "List").unwrap(),
+        Some(vec!["a".to_string(), "b".to_string()])
+    );
+    assert_eq!(
+        detect_tweak_state(&t, 11).unwrap().current_option_index,
+        Some(0)
+    );
+
+    apply(&t, 1);
+    assert_eq!(
+        detect_tweak_state(&t, 11).unwrap().current_option_index,
+        Some(1)
+    );
+
+    let result = restore_from_snapshot(&snapshot).expect("restore");
+    assert!(
+        result.success,
+        "restore reported failures: {:?}",
+        result.failures
+    );
+    assert_eq!(
+        registry_service::read_multi_string(&RegistryHive::Hkcu, &s.key, "List").unwrap(),
+        Some(original),
+        "revert did not restore the original REG_MULTI_SZ contents"
+    );
+}
+
+#[test]
+fn a_snapshot_written_before_schema_versioning_still_restores() {
+    let s = Scratch::new("rt_fixture_v0");
+    // The fixture's keys sit under SCRATCH_ROOT with a placeholder for the per-test subkey
+    let subkey = s.key.rsplit('\\').next().unwrap();
+    let json = include_str!("fixtures/snapshot_v0.json")
+        .replace("fixture_v0", &s.tweak_id)
+        .replace("__SCRATCH__", subkey);
+    let snapshot = snapshot_from_json(serde_json::from_str(&json).unwrap()).expect("migrate");
+
+    // The state the old tweak left behind: Flag changed from 7, Added created
+    registry_service::set_dword(&RegistryHive::Hkcu, &s.key, "Flag", 1).expect("seed");
+    registry_service::set_dword(&RegistryHive::Hkcu, &s.key, "Added", 1).expect("seed");
+
+    let result = restore_from_snapshot(&snapshot).expect("restore");
+    assert!(
+        result.success,
+        "restore reported failures: {:?}",
+        result.failures
+    );
+    assert_eq!(
+        registry_service::read_dword(&RegistryHive::Hkcu, &s.key, "Flag").unwrap(),
+        Some(7),
+        "a migrated snapshot did not restore the original value"
+    );
+    assert!(
+        !registry_service::value_exists(&RegistryHive::Hkcu, &s.key, "Added").unwrap_or(false),
+        "a migrated snapshot did not remove a value that didn't exist before"
+    );
+}
@@ -0,0 +1,438 @@
+//! Export of the applied tweaks as a standalone PowerShell script plus `.reg` files.
+//!
+//! The script reapplies each tweak's recorded option without the app, for machines it isn't
+//! installed on: registry changes are written to one `.reg` file per tweak and imported with
+//! `reg import`; service and scheduled task changes and the option's commands become PowerShell
+//! lines. The rest of the change types (hosts, firewall, power plans, BCD, AppX, files, ACLs,
+//! features, adapters, policies, apps) need the app's services, so they're listed in the script
+//! and in the report instead. HKCU and ALL_USERS values go to whoever runs the script.
+
+use crate::error::Error;
+use crate::models::{
+    ChangeElevation, CommandShell, RegistryAction, RegistryChange, RegistryHive, SchedulerAction,
+    SchedulerChange, ServiceChange, TweakDefinition, TweakOption,
+};
+use crate::services::elevation::{Elevation, PowerShellEngine};
+use crate::services::powershell::ps_quote;
+use crate::services::registry_value::parse_registry_value;
+use crate::services::{registry_service, system_info_service, tweak_loader};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+use super::export::{key_path, reg_data, write_reg_file, RegWriter, REG_FILE_HEADER};
+use super::storage::{get_applied_tweaks_in_revert_order, load_snapshot};
+
+/// Name of the script in the export directory
+const SCRIPT_FILE: &str = "apply.ps1";
+
+/// Folder (in the export directory) holding each tweak's `.reg` file
+const REG_DIR: &str = "registry";
+
+/// What an export wrote
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScriptExportReport {
+    /// Tweaks written to the script, in the order it applies them
+    pub exported: Vec<String>,
+    /// Exported tweaks with changes the script can't make, e.g. "2 hosts changes"
+    pub partial: Vec<PartialScriptExport>,
+    /// Applied tweaks left out: their definition is gone or their snapshot can't be read
+    pub skipped: Vec<String>,
+}
+
+/// The changes of an exported tweak that the script leaves out
+#[derive(Debug, Clone, Serialize)]
+pub struct PartialScriptExport {
+    pub tweak_id: String,
+    pub unsupported: Vec<String>,
+}
+
+/// Write a script reapplying every applied tweak's option to `dir`, with the `.reg` files it
+/// imports beside it. Tweaks are applied in the order they were applied here, oldest first.
+pub fn export_applied_tweaks_as_script(dir: &Path) -> Result<ScriptExportReport, Error> {
+    let reg_dir = dir.join(REG_DIR);
+    fs::create_dir_all(&reg_dir).map_err(|e| {
+        Error::BackupFailed(format!("Failed to create {}: {}", reg_dir.display(), e))
+    })?;
+
+    let version = system_info_service::get_runtime_context()?.windows_version();
+    let mut report = ScriptExportReport::default();
+    let mut script = ScriptWriter::default();
+    script.header(version);
+
+    let mut applied = get_applied_tweaks_in_revert_order()?;
+    applied.reverse();
+    for tweak_id in applied {
+        let (tweak, option_index) = match applied_option(&tweak_id) {
+            Ok(Some(applied)) => applied,
+            Ok(None) => {
+                log::warn!("Not exporting '{}': its tweak no longer exists", tweak_id);
+                report.skipped.push(tweak_id);
+                continue;
+            }
+            Err(e) => {
+                log::warn!("Not exporting '{}': {}", tweak_id, e);
+                report.skipped.push(tweak_id);
+                continue;
+            }
+        };
+        let option = &tweak.options[option_index];
+
+        let changes: Vec<RegistryChange> = registry_service::mapped_registry_changes(option)?
+            .into_iter()
+            .filter(|change| change.applies_to(version, system_info_service::system_facts()))
+            .collect();
+        let reg_file = (!changes.is_empty())
+            .then(|| -> Result<String, Error> {
+                let name = format!("{}.reg", tweak.id);
+                write_reg_file(
+                    &reg_dir.join(&name),
+                    &changes_to_reg(&tweak, option, &changes)?,
+                )?;
+                Ok(name)
+            })
+            .transpose()?;
+
+        let unsupported = unsupported_changes(option);
+        script.tweak(&tweak, option, &changes, reg_file.as_deref(), &unsupported);
+        if !unsupported.is_empty() {
+            report.partial.push(PartialScriptExport {
+                tweak_id: tweak.id.clone(),
+                unsupported,
+            });
+        }
+        report.exported.push(tweak.id);
+    }
+
+    // UTF-8 with a BOM, so Windows PowerShell doesn't read it as the ANSI code page
+    let path = dir.join(SCRIPT_FILE);
+    let bytes: Vec<u8> = "\u{FEFF}".bytes().chain(script.text.into_bytes()).collect();
+    fs::write(&path, bytes)
+        .map_err(|e| Error::BackupFailed(format!("Failed to write {}: {}", path.display(), e)))?;
+
+    log::info!(
+        "Exported {} applied tweaks as a script to {} ({} partial, {} skipped)",
+        report.exported.len(),
+        dir.display(),
+        report.partial.len(),
+        report.skipped.len()
+    );
+    Ok(report)
+}
+
+/// The definition of an applied tweak and the option its snapshot records, or `None` if the tweak
+/// no longer has that option
+fn applied_option(tweak_id: &str) -> Result<Option<(TweakDefinition, usize)>, Error> {
+    let snapshot = load_snapshot(tweak_id)?
+        .ok_or_else(|| Error::NotFound(format!("No snapshot for tweak '{}'", tweak_id)))?;
+    Ok(tweak_loader::get_tweak(tweak_id)?
+        .filter(|tweak| snapshot.applied_option_index < tweak.options.len())
+        .map(|tweak| (tweak, snapshot.applied_option_index)))
+}
+
+/// Render an option's registry changes as `.reg` text (CRLF line endings)
+fn changes_to_reg(
+    tweak: &TweakDefinition,
+    option: &TweakOption,
+    changes: &[RegistryChange],
+) -> Result<String, Error> {
+    let mut writer = RegWriter::default();
+    writer.line(REG_FILE_HEADER);
+    writer.line("");
+    writer.line(&format!(
+        "; \"{}\" ({}): {}",
+        tweak.name, tweak.id, option.label
+    ));
+
+    for change in changes {
+        // ALL_USERS has no .reg root; the script writes it for the user running it, like HKCU
+        let hive = match change.hive {
+            RegistryHive::AllUsers => RegistryHive::Hkcu,
+            hive => hive,
+        };
+        let key = key_path(&hive, &change.key);
+        match change.action {
+            RegistryAction::Set => {
+                let (Some(value_type), Some(value)) = (&change.value_type, &change.value) else {
+                    return Err(Error::ValidationError(format!(
+                        "Registry change on {} has no type or value",
+                        key
+                    )));
+                };
+                let data = reg_data(parse_registry_value(value_type, value)?);
+                writer.value(&key, &change.value_name, &data);
+            }
+            RegistryAction::DeleteValue => writer.value(&key, &change.value_name, "-"),
+            RegistryAction::DeleteKey => writer.delete_key(&key),
+            RegistryAction::CreateKey => writer.key(&key),
+        }
+    }
+
+    writer.line("");
+    Ok(writer.text)
+}
+
+/// The option's changes the script can't make, as counts per change type
+fn unsupported_changes(option: &TweakOption) -> Vec<String> {
+    [
+        (option.hosts_changes.len(), "hosts"),
+        (option.firewall_changes.len(), "firewall"),
+        (option.power_plan_changes.len(), "power plan"),
+        (option.bcdedit_changes.len(), "BCD"),
+        (option.appx_changes.len(), "AppX"),
+        (option.file_changes.len(), "file"),
+        (option.registry_acl_changes.len(), "registry ACL"),
+        (option.feature_changes.len(), "optional feature"),
+        (option.netadapter_changes.len(), "network adapter"),
+        (option.all_policy_changes().len(), "policy"),
+        (option.app_changes.len(), "app"),
+    ]
+    .into_iter()
+    .filter(|(count, _)| *count > 0)
+    .map(|(count, kind)| {
+        format!(
+            "{} {} change{}",
+            count,
+            kind,
+            if count == 1 { "" } else { "s" }
+        )
+    })
+    .collect()
+}
+
+/// Accumulates the script's lines (CRLF line endings)
+#[derive(Default)]
+struct ScriptWriter {
+    text: String,
+}
+
+impl ScriptWriter {
+    fn line(&mut self, line: &str) {
+        self.text.push_str(line);
+        self.text.push_str("\r\n");
+    }
+
+    fn header(&mut self, version: u32) {
+        self.line("#Requires -RunAsAdministrator");
+        self.line(&format!(
+            "# Tweaks applied by MagicX Toolbox on Windows {}, exported {}",
+            version,
+            chrono::Local::now().to_rfc3339()
+        ));
+        self.line("# Run elevated: powershell -ExecutionPolicy Bypass -File apply.ps1");
+        self.line("# HKCU values are written for the user running the script.");
+        self.line("");
+        self.line("$ErrorActionPreference = 'Continue'");
+    }
+
+    fn tweak(
+        &mut self,
+        tweak: &TweakDefinition,
+        option: &TweakOption,
+        registry_changes: &[RegistryChange],
+        reg_file: Option<&str>,
+        unsupported: &[String],
+    ) {
+        self.line("");
+        self.line(&format!(
+            "# --- {} ({}): {}",
+            tweak.name, tweak.id, option.label
+        ));
+        self.line(&format!(
+            "Write-Host {}",
+            ps_quote(&format!("{}: {}", tweak.name, option.label))
+        ));
+
+        let services = option.all_service_changes();
+        let elevated = registry_changes
+            .iter()
+            .map(|change| change.elevation)
+            .chain(services.iter().map(|change| change.elevation))
+            .chain(
+                option
+                    .scheduler_changes
+                    .iter()
+                    .map(|change| change.elevation),
+            )
+            .any(|declared| {
+                ChangeElevation::resolve(declared, tweak.elevation()) != Elevation::None
+            });
+        if elevated {
+            self.line(
+                "# Some of these changes are made as SYSTEM or TrustedInstaller in the app; as an",
+            );
+            self.line("# administrator, Windows may deny them.");
+        }
+        if !unsupported.is_empty() {
+            self.line(&format!(
+                "# Not exported (apply them from the app): {}",
+                unsupported.join(", ")
+            ));
+        }
+
+        let shell = option.command_shell();
+        let engine = option.powershell_engine();
+        for command in &option.pre_commands {
+            self.command(command, shell);
+        }
+        for script in &option.pre_powershell {
+            self.powershell(script, engine);
+        }
+
+        if let Some(reg_file) = reg_file {
+            self.line(&format!(
+                "reg.exe import (Join-Path $PSScriptRoot {})",
+                ps_quote(&format!("{}\\{}", REG_DIR, reg_file))
+            ));
+        }
+        for change in &services {
+            self.service(change);
+        }
+        for change in &option.scheduler_changes {
+            self.scheduled_task(change);
+        }
+
+        for command in &option.post_commands {
+            self.command(command, shell);
+        }
+        for script in &option.post_powershell {
+            self.powershell(script, engine);
+        }
+    }
+
+    fn service(&mut self, change: &ServiceChange) {
+        let name = ps_quote(&change.name);
+        if change.stop_service {
+            self.line(&format!(
+                "Stop-Service -Name {} -Force -ErrorAction SilentlyContinue",
+                name
+            ));
+        }
+        self.line(&format!(
+            "sc.exe config {} start= {} | Out-Null",
+            name,
+            change.startup.to_sc_start_type()
+        ));
+        if change.start_service {
+            self.line(&format!(
+                "Start-Service -Name {} -ErrorAction SilentlyContinue",
+                name
+            ));
+        }
+    }
+
+    fn scheduled_task(&mut self, change: &SchedulerChange) {
+        // Get-ScheduledTask only matches a folder path with its trailing backslash
+        let task_path = ps_quote(&format!("{}\\", change.task_path.trim_end_matches('\\')));
+        let verb = match change.action {
+            SchedulerAction::Enable => "Enable-ScheduledTask",
+            SchedulerAction::Disable => "Disable-ScheduledTask",
+            SchedulerAction::Delete => "Unregister-ScheduledTask -Confirm:$false",
+        };
+        // A pattern wins over a name, as when the app applies the change
+        let tasks = match (&change.task_name_pattern, &change.task_name) {
+            (Some(pattern), _) => format!(
+                "Get-ScheduledTask -TaskPath {} -ErrorAction SilentlyContinue \
+                 | Where-Object {{ $_.TaskName -match {} }}",
+                task_path,
+                ps_quote(pattern)
+            ),
+            (None, Some(name)) => format!(
+                "Get-ScheduledTask -TaskPath {} -TaskName {} -ErrorAction SilentlyContinue",
+                task_path,
+                ps_quote(name)
+            ),
+            // Rejected when tweaks are built
+            (None, None) => return,
+        };
+        self.line(&format!("{} | {} | Out-Null", tasks, verb));
+    }
+
+    /// A `pre_commands` / `post_commands` entry, in the option's shell
+    fn command(&mut self, command: &str, shell: CommandShell) {
+        match shell {
+            // --% passes the rest of the line to cmd.exe untouched
+            CommandShell::Cmd if !command.contains(['\r', '\n']) => {
+                self.line(&format!("cmd.exe --% /d /c {}", command));
+            }
+            CommandShell::Cmd => {
+                self.line(&format!("cmd.exe /d /c {}", ps_quote(command)));
+            }
+            CommandShell::Powershell | CommandShell::Pwsh => {
+                self.powershell(command, shell.powershell_engine());
+            }
+        }
+    }
+
+    /// A PowerShell script, in its own scope so its variables don't leak into the next tweak's
+    fn powershell(&mut self, script: &str, engine: PowerShellEngine) {
+        match engine {
+            PowerShellEngine::Windows => self.line("& {"),
+            // Run from Windows PowerShell, pwsh receives the block as an encoded command
+            PowerShellEngine::Core => self.line("pwsh.exe -NoProfile -Command {"),
+        }
+        for line in script.lines() {
+            self.line(&format!("    {}", line));
+        }
+        self.line("}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scheduled_tasks_are_matched_by_name_or_pattern() {
+        let exact = SchedulerChange {
+            task_path: "\\Microsoft\\Windows\\Application Experience".to_string(),
+            task_name: Some("Microsoft Compatibility Appraiser".to_string()),
+            task_name_pattern: None,
+            action: SchedulerAction::Disable,
+            skip_validation: false,
+            ignore_not_found: false,
+            elevation: None,
+        };
+        let pattern = SchedulerChange {
+            task_path: "\\Microsoft\\Windows\\UpdateOrchestrator\\".to_string(),
+            task_name: None,
+            task_name_pattern: Some("USO|Reboot".to_string()),
+            action: SchedulerAction::Delete,
+            ..exact.clone()
+        };
+
+        let mut script = ScriptWriter::default();
+        script.scheduled_task(&exact);
+        script.scheduled_task(&pattern);
+        assert_eq!(
+            script.text.split("\r\n").collect::<Vec<_>>(),
+            vec![
+                "Get-ScheduledTask -TaskPath '\\Microsoft\\Windows\\Application Experience\\' \
+                 -TaskName 'Microsoft Compatibility Appraiser' -ErrorAction SilentlyContinue \
+                 | Disable-ScheduledTask | Out-Null",
+                "Get-ScheduledTask -TaskPath '\\Microsoft\\Windows\\UpdateOrchestrator\\' \
+                 -ErrorAction SilentlyContinue | Where-Object { $_.TaskName -match 'USO|Reboot' } \
+                 | Unregister-ScheduledTask -Confirm:$false | Out-Null",
+                "",
+            ]
+        );
+    }
+
+    #[test]
+    fn commands_keep_their_quoting() {
+        let mut script = ScriptWriter::default();
+        script.command("reg add \"HKCU\\Test\" /v It's /f", CommandShell::Cmd);
+        script.command("Write-Output 'a'\nWrite-Output \"b\"", CommandShell::Pwsh);
+        assert_eq!(
+            script.text.split("\r\n").collect::<Vec<_>>(),
+            vec![
+                "cmd.exe --% /d /c reg add \"HKCU\\Test\" /v It's /f",
+                "pwsh.exe -NoProfile -Command {",
+                "    Write-Output 'a'",
+                "    Write-Output \"b\"",
+                "}",
+                "",
+            ]
+        );
+    }
+}
@@ -1,43 +1,48 @@
 //! Snapshot Storage Operations
 //!
 //! File I/O functions for snapshot persistence:
-//! - Directory and path management
+//! - Snapshot and file-backup paths (the directory itself comes from `location`)
 //! - Save, load, delete snapshots
-//! - List applied tweaks
+//! - File backups taken for `file_changes`
+//! - The folder holding a tweak's earlier generations (managed by `history`)
+//! - List applied tweaks (and the order to revert them all in)
+//! - Schema migration of snapshots written by older versions
+//! - Write-ahead intent records of applies in progress, so one cut short by a crash can be rolled back
+//! - Checksum verification on load (see `integrity`), and encryption if it's on (see `encryption`)
 
 use crate::error::Error;
-use crate::models::TweakSnapshot;
+use crate::models::{TweakSnapshot, SNAPSHOT_SCHEMA_VERSION};
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use std::fs::{self, File};
 use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-const SNAPSHOTS_DIR: &str = "snapshots";
+use super::integrity::{self, VerifiedJson};
+use super::location;
 
-/// Get the snapshots directory path (next to executable for portability)
+/// Get the snapshots directory path (see `location` for where it is and how it's chosen)
 pub fn get_snapshots_dir() -> Result<PathBuf, Error> {
-    let exe_dir = std::env::current_exe()
-        .map_err(|e| Error::BackupFailed(format!("Failed to get executable path: {}", e)))?
-        .parent()
-        .ok_or_else(|| Error::BackupFailed("Failed to get executable directory".into()))?
-        .to_path_buf();
-
-    let snapshots_dir = exe_dir.join(SNAPSHOTS_DIR);
-
-    // Create directory if it doesn't exist
-    if !snapshots_dir.exists() {
-        fs::create_dir_all(&snapshots_dir).map_err(|e| {
-            Error::BackupFailed(format!("Failed to create snapshots directory: {}", e))
-        })?;
-        log::debug!("Created snapshots directory at {:?}", snapshots_dir);
-    }
-
-    Ok(snapshots_dir)
+    location::snapshots_dir()
 }
 
 pub(crate) fn get_snapshot_path(tweak_id: &str) -> Result<PathBuf, Error> {
     Ok(get_snapshots_dir()?.join(format!("{}.json", tweak_id)))
 }
 
+/// Folder (relative to the snapshots directory) holding the file backups of a tweak's snapshots.
+/// Every snapshot of the tweak shares it; it is removed together with the snapshot.
+pub(crate) fn file_backups_dir_name(tweak_id: &str) -> String {
+    format!("{}.files", tweak_id)
+}
+
+/// Folder (relative to the snapshots directory) holding a tweak's earlier generations. It is
+/// removed together with the snapshot.
+pub(crate) fn history_dir_name(tweak_id: &str) -> String {
+    format!("{}.history", tweak_id)
+}
+
 /// Save a snapshot to disk atomically.
 ///
 /// The snapshot is the only record of the machine's original state, so a crash mid-write must never
@@ -46,20 +51,28 @@ pub(crate) fn get_snapshot_path(tweak_id: &str) -> Result<PathBuf, Error> {
 /// `MOVEFILE_REPLACE_EXISTING` on Windows). The replace is atomic, so no lock is needed and the last
 /// writer wins with a complete file.
 pub fn save_snapshot(snapshot: &TweakSnapshot) -> Result<(), Error> {
-    let dir = get_snapshots_dir()?;
-    let path = dir.join(format!("{}.json", snapshot.tweak_id));
+    write_snapshot_file(&get_snapshot_path(&snapshot.tweak_id)?, snapshot)
+}
+
+/// Atomically write a snapshot (with its checksum) to `path`; see `save_snapshot`
+pub(crate) fn write_snapshot_file(path: &Path, snapshot: &TweakSnapshot) -> Result<(), Error> {
+    write_atomically(path, &integrity::to_file_contents(snapshot)?)?;
+    log::debug!("Saved snapshot to {:?}", path);
+    Ok(())
+}
 
-    let json = serde_json::to_string_pretty(snapshot)
-        .map_err(|e| Error::BackupFailed(format!("Failed to serialize snapshot: {}", e)))?;
+/// Write `content` to a temp file next to `path`, then rename it over `path`
+fn write_atomically(path: &Path, content: &[u8]) -> Result<(), Error> {
+    let dir = path
+        .parent()
+        .ok_or_else(|| Error::BackupFailed(format!("Invalid snapshot path {}", path.display())))?;
 
-    let mut tmp = tempfile::NamedTempFile::new_in(&dir)
+    let mut tmp = tempfile::NamedTempFile::new_in(dir)
         .map_err(|e| Error::BackupFailed(format!("Failed to create temp snapshot file: {}", e)))?;
-    tmp.write_all(json.as_bytes())
+    tmp.write_all(content)
         .map_err(|e| Error::BackupFailed(format!("Failed to write snapshot: {}", e)))?;
-    tmp.persist(&path)
+    tmp.persist(path)
         .map_err(|e| Error::BackupFailed(format!("Failed to persist snapshot: {}", e)))?;
-
-    log::debug!("Saved snapshot to {:?}", path);
     Ok(())
 }
 
@@ -92,13 +105,18 @@ pub fn update_snapshot_metadata(
         .map_err(|e| Error::BackupFailed(format!("Failed to acquire file lock: {}", e)))?;
 
     // Read current content
-    let mut content = String::new();
+    let mut content = Vec::new();
     let mut file = file;
-    file.read_to_string(&mut content)
+    file.read_to_end(&mut content)
         .map_err(|e| Error::BackupFailed(format!("Failed to read snapshot: {}", e)))?;
 
-    let mut snapshot: TweakSnapshot = serde_json::from_str(&content)
-        .map_err(|e| Error::BackupFailed(format!("Failed to parse snapshot: {}", e)))?;
+    let VerifiedJson { json, .. } = integrity::verify(&content).map_err(|reason| {
+        Error::BackupFailed(format!(
+            "Snapshot for '{}' is corrupt: {}",
+            tweak_id, reason
+        ))
+    })?;
+    let mut snapshot = snapshot_from_json(json)?;
 
     log::debug!(
         "Updating snapshot metadata: option {} '{}' → {} '{}'",
@@ -111,8 +129,7 @@ pub fn update_snapshot_metadata(
     snapshot.applied_option_index = new_option_index;
     snapshot.applied_option_label = new_option_label.to_string();
 
-    let json = serde_json::to_string_pretty(&snapshot)
-        .map_err(|e| Error::BackupFailed(format!("Failed to serialize snapshot: {}", e)))?;
+    let content = integrity::to_file_contents(&snapshot)?;
 
     // Truncate and rewrite while holding lock
     file.set_len(0)
@@ -123,7 +140,7 @@ pub fn update_snapshot_metadata(
     file.seek(std::io::SeekFrom::Start(0))
         .map_err(|e| Error::BackupFailed(format!("Failed to seek in snapshot file: {}", e)))?;
 
-    file.write_all(json.as_bytes())
+    file.write_all(&content)
         .map_err(|e| Error::BackupFailed(format!("Failed to write snapshot: {}", e)))?;
 
     // Lock is automatically released when file is dropped
@@ -135,7 +152,8 @@ pub fn update_snapshot_metadata(
     Ok(())
 }
 
-/// Load snapshot for a tweak
+/// Load snapshot for a tweak. A snapshot that fails its checksum (or isn't valid JSON) is moved to
+/// `corrupt/` and treated as missing, so one damaged file doesn't break every snapshot listing.
 pub fn load_snapshot(tweak_id: &str) -> Result<Option<TweakSnapshot>, Error> {
     let path = get_snapshot_path(tweak_id)?;
 
@@ -144,11 +162,33 @@ pub fn load_snapshot(tweak_id: &str) -> Result<Option<TweakSnapshot>, Error> {
         return Ok(None);
     }
 
-    let content = fs::read_to_string(&path)
+    let content = fs::read(&path)
         .map_err(|e| Error::BackupFailed(format!("Failed to read snapshot: {}", e)))?;
+    let VerifiedJson { json, had_checksum } = match integrity::verify(&content) {
+        Ok(verified) => verified,
+        Err(reason) => {
+            integrity::quarantine(&path, tweak_id, &reason)?;
+            return Ok(None);
+        }
+    };
 
-    let snapshot: TweakSnapshot = serde_json::from_str(&content)
-        .map_err(|e| Error::BackupFailed(format!("Failed to parse snapshot: {}", e)))?;
+    let from_version = schema_version_of(&json);
+    let snapshot = snapshot_from_json(json)?;
+
+    // Write the upgraded (and checksummed) form back so the migration runs once per file. The
+    // snapshot is already in memory, so a failed write only means it's redone next time.
+    if from_version < SNAPSHOT_SCHEMA_VERSION || !had_checksum {
+        log::info!(
+            "Upgraded snapshot for tweak '{}' from schema {} to {}{}",
+            tweak_id,
+            from_version,
+            SNAPSHOT_SCHEMA_VERSION,
+            if had_checksum { "" } else { " with a checksum" }
+        );
+        if let Err(e) = save_snapshot(&snapshot) {
+            log::warn!("Failed to save upgraded snapshot for '{}': {}", tweak_id, e);
+        }
+    }
 
     // Warn (don't block) if the snapshot came from a different machine: its captured "original
     // state" describes another system, so restoring it here could target the wrong values.
@@ -187,6 +227,21 @@ pub fn delete_snapshot(tweak_id: &str) -> Result<(), Error> {
         log::debug!("Deleted snapshot for tweak '{}'", tweak_id);
     }
 
+    // File backups and earlier generations are only reachable through the snapshot; a leftover
+    // folder is just disk usage, so a failure here is logged rather than failing the revert.
+    let dir = get_snapshots_dir()?;
+    for (folder, what) in [
+        (file_backups_dir_name(tweak_id), "file backups"),
+        (history_dir_name(tweak_id), "snapshot history"),
+    ] {
+        let folder = dir.join(folder);
+        if folder.exists() {
+            if let Err(e) = fs::remove_dir_all(&folder) {
+                log::warn!("Failed to delete {} for tweak '{}': {}", what, tweak_id, e);
+            }
+        }
+    }
+
     Ok(())
 }
 
@@ -228,10 +283,286 @@ pub fn get_applied_tweaks() -> Result<Vec<String>, Error> {
     Ok(tweaks)
 }
 
+/// Applied tweak IDs in the order to revert them all: most recently applied first. A tweak applied
+/// on top of another captured that one's changes as its own "original" state, so it has to be
+/// reverted first for the earlier snapshot to put back the true baseline. Snapshots that can't be
+/// read come last, so their failures don't block the rest.
+pub fn get_applied_tweaks_in_revert_order() -> Result<Vec<String>, Error> {
+    let mut tweaks: Vec<(String, Option<DateTime<FixedOffset>>)> = get_applied_tweaks()?
+        .into_iter()
+        .map(|tweak_id| {
+            let applied_at = load_snapshot(&tweak_id)
+                .ok()
+                .flatten()
+                .and_then(|snapshot| DateTime::parse_from_rfc3339(&snapshot.created_at).ok());
+            (tweak_id, applied_at)
+        })
+        .collect();
+    sort_for_revert(&mut tweaks);
+    Ok(tweaks.into_iter().map(|(tweak_id, _)| tweak_id).collect())
+}
+
+/// Folder (in the snapshots directory) holding the intent records of applies in progress
+const INTENTS_DIR: &str = "intents";
+
+/// Write-ahead record of an apply, written before anything on the machine changes and removed once
+/// the apply returns. One still on disk at startup means the app or the machine died mid-apply,
+/// leaving the tweak possibly half-changed with a snapshot that doesn't say so.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApplyIntent {
+    pub tweak_id: String,
+    pub tweak_name: String,
+    /// Option being applied
+    pub option_index: usize,
+    pub option_label: String,
+    /// When the apply started (RFC 3339)
+    pub started_at: String,
+    /// When switching options: the option the snapshot recorded before, and the state captured
+    /// just before the switch, which is what a rollback restores. On a first apply both are None,
+    /// and the snapshot itself (if it got saved) is the state to roll back to.
+    #[serde(default)]
+    pub previous_option: Option<(usize, String)>,
+    #[serde(default)]
+    pub previous_state: Option<TweakSnapshot>,
+}
+
+/// Removes an intent record when the apply returns, however it returns. A panic keeps the record,
+/// since the apply may have stopped half-way like in a crash.
+pub struct ApplyIntentGuard {
+    path: PathBuf,
+}
+
+impl Drop for ApplyIntentGuard {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            return;
+        }
+        if let Err(e) = fs::remove_file(&self.path) {
+            log::warn!(
+                "Failed to remove apply intent {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+}
+
+fn intent_path(tweak_id: &str) -> Result<PathBuf, Error> {
+    Ok(get_snapshots_dir()?
+        .join(INTENTS_DIR)
+        .join(format!("{}.json", tweak_id)))
+}
+
+/// Durably record that an apply is about to change the machine. Hold the guard for the rest of the
+/// apply; dropping it marks the apply complete.
+pub fn begin_apply_intent(intent: &ApplyIntent) -> Result<ApplyIntentGuard, Error> {
+    let path = intent_path(&intent.tweak_id)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| {
+            Error::BackupFailed(format!("Failed to create {}: {}", dir.display(), e))
+        })?;
+    }
+    write_atomically(&path, &integrity::to_file_contents(intent)?)?;
+    log::debug!("Recorded apply intent for '{}'", intent.tweak_id);
+    Ok(ApplyIntentGuard { path })
+}
+
+/// Applies that never completed (see `ApplyIntent`), oldest first. Unreadable records are logged
+/// and skipped.
+pub fn incomplete_apply_intents() -> Result<Vec<ApplyIntent>, Error> {
+    let dir = get_snapshots_dir()?.join(INTENTS_DIR);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut intents = Vec::new();
+    for entry in fs::read_dir(&dir).map_err(|e| Error::BackupFailed(e.to_string()))? {
+        let path = entry
+            .map_err(|e| Error::BackupFailed(e.to_string()))?
+            .path();
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let intent = fs::read(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| integrity::verify(&content).map_err(|reason| reason.to_string()))
+            .and_then(|VerifiedJson { json, .. }| {
+                serde_json::from_value::<ApplyIntent>(json).map_err(|e| e.to_string())
+            });
+        match intent {
+            Ok(intent) => intents.push(intent),
+            Err(e) => log::warn!("Skipping unreadable apply intent {}: {}", path.display(), e),
+        }
+    }
+    intents.sort_by(|a, b| a.started_at.cmp(&b.started_at));
+    Ok(intents)
+}
+
+/// Whether an apply of this tweak was cut short (see `ApplyIntent`)
+pub fn has_incomplete_apply(tweak_id: &str) -> bool {
+    intent_path(tweak_id).is_ok_and(|path| path.exists())
+}
+
+/// Forget an incomplete apply, after it was rolled back or the user chose to keep the state
+pub fn discard_apply_intent(tweak_id: &str) -> Result<(), Error> {
+    let path = intent_path(tweak_id)?;
+    if path.exists() {
+        fs::remove_file(&path)
+            .map_err(|e| Error::BackupFailed(format!("Failed to remove apply intent: {}", e)))?;
+    }
+    Ok(())
+}
+
+/// One upgrade step per schema version: `MIGRATIONS[n]` turns a version `n` snapshot into a
+/// version `n + 1` one. Steps work on the raw JSON, so they can rename or reshape fields that the
+/// current model would reject. Bumping `SNAPSHOT_SCHEMA_VERSION` without adding a step is a compile
+/// error.
+const MIGRATIONS: [fn(&mut Map<String, Value>); SNAPSHOT_SCHEMA_VERSION as usize] = [
+    // 0 -> 1: version 1 added `schema_version` and `machine_guid`. A pre-versioning snapshot's
+    // machine is unknown, so `machine_guid` stays absent (no mismatch warning); nothing else changed.
+    |_| {},
+];
+
+/// The `schema_version` of a snapshot's JSON (0 for snapshots written before versioning)
+fn schema_version_of(json: &Value) -> u32 {
+    json.get("schema_version")
+        .and_then(Value::as_u64)
+        .map_or(0, |version| u32::try_from(version).unwrap_or(u32::MAX))
+}
+
+/// Parse a snapshot's JSON, upgrading it first if an older version of the app wrote it. A snapshot
+/// from a newer version is refused rather than read with fields this version doesn't understand.
+pub(crate) fn snapshot_from_json(mut json: Value) -> Result<TweakSnapshot, Error> {
+    let from_version = schema_version_of(&json);
+    if from_version > SNAPSHOT_SCHEMA_VERSION {
+        return Err(Error::BackupFailed(format!(
+            "Snapshot uses schema version {}, but this version of the app only understands up to {}",
+            from_version, SNAPSHOT_SCHEMA_VERSION
+        )));
+    }
+
+    let fields = json
+        .as_object_mut()
+        .ok_or_else(|| Error::BackupFailed("Snapshot is not a JSON object".to_string()))?;
+    for version in from_version..SNAPSHOT_SCHEMA_VERSION {
+        MIGRATIONS[version as usize](fields);
+        fields.insert("schema_version".to_string(), Value::from(version + 1));
+    }
+
+    serde_json::from_value(json)
+        .map_err(|e| Error::BackupFailed(format!("Failed to parse snapshot: {}", e)))
+}
+
+/// Newest first, unknown times last; ties by id so the order is stable
+fn sort_for_revert(tweaks: &mut [(String, Option<DateTime<FixedOffset>>)]) {
+    tweaks
+        .sort_by(|(a_id, a_time), (b_id, b_time)| b_time.cmp(a_time).then_with(|| a_id.cmp(b_id)));
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn revert_order_is_newest_first_with_unknown_times_last() {
+        let at = |s: &str| Some(DateTime::parse_from_rfc3339(s).unwrap());
+        let mut tweaks = vec![
+            ("unreadable".to_string(), None),
+            ("first".to_string(), at("2026-01-01T10:00:00+02:00")),
+            ("latest".to_string(), at("2026-01-02T09:00:00+00:00")),
+            // Same instant as "first" in another offset
+            ("also_first".to_string(), at("2026-01-01T08:00:00+00:00")),
+        ];
+        sort_for_revert(&mut tweaks);
+        let order: Vec<&str> = tweaks.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(order, vec!["latest", "also_first", "first", "unreadable"]);
+    }
+
+    #[test]
+    fn a_pre_versioning_snapshot_is_migrated_to_the_current_schema() {
+        let json: Value = serde_json::from_str(include_str!("fixtures/snapshot_v0.json")).unwrap();
+        assert_eq!(schema_version_of(&json), 0);
+
+        let snapshot = snapshot_from_json(json).unwrap();
+        assert_eq!(snapshot.schema_version, SNAPSHOT_SCHEMA_VERSION);
+        assert_eq!(snapshot.machine_guid, None);
+        assert_eq!(snapshot.registry_snapshots.len(), 2);
+        assert!(!snapshot.registry_snapshots[0].key_level);
+    }
+
+    #[test]
+    fn a_snapshot_from_a_newer_schema_is_refused() {
+        let mut json =
+            serde_json::to_value(TweakSnapshot::new("t", "T", 0, "opt", 11, false, None)).unwrap();
+        json["schema_version"] = Value::from(SNAPSHOT_SCHEMA_VERSION + 1);
+        let err = snapshot_from_json(json).unwrap_err();
+        assert!(err.to_string().contains("only understands"), "{}", err);
+    }
+
+    #[test]
+    fn loading_an_old_snapshot_file_rewrites_it_at_the_current_schema() {
+        let id = format!("__schema_migration_{}", std::process::id());
+        let old = include_str!("fixtures/snapshot_v0.json").replace("fixture_v0", &id);
+        fs::write(get_snapshot_path(&id).unwrap(), old).unwrap();
+
+        let loaded = load_snapshot(&id).unwrap().unwrap();
+        assert_eq!(loaded.schema_version, SNAPSHOT_SCHEMA_VERSION);
+        let on_disk: Value =
+            serde_json::from_str(&fs::read_to_string(get_snapshot_path(&id).unwrap()).unwrap())
+                .unwrap();
+        assert_eq!(schema_version_of(&on_disk), SNAPSHOT_SCHEMA_VERSION);
+
+        delete_snapshot(&id).unwrap();
+    }
+
+    #[test]
+    fn an_apply_intent_is_pending_until_its_guard_drops() {
+        let id = format!("__apply_intent_{}", std::process::id());
+        let guard = begin_apply_intent(&ApplyIntent {
+            tweak_id: id.clone(),
+            tweak_name: "T".to_string(),
+            option_index: 1,
+            option_label: "opt".to_string(),
+            started_at: chrono::Local::now().to_rfc3339(),
+            previous_option: Some((0, "other".to_string())),
+            previous_state: None,
+        })
+        .unwrap();
+
+        assert!(has_incomplete_apply(&id));
+        let pending = incomplete_apply_intents().unwrap();
+        let intent = pending.iter().find(|intent| intent.tweak_id == id).unwrap();
+        assert_eq!(intent.previous_option, Some((0, "other".to_string())));
+
+        drop(guard);
+        assert!(!has_incomplete_apply(&id));
+    }
+
+    #[test]
+    fn a_tampered_snapshot_is_quarantined_and_treated_as_missing() {
+        let id = format!("__corrupt_snapshot_{}", std::process::id());
+        save_snapshot(&TweakSnapshot::new(&id, "T", 0, "opt", 11, false, None)).unwrap();
+        let path = get_snapshot_path(&id).unwrap();
+        let tampered = fs::read_to_string(&path).unwrap().replace(
+            "\"applied_option_label\": \"opt\"",
+            "\"applied_option_label\": \"x\"",
+        );
+        fs::write(&path, tampered).unwrap();
+
+        assert!(load_snapshot(&id).unwrap().is_none());
+        assert!(!path.exists());
+        let corrupt_dir = get_snapshots_dir().unwrap().join("corrupt");
+        let moved: Vec<PathBuf> = fs::read_dir(&corrupt_dir)
+            .unwrap()
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.to_string_lossy().contains(&id))
+            .collect();
+        assert_eq!(moved.len(), 1);
+
+        fs::remove_file(&moved[0]).unwrap();
+        delete_snapshot(&id).unwrap();
+    }
+
     #[test]
     fn save_snapshot_atomically_replaces_an_existing_snapshot() {
         // Regression: the atomic write must *replace* an existing snapshot, not fail on
@@ -0,0 +1,123 @@
+//! Disk usage of the snapshots directory.
+//!
+//! Each applied tweak's footprint is its snapshot file, its kept generations (`{tweak_id}.history`),
+//! and the file backups of all of them (`{tweak_id}.files`). The total also counts what belongs to
+//! no single tweak: the baseline, the journal, notes, intent records, and quarantined snapshots.
+
+use crate::error::Error;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+use super::storage::{
+    file_backups_dir_name, get_applied_tweaks, get_snapshot_path, get_snapshots_dir,
+    history_dir_name, load_snapshot,
+};
+
+/// Disk used by one applied tweak
+#[derive(Debug, Clone, Serialize)]
+pub struct TweakStorageUsage {
+    pub tweak_id: String,
+    pub tweak_name: String,
+    /// When the tweak was first applied (the original snapshot's creation time)
+    pub created_at: String,
+    /// Snapshot file, history, and file backups together
+    pub size_bytes: u64,
+    /// The part of `size_bytes` taken by kept generations and their file backups, which history
+    /// retention can free
+    pub history_bytes: u64,
+}
+
+/// Disk used by snapshots, for the settings UI
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupStorageStats {
+    /// Largest first
+    pub tweaks: Vec<TweakStorageUsage>,
+    /// Everything in the snapshots directory
+    pub total_bytes: u64,
+    /// The tweak applied longest ago
+    pub oldest: Option<TweakStorageUsage>,
+}
+
+/// Size of a file, or of everything under a directory; 0 for what can't be read
+pub(super) fn path_size(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| path_size(&entry.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// Disk used by each applied tweak and by the snapshots directory as a whole
+pub fn get_backup_storage_stats() -> Result<BackupStorageStats, Error> {
+    let dir = get_snapshots_dir()?;
+    let mut tweaks = Vec::new();
+    for tweak_id in get_applied_tweaks()? {
+        let Some(snapshot) = load_snapshot(&tweak_id)? else {
+            continue;
+        };
+        // The original's file backups share `.files` with the generations', so the history's
+        // part of it is whatever isn't referenced by the original snapshot
+        let history_dir = dir.join(history_dir_name(&tweak_id));
+        let files_dir = dir.join(file_backups_dir_name(&tweak_id));
+        let original_files: u64 = snapshot
+            .file_snapshots
+            .iter()
+            .filter_map(|file| file.backup_path.as_deref())
+            .map(|path| path_size(&dir.join(path)))
+            .sum();
+        let files = path_size(&files_dir);
+        let history_bytes = path_size(&history_dir) + files.saturating_sub(original_files);
+
+        tweaks.push(TweakStorageUsage {
+            size_bytes: path_size(&get_snapshot_path(&tweak_id)?) + path_size(&history_dir) + files,
+            history_bytes,
+            tweak_id,
+            tweak_name: snapshot.tweak_name,
+            created_at: snapshot.created_at,
+        });
+    }
+
+    let oldest = tweaks
+        .iter()
+        .filter_map(|usage| {
+            chrono::DateTime::parse_from_rfc3339(&usage.created_at)
+                .ok()
+                .map(|created| (created, usage))
+        })
+        .min_by_key(|(created, _)| *created)
+        .map(|(_, usage)| usage.clone());
+    tweaks.sort_by(|a, b| b.size_bytes.cmp(&a.size_bytes));
+
+    Ok(BackupStorageStats {
+        tweaks,
+        total_bytes: path_size(&dir),
+        oldest,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_directory_is_as_large_as_everything_under_it() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.json"), [0u8; 100]).unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("sub").join("b.bin"), [0u8; 23]).unwrap();
+
+        assert_eq!(path_size(dir.path()), 123);
+        assert_eq!(path_size(&dir.path().join("a.json")), 100);
+        assert_eq!(path_size(&dir.path().join("missing")), 0);
+    }
+}
@@ -0,0 +1,183 @@
+//! BCD store service for reading and editing boot configuration elements.
+//!
+//! Uses `bcdedit.exe` to query, set, and delete elements of a boot entry.
+//! Requires administrator privileges.
+//!
+//! Element names in `bcdedit /enum` output are stable identifiers, but the values of boolean
+//! elements are printed in the display language (`Yes` on English Windows). Boolean values are
+//! normalized to `yes`/`no` where they can be recognized; an unrecognized localized spelling reads
+//! as a mismatch rather than a false match.
+
+use crate::error::Error;
+use crate::models::{BcdeditAction, BcdeditChange};
+use std::os::windows::process::CommandExt;
+use std::process::{Command, Output};
+
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+/// Boot entry targeted when a change omits `identifier`.
+pub const DEFAULT_IDENTIFIER: &str = "{current}";
+
+/// The identifier a change targets (`{current}` when omitted).
+pub fn resolve_identifier(identifier: Option<&str>) -> &str {
+    identifier.unwrap_or(DEFAULT_IDENTIFIER)
+}
+
+/// Run bcdedit with the given arguments, failing on a non-zero exit status.
+fn run_bcdedit(args: &[&str]) -> Result<Output, Error> {
+    let output = Command::new("bcdedit")
+        .args(args)
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .map_err(|e| Error::CommandExecution(format!("Failed to run bcdedit: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(Error::CommandExecution(format!(
+            "bcdedit {} failed: {} {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stdout).trim(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(output)
+}
+
+/// Read an element's value from a boot entry. Returns `None` if the element is not set.
+pub fn get_value(identifier: &str, element: &str) -> Result<Option<String>, Error> {
+    let output = run_bcdedit(&["/enum", identifier])?;
+    Ok(parse_element_value(
+        &String::from_utf8_lossy(&output.stdout),
+        element,
+    ))
+}
+
+/// Set an element's value on a boot entry.
+pub fn set_value(identifier: &str, element: &str, value: &str) -> Result<(), Error> {
+    run_bcdedit(&["/set", identifier, element, value])?;
+    log::info!("Set BCD element {} {} = {}", identifier, element, value);
+    Ok(())
+}
+
+/// Delete an element from a boot entry. Deleting an element that is not set is a no-op.
+pub fn delete_value(identifier: &str, element: &str) -> Result<(), Error> {
+    if get_value(identifier, element)?.is_none() {
+        log::debug!("BCD element {} {} already unset", identifier, element);
+        return Ok(());
+    }
+
+    run_bcdedit(&["/deletevalue", identifier, element])?;
+    log::info!("Deleted BCD element {} {}", identifier, element);
+    Ok(())
+}
+
+/// Apply a BCD change
+pub fn apply_bcdedit_change(change: &BcdeditChange) -> Result<(), Error> {
+    let identifier = resolve_identifier(change.identifier.as_deref());
+    match change.action {
+        BcdeditAction::Set => {
+            let value = change.value.as_deref().ok_or_else(|| {
+                Error::ValidationError(format!(
+                    "BCD element '{}' requires 'value' for set action",
+                    change.element
+                ))
+            })?;
+            set_value(identifier, &change.element, value)
+        }
+        BcdeditAction::DeleteValue => delete_value(identifier, &change.element),
+    }
+}
+
+/// Whether two element values are equivalent, treating the boolean spellings bcdedit accepts
+/// (`yes`/`on`/`true` and their negatives) as equal and ignoring case.
+pub fn values_equal(expected: &str, actual: &str) -> bool {
+    normalize_value(expected).eq_ignore_ascii_case(&normalize_value(actual))
+}
+
+/// Normalize a boolean element value to `yes`/`no` so it can be passed back to `bcdedit /set`;
+/// any other value is returned trimmed but otherwise unchanged.
+pub fn normalize_value(value: &str) -> String {
+    let trimmed = value.trim();
+    match trimmed.to_ascii_lowercase().as_str() {
+        // Not `1`/`0`: those are also legitimate values of integer elements such as `timeout`.
+        "yes" | "on" | "true" => "yes".to_string(),
+        "no" | "off" | "false" => "no".to_string(),
+        _ => trimmed.to_string(),
+    }
+}
+
+/// Find an element's value in `bcdedit /enum` output.
+///
+/// Each element is printed as `name<spaces>value`. Continuation lines of multi-value elements
+/// are indented and are not part of the first value, so only unindented lines are considered.
+fn parse_element_value(output: &str, element: &str) -> Option<String> {
+    output
+        .lines()
+        .filter(|line| !line.starts_with(char::is_whitespace))
+        .find_map(|line| {
+            let (name, value) = line.split_once(char::is_whitespace)?;
+            name.eq_ignore_ascii_case(element)
+                .then(|| value.trim().to_string())
+        })
+        .filter(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_ENUM: &str = "\
+Windows Boot Loader
+-------------------
+identifier              {current}
+device                  partition=C:
+path                    \\WINDOWS\\system32\\winload.efi
+description             Windows 11
+displaymessageoverride  Recovery
+recoverysequence        {a5c3b1d2-0000-0000-0000-000000000000}
+                        {b6d4c2e3-0000-0000-0000-000000000000}
+useplatformclock        Yes
+disabledynamictick      Yes
+nx                      OptIn
+";
+
+    #[test]
+    fn an_element_value_is_read_from_enum_output() {
+        assert_eq!(
+            parse_element_value(SAMPLE_ENUM, "useplatformclock").as_deref(),
+            Some("Yes")
+        );
+        assert_eq!(
+            parse_element_value(SAMPLE_ENUM, "NX").as_deref(),
+            Some("OptIn")
+        );
+        assert_eq!(
+            parse_element_value(SAMPLE_ENUM, "description").as_deref(),
+            Some("Windows 11")
+        );
+    }
+
+    #[test]
+    fn an_unset_element_reads_as_none() {
+        assert_eq!(parse_element_value(SAMPLE_ENUM, "tscsyncpolicy"), None);
+    }
+
+    #[test]
+    fn continuation_lines_do_not_leak_into_other_elements() {
+        assert_eq!(
+            parse_element_value(SAMPLE_ENUM, "recoverysequence").as_deref(),
+            Some("{a5c3b1d2-0000-0000-0000-000000000000}")
+        );
+    }
+
+    #[test]
+    fn boolean_spellings_compare_equal() {
+        assert!(values_equal("yes", "Yes"));
+        assert!(values_equal("on", "Yes"));
+        assert!(values_equal("false", "No"));
+        assert!(values_equal("Enhanced", "enhanced"));
+        assert!(!values_equal("yes", "No"));
+        assert!(!values_equal("yes", "Ja"));
+        assert!(!values_equal("0", "no"));
+    }
+}
@@ -0,0 +1,518 @@
+//! Deferred applies: tweaks queued to be applied at the next boot or at a set time.
+//!
+//! A risky tweak is easier to try on a machine that has just booted, with nothing else open, and
+//! some only behave once the services they touch haven't started yet. The queue is kept in
+//! `deferred/queue.json` in the snapshots directory; `deferred_runner` applies the entries that
+//! are due while the app runs, starting right at launch. So that the app is running after the next
+//! boot, queueing a next-boot apply also registers a logon task that starts it (elevated, without
+//! the UAC prompt), which is removed again once no next-boot apply waits. Since whoever can replace
+//! the executable would be started elevated too, the task is only registered while the executable
+//! and its folder can be changed by administrators only (e.g. installed in Program Files, not a
+//! portable copy in Downloads), and it's registered again when the executable has moved.
+//!
+//! An entry that ran is kept with its outcome for `FINISHED_RETENTION_DAYS`, so the user can see
+//! what happened while they weren't looking.
+//!
+//! The queue is written to a temp file that is renamed over it. A queue that can't be read all the
+//! same is moved aside (to `queue.corrupt-<time>.json`) and reported by the call that found it, so
+//! the calls after it start from an empty queue instead of all failing.
+
+use crate::error::Error;
+use crate::services::{
+    backup_service, protected_dir, scheduler_service, system_info_service, tweak_loader,
+};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Folder (in the snapshots directory) holding the queue
+const DEFERRED_DIR: &str = "deferred";
+
+const QUEUE_FILE: &str = "queue.json";
+
+/// How long an entry that ran is kept
+const FINISHED_RETENTION_DAYS: i64 = 30;
+
+/// Task Scheduler folder and name of the logon task that starts the app for next-boot applies
+const STARTUP_TASK_PATH: &str = "\\MagicX Toolbox";
+const STARTUP_TASK_NAME: &str = "Deferred apply";
+
+/// Serializes the queue's read-modify-write
+static QUEUE_LOCK: Mutex<()> = Mutex::new(());
+
+/// When a deferred apply runs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DeferredTrigger {
+    /// Once the machine has booted again after `queued_boot` (RFC 3339 boot time)
+    NextBoot { queued_boot: String },
+    /// At or after `at` (RFC 3339)
+    At { at: String },
+}
+
+/// How a deferred apply went
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeferredOutcome {
+    /// When it ran (RFC 3339)
+    pub finished_at: String,
+    pub success: bool,
+    pub message: String,
+}
+
+/// A queued apply
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeferredApply {
+    pub id: String,
+    pub tweak_id: String,
+    pub tweak_name: String,
+    pub option_index: usize,
+    pub option_label: String,
+    /// When it was queued (RFC 3339)
+    pub queued_at: String,
+    pub trigger: DeferredTrigger,
+    /// None while it waits
+    #[serde(default)]
+    pub outcome: Option<DeferredOutcome>,
+}
+
+impl DeferredApply {
+    /// Whether it waits and its trigger has come: the machine booted since it was queued, or its
+    /// time has passed
+    pub fn is_due(&self, now: DateTime<Local>, boot: DateTime<Local>) -> bool {
+        if self.outcome.is_some() {
+            return false;
+        }
+        match &self.trigger {
+            DeferredTrigger::NextBoot { queued_boot } => {
+                !backup_service::same_boot(queued_boot, boot)
+            }
+            DeferredTrigger::At { at } => {
+                DateTime::parse_from_rfc3339(at).is_ok_and(|at| at <= now.fixed_offset())
+            }
+        }
+    }
+
+    fn waits_for_boot(&self) -> bool {
+        self.outcome.is_none() && matches!(self.trigger, DeferredTrigger::NextBoot { .. })
+    }
+}
+
+fn queue_path() -> Result<PathBuf, Error> {
+    Ok(backup_service::storage::get_snapshots_dir()?
+        .join(DEFERRED_DIR)
+        .join(QUEUE_FILE))
+}
+
+fn read() -> Result<Vec<DeferredApply>, Error> {
+    let path = queue_path()?;
+    match fs::read(&path) {
+        Ok(content) => {
+            serde_json::from_slice(&content).map_err(|e| quarantine(&path, &e.to_string()))
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(Error::BackupFailed(format!(
+            "Failed to read the deferred apply queue: {}",
+            e
+        ))),
+    }
+}
+
+fn update<T>(change: impl FnOnce(&mut Vec<DeferredApply>) -> T) -> Result<T, Error> {
+    let _guard = QUEUE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut queue = read()?;
+    let result = change(&mut queue);
+    prune_finished(&mut queue, Local::now());
+
+    let path = queue_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| {
+            Error::BackupFailed(format!("Failed to create {}: {}", dir.display(), e))
+        })?;
+    }
+    let json = serde_json::to_string_pretty(&queue).map_err(|e| {
+        Error::BackupFailed(format!(
+            "Failed to serialize the deferred apply queue: {}",
+            e
+        ))
+    })?;
+    write_atomically(&path, json.as_bytes()).map_err(|e| {
+        Error::BackupFailed(format!("Failed to write the deferred apply queue: {}", e))
+    })?;
+    Ok(result)
+}
+
+/// Write `content` to a temp file next to `path`, then rename it over `path`
+fn write_atomically(path: &Path, content: &[u8]) -> io::Result<()> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+    tmp.write_all(content)?;
+    tmp.persist(path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+/// Move the unreadable queue at `path` aside, and return the error reporting it
+fn quarantine(path: &Path, reason: &str) -> Error {
+    let target = path.with_file_name(format!(
+        "queue.corrupt-{}.json",
+        Local::now().format("%Y%m%d%H%M%S%3f")
+    ));
+    match fs::rename(path, &target) {
+        Ok(()) => {
+            log::warn!(
+                "The deferred apply queue is corrupt ({}); moved it to {}",
+                reason,
+                target.display()
+            );
+            Error::BackupFailed(format!(
+                "The deferred apply queue was corrupt ({}) and was moved to {}; queued applies in it were dropped",
+                reason,
+                target.display()
+            ))
+        }
+        Err(e) => Error::BackupFailed(format!(
+            "Corrupt deferred apply queue {} ({}), and it couldn't be moved aside: {}",
+            path.display(),
+            reason,
+            e
+        )),
+    }
+}
+
+/// Drop entries that ran more than `FINISHED_RETENTION_DAYS` ago
+fn prune_finished(queue: &mut Vec<DeferredApply>, now: DateTime<Local>) {
+    let cutoff = now - chrono::Duration::days(FINISHED_RETENTION_DAYS);
+    queue.retain(|entry| {
+        !entry.outcome.as_ref().is_some_and(|outcome| {
+            DateTime::parse_from_rfc3339(&outcome.finished_at)
+                .is_ok_and(|finished| finished < cutoff.fixed_offset())
+        })
+    });
+}
+
+/// Every queued apply, waiting or finished, oldest first
+pub fn list() -> Result<Vec<DeferredApply>, Error> {
+    read()
+}
+
+/// The queued apply `id`, if it's still in the queue
+pub fn get(id: &str) -> Result<Option<DeferredApply>, Error> {
+    Ok(read()?.into_iter().find(|entry| entry.id == id))
+}
+
+/// Queue option `option_index` of `tweak_id`, to apply at `at` or, without one, at the next boot
+pub fn queue(
+    tweak_id: &str,
+    option_index: usize,
+    at: Option<DateTime<Local>>,
+) -> Result<DeferredApply, Error> {
+    let tweak = tweak_loader::get_tweak(tweak_id)?
+        .ok_or_else(|| Error::NotFound(format!("Tweak '{}'", tweak_id)))?;
+    let option = tweak.options.get(option_index).ok_or_else(|| {
+        Error::ValidationError(format!(
+            "Tweak '{}' has no option {}",
+            tweak_id, option_index
+        ))
+    })?;
+    let now = Local::now();
+    let trigger = match at {
+        Some(at) if at <= now => {
+            return Err(Error::ValidationError(
+                "A deferred apply has to be scheduled in the future".to_string(),
+            ))
+        }
+        Some(at) => DeferredTrigger::At {
+            at: at.to_rfc3339(),
+        },
+        None => DeferredTrigger::NextBoot {
+            queued_boot: system_info_service::boot_time().to_rfc3339(),
+        },
+    };
+
+    let mut bytes = [0u8; 8];
+    getrandom::getrandom(&mut bytes)
+        .map_err(|e| Error::WindowsApi(format!("Failed to generate an ID: {}", e)))?;
+    let entry = DeferredApply {
+        id: bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+        tweak_id: tweak.id.clone(),
+        tweak_name: tweak.name.clone(),
+        option_index,
+        option_label: option.label.clone(),
+        queued_at: now.to_rfc3339(),
+        trigger,
+        outcome: None,
+    };
+    log::info!(
+        "Queued '{}' option {} ({:?})",
+        entry.tweak_id,
+        option_index,
+        entry.trigger
+    );
+    update(|queue| {
+        // A newer request for the same tweak replaces one still waiting
+        queue.retain(|queued| queued.tweak_id != entry.tweak_id || queued.outcome.is_some());
+        queue.push(entry.clone());
+    })?;
+    sync_startup_task();
+    Ok(entry)
+}
+
+/// Remove `id` from the queue: a waiting apply is cancelled, a finished one dismissed. Returns
+/// false when it isn't in the queue.
+pub fn cancel(id: &str) -> Result<bool, Error> {
+    let removed = update(|queue| {
+        let before = queue.len();
+        queue.retain(|entry| entry.id != id);
+        queue.len() != before
+    })?;
+    if removed {
+        log::info!("Removed deferred apply {}", id);
+        sync_startup_task();
+    }
+    Ok(removed)
+}
+
+/// The waiting applies that are due now
+pub fn due() -> Result<Vec<DeferredApply>, Error> {
+    let (now, boot) = (Local::now(), system_info_service::boot_time());
+    Ok(read()?
+        .into_iter()
+        .filter(|entry| entry.is_due(now, boot))
+        .collect())
+}
+
+/// Record how `id` went. Returns the entry with its outcome, or None if it was cancelled meanwhile.
+pub fn finish(id: &str, success: bool, message: String) -> Result<Option<DeferredApply>, Error> {
+    let finished = update(|queue| {
+        let entry = queue.iter_mut().find(|entry| entry.id == id)?;
+        entry.outcome = Some(DeferredOutcome {
+            finished_at: Local::now().to_rfc3339(),
+            success,
+            message,
+        });
+        Some(entry.clone())
+    })?;
+    sync_startup_task();
+    Ok(finished)
+}
+
+/// Register the logon task that starts the app while a next-boot apply waits, and remove it once
+/// none does. Failing to is logged: the applies still run whenever the app is next started.
+pub fn sync_startup_task() {
+    let waiting = read().is_ok_and(|queue| queue.iter().any(DeferredApply::waits_for_boot));
+    let registered = scheduler_service::get_task_xml(STARTUP_TASK_PATH, STARTUP_TASK_NAME)
+        .ok()
+        .flatten();
+    let result = match (waiting, registered) {
+        (true, registered) => register_startup_task(registered.as_deref()),
+        (false, Some(_)) => scheduler_service::delete_task(STARTUP_TASK_PATH, STARTUP_TASK_NAME),
+        (false, None) => Ok(()),
+    };
+    if let Err(e) = result {
+        log::warn!(
+            "Failed to update the logon task for deferred applies: {}",
+            e
+        );
+    }
+}
+
+/// Register the logon task for this executable, unless `registered` (the registered task's XML)
+/// already starts it. Refused while a non-administrator could change the executable, removing a
+/// task registered before.
+fn register_startup_task(registered: Option<&str>) -> Result<(), Error> {
+    let exe = std::env::current_exe()
+        .map_err(|e| Error::WindowsApi(format!("Failed to get executable path: {}", e)))?;
+    if let Err(e) = check_admin_only(&exe) {
+        if registered.is_some() {
+            scheduler_service::delete_task(STARTUP_TASK_PATH, STARTUP_TASK_NAME)?;
+        }
+        return Err(e);
+    }
+    let command = xml_escape(&exe.to_string_lossy());
+    if registered.and_then(task_command) == Some(command.as_str()) {
+        return Ok(());
+    }
+    scheduler_service::register_task_xml(
+        STARTUP_TASK_PATH,
+        STARTUP_TASK_NAME,
+        &startup_task_xml(&command)?,
+    )
+}
+
+/// Refuse an executable that anyone but Administrators and SYSTEM could replace: the executable
+/// itself, or its folder (where a new file could be put in its place)
+fn check_admin_only(exe: &Path) -> Result<(), Error> {
+    let folder = exe.parent().unwrap_or(exe);
+    for path in [folder, exe] {
+        if !protected_dir::writable_by_admins_only(path)? {
+            return Err(Error::ValidationError(format!(
+                "Not starting the app at sign-in: non-administrators can change {} (install the app in Program Files); the queued applies run when the app is next started",
+                path.display()
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// The command of a task's XML, as written in it (XML-escaped)
+fn task_command(xml: &str) -> Option<&str> {
+    let start = xml.find("<Command>")? + "<Command>".len();
+    let end = start + xml[start..].find("</Command>")?;
+    Some(xml[start..end].trim())
+}
+
+/// A logon task for the current user that runs `command` (XML-escaped) with the highest rights the
+/// user has
+fn startup_task_xml(command: &str) -> Result<String, Error> {
+    let user = match (std::env::var("USERDOMAIN"), std::env::var("USERNAME")) {
+        (Ok(domain), Ok(name)) => format!("{}\\{}", domain, name),
+        (Err(_), Ok(name)) => name,
+        _ => {
+            return Err(Error::WindowsApi(
+                "Failed to get the current user name".to_string(),
+            ))
+        }
+    };
+    let user = xml_escape(&user);
+    Ok(format!(
+        r#"<Task version="1.2" xmlns="http://schemas.microsoft.com/windows/2004/02/mit/task">
+  <RegistrationInfo>
+    <Description>Starts MagicX Toolbox after sign-in to apply the tweaks queued for the next boot. Removed once none are left.</Description>
+  </RegistrationInfo>
+  <Triggers>
+    <LogonTrigger>
+      <Enabled>true</Enabled>
+      <UserId>{user}</UserId>
+    </LogonTrigger>
+  </Triggers>
+  <Principals>
+    <Principal id="Author">
+      <UserId>{user}</UserId>
+      <LogonType>InteractiveToken</LogonType>
+      <RunLevel>HighestAvailable</RunLevel>
+    </Principal>
+  </Principals>
+  <Settings>
+    <MultipleInstancesPolicy>IgnoreNew</MultipleInstancesPolicy>
+    <DisallowStartIfOnBatteries>false</DisallowStartIfOnBatteries>
+    <StopIfGoingOnBatteries>false</StopIfGoingOnBatteries>
+    <ExecutionTimeLimit>PT0S</ExecutionTimeLimit>
+    <Enabled>true</Enabled>
+  </Settings>
+  <Actions Context="Author">
+    <Exec>
+      <Command>{command}</Command>
+    </Exec>
+  </Actions>
+</Task>"#,
+        user = user,
+        command = command,
+    ))
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(trigger: DeferredTrigger) -> DeferredApply {
+        DeferredApply {
+            id: "1".into(),
+            tweak_id: "t".into(),
+            tweak_name: "T".into(),
+            option_index: 0,
+            option_label: "On".into(),
+            queued_at: Local::now().to_rfc3339(),
+            trigger,
+            outcome: None,
+        }
+    }
+
+    #[test]
+    fn a_next_boot_apply_is_due_only_after_a_reboot() {
+        let boot = Local::now() - chrono::Duration::hours(1);
+        let mut queued = entry(DeferredTrigger::NextBoot {
+            queued_boot: boot.to_rfc3339(),
+        });
+        assert!(!queued.is_due(Local::now(), boot));
+        assert!(queued.is_due(Local::now(), Local::now()));
+
+        queued.outcome = Some(DeferredOutcome {
+            finished_at: Local::now().to_rfc3339(),
+            success: true,
+            message: String::new(),
+        });
+        assert!(!queued.is_due(Local::now(), Local::now()));
+    }
+
+    #[test]
+    fn a_timed_apply_is_due_once_its_time_passes() {
+        let at = Local::now() + chrono::Duration::minutes(5);
+        let queued = entry(DeferredTrigger::At {
+            at: at.to_rfc3339(),
+        });
+        let boot = Local::now();
+        assert!(!queued.is_due(Local::now(), boot));
+        assert!(queued.is_due(at + chrono::Duration::seconds(1), boot));
+    }
+
+    #[test]
+    fn finished_entries_are_pruned_after_the_retention() {
+        let now = Local::now();
+        let finished = |days: i64| DeferredApply {
+            outcome: Some(DeferredOutcome {
+                finished_at: (now - chrono::Duration::days(days)).to_rfc3339(),
+                success: true,
+                message: String::new(),
+            }),
+            ..entry(DeferredTrigger::At {
+                at: now.to_rfc3339(),
+            })
+        };
+        let mut queue = vec![
+            finished(FINISHED_RETENTION_DAYS + 1),
+            finished(1),
+            entry(DeferredTrigger::At {
+                at: now.to_rfc3339(),
+            }),
+        ];
+        prune_finished(&mut queue, now);
+        assert_eq!(queue.len(), 2);
+    }
+
+    #[test]
+    fn the_registered_command_is_read_from_the_task_xml() {
+        let command = xml_escape("C:\\Program Files\\R&D\\magicx-toolbox.exe");
+        let xml = format!(
+            "<Task><Actions Context=\"Author\">\r\n    <Exec>\r\n      <Command>{}</Command>\r\n    </Exec></Actions></Task>",
+            command
+        );
+        assert_eq!(task_command(&xml), Some(command.as_str()));
+        assert_eq!(task_command("<Task></Task>"), None);
+    }
+
+    #[test]
+    fn a_corrupt_queue_is_moved_aside() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(QUEUE_FILE);
+        write_atomically(&path, b"[{\"id\":").unwrap();
+
+        let error = quarantine(&path, "truncated");
+        assert!(error.to_string().contains("was moved to"), "{}", error);
+        assert!(!path.exists());
+        let moved: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(moved.len(), 1);
+        assert!(moved[0].starts_with("queue.corrupt-"), "{:?}", moved);
+    }
+}
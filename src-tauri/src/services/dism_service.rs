@@ -0,0 +1,103 @@
+//! Windows optional feature service for enabling and disabling DISM features.
+//!
+//! Uses the DISM PowerShell module (`Get-WindowsOptionalFeature`, `Enable-WindowsOptionalFeature`,
+//! `Disable-WindowsOptionalFeature`) against the running image. Feature states are read as the
+//! `FeatureState` enum name, so nothing depends on localized text. Requires administrator
+//! privileges. Changes are made with `-NoRestart`; most features only finish switching after a
+//! reboot, during which DISM reports them as `EnablePending`/`DisablePending`.
+
+use crate::error::Error;
+use crate::models::{FeatureAction, FeatureChange};
+use crate::services::powershell::{ps_quote, run_script};
+
+/// Read a feature's state name (e.g. `Enabled`, `DisabledWithPayloadRemoved`).
+/// Returns `None` if no feature with that name exists on this edition.
+pub fn get_state(feature: &str) -> Result<Option<String>, Error> {
+    // Filtering the full list instead of passing -FeatureName: an unknown name is an error there,
+    // and telling it apart from a real failure would mean matching localized messages.
+    let script = format!(
+        "Get-WindowsOptionalFeature -Online | Where-Object FeatureName -eq {} | ForEach-Object {{ $_.State.ToString() }}",
+        ps_quote(feature)
+    );
+    Ok(parse_state(&run_script(&script)?))
+}
+
+/// Whether a feature is enabled, counting a pending enable as enabled.
+/// Returns `None` if the feature doesn't exist on this edition.
+pub fn is_enabled(feature: &str) -> Result<Option<bool>, Error> {
+    Ok(get_state(feature)?.as_deref().map(is_enabled_state))
+}
+
+/// Whether a state name means the feature is on, or will be after the next reboot.
+pub fn is_enabled_state(state: &str) -> bool {
+    matches!(state, "Enabled" | "EnablePending")
+}
+
+/// Turn a feature on without restarting.
+pub fn enable_feature(feature: &str) -> Result<(), Error> {
+    set_feature(feature, "Enable-WindowsOptionalFeature")?;
+    log::info!("Enabled optional feature '{}'", feature);
+    Ok(())
+}
+
+/// Turn a feature off without restarting.
+pub fn disable_feature(feature: &str) -> Result<(), Error> {
+    set_feature(feature, "Disable-WindowsOptionalFeature")?;
+    log::info!("Disabled optional feature '{}'", feature);
+    Ok(())
+}
+
+/// Run an enable/disable cmdlet and note when Windows wants a restart to finish the change.
+fn set_feature(feature: &str, cmdlet: &str) -> Result<(), Error> {
+    let script = format!(
+        "({} -Online -FeatureName {} -NoRestart).RestartNeeded",
+        cmdlet,
+        ps_quote(feature)
+    );
+    if run_script(&script)?.trim().eq_ignore_ascii_case("true") {
+        log::info!("Optional feature '{}' needs a restart to finish", feature);
+    }
+    Ok(())
+}
+
+/// Apply an optional feature change
+pub fn apply_feature_change(change: &FeatureChange) -> Result<(), Error> {
+    match change.action {
+        FeatureAction::Enable => enable_feature(&change.feature),
+        FeatureAction::Disable => disable_feature(&change.feature),
+    }
+}
+
+/// Parse the state name printed by [`get_state`]; no output means the feature doesn't exist.
+fn parse_state(output: &str) -> Option<String> {
+    output
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_output_parses_and_empty_output_is_none() {
+        assert_eq!(parse_state("Enabled\r\n").as_deref(), Some("Enabled"));
+        assert_eq!(
+            parse_state("\r\nDisabledWithPayloadRemoved\r\n").as_deref(),
+            Some("DisabledWithPayloadRemoved")
+        );
+        assert_eq!(parse_state(""), None);
+        assert_eq!(parse_state("\r\n"), None);
+    }
+
+    #[test]
+    fn pending_enable_counts_as_enabled() {
+        assert!(is_enabled_state("Enabled"));
+        assert!(is_enabled_state("EnablePending"));
+        assert!(!is_enabled_state("Disabled"));
+        assert!(!is_enabled_state("DisablePending"));
+        assert!(!is_enabled_state("DisabledWithPayloadRemoved"));
+    }
+}
@@ -14,10 +14,15 @@
 
 use crate::error::Error;
 use crate::models::{RegistryHive, RegistryValueType, SchedulerAction, ServiceStartupType};
-use crate::services::{registry_service, registry_value, scheduler_service, service_control};
+use crate::services::scheduler_service::{self, TaskChangeOutcome};
+use crate::services::service_control::{self, ServiceStatus};
+use crate::services::{audit, registry_service, registry_value};
 use serde::{Deserialize, Serialize};
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 
+use super::common::WaitLimit;
+use super::ti_elevation::{PowerShellEngine, PowerShellResult};
 use super::Elevation;
 
 const CREATE_NO_WINDOW: u32 = 0x0800_0000;
@@ -50,6 +55,8 @@ pub enum BrokerOp {
     },
     /// Start a service.
     SvcStart { name: String },
+    /// Query a service's state and startup type (answered with [`OpOutcome::Service`]).
+    SvcQuery { name: String },
     /// Stop a service.
     SvcStop { name: String },
     /// Enable / disable / delete a scheduled task.
@@ -58,12 +65,86 @@ pub enum BrokerOp {
         task_name: String,
         action: SchedulerAction,
     },
+    /// Enable / disable / delete every task in a folder whose name matches a regex (answered with
+    /// [`OpOutcome::Tasks`]).
+    SchedulerPattern {
+        task_path: String,
+        pattern: String,
+        action: SchedulerAction,
+    },
+    /// Register a scheduled task from its XML definition (recreates a deleted task).
+    SchedulerRegister {
+        task_path: String,
+        task_name: String,
+        xml: String,
+    },
     /// Run a PowerShell script (spawned as `-EncodedCommand`, no shell parsing).
-    Powershell { script: String },
+    Powershell {
+        script: String,
+        #[serde(default)]
+        engine: PowerShellEngine,
+    },
     /// Run an author-supplied `cmd.exe` command (single argv to `cmd /c`).
     RawCmd { command: String },
 }
 
+impl BrokerOp {
+    /// What the op does, as recorded in the elevation audit log: the command line for commands and
+    /// scripts, the typed operation and its target otherwise
+    fn describe(&self) -> String {
+        match self {
+            BrokerOp::RegSet {
+                hive,
+                key,
+                value_name,
+                value_type,
+                value,
+            } => format!(
+                "Set {}\\{}\\{} ({}) = {}",
+                hive.as_str(),
+                key,
+                value_name,
+                value_type.as_str(),
+                value
+            ),
+            BrokerOp::RegDeleteValue {
+                hive,
+                key,
+                value_name,
+            } => format!("Delete value {}\\{}\\{}", hive.as_str(), key, value_name),
+            BrokerOp::RegDeleteKey { hive, key } => {
+                format!("Delete key {}\\{}", hive.as_str(), key)
+            }
+            BrokerOp::RegCreateKey { hive, key } => {
+                format!("Create key {}\\{}", hive.as_str(), key)
+            }
+            BrokerOp::SvcSetStartup { name, startup } => {
+                format!("Set service {} startup to {}", name, startup.as_str())
+            }
+            BrokerOp::SvcStart { name } => format!("Start service {}", name),
+            BrokerOp::SvcQuery { name } => format!("Query service {}", name),
+            BrokerOp::SvcStop { name } => format!("Stop service {}", name),
+            BrokerOp::Scheduler {
+                task_path,
+                task_name,
+                action,
+            } => format!("{:?} task {}\\{}", action, task_path, task_name),
+            BrokerOp::SchedulerPattern {
+                task_path,
+                pattern,
+                action,
+            } => format!("{:?} tasks in {} matching '{}'", action, task_path, pattern),
+            BrokerOp::SchedulerRegister {
+                task_path,
+                task_name,
+                ..
+            } => format!("Register task {}\\{}", task_path, task_name),
+            BrokerOp::Powershell { script, engine } => format!("{} {}", engine.name(), script),
+            BrokerOp::RawCmd { command } => format!("cmd /c {}", command),
+        }
+    }
+}
+
 /// A batch of operations for one broker invocation.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct BrokerRequest {
@@ -72,6 +153,10 @@ pub struct BrokerRequest {
     /// reused pid) is detected rather than read as a fresh success. Callers may leave it 0.
     #[serde(default)]
     pub nonce: u64,
+    /// Stop at the first op that fails, leaving the rest unrun; the response then holds results
+    /// only up to and including the failure. Otherwise every op runs regardless.
+    #[serde(default)]
+    pub stop_on_error: bool,
     pub ops: Vec<BrokerOp>,
 }
 
@@ -79,6 +164,12 @@ pub struct BrokerRequest {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum OpOutcome {
     Ok,
+    /// The status read by a `SvcQuery` op.
+    Service(ServiceStatus),
+    /// The exit code and output of a `Powershell` / `RawCmd` op, also when it exited non-zero.
+    Output(PowerShellResult),
+    /// The tasks a `SchedulerPattern` op matched, with what happened to each.
+    Tasks(Vec<TaskChangeOutcome>),
     Err(String),
 }
 
@@ -89,6 +180,9 @@ pub struct BrokerResponse {
     #[serde(default)]
     pub nonce: u64,
     pub results: Vec<OpOutcome>,
+    /// How long each op took, in milliseconds. Positional like `results`.
+    #[serde(default)]
+    pub durations_ms: Vec<u64>,
 }
 
 impl BrokerResponse {
@@ -96,7 +190,8 @@ impl BrokerResponse {
     /// exactly one op.
     pub fn into_single(mut self) -> Result<(), Error> {
         match self.results.pop() {
-            Some(OpOutcome::Ok) => Ok(()),
+            Some(OpOutcome::Ok | OpOutcome::Service(_) | OpOutcome::Tasks(_)) => Ok(()),
+            Some(OpOutcome::Output(result)) => result.into_success("Command").map(|_| ()),
             Some(OpOutcome::Err(msg)) => Err(Error::ServiceControl(msg)),
             None => Err(Error::ServiceControl(
                 "broker returned no result for a single-op request".to_string(),
@@ -138,29 +233,65 @@ pub fn execute_op(op: &BrokerOp) -> Result<(), Error> {
         }
         BrokerOp::SvcStart { name } => service_control::start_service(name),
         BrokerOp::SvcStop { name } => service_control::stop_service(name),
+        BrokerOp::SvcQuery { name } => service_control::get_service_status(name).map(|_| ()),
         BrokerOp::Scheduler {
             task_path,
             task_name,
             action,
         } => scheduler_service::apply_scheduler_change(task_path, task_name, *action),
-        BrokerOp::Powershell { script } => run_powershell_encoded(script),
-        BrokerOp::RawCmd { command } => run_raw_cmd(command),
+        BrokerOp::SchedulerPattern {
+            task_path,
+            pattern,
+            action,
+        } => scheduler_service::apply_action_to_pattern(task_path, pattern, *action).map(|_| ()),
+        BrokerOp::SchedulerRegister {
+            task_path,
+            task_name,
+            xml,
+        } => scheduler_service::register_task_xml(task_path, task_name, xml),
+        BrokerOp::Powershell { script, engine } => run_powershell_encoded(script, *engine)
+            .and_then(|result| result.into_success("PowerShell"))
+            .map(|_| ()),
+        BrokerOp::RawCmd { command } => run_raw_cmd(command)
+            .and_then(|result| result.into_success("Command"))
+            .map(|_| ()),
     }
 }
 
 /// Execute every op, collecting per-op outcomes (never short-circuits: each op reports its own).
 pub fn execute_request(request: &BrokerRequest) -> BrokerResponse {
-    let results = request
-        .ops
-        .iter()
-        .map(|op| match execute_op(op) {
-            Ok(()) => OpOutcome::Ok,
-            Err(e) => OpOutcome::Err(e.to_string()),
-        })
-        .collect();
+    let mut results = Vec::with_capacity(request.ops.len());
+    let mut durations_ms = Vec::with_capacity(request.ops.len());
+    for op in &request.ops {
+        let started = Instant::now();
+        let result = match op {
+            BrokerOp::SvcQuery { name } => {
+                service_control::get_service_status(name).map(OpOutcome::Service)
+            }
+            BrokerOp::SchedulerPattern {
+                task_path,
+                pattern,
+                action,
+            } => scheduler_service::apply_action_to_pattern(task_path, pattern, *action)
+                .map(OpOutcome::Tasks),
+            BrokerOp::Powershell { script, engine } => {
+                run_powershell_encoded(script, *engine).map(OpOutcome::Output)
+            }
+            BrokerOp::RawCmd { command } => run_raw_cmd(command).map(OpOutcome::Output),
+            _ => execute_op(op).map(|()| OpOutcome::Ok),
+        };
+        let outcome = result.unwrap_or_else(|e| OpOutcome::Err(e.to_string()));
+        let failed = matches!(outcome, OpOutcome::Err(_));
+        results.push(outcome);
+        durations_ms.push(started.elapsed().as_millis() as u64);
+        if failed && request.stop_on_error {
+            break;
+        }
+    }
     BrokerResponse {
         nonce: request.nonce,
         results,
+        durations_ms,
     }
 }
 
@@ -200,6 +331,47 @@ pub fn run_broker(req_path: &str, resp_path: &str) -> i32 {
     0
 }
 
+/// Put the broker in a job that ends every process still in it when the broker exits. A command the
+/// app gave up on (timed out or cancelled) is ended by terminating its broker; without the job, the
+/// `cmd` / PowerShell process the broker started would keep running elevated. Best effort: a broker
+/// that can't set up the job still runs.
+pub fn kill_children_on_exit() {
+    use std::ffi::c_void;
+    use windows_sys::Win32::Foundation::{CloseHandle, GetLastError, FALSE};
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation,
+        SetInformationJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+        JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+    };
+    use windows_sys::Win32::System::Threading::GetCurrentProcess;
+
+    // SAFETY: `info` is a zero-initialized plain-data struct that outlives the calls. On success
+    // the job handle is deliberately left open: Windows closes it when the broker exits, which is
+    // what ends the processes left in the job.
+    unsafe {
+        let job = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+        if job.is_null() {
+            eprintln!("broker: CreateJobObject failed: {}", GetLastError());
+            return;
+        }
+        let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+        info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+        let configured = SetInformationJobObject(
+            job,
+            JobObjectExtendedLimitInformation,
+            &info as *const JOBOBJECT_EXTENDED_LIMIT_INFORMATION as *const c_void,
+            std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+        ) != FALSE;
+        if !configured || AssignProcessToJobObject(job, GetCurrentProcess()) == FALSE {
+            eprintln!(
+                "broker: failed to set up the job object: {}",
+                GetLastError()
+            );
+            CloseHandle(job);
+        }
+    }
+}
+
 /// Monotonic counter mixed into the per-invocation transport nonce.
 static BROKER_SEQ: AtomicU64 = AtomicU64::new(0);
 
@@ -207,7 +379,7 @@ static BROKER_SEQ: AtomicU64 = AtomicU64::new(0);
 /// invocations get distinct nonces even across a process restart that reuses our pid and resets the
 /// counter — the exact conjunction that could otherwise let a stale response file be read as a
 /// fresh success.
-fn next_nonce() -> u64 {
+pub(super) fn next_nonce() -> u64 {
     use std::time::{SystemTime, UNIX_EPOCH};
     let seq = BROKER_SEQ.fetch_add(1, Ordering::SeqCst);
     let nanos = SystemTime::now()
@@ -221,7 +393,10 @@ fn next_nonce() -> u64 {
 
 /// Parse a broker response, rejecting it unless its nonce matches the one we sent. This is what
 /// turns a stale or foreign response file into a hard error instead of a silent success.
-fn validate_response(resp_bytes: &[u8], expected_nonce: u64) -> Result<BrokerResponse, Error> {
+pub(super) fn validate_response(
+    resp_bytes: &[u8],
+    expected_nonce: u64,
+) -> Result<BrokerResponse, Error> {
     let resp: BrokerResponse = serde_json::from_slice(resp_bytes)
         .map_err(|e| Error::ServiceControl(format!("parse broker response: {}", e)))?;
     if resp.nonce != expected_nonce {
@@ -236,23 +411,89 @@ fn validate_response(resp_bytes: &[u8], expected_nonce: u64) -> Result<BrokerRes
 /// Run a batch of typed operations at the given elevation.
 ///
 /// `Elevation::None` runs them in-process (the effect services already hold the needed rights).
-/// `System` / `TrustedInstaller` serialize the request to a temp file and spawn
-/// `<this exe> --broker <req> <resp>` under the corresponding token (reusing the winlogon token-dup
-/// / TI parent-spoof primitives), then read the typed response back. No shell parses the
-/// operations, and the request *data* never appears on a command line — only our controlled
-/// temp-file paths do.
+/// `System` / `TrustedInstaller` send them to the level's persistent broker session (see
+/// `session`). Only if no session can be started do they fall back to a one-shot broker: serialize
+/// the request to a temp file and spawn `<this exe> --broker <req> <resp>` under the corresponding
+/// token (reusing the winlogon token-dup / TI parent-spoof primitives), then read the typed
+/// response back. No shell parses the operations, and the request *data* never appears on a
+/// command line — only our controlled temp-file paths and pipe names do.
+///
+/// Trust in a one-shot response is gated three ways: the response path is pre-cleared, the child's
+/// exit code must be 0 (run_broker returns 0 only *after* writing the response), and the
+/// response's nonce must match the one sent — so a leftover file from a prior run can never be read
+/// as this run's result.
+///
+/// An elevated request may take `timeout` (`ELEVATED_PROCESS_TIMEOUT_MS` when None), and can be
+/// cancelled with `cancel_elevated_operations`; either ends its broker and fails it with
+/// `Error::Timeout` / `Error::Cancelled`.
 ///
-/// Trust in the response is gated three ways: the response path is pre-cleared, the child's exit
-/// code must be 0 (run_broker returns 0 only *after* writing the response), and the response's
-/// nonce must match the one sent — so a leftover file from a prior run can never be read as this
-/// run's result.
+/// Every elevated op is recorded in the elevation audit log (see `audit`) once the request ends.
 pub fn run_elevated_broker(
     level: Elevation,
     request: &BrokerRequest,
+    timeout: Option<Duration>,
 ) -> Result<BrokerResponse, Error> {
     if !level.is_elevated() {
         return Ok(execute_request(request));
     }
+    let started = Instant::now();
+    let result = send_to_broker(level, request, timeout);
+    record_in_audit_log(level, request, &result, started.elapsed());
+    result
+}
+
+/// Record each op of an elevated request with its outcome and how long the broker took to run
+/// it, or with the request's error when no response came back (timed out, cancelled, or the broker
+/// failed). Without a response no op's own time is known, so the first op takes the time the
+/// request ran and the rest none, keeping the log's durations adding up to it.
+fn record_in_audit_log(
+    level: Elevation,
+    request: &BrokerRequest,
+    result: &Result<BrokerResponse, Error>,
+    request_duration: Duration,
+) {
+    for (index, op) in request.ops.iter().enumerate() {
+        let (exit_code, error, duration) = match result {
+            Ok(response) => {
+                let duration =
+                    Duration::from_millis(response.durations_ms.get(index).copied().unwrap_or(0));
+                match response.results.get(index) {
+                    Some(OpOutcome::Output(output)) => (Some(output.exit_code), None, duration),
+                    Some(OpOutcome::Err(msg)) => (None, Some(msg.clone()), duration),
+                    Some(_) => (Some(0), None, duration),
+                    // Stopped at an earlier failure, so this op never ran
+                    None if request.stop_on_error => continue,
+                    None => (
+                        None,
+                        Some("no result from the broker".to_string()),
+                        duration,
+                    ),
+                }
+            }
+            Err(e) => {
+                let duration = if index == 0 {
+                    request_duration
+                } else {
+                    Duration::ZERO
+                };
+                (None, Some(e.to_string()), duration)
+            }
+        };
+        audit::record(level, &op.describe(), exit_code, error, duration);
+    }
+}
+
+/// Send an elevated request to the level's broker session, or to a one-shot broker without one
+fn send_to_broker(
+    level: Elevation,
+    request: &BrokerRequest,
+    timeout: Option<Duration>,
+) -> Result<BrokerResponse, Error> {
+    crate::operations::check(&format!("a {} call", level.label()))?;
+    let limit = WaitLimit::start(timeout);
+    if let Some(response) = super::session::run_in_session(level, request, &limit)? {
+        return Ok(response);
+    }
 
     let exe = std::env::current_exe()
         .map_err(|e| Error::ServiceControl(format!("current_exe failed: {}", e)))?;
@@ -260,7 +501,7 @@ pub fn run_elevated_broker(
     let nonce = next_nonce();
     let wire = BrokerRequest {
         nonce,
-        ops: request.ops.clone(),
+        ..request.clone()
     };
 
     let dir = std::env::temp_dir();
@@ -276,18 +517,17 @@ pub fn run_elevated_broker(
     std::fs::write(&req_path, &req_json)
         .map_err(|e| Error::ServiceControl(format!("write broker request: {}", e)))?;
 
-    // Spawn "<exe>" --broker "<req>" "<resp>" directly (no cmd.exe wrapper). Paths are quoted; the
-    // values are our own generated temp names, never untrusted data.
-    let cmdline = format!(
-        "\"{}\" --broker \"{}\" \"{}\"",
-        exe.display(),
-        req_path.display(),
-        resp_path.display()
-    );
+    // Spawn "<exe>" --broker "<req>" "<resp>" directly (no cmd.exe wrapper). Each path is quoted by
+    // the argv rules the child parses with, so whatever %TEMP% contains reads back unchanged.
+    let req_arg = req_path.to_string_lossy();
+    let resp_arg = resp_path.to_string_lossy();
+    let cmdline = super::common::command_line(&exe, &["--broker", &req_arg, &resp_arg]);
 
     let spawn = match level {
-        Elevation::System => super::system_elevation::spawn_as_system(&cmdline),
-        Elevation::TrustedInstaller => super::ti_elevation::spawn_as_trusted_installer(&cmdline),
+        Elevation::System => super::system_elevation::spawn_as_system(&cmdline, &limit),
+        Elevation::TrustedInstaller => {
+            super::ti_elevation::spawn_as_trusted_installer(&cmdline, &limit)
+        }
         Elevation::None => unreachable!("handled above"),
     };
 
@@ -310,6 +550,45 @@ pub fn run_elevated_broker(
     validate_response(&read?, nonce)
 }
 
+/// Run a single operation at the given elevation, returning its outcome.
+fn run_single(
+    level: Elevation,
+    op: BrokerOp,
+    timeout: Option<Duration>,
+) -> Result<OpOutcome, Error> {
+    run_elevated_broker(
+        level,
+        &BrokerRequest {
+            nonce: 0,
+            stop_on_error: false,
+            ops: vec![op],
+        },
+        timeout,
+    )?
+    .results
+    .pop()
+    .ok_or_else(|| {
+        Error::ServiceControl("broker returned no result for a single-op request".to_string())
+    })
+}
+
+/// Run a `Powershell` or `RawCmd` op at the given elevation, returning its exit code and output. A
+/// non-zero exit is not an error here; the caller decides, with the output at hand. `timeout`
+/// replaces the default elevated-process timeout, for commands known to run long.
+pub(super) fn run_command_op(
+    level: Elevation,
+    op: BrokerOp,
+    timeout: Option<Duration>,
+) -> Result<PowerShellResult, Error> {
+    match run_single(level, op, timeout)? {
+        OpOutcome::Output(result) => Ok(result),
+        OpOutcome::Err(msg) => Err(Error::CommandExecution(msg)),
+        _ => Err(Error::CommandExecution(
+            "broker returned no output for a command".to_string(),
+        )),
+    }
+}
+
 /// Run a single operation at the given elevation, returning `Ok(())` on success. The elevated
 /// wrappers (`*_as_ti` / `*_as_system`) submit exactly one op through this.
 pub(super) fn run_one(level: Elevation, op: BrokerOp) -> Result<(), Error> {
@@ -317,12 +596,121 @@ pub(super) fn run_one(level: Elevation, op: BrokerOp) -> Result<(), Error> {
         level,
         &BrokerRequest {
             nonce: 0,
+            stop_on_error: false,
             ops: vec![op],
         },
+        None,
     )?
     .into_single()
 }
 
+/// A registry operation for [`run_registry_batch`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegistryOp {
+    /// Set a typed value.
+    Set {
+        hive: RegistryHive,
+        key: String,
+        value_name: String,
+        value_type: RegistryValueType,
+        value: serde_json::Value,
+    },
+    /// Delete a value (absent value is success).
+    DeleteValue {
+        hive: RegistryHive,
+        key: String,
+        value_name: String,
+    },
+    /// Delete a key recursively (absent key is success).
+    DeleteKey { hive: RegistryHive, key: String },
+    /// Create an empty key.
+    CreateKey { hive: RegistryHive, key: String },
+}
+
+impl From<RegistryOp> for BrokerOp {
+    fn from(op: RegistryOp) -> Self {
+        match op {
+            RegistryOp::Set {
+                hive,
+                key,
+                value_name,
+                value_type,
+                value,
+            } => BrokerOp::RegSet {
+                hive,
+                key,
+                value_name,
+                value_type,
+                value,
+            },
+            RegistryOp::DeleteValue {
+                hive,
+                key,
+                value_name,
+            } => BrokerOp::RegDeleteValue {
+                hive,
+                key,
+                value_name,
+            },
+            RegistryOp::DeleteKey { hive, key } => BrokerOp::RegDeleteKey { hive, key },
+            RegistryOp::CreateKey { hive, key } => BrokerOp::RegCreateKey { hive, key },
+        }
+    }
+}
+
+/// Run registry operations at `level` in order, stopping at the first one that fails.
+///
+/// SYSTEM / TrustedInstaller send the whole list to the broker in one round trip instead of one
+/// per operation, which is what makes a tweak with dozens of protected HKLM values fast.
+/// Unelevated runs them in-process and keeps each failure's typed error (e.g. `RequiresAdmin`).
+///
+/// The result has one entry per operation that ran: all of them, or those up to and including the
+/// failure, which is the last entry. An outer error means the batch as a whole failed (the broker
+/// couldn't start, timed out, or was cancelled), and any prefix of the operations may have run.
+pub fn run_registry_batch(
+    level: Elevation,
+    ops: &[RegistryOp],
+) -> Result<Vec<Result<(), Error>>, Error> {
+    if !level.is_elevated() {
+        let mut results = Vec::with_capacity(ops.len());
+        for op in ops {
+            let result = execute_op(&BrokerOp::from(op.clone()));
+            let failed = result.is_err();
+            results.push(result);
+            if failed {
+                break;
+            }
+        }
+        return Ok(results);
+    }
+
+    let request = BrokerRequest {
+        nonce: 0,
+        stop_on_error: true,
+        ops: ops.iter().cloned().map(BrokerOp::from).collect(),
+    };
+    let results: Vec<Result<(), Error>> = run_elevated_broker(level, &request, None)?
+        .results
+        .into_iter()
+        .map(|outcome| match outcome {
+            OpOutcome::Err(msg) => Err(Error::ServiceControl(msg)),
+            _ => Ok(()),
+        })
+        .collect();
+
+    // A short response is only valid when it ends at the failure that stopped it
+    let complete = results.len() == ops.len()
+        || (results.len() < ops.len() && results.last().is_some_and(|result| result.is_err()));
+    if !complete {
+        return Err(Error::ServiceControl(format!(
+            "broker returned {} results for {} registry operations",
+            results.len(),
+            ops.len()
+        )));
+    }
+    Ok(results)
+}
+
 /// Apply a scheduler change (enable / disable / delete) at `level` via the typed `Scheduler` op.
 ///
 /// Unelevated runs the COM op in-process; SYSTEM / TrustedInstaller run the *same* typed op inside
@@ -344,40 +732,96 @@ pub fn run_scheduler_op(
     )
 }
 
-/// Run a PowerShell script via `-EncodedCommand` (base64 of UTF-16LE). No shell parses the script.
-fn run_powershell_encoded(script: &str) -> Result<(), Error> {
+/// Apply a scheduler change to every task in `task_path` matching `pattern` at `level` via the typed
+/// `SchedulerPattern` op. The broker lists and changes the tasks in one round trip instead of one
+/// per task; the result holds each matched task's outcome and resulting state.
+pub fn run_scheduler_pattern(
+    level: Elevation,
+    task_path: &str,
+    pattern: &str,
+    action: SchedulerAction,
+) -> Result<Vec<TaskChangeOutcome>, Error> {
+    let op = BrokerOp::SchedulerPattern {
+        task_path: task_path.to_string(),
+        pattern: pattern.to_string(),
+        action,
+    };
+    match run_single(level, op, None)? {
+        OpOutcome::Tasks(outcomes) => Ok(outcomes),
+        OpOutcome::Err(msg) => Err(Error::CommandExecution(msg)),
+        _ => Err(Error::CommandExecution(
+            "broker returned no task results for a scheduler pattern".to_string(),
+        )),
+    }
+}
+
+/// Register a scheduled task from its XML definition at `level` via the typed `SchedulerRegister`
+/// op, the same way [`run_scheduler_op`] runs enable / disable / delete.
+pub fn run_scheduler_register(
+    level: Elevation,
+    task_path: &str,
+    task_name: &str,
+    xml: &str,
+) -> Result<(), Error> {
+    run_one(
+        level,
+        BrokerOp::SchedulerRegister {
+            task_path: task_path.to_string(),
+            task_name: task_name.to_string(),
+            xml: xml.to_string(),
+        },
+    )
+}
+
+/// Query a service's status at `level` via the typed `SvcQuery` op.
+///
+/// Protected services (Defender, some telemetry services) deny `SERVICE_QUERY_STATUS`, or their
+/// `Start` value, to an administrator; the broker's SYSTEM / TrustedInstaller token can read both.
+pub fn run_service_query(level: Elevation, service_name: &str) -> Result<ServiceStatus, Error> {
+    let op = BrokerOp::SvcQuery {
+        name: service_name.to_string(),
+    };
+    match run_single(level, op, None)? {
+        OpOutcome::Service(status) => Ok(status),
+        OpOutcome::Err(msg) => Err(Error::ServiceControl(msg)),
+        _ => Err(Error::ServiceControl(
+            "broker returned no status for a service query".to_string(),
+        )),
+    }
+}
+
+/// Run a PowerShell script in `engine` via `-EncodedCommand` (base64 of UTF-16LE). No shell parses
+/// the script. Only a failure to start PowerShell is an error; a non-zero exit is in the result.
+pub(super) fn run_powershell_encoded(
+    script: &str,
+    engine: PowerShellEngine,
+) -> Result<PowerShellResult, Error> {
     use std::os::windows::process::CommandExt;
 
     let utf16: Vec<u8> = script.encode_utf16().flat_map(u16::to_le_bytes).collect();
     let encoded = base64_encode(&utf16);
 
-    let output = std::process::Command::new("powershell.exe")
+    let output = std::process::Command::new(engine.executable()?)
         .args([
             "-NoProfile",
             "-NonInteractive",
             "-WindowStyle",
             "Hidden",
+            "-ExecutionPolicy",
+            "Bypass",
             "-EncodedCommand",
             &encoded,
         ])
         .creation_flags(CREATE_NO_WINDOW)
         .output()
         .map_err(|e| Error::CommandExecution(format!("Failed to run PowerShell: {}", e)))?;
-
-    if output.status.success() {
-        Ok(())
-    } else {
-        Err(Error::CommandExecution(format!(
-            "PowerShell failed with exit code {}: {}",
-            output.status.code().unwrap_or(-1),
-            String::from_utf8_lossy(&output.stderr).trim()
-        )))
-    }
+    Ok(PowerShellResult::from_output(&output))
 }
 
 /// Run an author-supplied `cmd.exe` command as a single raw argument (no escaping of a value into a
-/// larger command — the string IS the author's command).
-fn run_raw_cmd(command: &str) -> Result<(), Error> {
+/// larger command — the string IS the author's command). Like `run_powershell_encoded`, a non-zero
+/// exit is in the result rather than an error.
+fn run_raw_cmd(command: &str) -> Result<PowerShellResult, Error> {
     use std::os::windows::process::CommandExt;
 
     let output = std::process::Command::new("cmd")
@@ -385,16 +829,7 @@ fn run_raw_cmd(command: &str) -> Result<(), Error> {
         .creation_flags(CREATE_NO_WINDOW)
         .output()
         .map_err(|e| Error::CommandExecution(format!("Failed to run command: {}", e)))?;
-
-    if output.status.success() {
-        Ok(())
-    } else {
-        Err(Error::CommandExecution(format!(
-            "Command failed with exit code {}: {}",
-            output.status.code().unwrap_or(-1),
-            String::from_utf8_lossy(&output.stderr).trim()
-        )))
-    }
+    Ok(PowerShellResult::from_output(&output))
 }
 
 /// Standard base64 (RFC 4648) encoder — small enough not to justify a dependency.
@@ -463,6 +898,7 @@ mod tests {
     fn request_round_trips_through_json() {
         let req = BrokerRequest {
             nonce: 0xDEAD_BEEF,
+            stop_on_error: false,
             ops: vec![
                 BrokerOp::RegSet {
                     hive: RegistryHive::Hklm,
@@ -474,6 +910,12 @@ mod tests {
                 BrokerOp::SvcStop {
                     name: "Spooler".into(),
                 },
+                BrokerOp::SchedulerPattern {
+                    task_path: "\\Microsoft\\Windows\\Customer Experience Improvement Program"
+                        .into(),
+                    pattern: "^(Consolidator|UsbCeip)$".into(),
+                    action: SchedulerAction::Disable,
+                },
             ],
         };
         let json = serde_json::to_vec(&req).unwrap();
@@ -510,6 +952,175 @@ mod tests {
         );
     }
 
+    #[test]
+    fn executor_writes_binary_and_multi_string_values() {
+        // Every type reaches the broker as data, including the ones `reg add` couldn't express
+        // safely: binary blobs and string lists with quotes, `%` and `&` in them.
+        let scratch = Scratch::new();
+
+        let binary = BrokerOp::RegSet {
+            hive: RegistryHive::Hkcu,
+            key: scratch.key.clone(),
+            value_name: "Blob".into(),
+            value_type: RegistryValueType::Binary,
+            value: serde_json::json!([0, 1, 254, 255]),
+        };
+        assert!(execute_op(&binary).is_ok());
+        assert_eq!(
+            registry_service::read_binary(&RegistryHive::Hkcu, &scratch.key, "Blob").unwrap(),
+            Some(vec![0, 1, 254, 255])
+        );
+
+        let items = vec!["\"quoted\" value".to_string(), "%PATH% & more".to_string()];
+        let multi = BrokerOp::RegSet {
+            hive: RegistryHive::Hkcu,
+            key: scratch.key.clone(),
+            value_name: "List".into(),
+            value_type: RegistryValueType::MultiString,
+            value: serde_json::json!(items),
+        };
+        assert!(execute_op(&multi).is_ok());
+        assert_eq!(
+            registry_service::read_multi_string(&RegistryHive::Hkcu, &scratch.key, "List").unwrap(),
+            Some(items)
+        );
+    }
+
+    /// Registry names and data that a shell command line could never carry intact: quotes, trailing
+    /// backslashes, cmd metacharacters and environment-variable syntax
+    const ADVERSARIAL: &[&str] = &[
+        "\"",
+        "trailing\\",
+        "%PATH%",
+        "%%USERPROFILE%%",
+        "a\" & calc.exe & \"b",
+        "^&|<>()!",
+        "!ComSpec!",
+        " leading and trailing ",
+        "tab\there",
+        "line\nbreak",
+        "\\\"\\\\\"",
+        "é 中 ✓",
+    ];
+
+    #[test]
+    fn adversarial_names_and_data_round_trip_through_the_broker() {
+        let scratch = Scratch::new();
+        for (i, text) in ADVERSARIAL.iter().enumerate() {
+            // Key names can't hold a backslash (the subkey separator) or control characters
+            let key_name = text.replace(['\\', '\t', '\n'], "/");
+            let key = format!("{}\\{}_{}", scratch.key, i, key_name);
+            let ops = vec![
+                BrokerOp::RegSet {
+                    hive: RegistryHive::Hkcu,
+                    key: key.clone(),
+                    value_name: text.to_string(),
+                    value_type: RegistryValueType::String,
+                    value: serde_json::json!(text),
+                },
+                BrokerOp::RegSet {
+                    hive: RegistryHive::Hkcu,
+                    key: key.clone(),
+                    value_name: format!("{text} list"),
+                    value_type: RegistryValueType::MultiString,
+                    value: serde_json::json!([text, "after"]),
+                },
+            ];
+
+            // The request crosses the process boundary as JSON, then runs as typed ops
+            let req = BrokerRequest {
+                nonce: 0,
+                stop_on_error: false,
+                ops,
+            };
+            let back: BrokerRequest =
+                serde_json::from_slice(&serde_json::to_vec(&req).unwrap()).unwrap();
+            assert_eq!(req, back, "request for {text:?}");
+            let resp = execute_request(&back);
+            assert_eq!(resp.results, vec![OpOutcome::Ok, OpOutcome::Ok], "{text:?}");
+
+            assert_eq!(
+                registry_service::read_string(&RegistryHive::Hkcu, &key, text).unwrap(),
+                Some(text.to_string())
+            );
+            assert_eq!(
+                registry_service::read_multi_string(
+                    &RegistryHive::Hkcu,
+                    &key,
+                    &format!("{text} list")
+                )
+                .unwrap(),
+                Some(vec![text.to_string(), "after".to_string()])
+            );
+        }
+    }
+
+    #[test]
+    fn a_registry_batch_stops_at_the_first_failure() {
+        let scratch = Scratch::new();
+        let set = |name: &str, value: serde_json::Value| RegistryOp::Set {
+            hive: RegistryHive::Hkcu,
+            key: scratch.key.clone(),
+            value_name: name.into(),
+            value_type: RegistryValueType::Dword,
+            value,
+        };
+        let ops = [
+            set("First", serde_json::json!(1)),
+            // Not a DWORD, so the write fails before touching the registry
+            set("Bad", serde_json::json!("not a number")),
+            set("Never", serde_json::json!(3)),
+        ];
+
+        let results = run_registry_batch(Elevation::None, &ops).unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_ok());
+        assert!(matches!(results[1], Err(Error::ValidationError(_))));
+        assert_eq!(
+            registry_service::read_dword(&RegistryHive::Hkcu, &scratch.key, "First").unwrap(),
+            Some(1)
+        );
+        assert_eq!(
+            registry_service::read_dword(&RegistryHive::Hkcu, &scratch.key, "Never").unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn a_request_that_stops_on_error_leaves_the_rest_unrun() {
+        let scratch = Scratch::new();
+        let ops = vec![
+            BrokerOp::RegSet {
+                hive: RegistryHive::Hkcu,
+                key: scratch.key.clone(),
+                value_name: "Bad".into(),
+                value_type: RegistryValueType::Dword,
+                value: serde_json::json!("not a number"),
+            },
+            BrokerOp::RegCreateKey {
+                hive: RegistryHive::Hkcu,
+                key: scratch.key.clone(),
+            },
+        ];
+
+        let stopped = execute_request(&BrokerRequest {
+            nonce: 0,
+            stop_on_error: true,
+            ops: ops.clone(),
+        });
+        assert_eq!(stopped.results.len(), 1);
+        assert!(matches!(stopped.results[0], OpOutcome::Err(_)));
+        assert!(!registry_service::key_exists(&RegistryHive::Hkcu, &scratch.key).unwrap());
+
+        let all = execute_request(&BrokerRequest {
+            nonce: 0,
+            stop_on_error: false,
+            ops,
+        });
+        assert_eq!(all.results.len(), 2);
+        assert_eq!(all.results[1], OpOutcome::Ok);
+    }
+
     #[test]
     fn deleting_an_absent_value_is_success() {
         let scratch = Scratch::new();
@@ -532,6 +1143,7 @@ mod tests {
         let scratch = Scratch::new();
         let req = BrokerRequest {
             nonce: 0,
+            stop_on_error: false,
             ops: vec![
                 BrokerOp::RegCreateKey {
                     hive: RegistryHive::Hkcu,
@@ -548,6 +1160,8 @@ mod tests {
         };
         let resp = execute_request(&req);
         assert_eq!(resp.results, vec![OpOutcome::Ok, OpOutcome::Ok]);
+        // Each op is timed on its own, for the audit log
+        assert_eq!(resp.durations_ms.len(), 2);
     }
 
     #[test]
@@ -569,6 +1183,7 @@ mod tests {
 
         let req = BrokerRequest {
             nonce: 0,
+            stop_on_error: false,
             ops: vec![
                 BrokerOp::RegCreateKey {
                     hive: RegistryHive::Hkcu,
@@ -606,6 +1221,7 @@ mod tests {
         let scratch = Scratch::new();
         let req = BrokerRequest {
             nonce: 0,
+            stop_on_error: false,
             ops: vec![BrokerOp::RegSet {
                 hive: RegistryHive::Hkcu,
                 key: scratch.key.clone(),
@@ -614,7 +1230,7 @@ mod tests {
                 value: serde_json::json!(5),
             }],
         };
-        let resp = run_elevated_broker(Elevation::None, &req).unwrap();
+        let resp = run_elevated_broker(Elevation::None, &req, None).unwrap();
         assert_eq!(resp.results, vec![OpOutcome::Ok]);
         assert_eq!(
             registry_service::read_dword(&RegistryHive::Hkcu, &scratch.key, "N").unwrap(),
@@ -622,10 +1238,52 @@ mod tests {
         );
     }
 
+    #[test]
+    fn a_service_query_answers_with_the_status() {
+        let resp = execute_request(&BrokerRequest {
+            nonce: 0,
+            stop_on_error: false,
+            ops: vec![BrokerOp::SvcQuery {
+                name: "MagicXToolboxNoSuchService".into(),
+            }],
+        });
+        match resp.results.as_slice() {
+            [OpOutcome::Service(status)] => assert!(!status.exists),
+            other => panic!("expected a service status, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn a_command_answers_with_its_exit_code_and_output() {
+        let resp = execute_request(&BrokerRequest {
+            nonce: 0,
+            stop_on_error: false,
+            ops: vec![
+                BrokerOp::RawCmd {
+                    command: "echo broker-output".into(),
+                },
+                BrokerOp::RawCmd {
+                    command: "echo oops 1>&2 & exit /b 3".into(),
+                },
+            ],
+        });
+        match resp.results.as_slice() {
+            [OpOutcome::Output(ok), OpOutcome::Output(failed)] => {
+                assert!(ok.success);
+                assert!(ok.stdout.contains("broker-output"));
+                assert!(!failed.success);
+                assert_eq!(failed.exit_code, 3);
+                assert_eq!(failed.error_output(), "oops");
+            }
+            other => panic!("expected command output, got {other:?}"),
+        }
+    }
+
     #[test]
     fn execute_request_echoes_the_request_nonce() {
         let resp = execute_request(&BrokerRequest {
             nonce: 0xABCD_1234,
+            stop_on_error: false,
             ops: vec![],
         });
         assert_eq!(resp.nonce, 0xABCD_1234);
@@ -639,6 +1297,7 @@ mod tests {
         let stale = serde_json::to_vec(&BrokerResponse {
             nonce: 111,
             results: vec![OpOutcome::Ok],
+            durations_ms: vec![0],
         })
         .unwrap();
         let err = validate_response(&stale, 222).expect_err("mismatched nonce must be rejected");
@@ -650,6 +1309,7 @@ mod tests {
         let good = serde_json::to_vec(&BrokerResponse {
             nonce: 222,
             results: vec![OpOutcome::Ok],
+            durations_ms: vec![0],
         })
         .unwrap();
         let resp = validate_response(&good, 222).expect("matching nonce must validate");
@@ -2,13 +2,18 @@
 //!
 //! Shared utilities for elevation services:
 //! - String conversion functions
-//! - Security helpers (escaping, validation)
+//! - Security helpers (argument quoting, validation)
 //! - Windows API constants
+//! - The time limit and cancellation of elevated operations
 
 use crate::error::Error;
+use crate::models::{IntegrityLevel, ProcessPrivileges, TokenElevationType, TokenPrivilege};
 use std::ffi::OsStr;
 use std::os::windows::ffi::OsStrExt;
 use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 pub use windows_sys::Win32::Foundation::{CloseHandle, GetLastError, FALSE, HANDLE, LUID};
 pub use windows_sys::Win32::Security::{
@@ -36,9 +41,88 @@ pub use windows_sys::Win32::UI::WindowsAndMessaging::SW_HIDE;
 
 pub const INVALID_HANDLE_VALUE: HANDLE = -1isize as HANDLE;
 pub const STARTF_USESHOWWINDOW: u32 = 0x00000001;
-/// Timeout for waiting on elevated processes (30 seconds)
+/// Timeout for waiting on elevated processes (30 seconds), unless the operation sets its own
 pub const ELEVATED_PROCESS_TIMEOUT_MS: u32 = 30_000;
 
+/// How often a wait on an elevated process checks whether it was cancelled
+const CANCEL_POLL: Duration = Duration::from_millis(100);
+
+/// Bumped by `cancel_elevated_operations`; a wait that started before the bump is cancelled
+static CANCEL_EPOCH: AtomicU64 = AtomicU64::new(0);
+
+/// Why a wait on an elevated process was cut short
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interruption {
+    TimedOut,
+    Cancelled,
+}
+
+/// How long an elevated operation may take, and whether it has been cancelled. Started once per
+/// operation, so the time spent starting a broker counts against the timeout too.
+#[derive(Debug, Clone)]
+pub struct WaitLimit {
+    timeout: Duration,
+    deadline: Instant,
+    epoch: u64,
+    /// Cancellation flag of the tweak operation the call belongs to (see `crate::operations`)
+    operation: Option<Arc<AtomicBool>>,
+}
+
+impl WaitLimit {
+    /// Start the clock on an operation allowed `timeout` (`ELEVATED_PROCESS_TIMEOUT_MS` when None)
+    pub fn start(timeout: Option<Duration>) -> Self {
+        let timeout = timeout
+            .unwrap_or_else(|| Duration::from_millis(u64::from(ELEVATED_PROCESS_TIMEOUT_MS)));
+        Self {
+            timeout,
+            deadline: Instant::now() + timeout,
+            epoch: CANCEL_EPOCH.load(Ordering::SeqCst),
+            operation: crate::operations::current_flag(),
+        }
+    }
+
+    /// How long to wait before checking again
+    pub fn slice(&self) -> Duration {
+        self.deadline
+            .saturating_duration_since(Instant::now())
+            .min(CANCEL_POLL)
+    }
+
+    /// Why the wait has to stop now, if it does
+    pub fn interruption(&self) -> Option<Interruption> {
+        let operation_cancelled = self
+            .operation
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::SeqCst));
+        if operation_cancelled || CANCEL_EPOCH.load(Ordering::SeqCst) != self.epoch {
+            Some(Interruption::Cancelled)
+        } else if Instant::now() >= self.deadline {
+            Some(Interruption::TimedOut)
+        } else {
+            None
+        }
+    }
+
+    /// The error the operation described by `label` fails with after `interruption`
+    pub fn error(&self, interruption: Interruption, label: &str) -> Error {
+        match interruption {
+            Interruption::TimedOut => Error::Timeout(format!(
+                "{} did not finish within {:?}",
+                label, self.timeout
+            )),
+            Interruption::Cancelled => Error::Cancelled(format!("{} was cancelled", label)),
+        }
+    }
+}
+
+/// Cancel every elevated operation running now: the broker each one waits on is terminated, taking
+/// the commands it started with it, and the operation fails with `Error::Cancelled`. Operations
+/// started afterwards run normally.
+pub fn cancel_elevated_operations() {
+    CANCEL_EPOCH.fetch_add(1, Ordering::SeqCst);
+    log::info!("Cancelling running elevated operations");
+}
+
 /// Service is running (dwCurrentState value)
 pub const SERVICE_RUNNING: u32 = 4;
 
@@ -54,6 +138,51 @@ pub fn to_wide_string(s: &str) -> Vec<u16> {
         .collect()
 }
 
+/// Quote one argument for a `CreateProcess` command line so the child's argument parser (the
+/// `CommandLineToArgvW` rules the Rust and C runtimes follow) reads it back unchanged. Backslashes
+/// are literal except before a quote, where they are doubled; quotes are escaped. No shell is
+/// involved, so `%`, `^`, `&` and the like need no escaping and are never expanded.
+pub fn quote_arg(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '\n', '\u{b}', '"']) {
+        return arg.to_string();
+    }
+    let mut quoted = String::with_capacity(arg.len() + 2);
+    quoted.push('"');
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                // 2n+1 backslashes before a quote read back as n backslashes and the quote
+                quoted.extend(std::iter::repeat_n('\\', backslashes * 2 + 1));
+                quoted.push('"');
+                backslashes = 0;
+            }
+            _ => {
+                quoted.extend(std::iter::repeat_n('\\', backslashes));
+                quoted.push(c);
+                backslashes = 0;
+            }
+        }
+    }
+    // The closing quote must not be escaped by trailing backslashes
+    quoted.extend(std::iter::repeat_n('\\', backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+/// Build a `CreateProcess` command line running `program` with `args`, each quoted with
+/// [`quote_arg`]. The program is always quoted, since a path with spaces would otherwise be split
+/// (and searched for piece by piece); Windows paths can't contain quotes.
+pub fn command_line(program: &std::path::Path, args: &[&str]) -> String {
+    let mut line = format!("\"{}\"", program.display());
+    for arg in args {
+        line.push(' ');
+        line.push_str(&quote_arg(arg));
+    }
+    line
+}
+
 /// Convert u16 to lowercase for case-insensitive comparison
 pub fn char_to_lower(c: u16) -> u16 {
     if c >= b'A' as u16 && c <= b'Z' as u16 {
@@ -65,6 +194,12 @@ pub fn char_to_lower(c: u16) -> u16 {
 
 /// Enable SeDebugPrivilege for the current process
 pub fn enable_debug_privilege() -> Result<(), Error> {
+    enable_privilege("SeDebugPrivilege")
+}
+
+/// Enable a privilege (e.g. `SeTakeOwnershipPrivilege`) in the current process token.
+/// Fails if the token doesn't hold the privilege at all (not running as administrator).
+pub fn enable_privilege(name: &str) -> Result<(), Error> {
     // SAFETY: Windows API calls for privilege management. All handles are properly
     // closed using CloseHandle in deferred manner.
     unsafe {
@@ -81,8 +216,8 @@ pub fn enable_debug_privilege() -> Result<(), Error> {
             )));
         }
 
-        // Look up the LUID for SeDebugPrivilege
-        let privilege_name = to_wide_string("SeDebugPrivilege");
+        // Look up the LUID for the privilege
+        let privilege_name = to_wide_string(name);
         let mut luid: LUID = std::mem::zeroed();
         if LookupPrivilegeValueW(ptr::null(), privilege_name.as_ptr(), &mut luid) == FALSE {
             CloseHandle(token);
@@ -115,16 +250,146 @@ pub fn enable_debug_privilege() -> Result<(), Error> {
 
         // ERROR_NOT_ALL_ASSIGNED = 1300
         if error == 1300 {
-            return Err(Error::WindowsApi(
-                "SeDebugPrivilege not available - admin rights required".to_string(),
-            ));
+            return Err(Error::WindowsApi(format!(
+                "{} not available - admin rights required",
+                name
+            )));
         }
 
-        log::trace!("Successfully enabled SeDebugPrivilege");
+        log::trace!("Successfully enabled {}", name);
         Ok(())
     }
 }
 
+/// Privileges SYSTEM / TrustedInstaller elevation can't work without: SeDebugPrivilege to open
+/// winlogon.exe and TrustedInstaller.exe, SeImpersonatePrivilege to start a process with the
+/// duplicated SYSTEM token
+const ELEVATION_PRIVILEGES: &[&str] = &["SeDebugPrivilege", "SeImpersonatePrivilege"];
+
+/// The current process's token: how it's elevated, its integrity level, and its privileges
+pub fn process_privileges() -> Result<ProcessPrivileges, Error> {
+    // SAFETY: the token handle is opened here and closed once it's been read.
+    unsafe {
+        let mut token: HANDLE = ptr::null_mut();
+        if OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) == FALSE {
+            return Err(Error::WindowsApi(format!(
+                "OpenProcessToken failed: {}",
+                GetLastError()
+            )));
+        }
+        let result = read_token(token);
+        CloseHandle(token);
+        result
+    }
+}
+
+/// Read a token's elevation, integrity level, and privileges
+///
+/// # Safety
+/// `token` must be a token handle opened with `TOKEN_QUERY`.
+unsafe fn read_token(token: HANDLE) -> Result<ProcessPrivileges, Error> {
+    use windows_sys::Win32::Security::{
+        GetSidSubAuthority, GetSidSubAuthorityCount, TokenElevation,
+        TokenElevationType as TokenElevationTypeClass, TokenIntegrityLevel, TokenPrivileges,
+        SE_PRIVILEGE_ENABLED_BY_DEFAULT, TOKEN_ELEVATION, TOKEN_ELEVATION_TYPE,
+        TOKEN_MANDATORY_LABEL,
+    };
+
+    // Each buffer comes from `token_information`, sized and aligned for the structure read from it
+    let elevation_type = token_information(token, TokenElevationTypeClass)?;
+    let elevation_type = match *elevation_type.as_ptr().cast::<TOKEN_ELEVATION_TYPE>() {
+        2 => TokenElevationType::Full,
+        3 => TokenElevationType::Limited,
+        _ => TokenElevationType::Default,
+    };
+    let elevation = token_information(token, TokenElevation)?;
+    let is_elevated = (*elevation.as_ptr().cast::<TOKEN_ELEVATION>()).TokenIsElevated != 0;
+
+    let label = token_information(token, TokenIntegrityLevel)?;
+    let sid = (*label.as_ptr().cast::<TOKEN_MANDATORY_LABEL>()).Label.Sid;
+    let rid = match *GetSidSubAuthorityCount(sid) {
+        0 => 0,
+        count => *GetSidSubAuthority(sid, u32::from(count) - 1),
+    };
+
+    let buffer = token_information(token, TokenPrivileges)?;
+    let list = buffer.as_ptr().cast::<TOKEN_PRIVILEGES>();
+    let entries =
+        std::slice::from_raw_parts((*list).Privileges.as_ptr(), (*list).PrivilegeCount as usize);
+    let privileges: Vec<TokenPrivilege> = entries
+        .iter()
+        .map(|entry| TokenPrivilege {
+            name: privilege_name(&entry.Luid),
+            enabled: entry.Attributes & SE_PRIVILEGE_ENABLED != 0,
+            enabled_by_default: entry.Attributes & SE_PRIVILEGE_ENABLED_BY_DEFAULT != 0,
+        })
+        .collect();
+
+    let missing_for_elevation = ELEVATION_PRIVILEGES
+        .iter()
+        .filter(|name| !privileges.iter().any(|p| p.name == **name))
+        .map(|name| name.to_string())
+        .collect();
+
+    Ok(ProcessPrivileges {
+        elevation_type,
+        is_elevated,
+        integrity_level: IntegrityLevel::from_rid(rid),
+        privileges,
+        missing_for_elevation,
+    })
+}
+
+/// Read one class of token information into a buffer aligned for any token structure
+///
+/// # Safety
+/// `token` must be a token handle opened with `TOKEN_QUERY`.
+unsafe fn token_information(
+    token: HANDLE,
+    class: windows_sys::Win32::Security::TOKEN_INFORMATION_CLASS,
+) -> Result<Vec<u64>, Error> {
+    use windows_sys::Win32::Security::GetTokenInformation;
+
+    let mut needed = 0u32;
+    GetTokenInformation(token, class, ptr::null_mut(), 0, &mut needed);
+    if needed == 0 {
+        return Err(Error::WindowsApi(format!(
+            "GetTokenInformation({}) failed: {}",
+            class,
+            GetLastError()
+        )));
+    }
+    let mut buffer = vec![0u64; (needed as usize).div_ceil(8)];
+    if GetTokenInformation(
+        token,
+        class,
+        buffer.as_mut_ptr().cast(),
+        (buffer.len() * 8) as u32,
+        &mut needed,
+    ) == FALSE
+    {
+        return Err(Error::WindowsApi(format!(
+            "GetTokenInformation({}) failed: {}",
+            class,
+            GetLastError()
+        )));
+    }
+    Ok(buffer)
+}
+
+/// A privilege's name (e.g. "SeDebugPrivilege"), or its LUID if the name can't be looked up
+fn privilege_name(luid: &LUID) -> String {
+    use windows_sys::Win32::Security::LookupPrivilegeNameW;
+
+    let mut name = [0u16; 64];
+    let mut len = name.len() as u32;
+    // SAFETY: `name` holds `len` UTF-16 units; on success `len` is the length written.
+    if unsafe { LookupPrivilegeNameW(ptr::null(), luid, name.as_mut_ptr(), &mut len) } == FALSE {
+        return format!("LUID {}:{}", luid.HighPart, luid.LowPart);
+    }
+    String::from_utf16_lossy(&name[..len as usize])
+}
+
 /// Find a process ID by name
 pub fn find_process_by_name(target_name: &str) -> Result<u32, Error> {
     let target_wide = to_wide_string(target_name);
@@ -244,35 +509,39 @@ pub fn get_process_token(pid: u32) -> Result<HANDLE, Error> {
 /// with exit code 0 (which the broker would then read as success):
 /// - `WAIT_OBJECT_0` → the process exited; return its exit code (the `GetExitCodeProcess` BOOL is
 ///   checked, not assumed).
-/// - `WAIT_TIMEOUT`  → terminate the hung process and return a timeout error.
+/// - `limit` runs out, or the operation is cancelled → terminate the process and return
+///   `Error::Timeout` / `Error::Cancelled`.
 /// - anything else (`WAIT_FAILED`, …) → return an error carrying `GetLastError`, never `Ok(0)`.
 ///
 /// # Safety
 /// `pi` must hold valid process and thread handles from a successful `CreateProcess*`. Both handles
 /// are closed on every return path.
-pub(super) unsafe fn wait_and_reap(pi: &PROCESS_INFORMATION, label: &str) -> Result<i32, Error> {
+pub(super) unsafe fn wait_and_reap(
+    pi: &PROCESS_INFORMATION,
+    label: &str,
+    limit: &WaitLimit,
+) -> Result<i32, Error> {
     use windows_sys::Win32::System::Threading::{
         GetExitCodeProcess, TerminateProcess, WaitForSingleObject,
     };
     const WAIT_OBJECT_0: u32 = 0x0000_0000;
     const WAIT_TIMEOUT: u32 = 0x0000_0102;
 
-    let wait_result = WaitForSingleObject(pi.hProcess, ELEVATED_PROCESS_TIMEOUT_MS);
-
-    if wait_result == WAIT_TIMEOUT {
-        log::warn!(
-            "{} timed out after {}ms",
-            label,
-            ELEVATED_PROCESS_TIMEOUT_MS
-        );
-        TerminateProcess(pi.hProcess, 1);
-        CloseHandle(pi.hProcess);
-        CloseHandle(pi.hThread);
-        return Err(Error::ServiceControl(format!(
-            "{} timed out after {}ms",
-            label, ELEVATED_PROCESS_TIMEOUT_MS
-        )));
-    }
+    // Waited in slices, so a cancellation is noticed while the process runs
+    let wait_result = loop {
+        let result = WaitForSingleObject(pi.hProcess, limit.slice().as_millis() as u32);
+        if result != WAIT_TIMEOUT {
+            break result;
+        }
+        if let Some(interruption) = limit.interruption() {
+            let err = limit.error(interruption, label);
+            log::warn!("{}", err);
+            TerminateProcess(pi.hProcess, 1);
+            CloseHandle(pi.hProcess);
+            CloseHandle(pi.hThread);
+            return Err(err);
+        }
+    };
 
     if wait_result != WAIT_OBJECT_0 {
         // WAIT_FAILED (0xFFFF_FFFF) or any unexpected value: do NOT fall through to a bogus Ok(0).
@@ -300,3 +569,124 @@ pub(super) unsafe fn wait_and_reap(pi: &PROCESS_INFORMATION, label: &str) -> Res
     log::debug!("{} completed with exit code: {}", label, exit_code);
     Ok(exit_code as i32)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Split a command line the way a child process does
+    fn parse_command_line(line: &str) -> Vec<String> {
+        use windows_sys::Win32::Foundation::LocalFree;
+        use windows_sys::Win32::UI::Shell::CommandLineToArgvW;
+
+        let wide = to_wide_string(line);
+        let mut count = 0;
+        // SAFETY: `wide` is NUL-terminated; the returned array holds `count` NUL-terminated
+        // strings and is freed with LocalFree once they're copied.
+        unsafe {
+            let argv = CommandLineToArgvW(wide.as_ptr(), &mut count);
+            assert!(!argv.is_null(), "CommandLineToArgvW failed");
+            let args = (0..count as usize)
+                .map(|i| {
+                    let arg = *argv.add(i);
+                    let len = (0..).take_while(|&j| *arg.add(j) != 0).count();
+                    String::from_utf16_lossy(std::slice::from_raw_parts(arg, len))
+                })
+                .collect();
+            LocalFree(argv as _);
+            args
+        }
+    }
+
+    /// Deterministic xorshift, so a failing case reproduces
+    struct Rng(u64);
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+    }
+
+    /// A string made of the characters that break naive quoting: quotes, backslashes, whitespace,
+    /// cmd metacharacters and environment-variable syntax, plus some non-ASCII
+    fn adversarial_string(rng: &mut Rng) -> String {
+        const ALPHABET: &[char] = &[
+            '"', '\\', ' ', '\t', '%', '^', '&', '|', '<', '>', '!', '(', ')', ';', ',', '=', 'a',
+            'Z', '0', 'é', '中',
+        ];
+        let len = (rng.next() % 24) as usize;
+        (0..len)
+            .map(|_| ALPHABET[(rng.next() % ALPHABET.len() as u64) as usize])
+            .collect()
+    }
+
+    #[test]
+    fn quoted_arguments_read_back_unchanged() {
+        let program = std::path::Path::new("C:\\Program Files\\MagicX Toolbox\\app.exe");
+        let fixed = [
+            "",
+            "plain",
+            "with space",
+            "C:\\Temp\\trailing\\",
+            "\\\"",
+            "%PATH%",
+            "a\"; calc.exe & \"b",
+            "\\\\server\\share\\",
+        ];
+        let mut rng = Rng(0x5EED_CAFE_F00D_1234);
+        let generated: Vec<String> = (0..500).map(|_| adversarial_string(&mut rng)).collect();
+
+        for args in fixed
+            .iter()
+            .map(|s| vec![s.to_string()])
+            .chain(generated.chunks(3).map(<[String]>::to_vec))
+        {
+            let refs: Vec<&str> = args.iter().map(String::as_str).collect();
+            let line = command_line(program, &refs);
+            let parsed = parse_command_line(&line);
+            assert_eq!(parsed[0], program.to_string_lossy(), "program in {line}");
+            assert_eq!(parsed[1..], args[..], "arguments in {line}");
+        }
+    }
+
+    #[test]
+    fn the_process_token_can_be_read() {
+        let token = process_privileges().unwrap();
+        // Every token holds SeChangeNotifyPrivilege (bypass traverse checking), enabled
+        assert!(token
+            .privileges
+            .iter()
+            .any(|p| p.name == "SeChangeNotifyPrivilege" && p.enabled));
+        assert!(token.integrity_level != IntegrityLevel::Untrusted);
+        assert_eq!(IntegrityLevel::from_rid(0x2000), IntegrityLevel::Medium);
+        assert_eq!(IntegrityLevel::from_rid(0x3000), IntegrityLevel::High);
+        assert_eq!(IntegrityLevel::from_rid(0x4000), IntegrityLevel::System);
+    }
+
+    #[test]
+    fn a_limit_times_out_at_its_deadline() {
+        let limit = WaitLimit::start(Some(Duration::ZERO));
+        assert_eq!(limit.slice(), Duration::ZERO);
+        assert_eq!(limit.interruption(), Some(Interruption::TimedOut));
+        assert!(matches!(
+            limit.error(Interruption::TimedOut, "SYSTEM command"),
+            Error::Timeout(_)
+        ));
+
+        let limit = WaitLimit::start(None);
+        assert!(limit.slice() <= CANCEL_POLL);
+        assert_ne!(limit.interruption(), Some(Interruption::TimedOut));
+    }
+
+    #[test]
+    fn cancelling_interrupts_only_waits_already_running() {
+        let running = WaitLimit::start(Some(Duration::from_secs(60)));
+        cancel_elevated_operations();
+        let later = WaitLimit::start(Some(Duration::from_secs(60)));
+
+        assert_eq!(running.interruption(), Some(Interruption::Cancelled));
+        assert_eq!(later.interruption(), None);
+    }
+}
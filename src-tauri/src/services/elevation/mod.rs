@@ -8,6 +8,7 @@
 //!
 //! - `level`: the `Elevation` enum — the single dispatch value for the apply chain
 //! - `broker`: the elevated effect broker (protocol, executor, `--broker` entrypoint, `run_elevated_broker`)
+//! - `session`: the persistent per-level broker process, fed over a named pipe (`--broker-session`)
 //! - `common`: shared utilities, constants, and Windows API imports
 //! - `system_elevation`: SYSTEM token duplication (winlogon.exe) + spawn, and the SYSTEM wrappers
 //! - `ti_elevation`: TrustedInstaller parent-process spoof + spawn, and the TI wrappers
@@ -16,7 +17,7 @@
 //!
 //! ```ignore
 //! if can_use_system_elevation() {
-//!     run_command_as_system("cmd /c echo hello")?;
+//!     run_command_as_system("echo hello", None)?;
 //!     set_registry_value_as_system(
 //!         RegistryHive::Hklm,
 //!         "SOFTWARE\\Test",
@@ -30,26 +31,43 @@
 mod broker;
 mod common;
 mod level;
+mod session;
 mod system_elevation;
 mod ti_elevation;
 
 // Re-export the elevation level enum (the single dispatch value for the apply chain)
 pub use level::Elevation;
 
-// Re-export the broker entrypoint (called from the `--broker` subcommand in lib.rs) and the typed
-// scheduler op. The broker protocol types stay internal to this module — the elevated wrappers
-// build them.
-pub use broker::{run_broker, run_scheduler_op};
+// Re-export the broker entrypoints (called from the `--broker` / `--broker-session` subcommands in
+// lib.rs), the batched registry ops, the typed scheduler ops, and the elevated service query. The
+// broker protocol types stay internal to this module — the elevated wrappers build them.
+pub use broker::{
+    kill_children_on_exit, run_broker, run_registry_batch, run_scheduler_op, run_scheduler_pattern,
+    run_scheduler_register, run_service_query, RegistryOp,
+};
+pub use session::run_broker_session;
+
+// Re-export the session warm-up, so the UI can start a broker before the first operation needs it
+pub use session::warm_up_session;
+
+// Re-export the privilege helper for in-process operations that need more than admin rights
+// (e.g. taking ownership of registry keys), the token diagnostics, the process lookup (e.g. is
+// explorer.exe running), and the cancellation of running elevated operations
+pub use common::{
+    cancel_elevated_operations, enable_privilege, find_process_by_name, process_privileges,
+};
 
 // Re-export SYSTEM elevation functions
 pub use system_elevation::{
-    can_use_system_elevation, delete_registry_value_as_system, run_command_as_system,
-    set_registry_value_as_system, set_service_startup_as_system, start_service_as_system,
-    stop_service_as_system,
+    can_use_system_elevation, create_registry_key_as_system, delete_registry_key_as_system,
+    delete_registry_value_as_system, run_command_as_system, set_registry_value_as_system,
+    set_service_startup_as_system, start_service_as_system, stop_service_as_system,
 };
 
 // Re-export TrustedInstaller elevation functions
 pub use ti_elevation::{
+    create_registry_key_as_ti, delete_registry_key_as_ti, delete_registry_value_as_ti, find_pwsh,
     run_command_as_ti, run_powershell, run_powershell_as_system, run_powershell_as_ti,
-    set_service_startup_as_ti, start_service_as_ti, stop_service_as_ti,
+    run_powershell_with, set_registry_value_as_ti, set_service_startup_as_ti, start_service_as_ti,
+    stop_service_as_ti, PowerShellEngine, PowerShellResult,
 };
@@ -0,0 +1,478 @@
+//! Persistent elevated broker sessions.
+//!
+//! A one-shot broker costs a SYSTEM token duplication (or a TrustedInstaller service start and
+//! parent spoof) plus a process start for every operation, which adds up across a batch. Instead,
+//! the first SYSTEM / TrustedInstaller operation starts one broker for that level in session mode
+//! (`--broker-session <pipe>`), and every later operation at that level is sent to it over a named
+//! pipe. A frame is a little-endian `u32` length followed by the JSON of a [`BrokerRequest`] (or,
+//! coming back, a [`BrokerResponse`]), so the same typed protocol crosses as in the one-shot path.
+//!
+//! The app creates the pipe (one instance, local clients only) under a name containing a fresh
+//! nonce, and sends nothing until the connected client is the broker it spawned, checked by pid.
+//! Each request still carries a nonce the response has to echo. A request that outlives its
+//! timeout, or is cancelled, gets its broker terminated, which also ends the session; the next
+//! operation starts a new one. The broker exits when the app closes its end of the pipe.
+//!
+//! A session can also be started ahead of time with [`warm_up_session`] (the UI does this when the
+//! user opens a category with TrustedInstaller tweaks), so the first operation doesn't pay for the
+//! TI service start. A session nothing has used for [`IDLE_TIMEOUT`] is closed by a background
+//! reaper, so an elevated process doesn't outlive the work it was started for.
+
+use crate::error::Error;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::windows::io::{AsRawHandle, FromRawHandle};
+use std::ptr;
+use std::sync::{mpsc, Mutex, Once};
+use std::time::{Duration, Instant};
+
+use windows_sys::Win32::System::Pipes::{
+    ConnectNamedPipe, CreateNamedPipeW, GetNamedPipeClientProcessId, SetNamedPipeHandleState,
+};
+use windows_sys::Win32::System::Threading::{TerminateProcess, WaitForSingleObject};
+
+use super::broker::{
+    execute_request, next_nonce, validate_response, BrokerRequest, BrokerResponse,
+};
+use super::common::{
+    to_wide_string, CloseHandle, GetLastError, WaitLimit, FALSE, HANDLE, INVALID_HANDLE_VALUE,
+    PROCESS_INFORMATION,
+};
+use super::Elevation;
+
+// --- Win32 constants (stable ABI values; defined locally to avoid version-specific import churn) ---
+const PIPE_ACCESS_DUPLEX: u32 = 0x0000_0003;
+const FILE_FLAG_FIRST_PIPE_INSTANCE: u32 = 0x0008_0000;
+const PIPE_READMODE_BYTE: u32 = 0x0000_0000;
+const PIPE_WAIT: u32 = 0x0000_0000;
+const PIPE_NOWAIT: u32 = 0x0000_0001;
+const PIPE_REJECT_REMOTE_CLIENTS: u32 = 0x0000_0008;
+const ERROR_PIPE_CONNECTED: u32 = 535;
+const ERROR_PIPE_LISTENING: u32 = 536;
+const WAIT_TIMEOUT: u32 = 0x0000_0102;
+
+const PIPE_BUFFER_BYTES: u32 = 64 * 1024;
+
+/// Largest frame either side accepts
+const MAX_FRAME_BYTES: u32 = 64 * 1024 * 1024;
+
+/// How long a freshly started broker has to connect to the pipe
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+
+const CONNECT_POLL: Duration = Duration::from_millis(20);
+
+/// How long a session may sit unused before the reaper closes it
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// How often the reaper looks for idle sessions
+const REAP_INTERVAL: Duration = Duration::from_secs(30);
+
+static SYSTEM_SESSION: Mutex<Option<Session>> = Mutex::new(None);
+static TI_SESSION: Mutex<Option<Session>> = Mutex::new(None);
+static REAPER: Once = Once::new();
+
+/// A running session broker, terminated (if still running) and reaped when dropped
+struct BrokerProcess(PROCESS_INFORMATION);
+
+// SAFETY: process and thread handles are process-wide kernel handles, usable from any thread; the
+// session mutex serializes every use of them.
+unsafe impl Send for BrokerProcess {}
+
+impl BrokerProcess {
+    fn is_running(&self) -> bool {
+        // SAFETY: hProcess is a valid process handle owned by self.
+        unsafe { WaitForSingleObject(self.0.hProcess, 0) == WAIT_TIMEOUT }
+    }
+}
+
+impl Drop for BrokerProcess {
+    fn drop(&mut self) {
+        // SAFETY: both handles are owned by self and closed exactly once, here.
+        unsafe {
+            if self.is_running() {
+                TerminateProcess(self.0.hProcess, 1);
+            }
+            CloseHandle(self.0.hProcess);
+            CloseHandle(self.0.hThread);
+        }
+    }
+}
+
+/// A connected session. The pipe is declared first so it closes before the process is reaped.
+struct Session {
+    pipe: File,
+    process: BrokerProcess,
+    level: Elevation,
+    last_used: Instant,
+}
+
+impl Session {
+    /// Create the pipe, start the broker at `level`, and wait for it to connect
+    fn start(level: Elevation) -> Result<Self, Error> {
+        let exe = std::env::current_exe()
+            .map_err(|e| Error::ServiceControl(format!("current_exe failed: {}", e)))?;
+        let pipe_name = format!(
+            r"\\.\pipe\magicx-broker-{}-{:016x}",
+            std::process::id(),
+            next_nonce()
+        );
+        let pipe = create_pipe(&pipe_name)?;
+
+        let cmdline = super::common::command_line(&exe, &["--broker-session", &pipe_name]);
+        let process_info = match level {
+            Elevation::System => super::system_elevation::start_as_system(&cmdline),
+            Elevation::TrustedInstaller => {
+                super::ti_elevation::start_as_trusted_installer(&cmdline)
+            }
+            Elevation::None => unreachable!("sessions are only started for elevated levels"),
+        }?;
+        let process = BrokerProcess(process_info);
+
+        wait_for_broker(&pipe, &process)?;
+        log::info!(
+            "Started {} broker session (pid {})",
+            level.label(),
+            process.0.dwProcessId
+        );
+        Ok(Session {
+            pipe,
+            process,
+            level,
+            last_used: Instant::now(),
+        })
+    }
+
+    /// Send one request and read its response. The broker is terminated if the request runs past
+    /// `limit` or is cancelled, which ends the read with an error.
+    fn exchange(
+        &mut self,
+        request: &BrokerRequest,
+        limit: &WaitLimit,
+    ) -> Result<BrokerResponse, Error> {
+        let nonce = next_nonce();
+        let wire = serde_json::to_vec(&BrokerRequest {
+            nonce,
+            ..request.clone()
+        })
+        .map_err(|e| Error::ServiceControl(format!("serialize broker request: {}", e)))?;
+
+        let (done, finished) = mpsc::channel::<()>();
+        // Passed as an integer: the handle stays valid because self outlives the watchdog
+        let process = self.process.0.hProcess as usize;
+        let watch = limit.clone();
+        let watchdog = std::thread::spawn(move || loop {
+            if finished.recv_timeout(watch.slice()) != Err(mpsc::RecvTimeoutError::Timeout) {
+                return None;
+            }
+            if let Some(interruption) = watch.interruption() {
+                // SAFETY: the session (and so the handle) is alive until this thread is joined.
+                unsafe { TerminateProcess(process as HANDLE, 1) };
+                return Some(interruption);
+            }
+        });
+
+        let exchanged =
+            write_frame(&mut self.pipe, &wire).and_then(|()| read_frame(&mut self.pipe));
+        let _ = done.send(());
+        if let Some(interruption) = watchdog.join().unwrap_or(None) {
+            let err = limit.error(interruption, &format!("{} command", self.level.label()));
+            log::warn!("{}", err);
+            return Err(err);
+        }
+
+        match exchanged {
+            Ok(Some(bytes)) => validate_response(&bytes, nonce),
+            Ok(None) => Err(Error::ServiceControl(
+                "broker session closed without a response".to_string(),
+            )),
+            Err(e) => Err(Error::ServiceControl(format!(
+                "broker session failed: {}",
+                e
+            ))),
+        }
+    }
+}
+
+fn slot(level: Elevation) -> Option<&'static Mutex<Option<Session>>> {
+    match level {
+        Elevation::System => Some(&SYSTEM_SESSION),
+        Elevation::TrustedInstaller => Some(&TI_SESSION),
+        Elevation::None => None,
+    }
+}
+
+/// The running session taken out of `session`, or a newly started one if it has ended
+fn take_or_start(session: &mut Option<Session>, level: Elevation) -> Result<Session, Error> {
+    match session.take() {
+        Some(active) if active.process.is_running() => Ok(active),
+        ended => {
+            if ended.is_some() {
+                log::debug!("{} broker session ended; starting a new one", level.label());
+            }
+            let started = Session::start(level)?;
+            REAPER.call_once(|| {
+                std::thread::spawn(reap_idle_sessions);
+            });
+            Ok(started)
+        }
+    }
+}
+
+/// Run `request` in the broker session for `level`, starting one if there isn't one running.
+///
+/// `Ok(None)` means no session could be started, so nothing was sent and the caller can run the
+/// request in a one-shot broker instead. An error after the request was sent is returned as is
+/// (the broker may already have run some of it) and drops the session.
+pub(super) fn run_in_session(
+    level: Elevation,
+    request: &BrokerRequest,
+    limit: &WaitLimit,
+) -> Result<Option<BrokerResponse>, Error> {
+    let Some(slot) = slot(level) else {
+        return Ok(None);
+    };
+    let mut session = slot.lock().unwrap_or_else(|e| e.into_inner());
+
+    let mut active = match take_or_start(&mut session, level) {
+        Ok(active) => active,
+        Err(e) => {
+            log::warn!("Failed to start {} broker session: {}", level.label(), e);
+            return Ok(None);
+        }
+    };
+
+    // A session that failed is dropped (terminating its broker) rather than reused
+    let response = active.exchange(request, limit)?;
+    active.last_used = Instant::now();
+    *session = Some(active);
+    Ok(Some(response))
+}
+
+/// Start the broker session for `level` ahead of the first operation that needs it, or keep the
+/// running one from going idle. Nothing to do for [`Elevation::None`].
+pub fn warm_up_session(level: Elevation) -> Result<(), Error> {
+    let Some(slot) = slot(level) else {
+        return Ok(());
+    };
+    let mut session = slot.lock().unwrap_or_else(|e| e.into_inner());
+    let mut active = take_or_start(&mut session, level)?;
+    active.last_used = Instant::now();
+    *session = Some(active);
+    Ok(())
+}
+
+/// Close sessions that have gone unused for [`IDLE_TIMEOUT`]. Runs for the life of the app once
+/// the first session starts; a session busy with a request holds its lock, so it's never reaped
+/// mid-request.
+fn reap_idle_sessions() {
+    loop {
+        std::thread::sleep(REAP_INTERVAL);
+        for slot in [&SYSTEM_SESSION, &TI_SESSION] {
+            let Ok(mut session) = slot.try_lock() else {
+                continue;
+            };
+            if let Some(active) = session.take_if(|s| s.last_used.elapsed() >= IDLE_TIMEOUT) {
+                log::info!(
+                    "Closing idle {} broker session (pid {})",
+                    active.level.label(),
+                    active.process.0.dwProcessId
+                );
+            }
+        }
+    }
+}
+
+/// Create the single, local-only instance of the session pipe
+fn create_pipe(name: &str) -> Result<File, Error> {
+    let wide = to_wide_string(name);
+    // SAFETY: `wide` is a NUL-terminated name valid for the call; the returned handle is owned by
+    // the File it's wrapped in.
+    unsafe {
+        let handle = CreateNamedPipeW(
+            wide.as_ptr(),
+            PIPE_ACCESS_DUPLEX | FILE_FLAG_FIRST_PIPE_INSTANCE,
+            PIPE_READMODE_BYTE | PIPE_NOWAIT | PIPE_REJECT_REMOTE_CLIENTS,
+            1,
+            PIPE_BUFFER_BYTES,
+            PIPE_BUFFER_BYTES,
+            0,
+            ptr::null(),
+        );
+        if handle == INVALID_HANDLE_VALUE {
+            return Err(Error::ServiceControl(format!(
+                "CreateNamedPipe failed: {}",
+                GetLastError()
+            )));
+        }
+        Ok(File::from_raw_handle(handle as _))
+    }
+}
+
+/// Wait for the broker to connect, check that the client is the broker, then switch the pipe to
+/// blocking reads. The pipe is created non-blocking so a broker that never connects can't hang
+/// the app.
+fn wait_for_broker(pipe: &File, process: &BrokerProcess) -> Result<(), Error> {
+    let handle = pipe.as_raw_handle() as HANDLE;
+    let deadline = Instant::now() + CONNECT_TIMEOUT;
+    loop {
+        // In non-blocking mode ConnectNamedPipe only reports whether a client is connected yet.
+        // SAFETY: `handle` is the open pipe owned by `pipe`.
+        let status = unsafe {
+            if ConnectNamedPipe(handle, ptr::null_mut()) != FALSE {
+                ERROR_PIPE_LISTENING
+            } else {
+                GetLastError()
+            }
+        };
+        match status {
+            ERROR_PIPE_CONNECTED => break,
+            ERROR_PIPE_LISTENING => {}
+            err => {
+                return Err(Error::ServiceControl(format!(
+                    "ConnectNamedPipe failed: {}",
+                    err
+                )))
+            }
+        }
+        if !process.is_running() {
+            return Err(Error::ServiceControl(
+                "broker exited before connecting".to_string(),
+            ));
+        }
+        if Instant::now() >= deadline {
+            return Err(Error::ServiceControl(format!(
+                "broker did not connect within {}s",
+                CONNECT_TIMEOUT.as_secs()
+            )));
+        }
+        std::thread::sleep(CONNECT_POLL);
+    }
+
+    let mut client_pid = 0u32;
+    // SAFETY: `handle` is a connected pipe; `client_pid` is a valid out pointer.
+    if unsafe { GetNamedPipeClientProcessId(handle, &mut client_pid) } == FALSE {
+        return Err(Error::ServiceControl(format!(
+            "GetNamedPipeClientProcessId failed: {}",
+            unsafe { GetLastError() }
+        )));
+    }
+    if client_pid != process.0.dwProcessId {
+        return Err(Error::ServiceControl(format!(
+            "broker pipe client is pid {}, not the broker (pid {})",
+            client_pid, process.0.dwProcessId
+        )));
+    }
+
+    let mode = PIPE_READMODE_BYTE | PIPE_WAIT;
+    // SAFETY: `handle` is the open pipe; `mode` outlives the call.
+    if unsafe { SetNamedPipeHandleState(handle, &mode, ptr::null(), ptr::null()) } == FALSE {
+        return Err(Error::ServiceControl(format!(
+            "SetNamedPipeHandleState failed: {}",
+            unsafe { GetLastError() }
+        )));
+    }
+    Ok(())
+}
+
+fn write_frame(writer: &mut impl Write, bytes: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(bytes.len())
+        .ok()
+        .filter(|len| *len <= MAX_FRAME_BYTES)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "frame too large"))?;
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(bytes)?;
+    writer.flush()
+}
+
+/// Read one frame; `None` when the other side closed the pipe between frames
+fn read_frame(reader: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let len = u32::from_le_bytes(len);
+    if len > MAX_FRAME_BYTES {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("frame of {} bytes is too large", len),
+        ));
+    }
+    let mut bytes = vec![0u8; len as usize];
+    reader.read_exact(&mut bytes)?;
+    Ok(Some(bytes))
+}
+
+/// Session broker entrypoint: connect to the app's pipe and execute each request that arrives until
+/// the app closes it. Returns a process exit code (0 = the app ended the session; non-zero = a
+/// transport failure).
+pub fn run_broker_session(pipe_name: &str) -> i32 {
+    let mut pipe = match OpenOptions::new().read(true).write(true).open(pipe_name) {
+        Ok(pipe) => pipe,
+        Err(e) => {
+            eprintln!("broker: failed to open pipe {}: {}", pipe_name, e);
+            return 2;
+        }
+    };
+
+    loop {
+        let bytes = match read_frame(&mut pipe) {
+            Ok(Some(bytes)) => bytes,
+            Ok(None) => return 0,
+            Err(e) => {
+                eprintln!("broker: failed to read request: {}", e);
+                return 2;
+            }
+        };
+        let request: BrokerRequest = match serde_json::from_slice(&bytes) {
+            Ok(request) => request,
+            Err(e) => {
+                eprintln!("broker: failed to parse request: {}", e);
+                return 3;
+            }
+        };
+
+        let response = execute_request(&request);
+
+        let out = match serde_json::to_vec(&response) {
+            Ok(out) => out,
+            Err(e) => {
+                eprintln!("broker: failed to serialize response: {}", e);
+                return 4;
+            }
+        };
+        if let Err(e) = write_frame(&mut pipe, &out) {
+            eprintln!("broker: failed to write response: {}", e);
+            return 5;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn frames_round_trip_and_end_cleanly() {
+        let mut wire = Vec::new();
+        write_frame(&mut wire, b"{\"a\":1}").unwrap();
+        write_frame(&mut wire, b"").unwrap();
+
+        let mut reader = Cursor::new(wire);
+        assert_eq!(read_frame(&mut reader).unwrap().unwrap(), b"{\"a\":1}");
+        assert_eq!(read_frame(&mut reader).unwrap().unwrap(), b"");
+        assert!(read_frame(&mut reader).unwrap().is_none());
+    }
+
+    #[test]
+    fn an_oversized_or_truncated_frame_is_an_error() {
+        let mut oversized = Cursor::new((MAX_FRAME_BYTES + 1).to_le_bytes().to_vec());
+        assert!(read_frame(&mut oversized).is_err());
+
+        let mut truncated = 10u32.to_le_bytes().to_vec();
+        truncated.extend_from_slice(b"abc");
+        assert!(read_frame(&mut Cursor::new(truncated)).is_err());
+    }
+}
@@ -6,13 +6,15 @@
 use crate::error::Error;
 use crate::models::{RegistryHive, RegistryValueType, ServiceStartupType};
 use std::ptr;
+use std::time::Duration;
 
-use super::broker::{run_one, BrokerOp};
+use super::broker::{run_command_op, run_one, BrokerOp};
+use super::ti_elevation::PowerShellResult;
 use super::Elevation;
 
 use super::common::{
     enable_debug_privilege, find_process_by_name, get_process_token, to_wide_string, wait_and_reap,
-    CloseHandle, CreateProcessWithTokenW, GetLastError, CREATE_NO_WINDOW, FALSE, HANDLE,
+    CloseHandle, CreateProcessWithTokenW, GetLastError, WaitLimit, CREATE_NO_WINDOW, FALSE, HANDLE,
     LOGON_WITH_PROFILE, PROCESS_INFORMATION, STARTF_USESHOWWINDOW, STARTUPINFOW, SW_HIDE,
 };
 
@@ -25,16 +27,23 @@ fn get_system_token() -> Result<HANDLE, Error> {
 }
 
 /// Spawn a raw command line as SYSTEM (no `cmd.exe` wrapper) and wait for it to complete.
-/// Returns the exit code. This is the broker launcher; `execute_command_as_system` wraps a shell
-/// command in `cmd.exe /c` and delegates here.
-pub(super) fn spawn_as_system(command_line: &str) -> Result<i32, Error> {
+/// Returns the exit code. This is the one-shot broker launcher.
+pub(super) fn spawn_as_system(command_line: &str, limit: &WaitLimit) -> Result<i32, Error> {
+    let process_info = start_as_system(command_line)?;
+    // SAFETY: the handles come from the successful CreateProcessWithTokenW in start_as_system.
+    unsafe { wait_and_reap(&process_info, "SYSTEM command", limit) }
+}
+
+/// Start a raw command line as SYSTEM without waiting for it. The caller owns the returned process
+/// and thread handles. The session broker is started this way.
+pub(super) fn start_as_system(command_line: &str) -> Result<PROCESS_INFORMATION, Error> {
     let token = get_system_token()?;
     log::debug!("Got SYSTEM token, spawning: {}", command_line);
 
     let mut command_wide = to_wide_string(command_line);
 
     // SAFETY: Windows API calls for creating a process with impersonation token.
-    // Process and thread handles are closed after waiting for completion.
+    // Process and thread handles are handed to the caller.
     // Token handle is closed after use. The command_wide buffer remains valid
     // throughout the CreateProcessAsUserW call.
     unsafe {
@@ -87,13 +96,14 @@ pub(super) fn spawn_as_system(command_line: &str) -> Result<i32, Error> {
             )));
         }
 
-        wait_and_reap(&process_info, "SYSTEM command")
+        Ok(process_info)
     }
 }
 
 /// Set a registry value as SYSTEM via the elevated broker (typed `RegSetValueExW`, no reg.exe).
 /// The typed value crosses to the broker as data, dissolving the injection and REG_SZ-corruption
-/// classes the old `reg add` + `escape_shell_arg` path carried.
+/// classes the old `reg add` + `escape_shell_arg` path carried, and REG_BINARY / REG_MULTI_SZ are
+/// written natively like every other type.
 pub fn set_registry_value_as_system(
     hive: RegistryHive,
     key: &str,
@@ -130,19 +140,47 @@ pub fn delete_registry_value_as_system(
     )
 }
 
+/// Create a registry key as SYSTEM via the elevated broker (an existing key is left as is).
+pub fn create_registry_key_as_system(hive: RegistryHive, key: &str) -> Result<(), Error> {
+    run_one(
+        Elevation::System,
+        BrokerOp::RegCreateKey {
+            hive,
+            key: key.to_string(),
+        },
+    )
+}
+
+/// Delete a registry key and its subkeys as SYSTEM via the elevated broker. An absent key is
+/// reported as success by the broker.
+pub fn delete_registry_key_as_system(hive: RegistryHive, key: &str) -> Result<(), Error> {
+    run_one(
+        Elevation::System,
+        BrokerOp::RegDeleteKey {
+            hive,
+            key: key.to_string(),
+        },
+    )
+}
+
 /// Check if SYSTEM elevation is available (running as admin)
 pub fn can_use_system_elevation() -> bool {
     crate::services::system_info_service::is_running_as_admin()
 }
 
-/// Execute an arbitrary command as SYSTEM (via the elevated broker; `cmd /c` inside it).
-pub fn run_command_as_system(command: &str) -> Result<(), Error> {
+/// Execute an arbitrary command as SYSTEM (via the elevated broker; `cmd /c` inside it), returning
+/// its exit code and output. `timeout` replaces the default elevated-process timeout.
+pub fn run_command_as_system(
+    command: &str,
+    timeout: Option<Duration>,
+) -> Result<PowerShellResult, Error> {
     log::info!("Running command as SYSTEM: {}", command);
-    run_one(
+    run_command_op(
         Elevation::System,
         BrokerOp::RawCmd {
             command: command.to_string(),
         },
+        timeout,
     )
 }
 
@@ -4,13 +4,17 @@
 //! Also includes PowerShell execution and scheduled task commands.
 
 use crate::error::Error;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 use std::ptr;
+use std::sync::OnceLock;
+use std::time::Duration;
 
 use super::common::{
     enable_debug_privilege, to_wide_string, wait_and_reap, CloseHandle, CloseServiceHandle,
     CreateProcessW, DeleteProcThreadAttributeList, GetLastError, InitializeProcThreadAttributeList,
     OpenProcess, OpenSCManagerW, OpenServiceW, QueryServiceStatusEx, StartServiceW,
-    UpdateProcThreadAttribute, CREATE_NO_WINDOW, CREATE_UNICODE_ENVIRONMENT,
+    UpdateProcThreadAttribute, WaitLimit, CREATE_NO_WINDOW, CREATE_UNICODE_ENVIRONMENT,
     ERROR_SERVICE_ALREADY_RUNNING, EXTENDED_STARTUPINFO_PRESENT, FALSE, HANDLE,
     LPPROC_THREAD_ATTRIBUTE_LIST, PROCESS_CREATE_PROCESS, PROCESS_INFORMATION,
     PROC_THREAD_ATTRIBUTE_PARENT_PROCESS, SC_MANAGER_CONNECT, SC_STATUS_PROCESS_INFO,
@@ -18,16 +22,16 @@ use super::common::{
     STARTF_USESHOWWINDOW, STARTUPINFOEXW, STARTUPINFOW, SW_HIDE,
 };
 
-use super::broker::{run_one, BrokerOp};
+use super::broker::{run_command_op, run_one, run_powershell_encoded, BrokerOp};
 use super::Elevation;
-use crate::models::ServiceStartupType;
+use crate::models::{RegistryHive, RegistryValueType, ServiceStartupType};
 
 // ============================================================================
 // POWERSHELL EXECUTION
 // ============================================================================
 
-/// Result of a PowerShell command execution
-#[derive(Debug)]
+/// Result of a PowerShell script or command run at any elevation: its exit code and captured output
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PowerShellResult {
     /// Exit code from PowerShell
     pub exit_code: i32,
@@ -39,34 +43,116 @@ pub struct PowerShellResult {
     pub success: bool,
 }
 
-/// Execute a PowerShell command as the current user
-/// Uses -NoProfile and -ExecutionPolicy Bypass for reliability
+impl PowerShellResult {
+    pub fn from_output(output: &std::process::Output) -> Self {
+        Self {
+            exit_code: output.status.code().unwrap_or(-1),
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+            success: output.status.success(),
+        }
+    }
+
+    /// What the command printed about its failure: stderr, or stdout for tools (like sc.exe) that
+    /// report errors there
+    pub fn error_output(&self) -> &str {
+        let stderr = self.stderr.trim();
+        if stderr.is_empty() {
+            self.stdout.trim()
+        } else {
+            stderr
+        }
+    }
+
+    /// The result if the exit code was 0, otherwise an error naming `what` with the output
+    pub fn into_success(self, what: &str) -> Result<Self, Error> {
+        if self.success {
+            Ok(self)
+        } else {
+            Err(Error::CommandExecution(format!(
+                "{} failed with exit code {}: {}",
+                what,
+                self.exit_code,
+                self.error_output()
+            )))
+        }
+    }
+}
+
+/// Which PowerShell runs a script
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PowerShellEngine {
+    /// Windows PowerShell 5.1 (`powershell.exe`), part of every supported Windows
+    #[default]
+    Windows,
+    /// PowerShell 7+ (`pwsh.exe`), when it's installed
+    Core,
+}
+
+impl PowerShellEngine {
+    /// The executable's name, as shown in logs
+    pub fn name(self) -> &'static str {
+        match self {
+            PowerShellEngine::Windows => "powershell",
+            PowerShellEngine::Core => "pwsh",
+        }
+    }
+
+    /// The executable to start. Fails for [`PowerShellEngine::Core`] when pwsh isn't installed.
+    pub(super) fn executable(self) -> Result<PathBuf, Error> {
+        match self {
+            PowerShellEngine::Windows => Ok(PathBuf::from("powershell.exe")),
+            PowerShellEngine::Core => find_pwsh().ok_or_else(|| {
+                Error::CommandExecution(
+                    "PowerShell 7 (pwsh.exe) is not installed; install it or use Windows PowerShell"
+                        .to_string(),
+                )
+            }),
+        }
+    }
+}
+
+/// Where PowerShell 7 is installed, if it is: its default install folder first, then `PATH`.
+/// Looked up once; the elevated broker, which may not share the user's `PATH`, finds the default
+/// install the same way.
+pub fn find_pwsh() -> Option<PathBuf> {
+    static PWSH: OnceLock<Option<PathBuf>> = OnceLock::new();
+    PWSH.get_or_init(|| {
+        let installed = std::env::var_os("ProgramFiles").map(|dir| {
+            PathBuf::from(dir)
+                .join("PowerShell")
+                .join("7")
+                .join("pwsh.exe")
+        });
+        let on_path = std::env::var_os("PATH").and_then(|path| {
+            std::env::split_paths(&path)
+                .map(|dir| dir.join("pwsh.exe"))
+                .find(|candidate| candidate.is_file())
+        });
+        let found = installed.filter(|path| path.is_file()).or(on_path);
+        match &found {
+            Some(path) => log::debug!("Found PowerShell 7 at {}", path.display()),
+            None => log::debug!("PowerShell 7 (pwsh.exe) not found"),
+        }
+        found
+    })
+    .clone()
+}
+
+/// Execute a PowerShell command as the current user in Windows PowerShell
 pub fn run_powershell(script: &str) -> Result<PowerShellResult, Error> {
-    use std::os::windows::process::CommandExt;
-
-    log::info!("Running PowerShell command: {}", script);
-
-    let output = std::process::Command::new("powershell.exe")
-        .args([
-            "-NoProfile",
-            "-NonInteractive",
-            "-WindowStyle",
-            "Hidden",
-            "-ExecutionPolicy",
-            "Bypass",
-            "-Command",
-            script,
-        ])
-        .creation_flags(CREATE_NO_WINDOW)
-        .output()
-        .map_err(|e| Error::CommandExecution(format!("Failed to execute PowerShell: {}", e)))?;
-
-    let result = PowerShellResult {
-        exit_code: output.status.code().unwrap_or(-1),
-        stdout: String::from_utf8_lossy(&output.stdout).to_string(),
-        stderr: String::from_utf8_lossy(&output.stderr).to_string(),
-        success: output.status.success(),
-    };
+    run_powershell_with(script, PowerShellEngine::Windows)
+}
+
+/// Execute a PowerShell command as the current user in the given engine. The script is passed as
+/// `-EncodedCommand`, so no quoting of it can go wrong.
+pub fn run_powershell_with(
+    script: &str,
+    engine: PowerShellEngine,
+) -> Result<PowerShellResult, Error> {
+    log::info!("Running {} command: {}", engine.name(), script);
+
+    let result = run_powershell_encoded(script, engine)?;
 
     if result.success {
         log::debug!("PowerShell command succeeded");
@@ -84,14 +170,21 @@ pub fn run_powershell(script: &str) -> Result<PowerShellResult, Error> {
     Ok(result)
 }
 
-/// Execute a PowerShell command as SYSTEM via the elevated broker (`-EncodedCommand`; no shell).
-pub fn run_powershell_as_system(script: &str) -> Result<(), Error> {
-    log::info!("Running PowerShell command as SYSTEM: {}", script);
-    run_one(
+/// Execute a PowerShell command as SYSTEM via the elevated broker (`-EncodedCommand`; no shell),
+/// returning its exit code and output. `timeout` replaces the default elevated-process timeout.
+pub fn run_powershell_as_system(
+    script: &str,
+    engine: PowerShellEngine,
+    timeout: Option<Duration>,
+) -> Result<PowerShellResult, Error> {
+    log::info!("Running {} command as SYSTEM: {}", engine.name(), script);
+    run_command_op(
         Elevation::System,
         BrokerOp::Powershell {
             script: script.to_string(),
+            engine,
         },
+        timeout,
     )
 }
 
@@ -230,10 +323,21 @@ fn get_trusted_installer_handle() -> Result<HANDLE, Error> {
     }
 }
 
-/// Spawn a raw command line as TrustedInstaller via parent-process spoofing (no `cmd.exe` wrapper).
-/// This creates a process with TrustedInstaller.exe as its parent, inheriting the TI token.
-/// `execute_command_as_trusted_installer` wraps a shell command in `cmd.exe /c` and delegates here.
-pub(super) fn spawn_as_trusted_installer(command_line: &str) -> Result<i32, Error> {
+/// Spawn a raw command line as TrustedInstaller (no `cmd.exe` wrapper) and wait for it to complete.
+/// Returns the exit code. This is the one-shot broker launcher.
+pub(super) fn spawn_as_trusted_installer(
+    command_line: &str,
+    limit: &WaitLimit,
+) -> Result<i32, Error> {
+    let process_info = start_as_trusted_installer(command_line)?;
+    // SAFETY: the handles come from the successful CreateProcessW in start_as_trusted_installer.
+    unsafe { wait_and_reap(&process_info, "TrustedInstaller command", limit) }
+}
+
+/// Start a raw command line as TrustedInstaller via parent-process spoofing, without waiting for it.
+/// This creates a process with TrustedInstaller.exe as its parent, inheriting the TI token. The
+/// caller owns the returned process and thread handles.
+pub(super) fn start_as_trusted_installer(command_line: &str) -> Result<PROCESS_INFORMATION, Error> {
     log::info!("Spawning as TrustedInstaller: {}", command_line);
 
     let ti_handle = get_trusted_installer_handle()?;
@@ -348,10 +452,69 @@ pub(super) fn spawn_as_trusted_installer(command_line: &str) -> Result<i32, Erro
             )));
         }
 
-        wait_and_reap(&process_info, "TrustedInstaller command")
+        Ok(process_info)
     }
 }
 
+/// Set a registry value as TrustedInstaller via the elevated broker, for keys only TI may write.
+pub fn set_registry_value_as_ti(
+    hive: RegistryHive,
+    key: &str,
+    value_name: &str,
+    value_type: RegistryValueType,
+    value: serde_json::Value,
+) -> Result<(), Error> {
+    run_one(
+        Elevation::TrustedInstaller,
+        BrokerOp::RegSet {
+            hive,
+            key: key.to_string(),
+            value_name: value_name.to_string(),
+            value_type,
+            value,
+        },
+    )
+}
+
+/// Delete a registry value as TrustedInstaller via the elevated broker (absent is success).
+pub fn delete_registry_value_as_ti(
+    hive: RegistryHive,
+    key: &str,
+    value_name: &str,
+) -> Result<(), Error> {
+    run_one(
+        Elevation::TrustedInstaller,
+        BrokerOp::RegDeleteValue {
+            hive,
+            key: key.to_string(),
+            value_name: value_name.to_string(),
+        },
+    )
+}
+
+/// Create a registry key as TrustedInstaller via the elevated broker.
+pub fn create_registry_key_as_ti(hive: RegistryHive, key: &str) -> Result<(), Error> {
+    run_one(
+        Elevation::TrustedInstaller,
+        BrokerOp::RegCreateKey {
+            hive,
+            key: key.to_string(),
+        },
+    )
+}
+
+/// Delete a registry key and its subkeys as TrustedInstaller via the elevated broker (absent is
+/// success).
+pub fn delete_registry_key_as_ti(hive: RegistryHive, key: &str) -> Result<(), Error> {
+    run_one(
+        Elevation::TrustedInstaller,
+        BrokerOp::RegDeleteKey {
+            hive,
+            key: key.to_string(),
+        },
+    )
+}
+
 /// Set a Windows service startup type as TrustedInstaller (for protected services like WaaSMedicSvc).
 pub fn set_service_startup_as_ti(
     service_name: &str,
@@ -386,24 +549,41 @@ pub fn start_service_as_ti(service_name: &str) -> Result<(), Error> {
     )
 }
 
-/// Run an arbitrary command as TrustedInstaller (via the elevated broker; `cmd /c` inside it).
-pub fn run_command_as_ti(command: &str) -> Result<(), Error> {
+/// Run an arbitrary command as TrustedInstaller (via the elevated broker; `cmd /c` inside it),
+/// returning its exit code and output. `timeout` replaces the default elevated-process timeout.
+pub fn run_command_as_ti(
+    command: &str,
+    timeout: Option<Duration>,
+) -> Result<PowerShellResult, Error> {
     log::info!("Running command as TrustedInstaller: {}", command);
-    run_one(
+    run_command_op(
         Elevation::TrustedInstaller,
         BrokerOp::RawCmd {
             command: command.to_string(),
         },
+        timeout,
     )
 }
 
-/// Run a PowerShell command as TrustedInstaller via the elevated broker (`-EncodedCommand`; no shell).
-pub fn run_powershell_as_ti(script: &str) -> Result<(), Error> {
-    log::info!("Running PowerShell command as TrustedInstaller: {}", script);
-    run_one(
+/// Run a PowerShell command as TrustedInstaller via the elevated broker (`-EncodedCommand`; no
+/// shell), returning its exit code and output. `timeout` replaces the default elevated-process
+/// timeout.
+pub fn run_powershell_as_ti(
+    script: &str,
+    engine: PowerShellEngine,
+    timeout: Option<Duration>,
+) -> Result<PowerShellResult, Error> {
+    log::info!(
+        "Running {} command as TrustedInstaller: {}",
+        engine.name(),
+        script
+    );
+    run_command_op(
         Elevation::TrustedInstaller,
         BrokerOp::Powershell {
             script: script.to_string(),
+            engine,
         },
+        timeout,
     )
 }
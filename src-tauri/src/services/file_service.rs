@@ -0,0 +1,326 @@
+//! File and folder operations for tweak `file_changes`.
+//!
+//! Paths may contain `%VAR%` environment references, expanded against the app's environment.
+//! Folders are handled recursively for copy, delete, and backup. Paths under Program Files or
+//! the Windows directory require administrator privileges.
+
+use crate::error::Error;
+use crate::models::{FileAction, FileAttributes, FileChange};
+use std::ffi::OsStr;
+use std::fs;
+use std::os::windows::ffi::OsStrExt;
+use std::os::windows::fs::MetadataExt;
+use std::path::Path;
+
+use windows_sys::Win32::Storage::FileSystem::{
+    SetFileAttributesW, FILE_ATTRIBUTE_HIDDEN, FILE_ATTRIBUTE_READONLY, FILE_ATTRIBUTE_SYSTEM,
+};
+
+fn wide(s: &str) -> Vec<u16> {
+    OsStr::new(s)
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect()
+}
+
+/// Expand `%VAR%` references. Unknown variables (and a lone `%`) are left as written, so a typo
+/// surfaces as a path that doesn't exist rather than a path that silently points elsewhere.
+pub fn expand_path(path: &str) -> String {
+    let mut result = String::with_capacity(path.len());
+    let mut rest = path;
+
+    while let Some(start) = rest.find('%') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find('%') {
+            Some(end) if end > 0 => {
+                let name = &after[..end];
+                match std::env::var(name) {
+                    Ok(value) => result.push_str(&value),
+                    Err(_) => {
+                        result.push('%');
+                        result.push_str(name);
+                        result.push('%');
+                    }
+                }
+                rest = &after[end + 1..];
+            }
+            _ => {
+                result.push('%');
+                rest = after;
+            }
+        }
+    }
+
+    result.push_str(rest);
+    result
+}
+
+/// Whether anything (file, folder, or link) exists at `path`.
+pub fn path_exists(path: &str) -> bool {
+    fs::symlink_metadata(path).is_ok()
+}
+
+/// Whether `path` is a folder.
+pub fn is_dir(path: &str) -> bool {
+    fs::metadata(path).is_ok_and(|m| m.is_dir())
+}
+
+/// Read the raw attribute bits of `path`.
+pub fn get_attributes(path: &str) -> Result<u32, Error> {
+    fs::metadata(path)
+        .map(|m| m.file_attributes())
+        .map_err(|e| io_error("read attributes of", path, e))
+}
+
+/// Replace the attribute bits of `path`.
+pub fn set_attributes(path: &str, attributes: u32) -> Result<(), Error> {
+    let path_w = wide(path);
+    // SAFETY: `path_w` is a NUL-terminated wide string that outlives the call.
+    if unsafe { SetFileAttributesW(path_w.as_ptr(), attributes) } == 0 {
+        return Err(io_error(
+            "set attributes of",
+            path,
+            std::io::Error::last_os_error(),
+        ));
+    }
+    Ok(())
+}
+
+/// Apply the requested flags to an attribute word, leaving omitted flags unchanged.
+pub fn apply_attribute_flags(current: u32, flags: &FileAttributes) -> u32 {
+    [
+        (flags.readonly, FILE_ATTRIBUTE_READONLY),
+        (flags.hidden, FILE_ATTRIBUTE_HIDDEN),
+        (flags.system, FILE_ATTRIBUTE_SYSTEM),
+    ]
+    .into_iter()
+    .fold(current, |acc, (flag, bit)| match flag {
+        Some(true) => acc | bit,
+        Some(false) => acc & !bit,
+        None => acc,
+    })
+}
+
+/// Copy a file or folder (recursively) to `dest`, creating parent folders as needed.
+/// An existing file at `dest` is overwritten.
+pub fn copy_path(src: &Path, dest: &Path) -> Result<(), Error> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| io_error("create folder", &parent.display().to_string(), e))?;
+    }
+
+    if src.is_dir() {
+        fs::create_dir_all(dest)
+            .map_err(|e| io_error("create folder", &dest.display().to_string(), e))?;
+        let entries =
+            fs::read_dir(src).map_err(|e| io_error("read", &src.display().to_string(), e))?;
+        for entry in entries {
+            let entry = entry.map_err(|e| io_error("read", &src.display().to_string(), e))?;
+            copy_path(&entry.path(), &dest.join(entry.file_name()))?;
+        }
+    } else {
+        fs::copy(src, dest).map_err(|e| io_error("copy", &src.display().to_string(), e))?;
+    }
+
+    Ok(())
+}
+
+/// Delete a file or folder (recursively). Deleting a path that doesn't exist is a no-op.
+pub fn remove_path(path: &str) -> Result<(), Error> {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return Ok(());
+    };
+
+    if metadata.is_dir() {
+        fs::remove_dir_all(path).map_err(|e| io_error("delete", path, e))
+    } else {
+        // DeleteFile refuses read-only files; clear the flag first
+        if metadata.file_attributes() & FILE_ATTRIBUTE_READONLY != 0 {
+            set_attributes(path, metadata.file_attributes() & !FILE_ATTRIBUTE_READONLY)?;
+        }
+        fs::remove_file(path).map_err(|e| io_error("delete", path, e))
+    }
+}
+
+/// Whether the file at `path` holds exactly `expected`. A missing file is not a match.
+pub fn content_equals(path: &str, expected: &[u8]) -> Result<bool, Error> {
+    match fs::read(path) {
+        Ok(bytes) => Ok(bytes == expected),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(io_error("read", path, e)),
+    }
+}
+
+/// Whether two files hold identical bytes. A missing file on either side is not a match.
+pub fn files_equal(a: &str, b: &str) -> Result<bool, Error> {
+    match fs::read(a) {
+        Ok(bytes) => content_equals(b, &bytes),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(io_error("read", a, e)),
+    }
+}
+
+/// The destination of a copy or rename, expanded.
+pub fn destination(change: &FileChange) -> Result<String, Error> {
+    change
+        .destination
+        .as_deref()
+        .map(expand_path)
+        .ok_or_else(|| {
+            Error::ValidationError(format!(
+                "File change '{}' requires 'destination' for {} action",
+                change.path,
+                change.action.as_str()
+            ))
+        })
+}
+
+/// Apply a file change
+pub fn apply_file_change(change: &FileChange) -> Result<(), Error> {
+    let path = expand_path(&change.path);
+
+    match change.action {
+        FileAction::Copy => {
+            let dest = destination(change)?;
+            copy_path(Path::new(&path), Path::new(&dest))?;
+            log::info!("Copied '{}' to '{}'", path, dest);
+        }
+        FileAction::Delete => {
+            remove_path(&path)?;
+            log::info!("Deleted '{}'", path);
+        }
+        FileAction::Rename => {
+            let dest = destination(change)?;
+            if path_exists(&dest) {
+                return Err(Error::ValidationError(format!(
+                    "Cannot rename '{}': '{}' already exists",
+                    path, dest
+                )));
+            }
+            fs::rename(&path, &dest).map_err(|e| io_error("rename", &path, e))?;
+            log::info!("Renamed '{}' to '{}'", path, dest);
+        }
+        FileAction::SetAttributes => {
+            let flags = change.attributes.as_ref().ok_or_else(|| {
+                Error::ValidationError(format!(
+                    "File change '{}' requires 'attributes' for set_attributes action",
+                    change.path
+                ))
+            })?;
+            let current = get_attributes(&path)?;
+            set_attributes(&path, apply_attribute_flags(current, flags))?;
+            log::info!("Set attributes of '{}'", path);
+        }
+        FileAction::WriteContent => {
+            let content = change.content.as_deref().ok_or_else(|| {
+                Error::ValidationError(format!(
+                    "File change '{}' requires 'content' for write_content action",
+                    change.path
+                ))
+            })?;
+            if let Some(parent) = Path::new(&path).parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| io_error("create folder", &parent.display().to_string(), e))?;
+            }
+            fs::write(&path, content).map_err(|e| io_error("write", &path, e))?;
+            log::info!("Wrote {} bytes to '{}'", content.len(), path);
+        }
+    }
+
+    Ok(())
+}
+
+fn io_error(operation: &str, path: &str, e: std::io::Error) -> Error {
+    Error::CommandExecution(format!("Failed to {} '{}': {}", operation, path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "magicx_file_service_{}_{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn known_variables_expand_and_unknown_ones_are_left_alone() {
+        let temp = std::env::var("TEMP").unwrap();
+        assert_eq!(expand_path("%TEMP%\\x.txt"), format!("{}\\x.txt", temp));
+        assert_eq!(
+            expand_path("%MAGICX_DOES_NOT_EXIST%\\x"),
+            "%MAGICX_DOES_NOT_EXIST%\\x"
+        );
+        assert_eq!(expand_path("100% sure"), "100% sure");
+    }
+
+    #[test]
+    fn attribute_flags_only_touch_the_bits_they_name() {
+        let flags = FileAttributes {
+            readonly: Some(true),
+            hidden: Some(false),
+            system: None,
+        };
+        let current = FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM;
+        assert_eq!(
+            apply_attribute_flags(current, &flags),
+            FILE_ATTRIBUTE_READONLY | FILE_ATTRIBUTE_SYSTEM
+        );
+    }
+
+    #[test]
+    fn a_folder_copies_recursively_and_deletes_even_with_read_only_files() {
+        let dir = scratch_dir("copy");
+        let src = dir.join("src");
+        fs::create_dir_all(src.join("nested")).unwrap();
+        fs::write(src.join("nested").join("a.txt"), "hello").unwrap();
+
+        let dest = dir.join("dest");
+        copy_path(&src, &dest).unwrap();
+        let copied = dest.join("nested").join("a.txt");
+        assert_eq!(fs::read_to_string(&copied).unwrap(), "hello");
+
+        let copied = copied.to_string_lossy().to_string();
+        set_attributes(
+            &copied,
+            get_attributes(&copied).unwrap() | FILE_ATTRIBUTE_READONLY,
+        )
+        .unwrap();
+        remove_path(&copied).unwrap();
+        assert!(!path_exists(&copied));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn write_content_replaces_the_file_and_compares_equal() {
+        let dir = scratch_dir("write");
+        let path = dir
+            .join("sub")
+            .join("cfg.ini")
+            .to_string_lossy()
+            .to_string();
+        let change = FileChange {
+            path: path.clone(),
+            action: FileAction::WriteContent,
+            destination: None,
+            attributes: None,
+            content: Some("[x]\r\nkey=1\r\n".to_string()),
+            skip_backup: false,
+            skip_validation: false,
+        };
+
+        apply_file_change(&change).unwrap();
+        assert!(content_equals(&path, b"[x]\r\nkey=1\r\n").unwrap());
+        assert!(!content_equals(&dir.join("missing").to_string_lossy(), b"").unwrap());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,237 @@
+//! Operation journal: a record of every apply, revert, and batch, so the last one can be undone.
+//!
+//! Each operation lists the tweaks it changed, with the option each one was applied with before
+//! and after (None: not applied by the app, i.e. at its original state). That is enough to undo
+//! it through the normal apply/revert paths: a tweak that wasn't applied is reverted, one that was
+//! is switched back to its earlier option. Changes are taken from the snapshots before and after
+//! each tweak's operation, so a failed or no-op apply leaves nothing to undo.
+//!
+//! The journal lives in `journal/journal.json` in the snapshots directory, next to the snapshots
+//! it refers to, and keeps the last `MAX_OPERATIONS` operations.
+
+use crate::error::Error;
+use crate::services::{backup_service, tweak_loader};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Folder (in the snapshots directory) holding the journal
+const JOURNAL_DIR: &str = "journal";
+
+const JOURNAL_FILE: &str = "journal.json";
+
+/// Operations kept; older ones are dropped
+const MAX_OPERATIONS: usize = 100;
+
+/// Serializes the journal's read-modify-write
+static JOURNAL_LOCK: Mutex<()> = Mutex::new(());
+
+/// What the user did
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationKind {
+    Apply,
+    Revert,
+    RestoreGeneration,
+    BatchApply,
+    BatchRevert,
+    RestoreAll,
+}
+
+/// One tweak changed by an operation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalChange {
+    pub tweak_id: String,
+    pub tweak_name: String,
+    /// Option the tweak was applied with before (None: not applied)
+    pub before: Option<usize>,
+    /// Option it was applied with after (None: reverted)
+    pub after: Option<usize>,
+}
+
+/// One recorded operation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalOperation {
+    /// Increasing number, unique within the journal
+    pub id: u64,
+    pub kind: OperationKind,
+    /// When it finished (RFC 3339)
+    pub timestamp: String,
+    /// In the order they were made
+    pub changes: Vec<JournalChange>,
+    /// Whether it has been undone
+    #[serde(default)]
+    pub undone: bool,
+}
+
+/// An operation being recorded; see `track`
+pub struct PendingOperation {
+    kind: OperationKind,
+    changes: Vec<JournalChange>,
+}
+
+impl PendingOperation {
+    pub fn new(kind: OperationKind) -> Self {
+        Self {
+            kind,
+            changes: Vec::new(),
+        }
+    }
+
+    /// Run `operation` on one tweak, noting how its applied option changed
+    pub fn track<T>(&mut self, tweak_id: &str, operation: impl FnOnce() -> T) -> T {
+        let before = applied_option(tweak_id);
+        let result = operation();
+        let after = applied_option(tweak_id);
+        if before != after {
+            self.changes.push(JournalChange {
+                tweak_id: tweak_id.to_string(),
+                tweak_name: tweak_loader::get_tweak(tweak_id)
+                    .ok()
+                    .flatten()
+                    .map_or_else(|| tweak_id.to_string(), |tweak| tweak.name),
+                before,
+                after,
+            });
+        }
+        result
+    }
+
+    /// Drop the changes noted for `tweak_id`, once the operation itself has undone them
+    pub fn forget(&mut self, tweak_id: &str) {
+        self.changes.retain(|change| change.tweak_id != tweak_id);
+    }
+
+    /// Add the operation to the journal, if it changed anything. The tweaks are already changed at
+    /// this point, so a failure is logged rather than failing the operation.
+    pub fn record(self) {
+        if self.changes.is_empty() {
+            return;
+        }
+        let kind = self.kind;
+        if let Err(e) = update(|operations| {
+            let id = operations.last().map_or(1, |last| last.id + 1);
+            operations.push(JournalOperation {
+                id,
+                kind,
+                timestamp: chrono::Local::now().to_rfc3339(),
+                changes: self.changes,
+                undone: false,
+            });
+        }) {
+            log::warn!(
+                "Failed to record {:?} in the operation journal: {}",
+                kind,
+                e
+            );
+        }
+    }
+}
+
+/// The option a tweak's snapshot says it's applied with, or None without a snapshot
+fn applied_option(tweak_id: &str) -> Option<usize> {
+    backup_service::load_snapshot(tweak_id)
+        .ok()
+        .flatten()
+        .map(|snapshot| snapshot.applied_option_index)
+}
+
+fn journal_path() -> Result<PathBuf, Error> {
+    Ok(backup_service::storage::get_snapshots_dir()?
+        .join(JOURNAL_DIR)
+        .join(JOURNAL_FILE))
+}
+
+fn read() -> Result<Vec<JournalOperation>, Error> {
+    let path = journal_path()?;
+    match fs::read(&path) {
+        Ok(content) => serde_json::from_slice(&content).map_err(|e| {
+            Error::BackupFailed(format!(
+                "Corrupt operation journal {}: {}",
+                path.display(),
+                e
+            ))
+        }),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(Error::BackupFailed(format!(
+            "Failed to read operation journal: {}",
+            e
+        ))),
+    }
+}
+
+fn update(change: impl FnOnce(&mut Vec<JournalOperation>)) -> Result<(), Error> {
+    let _guard = JOURNAL_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut operations = read()?;
+    change(&mut operations);
+    let excess = operations.len().saturating_sub(MAX_OPERATIONS);
+    operations.drain(..excess);
+
+    let path = journal_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| {
+            Error::BackupFailed(format!("Failed to create {}: {}", dir.display(), e))
+        })?;
+    }
+    let json = serde_json::to_string_pretty(&operations)
+        .map_err(|e| Error::BackupFailed(format!("Failed to serialize journal: {}", e)))?;
+    fs::write(&path, json)
+        .map_err(|e| Error::BackupFailed(format!("Failed to write operation journal: {}", e)))
+}
+
+/// Recorded operations, newest first
+pub fn get_history() -> Result<Vec<JournalOperation>, Error> {
+    let mut operations = read()?;
+    operations.reverse();
+    Ok(operations)
+}
+
+/// The newest operation that hasn't been undone
+pub fn last_undoable() -> Result<Option<JournalOperation>, Error> {
+    Ok(read()?
+        .into_iter()
+        .rev()
+        .find(|operation| !operation.undone))
+}
+
+/// Record the outcome of undoing an operation: it's marked undone once every change is, otherwise
+/// only the changes that are left stay on it, so undoing again retries just those
+pub fn finish_undo(id: u64, remaining: Vec<JournalChange>) -> Result<(), Error> {
+    update(|operations| {
+        if let Some(operation) = operations.iter_mut().find(|operation| operation.id == id) {
+            operation.undone = remaining.is_empty();
+            if !remaining.is_empty() {
+                operation.changes = remaining;
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operations_round_trip_through_json() {
+        let operation = JournalOperation {
+            id: 7,
+            kind: OperationKind::BatchApply,
+            timestamp: "2026-01-01T10:00:00+00:00".to_string(),
+            changes: vec![JournalChange {
+                tweak_id: "t".to_string(),
+                tweak_name: "T".to_string(),
+                before: None,
+                after: Some(1),
+            }],
+            undone: false,
+        };
+        let json = serde_json::to_string(&operation).unwrap();
+        assert!(json.contains("\"kind\":\"batch_apply\""));
+
+        let parsed: JournalOperation = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.changes, operation.changes);
+        assert_eq!(parsed.kind, OperationKind::BatchApply);
+    }
+}
@@ -1,13 +1,43 @@
+pub mod appx_service;
+pub mod audio_service;
+pub mod audit;
 pub mod backup;
+pub mod bcdedit_service;
+pub mod deferred_service;
+pub mod dism_service;
 pub mod elevation;
+pub mod file_service;
 pub mod firewall_service;
 pub mod hosts_service;
+pub mod journal_service;
+pub mod monitor_service;
+pub mod network_service;
+pub mod notes_service;
+pub mod package_service;
+pub mod policy_service;
+pub mod power_service;
+pub mod powershell;
+pub mod profile_template_service;
+pub mod protected_dir;
+pub mod registry_acl_service;
 pub mod registry_service;
 pub mod registry_value;
+pub mod relaunch_service;
+pub mod restore_point_service;
 pub mod scheduler_service;
 pub mod service_control;
+pub mod shell_service;
+pub mod software_service;
+pub mod startup_service;
 pub mod system_info_service;
+pub mod time_service;
+pub mod tweak_editor_service;
 pub mod tweak_loader;
+pub mod tweak_pack_service;
+pub mod usage_stats_service;
+pub mod user_profile_service;
+pub mod visual_effects_service;
+pub mod windows_update_service;
 
 // Re-export backup_service for backwards compatibility
 pub use backup as backup_service;
@@ -0,0 +1,367 @@
+//! Live resource usage for the dashboard.
+//!
+//! `start` runs a thread that samples CPU, memory, disk, and GPU utilization every interval and
+//! emits each sample as `system://metrics`; `stop` ends it. One sampler runs at a time, so
+//! starting again replaces it (e.g. with a new interval). CPU comes from `GetSystemTimes`, memory
+//! from `GlobalMemoryStatusEx`, and disk and GPU from performance counters (PDH). A counter that
+//! can't be read (GPU counters need Windows 10 1709 and a WDDM 2.x driver) is reported as None
+//! rather than failing the sample. CPU and GPU temperatures are added on request, read as
+//! `get_thermal_info` reads them.
+
+use crate::debug::emit_event;
+use crate::error::Error;
+use crate::services::system_info_service::ThermalReader;
+use serde::Serialize;
+use std::mem::size_of;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use windows_sys::Win32::Foundation::FILETIME;
+use windows_sys::Win32::System::Performance::{
+    PdhAddEnglishCounterW, PdhCloseQuery, PdhCollectQueryData, PdhGetFormattedCounterArrayW,
+    PdhGetFormattedCounterValue, PdhOpenQueryW, PDH_CSTATUS_NEW_DATA, PDH_CSTATUS_VALID_DATA,
+    PDH_FMT_COUNTERVALUE, PDH_FMT_COUNTERVALUE_ITEM_W, PDH_FMT_DOUBLE, PDH_HCOUNTER, PDH_HQUERY,
+    PDH_MORE_DATA,
+};
+use windows_sys::Win32::System::SystemInformation::{GlobalMemoryStatusEx, MEMORYSTATUSEX};
+use windows_sys::Win32::System::Threading::GetSystemTimes;
+
+/// Event carrying a [`ResourceSample`]
+pub const METRICS_EVENT: &str = "system://metrics";
+
+/// Sampling interval when none is given
+pub const DEFAULT_INTERVAL_MS: u64 = 1000;
+
+/// Shortest interval allowed; PDH rates are unreliable over shorter spans
+pub const MIN_INTERVAL_MS: u64 = 250;
+
+/// Longest interval allowed
+pub const MAX_INTERVAL_MS: u64 = 60_000;
+
+const DISK_IDLE_COUNTER: &str = "\\PhysicalDisk(_Total)\\% Idle Time";
+const GPU_3D_COUNTER: &str = "\\GPU Engine(*engtype_3D)\\Utilization Percentage";
+
+/// Stop flag of the running sampler, if one is
+static RUNNING: Mutex<Option<Arc<AtomicBool>>> = Mutex::new(None);
+
+/// Resource usage at one moment
+#[derive(Debug, Clone, Serialize)]
+pub struct ResourceSample {
+    /// When it was taken (RFC 3339)
+    pub timestamp: String,
+    /// CPU busy time since the last sample, 0-100
+    pub cpu_percent: f64,
+    pub memory_used_bytes: u64,
+    pub memory_total_bytes: u64,
+    /// Physical memory in use, 0-100
+    pub memory_percent: f64,
+    /// Time the disks were busy since the last sample, 0-100
+    pub disk_percent: Option<f64>,
+    /// Utilization of the GPUs' 3D engines since the last sample, 0-100
+    pub gpu_percent: Option<f64>,
+    /// CPU temperature in °C, if temperatures were requested and can be read
+    pub cpu_temp_celsius: Option<f64>,
+    /// GPU temperature in °C, if temperatures were requested and can be read
+    pub gpu_temp_celsius: Option<f64>,
+}
+
+/// Start sampling every `interval_ms` (clamped to `MIN_INTERVAL_MS`-`MAX_INTERVAL_MS`), with
+/// temperatures if `thermal`, replacing the sampler already running. Returns the interval used.
+pub fn start(interval_ms: Option<u64>, thermal: bool) -> Result<u64, Error> {
+    let interval_ms = interval_ms
+        .unwrap_or(DEFAULT_INTERVAL_MS)
+        .clamp(MIN_INTERVAL_MS, MAX_INTERVAL_MS);
+    let stop = Arc::new(AtomicBool::new(false));
+    let mut running = RUNNING.lock().unwrap_or_else(|e| e.into_inner());
+
+    let flag = Arc::clone(&stop);
+    thread::Builder::new()
+        .name("resource-monitor".into())
+        .spawn(move || run(Duration::from_millis(interval_ms), thermal, &flag))
+        .map_err(|e| Error::WindowsApi(format!("Failed to start the resource monitor: {}", e)))?;
+
+    if let Some(previous) = running.replace(stop) {
+        previous.store(true, Ordering::SeqCst);
+    }
+    log::info!("Resource monitor started ({} ms)", interval_ms);
+    Ok(interval_ms)
+}
+
+/// Stop sampling. Returns false if no sampler was running.
+pub fn stop() -> bool {
+    let running = RUNNING.lock().unwrap_or_else(|e| e.into_inner()).take();
+    match running {
+        Some(stop) => {
+            stop.store(true, Ordering::SeqCst);
+            log::info!("Resource monitor stopped");
+            true
+        }
+        None => false,
+    }
+}
+
+fn run(interval: Duration, thermal: bool, stop: &AtomicBool) {
+    let mut cpu = CpuTimes::now();
+    let counters = Counters::open();
+    // Connected on this thread, which its WMI connection belongs to
+    let thermal = thermal.then(ThermalReader::new);
+    // PDH rates and CPU usage need two readings, so the first sample comes an interval in
+    while !stop.load(Ordering::SeqCst) {
+        thread::sleep(interval);
+        if stop.load(Ordering::SeqCst) {
+            break;
+        }
+        let sample = sample(&mut cpu, counters.as_ref(), thermal.as_ref());
+        emit_event(METRICS_EVENT, sample);
+    }
+}
+
+fn sample(
+    cpu: &mut Option<CpuTimes>,
+    counters: Option<&Counters>,
+    thermal: Option<&ThermalReader>,
+) -> ResourceSample {
+    let now = CpuTimes::now();
+    let cpu_percent = match (cpu.as_ref(), now.as_ref()) {
+        (Some(before), Some(after)) => before.busy_percent(after),
+        _ => 0.0,
+    };
+    *cpu = now;
+
+    let (memory_used_bytes, memory_total_bytes) = memory();
+    let (disk_percent, gpu_percent) = counters.map_or((None, None), Counters::read);
+    let temperatures = thermal.map(ThermalReader::read);
+    ResourceSample {
+        timestamp: chrono::Local::now().to_rfc3339(),
+        cpu_percent,
+        memory_used_bytes,
+        memory_total_bytes,
+        memory_percent: percent(memory_used_bytes as f64, memory_total_bytes as f64),
+        disk_percent,
+        gpu_percent,
+        cpu_temp_celsius: temperatures.as_ref().and_then(|info| info.cpu_celsius),
+        gpu_temp_celsius: temperatures.as_ref().and_then(|info| info.gpu_celsius),
+    }
+}
+
+/// `part` as a percentage of `whole`, 0-100
+fn percent(part: f64, whole: f64) -> f64 {
+    if whole > 0.0 {
+        (part / whole * 100.0).clamp(0.0, 100.0)
+    } else {
+        0.0
+    }
+}
+
+/// Idle, kernel (which includes idle), and user time of all CPUs, in 100 ns units
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CpuTimes {
+    idle: u64,
+    kernel: u64,
+    user: u64,
+}
+
+impl CpuTimes {
+    fn now() -> Option<Self> {
+        let mut idle = FILETIME::default();
+        let mut kernel = FILETIME::default();
+        let mut user = FILETIME::default();
+        // SAFETY: the three out-pointers are valid FILETIMEs
+        let ok = unsafe { GetSystemTimes(&mut idle, &mut kernel, &mut user) };
+        (ok != 0).then(|| CpuTimes {
+            idle: ticks(idle),
+            kernel: ticks(kernel),
+            user: ticks(user),
+        })
+    }
+
+    /// Share of the time from `self` to `later` that the CPUs were busy, 0-100
+    fn busy_percent(&self, later: &CpuTimes) -> f64 {
+        let idle = later.idle.saturating_sub(self.idle);
+        let total = later.kernel.saturating_sub(self.kernel) + later.user.saturating_sub(self.user);
+        percent(total.saturating_sub(idle) as f64, total as f64)
+    }
+}
+
+fn ticks(time: FILETIME) -> u64 {
+    (u64::from(time.dwHighDateTime) << 32) | u64::from(time.dwLowDateTime)
+}
+
+/// Physical memory in use and in total, in bytes
+fn memory() -> (u64, u64) {
+    let mut status = MEMORYSTATUSEX {
+        dwLength: size_of::<MEMORYSTATUSEX>() as u32,
+        ..Default::default()
+    };
+    // SAFETY: `status` is a MEMORYSTATUSEX with dwLength set, as the call requires
+    if unsafe { GlobalMemoryStatusEx(&mut status) } == 0 {
+        return (0, 0);
+    }
+    (
+        status.ullTotalPhys.saturating_sub(status.ullAvailPhys),
+        status.ullTotalPhys,
+    )
+}
+
+/// The PDH query behind the disk and GPU readings
+struct Counters {
+    query: PDH_HQUERY,
+    disk_idle: Option<PDH_HCOUNTER>,
+    gpu_3d: Option<PDH_HCOUNTER>,
+}
+
+impl Counters {
+    /// Open the query and take the first reading rates are measured from. None if PDH can't be
+    /// used at all.
+    fn open() -> Option<Self> {
+        let mut query: PDH_HQUERY = std::ptr::null_mut();
+        // SAFETY: a null data source means live data; `query` receives the handle
+        if unsafe { PdhOpenQueryW(std::ptr::null(), 0, &mut query) } != 0 {
+            log::warn!("Resource monitor: performance counters are unavailable");
+            return None;
+        }
+        let counters = Counters {
+            query,
+            disk_idle: add_counter(query, DISK_IDLE_COUNTER),
+            gpu_3d: add_counter(query, GPU_3D_COUNTER),
+        };
+        // SAFETY: `query` is the open query
+        unsafe { PdhCollectQueryData(query) };
+        Some(counters)
+    }
+
+    /// Disk and GPU utilization since the last reading
+    fn read(&self) -> (Option<f64>, Option<f64>) {
+        // SAFETY: `self.query` stays open until drop
+        if unsafe { PdhCollectQueryData(self.query) } != 0 {
+            return (None, None);
+        }
+        let disk = self
+            .disk_idle
+            .and_then(counter_value)
+            .map(|idle| (100.0 - idle).clamp(0.0, 100.0));
+        let gpu = self
+            .gpu_3d
+            .and_then(counter_sum)
+            .map(|busy| busy.clamp(0.0, 100.0));
+        (disk, gpu)
+    }
+}
+
+impl Drop for Counters {
+    fn drop(&mut self) {
+        // SAFETY: the query is open and closed only here; closing it frees its counters too
+        unsafe { PdhCloseQuery(self.query) };
+    }
+}
+
+fn add_counter(query: PDH_HQUERY, path: &str) -> Option<PDH_HCOUNTER> {
+    let wide: Vec<u16> = path.encode_utf16().chain(std::iter::once(0)).collect();
+    let mut counter: PDH_HCOUNTER = std::ptr::null_mut();
+    // SAFETY: `wide` is a NUL-terminated counter path; `counter` receives the handle
+    let status = unsafe { PdhAddEnglishCounterW(query, wide.as_ptr(), 0, &mut counter) };
+    if status != 0 {
+        log::warn!(
+            "Resource monitor: counter {} is unavailable (0x{:08X})",
+            path,
+            status
+        );
+        return None;
+    }
+    Some(counter)
+}
+
+fn valid(value: &PDH_FMT_COUNTERVALUE) -> bool {
+    value.CStatus == PDH_CSTATUS_VALID_DATA || value.CStatus == PDH_CSTATUS_NEW_DATA
+}
+
+/// A single-instance counter's value
+fn counter_value(counter: PDH_HCOUNTER) -> Option<f64> {
+    let mut value = PDH_FMT_COUNTERVALUE::default();
+    // SAFETY: `counter` belongs to the open query; `value` receives the formatted value
+    let status = unsafe {
+        PdhGetFormattedCounterValue(counter, PDH_FMT_DOUBLE, std::ptr::null_mut(), &mut value)
+    };
+    // SAFETY: PDH_FMT_DOUBLE fills doubleValue, and any bits are a valid f64
+    (status == 0 && valid(&value)).then_some(unsafe { value.Anonymous.doubleValue })
+}
+
+/// The sum of a wildcard counter's values over its instances (0 when it has none right now)
+fn counter_sum(counter: PDH_HCOUNTER) -> Option<f64> {
+    let mut size = 0u32;
+    let mut count = 0u32;
+    // SAFETY: a null buffer with size 0 asks for the size needed
+    let status = unsafe {
+        PdhGetFormattedCounterArrayW(
+            counter,
+            PDH_FMT_DOUBLE,
+            &mut size,
+            &mut count,
+            std::ptr::null_mut(),
+        )
+    };
+    if status == 0 {
+        return Some(0.0);
+    }
+    if status != PDH_MORE_DATA {
+        return None;
+    }
+
+    // The items are followed by the instance names they point to, so the buffer is sized in
+    // bytes; a Vec of items keeps it aligned
+    let item_size = size_of::<PDH_FMT_COUNTERVALUE_ITEM_W>();
+    let mut items =
+        vec![PDH_FMT_COUNTERVALUE_ITEM_W::default(); (size as usize).div_ceil(item_size)];
+    // SAFETY: `items` holds at least `size` bytes
+    let status = unsafe {
+        PdhGetFormattedCounterArrayW(
+            counter,
+            PDH_FMT_DOUBLE,
+            &mut size,
+            &mut count,
+            items.as_mut_ptr(),
+        )
+    };
+    if status != 0 {
+        return None;
+    }
+    Some(
+        items
+            .iter()
+            .take(count as usize)
+            .filter(|item| valid(&item.FmtValue))
+            // SAFETY: PDH_FMT_DOUBLE fills doubleValue
+            .map(|item| unsafe { item.FmtValue.Anonymous.doubleValue })
+            .sum(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cpu_usage_is_the_busy_share_of_the_elapsed_time() {
+        let before = CpuTimes {
+            idle: 100,
+            kernel: 300,
+            user: 100,
+        };
+        // 400 ticks elapsed (kernel 300 of which 100 idle, user 100): 300 busy
+        let after = CpuTimes {
+            idle: 200,
+            kernel: 600,
+            user: 200,
+        };
+        assert_eq!(before.busy_percent(&after), 75.0);
+        assert_eq!(before.busy_percent(&before), 0.0);
+    }
+
+    #[test]
+    fn percentages_stay_in_range() {
+        assert_eq!(percent(5.0, 0.0), 0.0);
+        assert_eq!(percent(150.0, 100.0), 100.0);
+        assert_eq!(percent(25.0, 100.0), 25.0);
+    }
+}
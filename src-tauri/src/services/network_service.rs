@@ -0,0 +1,146 @@
+//! Network adapter service for reading and setting advanced adapter properties.
+//!
+//! Uses the NetAdapter PowerShell module (`Get-NetAdapterAdvancedProperty`,
+//! `Set-NetAdapterAdvancedProperty`, ...) and addresses properties by their registry keyword
+//! (`*RSS`, `*LsoV2IPv4`, `*InterruptModeration`, `*EEE`), which unlike display names and display
+//! values is not localized or vendor-specific. Only physical adapters are considered. Setting a
+//! property restarts the adapter (a brief disconnect) and requires administrator privileges.
+
+use crate::error::Error;
+use crate::models::NetAdapterChange;
+use crate::services::powershell::{ps_quote, run_script};
+use serde::Deserialize;
+
+/// Adapter name pattern used when a change omits `adapter`: every physical adapter.
+pub const DEFAULT_ADAPTER: &str = "*";
+
+/// An adapter's value for one advanced property
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AdapterProperty {
+    /// Adapter name (e.g. "Ethernet")
+    pub name: String,
+    /// Current registry value, or None if the driver default is in effect
+    #[serde(default)]
+    pub value: Option<String>,
+}
+
+/// The adapter pattern a change targets (`*` when omitted).
+pub fn resolve_adapter(adapter: Option<&str>) -> &str {
+    adapter.unwrap_or(DEFAULT_ADAPTER)
+}
+
+/// Read `keyword` on every physical adapter whose name matches `adapter` (wildcards allowed).
+/// Adapters whose driver doesn't expose the property are left out.
+pub fn get_properties(adapter: &str, keyword: &str) -> Result<Vec<AdapterProperty>, Error> {
+    // -AllProperties also lists keywords without a display entry; filtering the full list avoids
+    // the per-adapter error Get-NetAdapterAdvancedProperty raises for an unknown keyword.
+    let script = format!(
+        "$names = @(Get-NetAdapter -Physical | Where-Object Name -like {} | ForEach-Object Name); \
+         $props = if ($names.Count -gt 0) {{ @(Get-NetAdapterAdvancedProperty -Name $names -AllProperties | Where-Object RegistryKeyword -eq {}) }} else {{ @() }}; \
+         ConvertTo-Json -Compress -InputObject @($props | ForEach-Object {{ [pscustomobject]@{{ Name = $_.Name; Value = ($_.RegistryValue | Select-Object -First 1) }} }})",
+        ps_quote(adapter),
+        ps_quote(keyword)
+    );
+    parse_property_list(&run_script(&script)?)
+}
+
+/// Set `keyword` to `value` on one adapter.
+pub fn set_property(adapter: &str, keyword: &str, value: &str) -> Result<(), Error> {
+    let script = format!(
+        "Set-NetAdapterAdvancedProperty -Name {} -RegistryKeyword {} -RegistryValue {}",
+        ps_quote(adapter),
+        ps_quote(keyword),
+        ps_quote(value)
+    );
+    run_script(&script)?;
+    log::info!("Set adapter '{}' {} = {}", adapter, keyword, value);
+    Ok(())
+}
+
+/// Put `keyword` back to the driver default on one adapter.
+pub fn reset_property(adapter: &str, keyword: &str) -> Result<(), Error> {
+    let script = format!(
+        "Reset-NetAdapterAdvancedProperty -Name {} -RegistryKeyword {}",
+        ps_quote(adapter),
+        ps_quote(keyword)
+    );
+    run_script(&script)?;
+    log::info!("Reset adapter '{}' {} to its default", adapter, keyword);
+    Ok(())
+}
+
+/// Apply a network adapter change to every matching adapter that exposes the property.
+/// Fails if no adapter does, so a change that can't take effect isn't reported as applied.
+pub fn apply_netadapter_change(change: &NetAdapterChange) -> Result<(), Error> {
+    let adapter = resolve_adapter(change.adapter.as_deref());
+    let properties = get_properties(adapter, &change.keyword)?;
+    if properties.is_empty() {
+        return Err(Error::NotFound(format!(
+            "No physical adapter matching '{}' has property '{}'",
+            adapter, change.keyword
+        )));
+    }
+
+    for property in properties {
+        if property.value.as_deref() == Some(change.value.as_str()) {
+            log::debug!(
+                "Adapter '{}' {} already {}",
+                property.name,
+                change.keyword,
+                change.value
+            );
+            continue;
+        }
+        set_property(&property.name, &change.keyword, &change.value)?;
+    }
+
+    Ok(())
+}
+
+/// Parse the JSON array emitted by [`get_properties`].
+fn parse_property_list(json: &str) -> Result<Vec<AdapterProperty>, Error> {
+    let json = json.trim();
+    if json.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    serde_json::from_str(json)
+        .map_err(|e| Error::CommandExecution(format!("Unexpected adapter property list: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_property_list_parses_with_and_without_values() {
+        let json = r#"[{"Name":"Ethernet","Value":"1"},{"Name":"Ethernet 2","Value":null}]"#;
+        let properties = parse_property_list(json).unwrap();
+        assert_eq!(
+            properties,
+            vec![
+                AdapterProperty {
+                    name: "Ethernet".to_string(),
+                    value: Some("1".to_string()),
+                },
+                AdapterProperty {
+                    name: "Ethernet 2".to_string(),
+                    value: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_output_is_an_empty_list() {
+        assert!(parse_property_list("[]").unwrap().is_empty());
+        assert!(parse_property_list("\r\n").unwrap().is_empty());
+    }
+
+    #[test]
+    fn an_omitted_adapter_targets_every_physical_adapter() {
+        assert_eq!(resolve_adapter(None), "*");
+        assert_eq!(resolve_adapter(Some("Wi-Fi")), "Wi-Fi");
+    }
+}
@@ -0,0 +1,195 @@
+//! Per-tweak notes: a line or two the user keeps on a tweak, e.g. why a risky one was applied.
+//!
+//! Notes are keyed by tweak ID in `notes/notes.json` in the snapshots directory, so they move with
+//! the snapshots (and travel in backup archives) but stay apart from them: a note outlives the
+//! snapshot it was written next to, and deleting a snapshot never loses it.
+
+use crate::error::Error;
+use crate::services::backup_service;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Folder (in the snapshots directory) holding the notes
+const NOTES_DIR: &str = "notes";
+
+const NOTES_FILE: &str = "notes.json";
+
+/// Longest note kept, in characters
+pub const MAX_NOTE_LENGTH: usize = 2000;
+
+/// Serializes the notes' read-modify-write
+static NOTES_LOCK: Mutex<()> = Mutex::new(());
+
+/// The user's note on one tweak
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TweakNote {
+    pub text: String,
+    /// When it was last changed (RFC 3339)
+    pub updated_at: String,
+}
+
+/// Notes by tweak ID
+pub type TweakNotes = BTreeMap<String, TweakNote>;
+
+fn notes_path() -> Result<PathBuf, Error> {
+    Ok(backup_service::storage::get_snapshots_dir()?
+        .join(NOTES_DIR)
+        .join(NOTES_FILE))
+}
+
+fn read() -> Result<TweakNotes, Error> {
+    let path = notes_path()?;
+    match fs::read(&path) {
+        Ok(content) => serde_json::from_slice(&content).map_err(|e| {
+            Error::BackupFailed(format!("Corrupt tweak notes {}: {}", path.display(), e))
+        }),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(TweakNotes::new()),
+        Err(e) => Err(Error::BackupFailed(format!(
+            "Failed to read tweak notes: {}",
+            e
+        ))),
+    }
+}
+
+fn update<T>(change: impl FnOnce(&mut TweakNotes) -> T) -> Result<T, Error> {
+    let _guard = NOTES_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut notes = read()?;
+    let result = change(&mut notes);
+
+    let path = notes_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| {
+            Error::BackupFailed(format!("Failed to create {}: {}", dir.display(), e))
+        })?;
+    }
+    let json = serde_json::to_string_pretty(&notes)
+        .map_err(|e| Error::BackupFailed(format!("Failed to serialize tweak notes: {}", e)))?;
+    fs::write(&path, json)
+        .map_err(|e| Error::BackupFailed(format!("Failed to write tweak notes: {}", e)))?;
+    Ok(result)
+}
+
+/// Set `text` as the note of `notes[tweak_id]`, or remove the note when it's blank. Returns the
+/// note kept.
+fn put_note(notes: &mut TweakNotes, tweak_id: &str, text: &str, now: String) -> Option<TweakNote> {
+    let text = text.trim();
+    if text.is_empty() {
+        notes.remove(tweak_id);
+        return None;
+    }
+    let note = TweakNote {
+        text: text.to_string(),
+        updated_at: now,
+    };
+    notes.insert(tweak_id.to_string(), note.clone());
+    Some(note)
+}
+
+/// Every note, by tweak ID
+pub fn get_notes() -> Result<TweakNotes, Error> {
+    read()
+}
+
+/// The note on `tweak_id`, if it has one
+pub fn get_note(tweak_id: &str) -> Result<Option<TweakNote>, Error> {
+    Ok(read()?.remove(tweak_id))
+}
+
+/// Set the note on `tweak_id`; blank text removes it. Returns the note kept.
+pub fn set_note(tweak_id: &str, text: &str) -> Result<Option<TweakNote>, Error> {
+    if text.trim().chars().count() > MAX_NOTE_LENGTH {
+        return Err(Error::ValidationError(format!(
+            "Notes are limited to {} characters",
+            MAX_NOTE_LENGTH
+        )));
+    }
+    update(|notes| put_note(notes, tweak_id, text, chrono::Local::now().to_rfc3339()))
+}
+
+/// Add notes from elsewhere (a backup archive), keeping the note already here for a tweak that has
+/// one. Returns the number added.
+pub fn merge_notes(imported: TweakNotes) -> Result<usize, Error> {
+    if imported.is_empty() {
+        return Ok(0);
+    }
+    update(|notes| {
+        let before = notes.len();
+        for (tweak_id, note) in imported {
+            notes.entry(tweak_id).or_insert(note);
+        }
+        notes.len() - before
+    })
+}
+
+/// Move the notes kept under a renamed tweak's earlier ID to its current one (`renamed_to` gives
+/// it), unless the tweak already has a note there. Returns the number moved.
+pub fn rename_notes<'a>(renamed_to: impl Fn(&str) -> Option<&'a str>) -> Result<usize, Error> {
+    if !read()?
+        .keys()
+        .any(|tweak_id| renamed_to(tweak_id).is_some())
+    {
+        return Ok(0);
+    }
+    update(|notes| move_renamed(notes, renamed_to))
+}
+
+fn move_renamed<'a>(notes: &mut TweakNotes, renamed_to: impl Fn(&str) -> Option<&'a str>) -> usize {
+    let renamed: Vec<(String, &str)> = notes
+        .keys()
+        .filter_map(|old_id| renamed_to(old_id).map(|new_id| (old_id.clone(), new_id)))
+        .collect();
+    let mut moved = 0;
+    for (old_id, new_id) in renamed {
+        if notes.contains_key(new_id) {
+            continue;
+        }
+        if let Some(note) = notes.remove(&old_id) {
+            notes.insert(new_id.to_string(), note);
+            moved += 1;
+        }
+    }
+    moved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blank_text_removes_the_note() {
+        let mut notes = TweakNotes::new();
+        let kept = put_note(
+            &mut notes,
+            "t",
+            "  needed for the VPN client \n",
+            "now".into(),
+        );
+        assert_eq!(kept.unwrap().text, "needed for the VPN client");
+        assert_eq!(notes.len(), 1);
+
+        assert!(put_note(&mut notes, "t", "   ", "later".into()).is_none());
+        assert!(notes.is_empty());
+    }
+
+    #[test]
+    fn a_renamed_tweaks_note_moves_unless_the_new_id_has_one() {
+        let mut notes = TweakNotes::new();
+        put_note(&mut notes, "old", "moved", "now".into());
+        put_note(&mut notes, "older", "kept apart", "now".into());
+        put_note(&mut notes, "current", "already here", "now".into());
+        let renamed_to = |id: &str| match id {
+            "old" => Some("new"),
+            "older" => Some("current"),
+            _ => None,
+        };
+
+        assert_eq!(move_renamed(&mut notes, renamed_to), 1);
+        assert_eq!(notes["new"].text, "moved");
+        assert_eq!(notes["current"].text, "already here");
+        assert!(notes.contains_key("older") && !notes.contains_key("old"));
+    }
+}
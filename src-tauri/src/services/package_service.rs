@@ -0,0 +1,307 @@
+//! Application package service for installing and uninstalling software through winget.
+//!
+//! Packages are addressed by their winget identifier with `--exact`, so a change never matches a
+//! similarly named package. winget reports results as HRESULT exit codes: the ones that mean
+//! "nothing to do" (already installed, not installed) count as success, and an installer that
+//! needs a reboot to finish is logged rather than failed. Each package's progress is sent to the
+//! frontend as an `app-change-progress` event, since a single install can take minutes.
+//! winget must be available (App Installer); machine-wide installers need administrator
+//! privileges.
+
+use crate::debug::emit_event;
+use crate::error::Error;
+use crate::models::{AppAction, AppChange};
+use serde::Serialize;
+use std::os::windows::process::CommandExt;
+use std::process::Command;
+
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+/// Source used for installs when a change omits `source`.
+pub const DEFAULT_SOURCE: &str = "winget";
+
+/// Frontend event carrying [`AppProgress`] payloads.
+pub const PROGRESS_EVENT: &str = "app-change-progress";
+
+// winget exit codes (HRESULTs) with a meaning other than plain failure
+const NO_APPLICATIONS_FOUND: u32 = 0x8A15_0014;
+const UPDATE_NOT_APPLICABLE: u32 = 0x8A15_002B;
+const PACKAGE_ALREADY_INSTALLED: u32 = 0x8A15_0061;
+const INSTALL_REBOOT_REQUIRED_TO_FINISH: u32 = 0x8A15_0109;
+const INSTALL_REBOOT_INITIATED: u32 = 0x8A15_010B;
+const INSTALL_ALREADY_INSTALLED: u32 = 0x8A15_010D;
+
+/// How a winget install or uninstall ended, when it didn't fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WingetOutcome {
+    /// The package was installed or uninstalled
+    Done,
+    /// The package was already in the requested state
+    NothingToDo,
+    /// The change went through but only completes after a reboot
+    RebootRequired,
+}
+
+/// Stage of one package in an `app-change-progress` event
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AppProgressStage {
+    Started,
+    Finished,
+    Failed,
+}
+
+/// Progress of one package within a batch of application changes
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppProgress {
+    pub tweak_id: String,
+    /// winget package identifier
+    pub id: String,
+    /// "install" or "uninstall"
+    pub action: &'static str,
+    /// 1-based position of this package in the batch
+    pub index: usize,
+    pub total: usize,
+    pub stage: AppProgressStage,
+    /// Failure reason (failed stage only)
+    pub message: Option<String>,
+}
+
+impl AppProgress {
+    pub fn new(tweak_id: &str, id: &str, action: AppAction, index: usize, total: usize) -> Self {
+        Self {
+            tweak_id: tweak_id.to_string(),
+            id: id.to_string(),
+            action: action.as_str(),
+            index,
+            total,
+            stage: AppProgressStage::Started,
+            message: None,
+        }
+    }
+
+    /// Send this package's progress at `stage` to the frontend.
+    pub fn emit(&self, stage: AppProgressStage, message: Option<String>) {
+        emit_event(
+            PROGRESS_EVENT,
+            AppProgress {
+                stage,
+                message,
+                ..self.clone()
+            },
+        );
+    }
+}
+
+/// Run winget with the given arguments, returning its exit code and stdout.
+fn run_winget(args: &[&str]) -> Result<(i32, String), Error> {
+    let output = Command::new("winget")
+        .args(args)
+        .args(["--accept-source-agreements", "--disable-interactivity"])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .map_err(|e| {
+            Error::CommandExecution(format!(
+                "Failed to run winget (is App Installer installed?): {}",
+                e
+            ))
+        })?;
+
+    Ok((
+        output.status.code().unwrap_or(-1),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+    ))
+}
+
+/// Whether a package is installed, matched by exact identifier.
+pub fn is_installed(id: &str) -> Result<bool, Error> {
+    let (code, stdout) = run_winget(&["list", "--id", id, "--exact"])?;
+    match code as u32 {
+        0 => Ok(true),
+        NO_APPLICATIONS_FOUND => Ok(false),
+        _ => Err(Error::CommandExecution(format!(
+            "winget list {} failed with exit code {}: {}",
+            id,
+            describe_exit_code(code),
+            last_message(&stdout)
+        ))),
+    }
+}
+
+/// Install a package silently from `source` (default: winget), optionally at an exact version.
+pub fn install(
+    id: &str,
+    source: Option<&str>,
+    version: Option<&str>,
+) -> Result<WingetOutcome, Error> {
+    let mut args = vec![
+        "install",
+        "--id",
+        id,
+        "--exact",
+        "--source",
+        source.unwrap_or(DEFAULT_SOURCE),
+        "--silent",
+        "--accept-package-agreements",
+    ];
+    if let Some(version) = version {
+        args.extend(["--version", version]);
+    }
+    run_package_action(AppAction::Install, id, &args)
+}
+
+/// Uninstall a package silently, optionally only as installed from `source`.
+pub fn uninstall(id: &str, source: Option<&str>) -> Result<WingetOutcome, Error> {
+    let mut args = vec!["uninstall", "--id", id, "--exact", "--silent"];
+    if let Some(source) = source {
+        args.extend(["--source", source]);
+    }
+    run_package_action(AppAction::Uninstall, id, &args)
+}
+
+/// Apply an application change
+pub fn apply_app_change(change: &AppChange) -> Result<WingetOutcome, Error> {
+    match change.action {
+        AppAction::Install => install(
+            &change.id,
+            change.source.as_deref(),
+            change.version.as_deref(),
+        ),
+        AppAction::Uninstall => uninstall(&change.id, change.source.as_deref()),
+    }
+}
+
+fn run_package_action(action: AppAction, id: &str, args: &[&str]) -> Result<WingetOutcome, Error> {
+    let (code, stdout) = run_winget(args)?;
+    let outcome = classify_exit_code(action, code).ok_or_else(|| {
+        Error::CommandExecution(format!(
+            "winget {} {} failed with exit code {}: {}",
+            action.as_str(),
+            id,
+            describe_exit_code(code),
+            last_message(&stdout)
+        ))
+    })?;
+
+    match outcome {
+        WingetOutcome::Done => log::info!("{} package '{}'", past_tense(action), id),
+        WingetOutcome::NothingToDo => log::info!(
+            "Package '{}' already {}",
+            id,
+            past_tense(action).to_lowercase()
+        ),
+        WingetOutcome::RebootRequired => log::info!(
+            "{} package '{}' (a restart is needed to finish)",
+            past_tense(action),
+            id
+        ),
+    }
+    Ok(outcome)
+}
+
+fn past_tense(action: AppAction) -> &'static str {
+    match action {
+        AppAction::Install => "Installed",
+        AppAction::Uninstall => "Uninstalled",
+    }
+}
+
+/// Map a winget exit code to an outcome, or None if it is a failure.
+fn classify_exit_code(action: AppAction, code: i32) -> Option<WingetOutcome> {
+    match (action, code as u32) {
+        (_, 0) => Some(WingetOutcome::Done),
+        (AppAction::Install, PACKAGE_ALREADY_INSTALLED)
+        | (AppAction::Install, INSTALL_ALREADY_INSTALLED)
+        | (AppAction::Install, UPDATE_NOT_APPLICABLE)
+        | (AppAction::Uninstall, NO_APPLICATIONS_FOUND) => Some(WingetOutcome::NothingToDo),
+        (_, INSTALL_REBOOT_REQUIRED_TO_FINISH) | (_, INSTALL_REBOOT_INITIATED) => {
+            Some(WingetOutcome::RebootRequired)
+        }
+        _ => None,
+    }
+}
+
+/// A winget exit code as hex, with its meaning when it is one of the common failures.
+fn describe_exit_code(code: i32) -> String {
+    let meaning = match code as u32 {
+        NO_APPLICATIONS_FOUND => "no package matches the identifier",
+        0x8A15_0101 => "the application is in use",
+        0x8A15_0102 => "another installation is in progress",
+        0x8A15_0103 => "a file is in use",
+        0x8A15_0104 => "a dependency is missing",
+        0x8A15_0105 => "the disk is full",
+        0x8A15_0106 => "not enough memory",
+        0x8A15_0107 => "no network connection",
+        0x8A15_010A => "a restart is required before installing",
+        0x8A15_010C => "cancelled",
+        0x8A15_010E => "a newer version is already installed",
+        0x8A15_010F => "blocked by policy",
+        _ => return format!("0x{:08X}", code as u32),
+    };
+    format!("0x{:08X} ({})", code as u32, meaning)
+}
+
+/// The last line of winget output that isn't blank or a progress bar redraw.
+fn last_message(stdout: &str) -> &str {
+    stdout
+        .split(['\r', '\n'])
+        .rev()
+        .map(str::trim)
+        .find(|line| line.chars().any(char::is_alphanumeric))
+        .unwrap_or("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hresult(code: u32) -> i32 {
+        code as i32
+    }
+
+    #[test]
+    fn not_needed_changes_count_as_success_only_for_their_action() {
+        assert_eq!(
+            classify_exit_code(AppAction::Install, hresult(PACKAGE_ALREADY_INSTALLED)),
+            Some(WingetOutcome::NothingToDo)
+        );
+        assert_eq!(
+            classify_exit_code(AppAction::Uninstall, hresult(NO_APPLICATIONS_FOUND)),
+            Some(WingetOutcome::NothingToDo)
+        );
+        // An install whose identifier matches nothing is a real failure
+        assert_eq!(
+            classify_exit_code(AppAction::Install, hresult(NO_APPLICATIONS_FOUND)),
+            None
+        );
+    }
+
+    #[test]
+    fn reboot_and_failure_codes_are_classified() {
+        assert_eq!(
+            classify_exit_code(AppAction::Install, 0),
+            Some(WingetOutcome::Done)
+        );
+        assert_eq!(
+            classify_exit_code(
+                AppAction::Uninstall,
+                hresult(INSTALL_REBOOT_REQUIRED_TO_FINISH)
+            ),
+            Some(WingetOutcome::RebootRequired)
+        );
+        assert_eq!(classify_exit_code(AppAction::Install, 1), None);
+        assert_eq!(
+            describe_exit_code(hresult(0x8A15_0107)),
+            "0x8A150107 (no network connection)"
+        );
+        assert_eq!(describe_exit_code(-1), "0xFFFFFFFF");
+    }
+
+    #[test]
+    fn the_last_message_skips_progress_bar_redraws() {
+        let stdout = "Found OneDrive [Microsoft.OneDrive]\r\n  ██████▒▒▒▒  60%\r  ██████████  100%\r\nSuccessfully uninstalled\r\n  -\r\n";
+        assert_eq!(last_message(stdout), "Successfully uninstalled");
+        assert_eq!(last_message(""), "");
+    }
+}
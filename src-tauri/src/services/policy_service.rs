@@ -0,0 +1,531 @@
+//! Group Policy service for policy registry values, the local Registry.pol files, and refresh.
+//!
+//! A policy value is written straight to its Policies key so it takes effect immediately. With
+//! `write_pol` it is also recorded in the local Group Policy object
+//! (`%SystemRoot%\System32\GroupPolicy\{Machine,User}\Registry.pol`), which makes Windows re-apply
+//! it on every refresh; `gpt.ini` is then bumped so the change is picked up. `gpupdate` runs once
+//! per scope after a batch of changes. Machine policies and any Registry.pol write require
+//! administrator privileges.
+//!
+//! Registry.pol layout: the `PReg` signature and version 1, followed by one record per value,
+//! `[key;value;type;size;data]`, where the brackets, separators, key, and value are UTF-16LE
+//! (key and value NUL-terminated) and type and size are little-endian u32s.
+
+use crate::error::Error;
+use crate::models::{PolicyAction, PolicyChange, PolicyFileEntry, PolicyScope, RegistryHive};
+use crate::services::registry_value::{self, RegistryValue};
+use crate::services::{registry_service, trusted_installer};
+use std::fs;
+use std::os::windows::process::CommandExt;
+use std::path::PathBuf;
+use std::process::Command;
+
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+const POL_SIGNATURE: &[u8; 4] = b"PReg";
+const POL_VERSION: u32 = 1;
+
+/// Value-name prefix Registry.pol uses to record that policy deletes a value.
+const DELETE_PREFIX: &str = "**del.";
+
+/// Registry client-side extension, which processes Registry.pol.
+const REGISTRY_CSE: &str = "{35378EAC-683F-11D2-A89A-00C04FBBCFA2}";
+
+const REG_SZ: u32 = 1;
+const REG_EXPAND_SZ: u32 = 2;
+const REG_BINARY: u32 = 3;
+const REG_DWORD: u32 = 4;
+const REG_MULTI_SZ: u32 = 7;
+const REG_QWORD: u32 = 11;
+
+fn group_policy_dir() -> PathBuf {
+    let system_root = std::env::var("SystemRoot").unwrap_or_else(|_| "C:\\Windows".to_string());
+    PathBuf::from(system_root)
+        .join("System32")
+        .join("GroupPolicy")
+}
+
+/// The local Group Policy object's Registry.pol for a scope.
+pub fn pol_path(scope: PolicyScope) -> PathBuf {
+    let folder = match scope {
+        PolicyScope::Machine => "Machine",
+        PolicyScope::User => "User",
+    };
+    group_policy_dir().join(folder).join("Registry.pol")
+}
+
+/// Read every record of a scope's Registry.pol. A missing file has no records.
+pub fn read_pol(scope: PolicyScope) -> Result<Vec<PolicyFileEntry>, Error> {
+    let path = pol_path(scope);
+    match fs::read(&path) {
+        Ok(bytes) => parse_pol(&bytes),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(io_error("read", &path, e)),
+    }
+}
+
+/// Replace a scope's Registry.pol and bump gpt.ini so Group Policy processes it again.
+fn write_pol(scope: PolicyScope, entries: &[PolicyFileEntry]) -> Result<(), Error> {
+    let path = pol_path(scope);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| io_error("create", parent, e))?;
+    }
+    fs::write(&path, serialize_pol(entries)).map_err(|e| io_error("write", &path, e))?;
+
+    let gpt_ini = group_policy_dir().join("gpt.ini");
+    let current = match fs::read(&gpt_ini) {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(io_error("read", &gpt_ini, e)),
+    };
+    fs::write(&gpt_ini, update_gpt_ini(&current, scope)).map_err(|e| io_error("write", &gpt_ini, e))
+}
+
+/// Whether a Registry.pol record is about `key`\`value_name`, as a value or a deletion marker.
+pub fn is_record_for(entry: &PolicyFileEntry, key: &str, value_name: &str) -> bool {
+    let name = entry
+        .value_name
+        .get(..DELETE_PREFIX.len())
+        .filter(|prefix| prefix.eq_ignore_ascii_case(DELETE_PREFIX))
+        .map_or(entry.value_name.as_str(), |_| {
+            &entry.value_name[DELETE_PREFIX.len()..]
+        });
+    entry.key.eq_ignore_ascii_case(key) && name.eq_ignore_ascii_case(value_name)
+}
+
+/// The Registry.pol record for a value, if the local policy mentions it.
+pub fn get_entry(
+    scope: PolicyScope,
+    key: &str,
+    value_name: &str,
+) -> Result<Option<PolicyFileEntry>, Error> {
+    Ok(read_pol(scope)?
+        .into_iter()
+        .find(|entry| is_record_for(entry, key, value_name)))
+}
+
+/// Replace the Registry.pol record for a value with `entry`, or drop it when `entry` is None.
+pub fn set_entry(
+    scope: PolicyScope,
+    key: &str,
+    value_name: &str,
+    entry: Option<PolicyFileEntry>,
+) -> Result<(), Error> {
+    let mut entries = read_pol(scope)?;
+    let before = entries.len();
+    entries.retain(|existing| !is_record_for(existing, key, value_name));
+    if entry.is_none() && entries.len() == before {
+        return Ok(());
+    }
+    entries.extend(entry);
+    write_pol(scope, &entries)?;
+    log::info!(
+        "Updated {} Registry.pol record for {}\\{}",
+        scope.as_str(),
+        key,
+        value_name
+    );
+    Ok(())
+}
+
+/// The Registry.pol record a change writes.
+pub fn entry_for_change(change: &PolicyChange) -> Result<PolicyFileEntry, Error> {
+    match change.action {
+        PolicyAction::Set => {
+            let (value_type, value) = match (&change.value_type, &change.value) {
+                (Some(value_type), Some(value)) => (value_type, value),
+                _ => {
+                    return Err(Error::ValidationError(format!(
+                        "Policy '{}' requires value_type and value for set action",
+                        change.value_name
+                    )))
+                }
+            };
+            let (value_type, data) =
+                encode_value(&registry_value::parse_registry_value(value_type, value)?);
+            Ok(PolicyFileEntry {
+                key: change.key.clone(),
+                value_name: change.value_name.clone(),
+                value_type,
+                data,
+            })
+        }
+        PolicyAction::DeleteValue => Ok(PolicyFileEntry {
+            key: change.key.clone(),
+            value_name: format!("{}{}", DELETE_PREFIX, change.value_name),
+            value_type: REG_SZ,
+            data: utf16_z(" "),
+        }),
+    }
+}
+
+/// Run `gpupdate` for a scope so Group Policy processes the new state now.
+pub fn refresh(scope: PolicyScope) -> Result<(), Error> {
+    let target = match scope {
+        PolicyScope::Machine => "/target:computer",
+        PolicyScope::User => "/target:user",
+    };
+    let output = Command::new("gpupdate")
+        .args([target, "/force"])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .map_err(|e| Error::CommandExecution(format!("Failed to run gpupdate: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(Error::CommandExecution(format!(
+            "gpupdate {} failed: {} {}",
+            target,
+            String::from_utf8_lossy(&output.stdout).trim(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    log::info!("Refreshed {} policy", scope.as_str());
+    Ok(())
+}
+
+/// Apply a policy change: the registry value, then the Registry.pol record when requested.
+/// The caller refreshes Group Policy once the whole batch is written.
+pub fn apply_policy_change(change: &PolicyChange, use_system: bool) -> Result<(), Error> {
+    let hive = change.scope.hive();
+    match change.action {
+        PolicyAction::Set => {
+            let (value_type, value) = match (&change.value_type, &change.value) {
+                (Some(value_type), Some(value)) => (value_type, value),
+                _ => {
+                    return Err(Error::ValidationError(format!(
+                        "Policy '{}' requires value_type and value for set action",
+                        change.value_name
+                    )))
+                }
+            };
+            registry_value::write_registry_json_value(
+                &hive,
+                &change.key,
+                &change.value_name,
+                value_type,
+                value,
+                use_system,
+            )?;
+        }
+        PolicyAction::DeleteValue => {
+            if use_system && matches!(hive, RegistryHive::Hklm) {
+                trusted_installer::delete_registry_value_as_system(
+                    hive,
+                    &change.key,
+                    &change.value_name,
+                )?;
+            } else {
+                match registry_service::delete_value(&hive, &change.key, &change.value_name) {
+                    Ok(()) | Err(Error::RegistryKeyNotFound(_)) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+    }
+
+    if change.write_pol {
+        set_entry(
+            change.scope,
+            &change.key,
+            &change.value_name,
+            Some(entry_for_change(change)?),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Encode a value the way the registry stores it: (raw type, data bytes).
+fn encode_value(value: &RegistryValue) -> (u32, Vec<u8>) {
+    match value {
+        RegistryValue::Dword(v) => (REG_DWORD, v.to_le_bytes().to_vec()),
+        RegistryValue::Qword(v) => (REG_QWORD, v.to_le_bytes().to_vec()),
+        RegistryValue::String(s) => (REG_SZ, utf16_z(s)),
+        RegistryValue::ExpandString(s) => (REG_EXPAND_SZ, utf16_z(s)),
+        RegistryValue::MultiString(items) => {
+            let mut data: Vec<u8> = items.iter().flat_map(|s| utf16_z(s)).collect();
+            data.extend_from_slice(&[0, 0]);
+            (REG_MULTI_SZ, data)
+        }
+        RegistryValue::Binary(bytes) => (REG_BINARY, bytes.clone()),
+    }
+}
+
+/// UTF-16LE bytes of `s` followed by a NUL character.
+fn utf16_z(s: &str) -> Vec<u8> {
+    s.encode_utf16()
+        .chain(std::iter::once(0))
+        .flat_map(u16::to_le_bytes)
+        .collect()
+}
+
+/// Serialize records into Registry.pol bytes.
+fn serialize_pol(entries: &[PolicyFileEntry]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(POL_SIGNATURE);
+    out.extend_from_slice(&POL_VERSION.to_le_bytes());
+
+    let char_bytes = |c: char| (c as u16).to_le_bytes();
+    for entry in entries {
+        out.extend_from_slice(&char_bytes('['));
+        out.extend_from_slice(&utf16_z(&entry.key));
+        out.extend_from_slice(&char_bytes(';'));
+        out.extend_from_slice(&utf16_z(&entry.value_name));
+        out.extend_from_slice(&char_bytes(';'));
+        out.extend_from_slice(&entry.value_type.to_le_bytes());
+        out.extend_from_slice(&char_bytes(';'));
+        out.extend_from_slice(&(entry.data.len() as u32).to_le_bytes());
+        out.extend_from_slice(&char_bytes(';'));
+        out.extend_from_slice(&entry.data);
+        out.extend_from_slice(&char_bytes(']'));
+    }
+
+    out
+}
+
+/// Sequential reader over Registry.pol bytes.
+struct PolReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl PolReader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8], Error> {
+        let end = self.pos + len;
+        let slice = self.bytes.get(self.pos..end).ok_or_else(|| {
+            Error::ValidationError("Registry.pol ends in the middle of a record".to_string())
+        })?;
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u16(&mut self) -> Result<u16, Error> {
+        let b = self.take(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), Error> {
+        let at = self.pos;
+        if self.u16()? != c as u16 {
+            return Err(Error::ValidationError(format!(
+                "Registry.pol: expected '{}' at byte {}",
+                c, at
+            )));
+        }
+        Ok(())
+    }
+
+    /// A NUL-terminated UTF-16 string.
+    fn string(&mut self) -> Result<String, Error> {
+        let mut units = Vec::new();
+        loop {
+            match self.u16()? {
+                0 => break,
+                unit => units.push(unit),
+            }
+        }
+        Ok(String::from_utf16_lossy(&units))
+    }
+}
+
+/// Parse Registry.pol bytes into records.
+fn parse_pol(bytes: &[u8]) -> Result<Vec<PolicyFileEntry>, Error> {
+    if bytes.is_empty() {
+        return Ok(Vec::new());
+    }
+    if bytes.len() < 8 || &bytes[..4] != POL_SIGNATURE {
+        return Err(Error::ValidationError(
+            "Registry.pol has no PReg signature".to_string(),
+        ));
+    }
+
+    let mut reader = PolReader { bytes, pos: 8 };
+    let mut entries = Vec::new();
+    while reader.pos < bytes.len() {
+        reader.expect('[')?;
+        let key = reader.string()?;
+        reader.expect(';')?;
+        let value_name = reader.string()?;
+        reader.expect(';')?;
+        let value_type = reader.u32()?;
+        reader.expect(';')?;
+        let size = reader.u32()? as usize;
+        reader.expect(';')?;
+        let data = reader.take(size)?.to_vec();
+        reader.expect(']')?;
+        entries.push(PolicyFileEntry {
+            key,
+            value_name,
+            value_type,
+            data,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Bump the scope's half of the gpt.ini version and make sure the scope lists the registry
+/// extension, without which the local GPO's Registry.pol is not processed.
+fn update_gpt_ini(content: &str, scope: PolicyScope) -> String {
+    let (names_key, tool_guid) = match scope {
+        PolicyScope::Machine => (
+            "gPCMachineExtensionNames",
+            "{D02B1F72-3407-48AE-BA88-E8213C6761F1}",
+        ),
+        PolicyScope::User => (
+            "gPCUserExtensionNames",
+            "{D02B1F73-3407-48AE-BA88-E8213C6761F1}",
+        ),
+    };
+
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    if !lines
+        .iter()
+        .any(|l| l.trim().eq_ignore_ascii_case("[General]"))
+    {
+        lines.insert(0, "[General]".to_string());
+    }
+    let general = lines
+        .iter()
+        .position(|l| l.trim().eq_ignore_ascii_case("[General]"))
+        .unwrap_or(0);
+
+    let find = |lines: &[String], name: &str| {
+        lines.iter().position(|l| {
+            l.split_once('=')
+                .is_some_and(|(k, _)| k.trim().eq_ignore_ascii_case(name))
+        })
+    };
+    let value_of = |line: &str| {
+        line.split_once('=')
+            .map_or("", |(_, v)| v.trim())
+            .to_string()
+    };
+
+    // Version: machine changes count in the low word, user changes in the high word
+    let version =
+        find(&lines, "Version").map_or(0, |i| value_of(&lines[i]).parse::<u32>().unwrap_or(0));
+    let bumped = match scope {
+        PolicyScope::Machine => (version & 0xFFFF_0000) | (version.wrapping_add(1) & 0xFFFF),
+        PolicyScope::User => version.wrapping_add(0x1_0000),
+    };
+    match find(&lines, "Version") {
+        Some(i) => lines[i] = format!("Version={}", bumped),
+        None => lines.insert(general + 1, format!("Version={}", bumped)),
+    }
+
+    // Extension list: sorted `[{cse}{tool}]` pairs
+    let existing = find(&lines, names_key).map(|i| value_of(&lines[i]));
+    let has_registry_cse = existing
+        .as_deref()
+        .is_some_and(|v| v.to_ascii_uppercase().contains(REGISTRY_CSE));
+    if !has_registry_cse {
+        let mut pairs: Vec<String> = existing
+            .as_deref()
+            .unwrap_or_default()
+            .split(']')
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .map(|p| format!("{}]", p))
+            .collect();
+        pairs.push(format!("[{}{}]", REGISTRY_CSE, tool_guid));
+        pairs.sort_by_key(|p| p.to_ascii_uppercase());
+        let line = format!("{}={}", names_key, pairs.concat());
+        match find(&lines, names_key) {
+            Some(i) => lines[i] = line,
+            None => lines.insert(general + 1, line),
+        }
+    }
+
+    let mut out = lines.join("\r\n");
+    out.push_str("\r\n");
+    out
+}
+
+fn io_error(operation: &str, path: &std::path::Path, e: std::io::Error) -> Error {
+    Error::CommandExecution(format!(
+        "Failed to {} '{}': {}",
+        operation,
+        path.display(),
+        e
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(value_name: &str, value_type: u32, data: Vec<u8>) -> PolicyFileEntry {
+        PolicyFileEntry {
+            key: "Software\\Policies\\Microsoft\\Windows\\DataCollection".to_string(),
+            value_name: value_name.to_string(),
+            value_type,
+            data,
+        }
+    }
+
+    #[test]
+    fn records_survive_a_serialize_parse_round_trip() {
+        let entries = vec![
+            entry("AllowTelemetry", REG_DWORD, 0u32.to_le_bytes().to_vec()),
+            entry("**del.DoNotShowFeedbackNotifications", REG_SZ, utf16_z(" ")),
+        ];
+        let bytes = serialize_pol(&entries);
+        assert_eq!(&bytes[..4], b"PReg");
+        assert_eq!(parse_pol(&bytes).unwrap(), entries);
+        assert!(parse_pol(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn a_truncated_or_foreign_file_is_rejected() {
+        let bytes = serialize_pol(&[entry("AllowTelemetry", REG_DWORD, vec![0, 0, 0, 0])]);
+        assert!(parse_pol(&bytes[..bytes.len() - 3]).is_err());
+        assert!(parse_pol(b"REGEDIT4").is_err());
+    }
+
+    #[test]
+    fn deletion_markers_count_as_records_for_their_value() {
+        let marker = entry("**del.AllowTelemetry", REG_SZ, utf16_z(" "));
+        let key = "SOFTWARE\\Policies\\Microsoft\\Windows\\DataCollection";
+        assert!(is_record_for(&marker, key, "allowtelemetry"));
+        assert!(!is_record_for(&marker, key, "AllowTelemetry2"));
+    }
+
+    #[test]
+    fn values_encode_as_the_registry_stores_them() {
+        assert_eq!(
+            encode_value(&RegistryValue::Dword(1)),
+            (REG_DWORD, vec![1, 0, 0, 0])
+        );
+        assert_eq!(
+            encode_value(&RegistryValue::MultiString(vec!["a".to_string()])),
+            (REG_MULTI_SZ, vec![b'a', 0, 0, 0, 0, 0])
+        );
+    }
+
+    #[test]
+    fn gpt_ini_gets_a_version_bump_and_the_registry_extension() {
+        let ini = "[General]\r\nVersion=65538\r\ngPCMachineExtensionNames=[{0000-B}{0000-C}]\r\n";
+        let machine = update_gpt_ini(ini, PolicyScope::Machine);
+        assert!(machine.contains("Version=65539\r\n"));
+        assert!(machine.contains(
+            "gPCMachineExtensionNames=[{0000-B}{0000-C}][{35378EAC-683F-11D2-A89A-00C04FBBCFA2}{D02B1F72-3407-48AE-BA88-E8213C6761F1}]"
+        ));
+
+        let user = update_gpt_ini("", PolicyScope::User);
+        assert!(user.starts_with("[General]\r\n"));
+        assert!(user.contains("Version=65536\r\n"));
+        assert!(user.contains("gPCUserExtensionNames=[{35378EAC-"));
+
+        // Already listed: only the version changes
+        let again = update_gpt_ini(&machine, PolicyScope::Machine);
+        assert!(again.contains("Version=65540\r\n"));
+        assert_eq!(again.matches(REGISTRY_CSE).count(), 1);
+    }
+}
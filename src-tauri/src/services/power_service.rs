@@ -0,0 +1,370 @@
+//! Windows power plan operations (typed, via the Power API in `powrprof.dll`).
+//!
+//! Schemes, subgroups and settings are addressed by GUID and values are read as numeric indices,
+//! so every read is locale-free — unlike `powercfg /query`, whose labels are localized. Scheme
+//! aliases mirror the ones `powercfg` accepts, so authors can write `SCHEME_MIN` instead of a GUID.
+
+use crate::error::Error;
+use crate::models::tweak_validation::guid_digits;
+use crate::models::{PowerPlanAction, PowerPlanChange};
+use std::ptr;
+
+use windows_sys::core::GUID;
+use windows_sys::Win32::Foundation::LocalFree;
+use windows_sys::Win32::System::Power::{
+    PowerGetActiveScheme, PowerReadACValueIndex, PowerReadDCValueIndex, PowerReadFriendlyName,
+    PowerSetActiveScheme, PowerWriteACValueIndex, PowerWriteDCValueIndex,
+};
+
+const ERROR_SUCCESS: u32 = 0;
+const ERROR_FILE_NOT_FOUND: u32 = 2;
+
+/// Alias for whichever scheme is active when an option starts applying.
+pub const SCHEME_CURRENT: &str = "SCHEME_CURRENT";
+
+/// The built-in scheme aliases `powercfg` understands (SCHEME_CURRENT is resolved separately).
+const SCHEME_ALIASES: &[(&str, &str)] = &[
+    // High performance
+    ("SCHEME_MIN", "8c5e7fda-e8bf-4a96-9a85-a6e23a8c635c"),
+    // Power saver
+    ("SCHEME_MAX", "a1841308-3541-4fab-bc81-f71556f20b4a"),
+    ("SCHEME_BALANCED", "381b4222-f694-41f0-9685-ff5bb260df2e"),
+];
+
+/// The AC and DC value indices of one power setting within a scheme.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PowerSettingValues {
+    pub ac: u32,
+    pub dc: u32,
+}
+
+/// Parse a GUID string (`xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx`, braces optional).
+pub fn parse_guid(value: &str) -> Result<GUID, Error> {
+    guid_digits(value)
+        .and_then(|digits| u128::from_str_radix(&digits, 16).ok())
+        .map(GUID::from_u128)
+        .ok_or_else(|| Error::ValidationError(format!("Invalid GUID: '{}'", value)))
+}
+
+/// Format a GUID in the canonical lowercase form `powercfg` prints.
+pub fn format_guid(guid: &GUID) -> String {
+    let d = guid.data4;
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:02x}{:02x}-{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}",
+        guid.data1, guid.data2, guid.data3, d[0], d[1], d[2], d[3], d[4], d[5], d[6], d[7]
+    )
+}
+
+/// Normalize a GUID string to its canonical form (so comparisons ignore case and braces).
+pub fn normalize_guid(value: &str) -> Result<String, Error> {
+    parse_guid(value).map(|guid| format_guid(&guid))
+}
+
+/// Resolve a scheme reference to a canonical GUID. `None` and `SCHEME_CURRENT` resolve to
+/// `current_scheme` (the scheme active when the option started), so every change in one option
+/// targets the same scheme even if an earlier change switched the active scheme.
+pub fn resolve_scheme(scheme: Option<&str>, current_scheme: &str) -> Result<String, Error> {
+    let scheme = match scheme {
+        None => return Ok(current_scheme.to_string()),
+        Some(s) => s.trim(),
+    };
+
+    if scheme.eq_ignore_ascii_case(SCHEME_CURRENT) {
+        return Ok(current_scheme.to_string());
+    }
+
+    if let Some((_, guid)) = SCHEME_ALIASES
+        .iter()
+        .find(|(alias, _)| alias.eq_ignore_ascii_case(scheme))
+    {
+        return Ok(guid.to_string());
+    }
+
+    normalize_guid(scheme)
+}
+
+/// Read the GUID of the currently active power scheme.
+pub fn get_active_scheme() -> Result<String, Error> {
+    let mut active: *mut GUID = ptr::null_mut();
+
+    // SAFETY: on success the API allocates `active` with LocalAlloc; it is copied and freed below.
+    let status = unsafe { PowerGetActiveScheme(ptr::null_mut(), &mut active) };
+    if status != ERROR_SUCCESS || active.is_null() {
+        return Err(Error::WindowsApi(format!(
+            "PowerGetActiveScheme failed: {}",
+            status
+        )));
+    }
+
+    // SAFETY: `active` is non-null and points at a GUID owned by us until LocalFree.
+    let guid = unsafe { *active };
+    unsafe { LocalFree(active.cast()) };
+
+    Ok(format_guid(&guid))
+}
+
+/// Read the display name of a scheme (e.g. "Balanced"), as Windows shows it in this locale.
+pub fn read_scheme_name(scheme: &str) -> Result<String, Error> {
+    let guid = parse_guid(scheme)?;
+    let mut size = 0u32;
+
+    // SAFETY: a null buffer asks for the size of the name in bytes; `guid` outlives the call.
+    let status = unsafe {
+        PowerReadFriendlyName(
+            ptr::null_mut(),
+            &guid,
+            ptr::null(),
+            ptr::null(),
+            ptr::null_mut(),
+            &mut size,
+        )
+    };
+    if status != ERROR_SUCCESS {
+        return Err(Error::WindowsApi(format!(
+            "PowerReadFriendlyName '{}' failed: {}",
+            scheme, status
+        )));
+    }
+
+    let mut buffer = vec![0u16; (size as usize).div_ceil(2)];
+    // SAFETY: `buffer` holds at least `size` bytes.
+    let status = unsafe {
+        PowerReadFriendlyName(
+            ptr::null_mut(),
+            &guid,
+            ptr::null(),
+            ptr::null(),
+            buffer.as_mut_ptr().cast(),
+            &mut size,
+        )
+    };
+    if status != ERROR_SUCCESS {
+        return Err(Error::WindowsApi(format!(
+            "PowerReadFriendlyName '{}' failed: {}",
+            scheme, status
+        )));
+    }
+
+    let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+    Ok(String::from_utf16_lossy(&buffer[..len]))
+}
+
+/// Make `scheme` (a GUID or alias) the active power scheme.
+pub fn set_active_scheme(scheme: &str) -> Result<(), Error> {
+    let current = get_active_scheme()?;
+    let resolved = resolve_scheme(Some(scheme), &current)?;
+    let guid = parse_guid(&resolved)?;
+
+    // SAFETY: `guid` outlives the call; a null root key targets the system power key.
+    let status = unsafe { PowerSetActiveScheme(ptr::null_mut(), &guid) };
+    if status != ERROR_SUCCESS {
+        return Err(Error::WindowsApi(format!(
+            "PowerSetActiveScheme '{}' failed: {}",
+            resolved, status
+        )));
+    }
+
+    log::info!("Set active power scheme: {}", resolved);
+    Ok(())
+}
+
+/// Read the AC and DC value indices of a setting. A scheme/subgroup/setting that does not exist is
+/// `Error::NotFound`; any other failure is `Error::WindowsApi`.
+pub fn read_setting(
+    scheme: &str,
+    subgroup: &str,
+    setting: &str,
+) -> Result<PowerSettingValues, Error> {
+    let scheme_guid = parse_guid(scheme)?;
+    let subgroup_guid = parse_guid(subgroup)?;
+    let setting_guid = parse_guid(setting)?;
+    let mut ac = 0u32;
+    let mut dc = 0u32;
+
+    // SAFETY: all GUID pointers reference locals that outlive the calls.
+    let ac_status = unsafe {
+        PowerReadACValueIndex(
+            ptr::null_mut(),
+            &scheme_guid,
+            &subgroup_guid,
+            &setting_guid,
+            &mut ac,
+        )
+    };
+    check_setting_status(ac_status, "PowerReadACValueIndex", scheme, setting)?;
+
+    let dc_status = unsafe {
+        PowerReadDCValueIndex(
+            ptr::null_mut(),
+            &scheme_guid,
+            &subgroup_guid,
+            &setting_guid,
+            &mut dc,
+        )
+    };
+    check_setting_status(dc_status, "PowerReadDCValueIndex", scheme, setting)?;
+
+    Ok(PowerSettingValues { ac, dc })
+}
+
+/// Write the AC and/or DC value index of a setting. When the scheme is the active one it is
+/// re-applied afterwards, which is how Windows commits a change to the running configuration.
+pub fn write_setting(
+    scheme: &str,
+    subgroup: &str,
+    setting: &str,
+    ac_value: Option<u32>,
+    dc_value: Option<u32>,
+) -> Result<(), Error> {
+    let scheme_guid = parse_guid(scheme)?;
+    let subgroup_guid = parse_guid(subgroup)?;
+    let setting_guid = parse_guid(setting)?;
+
+    if let Some(ac) = ac_value {
+        // SAFETY: all GUID pointers reference locals that outlive the call.
+        let status = unsafe {
+            PowerWriteACValueIndex(
+                ptr::null_mut(),
+                &scheme_guid,
+                &subgroup_guid,
+                &setting_guid,
+                ac,
+            )
+        };
+        check_setting_status(status, "PowerWriteACValueIndex", scheme, setting)?;
+    }
+
+    if let Some(dc) = dc_value {
+        // SAFETY: all GUID pointers reference locals that outlive the call.
+        let status = unsafe {
+            PowerWriteDCValueIndex(
+                ptr::null_mut(),
+                &scheme_guid,
+                &subgroup_guid,
+                &setting_guid,
+                dc,
+            )
+        };
+        check_setting_status(status, "PowerWriteDCValueIndex", scheme, setting)?;
+    }
+
+    if get_active_scheme()? == normalize_guid(scheme)? {
+        // SAFETY: `scheme_guid` outlives the call.
+        let status = unsafe { PowerSetActiveScheme(ptr::null_mut(), &scheme_guid) };
+        if status != ERROR_SUCCESS {
+            return Err(Error::WindowsApi(format!(
+                "PowerSetActiveScheme '{}' failed while committing setting '{}': {}",
+                scheme, setting, status
+            )));
+        }
+    }
+
+    log::info!(
+        "Set power setting {}\\{}\\{} (ac={:?}, dc={:?})",
+        scheme,
+        subgroup,
+        setting,
+        ac_value,
+        dc_value
+    );
+    Ok(())
+}
+
+fn check_setting_status(status: u32, api: &str, scheme: &str, setting: &str) -> Result<(), Error> {
+    match status {
+        ERROR_SUCCESS => Ok(()),
+        ERROR_FILE_NOT_FOUND => Err(Error::NotFound(format!(
+            "Power setting '{}' in scheme '{}'",
+            setting, scheme
+        ))),
+        code => Err(Error::WindowsApi(format!(
+            "{} failed for setting '{}' in scheme '{}': {}",
+            api, setting, scheme, code
+        ))),
+    }
+}
+
+/// Apply a single power plan change. `current_scheme` is the scheme that was active when the
+/// option started applying; it is what `SCHEME_CURRENT` (or an omitted scheme) resolves to.
+pub fn apply_power_plan_change(
+    change: &PowerPlanChange,
+    current_scheme: &str,
+) -> Result<(), Error> {
+    let scheme = resolve_scheme(change.scheme.as_deref(), current_scheme)?;
+
+    match change.action {
+        PowerPlanAction::SetActiveScheme => set_active_scheme(&scheme),
+        PowerPlanAction::SetSettingValue => {
+            let (subgroup, setting) = setting_target(change)?;
+            write_setting(&scheme, subgroup, setting, change.ac_value, change.dc_value)
+        }
+    }
+}
+
+/// The `(subgroup, setting)` pair of a set_setting_value change.
+pub fn setting_target(change: &PowerPlanChange) -> Result<(&str, &str), Error> {
+    match (change.subgroup.as_deref(), change.setting.as_deref()) {
+        (Some(subgroup), Some(setting)) => Ok((subgroup, setting)),
+        _ => Err(Error::ValidationError(
+            "set_setting_value requires both 'subgroup' and 'setting'".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_guid_round_trips_to_its_canonical_lowercase_form() {
+        let guid = parse_guid("{381B4222-F694-41F0-9685-FF5BB260DF2E}").unwrap();
+        assert_eq!(format_guid(&guid), "381b4222-f694-41f0-9685-ff5bb260df2e");
+    }
+
+    #[test]
+    fn malformed_guids_are_rejected() {
+        assert!(parse_guid("").is_err());
+        assert!(parse_guid("381b4222-f694-41f0-9685").is_err());
+        assert!(parse_guid("381b4222-f694-41f0-9685-ff5bb260df2z").is_err());
+        assert!(parse_guid("381b4222f694-41f0-9685-ff5bb260df2e-").is_err());
+    }
+
+    #[test]
+    fn aliases_resolve_to_the_builtin_scheme_guids() {
+        let current = "00000000-0000-0000-0000-000000000001";
+        assert_eq!(
+            resolve_scheme(Some("scheme_min"), current).unwrap(),
+            "8c5e7fda-e8bf-4a96-9a85-a6e23a8c635c"
+        );
+        assert_eq!(
+            resolve_scheme(Some("SCHEME_BALANCED"), current).unwrap(),
+            "381b4222-f694-41f0-9685-ff5bb260df2e"
+        );
+    }
+
+    #[test]
+    fn an_omitted_or_current_scheme_resolves_to_the_scheme_active_at_start() {
+        // Every change in one option must target the same scheme, even after an earlier change
+        // switched the active one — so SCHEME_CURRENT is pinned, not re-read.
+        let current = "a1841308-3541-4fab-bc81-f71556f20b4a";
+        assert_eq!(resolve_scheme(None, current).unwrap(), current);
+        assert_eq!(
+            resolve_scheme(Some("SCHEME_CURRENT"), current).unwrap(),
+            current
+        );
+    }
+
+    // Reads the live power configuration, which Server Core and containers may not have, so it's
+    // #[ignore]d like the other live-system tests; run it with `cargo test -- --ignored`.
+    #[test]
+    #[ignore = "reads the live power configuration, which may be absent — run with --ignored"]
+    fn the_active_scheme_is_readable_and_well_formed() {
+        let active = get_active_scheme().expect("the active power scheme should be readable");
+        assert_eq!(
+            normalize_guid(&active).ok().as_deref(),
+            Some(active.as_str())
+        );
+        let name = read_scheme_name(&active).expect("the active scheme should have a name");
+        assert!(!name.is_empty());
+    }
+}
@@ -0,0 +1,61 @@
+//! Helpers shared by the services that drive a PowerShell module (DISM, AppX, NetAdapter, ...).
+//!
+//! Values are only ever passed to scripts as single-quoted literals, which expand nothing.
+//! PowerShell also ends a single-quoted string at the typographic quotes U+2018–U+201B, so those
+//! are doubled along with `'`; a package or adapter name containing one can't break out of the
+//! literal.
+
+use crate::error::Error;
+use crate::services::trusted_installer;
+
+/// Characters PowerShell reads as a single quote
+const SINGLE_QUOTES: [char; 5] = ['\'', '\u{2018}', '\u{2019}', '\u{201A}', '\u{201B}'];
+
+/// Quote a value as a PowerShell single-quoted string literal.
+pub fn ps_quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('\'');
+    for c in value.chars() {
+        if SINGLE_QUOTES.contains(&c) {
+            quoted.push(c);
+        }
+        quoted.push(c);
+    }
+    quoted.push('\'');
+    quoted
+}
+
+/// Run a script with errors made terminating, returning stdout on success.
+pub fn run_script(script: &str) -> Result<String, Error> {
+    let result = trusted_installer::run_powershell(&format!(
+        "$ErrorActionPreference = 'Stop'; $ProgressPreference = 'SilentlyContinue'; {}",
+        script
+    ))?;
+
+    if !result.success {
+        return Err(Error::CommandExecution(format!(
+            "PowerShell failed with exit code {}: {}",
+            result.exit_code,
+            result.stderr.trim()
+        )));
+    }
+
+    Ok(result.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoting_doubles_every_single_quote_character() {
+        assert_eq!(ps_quote("Microsoft.BingNews"), "'Microsoft.BingNews'");
+        assert_eq!(ps_quote("it's"), "'it''s'");
+        assert_eq!(ps_quote("a\u{2019}b"), "'a\u{2019}\u{2019}b'");
+        assert_eq!(
+            ps_quote("\u{2018}\u{201A}\u{201B}"),
+            "'\u{2018}\u{2018}\u{201A}\u{201A}\u{201B}\u{201B}'"
+        );
+        assert_eq!(ps_quote("$env:TEMP \"x\""), "'$env:TEMP \"x\"'");
+    }
+}
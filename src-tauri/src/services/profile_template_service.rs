@@ -0,0 +1,188 @@
+//! Built-in profile templates: one-click baselines for a kind of machine.
+//!
+//! Each template is a rule over tweak tags, not a list of tweak IDs, and is built from the tweaks
+//! this machine offers whenever it's asked for, so it follows the tweak set instead of going stale
+//! as a shipped file would. A template selects a tweak that carries one of its tags and none of
+//! the tags it leaves out, picking the tweak's first option (the tweaked state, by convention).
+//! High and critical risk, deprecated, and custom tweaks are never selected: a baseline applied in
+//! one click should only contain what the app ships and rates safe to try.
+
+use crate::models::{RiskLevel, TweakDefinition};
+use serde::Serialize;
+
+/// A template's rule: which tags it takes tweaks from and which it leaves out
+struct TemplateRule {
+    id: &'static str,
+    name: &'static str,
+    description: &'static str,
+    tags: &'static [&'static str],
+    excluded_tags: &'static [&'static str],
+}
+
+const TEMPLATES: &[TemplateRule] = &[
+    TemplateRule {
+        id: "gaming_desktop",
+        name: "Gaming Desktop",
+        description: "Lower game latency and trade power saving for performance, without ads",
+        tags: &["gaming", "battery", "ads"],
+        excluded_tags: &[],
+    },
+    TemplateRule {
+        id: "developer_laptop",
+        name: "Developer Laptop",
+        description: "Quiet the telemetry, ads, and AI features, and leave power management alone",
+        tags: &["telemetry", "ads", "ai"],
+        excluded_tags: &["battery", "gaming"],
+    },
+    TemplateRule {
+        id: "vm_test",
+        name: "VM / Test",
+        description:
+            "A light machine: no telemetry, ads, or AI, and no animations or sleep to wake from",
+        tags: &["telemetry", "ads", "ai", "battery"],
+        excluded_tags: &["gaming"],
+    },
+    TemplateRule {
+        id: "privacy_focused",
+        name: "Privacy-focused",
+        description: "Limit what Windows records and shares, and turn off telemetry, ads, and AI",
+        tags: &["privacy", "telemetry", "ads", "ai"],
+        excluded_tags: &[],
+    },
+];
+
+/// One tweak a template selects, and the option it selects
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TweakSelection {
+    pub tweak_id: String,
+    pub selected_option_index: usize,
+    pub selected_option_label: String,
+    pub category_id: String,
+}
+
+/// A built-in profile template, generated from the available tweaks
+#[derive(Debug, Clone, Serialize)]
+pub struct BuiltinProfile {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Tags the template takes tweaks from
+    pub tags: &'static [&'static str],
+    /// The selected tweaks, by category and then ID
+    pub selections: Vec<TweakSelection>,
+}
+
+impl TemplateRule {
+    fn selects(&self, tweak: &TweakDefinition) -> bool {
+        matches!(tweak.risk_level, RiskLevel::Low | RiskLevel::Medium)
+            && !tweak.deprecated
+            && !tweak.custom
+            && tweak
+                .tags
+                .iter()
+                .any(|tag| self.tags.contains(&tag.as_str()))
+            && !tweak
+                .tags
+                .iter()
+                .any(|tag| self.excluded_tags.contains(&tag.as_str()))
+    }
+
+    fn build(&self, tweaks: &[&TweakDefinition]) -> BuiltinProfile {
+        let mut selections: Vec<TweakSelection> = tweaks
+            .iter()
+            .filter(|tweak| self.selects(tweak))
+            .filter_map(|tweak| {
+                tweak.options.first().map(|option| TweakSelection {
+                    tweak_id: tweak.id.clone(),
+                    selected_option_index: 0,
+                    selected_option_label: option.label.clone(),
+                    category_id: tweak.category_id.clone(),
+                })
+            })
+            .collect();
+        selections
+            .sort_by(|a, b| (&a.category_id, &a.tweak_id).cmp(&(&b.category_id, &b.tweak_id)));
+        BuiltinProfile {
+            id: self.id,
+            name: self.name,
+            description: self.description,
+            tags: self.tags,
+            selections,
+        }
+    }
+}
+
+/// Every built-in template, built from `tweaks` (the tweaks available on this machine)
+pub fn builtin_profiles(tweaks: &[&TweakDefinition]) -> Vec<BuiltinProfile> {
+    TEMPLATES.iter().map(|rule| rule.build(tweaks)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::tweak_validation::is_tweak_tag;
+
+    fn tweak(id: &str, risk: &str, tags: &[&str]) -> TweakDefinition {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": id,
+            "description": "",
+            "risk_level": risk,
+            "category_id": "test",
+            "tags": tags,
+            "options": [{ "label": "Tweaked" }, { "label": "Default" }],
+        }))
+        .unwrap()
+    }
+
+    fn selected<'a>(profiles: &'a [BuiltinProfile], id: &str) -> Vec<&'a str> {
+        profiles
+            .iter()
+            .find(|profile| profile.id == id)
+            .unwrap()
+            .selections
+            .iter()
+            .map(|selection| selection.tweak_id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn templates_only_use_declared_tags() {
+        for rule in TEMPLATES {
+            assert!(!rule.tags.is_empty(), "{} takes no tags", rule.id);
+            for tag in rule.tags.iter().chain(rule.excluded_tags) {
+                assert!(is_tweak_tag(tag), "{}: unknown tag '{}'", rule.id, tag);
+            }
+        }
+    }
+
+    #[test]
+    fn templates_select_the_first_option_of_safe_tagged_tweaks() {
+        let mut deprecated = tweak("old_ads", "low", &["ads"]);
+        deprecated.deprecated = true;
+        let mut custom = tweak("my_ads", "low", &["ads"]);
+        custom.custom = true;
+        let tweaks = [
+            tweak("game_mode", "low", &["gaming"]),
+            tweak("throttling", "medium", &["battery"]),
+            tweak("telemetry", "low", &["telemetry", "privacy"]),
+            tweak("risky_privacy", "high", &["privacy"]),
+            tweak("untagged", "low", &[]),
+            deprecated,
+            custom,
+        ];
+        let profiles = builtin_profiles(&tweaks.iter().collect::<Vec<_>>());
+
+        assert_eq!(
+            selected(&profiles, "gaming_desktop"),
+            ["game_mode", "throttling"]
+        );
+        assert_eq!(selected(&profiles, "developer_laptop"), ["telemetry"]);
+        assert_eq!(selected(&profiles, "vm_test"), ["telemetry", "throttling"]);
+        assert_eq!(selected(&profiles, "privacy_focused"), ["telemetry"]);
+
+        let selection = &profiles[0].selections[0];
+        assert_eq!(selection.selected_option_index, 0);
+        assert_eq!(selection.selected_option_label, "Tweaked");
+    }
+}
@@ -0,0 +1,416 @@
+//! Folders only Administrators and SYSTEM can write to, for files the elevated app trusts.
+//!
+//! `%ProgramData%` lets every user create folders and files in it, and whoever creates one owns
+//! it. The app's folder there holds what the elevated app acts on: the snapshot location setting,
+//! the snapshots, and the tweak YAML of `tweaks.d` and the tweak packs. A standard user who could
+//! write those could have their own changes made as administrator. So these folders are created
+//! with a protected DACL giving SYSTEM and Administrators full control and Users read access, and
+//! an elevated instance puts that DACL and an Administrators owner back on a folder that lacks
+//! them, e.g. one made by a version that didn't set it. Files loaded as tweaks are also checked
+//! for an Administrators or SYSTEM owner, since a file planted before that keeps its owner.
+
+use crate::error::Error;
+use crate::services::system_info_service;
+use std::ffi::c_void;
+use std::io;
+use std::os::windows::ffi::OsStrExt;
+use std::path::Path;
+use std::ptr;
+
+use windows_sys::Win32::Foundation::{
+    LocalFree, ERROR_ALREADY_EXISTS, ERROR_SUCCESS, GENERIC_ALL, GENERIC_READ, GENERIC_WRITE,
+};
+use windows_sys::Win32::Security::Authorization::{
+    ConvertSidToStringSidW, ConvertStringSecurityDescriptorToSecurityDescriptorW,
+    GetNamedSecurityInfoW, SetNamedSecurityInfoW, SDDL_REVISION_1, SE_FILE_OBJECT,
+};
+use windows_sys::Win32::Security::{
+    GetAce, GetSecurityDescriptorControl, GetSecurityDescriptorDacl, GetSecurityDescriptorOwner,
+    IsWellKnownSid, WinBuiltinAdministratorsSid, WinCreatorOwnerSid, WinLocalSystemSid,
+    ACCESS_ALLOWED_ACE, ACE_HEADER, ACL, DACL_SECURITY_INFORMATION, OWNER_SECURITY_INFORMATION,
+    PROTECTED_DACL_SECURITY_INFORMATION, PSECURITY_DESCRIPTOR, PSID, SECURITY_ATTRIBUTES,
+    SE_DACL_PROTECTED,
+};
+use windows_sys::Win32::Storage::FileSystem::{
+    CreateDirectoryW, DELETE, FILE_APPEND_DATA, FILE_DELETE_CHILD, FILE_READ_DATA,
+    FILE_WRITE_ATTRIBUTES, FILE_WRITE_DATA, FILE_WRITE_EA, WRITE_DAC, WRITE_OWNER,
+};
+
+/// DACL of the protected folders: full control for SYSTEM and Administrators, read and list for
+/// Users, inherited by everything in them and not by them from `%ProgramData%`
+pub const ADMIN_ONLY_DACL: &str = "D:P(A;OICI;FA;;;SY)(A;OICI;FA;;;BA)(A;OICI;0x1200a9;;;BU)";
+
+/// DACL of a file only SYSTEM and Administrators may read, such as the snapshot key: the protected
+/// folders let Users read what's in them
+pub const PRIVATE_FILE_DACL: &str = "D:P(A;;FA;;;SY)(A;;FA;;;BA)";
+
+/// Owner an elevated instance gives a protected folder (BUILTIN\Administrators)
+const ADMIN_OWNER: &str = "O:BA";
+
+/// NT SERVICE\TrustedInstaller, which owns and may write much of Program Files
+const TRUSTED_INSTALLER_SID: &str =
+    "S-1-5-80-956008885-3418522649-1831038044-1853292631-2271478464";
+
+// ACE types (winnt.h). Denied entries can only take rights away.
+const ACCESS_ALLOWED_ACE_TYPE: u8 = 0;
+const ACCESS_DENIED_ACE_TYPE: u8 = 1;
+
+/// Rights that let a trustee change a folder or what's in it (adding a file or subfolder is
+/// `FILE_WRITE_DATA` / `FILE_APPEND_DATA` on a folder)
+const WRITE_RIGHTS: u32 = FILE_WRITE_DATA
+    | FILE_APPEND_DATA
+    | FILE_WRITE_EA
+    | FILE_WRITE_ATTRIBUTES
+    | FILE_DELETE_CHILD
+    | DELETE
+    | WRITE_DAC
+    | WRITE_OWNER
+    | GENERIC_WRITE
+    | GENERIC_ALL;
+
+/// Rights that let a trustee read a file's contents
+const READ_RIGHTS: u32 = FILE_READ_DATA | GENERIC_READ | GENERIC_ALL;
+
+fn wide(path: &Path) -> Vec<u16> {
+    path.as_os_str()
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect()
+}
+
+/// Memory returned by a Win32 API that must be released with `LocalFree`.
+struct LocalBuffer(*mut c_void);
+
+impl Drop for LocalBuffer {
+    fn drop(&mut self) {
+        if !self.0.is_null() {
+            // SAFETY: the pointer came from an API documented to allocate with LocalAlloc.
+            unsafe { LocalFree(self.0) };
+        }
+    }
+}
+
+/// Owner, DACL, and control flags of a security descriptor, pointing into it
+struct Security {
+    owner: PSID,
+    dacl: *mut ACL,
+    control: u16,
+    _descriptor: LocalBuffer,
+}
+
+impl Security {
+    fn from_descriptor(descriptor: LocalBuffer) -> Self {
+        let mut owner: PSID = ptr::null_mut();
+        let mut dacl: *mut ACL = ptr::null_mut();
+        let mut present = 0;
+        let mut defaulted = 0;
+        let mut control = 0u16;
+        let mut revision = 0u32;
+        // SAFETY: `descriptor` is a valid self-relative descriptor; outputs point into it.
+        unsafe {
+            GetSecurityDescriptorOwner(descriptor.0, &mut owner, &mut defaulted);
+            GetSecurityDescriptorDacl(descriptor.0, &mut present, &mut dacl, &mut defaulted);
+            GetSecurityDescriptorControl(descriptor.0, &mut control, &mut revision);
+        }
+        Security {
+            owner,
+            dacl,
+            control,
+            _descriptor: descriptor,
+        }
+    }
+
+    /// Owned by Administrators or SYSTEM, with a protected DACL only they can write under
+    fn is_admin_only(&self) -> bool {
+        self.is_admin_writable() && self.control & SE_DACL_PROTECTED != 0
+    }
+
+    /// Owned by Administrators or SYSTEM, with a DACL (inherited or not) only they can write under
+    fn is_admin_writable(&self) -> bool {
+        is_admin_sid(self.owner) && only_admins_write(self.dacl)
+    }
+
+    /// Owned by Administrators or SYSTEM, with a protected DACL only they can read or write under
+    fn is_private(&self) -> bool {
+        is_admin_sid(self.owner)
+            && self.control & SE_DACL_PROTECTED != 0
+            && only_admins_granted(self.dacl, WRITE_RIGHTS | READ_RIGHTS)
+    }
+}
+
+/// A security descriptor parsed from SDDL
+fn descriptor(sddl: &str) -> Result<LocalBuffer, Error> {
+    let sddl_w: Vec<u16> = sddl.encode_utf16().chain(std::iter::once(0)).collect();
+    let mut descriptor: PSECURITY_DESCRIPTOR = ptr::null_mut();
+    // SAFETY: `sddl_w` is NUL-terminated; the descriptor is freed by LocalBuffer.
+    if unsafe {
+        ConvertStringSecurityDescriptorToSecurityDescriptorW(
+            sddl_w.as_ptr(),
+            SDDL_REVISION_1,
+            &mut descriptor,
+            ptr::null_mut(),
+        )
+    } == 0
+    {
+        return Err(Error::WindowsApi(format!(
+            "Invalid security descriptor '{}': {}",
+            sddl,
+            io::Error::last_os_error()
+        )));
+    }
+    Ok(LocalBuffer(descriptor))
+}
+
+fn read_security(path: &Path) -> Result<Security, Error> {
+    let name = wide(path);
+    let mut owner: PSID = ptr::null_mut();
+    let mut dacl: *mut ACL = ptr::null_mut();
+    let mut descriptor: PSECURITY_DESCRIPTOR = ptr::null_mut();
+    // SAFETY: all out-pointers are valid locals; the descriptor is freed by LocalBuffer.
+    let status = unsafe {
+        GetNamedSecurityInfoW(
+            name.as_ptr(),
+            SE_FILE_OBJECT,
+            OWNER_SECURITY_INFORMATION | DACL_SECURITY_INFORMATION,
+            &mut owner,
+            ptr::null_mut(),
+            &mut dacl,
+            ptr::null_mut(),
+            &mut descriptor,
+        )
+    };
+    if status != ERROR_SUCCESS {
+        return Err(Error::WindowsApi(format!(
+            "Failed to read the permissions of {}: {}",
+            path.display(),
+            io::Error::from_raw_os_error(status as i32)
+        )));
+    }
+    Ok(Security::from_descriptor(LocalBuffer(descriptor)))
+}
+
+/// Whether `sid` is Administrators, SYSTEM, or TrustedInstaller
+fn is_admin_sid(sid: PSID) -> bool {
+    // SAFETY: the SID is non-null and points into a live descriptor; IsWellKnownSid only reads it.
+    !sid.is_null()
+        && (unsafe {
+            IsWellKnownSid(sid, WinBuiltinAdministratorsSid) != 0
+                || IsWellKnownSid(sid, WinLocalSystemSid) != 0
+        } || sid_string(sid).is_some_and(|sid| sid == TRUSTED_INSTALLER_SID))
+}
+
+/// `sid` as a string (S-1-5-...)
+fn sid_string(sid: PSID) -> Option<String> {
+    let mut text: *mut u16 = ptr::null_mut();
+    // SAFETY: `sid` points into a live descriptor; the string is freed by LocalBuffer.
+    if unsafe { ConvertSidToStringSidW(sid, &mut text) } == 0 {
+        return None;
+    }
+    let _text = LocalBuffer(text as *mut c_void);
+    // SAFETY: the API returns a NUL-terminated string.
+    let len = (0..).take_while(|&i| unsafe { *text.add(i) } != 0).count();
+    Some(String::from_utf16_lossy(unsafe {
+        std::slice::from_raw_parts(text, len)
+    }))
+}
+
+/// CREATOR OWNER, which stands for whoever creates a file or folder in an inheriting folder:
+/// someone who could already write there
+fn is_creator_owner(sid: PSID) -> bool {
+    // SAFETY: as in `is_admin_sid`.
+    !sid.is_null() && unsafe { IsWellKnownSid(sid, WinCreatorOwnerSid) != 0 }
+}
+
+/// Whether only Administrators and SYSTEM can write under `dacl`
+fn only_admins_write(dacl: *const ACL) -> bool {
+    only_admins_granted(dacl, WRITE_RIGHTS)
+}
+
+/// Whether `dacl` grants any of `rights` to Administrators and SYSTEM only. A null DACL lets
+/// everyone in, and any other kind of allow entry (object, callback) is taken as letting someone
+/// else in.
+fn only_admins_granted(dacl: *const ACL, rights: u32) -> bool {
+    if dacl.is_null() {
+        return false;
+    }
+    // SAFETY: `dacl` points into a live descriptor; GetAce returns entries inside it.
+    unsafe {
+        for index in 0..(*dacl).AceCount as u32 {
+            let mut ace: *mut c_void = ptr::null_mut();
+            if GetAce(dacl, index, &mut ace) == 0 {
+                return false;
+            }
+            let header = &*(ace as *const ACE_HEADER);
+            match header.AceType {
+                ACCESS_DENIED_ACE_TYPE => continue,
+                ACCESS_ALLOWED_ACE_TYPE => {}
+                _ => return false,
+            }
+            let entry = &*(ace as *const ACCESS_ALLOWED_ACE);
+            let sid = &entry.SidStart as *const u32 as PSID;
+            if entry.Mask & rights != 0 && !is_admin_sid(sid) && !is_creator_owner(sid) {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// Create `dir` with [`ADMIN_ONLY_DACL`] if it doesn't exist (its parent must), and when running
+/// as administrator, give an existing one that DACL and an Administrators owner if it lacks them
+pub fn ensure(dir: &Path) -> Result<(), Error> {
+    if !dir.is_dir() {
+        create(dir)?;
+    }
+    if system_info_service::is_running_as_admin() && !read_security(dir)?.is_admin_only() {
+        restrict(dir)?;
+        log::info!("Restricted {} to Administrators and SYSTEM", dir.display());
+    }
+    Ok(())
+}
+
+fn create(dir: &Path) -> Result<(), Error> {
+    // No owner in it: a standard user can't make Administrators the owner, and an administrator's
+    // new folders are owned by Administrators anyway
+    let descriptor = descriptor(ADMIN_ONLY_DACL)?;
+    let attributes = SECURITY_ATTRIBUTES {
+        nLength: std::mem::size_of::<SECURITY_ATTRIBUTES>() as u32,
+        lpSecurityDescriptor: descriptor.0,
+        bInheritHandle: 0,
+    };
+    let name = wide(dir);
+    // SAFETY: `name` is NUL-terminated and `attributes` points at a live descriptor.
+    if unsafe { CreateDirectoryW(name.as_ptr(), &attributes) } == 0 {
+        let e = io::Error::last_os_error();
+        if e.raw_os_error() != Some(ERROR_ALREADY_EXISTS as i32) {
+            return Err(Error::WindowsApi(format!(
+                "Failed to create {}: {}",
+                dir.display(),
+                e
+            )));
+        }
+    }
+    log::debug!("Created {} for Administrators and SYSTEM", dir.display());
+    Ok(())
+}
+
+/// Replace a folder's owner and DACL. Everything in it inherits the new entries, and loses the
+/// ones it inherited before.
+fn restrict(dir: &Path) -> Result<(), Error> {
+    set_security(dir, ADMIN_ONLY_DACL)
+}
+
+/// Give a file or folder an Administrators owner and the protected DACL `dacl` (SDDL)
+fn set_security(path: &Path, dacl: &str) -> Result<(), Error> {
+    let security = Security::from_descriptor(descriptor(&format!("{}{}", ADMIN_OWNER, dacl))?);
+    let name = wide(path);
+    // SAFETY: `name` is NUL-terminated; owner and DACL point into the live descriptor.
+    let status = unsafe {
+        SetNamedSecurityInfoW(
+            name.as_ptr(),
+            SE_FILE_OBJECT,
+            OWNER_SECURITY_INFORMATION
+                | DACL_SECURITY_INFORMATION
+                | PROTECTED_DACL_SECURITY_INFORMATION,
+            security.owner,
+            ptr::null_mut(),
+            security.dacl,
+            ptr::null(),
+        )
+    };
+    if status != ERROR_SUCCESS {
+        return Err(Error::WindowsApi(format!(
+            "Failed to restrict {} to Administrators and SYSTEM: {}",
+            path.display(),
+            io::Error::from_raw_os_error(status as i32)
+        )));
+    }
+    Ok(())
+}
+
+/// Give a file [`PRIVATE_FILE_DACL`] and an Administrators owner, unless it has them already.
+/// Needs administrator rights.
+pub fn make_private(path: &Path) -> Result<(), Error> {
+    if !read_security(path)?.is_private() {
+        set_security(path, PRIVATE_FILE_DACL)?;
+        log::info!(
+            "Made {} readable by Administrators and SYSTEM only",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Whether a file or folder is owned by Administrators or SYSTEM
+pub fn owned_by_admins(path: &Path) -> Result<bool, Error> {
+    Ok(is_admin_sid(read_security(path)?.owner))
+}
+
+/// Whether only Administrators and SYSTEM can change a file or folder: it's owned by them and its
+/// DACL lets no one else write. Unlike the protected folders, its DACL may be inherited, as under
+/// Program Files.
+pub fn writable_by_admins_only(path: &Path) -> Result<bool, Error> {
+    Ok(read_security(path)?.is_admin_writable())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(sddl: &str) -> Security {
+        Security::from_descriptor(descriptor(sddl).unwrap())
+    }
+
+    #[test]
+    fn only_administrators_and_system_can_write_under_the_dacl() {
+        assert!(parsed(&format!("{}{}", ADMIN_OWNER, ADMIN_ONLY_DACL)).is_admin_only());
+        assert!(parsed(&format!("O:SY{}", ADMIN_ONLY_DACL)).is_admin_only());
+
+        // Users owning it, Users allowed to add files, or a DACL that still inherits
+        assert!(!parsed(&format!("O:BU{}", ADMIN_ONLY_DACL)).is_admin_only());
+        assert!(!parsed("O:BAD:P(A;OICI;FA;;;BA)(A;CI;0x6;;;BU)").is_admin_only());
+        assert!(!parsed("O:BAD:(A;OICI;FA;;;SY)(A;OICI;FA;;;BA)").is_admin_only());
+        // A denied entry takes nothing away from the check
+        assert!(parsed("O:BAD:P(D;OICI;FA;;;BG)(A;OICI;FA;;;BA)").is_admin_only());
+    }
+
+    #[test]
+    fn a_private_file_is_readable_by_administrators_and_system_only() {
+        assert!(parsed(&format!("{}{}", ADMIN_OWNER, PRIVATE_FILE_DACL)).is_private());
+        // What a file in a protected folder inherits: Users may read it
+        let inherited = "O:BAD:PAI(A;ID;FA;;;SY)(A;ID;FA;;;BA)(A;ID;0x1200a9;;;BU)";
+        assert!(parsed(inherited).is_admin_writable());
+        assert!(!parsed(inherited).is_private());
+    }
+
+    #[test]
+    fn a_program_files_dacl_is_admin_writable_but_a_users_one_is_not() {
+        // The entries a folder in Program Files inherits
+        let program_files = format!(
+            "O:{ti}D:AI(A;ID;FA;;;{ti})(A;OICIID;FA;;;SY)(A;OICIID;FA;;;BA)(A;OICIID;0x1200a9;;;BU)(A;OICIIOID;GA;;;CO)(A;OICIID;0x1200a9;;;AC)",
+            ti = TRUSTED_INSTALLER_SID
+        );
+        let security = parsed(&program_files);
+        assert!(security.is_admin_writable());
+        assert!(!security.is_admin_only());
+
+        // A portable copy in the user's Downloads
+        let user_folder = "O:BUD:AI(A;OICIID;FA;;;SY)(A;OICIID;FA;;;BA)(A;OICIID;FA;;;BU)";
+        assert!(!parsed(user_folder).is_admin_writable());
+    }
+
+    // Creates a folder in %TEMP%, which a standard user can do too; it's owned by whoever runs it,
+    // so only the DACL is checked
+    #[test]
+    fn a_created_folder_gets_the_admin_only_dacl() {
+        let dir = std::env::temp_dir().join(format!("magicx-protected-dir-{}", std::process::id()));
+        let _ = std::fs::remove_dir(&dir);
+        ensure(&dir).unwrap();
+        let security = read_security(&dir).unwrap();
+        let protected = security.control & SE_DACL_PROTECTED != 0;
+        let admin_only = only_admins_write(security.dacl);
+        std::fs::remove_dir(&dir).unwrap();
+        assert!(protected);
+        assert!(admin_only);
+    }
+}
@@ -0,0 +1,652 @@
+//! Registry key ownership and permission service for tweak `registry_acl_changes`.
+//!
+//! Uses the Win32 security API (`GetNamedSecurityInfoW` / `SetNamedSecurityInfoW`) in-process.
+//! Snapshots hold the key's owner and DACL as SDDL, so revert puts back the exact original
+//! descriptor, including a TrustedInstaller owner. Requires administrator privileges: ownership
+//! changes need SeTakeOwnershipPrivilege, and restoring another account as owner needs
+//! SeRestorePrivilege.
+
+use crate::error::Error;
+use crate::models::{RegistryAccess, RegistryAclAction, RegistryAclChange, RegistryHive};
+use crate::services::elevation::enable_privilege;
+use crate::services::user_profile_service;
+use std::ffi::{c_void, OsStr};
+use std::os::windows::ffi::OsStrExt;
+use std::ptr;
+
+use windows_sys::Win32::Foundation::{
+    CloseHandle, LocalFree, ERROR_FILE_NOT_FOUND, ERROR_SUCCESS, GENERIC_ALL, GENERIC_READ, HANDLE,
+};
+use windows_sys::Win32::Security::Authorization::{
+    ConvertSecurityDescriptorToStringSecurityDescriptorW, ConvertSidToStringSidW,
+    ConvertStringSecurityDescriptorToSecurityDescriptorW, ConvertStringSidToSidW,
+    GetNamedSecurityInfoW, SetEntriesInAclW, SetNamedSecurityInfoW, EXPLICIT_ACCESS_W,
+    NO_MULTIPLE_TRUSTEE, REVOKE_ACCESS, SDDL_REVISION_1, SET_ACCESS, SE_REGISTRY_KEY,
+    TRUSTEE_IS_SID, TRUSTEE_IS_UNKNOWN, TRUSTEE_W,
+};
+use windows_sys::Win32::Security::{
+    EqualSid, GetAce, GetLengthSid, GetSecurityDescriptorControl, GetSecurityDescriptorDacl,
+    GetSecurityDescriptorOwner, GetTokenInformation, LookupAccountNameW, TokenUser,
+    ACCESS_ALLOWED_ACE, ACE_HEADER, ACL, DACL_SECURITY_INFORMATION, INHERITED_ACE, NO_INHERITANCE,
+    OWNER_SECURITY_INFORMATION, PROTECTED_DACL_SECURITY_INFORMATION, PSECURITY_DESCRIPTOR, PSID,
+    SE_DACL_PROTECTED, TOKEN_QUERY, TOKEN_USER, UNPROTECTED_DACL_SECURITY_INFORMATION,
+};
+use windows_sys::Win32::System::Registry::{KEY_ALL_ACCESS, KEY_READ};
+use windows_sys::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
+
+/// Trustee used when a take_ownership or grant change omits `trustee` (BUILTIN\Administrators)
+pub const DEFAULT_TRUSTEE: &str = "BA";
+
+// ACE types (winnt.h). Allowed and denied entries share the ACCESS_ALLOWED_ACE layout.
+const ACCESS_ALLOWED_ACE_TYPE: u8 = 0;
+const ACCESS_DENIED_ACE_TYPE: u8 = 1;
+
+fn wide(s: &str) -> Vec<u16> {
+    OsStr::new(s)
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect()
+}
+
+/// Memory returned by a Win32 API that must be released with `LocalFree`.
+struct LocalBuffer(*mut c_void);
+
+impl Drop for LocalBuffer {
+    fn drop(&mut self) {
+        if !self.0.is_null() {
+            // SAFETY: the pointer came from an API documented to allocate with LocalAlloc.
+            unsafe { LocalFree(self.0) };
+        }
+    }
+}
+
+/// A SID copied into an owned, suitably aligned buffer.
+struct Sid(Vec<u32>);
+
+impl Sid {
+    fn as_psid(&self) -> PSID {
+        self.0.as_ptr() as PSID
+    }
+}
+
+/// Owner and DACL of a key, pointing into the descriptor they were read from.
+struct SecurityInfo {
+    owner: PSID,
+    dacl: *mut ACL,
+    descriptor: LocalBuffer,
+}
+
+/// What a key's security descriptor says about one trustee.
+pub struct TrusteeState {
+    /// Whether the trustee owns the key
+    pub is_owner: bool,
+    /// Access granted by the trustee's explicit (non-inherited) allow entries, generic bits mapped
+    pub explicit_allow_mask: u32,
+    /// Whether the trustee has any explicit allow or deny entry
+    pub has_explicit_entries: bool,
+}
+
+/// The trustee a take_ownership or grant change targets (Administrators when omitted).
+pub fn resolve_trustee(trustee: Option<&str>) -> &str {
+    trustee.unwrap_or(DEFAULT_TRUSTEE)
+}
+
+/// Registry access mask for an access level.
+pub fn access_mask(access: RegistryAccess) -> u32 {
+    match access {
+        RegistryAccess::FullControl => KEY_ALL_ACCESS,
+        RegistryAccess::Read => KEY_READ,
+    }
+}
+
+/// Replace generic rights in an ACE mask with the registry rights they stand for.
+fn map_generic_mask(mask: u32) -> u32 {
+    let mut mapped = mask & !(GENERIC_ALL | GENERIC_READ);
+    if mask & GENERIC_ALL != 0 {
+        mapped |= KEY_ALL_ACCESS;
+    }
+    if mask & GENERIC_READ != 0 {
+        mapped |= KEY_READ;
+    }
+    mapped
+}
+
+/// Security API object name of a key, e.g. `MACHINE\SOFTWARE\Foo`.
+fn object_name(hive: RegistryHive, key: &str) -> Result<String, Error> {
+    if hive == RegistryHive::Hkcu {
+        if let Some(key) = user_profile_service::route_hkcu_key(key) {
+            return Ok(format!("USERS\\{}", key));
+        }
+    }
+    let root = match hive {
+        RegistryHive::Hkcu => "CURRENT_USER",
+        RegistryHive::Hklm => "MACHINE",
+        RegistryHive::Hkcr => "CLASSES_ROOT",
+        RegistryHive::Hku => "USERS",
+        RegistryHive::AllUsers => {
+            return Err(Error::ValidationError(
+                "Registry ACL changes can't target ALL_USERS; use HKU with a SID".into(),
+            ))
+        }
+    };
+    Ok(format!("{}\\{}", root, key.trim_matches('\\')))
+}
+
+fn enable_security_privileges() -> Result<(), Error> {
+    enable_privilege("SeTakeOwnershipPrivilege")?;
+    enable_privilege("SeRestorePrivilege")
+}
+
+fn api_error(operation: &str, name: &str, code: u32) -> Error {
+    Error::WindowsApi(format!(
+        "{} failed for '{}': {}",
+        operation,
+        name,
+        std::io::Error::from_raw_os_error(code as i32)
+    ))
+}
+
+/// Read a key's owner and DACL. Returns `None` if the key doesn't exist.
+fn read_security(hive: RegistryHive, key: &str) -> Result<Option<SecurityInfo>, Error> {
+    let name = object_name(hive, key)?;
+    let name_w = wide(&name);
+    let mut owner: PSID = ptr::null_mut();
+    let mut dacl: *mut ACL = ptr::null_mut();
+    let mut descriptor: PSECURITY_DESCRIPTOR = ptr::null_mut();
+
+    // SAFETY: all out-pointers are valid locals; the descriptor is freed by LocalBuffer.
+    let status = unsafe {
+        GetNamedSecurityInfoW(
+            name_w.as_ptr(),
+            SE_REGISTRY_KEY,
+            OWNER_SECURITY_INFORMATION | DACL_SECURITY_INFORMATION,
+            &mut owner,
+            ptr::null_mut(),
+            &mut dacl,
+            ptr::null_mut(),
+            &mut descriptor,
+        )
+    };
+
+    match status {
+        ERROR_SUCCESS => Ok(Some(SecurityInfo {
+            owner,
+            dacl,
+            descriptor: LocalBuffer(descriptor),
+        })),
+        ERROR_FILE_NOT_FOUND => Ok(None),
+        code => Err(api_error("GetNamedSecurityInfo", &name, code)),
+    }
+}
+
+fn read_existing_security(hive: RegistryHive, key: &str) -> Result<SecurityInfo, Error> {
+    read_security(hive, key)?.ok_or_else(|| {
+        Error::NotFound(format!(
+            "Registry key {}\\{} does not exist",
+            hive.as_str(),
+            key
+        ))
+    })
+}
+
+fn write_security(
+    hive: RegistryHive,
+    key: &str,
+    information: u32,
+    owner: PSID,
+    dacl: *const ACL,
+) -> Result<(), Error> {
+    let name = object_name(hive, key)?;
+    let name_w = wide(&name);
+    // SAFETY: `name_w` is NUL-terminated; owner/dacl are null or point into live descriptors.
+    let status = unsafe {
+        SetNamedSecurityInfoW(
+            name_w.as_ptr(),
+            SE_REGISTRY_KEY,
+            information,
+            owner,
+            ptr::null_mut(),
+            dacl,
+            ptr::null(),
+        )
+    };
+    if status != ERROR_SUCCESS {
+        return Err(api_error("SetNamedSecurityInfo", &name, status));
+    }
+    Ok(())
+}
+
+/// String SID of the account this process runs as (e.g. `S-1-5-21-...-1001`).
+pub fn current_user_sid() -> Result<String, Error> {
+    let mut token: HANDLE = ptr::null_mut();
+    // SAFETY: GetCurrentProcess returns a pseudo-handle that needs no closing; `token` is closed
+    // below once the user has been read.
+    if unsafe { OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) } == 0 {
+        return Err(Error::WindowsApi(format!(
+            "OpenProcessToken failed: {}",
+            std::io::Error::last_os_error()
+        )));
+    }
+
+    let mut len = 0u32;
+    // SAFETY: size query; a null buffer with zero length is allowed.
+    unsafe { GetTokenInformation(token, TokenUser, ptr::null_mut(), 0, &mut len) };
+    let mut buffer = vec![0u64; (len as usize).div_ceil(8)];
+    // SAFETY: `buffer` holds at least `len` bytes and is aligned for TOKEN_USER.
+    let read = unsafe {
+        GetTokenInformation(
+            token,
+            TokenUser,
+            buffer.as_mut_ptr() as *mut c_void,
+            len,
+            &mut len,
+        )
+    };
+    // SAFETY: `token` was opened above and is not used after this.
+    unsafe { CloseHandle(token) };
+    if read == 0 {
+        return Err(Error::WindowsApi(format!(
+            "GetTokenInformation(TokenUser) failed: {}",
+            std::io::Error::last_os_error()
+        )));
+    }
+
+    // SAFETY: on success the buffer starts with a TOKEN_USER whose SID points into the buffer.
+    let sid = unsafe { (*(buffer.as_ptr() as *const TOKEN_USER)).User.Sid };
+    let mut string: *mut u16 = ptr::null_mut();
+    // SAFETY: `sid` is valid while `buffer` lives; the returned string is freed by the guard.
+    if unsafe { ConvertSidToStringSidW(sid, &mut string) } == 0 {
+        return Err(Error::WindowsApi(format!(
+            "ConvertSidToStringSid failed: {}",
+            std::io::Error::last_os_error()
+        )));
+    }
+    let _guard = LocalBuffer(string as *mut c_void);
+    // SAFETY: ConvertSidToStringSidW returns a NUL-terminated string.
+    let chars = unsafe {
+        let len = (0..).take_while(|&i| *string.add(i) != 0).count();
+        std::slice::from_raw_parts(string, len)
+    };
+    Ok(String::from_utf16_lossy(chars))
+}
+
+/// Resolve a trustee (SID string, SDDL alias such as `BA`, or account name) to a SID.
+fn lookup_sid(trustee: &str) -> Result<Sid, Error> {
+    let trustee_w = wide(trustee);
+
+    let mut converted: PSID = ptr::null_mut();
+    // SAFETY: `trustee_w` is NUL-terminated; the returned SID is copied, then freed.
+    if unsafe { ConvertStringSidToSidW(trustee_w.as_ptr(), &mut converted) } != 0 {
+        let _guard = LocalBuffer(converted);
+        // SAFETY: `converted` is a valid SID of GetLengthSid bytes.
+        let len = unsafe { GetLengthSid(converted) } as usize;
+        let mut buffer = vec![0u32; len.div_ceil(4)];
+        // SAFETY: `buffer` holds at least `len` bytes and doesn't overlap the API's allocation.
+        unsafe {
+            ptr::copy_nonoverlapping(converted as *const u8, buffer.as_mut_ptr() as *mut u8, len)
+        };
+        return Ok(Sid(buffer));
+    }
+
+    let mut sid_len = 0u32;
+    let mut domain_len = 0u32;
+    let mut sid_use = 0;
+    // SAFETY: size query; null buffers with zero lengths are allowed.
+    unsafe {
+        LookupAccountNameW(
+            ptr::null(),
+            trustee_w.as_ptr(),
+            ptr::null_mut(),
+            &mut sid_len,
+            ptr::null_mut(),
+            &mut domain_len,
+            &mut sid_use,
+        )
+    };
+    if sid_len == 0 {
+        return Err(Error::ValidationError(format!(
+            "Unknown account '{}'",
+            trustee
+        )));
+    }
+
+    let mut buffer = vec![0u32; (sid_len as usize).div_ceil(4)];
+    let mut domain = vec![0u16; domain_len as usize];
+    // SAFETY: buffers are at least as large as the sizes reported by the query above.
+    if unsafe {
+        LookupAccountNameW(
+            ptr::null(),
+            trustee_w.as_ptr(),
+            buffer.as_mut_ptr() as PSID,
+            &mut sid_len,
+            domain.as_mut_ptr(),
+            &mut domain_len,
+            &mut sid_use,
+        )
+    } == 0
+    {
+        return Err(Error::WindowsApi(format!(
+            "LookupAccountName failed for '{}': {}",
+            trustee,
+            std::io::Error::last_os_error()
+        )));
+    }
+    Ok(Sid(buffer))
+}
+
+/// Read a key's owner and DACL as SDDL. Returns `None` if the key doesn't exist.
+pub fn get_sddl(hive: RegistryHive, key: &str) -> Result<Option<String>, Error> {
+    let Some(info) = read_security(hive, key)? else {
+        return Ok(None);
+    };
+
+    let mut sddl: *mut u16 = ptr::null_mut();
+    // SAFETY: the descriptor is live for the call; the returned string is freed by LocalBuffer.
+    if unsafe {
+        ConvertSecurityDescriptorToStringSecurityDescriptorW(
+            info.descriptor.0,
+            SDDL_REVISION_1,
+            OWNER_SECURITY_INFORMATION | DACL_SECURITY_INFORMATION,
+            &mut sddl,
+            ptr::null_mut(),
+        )
+    } == 0
+    {
+        return Err(Error::WindowsApi(format!(
+            "Failed to convert the security descriptor of {}\\{}: {}",
+            hive.as_str(),
+            key,
+            std::io::Error::last_os_error()
+        )));
+    }
+    let _guard = LocalBuffer(sddl as *mut c_void);
+
+    // SAFETY: the API returns a NUL-terminated wide string.
+    let text = unsafe {
+        let len = (0..).take_while(|&i| *sddl.add(i) != 0).count();
+        String::from_utf16_lossy(std::slice::from_raw_parts(sddl, len))
+    };
+    Ok(Some(text))
+}
+
+/// Write back an owner and DACL captured by [`get_sddl`], including whether the DACL inherits
+/// from the parent key.
+pub fn set_sddl(hive: RegistryHive, key: &str, sddl: &str) -> Result<(), Error> {
+    enable_security_privileges()?;
+
+    let sddl_w = wide(sddl);
+    let mut descriptor: PSECURITY_DESCRIPTOR = ptr::null_mut();
+    // SAFETY: `sddl_w` is NUL-terminated; the descriptor is freed by LocalBuffer.
+    if unsafe {
+        ConvertStringSecurityDescriptorToSecurityDescriptorW(
+            sddl_w.as_ptr(),
+            SDDL_REVISION_1,
+            &mut descriptor,
+            ptr::null_mut(),
+        )
+    } == 0
+    {
+        return Err(Error::ValidationError(format!(
+            "Invalid security descriptor '{}': {}",
+            sddl,
+            std::io::Error::last_os_error()
+        )));
+    }
+    let _guard = LocalBuffer(descriptor);
+
+    let mut owner: PSID = ptr::null_mut();
+    let mut dacl: *mut ACL = ptr::null_mut();
+    let mut present = 0;
+    let mut defaulted = 0;
+    let mut control = 0u16;
+    let mut revision = 0u32;
+    // SAFETY: `descriptor` is a valid self-relative descriptor; outputs point into it.
+    unsafe {
+        GetSecurityDescriptorOwner(descriptor, &mut owner, &mut defaulted);
+        GetSecurityDescriptorDacl(descriptor, &mut present, &mut dacl, &mut defaulted);
+        GetSecurityDescriptorControl(descriptor, &mut control, &mut revision);
+    }
+
+    let protection = if control & SE_DACL_PROTECTED != 0 {
+        PROTECTED_DACL_SECURITY_INFORMATION
+    } else {
+        UNPROTECTED_DACL_SECURITY_INFORMATION
+    };
+    write_security(
+        hive,
+        key,
+        OWNER_SECURITY_INFORMATION | DACL_SECURITY_INFORMATION | protection,
+        owner,
+        dacl,
+    )?;
+    log::info!("Restored security descriptor of {}\\{}", hive.as_str(), key);
+    Ok(())
+}
+
+/// Make `trustee` the owner of a key.
+pub fn take_ownership(hive: RegistryHive, key: &str, trustee: &str) -> Result<(), Error> {
+    enable_security_privileges()?;
+    read_existing_security(hive, key)?;
+    let sid = lookup_sid(trustee)?;
+    write_security(
+        hive,
+        key,
+        OWNER_SECURITY_INFORMATION,
+        sid.as_psid(),
+        ptr::null(),
+    )?;
+    log::info!(
+        "Took ownership of {}\\{} for '{}'",
+        hive.as_str(),
+        key,
+        trustee
+    );
+    Ok(())
+}
+
+/// Merge one explicit entry for `trustee` into a key's DACL. The entry applies to the key itself
+/// only; subkeys keep their own permissions.
+fn update_dacl(
+    hive: RegistryHive,
+    key: &str,
+    trustee: &str,
+    mode: i32,
+    mask: u32,
+) -> Result<(), Error> {
+    let info = read_existing_security(hive, key)?;
+    let sid = lookup_sid(trustee)?;
+
+    let entry = EXPLICIT_ACCESS_W {
+        grfAccessPermissions: mask,
+        grfAccessMode: mode,
+        grfInheritance: NO_INHERITANCE,
+        Trustee: TRUSTEE_W {
+            pMultipleTrustee: ptr::null_mut(),
+            MultipleTrusteeOperation: NO_MULTIPLE_TRUSTEE,
+            TrusteeForm: TRUSTEE_IS_SID,
+            TrusteeType: TRUSTEE_IS_UNKNOWN,
+            ptstrName: sid.as_psid() as *mut u16,
+        },
+    };
+    let mut new_dacl: *mut ACL = ptr::null_mut();
+    // SAFETY: `entry` and the existing DACL are valid for the call; the new ACL is freed below.
+    let status = unsafe { SetEntriesInAclW(1, &entry, info.dacl, &mut new_dacl) };
+    if status != ERROR_SUCCESS {
+        return Err(api_error(
+            "SetEntriesInAcl",
+            &object_name(hive, key)?,
+            status,
+        ));
+    }
+    let _guard = LocalBuffer(new_dacl as *mut c_void);
+
+    write_security(
+        hive,
+        key,
+        DACL_SECURITY_INFORMATION,
+        ptr::null_mut(),
+        new_dacl,
+    )
+}
+
+/// Give `trustee` an explicit allow entry, replacing any explicit entries it already had.
+pub fn grant_access(
+    hive: RegistryHive,
+    key: &str,
+    trustee: &str,
+    access: RegistryAccess,
+) -> Result<(), Error> {
+    update_dacl(hive, key, trustee, SET_ACCESS, access_mask(access))?;
+    log::info!(
+        "Granted {} on {}\\{} to '{}'",
+        access.as_str(),
+        hive.as_str(),
+        key,
+        trustee
+    );
+    Ok(())
+}
+
+/// Remove every explicit entry for `trustee`. Inherited entries are not affected.
+pub fn revoke_access(hive: RegistryHive, key: &str, trustee: &str) -> Result<(), Error> {
+    update_dacl(hive, key, trustee, REVOKE_ACCESS, 0)?;
+    log::info!(
+        "Revoked explicit access on {}\\{} from '{}'",
+        hive.as_str(),
+        key,
+        trustee
+    );
+    Ok(())
+}
+
+/// Summarize what a key's descriptor says about `trustee`. Returns `None` if the key doesn't exist.
+pub fn trustee_state(
+    hive: RegistryHive,
+    key: &str,
+    trustee: &str,
+) -> Result<Option<TrusteeState>, Error> {
+    let Some(info) = read_security(hive, key)? else {
+        return Ok(None);
+    };
+    let sid = lookup_sid(trustee)?;
+
+    // SAFETY: owner, DACL, and ACEs all point into the live descriptor held by `info`.
+    unsafe {
+        let mut state = TrusteeState {
+            is_owner: !info.owner.is_null() && EqualSid(info.owner, sid.as_psid()) != 0,
+            explicit_allow_mask: 0,
+            has_explicit_entries: false,
+        };
+
+        // A null DACL grants everyone full access but holds no explicit entries
+        let count = if info.dacl.is_null() {
+            0
+        } else {
+            (*info.dacl).AceCount
+        };
+        for index in 0..count as u32 {
+            let mut ace: *mut c_void = ptr::null_mut();
+            if GetAce(info.dacl, index, &mut ace) == 0 {
+                continue;
+            }
+            let header = &*(ace as *const ACE_HEADER);
+            if header.AceFlags as u32 & INHERITED_ACE != 0
+                || !matches!(
+                    header.AceType,
+                    ACCESS_ALLOWED_ACE_TYPE | ACCESS_DENIED_ACE_TYPE
+                )
+            {
+                continue;
+            }
+
+            let entry = &*(ace as *const ACCESS_ALLOWED_ACE);
+            let ace_sid = &entry.SidStart as *const u32 as PSID;
+            if EqualSid(ace_sid, sid.as_psid()) == 0 {
+                continue;
+            }
+            state.has_explicit_entries = true;
+            if header.AceType == ACCESS_ALLOWED_ACE_TYPE {
+                state.explicit_allow_mask |= map_generic_mask(entry.Mask);
+            }
+        }
+
+        Ok(Some(state))
+    }
+}
+
+/// Apply a registry ACL change
+pub fn apply_registry_acl_change(change: &RegistryAclChange) -> Result<(), Error> {
+    match change.action {
+        RegistryAclAction::TakeOwnership => take_ownership(
+            change.hive,
+            &change.key,
+            resolve_trustee(change.trustee.as_deref()),
+        ),
+        RegistryAclAction::Grant => grant_access(
+            change.hive,
+            &change.key,
+            resolve_trustee(change.trustee.as_deref()),
+            change.access.unwrap_or_default(),
+        ),
+        RegistryAclAction::Revoke => {
+            // No default here: revoking Administrators by accident could lock the key
+            let trustee = change.trustee.as_deref().ok_or_else(|| {
+                Error::ValidationError(format!(
+                    "Registry ACL change for '{}' requires 'trustee' for revoke action",
+                    change.key
+                ))
+            })?;
+            revoke_access(change.hive, &change.key, trustee)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn object_names_use_security_api_roots() {
+        assert_eq!(
+            object_name(RegistryHive::Hklm, "SOFTWARE\\Policies\\").unwrap(),
+            "MACHINE\\SOFTWARE\\Policies"
+        );
+        assert_eq!(
+            object_name(RegistryHive::Hkcu, "Software").unwrap(),
+            "CURRENT_USER\\Software"
+        );
+        assert_eq!(
+            object_name(RegistryHive::Hku, ".DEFAULT\\Software").unwrap(),
+            "USERS\\.DEFAULT\\Software"
+        );
+        assert!(object_name(RegistryHive::AllUsers, "Software").is_err());
+    }
+
+    #[test]
+    fn generic_rights_map_to_registry_rights() {
+        assert_eq!(map_generic_mask(GENERIC_ALL), KEY_ALL_ACCESS);
+        assert_eq!(map_generic_mask(GENERIC_READ), KEY_READ);
+        assert_eq!(map_generic_mask(KEY_READ), KEY_READ);
+    }
+
+    #[test]
+    fn an_existing_key_has_an_sddl_and_a_missing_key_has_none() {
+        let sddl = get_sddl(RegistryHive::Hkcu, "Software").unwrap().unwrap();
+        assert!(sddl.starts_with("O:"), "unexpected SDDL: {}", sddl);
+        assert!(sddl.contains("D:"), "unexpected SDDL: {}", sddl);
+
+        assert!(get_sddl(
+            RegistryHive::Hkcu,
+            "Software\\MagicXToolboxTest\\DoesNotExist"
+        )
+        .unwrap()
+        .is_none());
+    }
+
+    #[test]
+    fn sid_strings_aliases_and_unknown_accounts_resolve_as_expected() {
+        assert!(lookup_sid("S-1-5-32-544").is_ok());
+        assert!(lookup_sid(DEFAULT_TRUSTEE).is_ok());
+        assert!(lookup_sid("MagicX No Such Account 7f3a").is_err());
+    }
+}
@@ -1,5 +1,6 @@
 use crate::error::Error;
-use crate::models::{RegistryHive, RegistryValueType};
+use crate::models::{RegistryChange, RegistryHive, RegistryValueType, TweakOption};
+use crate::services::{audio_service, user_profile_service, visual_effects_service};
 use std::io;
 use winreg::enums::*;
 use winreg::types::{FromRegValue, ToRegValue};
@@ -12,6 +13,9 @@ fn hive_name(hive: &RegistryHive) -> &'static str {
     match hive {
         RegistryHive::Hkcu => "HKCU",
         RegistryHive::Hklm => "HKLM",
+        RegistryHive::Hkcr => "HKCR",
+        RegistryHive::Hku => "HKU",
+        RegistryHive::AllUsers => "ALL_USERS",
     }
 }
 
@@ -20,9 +24,99 @@ fn get_hive_key(hive: &RegistryHive) -> Result<HKEY, Error> {
     match hive {
         RegistryHive::Hkcu => Ok(HKEY_CURRENT_USER),
         RegistryHive::Hklm => Ok(HKEY_LOCAL_MACHINE),
+        RegistryHive::Hkcr => Ok(HKEY_CLASSES_ROOT),
+        RegistryHive::Hku => Ok(HKEY_USERS),
+        RegistryHive::AllUsers => Err(Error::ValidationError(
+            "ALL_USERS must be expanded into per-user HKU changes before use".into(),
+        )),
     }
 }
 
+/// Whether an HKU subkey is a user hive root: a user account SID (`S-1-5-21-...`), not its
+/// `_Classes` companion. `.DEFAULT` isn't one: despite the name it's LocalSystem's hive, not the
+/// template new accounts are created from.
+fn is_user_hive_root(name: &str) -> bool {
+    name.starts_with("S-1-5-21-") && !name.to_ascii_lowercase().ends_with("_classes")
+}
+
+/// SIDs of every user hive currently loaded under HKU. Hives of users who aren't signed in are not
+/// loaded, so they aren't included.
+pub fn user_hive_roots() -> Result<Vec<String>, Error> {
+    let users = RegKey::predef(HKEY_USERS);
+    let mut roots = Vec::new();
+    for name in users.enum_keys() {
+        let name = name.map_err(|e| {
+            Error::RegistryOperation(format!("Failed to enumerate user hives: {}", e))
+        })?;
+        if is_user_hive_root(&name) {
+            roots.push(name);
+        }
+    }
+    roots.sort();
+    Ok(roots)
+}
+
+/// Expand an `ALL_USERS` change into one HKU change per loaded user hive, and route an HKCU change
+/// to the target user's hive when one is set (see `user_profile_service`); any other change is
+/// returned as is.
+pub fn expand_registry_change(change: &RegistryChange) -> Result<Vec<RegistryChange>, Error> {
+    if change.hive == RegistryHive::Hkcu {
+        return Ok(vec![
+            match user_profile_service::route_hkcu_key(&change.key) {
+                Some(key) => RegistryChange {
+                    hive: RegistryHive::Hku,
+                    key,
+                    ..change.clone()
+                },
+                None => change.clone(),
+            },
+        ]);
+    }
+    if change.hive != RegistryHive::AllUsers {
+        return Ok(vec![change.clone()]);
+    }
+
+    Ok(user_hive_roots()?
+        .into_iter()
+        .map(|root| RegistryChange {
+            hive: RegistryHive::Hku,
+            key: format!("{}\\{}", root, change.key.trim_start_matches('\\')),
+            ..change.clone()
+        })
+        .collect())
+}
+
+/// [`expand_registry_change`] over a list of changes, keeping their order.
+pub fn expand_registry_changes<'a>(
+    changes: impl IntoIterator<Item = &'a RegistryChange>,
+) -> Result<Vec<RegistryChange>, Error> {
+    let mut expanded = Vec::new();
+    for change in changes {
+        expanded.extend(expand_registry_change(change)?);
+    }
+    Ok(expanded)
+}
+
+/// Every concrete registry change an option makes, in order: its own registry changes, those its
+/// MMCSS, context menu, visual effects, and audio changes map to, and the `UserPreferencesMask`
+/// write for its mask effects, with ALL_USERS changes expanded to each user's hive.
+pub fn option_registry_changes(option: &TweakOption) -> Result<Vec<RegistryChange>, Error> {
+    expand_registry_changes(&mapped_registry_changes(option)?)
+}
+
+/// [`option_registry_changes`] before expansion: HKCU and ALL_USERS changes are left as declared,
+/// for output that isn't tied to this machine's user hives.
+pub fn mapped_registry_changes(option: &TweakOption) -> Result<Vec<RegistryChange>, Error> {
+    let mut changes = option.all_registry_changes();
+    for audio in &option.audio_changes {
+        changes.extend(audio_service::to_registry_changes(audio)?);
+    }
+    changes.extend(visual_effects_service::preferences_mask_change(
+        &option.visual_effects_changes,
+    )?);
+    Ok(changes)
+}
+
 /// Classify a subkey-open failure: a *missing key* is `RegistryKeyNotFound`, anything else is
 /// `RegistryAccessDenied`.
 ///
@@ -96,7 +190,25 @@ pub fn read_multi_string(
     key_path: &str,
     value_name: &str,
 ) -> Result<Option<Vec<String>>, Error> {
-    read_typed(hive, key_path, value_name, "MultiString")
+    log::trace!(
+        "Reading MultiString {}\\{}\\{}",
+        hive_name(hive),
+        key_path,
+        value_name
+    );
+    let reg_key = open_read_key(hive, key_path, value_name)?;
+    match reg_key.get_raw_value(value_name) {
+        Ok(v) if v.vtype == REG_MULTI_SZ => Ok(Some(decode_multi_string(&v.bytes))),
+        Ok(v) => Err(Error::RegistryOperation(format!(
+            "Failed to read MultiString from {}: stored as {:?}",
+            value_name, v.vtype
+        ))),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(Error::RegistryOperation(format!(
+            "Failed to read MultiString from {}: {}",
+            value_name, e
+        ))),
+    }
 }
 
 /// Read a QWORD (u64) value from registry
@@ -132,11 +244,11 @@ pub fn read_binary(
 }
 
 /// Check if write access is allowed for the given hive.
-/// HKLM modifications require admin privileges.
+/// Modifications outside HKCU require admin privileges.
 fn require_write_access(hive: &RegistryHive) -> Result<(), Error> {
     use crate::services::system_info_service::is_running_as_admin;
-    if matches!(hive, RegistryHive::Hklm) && !is_running_as_admin() {
-        log::warn!("HKLM modification requires admin privileges");
+    if hive.requires_admin() && !is_running_as_admin() {
+        log::warn!("{} modification requires admin privileges", hive_name(hive));
         return Err(Error::RequiresAdmin);
     }
     Ok(())
@@ -155,7 +267,7 @@ fn open_write_key(hive: &RegistryHive, key_path: &str) -> Result<RegKey, Error>
     Ok(reg_key)
 }
 
-/// Set a value winreg encodes natively via `set_value` (DWORD / QWORD / String / MultiString).
+/// Set a value winreg encodes natively via `set_value` (DWORD / QWORD / String).
 fn set_typed<T: ToRegValue>(
     hive: &RegistryHive,
     key_path: &str,
@@ -208,7 +320,14 @@ pub fn set_multi_string(
     value_name: &str,
     value: &[String],
 ) -> Result<(), Error> {
-    set_typed(hive, key_path, value_name, &value.to_vec(), "MultiString")
+    set_raw(
+        hive,
+        key_path,
+        value_name,
+        REG_MULTI_SZ,
+        encode_multi_string(value),
+        "MultiString",
+    )
 }
 
 /// Set a QWORD (u64) value in registry
@@ -291,6 +410,36 @@ fn encode_utf16_registry_string(value: &str) -> Vec<u8> {
         .collect()
 }
 
+/// Encode REG_MULTI_SZ data: each string NUL-terminated, then a final NUL ending the list.
+///
+/// Done by hand rather than through winreg's `Vec<String>` impl, which writes an empty list as a
+/// single empty string that reads back as `[""]` and so never compares equal to `[]`.
+fn encode_multi_string(items: &[String]) -> Vec<u8> {
+    items
+        .iter()
+        .flat_map(|item| item.encode_utf16().chain(std::iter::once(0)))
+        .chain(std::iter::once(0))
+        .flat_map(u16::to_le_bytes)
+        .collect()
+}
+
+/// Decode REG_MULTI_SZ data: the strings between NULs, up to the trailing NULs that end the list.
+/// An empty string in the middle is kept as an item. Data written by other tools is often missing
+/// the final NUL (or both), so a truncated list still decodes.
+fn decode_multi_string(bytes: &[u8]) -> Vec<String> {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    let Some(last) = units.iter().rposition(|&unit| unit != 0) else {
+        return Vec::new();
+    };
+    units[..=last]
+        .split(|&unit| unit == 0)
+        .map(String::from_utf16_lossy)
+        .collect()
+}
+
 /// Delete a registry value
 pub fn delete_value(hive: &RegistryHive, key_path: &str, value_name: &str) -> Result<(), Error> {
     log::debug!(
@@ -356,6 +505,44 @@ pub fn delete_key(hive: &RegistryHive, key_path: &str) -> Result<(), Error> {
     Ok(())
 }
 
+/// Names of a key's direct subkeys
+pub fn list_subkeys(hive: &RegistryHive, key_path: &str) -> Result<Vec<String>, Error> {
+    let hive_key = get_hive_key(hive)?;
+    let reg_key = RegKey::predef(hive_key)
+        .open_subkey_with_flags(key_path, KEY_READ)
+        .map_err(|e| classify_open_error(&e, key_path))?;
+    reg_key
+        .enum_keys()
+        .map(|name| {
+            name.map_err(|e| {
+                Error::RegistryOperation(format!(
+                    "Failed to enumerate subkeys of {}: {}",
+                    key_path, e
+                ))
+            })
+        })
+        .collect()
+}
+
+/// Names of a key's values ("" for a set default value)
+pub fn list_value_names(hive: &RegistryHive, key_path: &str) -> Result<Vec<String>, Error> {
+    let hive_key = get_hive_key(hive)?;
+    let reg_key = RegKey::predef(hive_key)
+        .open_subkey_with_flags(key_path, KEY_READ)
+        .map_err(|e| classify_open_error(&e, key_path))?;
+    reg_key
+        .enum_values()
+        .map(|value| {
+            value.map(|(name, _)| name).map_err(|e| {
+                Error::RegistryOperation(format!(
+                    "Failed to enumerate values of {}: {}",
+                    key_path, e
+                ))
+            })
+        })
+        .collect()
+}
+
 /// Check if a registry key exists
 pub fn key_exists(hive: &RegistryHive, key_path: &str) -> Result<bool, Error> {
     let hive_key = get_hive_key(hive)?;
@@ -366,6 +553,16 @@ pub fn key_exists(hive: &RegistryHive, key_path: &str) -> Result<bool, Error> {
     }
 }
 
+/// Open a key for change notifications (`RegNotifyChangeKeyValue`), or None if it doesn't exist
+pub fn open_key_for_notify(hive: &RegistryHive, key_path: &str) -> Result<Option<RegKey>, Error> {
+    let hive_key = get_hive_key(hive)?;
+    match RegKey::predef(hive_key).open_subkey_with_flags(key_path, KEY_NOTIFY) {
+        Ok(key) => Ok(Some(key)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(Error::RegistryAccessDenied(e.to_string())),
+    }
+}
+
 /// Check if a registry value exists
 pub fn value_exists(hive: &RegistryHive, key_path: &str, value_name: &str) -> Result<bool, Error> {
     let hive_key = get_hive_key(hive)?;
@@ -448,6 +645,90 @@ mod tests {
         assert!(result.unwrap());
     }
 
+    #[test]
+    fn only_user_sids_are_user_hive_roots() {
+        assert!(!is_user_hive_root(".DEFAULT"));
+        assert!(is_user_hive_root(
+            "S-1-5-21-1004336348-1177238915-682003330-1001"
+        ));
+        assert!(!is_user_hive_root(
+            "S-1-5-21-1004336348-1177238915-682003330-1001_Classes"
+        ));
+        assert!(!is_user_hive_root("S-1-5-18"));
+        assert!(!is_user_hive_root("S-1-5-19"));
+    }
+
+    #[test]
+    fn non_all_users_changes_are_not_expanded() {
+        let change = RegistryChange {
+            hive: RegistryHive::Hkcr,
+            key: "*\\shell\\Example".to_string(),
+            value_name: String::new(),
+            action: crate::models::RegistryAction::CreateKey,
+            value_type: None,
+            value: None,
+            windows_versions: None,
+            conditions: None,
+            requires_reboot: false,
+            requires_logoff: false,
+            skip_validation: false,
+            elevation: None,
+        };
+        let expanded = expand_registry_change(&change).unwrap();
+        assert_eq!(expanded.len(), 1);
+        assert_eq!(expanded[0].hive, RegistryHive::Hkcr);
+        assert_eq!(expanded[0].key, change.key);
+    }
+
+    #[test]
+    fn all_users_expands_to_user_sids_only() {
+        let change = RegistryChange {
+            hive: RegistryHive::AllUsers,
+            key: "Software\\Example".to_string(),
+            value_name: "Flag".to_string(),
+            action: crate::models::RegistryAction::Set,
+            value_type: Some(RegistryValueType::Dword),
+            value: Some(serde_json::json!(1)),
+            windows_versions: None,
+            conditions: None,
+            requires_reboot: false,
+            requires_logoff: false,
+            skip_validation: false,
+            elevation: None,
+        };
+        let expanded = expand_registry_change(&change).unwrap();
+        assert!(expanded.iter().all(|c| c.hive == RegistryHive::Hku
+            && c.key.starts_with("S-1-5-21-")
+            && c.key.ends_with("\\Software\\Example")));
+    }
+
+    #[test]
+    fn multi_string_data_round_trips_including_an_empty_list() {
+        let items = vec!["svchost".to_string(), "Ünïcode".to_string()];
+        let encoded = encode_multi_string(&items);
+        assert_eq!(encoded.len(), (7 + 1 + 7 + 1 + 1) * 2);
+        assert_eq!(decode_multi_string(&encoded), items);
+
+        assert_eq!(encode_multi_string(&[]), vec![0, 0]);
+        assert!(decode_multi_string(&[0, 0]).is_empty());
+        assert!(decode_multi_string(&[]).is_empty());
+    }
+
+    #[test]
+    fn multi_string_data_without_terminators_still_decodes() {
+        let bytes: Vec<u8> = "a\0b".encode_utf16().flat_map(u16::to_le_bytes).collect();
+        assert_eq!(decode_multi_string(&bytes), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn an_empty_item_in_the_middle_does_not_end_the_list() {
+        let bytes: Vec<u8> = "a\0\0b\0\0"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        assert_eq!(decode_multi_string(&bytes), vec!["a", "", "b"]);
+    }
+
     #[test]
     fn deleting_a_value_under_a_missing_key_reports_not_found_not_access_denied() {
         // Regression for the did-it-work idempotency contract: apply/revert/broker treat a
@@ -76,8 +76,9 @@ pub fn write_registry_json_value(
 
     // HKCU is the user's own hive — always writable directly, so no elevation is needed even for a
     // requires_system tweak (running as SYSTEM would target SYSTEM's own HKCU, not the user's).
-    // Only HKLM under use_system needs the elevated broker (typed RegSetValueExW as SYSTEM).
-    if use_system && matches!(hive, RegistryHive::Hklm) {
+    // Only machine-wide hives (HKLM, HKCR, HKU) under use_system need the elevated broker (typed
+    // RegSetValueExW as SYSTEM).
+    if use_system && hive.requires_admin() {
         return trusted_installer::set_registry_value_as_system(
             *hive,
             key,
@@ -135,12 +136,20 @@ fn parse_multi_string(value: &serde_json::Value) -> Result<Vec<String>, Error> {
     arr.iter()
         .enumerate()
         .map(|(index, item)| {
-            item.as_str().map(str::to_string).ok_or_else(|| {
+            let item = item.as_str().ok_or_else(|| {
                 Error::ValidationError(format!(
                     "REG_MULTI_SZ item [{}] must be a string, got: {}",
                     index, item
                 ))
-            })
+            })?;
+            // An empty string (or an embedded NUL) ends a REG_MULTI_SZ list, so it can't be stored
+            if item.is_empty() || item.contains('\0') {
+                return Err(Error::ValidationError(format!(
+                    "REG_MULTI_SZ item [{}] must be non-empty and contain no NUL characters",
+                    index
+                )));
+            }
+            Ok(item.to_string())
         })
         .collect()
 }
@@ -256,6 +265,37 @@ mod tests {
         assert!(err.to_string().contains("Invalid REG_BINARY byte"));
     }
 
+    #[test]
+    fn parses_multi_string_array() {
+        let parsed =
+            parse_registry_value(&RegistryValueType::MultiString, &json!(["a", "b"])).unwrap();
+
+        assert_eq!(
+            parsed,
+            RegistryValue::MultiString(vec!["a".to_string(), "b".to_string()])
+        );
+    }
+
+    #[test]
+    fn rejects_empty_multi_string_item() {
+        let err = parse_registry_value(&RegistryValueType::MultiString, &json!(["a", "", "b"]))
+            .unwrap_err();
+
+        assert!(err.to_string().contains("item [1]"));
+    }
+
+    #[test]
+    fn matches_empty_multi_string_list() {
+        let matches = registry_values_match(
+            &RegistryValueType::MultiString,
+            &Some(json!([])),
+            &Some(json!([])),
+        )
+        .unwrap();
+
+        assert!(matches);
+    }
+
     #[test]
     fn matches_authored_binary_string_to_read_byte_array() {
         let matches = registry_values_match(
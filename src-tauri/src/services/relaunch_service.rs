@@ -0,0 +1,187 @@
+//! Relaunching the app elevated without losing the user's staged changes.
+//!
+//! Most tweaks need administrator rights. Rather than failing them with `RequiresAdmin`, the app
+//! can start a new copy of itself through the UAC prompt and exit. Changes the user had staged but
+//! not applied are written to a file in the temp folder, whose path is passed to the new copy as
+//! `--resume-operations <file>`. At startup the new copy reads the file (and deletes it), and the
+//! UI takes the changes back with `take_resumed_operations` and stages them again.
+
+use crate::error::Error;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Argument carrying the staged-changes file to an elevated copy of the app
+const RESUME_ARG: &str = "--resume-operations";
+
+/// `ShellExecuteW`'s result when the user declines the UAC prompt
+const SE_ERR_ACCESSDENIED: usize = 5;
+
+/// Changes handed over by the copy that relaunched this one, until the UI takes them
+static RESUMED: Mutex<Option<Vec<PendingOperation>>> = Mutex::new(None);
+
+/// A change the user staged but hadn't applied yet
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingOperation {
+    pub tweak_id: String,
+    pub option_index: usize,
+    /// Apply even though the tweak is still waiting for a reboot from its last change
+    #[serde(default)]
+    pub force: bool,
+    /// The user confirmed the risk of a high or critical risk tweak when staging it. The new
+    /// instance still takes its own `acknowledge_risk` token before applying.
+    #[serde(default)]
+    pub risk_acknowledged: bool,
+}
+
+fn resume_file() -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "magicx-resume-{}-{}.json",
+        std::process::id(),
+        chrono::Local::now().timestamp_millis()
+    ))
+}
+
+/// The staged-changes file in the command line, if this copy was relaunched with one
+fn resume_arg(args: &[String]) -> Option<&str> {
+    let pos = args.iter().position(|arg| arg == RESUME_ARG)?;
+    args.get(pos + 1).map(String::as_str)
+}
+
+fn read_resume_file(path: &Path) -> Result<Vec<PendingOperation>, Error> {
+    let content = std::fs::read(path)
+        .map_err(|e| Error::NotFound(format!("Failed to read {}: {}", path.display(), e)))?;
+    serde_json::from_slice(&content).map_err(|e| {
+        Error::ValidationError(format!(
+            "Invalid staged changes in {}: {}",
+            path.display(),
+            e
+        ))
+    })
+}
+
+/// Pick up the changes handed over by the copy that relaunched this one. Called once at startup.
+pub fn load_resumed_operations() {
+    let args: Vec<String> = std::env::args().collect();
+    let Some(path) = resume_arg(&args).map(Path::new) else {
+        return;
+    };
+    match read_resume_file(path) {
+        Ok(operations) => {
+            log::info!(
+                "Relaunched elevated with {} staged change(s)",
+                operations.len()
+            );
+            *RESUMED.lock().unwrap_or_else(|e| e.into_inner()) = Some(operations);
+        }
+        Err(e) => log::warn!("Failed to pick up staged changes: {}", e),
+    }
+    let _ = std::fs::remove_file(path);
+}
+
+/// The staged changes handed over at relaunch; empty after the first call or without a relaunch
+pub fn take_resumed_operations() -> Vec<PendingOperation> {
+    RESUMED
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .take()
+        .unwrap_or_default()
+}
+
+/// Start an elevated copy of the app through the UAC prompt, handing it `pending`. The caller
+/// exits this copy once it returns. Declining the prompt fails with `Error::Cancelled`.
+pub fn relaunch_elevated(pending: &[PendingOperation]) -> Result<(), Error> {
+    use std::ffi::OsStr;
+    use std::os::windows::ffi::OsStrExt;
+    use std::ptr;
+    use windows_sys::Win32::UI::Shell::ShellExecuteW;
+    use windows_sys::Win32::UI::WindowsAndMessaging::SW_SHOWNORMAL;
+
+    let exe_path = std::env::current_exe()
+        .map_err(|e| Error::WindowsApi(format!("Failed to get executable path: {}", e)))?;
+
+    let file = if pending.is_empty() {
+        None
+    } else {
+        let path = resume_file();
+        let json = serde_json::to_vec(pending).map_err(|e| {
+            Error::ValidationError(format!("Failed to serialize staged changes: {}", e))
+        })?;
+        std::fs::write(&path, json)
+            .map_err(|e| Error::WindowsApi(format!("Failed to write {}: {}", path.display(), e)))?;
+        Some(path)
+    };
+    let parameters = file
+        .as_ref()
+        .map(|path| format!("{} \"{}\"", RESUME_ARG, path.display()));
+
+    let wide = |s: &OsStr| -> Vec<u16> { s.encode_wide().chain(std::iter::once(0)).collect() };
+    let exe_path_wide = wide(exe_path.as_os_str());
+    let runas = wide(OsStr::new("runas"));
+    let parameters_wide = parameters.as_deref().map(|p| wide(OsStr::new(p)));
+
+    // SAFETY: every string is a NUL-terminated UTF-16 buffer that outlives the call. The "runas"
+    // verb shows the UAC prompt; a result above 32 means the new copy started.
+    let result = unsafe {
+        ShellExecuteW(
+            ptr::null_mut(),
+            runas.as_ptr(),
+            exe_path_wide.as_ptr(),
+            parameters_wide.as_ref().map_or(ptr::null(), |p| p.as_ptr()),
+            ptr::null(),
+            SW_SHOWNORMAL,
+        )
+    } as usize;
+
+    if result <= 32 {
+        if let Some(path) = &file {
+            let _ = std::fs::remove_file(path);
+        }
+        return Err(if result == SE_ERR_ACCESSDENIED {
+            Error::Cancelled("The administrator prompt was declined".to_string())
+        } else {
+            Error::WindowsApi(format!(
+                "Failed to restart as admin, error code: {}",
+                result
+            ))
+        });
+    }
+    log::info!(
+        "Started an elevated copy with {} staged change(s)",
+        pending.len()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_resume_file_is_read_from_its_argument() {
+        let args: Vec<String> = ["app.exe", RESUME_ARG, "C:\\Temp\\resume.json"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(resume_arg(&args), Some("C:\\Temp\\resume.json"));
+        assert_eq!(resume_arg(&args[..2]), None);
+        assert_eq!(resume_arg(&["app.exe".to_string()]), None);
+    }
+
+    #[test]
+    fn staged_changes_default_to_not_forced() {
+        // Written by a version without `force` and `risk_acknowledged`
+        let operations: Vec<PendingOperation> =
+            serde_json::from_str(r#"[{"tweak_id":"t","option_index":1}]"#).unwrap();
+        assert_eq!(
+            operations,
+            vec![PendingOperation {
+                tweak_id: "t".to_string(),
+                option_index: 1,
+                force: false,
+                risk_acknowledged: false,
+            }]
+        );
+        assert!(!operations[0].risk_acknowledged);
+    }
+}
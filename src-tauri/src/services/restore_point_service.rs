@@ -0,0 +1,115 @@
+//! Windows System Restore points, through the `SystemRestore` WMI provider (`root\default`).
+//!
+//! Points are created with the provider's `CreateRestorePoint` method and listed by querying its
+//! instances. The method doesn't return the new point's sequence number, so it's found by listing
+//! again and taking the point that wasn't there before. Windows creates at most one point per
+//! 24 hours by default (`SystemRestorePointCreationFrequency`); a throttled request still reports
+//! success, so it shows up here as no new point and is returned as an error.
+
+use crate::error::Error;
+use crate::models::RestorePoint;
+use crate::services::powershell::{ps_quote, run_script};
+use crate::services::system_info_service::parse_wmi_datetime_to_iso;
+use serde::Deserialize;
+use wmi::WMIConnection;
+
+/// `RestorePointType` for a point created before applying settings (MODIFY_SETTINGS)
+const MODIFY_SETTINGS: u32 = 12;
+
+/// `EventType` marking the start of a system change (BEGIN_SYSTEM_CHANGE)
+const BEGIN_SYSTEM_CHANGE: u32 = 100;
+
+/// One instance of the `SystemRestore` class
+#[derive(Deserialize, Debug)]
+#[serde(rename = "SystemRestore")]
+#[serde(rename_all = "PascalCase")]
+struct WmiRestorePoint {
+    sequence_number: u32,
+    description: Option<String>,
+    creation_time: Option<String>,
+    restore_point_type: Option<u32>,
+}
+
+/// List the restore points on this machine, oldest first
+pub fn list_restore_points() -> Result<Vec<RestorePoint>, Error> {
+    let con = WMIConnection::with_namespace_path("root\\default").map_err(|e| {
+        Error::WindowsApi(format!(
+            "Failed to connect to the SystemRestore provider: {}",
+            e
+        ))
+    })?;
+    let raw: Vec<WmiRestorePoint> = con
+        .query()
+        .map_err(|e| Error::WindowsApi(format!("Failed to query restore points: {}", e)))?;
+
+    let mut points: Vec<RestorePoint> = raw
+        .into_iter()
+        .map(|point| RestorePoint {
+            sequence_number: point.sequence_number,
+            description: point.description.unwrap_or_default(),
+            created_at: point
+                .creation_time
+                .as_deref()
+                .map(parse_wmi_datetime_to_iso)
+                .unwrap_or_default(),
+            restore_point_type: point.restore_point_type.unwrap_or_default(),
+        })
+        .collect();
+    points.sort_by_key(|point| point.sequence_number);
+    Ok(points)
+}
+
+/// Create a restore point and return it. Requires administrator privileges and System Protection
+/// turned on for the system drive.
+pub fn create_restore_point(description: &str) -> Result<RestorePoint, Error> {
+    let last_before = latest_sequence_number()?;
+    log::info!("Creating restore point '{}'", description);
+
+    let script = format!(
+        "(Invoke-CimMethod -Namespace root/default -ClassName SystemRestore -MethodName CreateRestorePoint -Arguments @{{ Description = {}; RestorePointType = [uint32]{}; EventType = [uint32]{} }}).ReturnValue",
+        ps_quote(description),
+        MODIFY_SETTINGS,
+        BEGIN_SYSTEM_CHANGE
+    );
+    let stdout = run_script(&script)?;
+
+    // ReturnValue is a Win32 error code, e.g. 1058 when System Protection is off
+    match stdout.trim().parse::<u32>() {
+        Ok(0) => {}
+        Ok(code) => {
+            return Err(Error::CommandExecution(format!(
+                "CreateRestorePoint failed with error {} (is System Protection turned on for the system drive?)",
+                code
+            )))
+        }
+        Err(_) => {
+            return Err(Error::CommandExecution(format!(
+                "Unexpected CreateRestorePoint output: {}",
+                stdout.trim()
+            )))
+        }
+    }
+
+    let created = list_restore_points()?
+        .into_iter()
+        .rfind(|point| last_before.is_none_or(|last| point.sequence_number > last))
+        .ok_or_else(|| {
+            Error::CommandExecution(
+                "Windows skipped the restore point because another one was created in the last 24 hours"
+                    .to_string(),
+            )
+        })?;
+
+    log::info!(
+        "Created restore point {} '{}'",
+        created.sequence_number,
+        created.description
+    );
+    Ok(created)
+}
+
+fn latest_sequence_number() -> Result<Option<u32>, Error> {
+    Ok(list_restore_points()?
+        .last()
+        .map(|point| point.sequence_number))
+}
@@ -5,20 +5,32 @@
 //! the actual fix for the locale class: the old code parsed the localized "Status:" line, so it
 //! silently misread state on non-English Windows.
 //!
-//! Supports both exact task names and regex patterns for matching multiple tasks.
+//! Supports both exact task names and regex patterns for matching multiple tasks. A task's
+//! definition can be read as XML and registered again from it, which is how a deleted task is put
+//! back on revert. `list_logon_tasks` walks every folder but Windows' own for the tasks that run at
+//! sign-in, which the startup inventory lists.
+//!
+//! Under SYSTEM / TrustedInstaller these same calls run inside the elevated broker: one typed op
+//! per exact task, and one per pattern, which lists and changes every matching task in a single
+//! round trip and returns each task's resulting state.
 
 use crate::error::Error;
 use crate::models::tweak::SchedulerAction;
 use regex_lite::Regex;
+use serde::{Deserialize, Serialize};
 use std::cell::Cell;
 use std::sync::Mutex;
 
-use windows::core::BSTR;
+use windows::core::{Interface, BSTR};
 use windows::Win32::Foundation::{VARIANT_FALSE, VARIANT_TRUE};
 use windows::Win32::System::Com::{
     CoCreateInstance, CoInitializeEx, CLSCTX_ALL, COINIT_MULTITHREADED,
 };
-use windows::Win32::System::TaskScheduler::{ITaskService, TaskScheduler, TASK_ENUM_HIDDEN};
+use windows::Win32::System::TaskScheduler::{
+    IExecAction, IRegisteredTask, ITaskDefinition, ITaskFolder, ITaskService, TaskScheduler,
+    TASK_ACTION_EXEC, TASK_ACTION_TYPE, TASK_CREATE_OR_UPDATE, TASK_ENUM_HIDDEN,
+    TASK_TRIGGER_LOGON, TASK_TRIGGER_TYPE2,
+};
 use windows::Win32::System::Variant::VARIANT;
 
 // TASK_STATE numeric values (the locale-free source of truth).
@@ -31,8 +43,11 @@ const TASK_STATE_RUNNING: i32 = 4;
 const HRESULT_FILE_NOT_FOUND: u32 = 0x8007_0002;
 const HRESULT_PATH_NOT_FOUND: u32 = 0x8007_0003;
 
+/// Folder of Windows' own tasks, which [`list_logon_tasks`] leaves to the tweaks.
+const WINDOWS_TASKS_FOLDER: &str = "\\Microsoft";
+
 /// State of a scheduled task.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TaskState {
     /// Task is enabled and ready to run.
     Ready,
@@ -74,6 +89,30 @@ pub struct TaskInfo {
     pub state: TaskState,
 }
 
+/// A task that runs when a user signs in (see [`list_logon_tasks`]).
+#[derive(Debug, Clone)]
+pub struct LogonTask {
+    /// Folder the task is in (e.g. `\` or `\Vendor`)
+    pub path: String,
+    pub name: String,
+    pub enabled: bool,
+    /// Program the task's first "start a program" action runs, if it has one
+    pub program: Option<String>,
+    /// Arguments of that action
+    pub arguments: Option<String>,
+}
+
+/// The result of applying an action to one task matched by a pattern.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskChangeOutcome {
+    pub name: String,
+    /// State read back after the action (`NotFound` once deleted), or the state before it when
+    /// the action failed
+    pub state: TaskState,
+    /// Why the action failed, if it did
+    pub error: Option<String>,
+}
+
 fn com_err(e: windows::core::Error) -> Error {
     Error::CommandExecution(format!("Task Scheduler COM error: {}", e))
 }
@@ -163,6 +202,64 @@ pub fn get_task_state(task_path: &str, task_name: &str) -> Result<TaskState, Err
     })
 }
 
+/// Get a task's full definition as Task Scheduler XML, or None if the task doesn't exist.
+pub fn get_task_xml(task_path: &str, task_name: &str) -> Result<Option<String>, Error> {
+    with_task_service(|service| unsafe {
+        let folder = match service.GetFolder(&BSTR::from(task_path)) {
+            Ok(f) => f,
+            Err(e) if is_not_found(&e) => return Ok(None),
+            Err(e) => return Err(com_err(e)),
+        };
+        let task = match folder.GetTask(&BSTR::from(task_name)) {
+            Ok(t) => t,
+            Err(e) if is_not_found(&e) => return Ok(None),
+            Err(e) => return Err(com_err(e)),
+        };
+        Ok(Some(task.Xml().map_err(com_err)?.to_string()))
+    })
+}
+
+/// Register a task from its XML definition (as returned by [`get_task_xml`]), replacing any task
+/// with the same name and creating the folder if it's gone. The task runs as the principal in the
+/// XML, so a SYSTEM task is registered back as a SYSTEM task.
+pub fn register_task_xml(task_path: &str, task_name: &str, xml: &str) -> Result<(), Error> {
+    log::info!("Registering scheduled task: {}\\{}", task_path, task_name);
+    with_task_service(|service| unsafe {
+        let folder: ITaskFolder = match service.GetFolder(&BSTR::from(task_path)) {
+            Ok(f) => f,
+            Err(e) if is_not_found(&e) => service
+                .GetFolder(&BSTR::from("\\"))
+                .and_then(|root| root.CreateFolder(&BSTR::from(task_path), &VARIANT::default()))
+                .map_err(com_err)?,
+            Err(e) => return Err(com_err(e)),
+        };
+
+        let definition = service.NewTask(0).map_err(com_err)?;
+        definition.SetXmlText(&BSTR::from(xml)).map_err(com_err)?;
+        let principal = definition.Principal().map_err(com_err)?;
+        let logon_type = principal.LogonType().map_err(com_err)?;
+        let user_id = principal.UserId().map_err(com_err)?;
+        let user = if user_id.is_empty() {
+            VARIANT::default()
+        } else {
+            VARIANT::from(user_id)
+        };
+
+        folder
+            .RegisterTaskDefinition(
+                &BSTR::from(task_name),
+                &definition,
+                TASK_CREATE_OR_UPDATE.0,
+                &user,
+                &VARIANT::default(),
+                logon_type,
+                &VARIANT::default(),
+            )
+            .map_err(com_err)?;
+        Ok(())
+    })
+}
+
 /// Enable or disable a scheduled task (the two paths differ only in the flag).
 fn set_task_enabled(task_path: &str, task_name: &str, enabled: bool) -> Result<(), Error> {
     let flag = if enabled { VARIANT_TRUE } else { VARIANT_FALSE };
@@ -261,54 +358,138 @@ pub fn find_tasks_by_pattern(task_path: &str, pattern: &str) -> Result<Vec<TaskI
     Ok(matching)
 }
 
-/// Apply action to multiple tasks found by pattern.
-/// Returns `(success_count, error_count, errors)`.
+/// Apply an action to every task in a folder whose name matches a regex pattern, returning one
+/// outcome per matching task (none when nothing matches). A task that fails doesn't stop the rest.
 pub fn apply_action_to_pattern(
     task_path: &str,
     pattern: &str,
     action: SchedulerAction,
-    ignore_not_found: bool,
-) -> Result<(usize, usize, Vec<String>), Error> {
+) -> Result<Vec<TaskChangeOutcome>, Error> {
     let tasks = find_tasks_by_pattern(task_path, pattern)?;
 
-    if tasks.is_empty() {
-        if ignore_not_found {
-            log::warn!(
-                "No tasks found matching pattern '{}' in '{}' (ignore_not_found=true)",
-                pattern,
-                task_path
+    Ok(tasks
+        .into_iter()
+        .map(|task| {
+            log::info!(
+                "Applying {:?} to task '{}\\{}'",
+                action,
+                task_path,
+                task.name
             );
-            return Ok((0, 0, Vec::new()));
-        } else {
-            return Err(Error::CommandExecution(format!(
-                "No tasks found matching pattern '{}' in '{}'",
-                pattern, task_path
-            )));
+            match apply_scheduler_change(task_path, &task.name, action) {
+                Ok(()) => TaskChangeOutcome {
+                    state: get_task_state(task_path, &task.name)
+                        .unwrap_or_else(|e| TaskState::Unknown(e.to_string())),
+                    name: task.name,
+                    error: None,
+                },
+                Err(e) => TaskChangeOutcome {
+                    name: task.name,
+                    state: task.state,
+                    error: Some(e.to_string()),
+                },
+            }
+        })
+        .collect())
+}
+
+/// Every task with a logon trigger, in every folder but `\Microsoft` (Windows' own tasks).
+/// Folders and tasks that can't be read are skipped.
+pub fn list_logon_tasks() -> Result<Vec<LogonTask>, Error> {
+    with_task_service(|service| unsafe {
+        let root = service.GetFolder(&BSTR::from("\\")).map_err(com_err)?;
+        let mut tasks = Vec::new();
+        collect_logon_tasks(&root, &mut tasks);
+        log::debug!("Found {} logon tasks", tasks.len());
+        Ok(tasks)
+    })
+}
+
+/// Add the logon tasks in `folder` and its subfolders to `tasks`
+fn collect_logon_tasks(folder: &ITaskFolder, tasks: &mut Vec<LogonTask>) {
+    // SAFETY: COM calls on a live folder; both collections are 1-indexed up to their count.
+    unsafe {
+        let path = folder
+            .Path()
+            .map(|path| path.to_string())
+            .unwrap_or_default();
+        if path.eq_ignore_ascii_case(WINDOWS_TASKS_FOLDER) {
+            return;
+        }
+        if let Ok(registered) = folder.GetTasks(TASK_ENUM_HIDDEN.0) {
+            for i in 1..=registered.Count().unwrap_or(0) {
+                let Ok(task) = registered.get_Item(&VARIANT::from(i)) else {
+                    continue;
+                };
+                match logon_task(&path, &task) {
+                    Ok(found) => tasks.extend(found),
+                    Err(e) => log::debug!("Skipping a task in '{}': {}", path, e),
+                }
+            }
+        }
+        if let Ok(subfolders) = folder.GetFolders(0) {
+            for i in 1..=subfolders.Count().unwrap_or(0) {
+                if let Ok(subfolder) = subfolders.get_Item(&VARIANT::from(i)) {
+                    collect_logon_tasks(&subfolder, tasks);
+                }
+            }
         }
     }
+}
 
-    let mut success_count = 0;
-    let mut error_count = 0;
-    let mut errors = Vec::new();
+/// `task` as a logon task, or None if none of its triggers is a logon
+fn logon_task(path: &str, task: &IRegisteredTask) -> windows::core::Result<Option<LogonTask>> {
+    // SAFETY: COM calls on a live task; both collections are 1-indexed up to their count.
+    unsafe {
+        let definition = task.Definition()?;
+        let triggers = definition.Triggers()?;
+        let mut count = 0;
+        triggers.Count(&mut count)?;
+        let mut at_logon = false;
+        for i in 1..=count {
+            let mut kind = TASK_TRIGGER_TYPE2::default();
+            triggers.get_Item(i)?.Type(&mut kind)?;
+            at_logon |= kind == TASK_TRIGGER_LOGON;
+        }
+        if !at_logon {
+            return Ok(None);
+        }
 
-    for task in tasks {
-        log::info!(
-            "Applying {:?} to task '{}\\{}'",
-            action,
-            task_path,
-            task.name
-        );
+        let (program, arguments) = first_exec_action(&definition)?.unzip();
+        Ok(Some(LogonTask {
+            path: path.to_string(),
+            name: task.Name()?.to_string(),
+            enabled: task.Enabled()? != VARIANT_FALSE,
+            program,
+            arguments: arguments.filter(|arguments| !arguments.is_empty()),
+        }))
+    }
+}
 
-        match apply_scheduler_change(task_path, &task.name, action) {
-            Ok(()) => success_count += 1,
-            Err(e) => {
-                error_count += 1;
-                errors.push(format!("{}\\{}: {}", task_path, task.name, e));
+/// Program and arguments of the first "start a program" action, if the task has one
+fn first_exec_action(
+    definition: &ITaskDefinition,
+) -> windows::core::Result<Option<(String, String)>> {
+    // SAFETY: COM calls on a live definition; the collection is 1-indexed up to its count.
+    unsafe {
+        let actions = definition.Actions()?;
+        let mut count = 0;
+        actions.Count(&mut count)?;
+        for i in 1..=count {
+            let action = actions.get_Item(i)?;
+            let mut kind = TASK_ACTION_TYPE::default();
+            action.Type(&mut kind)?;
+            if kind != TASK_ACTION_EXEC {
+                continue;
             }
+            let exec: IExecAction = action.cast()?;
+            let (mut program, mut arguments) = (BSTR::new(), BSTR::new());
+            exec.Path(&mut program)?;
+            exec.Arguments(&mut arguments)?;
+            return Ok(Some((program.to_string(), arguments.to_string())));
         }
+        Ok(None)
     }
-
-    Ok((success_count, error_count, errors))
 }
 
 #[cfg(test)]
@@ -327,6 +508,20 @@ mod tests {
         assert!(matches!(task_state_from_com(0), TaskState::Unknown(_)));
     }
 
+    #[test]
+    fn task_states_round_trip_through_the_broker_wire_format() {
+        let outcome = TaskChangeOutcome {
+            name: "Consolidator".to_string(),
+            state: TaskState::Unknown("TASK_STATE(0)".to_string()),
+            error: Some("Access is denied.".to_string()),
+        };
+        let json = serde_json::to_string(&outcome).unwrap();
+        assert_eq!(
+            serde_json::from_str::<TaskChangeOutcome>(&json).unwrap(),
+            outcome
+        );
+    }
+
     #[test]
     fn task_state_from_str_parses_known_states() {
         assert_eq!(TaskState::from_str("Ready"), TaskState::Ready);
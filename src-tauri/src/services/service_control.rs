@@ -13,6 +13,7 @@
 use crate::error::Error;
 use crate::models::{RegistryHive, ServiceStartupType};
 use crate::services::registry_service;
+use serde::{Deserialize, Serialize};
 use std::ffi::OsStr;
 use std::os::windows::ffi::OsStrExt;
 use std::ptr;
@@ -54,7 +55,7 @@ const STOP_TIMEOUT_MS: u128 = 30_000;
 const START_TIMEOUT_MS: u128 = 30_000;
 
 /// Service running state.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ServiceState {
     Running,
     Stopped,
@@ -66,8 +67,8 @@ pub enum ServiceState {
     Unknown,
 }
 
-/// Service status information.
-#[derive(Debug, Clone)]
+/// Service status information. Serializable so the elevated broker can send it back.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[allow(dead_code)] // name field reserved for future use
 pub struct ServiceStatus {
     pub name: String,
@@ -0,0 +1,60 @@
+//! Windows shell service for restarting explorer.exe after tweaks it only reads at startup.
+//!
+//! Explorer is ended with `taskkill`; Winlogon normally starts a new shell on its own
+//! (`AutoRestartShell`). If none appears within a few seconds, explorer.exe is started directly.
+
+use crate::error::Error;
+use crate::services::elevation::find_process_by_name;
+use std::os::windows::process::CommandExt;
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+/// taskkill exit code when no process matched
+const TASKKILL_NOT_FOUND: i32 = 128;
+
+/// How long to wait for Winlogon to restart the shell before starting it ourselves
+const AUTO_RESTART_TIMEOUT: Duration = Duration::from_secs(5);
+
+fn is_explorer_running() -> bool {
+    find_process_by_name("explorer.exe").is_ok()
+}
+
+/// End explorer.exe and make sure a new shell comes back.
+pub fn restart_explorer() -> Result<(), Error> {
+    log::info!("Restarting explorer.exe");
+    let status = Command::new("taskkill")
+        .args(["/F", "/IM", "explorer.exe"])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .map_err(|e| Error::CommandExecution(format!("Failed to run taskkill: {}", e)))?
+        .status;
+    match status.code() {
+        Some(0) | Some(TASKKILL_NOT_FOUND) => {}
+        code => {
+            return Err(Error::CommandExecution(format!(
+                "taskkill explorer.exe failed with exit code {}",
+                code.unwrap_or(-1)
+            )))
+        }
+    }
+
+    // Give the old instance time to exit before looking for the new one
+    thread::sleep(Duration::from_millis(500));
+    let start = Instant::now();
+    while start.elapsed() < AUTO_RESTART_TIMEOUT {
+        if is_explorer_running() {
+            log::info!("Explorer restarted");
+            return Ok(());
+        }
+        thread::sleep(Duration::from_millis(250));
+    }
+
+    log::info!("Explorer didn't restart on its own; starting it");
+    Command::new("explorer.exe")
+        .spawn()
+        .map_err(|e| Error::CommandExecution(format!("Failed to start explorer.exe: {}", e)))?;
+    Ok(())
+}
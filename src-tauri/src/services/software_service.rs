@@ -0,0 +1,207 @@
+//! Installed software inventory, as Apps & features lists it.
+//!
+//! Programs register an entry under an `Uninstall` key: machine-wide ones in HKLM (with 32-bit
+//! programs under `WOW6432Node` on 64-bit Windows) and per-user ones in HKCU of the account the app
+//! runs as. Entries without a display name, system components, and updates of another entry are
+//! left out, as Apps & features leaves them out. Store (AppX) apps aren't registered here.
+
+use crate::error::Error;
+use serde::Serialize;
+use winreg::enums::*;
+use winreg::RegKey;
+use winreg::HKEY;
+
+const UNINSTALL_KEY: &str = "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Uninstall";
+const UNINSTALL_KEY_WOW64: &str =
+    "SOFTWARE\\WOW6432Node\\Microsoft\\Windows\\CurrentVersion\\Uninstall";
+
+/// Where a program is registered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SoftwareScope {
+    /// HKLM, 64-bit (or native) registry view
+    Machine,
+    /// HKLM, `WOW6432Node` (32-bit programs on 64-bit Windows)
+    Machine32,
+    /// HKCU of the account the app runs as
+    User,
+}
+
+/// A program in the inventory
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InstalledSoftware {
+    /// Name of the program's `Uninstall` subkey (a product code for MSI installs)
+    pub id: String,
+    pub name: String,
+    pub version: Option<String>,
+    pub publisher: Option<String>,
+    /// Install date (YYYY-MM-DD), if the installer recorded one
+    pub install_date: Option<String>,
+    /// Size the installer estimated, in KB
+    pub estimated_size_kb: Option<u32>,
+    pub scope: SoftwareScope,
+}
+
+/// The values of an `Uninstall` entry the inventory reads
+#[derive(Debug, Default)]
+struct UninstallEntry {
+    display_name: Option<String>,
+    display_version: Option<String>,
+    publisher: Option<String>,
+    install_date: Option<String>,
+    estimated_size: Option<u32>,
+    system_component: Option<u32>,
+    parent_key_name: Option<String>,
+    release_type: Option<String>,
+}
+
+impl UninstallEntry {
+    fn read(key: &RegKey) -> Self {
+        let string = |name: &str| {
+            key.get_value::<String, _>(name)
+                .ok()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        UninstallEntry {
+            display_name: string("DisplayName"),
+            display_version: string("DisplayVersion"),
+            publisher: string("Publisher"),
+            install_date: string("InstallDate"),
+            estimated_size: key.get_value("EstimatedSize").ok(),
+            system_component: key.get_value("SystemComponent").ok(),
+            parent_key_name: string("ParentKeyName"),
+            release_type: string("ReleaseType"),
+        }
+    }
+
+    /// The entry as a listed program, or None if Apps & features hides it
+    fn into_software(self, id: String, scope: SoftwareScope) -> Option<InstalledSoftware> {
+        let is_update = self.parent_key_name.is_some()
+            || self
+                .release_type
+                .as_deref()
+                .is_some_and(|release| matches!(release, "Update" | "Hotfix" | "Security Update"));
+        if self.system_component == Some(1) || is_update {
+            return None;
+        }
+        Some(InstalledSoftware {
+            id,
+            name: self.display_name?,
+            version: self.display_version,
+            publisher: self.publisher,
+            install_date: self.install_date.as_deref().and_then(parse_install_date),
+            estimated_size_kb: self.estimated_size.filter(|&size| size > 0),
+            scope,
+        })
+    }
+}
+
+/// `InstallDate` as YYYY-MM-DD. Installers write YYYYMMDD; anything else is dropped.
+fn parse_install_date(value: &str) -> Option<String> {
+    if value.len() != 8 || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    chrono::NaiveDate::parse_from_str(value, "%Y%m%d")
+        .ok()
+        .map(|date| date.format("%Y-%m-%d").to_string())
+}
+
+/// The programs registered under one `Uninstall` key. A key that doesn't exist has none.
+fn list_key(hive: HKEY, path: &str, scope: SoftwareScope) -> Result<Vec<InstalledSoftware>, Error> {
+    let uninstall = match RegKey::predef(hive).open_subkey_with_flags(path, KEY_READ) {
+        Ok(key) => key,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(Error::RegistryAccessDenied(format!(
+                "Failed to open {}: {}",
+                path, e
+            )))
+        }
+    };
+
+    let mut software = Vec::new();
+    for id in uninstall.enum_keys().filter_map(Result::ok) {
+        let Ok(key) = uninstall.open_subkey_with_flags(&id, KEY_READ) else {
+            continue;
+        };
+        software.extend(UninstallEntry::read(&key).into_software(id, scope));
+    }
+    Ok(software)
+}
+
+/// Every installed program, sorted by name
+pub fn get_installed_software() -> Result<Vec<InstalledSoftware>, Error> {
+    let mut software = list_key(HKEY_LOCAL_MACHINE, UNINSTALL_KEY, SoftwareScope::Machine)?;
+    software.extend(list_key(
+        HKEY_LOCAL_MACHINE,
+        UNINSTALL_KEY_WOW64,
+        SoftwareScope::Machine32,
+    )?);
+    software.extend(list_key(
+        HKEY_CURRENT_USER,
+        UNINSTALL_KEY,
+        SoftwareScope::User,
+    )?);
+
+    software.sort_by_cached_key(|program| {
+        (
+            program.name.to_lowercase(),
+            program.version.clone(),
+            program.id.clone(),
+        )
+    });
+    // Some installers register the same program in more than one view
+    software.dedup_by(|a, b| a.name == b.name && a.version == b.version && a.id == b.id);
+    log::debug!("Found {} installed programs", software.len());
+    Ok(software)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str) -> UninstallEntry {
+        UninstallEntry {
+            display_name: Some(name.to_string()),
+            install_date: Some("20240115".to_string()),
+            estimated_size: Some(2048),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn entries_become_programs_with_iso_install_dates() {
+        let program = entry("7-Zip")
+            .into_software("7-Zip".to_string(), SoftwareScope::Machine)
+            .unwrap();
+        assert_eq!(program.install_date.as_deref(), Some("2024-01-15"));
+        assert_eq!(program.estimated_size_kb, Some(2048));
+
+        assert_eq!(parse_install_date("2024-01-15"), None);
+        assert_eq!(parse_install_date("20241345"), None);
+        assert_eq!(parse_install_date("2024115"), None);
+    }
+
+    #[test]
+    fn hidden_components_and_updates_are_left_out() {
+        let listed = |entry: UninstallEntry| {
+            entry
+                .into_software("id".to_string(), SoftwareScope::User)
+                .is_some()
+        };
+        assert!(!listed(UninstallEntry::default()));
+        assert!(!listed(UninstallEntry {
+            system_component: Some(1),
+            ..entry("Runtime")
+        }));
+        assert!(!listed(UninstallEntry {
+            parent_key_name: Some("OfficeProPlus".to_string()),
+            ..entry("Office update")
+        }));
+        assert!(!listed(UninstallEntry {
+            release_type: Some("Security Update".to_string()),
+            ..entry("KB123")
+        }));
+    }
+}
@@ -0,0 +1,872 @@
+//! Startup programs: what starts when a user signs in, what it costs, and turning it off.
+//!
+//! Programs start at sign-in from the `Run` keys (HKLM, its `WOW6432Node` view, and HKCU of the
+//! account the app runs as), the Startup folders (the account's and the all-users one), and
+//! scheduled tasks with a logon trigger. Run keys and Startup folders are turned off the way Task
+//! Manager's Startup tab does it, with an `Explorer\StartupApproved` value, so the entry stays in
+//! place and Task Manager shows the same state; logon tasks are disabled in Task Scheduler. Every
+//! change records the entry's prior state in `startup_changes.json` in the ProgramData app folder,
+//! and `undo_last_change` puts the most recent one back.
+//!
+//! Impact is measured rather than guessed: the processes running an entry's program that started
+//! within `STARTUP_WINDOW_MINUTES` of boot are rated by their CPU time and I/O, with Task Manager's
+//! thresholds. Both are counted from when each process started, so the rating matches Task
+//! Manager's best shortly after sign-in.
+
+use crate::error::Error;
+use crate::services::{backup_service, scheduler_service, system_info_service};
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use windows::core::{Interface, HSTRING};
+use windows::Win32::System::Com::{
+    CoCreateInstance, CoInitializeEx, IPersistFile, CLSCTX_INPROC_SERVER, COINIT_MULTITHREADED,
+    STGM_READ,
+};
+use windows::Win32::UI::Shell::{IShellLinkW, ShellLink};
+use windows_sys::Win32::Foundation::{CloseHandle, FALSE, FILETIME, INVALID_HANDLE_VALUE};
+use windows_sys::Win32::System::Diagnostics::ToolHelp::{
+    CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W, TH32CS_SNAPPROCESS,
+};
+use windows_sys::Win32::System::Threading::{
+    GetProcessIoCounters, GetProcessTimes, OpenProcess, QueryFullProcessImageNameW, IO_COUNTERS,
+    PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+};
+use winreg::enums::*;
+use winreg::{RegKey, RegValue, HKEY};
+
+const RUN_KEY: &str = "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Run";
+const RUN_KEY_WOW64: &str = "SOFTWARE\\WOW6432Node\\Microsoft\\Windows\\CurrentVersion\\Run";
+const APPROVED_RUN_KEY: &str =
+    "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Explorer\\StartupApproved\\Run";
+const APPROVED_RUN32_KEY: &str =
+    "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Explorer\\StartupApproved\\Run32";
+const APPROVED_FOLDER_KEY: &str =
+    "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Explorer\\StartupApproved\\StartupFolder";
+
+/// Startup folders, relative to %APPDATA% and %ProgramData%
+const USER_STARTUP_FOLDER: &str = "Microsoft\\Windows\\Start Menu\\Programs\\Startup";
+const COMMON_STARTUP_FOLDER: &str = "Microsoft\\Windows\\Start Menu\\Programs\\StartUp";
+
+/// First byte of the `StartupApproved` values Task Manager writes
+const APPROVED_ENABLED: u8 = 0x02;
+const APPROVED_DISABLED: u8 = 0x03;
+
+/// How long after boot a process may start and still count as started at sign-in
+const STARTUP_WINDOW_MINUTES: u64 = 10;
+
+/// Task Manager's impact thresholds: High above either upper bound, Low below both lower ones
+const HIGH_CPU_MS: u64 = 1000;
+const HIGH_IO_BYTES: u64 = 3 * 1024 * 1024;
+const MEDIUM_CPU_MS: u64 = 300;
+const MEDIUM_IO_BYTES: u64 = 300 * 1024;
+
+/// 100 ns intervals from 1601-01-01 (the FILETIME epoch) to 1970-01-01
+const FILETIME_UNIX_EPOCH: u64 = 116_444_736_000_000_000;
+const FILETIME_TICKS_PER_MS: u64 = 10_000;
+
+const CHANGES_FILE: &str = "startup_changes.json";
+
+/// Changes kept for undo; older ones are dropped
+const MAX_CHANGES: usize = 50;
+
+/// Serializes the change log's read-modify-write
+static CHANGES_LOCK: Mutex<()> = Mutex::new(());
+
+thread_local! {
+    /// Whether this thread has initialized COM (see `scheduler_service::with_task_service`)
+    static COM_READY: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Where a startup entry is registered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupSource {
+    /// HKLM `Run`, 64-bit (or native) registry view
+    RunMachine,
+    /// HKLM `WOW6432Node` `Run` (32-bit programs on 64-bit Windows)
+    RunMachine32,
+    /// HKCU `Run` of the account the app runs as
+    RunUser,
+    /// The all-users Startup folder
+    StartupFolderMachine,
+    /// The Startup folder of the account the app runs as
+    StartupFolderUser,
+    /// A scheduled task with a logon trigger
+    LogonTask,
+}
+
+impl StartupSource {
+    const ALL: [StartupSource; 6] = [
+        StartupSource::RunMachine,
+        StartupSource::RunMachine32,
+        StartupSource::RunUser,
+        StartupSource::StartupFolderMachine,
+        StartupSource::StartupFolderUser,
+        StartupSource::LogonTask,
+    ];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            StartupSource::RunMachine => "run_machine",
+            StartupSource::RunMachine32 => "run_machine32",
+            StartupSource::RunUser => "run_user",
+            StartupSource::StartupFolderMachine => "startup_folder_machine",
+            StartupSource::StartupFolderUser => "startup_folder_user",
+            StartupSource::LogonTask => "logon_task",
+        }
+    }
+
+    /// Hive and key of the `StartupApproved` values that turn this source's entries off; None for
+    /// logon tasks, which Task Scheduler turns off
+    fn approved_key(self) -> Option<(HKEY, &'static str)> {
+        match self {
+            StartupSource::RunMachine => Some((HKEY_LOCAL_MACHINE, APPROVED_RUN_KEY)),
+            StartupSource::RunMachine32 => Some((HKEY_LOCAL_MACHINE, APPROVED_RUN32_KEY)),
+            StartupSource::RunUser => Some((HKEY_CURRENT_USER, APPROVED_RUN_KEY)),
+            StartupSource::StartupFolderMachine => Some((HKEY_LOCAL_MACHINE, APPROVED_FOLDER_KEY)),
+            StartupSource::StartupFolderUser => Some((HKEY_CURRENT_USER, APPROVED_FOLDER_KEY)),
+            StartupSource::LogonTask => None,
+        }
+    }
+}
+
+/// How much an entry slows sign-in, as Task Manager rates it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupImpact {
+    High,
+    Medium,
+    Low,
+    /// None of its processes started at sign-in (or its program isn't known)
+    NotMeasured,
+}
+
+/// An entry in the inventory
+#[derive(Debug, Clone, Serialize)]
+pub struct StartupItem {
+    /// `<source>:<name>`, the name being the Run value, the Startup folder file, or the task path
+    pub id: String,
+    pub name: String,
+    pub source: StartupSource,
+    /// Command line it runs (a shortcut's target and arguments), if known
+    pub command: Option<String>,
+    pub enabled: bool,
+    pub impact: StartupImpact,
+    /// CPU time its processes used since they started at sign-in, when measured
+    pub cpu_ms: Option<u64>,
+    /// Bytes those processes read and wrote
+    pub io_bytes: Option<u64>,
+}
+
+/// An entry's state before a change
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum PriorState {
+    /// Its `StartupApproved` value, None if it had none
+    Approved { value: Option<Vec<u8>> },
+    /// Whether the logon task was enabled
+    Task { enabled: bool },
+}
+
+/// A change made by `set_enabled`, kept for undo
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StartupChange {
+    /// The entry's ID (see `StartupItem::id`)
+    pub id: String,
+    /// Whether the change enabled the entry
+    pub enabled: bool,
+    /// When it was made (RFC 3339)
+    pub changed_at: String,
+    prior: PriorState,
+}
+
+/// An entry found in one of the sources
+#[derive(Debug)]
+struct Entry {
+    source: StartupSource,
+    /// Run value, Startup folder file, or task path
+    key: String,
+    name: String,
+    command: Option<String>,
+    /// Path (or file name) of the program it starts
+    program: Option<String>,
+    /// Whether it's enabled, for logon tasks (the others keep it in `StartupApproved`)
+    enabled: Option<bool>,
+}
+
+/// CPU time and I/O of the processes running one program
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Usage {
+    cpu_ms: u64,
+    io_bytes: u64,
+}
+
+fn registry_err(action: &str, path: &str, e: io::Error) -> Error {
+    let message = format!("Failed to {} {}: {}", action, path, e);
+    if e.kind() == io::ErrorKind::PermissionDenied {
+        Error::RegistryAccessDenied(message)
+    } else {
+        Error::RegistryOperation(message)
+    }
+}
+
+fn env_dir(var: &str) -> Option<PathBuf> {
+    std::env::var_os(var).map(PathBuf::from)
+}
+
+/// `value` with `%NAME%` environment variables expanded; unknown ones are left as they are
+fn expand_env(value: &str) -> String {
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('%') {
+        let Some(len) = rest[start + 1..].find('%') else {
+            break;
+        };
+        let name = &rest[start + 1..start + 1 + len];
+        expanded.push_str(&rest[..start]);
+        match std::env::var(name) {
+            Ok(var) if !name.is_empty() => expanded.push_str(&var),
+            _ => expanded.push_str(&rest[start..start + len + 2]),
+        }
+        rest = &rest[start + len + 2..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+/// The program a command line starts: the quoted part if it's quoted, else everything up to the
+/// first `.exe`, else the first word
+fn command_program(command: &str) -> Option<String> {
+    let command = command.trim();
+    let program = if let Some(quoted) = command.strip_prefix('"') {
+        quoted.split('"').next()?
+    } else if let Some(end) = command.to_ascii_lowercase().find(".exe") {
+        &command[..end + 4]
+    } else {
+        command.split_whitespace().next()?
+    };
+    Some(expand_env(program.trim())).filter(|program| !program.is_empty())
+}
+
+/// Whether a `StartupApproved` value leaves its entry enabled. Task Manager writes an even first
+/// byte (02, or 06) for enabled and an odd one (03, or 07) for disabled; no value is enabled.
+fn approved_enabled(value: Option<&[u8]>) -> bool {
+    value
+        .and_then(|value| value.first())
+        .is_none_or(|flag| flag & 1 == 0)
+}
+
+/// The `StartupApproved` value Task Manager writes: the flag, three zero bytes, and when the entry
+/// was disabled (a FILETIME, zero while enabled)
+fn approved_value(enabled: bool, now: u64) -> Vec<u8> {
+    let (flag, disabled_at) = if enabled {
+        (APPROVED_ENABLED, 0)
+    } else {
+        (APPROVED_DISABLED, now)
+    };
+    let mut value = vec![flag, 0, 0, 0];
+    value.extend_from_slice(&disabled_at.to_le_bytes());
+    value
+}
+
+fn to_filetime<Tz: TimeZone>(time: &DateTime<Tz>) -> u64 {
+    (time.timestamp_micros().max(0) as u64) * 10 + FILETIME_UNIX_EPOCH
+}
+
+fn filetime_ticks(time: &FILETIME) -> u64 {
+    (u64::from(time.dwHighDateTime) << 32) | u64::from(time.dwLowDateTime)
+}
+
+/// Task Manager's rating of the measured usage
+fn classify(usage: Option<Usage>) -> StartupImpact {
+    match usage {
+        None => StartupImpact::NotMeasured,
+        Some(usage) if usage.cpu_ms > HIGH_CPU_MS || usage.io_bytes > HIGH_IO_BYTES => {
+            StartupImpact::High
+        }
+        Some(usage) if usage.cpu_ms >= MEDIUM_CPU_MS || usage.io_bytes >= MEDIUM_IO_BYTES => {
+            StartupImpact::Medium
+        }
+        Some(_) => StartupImpact::Low,
+    }
+}
+
+/// The usage of `program`'s processes: matched by full path, or by file name when the entry only
+/// names the program (e.g. `rundll32.exe`)
+fn usage_of(program: &str, usage: &HashMap<String, Usage>) -> Option<Usage> {
+    let program = program.to_lowercase();
+    if program.contains('\\') {
+        return usage.get(&program).copied();
+    }
+    usage
+        .iter()
+        .filter(|(image, _)| image.rsplit('\\').next() == Some(program.as_str()))
+        .map(|(_, usage)| *usage)
+        .reduce(|a, b| Usage {
+            cpu_ms: a.cpu_ms + b.cpu_ms,
+            io_bytes: a.io_bytes + b.io_bytes,
+        })
+}
+
+/// Image path (lowercase), start time (FILETIME), and usage of a process, if it can be opened
+fn process_usage(pid: u32) -> Option<(String, u64, Usage)> {
+    // SAFETY: the handle is checked and closed below; every out pointer is a live local, and the
+    // image buffer's length is passed in `len`.
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, FALSE, pid);
+        if process.is_null() {
+            return None;
+        }
+
+        let mut image = [0u16; 1024];
+        let mut len = image.len() as u32;
+        let mut created = FILETIME::default();
+        let mut exited = FILETIME::default();
+        let mut kernel = FILETIME::default();
+        let mut user = FILETIME::default();
+        let mut io: IO_COUNTERS = std::mem::zeroed();
+        let ok =
+            QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, image.as_mut_ptr(), &mut len)
+                != FALSE
+                && GetProcessTimes(process, &mut created, &mut exited, &mut kernel, &mut user)
+                    != FALSE
+                && GetProcessIoCounters(process, &mut io) != FALSE;
+        CloseHandle(process);
+        if !ok {
+            return None;
+        }
+
+        Some((
+            String::from_utf16_lossy(&image[..len as usize]).to_lowercase(),
+            filetime_ticks(&created),
+            Usage {
+                cpu_ms: (filetime_ticks(&kernel) + filetime_ticks(&user)) / FILETIME_TICKS_PER_MS,
+                io_bytes: io.ReadTransferCount + io.WriteTransferCount,
+            },
+        ))
+    }
+}
+
+/// Usage of the processes that started within `STARTUP_WINDOW_MINUTES` of boot, by image path
+/// (lowercase). Processes the app can't open (protected ones) are left out.
+fn startup_process_usage() -> Result<HashMap<String, Usage>, Error> {
+    let window_end = to_filetime(&system_info_service::boot_time())
+        + STARTUP_WINDOW_MINUTES * 60 * 1000 * FILETIME_TICKS_PER_MS;
+    let mut usage: HashMap<String, Usage> = HashMap::new();
+
+    // SAFETY: ToolHelp enumeration over a snapshot handle that is closed after the loop.
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0);
+        if snapshot == INVALID_HANDLE_VALUE {
+            return Err(Error::WindowsApi(format!(
+                "CreateToolhelp32Snapshot failed: {}",
+                io::Error::last_os_error()
+            )));
+        }
+
+        let mut entry: PROCESSENTRY32W = std::mem::zeroed();
+        entry.dwSize = std::mem::size_of::<PROCESSENTRY32W>() as u32;
+        let mut more = Process32FirstW(snapshot, &mut entry) != FALSE;
+        while more {
+            if let Some((image, started, process)) = process_usage(entry.th32ProcessID) {
+                if started <= window_end {
+                    let total = usage.entry(image).or_default();
+                    total.cpu_ms += process.cpu_ms;
+                    total.io_bytes += process.io_bytes;
+                }
+            }
+            more = Process32NextW(snapshot, &mut entry) != FALSE;
+        }
+        CloseHandle(snapshot);
+    }
+    Ok(usage)
+}
+
+/// The target and arguments of a shortcut
+fn shortcut_target(path: &Path) -> Result<(String, String), Error> {
+    let com_err = |e: windows::core::Error| {
+        Error::WindowsApi(format!("Failed to read shortcut {}: {}", path.display(), e))
+    };
+    // SAFETY: COM is initialized once per thread, as in scheduler_service; both buffers' lengths
+    // are passed with them, and the link is released when dropped.
+    unsafe {
+        COM_READY.with(|ready| {
+            if !ready.get() {
+                let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+                ready.set(true);
+            }
+        });
+        let link: IShellLinkW =
+            CoCreateInstance(&ShellLink, None, CLSCTX_INPROC_SERVER).map_err(com_err)?;
+        link.cast::<IPersistFile>()
+            .and_then(|file| file.Load(&HSTRING::from(path), STGM_READ))
+            .map_err(com_err)?;
+
+        let mut target = [0u16; 1024];
+        let mut arguments = [0u16; 1024];
+        link.GetPath(&mut target, std::ptr::null_mut(), 0)
+            .map_err(com_err)?;
+        link.GetArguments(&mut arguments).map_err(com_err)?;
+        let text = |buffer: &[u16]| {
+            let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+            String::from_utf16_lossy(&buffer[..len])
+        };
+        Ok((text(&target), text(&arguments)))
+    }
+}
+
+/// The entries of one `Run` key. A key that doesn't exist has none.
+fn run_entries(hive: HKEY, path: &str, source: StartupSource) -> Result<Vec<Entry>, Error> {
+    let run = match RegKey::predef(hive).open_subkey_with_flags(path, KEY_READ) {
+        Ok(key) => key,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(registry_err("open", path, e)),
+    };
+    Ok(run
+        .enum_values()
+        .filter_map(Result::ok)
+        .filter(|(name, _)| !name.is_empty())
+        .filter_map(|(name, _)| {
+            let command: String = run.get_value(&name).ok()?;
+            Some(Entry {
+                source,
+                key: name.clone(),
+                name,
+                program: command_program(&command),
+                command: Some(command),
+                enabled: None,
+            })
+        })
+        .collect())
+}
+
+/// The entries of a Startup folder. A folder that doesn't exist has none.
+fn folder_entries(folder: Option<PathBuf>, source: StartupSource) -> Vec<Entry> {
+    let Some(files) = folder.and_then(|folder| fs::read_dir(folder).ok()) else {
+        return Vec::new();
+    };
+    files
+        .filter_map(Result::ok)
+        .filter(|file| file.file_type().is_ok_and(|kind| kind.is_file()))
+        .filter_map(|file| {
+            let key = file.file_name().to_string_lossy().into_owned();
+            if key.eq_ignore_ascii_case("desktop.ini") {
+                return None;
+            }
+            let path = file.path();
+            let name = path.file_stem()?.to_string_lossy().into_owned();
+            let is_shortcut = path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("lnk"));
+            let (command, program) = if is_shortcut {
+                match shortcut_target(&path) {
+                    Ok((target, arguments)) if arguments.is_empty() => {
+                        (Some(format!("\"{}\"", target)), Some(target))
+                    }
+                    Ok((target, arguments)) => {
+                        (Some(format!("\"{}\" {}", target, arguments)), Some(target))
+                    }
+                    Err(e) => {
+                        log::debug!("{}", e);
+                        (None, None)
+                    }
+                }
+            } else {
+                let path = path.to_string_lossy().into_owned();
+                (Some(format!("\"{}\"", path)), Some(path))
+            };
+            Some(Entry {
+                source,
+                key,
+                name,
+                command,
+                program: program.filter(|program| !program.is_empty()),
+                enabled: None,
+            })
+        })
+        .collect()
+}
+
+/// The logon tasks as entries, keyed by their full path
+fn task_entries() -> Result<Vec<Entry>, Error> {
+    Ok(scheduler_service::list_logon_tasks()?
+        .into_iter()
+        .map(|task| {
+            let program = task
+                .program
+                .as_deref()
+                .map(|program| expand_env(program.trim_matches('"')));
+            let command = task.program.map(|program| match &task.arguments {
+                Some(arguments) => format!("\"{}\" {}", program, arguments),
+                None => format!("\"{}\"", program),
+            });
+            Entry {
+                source: StartupSource::LogonTask,
+                key: task_key(&task.path, &task.name),
+                name: task.name,
+                command,
+                program,
+                enabled: Some(task.enabled),
+            }
+        })
+        .collect())
+}
+
+fn task_key(folder: &str, name: &str) -> String {
+    format!("{}\\{}", folder.trim_end_matches('\\'), name)
+}
+
+/// Folder and name of the task at `key`
+fn split_task_key(key: &str) -> Result<(&str, &str), Error> {
+    match key.rsplit_once('\\') {
+        Some(("", name)) if !name.is_empty() => Ok(("\\", name)),
+        Some((folder, name)) if folder.starts_with('\\') && !name.is_empty() => Ok((folder, name)),
+        _ => Err(Error::ValidationError(format!(
+            "Invalid task path '{}'",
+            key
+        ))),
+    }
+}
+
+/// Source and key of the entry with `id`
+fn parse_id(id: &str) -> Result<(StartupSource, &str), Error> {
+    id.split_once(':')
+        .and_then(|(source, key)| {
+            let source = StartupSource::ALL
+                .into_iter()
+                .find(|candidate| candidate.as_str() == source)?;
+            Some((source, key)).filter(|(_, key)| !key.is_empty())
+        })
+        .ok_or_else(|| Error::ValidationError(format!("Invalid startup entry ID '{}'", id)))
+}
+
+fn entries(source: StartupSource) -> Result<Vec<Entry>, Error> {
+    match source {
+        StartupSource::RunMachine => run_entries(HKEY_LOCAL_MACHINE, RUN_KEY, source),
+        StartupSource::RunMachine32 => run_entries(HKEY_LOCAL_MACHINE, RUN_KEY_WOW64, source),
+        StartupSource::RunUser => run_entries(HKEY_CURRENT_USER, RUN_KEY, source),
+        StartupSource::StartupFolderMachine => Ok(folder_entries(
+            env_dir("ProgramData").map(|dir| dir.join(COMMON_STARTUP_FOLDER)),
+            source,
+        )),
+        StartupSource::StartupFolderUser => Ok(folder_entries(
+            env_dir("APPDATA").map(|dir| dir.join(USER_STARTUP_FOLDER)),
+            source,
+        )),
+        StartupSource::LogonTask => task_entries(),
+    }
+}
+
+/// The `StartupApproved` value named `name`, None if there isn't one
+fn read_approved(hive: HKEY, path: &str, name: &str) -> Result<Option<Vec<u8>>, Error> {
+    let key = match RegKey::predef(hive).open_subkey_with_flags(path, KEY_READ) {
+        Ok(key) => key,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(registry_err("open", path, e)),
+    };
+    match key.get_raw_value(name) {
+        Ok(value) => Ok(Some(value.bytes)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(registry_err("read", path, e)),
+    }
+}
+
+/// Set the `StartupApproved` value named `name`, or delete it for None
+fn write_approved(hive: HKEY, path: &str, name: &str, value: Option<&[u8]>) -> Result<(), Error> {
+    let (key, _) = RegKey::predef(hive)
+        .create_subkey_with_flags(path, KEY_READ | KEY_WRITE)
+        .map_err(|e| registry_err("open", path, e))?;
+    match value {
+        Some(bytes) => key
+            .set_raw_value(
+                name,
+                &RegValue {
+                    bytes: bytes.to_vec(),
+                    vtype: REG_BINARY,
+                },
+            )
+            .map_err(|e| registry_err("write", path, e)),
+        None => match key.delete_value(name) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(registry_err("write", path, e)),
+            _ => Ok(()),
+        },
+    }
+}
+
+/// Every startup entry, sorted by name. Logon tasks are left out (and logged) if Task Scheduler
+/// can't be queried, and impact is left unmeasured if the processes can't be listed.
+pub fn get_startup_items() -> Result<Vec<StartupItem>, Error> {
+    let mut found = Vec::new();
+    for source in StartupSource::ALL {
+        match entries(source) {
+            Ok(entries) => found.extend(entries),
+            Err(e) if source == StartupSource::LogonTask => {
+                log::warn!("Failed to list logon tasks: {}", e)
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    let usage = startup_process_usage()
+        .map_err(|e| log::warn!("Failed to measure startup impact: {}", e))
+        .unwrap_or_default();
+
+    let mut items = found
+        .into_iter()
+        .map(|entry| {
+            let enabled = match (entry.enabled, entry.source.approved_key()) {
+                (Some(enabled), _) => enabled,
+                (None, Some((hive, path))) => {
+                    approved_enabled(read_approved(hive, path, &entry.key)?.as_deref())
+                }
+                (None, None) => true,
+            };
+            let measured = entry
+                .program
+                .as_deref()
+                .and_then(|program| usage_of(program, &usage));
+            Ok(StartupItem {
+                id: format!("{}:{}", entry.source.as_str(), entry.key),
+                name: entry.name,
+                source: entry.source,
+                command: entry.command,
+                enabled,
+                impact: classify(measured),
+                cpu_ms: measured.map(|usage| usage.cpu_ms),
+                io_bytes: measured.map(|usage| usage.io_bytes),
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    items.sort_by_cached_key(|item| (item.name.to_lowercase(), item.id.clone()));
+    log::debug!("Found {} startup entries", items.len());
+    Ok(items)
+}
+
+/// The entry's current state
+fn current_state(source: StartupSource, key: &str) -> Result<PriorState, Error> {
+    match source.approved_key() {
+        Some((hive, path)) => Ok(PriorState::Approved {
+            value: read_approved(hive, path, key)?,
+        }),
+        None => {
+            let (folder, name) = split_task_key(key)?;
+            match scheduler_service::get_task_state(folder, name)? {
+                scheduler_service::TaskState::NotFound => Err(Error::NotFound(format!(
+                    "Scheduled task '{}' not found",
+                    key
+                ))),
+                state => Ok(PriorState::Task {
+                    enabled: state != scheduler_service::TaskState::Disabled,
+                }),
+            }
+        }
+    }
+}
+
+/// Put the entry in `state`
+fn restore_state(source: StartupSource, key: &str, state: &PriorState) -> Result<(), Error> {
+    match (source.approved_key(), state) {
+        (Some((hive, path)), PriorState::Approved { value }) => {
+            write_approved(hive, path, key, value.as_deref())
+        }
+        (None, PriorState::Task { enabled }) => set_task_enabled(key, *enabled),
+        _ => Err(Error::ValidationError(format!(
+            "Recorded state doesn't fit startup entry '{}'",
+            key
+        ))),
+    }
+}
+
+fn set_task_enabled(key: &str, enabled: bool) -> Result<(), Error> {
+    let (folder, name) = split_task_key(key)?;
+    if enabled {
+        scheduler_service::enable_task(folder, name)
+    } else {
+        scheduler_service::disable_task(folder, name)
+    }
+}
+
+fn changes_path() -> Result<PathBuf, Error> {
+    backup_service::program_data_file(CHANGES_FILE)
+}
+
+fn read_changes() -> Result<Vec<StartupChange>, Error> {
+    let path = changes_path()?;
+    match fs::read(&path) {
+        Ok(content) => serde_json::from_slice(&content).map_err(|e| {
+            Error::BackupFailed(format!(
+                "Corrupt startup change log {}: {}",
+                path.display(),
+                e
+            ))
+        }),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(Error::BackupFailed(format!(
+            "Failed to read the startup change log: {}",
+            e
+        ))),
+    }
+}
+
+fn write_changes(changes: &[StartupChange]) -> Result<(), Error> {
+    let path = changes_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| {
+            Error::BackupFailed(format!("Failed to create {}: {}", dir.display(), e))
+        })?;
+    }
+    let json = serde_json::to_string_pretty(changes).map_err(|e| {
+        Error::BackupFailed(format!("Failed to serialize the startup change log: {}", e))
+    })?;
+    fs::write(&path, json)
+        .map_err(|e| Error::BackupFailed(format!("Failed to write {}: {}", path.display(), e)))
+}
+
+/// Whether an entry with `key` exists in `source`
+fn entry_exists(source: StartupSource, key: &str) -> Result<bool, Error> {
+    Ok(entries(source)?
+        .iter()
+        .any(|entry| entry.key.eq_ignore_ascii_case(key)))
+}
+
+/// Enable or disable the entry with `id`, recording its prior state for `undo_last_change`. If
+/// the change can't be recorded it's put back, so every change can be undone.
+pub fn set_enabled(id: &str, enabled: bool) -> Result<(), Error> {
+    let (source, key) = parse_id(id)?;
+    if source != StartupSource::LogonTask && !entry_exists(source, key)? {
+        return Err(Error::NotFound(format!("Startup entry '{}' not found", id)));
+    }
+
+    let _guard = CHANGES_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut changes = read_changes()?;
+    let prior = current_state(source, key)?;
+    match source.approved_key() {
+        Some((hive, path)) => write_approved(
+            hive,
+            path,
+            key,
+            Some(&approved_value(enabled, to_filetime(&Utc::now()))),
+        )?,
+        None => set_task_enabled(key, enabled)?,
+    }
+
+    changes.push(StartupChange {
+        id: id.to_string(),
+        enabled,
+        changed_at: chrono::Local::now().to_rfc3339(),
+        prior: prior.clone(),
+    });
+    let excess = changes.len().saturating_sub(MAX_CHANGES);
+    changes.drain(..excess);
+    if let Err(e) = write_changes(&changes) {
+        if let Err(restore) = restore_state(source, key, &prior) {
+            log::warn!("Failed to put back startup entry '{}': {}", id, restore);
+        }
+        return Err(e);
+    }
+    log::info!(
+        "{} startup entry '{}'",
+        if enabled { "Enabled" } else { "Disabled" },
+        id
+    );
+    Ok(())
+}
+
+/// The recorded changes, newest first
+pub fn list_changes() -> Result<Vec<StartupChange>, Error> {
+    let mut changes = read_changes()?;
+    changes.reverse();
+    Ok(changes)
+}
+
+/// Put back the entry the most recent change changed. Returns that change, or None if there's
+/// nothing to undo.
+pub fn undo_last_change() -> Result<Option<StartupChange>, Error> {
+    let _guard = CHANGES_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut changes = read_changes()?;
+    let Some(change) = changes.pop() else {
+        return Ok(None);
+    };
+    let (source, key) = parse_id(&change.id)?;
+    restore_state(source, key, &change.prior)?;
+    write_changes(&changes)?;
+    log::info!("Undid the change to startup entry '{}'", change.id);
+    Ok(Some(change))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn startup_approved_values_follow_task_manager() {
+        assert!(approved_enabled(None));
+        assert!(approved_enabled(Some(&[0x02, 0, 0, 0])));
+        assert!(approved_enabled(Some(&[0x06, 0, 0, 0])));
+        assert!(!approved_enabled(Some(&[0x03, 0, 0, 0])));
+        assert!(!approved_enabled(Some(&[0x07, 0, 0, 0])));
+
+        let disabled = approved_value(false, 0x01DA_0000_0000_0001);
+        assert_eq!(disabled.len(), 12);
+        assert!(!approved_enabled(Some(&disabled)));
+        assert_eq!(disabled[4..], 0x01DA_0000_0000_0001u64.to_le_bytes());
+        assert_eq!(
+            approved_value(true, 42),
+            [0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn command_lines_name_their_program() {
+        assert_eq!(
+            command_program("\"C:\\Program Files\\App\\app.exe\" --minimized").as_deref(),
+            Some("C:\\Program Files\\App\\app.exe")
+        );
+        assert_eq!(
+            command_program("C:\\Program Files\\App\\app.exe /background").as_deref(),
+            Some("C:\\Program Files\\App\\app.exe")
+        );
+        assert_eq!(
+            command_program("rundll32 shell32.dll,Control_RunDLL").as_deref(),
+            Some("rundll32")
+        );
+        assert_eq!(
+            command_program("%NO_SUCH_VARIABLE%\\tool.exe").as_deref(),
+            Some("%NO_SUCH_VARIABLE%\\tool.exe")
+        );
+        assert_eq!(command_program("  "), None);
+    }
+
+    #[test]
+    fn impact_uses_task_manager_thresholds() {
+        let rate = |cpu_ms, io_bytes| classify(Some(Usage { cpu_ms, io_bytes }));
+        assert_eq!(rate(1001, 0), StartupImpact::High);
+        assert_eq!(rate(0, HIGH_IO_BYTES + 1), StartupImpact::High);
+        assert_eq!(rate(300, 0), StartupImpact::Medium);
+        assert_eq!(rate(0, MEDIUM_IO_BYTES), StartupImpact::Medium);
+        assert_eq!(rate(299, MEDIUM_IO_BYTES - 1), StartupImpact::Low);
+        assert_eq!(classify(None), StartupImpact::NotMeasured);
+    }
+
+    #[test]
+    fn ids_and_task_paths_round_trip() {
+        let (source, key) = parse_id("logon_task:\\Vendor\\Updater").unwrap();
+        assert_eq!(source, StartupSource::LogonTask);
+        assert_eq!(split_task_key(key).unwrap(), ("\\Vendor", "Updater"));
+        assert_eq!(task_key("\\", "Sync"), "\\Sync");
+        assert_eq!(split_task_key("\\Sync").unwrap(), ("\\", "Sync"));
+
+        assert_eq!(
+            parse_id("run_user:OneDrive").unwrap(),
+            (StartupSource::RunUser, "OneDrive")
+        );
+        assert!(parse_id("run_user:").is_err());
+        assert!(parse_id("registry:OneDrive").is_err());
+        assert!(split_task_key("Updater").is_err());
+    }
+}
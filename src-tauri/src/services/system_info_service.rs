@@ -1,10 +1,13 @@
 use crate::error::Error;
 use crate::models::{
-    CpuInfo, DeviceInfo, DiskInfo, GpuInfo, HardwareInfo, MemoryInfo, MotherboardInfo, SystemInfo,
-    WindowsInfo,
+    CpuInfo, DeviceInfo, DiskInfo, GpuInfo, HardwareInfo, HardwareSection, MemoryInfo, MonitorInfo,
+    MotherboardInfo, NetworkInfo, NetworkMedium, PowerInfo, SensorKind, SystemFacts, SystemInfo,
+    ThermalInfo, ThermalSensor, ThermalSource, WifiInfo, WindowsBuild, WindowsEdition, WindowsInfo,
 };
+use crate::services::{power_service, windows_update_service};
 use serde::Deserialize;
 use std::env;
+use std::sync::OnceLock;
 use winreg::enums::*;
 use winreg::RegKey;
 use wmi::WMIConnection;
@@ -267,7 +270,7 @@ fn is_leap_year(year: i32) -> bool {
 }
 
 /// Parse WMI datetime to ISO 8601 format
-fn parse_wmi_datetime_to_iso(wmi_datetime: &str) -> String {
+pub(crate) fn parse_wmi_datetime_to_iso(wmi_datetime: &str) -> String {
     if wmi_datetime.len() < 14 {
         return wmi_datetime.to_string();
     }
@@ -282,79 +285,153 @@ fn parse_wmi_datetime_to_iso(wmi_datetime: &str) -> String {
     )
 }
 
-/// Get hardware information using WMI queries (parallelized with connection reuse)
-/// Uses 3 threads instead of 7 to reduce COM initialization overhead:
-/// - Thread 1: Fast cimv2 queries (CPU, Memory, Motherboard, Network) - same connection
+/// Hardware info gathered by [`gather_hardware`]; None for the sections that weren't asked for
+/// or couldn't be queried
+#[derive(Debug, Default)]
+pub struct HardwareUpdate {
+    cpu: Option<CpuInfo>,
+    gpu: Option<Vec<GpuInfo>>,
+    monitors: Option<Vec<MonitorInfo>>,
+    memory: Option<MemoryInfo>,
+    motherboard: Option<MotherboardInfo>,
+    disks: Option<Vec<DiskInfo>>,
+    network: Option<Vec<NetworkInfo>>,
+    device: Option<DeviceInfo>,
+}
+
+impl HardwareUpdate {
+    /// Replace the sections this update holds in `hardware` and `device`, returning them
+    pub fn apply(
+        self,
+        hardware: &mut HardwareInfo,
+        device: &mut DeviceInfo,
+    ) -> Vec<HardwareSection> {
+        let mut applied = Vec::new();
+        if let Some(cpu) = self.cpu {
+            hardware.cpu = cpu;
+            applied.push(HardwareSection::Cpu);
+        }
+        if let Some(gpu) = self.gpu {
+            hardware.gpu = gpu;
+            applied.push(HardwareSection::Gpu);
+        }
+        if let Some(monitors) = self.monitors {
+            hardware.monitors = monitors;
+            applied.push(HardwareSection::Monitors);
+        }
+        if let Some(memory) = self.memory {
+            hardware.memory = memory;
+            applied.push(HardwareSection::Memory);
+        }
+        if let Some(motherboard) = self.motherboard {
+            hardware.motherboard = motherboard;
+            applied.push(HardwareSection::Motherboard);
+        }
+        if let Some(disks) = self.disks {
+            hardware.total_storage_gb = disks.iter().map(|d| d.size_gb).sum();
+            hardware.disks = disks;
+            applied.push(HardwareSection::Disks);
+        }
+        if let Some(network) = self.network {
+            hardware.network = network;
+            applied.push(HardwareSection::Network);
+        }
+        if let Some(info) = self.device {
+            *device = info;
+            applied.push(HardwareSection::Device);
+        }
+        applied
+    }
+}
+
+/// Gather the given hardware sections using WMI queries (parallelized with connection reuse)
+/// Uses up to 3 threads, each started only if one of its sections is asked for:
+/// - Thread 1: Fast cimv2 queries (CPU, Memory, Motherboard, Network, Device) - same connection
 /// - Thread 2: Slow cimv2 queries (GPU, Monitors) - WinAPI/registry intensive
 /// - Thread 3: Storage namespace queries (Disks) - different WMI namespace
-fn get_hardware_info() -> HardwareInfo {
-    log::debug!("Gathering hardware information via WMI (3-thread hybrid)");
+pub fn gather_hardware(sections: &[HardwareSection]) -> HardwareUpdate {
+    log::debug!(
+        "Gathering hardware information via WMI (3-thread hybrid): {:?}",
+        sections
+    );
 
     use std::thread;
+    use HardwareSection::*;
     let start = std::time::Instant::now();
+    let wants = |section: HardwareSection| sections.contains(&section);
 
     // Run WMI queries in parallel using scoped threads with connection reuse
-    let (cpu, memory, motherboard, network, gpu, monitors, disks) = thread::scope(|s| {
-        // Thread 1: Fast cimv2 queries - reuse single connection for 4 queries
-        let fast_cimv2_handle = s.spawn(|| match WMIConnection::new() {
-            Ok(con) => (
-                get_cpu_info(&con),
-                get_memory_info(&con),
-                get_motherboard_info(&con),
-                get_network_info(&con),
-            ),
-            Err(e) => {
-                log::debug!("WMI connection failed for fast cimv2 queries: {}", e);
-                (
-                    Default::default(),
-                    Default::default(),
-                    Default::default(),
-                    Default::default(),
-                )
-            }
-        });
+    let update = thread::scope(|s| {
+        // Thread 1: Fast cimv2 queries - reuse single connection for up to 5 queries
+        let fast_cimv2_handle = [Cpu, Memory, Motherboard, Network, Device]
+            .into_iter()
+            .any(wants)
+            .then(|| {
+                s.spawn(|| match WMIConnection::new() {
+                    Ok(con) => (
+                        wants(Cpu).then(|| get_cpu_info(&con)),
+                        wants(Memory).then(|| get_memory_info(&con)),
+                        wants(Motherboard).then(|| get_motherboard_info(&con)),
+                        wants(Network).then(|| get_network_info(&con)),
+                        wants(Device).then(|| get_device_info(&con)),
+                    ),
+                    Err(e) => {
+                        log::debug!("WMI connection failed for fast cimv2 queries: {}", e);
+                        Default::default()
+                    }
+                })
+            });
 
         // Thread 2: Slow cimv2 queries (GPU + Monitors) - WinAPI/registry heavy
-        let slow_cimv2_handle = s.spawn(|| match WMIConnection::new() {
-            Ok(con) => (get_gpu_info(&con), get_monitor_info(&con)),
-            Err(e) => {
-                log::debug!("WMI connection failed for slow cimv2 queries: {}", e);
-                (Default::default(), Default::default())
-            }
+        let slow_cimv2_handle = [Gpu, Monitors].into_iter().any(wants).then(|| {
+            s.spawn(|| match WMIConnection::new() {
+                Ok(con) => (
+                    wants(Gpu).then(|| get_gpu_info(&con)),
+                    wants(Monitors).then(|| get_monitor_info(&con)),
+                ),
+                Err(e) => {
+                    log::debug!("WMI connection failed for slow cimv2 queries: {}", e);
+                    Default::default()
+                }
+            })
         });
 
         // Thread 3: Storage namespace - uses different WMI namespace internally
-        let storage_handle = s.spawn(|| match WMIConnection::new() {
-            Ok(con) => get_disk_info(&con),
-            Err(e) => {
-                log::debug!("WMI connection failed for disk info: {}", e);
-                Default::default()
-            }
+        let storage_handle = wants(Disks).then(|| {
+            s.spawn(|| match WMIConnection::new() {
+                Ok(con) => Some(get_disk_info(&con)),
+                Err(e) => {
+                    log::debug!("WMI connection failed for disk info: {}", e);
+                    None
+                }
+            })
         });
 
         // Wait for all threads to complete
-        let (cpu, memory, motherboard, network) = fast_cimv2_handle.join().unwrap_or_default();
-        let (gpu, monitors) = slow_cimv2_handle.join().unwrap_or_default();
-        let disks = storage_handle.join().unwrap_or_default();
-
-        (cpu, memory, motherboard, network, gpu, monitors, disks)
+        let (cpu, memory, motherboard, network, device) = fast_cimv2_handle
+            .and_then(|handle| handle.join().ok())
+            .unwrap_or_default();
+        let (gpu, monitors) = slow_cimv2_handle
+            .and_then(|handle| handle.join().ok())
+            .unwrap_or_default();
+        let disks = storage_handle
+            .and_then(|handle| handle.join().ok())
+            .flatten();
+
+        HardwareUpdate {
+            cpu,
+            gpu,
+            monitors,
+            memory,
+            motherboard,
+            disks,
+            network,
+            device,
+        }
     });
 
     log::debug!("Hardware info gathered in {:?}", start.elapsed());
-
-    // Calculate total storage
-    let total_storage_gb: f64 = disks.iter().map(|d| d.size_gb).sum();
-
-    HardwareInfo {
-        cpu,
-        gpu,
-        monitors,
-        memory,
-        motherboard,
-        disks,
-        network,
-        total_storage_gb,
-    }
+    update
 }
 
 use std::mem::size_of;
@@ -381,7 +458,7 @@ struct Win32PnPEntity {
 }
 
 /// Get monitor information using WinAPI (EnumDisplayDevices/EnumDisplaySettings) + WMI
-fn get_monitor_info(_wmi_con: &WMIConnection) -> Vec<crate::models::MonitorInfo> {
+fn get_monitor_info(_wmi_con: &WMIConnection) -> Vec<MonitorInfo> {
     log::debug!("Gathering monitor info via Nested EnumDisplayDevices + WMI + PnP");
 
     let monitor_names = get_all_monitor_names();
@@ -505,7 +582,7 @@ fn get_monitor_info(_wmi_con: &WMIConnection) -> Vec<crate::models::MonitorInfo>
                         }
 
                         log::debug!("  -> Added: {} - {} @ {}Hz", name, resolution, refresh_rate);
-                        monitors.push(crate::models::MonitorInfo {
+                        monitors.push(MonitorInfo {
                             name: name.clone(),
                             resolution: resolution.clone(),
                             refresh_rate,
@@ -573,6 +650,28 @@ fn get_all_monitor_names() -> std::collections::HashMap<String, String> {
     map
 }
 
+use std::collections::HashMap;
+use windows_sys::Win32::Foundation::{ERROR_SUCCESS, HANDLE};
+use windows_sys::Win32::NetworkManagement::WiFi::{
+    wlan_interface_state_connected, wlan_intf_opcode_current_connection, WlanCloseHandle,
+    WlanEnumInterfaces, WlanFreeMemory, WlanOpenHandle, WlanQueryInterface,
+    WLAN_CONNECTION_ATTRIBUTES, WLAN_INTERFACE_INFO_LIST,
+};
+
+/// WLAN client version for Windows Vista and later
+const WLAN_CLIENT_VERSION: u32 = 2;
+
+// `NDIS_PHYSICAL_MEDIUM` values (ntddndis.h)
+const NDIS_MEDIUM_UNSPECIFIED: u32 = 0;
+const NDIS_MEDIUM_WIRELESS_LAN: u32 = 1;
+const NDIS_MEDIUM_WIRELESS_WAN: u32 = 8;
+const NDIS_MEDIUM_NATIVE_802_11: u32 = 9;
+const NDIS_MEDIUM_BLUETOOTH: u32 = 10;
+const NDIS_MEDIUM_802_3: u32 = 14;
+
+/// `AddressFamily` of an IPv4 DNS server entry
+const AF_INET: u16 = 2;
+
 #[derive(Deserialize, Debug)]
 #[serde(rename = "Win32_NetworkAdapterConfiguration")]
 #[serde(rename_all = "PascalCase")]
@@ -586,10 +685,135 @@ struct Win32NetworkAdapterConfiguration {
     ip_enabled: Option<bool>,
     #[serde(rename = "DHCPEnabled")]
     dhcp_enabled: Option<bool>,
+    #[serde(rename = "DNSServerSearchOrder")]
+    dns_server_search_order: Option<Vec<String>>,
+    interface_index: Option<u32>,
+    #[serde(rename = "SettingID")]
+    setting_id: Option<String>,
 }
 
-/// Get network information from WMI
-fn get_network_info(wmi_con: &WMIConnection) -> Vec<crate::models::NetworkInfo> {
+#[derive(Deserialize, Debug)]
+#[serde(rename = "MSFT_NetAdapter")]
+#[serde(rename_all = "PascalCase")]
+struct MsftNetAdapter {
+    interface_index: Option<u32>,
+    ndis_physical_medium: Option<u32>,
+    receive_link_speed: Option<u64>,
+    transmit_link_speed: Option<u64>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename = "MSFT_DNSClientServerAddress")]
+#[serde(rename_all = "PascalCase")]
+struct MsftDnsClientServerAddress {
+    interface_index: Option<u32>,
+    address_family: Option<u16>,
+    server_addresses: Option<Vec<String>>,
+}
+
+fn network_medium(ndis_medium: Option<u32>) -> NetworkMedium {
+    match ndis_medium {
+        None | Some(NDIS_MEDIUM_UNSPECIFIED) => NetworkMedium::Unknown,
+        Some(NDIS_MEDIUM_802_3) => NetworkMedium::Ethernet,
+        Some(NDIS_MEDIUM_WIRELESS_LAN | NDIS_MEDIUM_NATIVE_802_11) => NetworkMedium::Wifi,
+        Some(NDIS_MEDIUM_WIRELESS_WAN) => NetworkMedium::Cellular,
+        Some(NDIS_MEDIUM_BLUETOOTH) => NetworkMedium::Bluetooth,
+        Some(_) => NetworkMedium::Other,
+    }
+}
+
+/// The faster of the adapter's receive and transmit link speeds in Mbps, None without a link
+fn link_speed_mbps(adapter: &MsftNetAdapter) -> Option<u64> {
+    let bps = adapter
+        .receive_link_speed
+        .unwrap_or(0)
+        .max(adapter.transmit_link_speed.unwrap_or(0));
+    (bps > 0).then_some(bps / 1_000_000)
+}
+
+/// DNS servers by interface index, IPv4 before IPv6, each family in the order it's tried
+fn dns_servers_by_interface(
+    mut entries: Vec<MsftDnsClientServerAddress>,
+) -> HashMap<u32, Vec<String>> {
+    entries.sort_by_key(|entry| entry.address_family != Some(AF_INET));
+    let mut servers: HashMap<u32, Vec<String>> = HashMap::new();
+    for entry in entries {
+        if let (Some(index), Some(addresses)) = (entry.interface_index, entry.server_addresses) {
+            servers.entry(index).or_default().extend(addresses);
+        }
+    }
+    servers
+}
+
+/// The network each connected Wi-Fi adapter is on, by interface GUID (as `power_service`
+/// formats it). Empty if the WLAN service isn't running; on Windows 11 24H2 and later, also while
+/// location access is off, since Windows then withholds the connection from desktop apps.
+fn wifi_connections() -> HashMap<String, WifiInfo> {
+    let mut connections = HashMap::new();
+    let mut client: HANDLE = std::ptr::null_mut();
+    let mut negotiated = 0u32;
+
+    // SAFETY: the client handle and every buffer WLAN returns are freed below; the interface list
+    // holds `dwNumberOfItems` entries, and a current-connection query returns a
+    // WLAN_CONNECTION_ATTRIBUTES.
+    unsafe {
+        if WlanOpenHandle(
+            WLAN_CLIENT_VERSION,
+            std::ptr::null(),
+            &mut negotiated,
+            &mut client,
+        ) != ERROR_SUCCESS
+        {
+            return connections;
+        }
+
+        let mut list: *mut WLAN_INTERFACE_INFO_LIST = std::ptr::null_mut();
+        if WlanEnumInterfaces(client, std::ptr::null(), &mut list) == ERROR_SUCCESS {
+            let interfaces = std::slice::from_raw_parts(
+                (*list).InterfaceInfo.as_ptr(),
+                (*list).dwNumberOfItems as usize,
+            );
+            for interface in interfaces
+                .iter()
+                .filter(|interface| interface.isState == wlan_interface_state_connected)
+            {
+                let mut size = 0u32;
+                let mut data: *mut std::ffi::c_void = std::ptr::null_mut();
+                if WlanQueryInterface(
+                    client,
+                    &interface.InterfaceGuid,
+                    wlan_intf_opcode_current_connection,
+                    std::ptr::null(),
+                    &mut size,
+                    &mut data,
+                    std::ptr::null_mut(),
+                ) != ERROR_SUCCESS
+                {
+                    continue;
+                }
+                let association =
+                    &(*(data as *const WLAN_CONNECTION_ATTRIBUTES)).wlanAssociationAttributes;
+                let ssid = &association.dot11Ssid;
+                let len = (ssid.uSSIDLength as usize).min(ssid.ucSSID.len());
+                connections.insert(
+                    power_service::format_guid(&interface.InterfaceGuid),
+                    WifiInfo {
+                        ssid: String::from_utf8_lossy(&ssid.ucSSID[..len]).into_owned(),
+                        signal_percent: association.wlanSignalQuality.min(100),
+                    },
+                );
+                WlanFreeMemory(data);
+            }
+            WlanFreeMemory(list as *const std::ffi::c_void);
+        }
+        WlanCloseHandle(client, std::ptr::null());
+    }
+    connections
+}
+
+/// Get network information from WMI: the adapters' addresses from cimv2, their medium, link speed,
+/// and DNS servers from the NetAdapter and DnsClient providers, and the Wi-Fi network from WLAN
+fn get_network_info(wmi_con: &WMIConnection) -> Vec<NetworkInfo> {
     let query: Vec<Win32NetworkAdapterConfiguration> = match wmi_con.query() {
         Ok(results) => results,
         Err(e) => {
@@ -598,10 +822,50 @@ fn get_network_info(wmi_con: &WMIConnection) -> Vec<crate::models::NetworkInfo>
         }
     };
 
+    let (net_adapters, dns_entries) =
+        match WMIConnection::with_namespace_path("root\\StandardCimv2") {
+            Ok(con) => (
+                con.query::<MsftNetAdapter>().unwrap_or_else(|e| {
+                    log::debug!("Failed to query MSFT_NetAdapter: {}", e);
+                    vec![]
+                }),
+                con.query::<MsftDnsClientServerAddress>()
+                    .unwrap_or_else(|e| {
+                        log::debug!("Failed to query MSFT_DNSClientServerAddress: {}", e);
+                        vec![]
+                    }),
+            ),
+            Err(e) => {
+                log::debug!("WMI connection failed for StandardCimv2: {}", e);
+                (vec![], vec![])
+            }
+        };
+    let net_adapters: HashMap<u32, MsftNetAdapter> = net_adapters
+        .into_iter()
+        .filter_map(|adapter| Some((adapter.interface_index?, adapter)))
+        .collect();
+    let mut dns_servers = dns_servers_by_interface(dns_entries);
+    let wifi_networks = wifi_connections();
+
     query
         .into_iter()
         .filter(|adapter| adapter.ip_enabled.unwrap_or(false))
         .map(|adapter| {
+            let net_adapter = adapter
+                .interface_index
+                .and_then(|index| net_adapters.get(&index));
+            // IPv4 only, if the DnsClient provider couldn't be queried
+            let dns = adapter
+                .interface_index
+                .and_then(|index| dns_servers.remove(&index))
+                .or(adapter.dns_server_search_order)
+                .unwrap_or_default();
+            let wifi = adapter
+                .setting_id
+                .as_deref()
+                .and_then(|id| power_service::normalize_guid(id).ok())
+                .and_then(|guid| wifi_networks.get(&guid).cloned());
+
             // Get first IPv4 address (usually the main one)
             let ip_address = adapter
                 .ip_address
@@ -610,13 +874,17 @@ fn get_network_info(wmi_con: &WMIConnection) -> Vec<crate::models::NetworkInfo>
                 .cloned()
                 .unwrap_or_else(|| "Unknown".to_string());
 
-            crate::models::NetworkInfo {
+            NetworkInfo {
                 name: adapter
                     .description
                     .unwrap_or_else(|| "Unknown Adapter".to_string()),
                 mac_address: adapter.mac_address.unwrap_or_else(|| "Unknown".to_string()),
                 ip_address,
                 dhcp_enabled: adapter.dhcp_enabled.unwrap_or(false),
+                medium: network_medium(net_adapter.and_then(|a| a.ndis_physical_medium)),
+                link_speed_mbps: net_adapter.and_then(link_speed_mbps),
+                dns_servers: dns,
+                wifi,
             }
         })
         .collect()
@@ -977,18 +1245,338 @@ fn get_device_info(wmi_con: &WMIConnection) -> DeviceInfo {
     }
 }
 
-/// Get full system information
-pub fn get_system_info() -> Result<SystemInfo, Error> {
+#[derive(Deserialize, Debug)]
+#[serde(rename = "BatteryStaticData")]
+#[serde(rename_all = "PascalCase")]
+struct BatteryStaticData {
+    designed_capacity: Option<u32>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename = "BatteryFullChargedCapacity")]
+#[serde(rename_all = "PascalCase")]
+struct BatteryFullChargedCapacity {
+    full_charged_capacity: Option<u32>,
+}
+
+use windows_sys::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+/// `SYSTEM_POWER_STATUS` values meaning "unknown"
+const POWER_STATUS_UNKNOWN: u8 = 255;
+/// `BatteryFlag` bits
+const BATTERY_FLAG_CHARGING: u8 = 8;
+const BATTERY_FLAG_NO_BATTERY: u8 = 128;
+
+fn get_power_status() -> Option<SYSTEM_POWER_STATUS> {
+    let mut status = SYSTEM_POWER_STATUS::default();
+    // SAFETY: `status` is a valid out-pointer for the call
+    (unsafe { GetSystemPowerStatus(&mut status) } != 0).then_some(status)
+}
+
+/// Battery presence, charge, and AC state from `GetSystemPowerStatus`
+fn power_info_from_status(status: &SYSTEM_POWER_STATUS) -> PowerInfo {
+    let has_battery = !matches!(
+        status.BatteryFlag,
+        BATTERY_FLAG_NO_BATTERY | POWER_STATUS_UNKNOWN
+    );
+    PowerInfo {
+        has_battery,
+        on_ac_power: match status.ACLineStatus {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        },
+        charging: has_battery && status.BatteryFlag & BATTERY_FLAG_CHARGING != 0,
+        charge_percent: (has_battery && status.BatteryLifePercent <= 100)
+            .then_some(status.BatteryLifePercent),
+        ..Default::default()
+    }
+}
+
+/// Full charge capacity as a percentage of the design capacity, to one decimal
+fn battery_health(design_mwh: u32, full_charge_mwh: u32) -> Option<f64> {
+    (design_mwh > 0)
+        .then(|| (f64::from(full_charge_mwh) / f64::from(design_mwh) * 1000.0).round() / 10.0)
+}
+
+/// Get battery status (GetSystemPowerStatus), battery capacities (root\wmi), and the active
+/// power plan
+fn get_power_info() -> PowerInfo {
+    let mut power = get_power_status()
+        .map(|status| power_info_from_status(&status))
+        .unwrap_or_default();
+
+    if power.has_battery {
+        if let Ok(battery_con) = WMIConnection::with_namespace_path("root\\wmi") {
+            // Summed over every battery; a class some firmware doesn't provide is left unknown
+            let static_data: Vec<BatteryStaticData> = battery_con.query().unwrap_or_default();
+            let full_charged: Vec<BatteryFullChargedCapacity> =
+                battery_con.query().unwrap_or_default();
+            power.design_capacity_mwh = static_data
+                .iter()
+                .filter_map(|battery| battery.designed_capacity)
+                .reduce(|a, b| a + b);
+            power.full_charge_capacity_mwh = full_charged
+                .iter()
+                .filter_map(|battery| battery.full_charged_capacity)
+                .reduce(|a, b| a + b);
+        }
+        if let (Some(design), Some(full)) =
+            (power.design_capacity_mwh, power.full_charge_capacity_mwh)
+        {
+            power.health_percent = battery_health(design, full);
+        }
+    }
+
+    match power_service::get_active_scheme() {
+        Ok(scheme) => {
+            power.active_plan_name = power_service::read_scheme_name(&scheme)
+                .map_err(|e| log::debug!("Failed to read the power plan name: {}", e))
+                .ok();
+            power.active_plan_guid = Some(scheme);
+        }
+        Err(e) => log::debug!("Failed to read the active power plan: {}", e),
+    }
+
+    log::debug!(
+        "Power info: battery={}, charge={:?}, health={:?}, plan={:?}",
+        power.has_battery,
+        power.charge_percent,
+        power.health_percent,
+        power.active_plan_name
+    );
+    power
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename = "Sensor")]
+#[serde(rename_all = "PascalCase")]
+struct HardwareMonitorSensor {
+    name: String,
+    sensor_type: String,
+    value: Option<f32>,
+    /// Identifier of the hardware the sensor is on
+    parent: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename = "Hardware")]
+#[serde(rename_all = "PascalCase")]
+struct HardwareMonitorHardware {
+    identifier: String,
+    name: String,
+    hardware_type: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename = "MSAcpi_ThermalZoneTemperature")]
+#[serde(rename_all = "PascalCase")]
+struct MsAcpiThermalZoneTemperature {
+    instance_name: String,
+    /// Tenths of a kelvin
+    current_temperature: Option<u32>,
+}
+
+/// Sensor sources, in the order they're tried: hardware monitors whose WMI provider is running,
+/// which read the CPU and GPU sensors, before ACPI thermal zones
+const HARDWARE_MONITORS: &[(ThermalSource, &str)] = &[
+    (
+        ThermalSource::LibreHardwareMonitor,
+        "root\\LibreHardwareMonitor",
+    ),
+    (
+        ThermalSource::OpenHardwareMonitor,
+        "root\\OpenHardwareMonitor",
+    ),
+];
+
+/// Reads temperatures and fan speeds, keeping its WMI connection so repeated reads (the resource
+/// monitor's) are cheap. A WMI connection belongs to the thread that made it.
+pub struct ThermalReader {
+    source: ThermalSource,
+    con: Option<WMIConnection>,
+}
+
+impl ThermalReader {
+    /// Connect to the first sensor source there is
+    pub fn new() -> Self {
+        for &(source, namespace) in HARDWARE_MONITORS {
+            if let Ok(con) = WMIConnection::with_namespace_path(namespace) {
+                return ThermalReader {
+                    source,
+                    con: Some(con),
+                };
+            }
+        }
+        match WMIConnection::with_namespace_path("root\\wmi") {
+            Ok(con) => ThermalReader {
+                source: ThermalSource::Acpi,
+                con: Some(con),
+            },
+            Err(_) => ThermalReader {
+                source: ThermalSource::None,
+                con: None,
+            },
+        }
+    }
+
+    /// Read every sensor
+    pub fn read(&self) -> ThermalInfo {
+        let Some(con) = &self.con else {
+            return ThermalInfo::default();
+        };
+        let info = match self.source {
+            ThermalSource::LibreHardwareMonitor | ThermalSource::OpenHardwareMonitor => {
+                let hardware: Vec<HardwareMonitorHardware> = con.query().unwrap_or_default();
+                let sensors: Vec<HardwareMonitorSensor> = con.query().unwrap_or_default();
+                thermal_from_hardware_monitor(self.source, &hardware, sensors)
+            }
+            ThermalSource::Acpi => {
+                let zones: Vec<MsAcpiThermalZoneTemperature> = con.query().unwrap_or_default();
+                thermal_from_acpi(zones)
+            }
+            ThermalSource::None => ThermalInfo::default(),
+        };
+        if info.sensors.is_empty() {
+            return ThermalInfo::default();
+        }
+        info
+    }
+}
+
+impl Default for ThermalReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The hottest of the temperature sensors on hardware matching `hardware`, preferring those whose
+/// name matches `preferred` (the package or core sensor over per-core and hotspot ones)
+fn hottest(
+    sensors: &[(String, ThermalSensor)],
+    hardware: impl Fn(&str) -> bool,
+    preferred: impl Fn(&str) -> bool,
+) -> Option<f64> {
+    let temperatures: Vec<&ThermalSensor> = sensors
+        .iter()
+        .filter(|(hardware_type, sensor)| {
+            sensor.kind == SensorKind::Temperature && hardware(hardware_type)
+        })
+        .map(|(_, sensor)| sensor)
+        .collect();
+    temperatures
+        .iter()
+        .filter(|sensor| preferred(&sensor.name))
+        .map(|sensor| sensor.value)
+        .reduce(f64::max)
+        .or_else(|| {
+            temperatures
+                .iter()
+                .map(|sensor| sensor.value)
+                .reduce(f64::max)
+        })
+}
+
+fn thermal_from_hardware_monitor(
+    source: ThermalSource,
+    hardware: &[HardwareMonitorHardware],
+    sensors: Vec<HardwareMonitorSensor>,
+) -> ThermalInfo {
+    // With the type of the hardware each is on (e.g. "Cpu", "GpuNvidia")
+    let sensors: Vec<(String, ThermalSensor)> = sensors
+        .into_iter()
+        .filter_map(|sensor| {
+            let kind = match sensor.sensor_type.as_str() {
+                "Temperature" => SensorKind::Temperature,
+                "Fan" => SensorKind::Fan,
+                _ => return None,
+            };
+            let parent = hardware.iter().find(|h| h.identifier == sensor.parent);
+            Some((
+                parent.map(|h| h.hardware_type.clone()).unwrap_or_default(),
+                ThermalSensor {
+                    hardware: parent.map(|h| h.name.clone()).unwrap_or(sensor.parent),
+                    name: sensor.name,
+                    kind,
+                    value: f64::from(sensor.value?),
+                },
+            ))
+        })
+        .collect();
+
+    let cpu_celsius = hottest(
+        &sensors,
+        |hardware_type| hardware_type.eq_ignore_ascii_case("cpu"),
+        |name| name.contains("Package") || name.contains("Tctl") || name.contains("Tdie"),
+    );
+    let gpu_celsius = hottest(
+        &sensors,
+        |hardware_type| hardware_type.to_ascii_lowercase().starts_with("gpu"),
+        |name| name == "GPU Core",
+    );
+    ThermalInfo {
+        source,
+        cpu_celsius,
+        gpu_celsius,
+        sensors: sensors.into_iter().map(|(_, sensor)| sensor).collect(),
+    }
+}
+
+/// 0 °C in tenths of a kelvin, as ACPI rounds it
+const ZERO_CELSIUS_DECIKELVIN: u32 = 2732;
+
+/// Tenths of a kelvin in °C
+fn decikelvin_to_celsius(decikelvin: u32) -> f64 {
+    (f64::from(decikelvin) - f64::from(ZERO_CELSIUS_DECIKELVIN)) / 10.0
+}
+
+fn thermal_from_acpi(zones: Vec<MsAcpiThermalZoneTemperature>) -> ThermalInfo {
+    let sensors: Vec<ThermalSensor> = zones
+        .into_iter()
+        .filter_map(|zone| {
+            // Firmware without a real sensor reports 0 K or 0 °C
+            let decikelvin = zone
+                .current_temperature
+                .filter(|&decikelvin| decikelvin > ZERO_CELSIUS_DECIKELVIN)?;
+            Some(ThermalSensor {
+                hardware: zone.instance_name,
+                name: "Thermal zone".to_string(),
+                kind: SensorKind::Temperature,
+                value: decikelvin_to_celsius(decikelvin),
+            })
+        })
+        .collect();
+    ThermalInfo {
+        source: ThermalSource::Acpi,
+        cpu_celsius: sensors.iter().map(|sensor| sensor.value).reduce(f64::max),
+        gpu_celsius: None,
+        sensors,
+    }
+}
+
+/// Get temperatures and fan speeds from the first sensor source there is
+pub fn get_thermal_info() -> ThermalInfo {
+    let info = ThermalReader::new().read();
+    log::debug!(
+        "Thermal info: source={:?}, cpu={:?}, gpu={:?}, sensors={}",
+        info.source,
+        info.cpu_celsius,
+        info.gpu_celsius,
+        info.sensors.len()
+    );
+    info
+}
+
+/// Get full system information around hardware and device info gathered before (see
+/// `system_info_cache`); the rest is quick to read, so it's read on every call
+pub fn get_system_info(hardware: HardwareInfo, device: DeviceInfo) -> Result<SystemInfo, Error> {
     log::debug!("Gathering system information");
     let windows = get_windows_info()?;
     let computer_name = env::var("COMPUTERNAME").unwrap_or_else(|_| "Unknown".to_string());
     let username = env::var("USERNAME").unwrap_or_else(|_| "Unknown".to_string());
     let is_admin = is_running_as_admin();
-
-    // Get hardware and device info using the same WMI connection
-    let wmi_con = WMIConnection::new().ok();
-    let hardware = get_hardware_info();
-    let device = wmi_con.as_ref().map(get_device_info).unwrap_or_default();
+    let power = get_power_info();
+    let reboot_pending = windows_update_service::is_reboot_pending();
 
     log::debug!(
         "System info: computer={}, user={}, admin={}, device={}",
@@ -1005,6 +1593,8 @@ pub fn get_system_info() -> Result<SystemInfo, Error> {
         is_admin,
         hardware,
         device,
+        power,
+        reboot_pending,
     })
 }
 
@@ -1016,6 +1606,106 @@ pub fn get_runtime_context() -> Result<RuntimeContext, Error> {
     })
 }
 
+/// Hardware, edition, and build facts used to evaluate tweak `conditions` and `${gpu_vendor}`.
+///
+/// Gathered once per process (WMI queries are slow and none of these change while the app runs).
+/// Facts that can't be read fall back to their defaults, which fail any guard that requires them.
+pub fn system_facts() -> &'static SystemFacts {
+    static FACTS: OnceLock<SystemFacts> = OnceLock::new();
+    FACTS.get_or_init(|| {
+        let wmi_con = WMIConnection::new().ok();
+        let is_laptop = wmi_con
+            .as_ref()
+            .is_some_and(|con| get_device_info(con).pc_type == "Laptop");
+        let has_battery =
+            get_power_status().is_some_and(|status| power_info_from_status(&status).has_battery);
+        let has_ssd = wmi_con.as_ref().is_some_and(|con| {
+            get_disk_info(con).iter().any(|disk| {
+                matches!(disk.drive_type.as_str(), "SSD" | "SCM") || disk.interface_type == "NVMe"
+            })
+        });
+        let ram_gb = wmi_con
+            .as_ref()
+            .map(|con| get_memory_info(con).total_gb)
+            .unwrap_or(0.0);
+        let gpu_names: Vec<String> = wmi_con
+            .as_ref()
+            .map(|con| get_gpu_info(con).into_iter().map(|gpu| gpu.name).collect())
+            .unwrap_or_default();
+        let current_version = RegKey::predef(HKEY_LOCAL_MACHINE)
+            .open_subkey_with_flags("SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion", KEY_READ)
+            .ok();
+        let edition = current_version
+            .as_ref()
+            .and_then(|key| key.get_value::<String, _>("EditionID").ok())
+            .and_then(|id| edition_from_id(&id));
+        let build = WindowsBuild {
+            build: current_version
+                .as_ref()
+                .and_then(|key| key.get_value::<String, _>("CurrentBuildNumber").ok())
+                .and_then(|build| build.parse().ok())
+                .unwrap_or(0),
+            revision: current_version
+                .as_ref()
+                .and_then(|key| key.get_value::<u32, _>("UBR").ok()),
+        };
+
+        let facts = SystemFacts {
+            is_laptop,
+            has_battery,
+            has_ssd,
+            edition,
+            ram_gb,
+            build,
+            gpu_vendor: gpu_vendor_from_names(&gpu_names).to_string(),
+        };
+        log::debug!("System facts for tweak conditions: {:?}", facts);
+        facts
+    })
+}
+
+/// The vendor of the primary GPU. A discrete NVIDIA or AMD GPU wins over integrated Intel
+/// graphics, so hybrid laptops report the GPU games and drivers care about.
+fn gpu_vendor_from_names(names: &[String]) -> &'static str {
+    let names: Vec<String> = names.iter().map(|name| name.to_lowercase()).collect();
+    let has = |needles: &[&str]| {
+        names
+            .iter()
+            .any(|name| needles.iter().any(|needle| name.contains(needle)))
+    };
+    if has(&["nvidia", "geforce", "quadro"]) {
+        "nvidia"
+    } else if has(&["amd", "radeon"]) {
+        "amd"
+    } else if has(&["intel"]) {
+        "intel"
+    } else {
+        "unknown"
+    }
+}
+
+/// Map an `EditionID` (e.g. `Core`, `ProfessionalWorkstation`, `EnterpriseS`) to its edition family.
+fn edition_from_id(id: &str) -> Option<WindowsEdition> {
+    if id.starts_with("Core") {
+        Some(WindowsEdition::Home)
+    } else if id.starts_with("Professional") {
+        // ProfessionalEducation is Pro Education, which is managed like Education
+        if id.ends_with("Education") {
+            Some(WindowsEdition::Education)
+        } else {
+            Some(WindowsEdition::Pro)
+        }
+    } else if id.starts_with("Education") {
+        Some(WindowsEdition::Education)
+    } else if id.starts_with("Enterprise") || id.starts_with("IoTEnterprise") {
+        Some(WindowsEdition::Enterprise)
+    } else if id.starts_with("Server") {
+        Some(WindowsEdition::Server)
+    } else {
+        None
+    }
+}
+
 /// Check if running as administrator
 /// Uses a simple heuristic: try to open a protected registry key
 pub fn is_running_as_admin() -> bool {
@@ -1039,10 +1729,53 @@ pub fn machine_guid() -> Option<String> {
         .ok()
 }
 
+/// When the machine last booted, from the milliseconds elapsed since then. Two calls in the same
+/// boot can differ by a few milliseconds, so compare boot times with some tolerance.
+pub fn boot_time() -> chrono::DateTime<chrono::Local> {
+    // SAFETY: GetTickCount64 takes no arguments and can't fail
+    let uptime_ms = unsafe { windows_sys::Win32::System::SystemInformation::GetTickCount64() };
+    chrono::Local::now() - chrono::Duration::milliseconds(uptime_ms as i64)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_network_details_from_the_netadapter_and_dnsclient_providers() {
+        assert_eq!(network_medium(Some(14)), NetworkMedium::Ethernet);
+        assert_eq!(network_medium(Some(9)), NetworkMedium::Wifi);
+        assert_eq!(network_medium(Some(0)), NetworkMedium::Unknown);
+        assert_eq!(network_medium(Some(17)), NetworkMedium::Other);
+
+        let adapter = MsftNetAdapter {
+            interface_index: Some(12),
+            ndis_physical_medium: Some(9),
+            receive_link_speed: Some(866_700_000),
+            transmit_link_speed: Some(1_200_000_000),
+        };
+        assert_eq!(link_speed_mbps(&adapter), Some(1200));
+        assert_eq!(
+            link_speed_mbps(&MsftNetAdapter {
+                receive_link_speed: Some(0),
+                transmit_link_speed: None,
+                ..adapter
+            }),
+            None
+        );
+
+        let entry = |family, addresses: &[&str]| MsftDnsClientServerAddress {
+            interface_index: Some(12),
+            address_family: Some(family),
+            server_addresses: Some(addresses.iter().map(|a| a.to_string()).collect()),
+        };
+        let servers = dns_servers_by_interface(vec![
+            entry(23, &["2606:4700:4700::1111"]),
+            entry(AF_INET, &["1.1.1.1", "1.0.0.1"]),
+        ]);
+        assert_eq!(servers[&12], ["1.1.1.1", "1.0.0.1", "2606:4700:4700::1111"]);
+    }
+
     #[test]
     fn test_get_windows_info() {
         let result = get_windows_info();
@@ -1052,6 +1785,143 @@ mod tests {
         assert!(!info.build_number.is_empty());
     }
 
+    #[test]
+    fn test_power_info_reads_battery_status_flags() {
+        let desktop = power_info_from_status(&SYSTEM_POWER_STATUS {
+            ACLineStatus: 1,
+            BatteryFlag: BATTERY_FLAG_NO_BATTERY,
+            BatteryLifePercent: POWER_STATUS_UNKNOWN,
+            ..Default::default()
+        });
+        assert!(!desktop.has_battery);
+        assert_eq!(desktop.on_ac_power, Some(true));
+        assert_eq!(desktop.charge_percent, None);
+
+        let laptop = power_info_from_status(&SYSTEM_POWER_STATUS {
+            ACLineStatus: 0,
+            BatteryFlag: 1 | BATTERY_FLAG_CHARGING,
+            BatteryLifePercent: 80,
+            ..Default::default()
+        });
+        assert!(laptop.has_battery && laptop.charging);
+        assert_eq!(laptop.on_ac_power, Some(false));
+        assert_eq!(laptop.charge_percent, Some(80));
+
+        assert_eq!(battery_health(50_000, 42_500), Some(85.0));
+        assert_eq!(battery_health(0, 42_500), None);
+    }
+
+    #[test]
+    fn test_hardware_monitor_temperatures_prefer_package_and_core_sensors() {
+        let hardware = |identifier: &str, hardware_type: &str| HardwareMonitorHardware {
+            identifier: identifier.to_string(),
+            name: identifier.to_string(),
+            hardware_type: hardware_type.to_string(),
+        };
+        let sensor =
+            |parent: &str, name: &str, sensor_type: &str, value: f32| HardwareMonitorSensor {
+                name: name.to_string(),
+                sensor_type: sensor_type.to_string(),
+                value: Some(value),
+                parent: parent.to_string(),
+            };
+        let info = thermal_from_hardware_monitor(
+            ThermalSource::LibreHardwareMonitor,
+            &[
+                hardware("/amdcpu/0", "Cpu"),
+                hardware("/gpu-nvidia/0", "GpuNvidia"),
+            ],
+            vec![
+                sensor("/amdcpu/0", "Core #1", "Temperature", 71.0),
+                sensor("/amdcpu/0", "Core (Tctl/Tdie)", "Temperature", 65.0),
+                sensor("/amdcpu/0", "CPU Total", "Load", 40.0),
+                sensor("/gpu-nvidia/0", "GPU Hot Spot", "Temperature", 80.0),
+                sensor("/gpu-nvidia/0", "GPU Core", "Temperature", 62.0),
+                sensor("/gpu-nvidia/0", "GPU Fan", "Fan", 1500.0),
+            ],
+        );
+        assert_eq!(info.cpu_celsius, Some(65.0));
+        assert_eq!(info.gpu_celsius, Some(62.0));
+        assert_eq!(info.sensors.len(), 5);
+        assert!(info
+            .sensors
+            .iter()
+            .any(|sensor| sensor.kind == SensorKind::Fan && sensor.hardware == "/gpu-nvidia/0"));
+    }
+
+    #[test]
+    fn test_acpi_zones_are_converted_from_tenths_of_a_kelvin() {
+        assert_eq!(decikelvin_to_celsius(3232), 50.0);
+        let info = thermal_from_acpi(vec![
+            MsAcpiThermalZoneTemperature {
+                instance_name: "ACPI\\ThermalZone\\TZ00_0".to_string(),
+                current_temperature: Some(3182),
+            },
+            MsAcpiThermalZoneTemperature {
+                instance_name: "ACPI\\ThermalZone\\TZ01_0".to_string(),
+                current_temperature: Some(2732),
+            },
+        ]);
+        assert_eq!(info.cpu_celsius, Some(45.0));
+        assert_eq!(info.sensors.len(), 1);
+    }
+
+    #[test]
+    fn test_gpu_vendor_prefers_discrete_gpus() {
+        let names = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            gpu_vendor_from_names(&names(&[
+                "Intel(R) UHD Graphics 630",
+                "NVIDIA GeForce RTX 3060"
+            ])),
+            "nvidia"
+        );
+        assert_eq!(
+            gpu_vendor_from_names(&names(&["AMD Radeon(TM) Graphics"])),
+            "amd"
+        );
+        assert_eq!(
+            gpu_vendor_from_names(&names(&["Intel(R) Iris(R) Xe Graphics"])),
+            "intel"
+        );
+        assert_eq!(gpu_vendor_from_names(&[]), "unknown");
+    }
+
+    #[test]
+    fn test_edition_from_id_maps_edition_families() {
+        assert_eq!(edition_from_id("Core"), Some(WindowsEdition::Home));
+        assert_eq!(
+            edition_from_id("CoreSingleLanguage"),
+            Some(WindowsEdition::Home)
+        );
+        assert_eq!(edition_from_id("Professional"), Some(WindowsEdition::Pro));
+        assert_eq!(
+            edition_from_id("ProfessionalWorkstation"),
+            Some(WindowsEdition::Pro)
+        );
+        assert_eq!(
+            edition_from_id("ProfessionalEducation"),
+            Some(WindowsEdition::Education)
+        );
+        assert_eq!(
+            edition_from_id("Education"),
+            Some(WindowsEdition::Education)
+        );
+        assert_eq!(
+            edition_from_id("EnterpriseS"),
+            Some(WindowsEdition::Enterprise)
+        );
+        assert_eq!(
+            edition_from_id("IoTEnterpriseS"),
+            Some(WindowsEdition::Enterprise)
+        );
+        assert_eq!(
+            edition_from_id("ServerDatacenter"),
+            Some(WindowsEdition::Server)
+        );
+        assert_eq!(edition_from_id("Cloud"), None);
+    }
+
     // ========================================================================
     // is_leap_year tests
     // ========================================================================
@@ -0,0 +1,78 @@
+//! Windows Time service (w32time) configuration.
+//!
+//! Time changes are written as the w32time registry values (see `TimeChange::to_registry_changes`),
+//! so they're captured and restored like any registry value. The service only reads those values
+//! at startup or when told to, so after they're applied or restored the service is asked to
+//! reload them with `w32tm /config /update` and to sync with the new configuration.
+
+use crate::error::Error;
+use std::os::windows::process::CommandExt;
+use std::process::Command;
+
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+/// Service key every w32time setting lives under (in HKLM)
+const W32TIME_SERVICE_KEY: &str = "SYSTEM\\CurrentControlSet\\Services\\W32Time";
+
+/// Whether a key (under HKLM) belongs to the Windows Time service
+pub fn is_w32time_key(key: &str) -> bool {
+    let key = key.trim_start_matches('\\');
+    key.len() >= W32TIME_SERVICE_KEY.len()
+        && key[..W32TIME_SERVICE_KEY.len()].eq_ignore_ascii_case(W32TIME_SERVICE_KEY)
+        && key[W32TIME_SERVICE_KEY.len()..]
+            .chars()
+            .next()
+            .is_none_or(|c| c == '\\')
+}
+
+fn run_w32tm(args: &[&str]) -> Result<(), Error> {
+    let output = Command::new("w32tm")
+        .args(args)
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .map_err(|e| Error::CommandExecution(format!("Failed to run w32tm: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(Error::CommandExecution(format!(
+            "w32tm {} failed: {} {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stdout).trim(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+/// Have the running Windows Time service reload its configuration, then start a sync with it.
+/// Fails if the service isn't running; it reads the new values when it next starts.
+pub fn update_config() -> Result<(), Error> {
+    run_w32tm(&["/config", "/update"])?;
+    log::info!("Reloaded Windows Time configuration");
+    // The new configuration is in effect; a failed resync only means the clock catches up at the
+    // next regular poll
+    if let Err(e) = run_w32tm(&["/resync", "/nowait"]) {
+        log::warn!("Failed to start a time resync: {}", e);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn w32time_keys_are_recognized_by_prefix() {
+        assert!(is_w32time_key(
+            "SYSTEM\\CurrentControlSet\\Services\\W32Time\\Parameters"
+        ));
+        assert!(is_w32time_key(
+            "system\\currentcontrolset\\services\\w32time"
+        ));
+        assert!(!is_w32time_key(
+            "SYSTEM\\CurrentControlSet\\Services\\W32TimeExtra\\Parameters"
+        ));
+        assert!(!is_w32time_key(
+            "SYSTEM\\CurrentControlSet\\Services\\Dnscache"
+        ));
+    }
+}
@@ -0,0 +1,293 @@
+//! Authoring custom tweaks from the UI, without writing their YAML by hand.
+//!
+//! A draft is one tweak, with the fields it has in YAML, and the `tweaks.d` file it goes in. The
+//! draft is put in that file (in place of the tweak it was opened from, or at the end), and the
+//! file is validated as `tweak_loader` validates it at startup: with the shared rules, against the
+//! compiled tweaks and the custom tweaks of the other files. Saving writes the file only when it
+//! has no errors. A saved tweak is loaded at the next launch (debug builds reload it right away).
+//! Saving rewrites the whole file, so comments in a hand-written one are lost.
+
+use crate::error::Error;
+use crate::models::CategoryDefinition;
+use crate::services::{protected_dir, system_info_service, tweak_loader};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Serializes saving drafts, a read-modify-write of their file
+static SAVE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Names Windows keeps for devices, whatever extension follows them; COM and LPT take a digit
+const DEVICE_NAMES: &[&str] = &["CON", "PRN", "AUX", "NUL", "CONIN$", "CONOUT$"];
+
+/// A custom tweak being authored
+#[derive(Debug, Clone, Deserialize)]
+pub struct TweakDraft {
+    /// File in `tweaks.d` the tweak goes in (e.g. `my_tweaks.yaml`); created if it doesn't exist
+    pub file: String,
+    /// The file's category: needed for a new file, and replaces an existing file's
+    #[serde(default)]
+    pub category: Option<CategoryDefinition>,
+    /// The tweak, with the fields of a tweak in YAML. Its fields are written in this order.
+    pub tweak: serde_yaml_bw::Value,
+    /// ID of the tweak in the file that the draft edits; None adds a tweak
+    #[serde(default)]
+    pub original_id: Option<String>,
+}
+
+/// What validating or saving a draft found
+#[derive(Debug, Clone, Serialize)]
+pub struct DraftReport {
+    pub file: String,
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+    /// Whether the file was written; never when there are errors
+    pub saved: bool,
+}
+
+/// A tweak file, with its tweaks kept as they're written
+#[derive(Debug, Serialize, Deserialize)]
+struct DraftFile {
+    category: CategoryDefinition,
+    #[serde(default)]
+    tweaks: Vec<serde_yaml_bw::Value>,
+}
+
+/// Validate a draft in its file, without saving it
+pub fn validate_draft(draft: &TweakDraft) -> Result<DraftReport, Error> {
+    let (_, report) = prepare(draft)?;
+    Ok(report)
+}
+
+/// Save a draft to its file, unless the file would have errors
+pub fn save_draft(draft: &TweakDraft) -> Result<DraftReport, Error> {
+    let _guard = SAVE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (content, mut report) = prepare(draft)?;
+    if !report.errors.is_empty() {
+        return Ok(report);
+    }
+
+    // tweaks.d is writable by Administrators and SYSTEM only (see `protected_dir`)
+    if !system_info_service::is_running_as_admin() {
+        return Err(Error::RequiresAdmin);
+    }
+    let dir = tweak_loader::custom_tweaks_dir()?;
+    protected_dir::ensure(&dir)?;
+    // Written to a temp file and renamed over the file, so a failed save leaves it as it was
+    let path = dir.join(&draft.file);
+    let mut tmp = tempfile::NamedTempFile::new_in(&dir)
+        .map_err(|e| Error::BackupFailed(format!("Failed to create a temp file: {}", e)))?;
+    tmp.write_all(content.as_bytes())
+        .map_err(|e| Error::BackupFailed(format!("Failed to write {}: {}", path.display(), e)))?;
+    tmp.persist(&path)
+        .map_err(|e| Error::BackupFailed(format!("Failed to write {}: {}", path.display(), e)))?;
+    log::info!("Saved custom tweak file {}", draft.file);
+    report.saved = true;
+    Ok(report)
+}
+
+/// The draft's file with the draft in it, as YAML, and what validating it found
+fn prepare(draft: &TweakDraft) -> Result<(String, DraftReport), Error> {
+    check_file_name(&draft.file)?;
+    let path = tweak_loader::custom_tweaks_dir()?.join(&draft.file);
+    let file = merge(draft, read_file(&path, &draft.file)?)?;
+    let content = serde_yaml_bw::to_string(&file)
+        .map_err(|e| Error::ValidationError(format!("Failed to write the tweak as YAML: {}", e)))?;
+    let (errors, warnings) = tweak_loader::validate_custom_file(&draft.file, &content);
+    Ok((
+        content,
+        DraftReport {
+            file: draft.file.clone(),
+            errors,
+            warnings,
+            saved: false,
+        },
+    ))
+}
+
+/// A file name directly in `tweaks.d`, ending in `.yaml` or `.yml`
+fn check_file_name(name: &str) -> Result<(), Error> {
+    let plain = !name.starts_with('.') && !name.contains(['/', '\\', ':']);
+    let yaml = name.len() > ".yml".len() && (name.ends_with(".yaml") || name.ends_with(".yml"));
+    if plain && yaml && !is_device_name(name) {
+        Ok(())
+    } else {
+        Err(Error::ValidationError(format!(
+            "'{}' isn't a tweak file name (e.g. my_tweaks.yaml)",
+            name
+        )))
+    }
+}
+
+/// Whether Windows opens the device `name` starts with (e.g. `nul.yaml`, `COM1 .yml`) instead of
+/// a file
+fn is_device_name(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or_default();
+    let stem = stem.trim_end_matches(' ').to_ascii_uppercase();
+    let port = stem
+        .strip_prefix("COM")
+        .or_else(|| stem.strip_prefix("LPT"))
+        .is_some_and(|digit| {
+            matches!(
+                digit,
+                "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9" | "¹" | "²" | "³"
+            )
+        });
+    port || DEVICE_NAMES.contains(&stem.as_str())
+}
+
+/// The file as it is, or None if it doesn't exist yet
+fn read_file(path: &Path, name: &str) -> Result<Option<DraftFile>, Error> {
+    match fs::read_to_string(path) {
+        Ok(content) => serde_yaml_bw::from_str(&content)
+            .map(Some)
+            .map_err(|e| Error::ValidationError(format!("{} can't be edited: {}", name, e))),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(Error::BackupFailed(format!(
+            "Failed to read {}: {}",
+            path.display(),
+            e
+        ))),
+    }
+}
+
+/// Put the draft's tweak in its file (`current`, if the file exists): in place of the tweak it
+/// edits, or at the end
+fn merge(draft: &TweakDraft, current: Option<DraftFile>) -> Result<DraftFile, Error> {
+    let mut file = match (current, &draft.category) {
+        (Some(mut file), category) => {
+            if let Some(category) = category {
+                file.category = category.clone();
+            }
+            file
+        }
+        (None, Some(category)) => DraftFile {
+            category: category.clone(),
+            tweaks: Vec::new(),
+        },
+        (None, None) => {
+            return Err(Error::ValidationError(format!(
+                "{} is a new file, so the tweak needs a category",
+                draft.file
+            )))
+        }
+    };
+
+    match &draft.original_id {
+        Some(original) => {
+            let index = file
+                .tweaks
+                .iter()
+                .position(|tweak| {
+                    tweak.get("id").and_then(|id| id.as_str()) == Some(original.as_str())
+                })
+                .ok_or_else(|| {
+                    Error::NotFound(format!("Tweak '{}' in {}", original, draft.file))
+                })?;
+            file.tweaks[index] = draft.tweak.clone();
+        }
+        None => file.tweaks.push(draft.tweak.clone()),
+    }
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn yaml(text: &str) -> serde_yaml_bw::Value {
+        serde_yaml_bw::from_str(text).unwrap()
+    }
+
+    fn draft(tweak: &str, original_id: Option<&str>) -> TweakDraft {
+        TweakDraft {
+            file: "mine.yaml".to_string(),
+            category: None,
+            tweak: yaml(tweak),
+            original_id: original_id.map(str::to_string),
+        }
+    }
+
+    fn ids(file: &DraftFile) -> Vec<&str> {
+        file.tweaks
+            .iter()
+            .filter_map(|tweak| tweak.get("id").and_then(|id| id.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn a_draft_replaces_the_tweak_it_edits_in_place() {
+        let current: DraftFile = serde_yaml_bw::from_str(
+            "category:\n  id: mine\n  name: Mine\n  description: My tweaks\n  icon: mdi:star\n\
+             tweaks:\n  - id: first\n  - id: second\n  - id: third\n",
+        )
+        .unwrap();
+
+        let file = merge(&draft("id: renamed", Some("second")), Some(current)).unwrap();
+        assert_eq!(ids(&file), ["first", "renamed", "third"]);
+
+        let file = merge(&draft("id: fourth", None), Some(file)).unwrap();
+        assert_eq!(ids(&file), ["first", "renamed", "third", "fourth"]);
+
+        assert!(matches!(
+            merge(&draft("id: x", Some("missing")), Some(file)),
+            Err(Error::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn a_new_file_needs_a_category() {
+        assert!(merge(&draft("id: first", None), None).is_err());
+
+        let mut with_category = draft("id: first", None);
+        with_category.category = Some(CategoryDefinition {
+            id: "mine".to_string(),
+            name: "Mine".to_string(),
+            description: "My tweaks".to_string(),
+            icon: "mdi:star".to_string(),
+            order: 0,
+        });
+        let file = merge(&with_category, None).unwrap();
+        assert_eq!(file.category.id, "mine");
+        assert_eq!(ids(&file), ["first"]);
+    }
+
+    #[test]
+    fn drafts_are_saved_only_directly_in_tweaks_d() {
+        for name in ["mine.yaml", "my tweaks.yml"] {
+            assert!(check_file_name(name).is_ok(), "{}", name);
+        }
+        for name in [
+            "",
+            ".yaml",
+            "mine.json",
+            "../mine.yaml",
+            "pack/mine.yaml",
+            "C:mine.yaml",
+            ".hidden.yaml",
+        ] {
+            assert!(check_file_name(name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn drafts_are_not_saved_under_device_names() {
+        for name in [
+            "con.yaml",
+            "NUL.yml",
+            "Aux.backup.yaml",
+            "prn .yaml",
+            "com1.yaml",
+            "LPT9.yaml",
+            "com².yaml",
+            "conout$.yaml",
+        ] {
+            assert!(check_file_name(name).is_err(), "{}", name);
+        }
+        for name in ["console.yaml", "com10.yaml", "lpt0.yaml", "nullable.yml"] {
+            assert!(check_file_name(name).is_ok(), "{}", name);
+        }
+    }
+}
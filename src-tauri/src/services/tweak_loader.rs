@@ -2,45 +2,528 @@
 //!
 //! Tweaks are compiled from YAML files at build time by `build.rs`.
 //! This eliminates runtime file I/O and YAML parsing for instant loading.
+//!
+//! The user can add tweaks of their own without rebuilding: YAML files in the same format, in
+//! `%ProgramData%\MagicX Toolbox\tweaks.d`, are read once when the tweaks are first loaded. Each
+//! file is validated with the rules build.rs applies (`models::tweak_validation`); a file with
+//! errors is left out as a whole, and its errors are logged and kept for `get_custom_tweaks`. A
+//! custom tweak can't reuse a compiled tweak's ID, but its file may name a compiled category to add
+//! to it. Custom tweaks are marked `custom`. The files of the installed tweak pack (see
+//! `tweak_pack_service`), as verified against its signed manifest, are read after them, the same
+//! way. When `tweaks.d` exists, the JSON
+//! Schema of the file format is kept next to them as `tweak.schema.json`, for editors.
+//!
+//! `${variable}` placeholders in option strings (see `models::tweak_template`) are expanded with
+//! this machine's values the first time the tweaks are loaded.
+//!
+//! In debug builds `watch` reloads the tweaks when a YAML file in `src-tauri/tweaks` or `tweaks.d`
+//! changes, parsing the source files with the build's validation instead of using the compiled
+//! ones, and reports it with `tweaks://reloaded`. Tweak authors see an edit without rebuilding.
 
 use crate::error::Error;
-use crate::generated_tweaks::{CATEGORIES, TWEAKS};
+use crate::generated_tweaks::{CATEGORIES, TEMPLATED_TWEAKS, TWEAKS, TWEAK_SCHEMA_JSON};
+use crate::models::tweak_fragments::Fragments;
+use crate::models::tweak_template::{expand_json_strings, TemplateError};
+use crate::models::tweak_validation::{TweakFile, ValidationContext};
 use crate::models::{CategoryDefinition, TweakDefinition};
+use crate::services::{
+    backup, protected_dir, registry_acl_service, system_info_service, tweak_pack_service,
+};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
+use std::time::Instant;
+
+/// Folder (in the ProgramData app folder) holding the user's tweak files
+const CUSTOM_TWEAKS_DIR: &str = "tweaks.d";
+
+/// Start of the name reported for a file of the installed tweak pack
+const PACK_FILE_PREFIX: &str = "pack/";
+
+/// The JSON Schema's file name in `tweaks.d`
+const SCHEMA_FILE: &str = "tweak.schema.json";
+
+/// What loading the `tweaks.d` files found
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CustomTweaksReport {
+    /// The folder read, or None if it couldn't be located
+    pub directory: Option<String>,
+    /// Each YAML file in it by file name, then the installed tweak pack's as `pack/<file name>`
+    pub files: Vec<CustomTweakFile>,
+}
+
+/// One file of `tweaks.d`
+#[derive(Debug, Clone, Serialize)]
+pub struct CustomTweakFile {
+    pub file: String,
+    /// IDs of the tweaks loaded from it; empty if it has errors
+    pub tweak_ids: Vec<String>,
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+/// The custom tweaks, before their templates are expanded
+struct CustomTweaks {
+    categories: Vec<CategoryDefinition>,
+    tweaks: Vec<TweakDefinition>,
+    report: CustomTweaksReport,
+}
+
+/// The tweaks on offer: the compiled and custom ones with their templates expanded, and their
+/// categories
+struct Loaded {
+    /// Sorted by `order`
+    categories: Vec<CategoryDefinition>,
+    tweaks: HashMap<String, TweakDefinition>,
+    /// The ID each alias (an earlier ID) stands for
+    aliases: HashMap<String, String>,
+    custom: CustomTweaksReport,
+}
+
+/// The loaded tweaks, leaked so lookups can hand out `'static` references. Only a reload (debug
+/// builds) replaces them, leaking the set it replaces.
+static LOADED: RwLock<Option<&'static Loaded>> = RwLock::new(None);
+
+fn loaded() -> &'static Loaded {
+    if let Some(loaded) = *LOADED.read().unwrap_or_else(|e| e.into_inner()) {
+        return loaded;
+    }
+    let mut slot = LOADED.write().unwrap_or_else(|e| e.into_inner());
+    *slot.get_or_insert_with(|| {
+        let start = Instant::now();
+        let compiled = TWEAKS.values().cloned().collect();
+        let decoded = start.elapsed();
+        let loaded: &'static Loaded =
+            Box::leak(Box::new(load(CATEGORIES.clone(), compiled, |tweak| {
+                TEMPLATED_TWEAKS.contains(&tweak.id.as_str())
+            })));
+        log::info!(
+            "Loaded {} tweaks in {:?} ({:?} decoding the embedded ones)",
+            loaded.tweaks.len(),
+            start.elapsed(),
+            decoded
+        );
+        loaded
+    })
+}
+
+/// Merge the custom tweaks into `compiled` and expand the templates of those `is_templated` picks
+/// out and of the custom tweaks that have any. A tweak that references a variable this machine
+/// has no value for is left out, rather than offered with a broken path.
+fn load(
+    mut categories: Vec<CategoryDefinition>,
+    compiled: Vec<TweakDefinition>,
+    is_templated: impl Fn(&TweakDefinition) -> bool,
+) -> Loaded {
+    let custom = load_custom_tweaks(&compiled);
+    for category in custom.categories {
+        if !categories.iter().any(|c| c.id == category.id) {
+            categories.push(category);
+        }
+    }
+    categories.sort_by_key(|c| c.order);
+
+    let tweaks: HashMap<String, TweakDefinition> = compiled
+        .into_iter()
+        .chain(custom.tweaks)
+        .filter_map(|tweak| {
+            let templated = if tweak.custom {
+                has_templates(&tweak)
+            } else {
+                is_templated(&tweak)
+            };
+            if !templated {
+                return Some((tweak.id.clone(), tweak));
+            }
+            match expand_tweak(&tweak) {
+                Ok(expanded) => Some((tweak.id.clone(), expanded)),
+                Err(e) => {
+                    log::warn!("Tweak '{}' is unavailable on this machine: {}", tweak.id, e);
+                    None
+                }
+            }
+        })
+        .collect();
+    let aliases = tweaks
+        .values()
+        .flat_map(|tweak| {
+            tweak
+                .aliases
+                .iter()
+                .map(|alias| (alias.clone(), tweak.id.clone()))
+        })
+        .collect();
+    Loaded {
+        categories,
+        tweaks,
+        aliases,
+        custom: custom.report,
+    }
+}
+
+/// Read and validate every YAML file in `tweaks.d`, in file name order
+fn load_custom_tweaks(compiled: &[TweakDefinition]) -> CustomTweaks {
+    let mut custom = CustomTweaks {
+        categories: Vec::new(),
+        tweaks: Vec::new(),
+        report: CustomTweaksReport::default(),
+    };
+    // The tweaks.d files' contents, then those of the installed tweak pack (see
+    // `tweak_pack_service`), or why they can't be loaded
+    let mut files: Vec<(String, Result<String, String>)> = Vec::new();
+    match custom_tweaks_dir() {
+        Ok(dir) => {
+            if dir.is_dir() {
+                // Restricts a folder made before it was created admin-only (when elevated)
+                if let Err(e) = protected_dir::ensure(&dir) {
+                    log::warn!("{}", e);
+                }
+                write_schema(&dir);
+            }
+            files.extend(
+                listed_yaml_files(&dir)
+                    .into_iter()
+                    .map(|(file, path)| (file, read_custom_file(&path))),
+            );
+            custom.report.directory = Some(dir.display().to_string());
+        }
+        Err(e) => log::warn!("Custom tweaks are unavailable: {}", e),
+    }
+    match tweak_pack_service::active_pack_files() {
+        Ok(pack) => files.extend(
+            pack.into_iter()
+                .map(|(name, content)| (format!("{}{}", PACK_FILE_PREFIX, name), Ok(content))),
+        ),
+        // The pack is left out as a whole, reported as its manifest
+        Err(e) => files.push((
+            format!("{}{}", PACK_FILE_PREFIX, tweak_pack_service::MANIFEST_FILE),
+            Err(format!("Not loaded: {}", e)),
+        )),
+    }
+
+    // Custom tweaks are checked against the compiled ones and each other, as build.rs checks
+    // the compiled files against each other
+    let mut ctx = ValidationContext::new();
+    for tweak in compiled {
+        ctx.claim_tweak(tweak);
+    }
+    for (file, content) in files {
+        let content = match content {
+            Ok(content) => content,
+            Err(e) => {
+                ctx.errors.push(format!("[{}] {}", file, e));
+                custom.report.files.push(file_report(&mut ctx, &file, &[]));
+                continue;
+            }
+        };
+        let loaded = load_custom_file(&mut ctx, &file, &content);
+        let tweaks = loaded
+            .as_ref()
+            .map_or(&[][..], |(_, tweaks)| tweaks.as_slice());
+        let report = file_report(&mut ctx, &file, tweaks);
+        for error in &report.errors {
+            log::warn!("Custom tweak file rejected: {}", error);
+        }
+        for warning in &report.warnings {
+            log::info!("Custom tweak warning: {}", warning);
+        }
+        custom.report.files.push(report);
+        if let Some((category, tweaks)) = loaded {
+            custom.categories.push(category);
+            custom.tweaks.extend(tweaks);
+        }
+    }
+    log::info!(
+        "Loaded {} custom tweaks from {} files",
+        custom.tweaks.len(),
+        custom.report.files.len()
+    );
+    custom
+}
+
+/// Read a `tweaks.d` file, refusing one not owned by Administrators or SYSTEM: a standard user may
+/// have put it there before the folder was made admin-only, and it would be applied as
+/// administrator
+fn read_custom_file(path: &Path) -> Result<String, String> {
+    match protected_dir::owned_by_admins(path) {
+        Ok(true) => {}
+        Ok(false) => return Err(
+            "Not loaded: the file isn't owned by Administrators or SYSTEM (recreate it as administrator)"
+                .to_string(),
+        ),
+        Err(e) => return Err(format!("Not loaded: {}", e)),
+    }
+    fs::read_to_string(path).map_err(|e| format!("Failed to read the file: {}", e))
+}
+
+/// The user's `tweaks.d` folder (which may not exist yet)
+pub(crate) fn custom_tweaks_dir() -> Result<PathBuf, Error> {
+    backup::program_data_file(CUSTOM_TWEAKS_DIR)
+}
+
+/// Validate a `tweaks.d` file's contents as they'd be loaded: against the compiled tweaks and the
+/// custom tweaks of the other files. Returns its errors and warnings.
+pub(crate) fn validate_custom_file(file: &str, content: &str) -> (Vec<String>, Vec<String>) {
+    let loaded = loaded();
+    let own: HashSet<&str> = loaded
+        .custom
+        .files
+        .iter()
+        .filter(|report| report.file == file)
+        .flat_map(|report| report.tweak_ids.iter().map(String::as_str))
+        .collect();
+    let mut ctx = ValidationContext::new();
+    for tweak in TWEAKS.values() {
+        ctx.claim_tweak(tweak);
+    }
+    for tweak in loaded.tweaks.values() {
+        if tweak.custom && !own.contains(tweak.id.as_str()) {
+            ctx.claim_tweak(tweak);
+        }
+    }
+    parse_tweak_file(&mut ctx, &Fragments::default(), file, content);
+    (ctx.errors, ctx.warnings)
+}
+
+/// The YAML files in `dir` in name order, by file name
+fn listed_yaml_files(dir: &Path) -> Vec<(String, PathBuf)> {
+    match yaml_files(dir) {
+        Ok(mut names) => {
+            names.sort();
+            names
+                .into_iter()
+                .map(|name| {
+                    let path = dir.join(&name);
+                    (name, path)
+                })
+                .collect()
+        }
+        Err(e) => {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                log::warn!("Failed to read {}: {}", dir.display(), e);
+            }
+            Vec::new()
+        }
+    }
+}
+
+/// Keep the schema in `dir` up to date with this build's
+fn write_schema(dir: &Path) {
+    let path = dir.join(SCHEMA_FILE);
+    if fs::read_to_string(&path).is_ok_and(|current| current == TWEAK_SCHEMA_JSON) {
+        return;
+    }
+    if let Err(e) = fs::write(&path, TWEAK_SCHEMA_JSON) {
+        log::warn!("Failed to write {}: {}", path.display(), e);
+    }
+}
 
-/// Load all categories (pre-compiled at build time).
+/// The JSON Schema of the tweak YAML format, generated by build.rs from the schema types
+pub fn tweak_schema() -> serde_json::Value {
+    serde_json::from_str(TWEAK_SCHEMA_JSON).expect("Failed to parse embedded tweak schema JSON")
+}
+
+/// Validate a tweak pack's files (name and contents) against the compiled tweaks and each other.
+/// Returns the errors.
+pub(crate) fn validate_tweak_pack(files: &[(String, String)]) -> Vec<String> {
+    let mut ctx = ValidationContext::new();
+    for tweak in TWEAKS.values() {
+        ctx.claim_tweak(tweak);
+    }
+    for (file, content) in files {
+        parse_tweak_file(&mut ctx, &Fragments::default(), file, content);
+    }
+    ctx.errors
+}
+
+/// Names of the YAML files in `dir`
+fn yaml_files(dir: &Path) -> std::io::Result<Vec<String>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|e| e == "yaml" || e == "yml") {
+            if let Some(name) = path.file_name() {
+                files.push(name.to_string_lossy().into_owned());
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Parse and validate one tweak file, as build.rs does. Returns its category and tweaks, or None
+/// if it has errors (which are left in `ctx`). Only the built-in tweaks have `fragments`; custom
+/// files are parsed with none.
+fn parse_tweak_file(
+    ctx: &mut ValidationContext,
+    fragments: &Fragments,
+    file: &str,
+    content: &str,
+) -> Option<(CategoryDefinition, Vec<TweakDefinition>)> {
+    let tweak_file: TweakFile = match fragments.parse(content) {
+        Ok(tweak_file) => tweak_file,
+        Err(e) => {
+            ctx.errors.push(format!("[{}] Parse error: {}", file, e));
+            return None;
+        }
+    };
+    if !ctx.validate_file(file, &tweak_file) {
+        return None;
+    }
+
+    let category_id = tweak_file.category.id.clone();
+    let tweaks = tweak_file
+        .tweaks
+        .into_iter()
+        .map(|raw| raw.into_definition(&category_id))
+        .collect();
+    Some((tweak_file.category, tweaks))
+}
+
+/// Parse and validate one custom tweak file. Returns its category and tweaks, or None if it has
+/// errors (which are left in `ctx`, and don't claim its IDs).
+fn load_custom_file(
+    ctx: &mut ValidationContext,
+    file: &str,
+    content: &str,
+) -> Option<(CategoryDefinition, Vec<TweakDefinition>)> {
+    let claims = ctx.claims();
+    let Some((category, tweaks)) = parse_tweak_file(ctx, &Fragments::default(), file, content)
+    else {
+        ctx.restore_claims(claims);
+        return None;
+    };
+    let tweaks = tweaks
+        .into_iter()
+        .map(|tweak| TweakDefinition {
+            custom: true,
+            ..tweak
+        })
+        .collect();
+    Some((category, tweaks))
+}
+
+/// The report of a file just loaded, taking its errors and warnings out of `ctx`
+fn file_report(
+    ctx: &mut ValidationContext,
+    file: &str,
+    tweaks: &[TweakDefinition],
+) -> CustomTweakFile {
+    CustomTweakFile {
+        file: file.to_string(),
+        tweak_ids: tweaks.iter().map(|tweak| tweak.id.clone()).collect(),
+        errors: std::mem::take(&mut ctx.errors),
+        warnings: std::mem::take(&mut ctx.warnings),
+    }
+}
+
+/// Whether a tweak's options have a `${` to expand (a template, or `$${` for a literal one)
+fn has_templates(tweak: &TweakDefinition) -> bool {
+    serde_json::to_string(&tweak.options).is_ok_and(|json| json.contains("${"))
+}
+
+/// Expand every `${variable}` in a tweak's options.
+fn expand_tweak(tweak: &TweakDefinition) -> Result<TweakDefinition, Error> {
+    let mut options = serde_json::to_value(&tweak.options)
+        .map_err(|e| Error::ValidationError(format!("Failed to serialize options: {}", e)))?;
+    expand_json_strings(&mut options, &mut template_value).map_err(|e| match e {
+        TemplateError::Unresolved(name) => {
+            Error::NotFound(format!("No value for template variable `${{{}}}`", name))
+        }
+        other => Error::ValidationError(other.to_string()),
+    })?;
+    let options = serde_json::from_value(options)
+        .map_err(|e| Error::ValidationError(format!("Failed to read expanded options: {}", e)))?;
+    Ok(TweakDefinition {
+        options,
+        ..tweak.clone()
+    })
+}
+
+/// This machine's value for a template variable, or None if it can't be determined.
+fn template_value(name: &str) -> Option<String> {
+    let env_var = |var: &str| env::var(var).ok().filter(|value| !value.is_empty());
+    match name {
+        "username" => env_var("USERNAME"),
+        "sid" => {
+            static SID: OnceLock<Option<String>> = OnceLock::new();
+            SID.get_or_init(|| {
+                registry_acl_service::current_user_sid()
+                    .inspect_err(|e| log::warn!("Could not read the current user's SID: {}", e))
+                    .ok()
+            })
+            .clone()
+        }
+        "user_profile" => env_var("USERPROFILE"),
+        "app_data" => env_var("APPDATA"),
+        "local_app_data" => env_var("LOCALAPPDATA"),
+        "program_files" => env_var("ProgramFiles"),
+        "program_files_x86" => env_var("ProgramFiles(x86)"),
+        "program_data" => env_var("ProgramData"),
+        "system_root" => env_var("SystemRoot"),
+        "system_drive" => env_var("SystemDrive"),
+        "gpu_vendor" => Some(system_info_service::system_facts().gpu_vendor.clone()),
+        _ => None,
+    }
+}
+
+/// Load all categories (pre-compiled at build time, plus those of custom tweak files).
 ///
 /// Categories are sorted by their `order` field.
 pub fn load_all_categories() -> Result<&'static [CategoryDefinition], Error> {
+    let categories = &loaded().categories;
     log::debug!(
-        "Returning {} pre-compiled categories",
+        "Returning {} categories ({} pre-compiled)",
+        categories.len(),
         crate::generated_tweaks::CATEGORY_COUNT
     );
-    Ok(CATEGORIES.as_slice())
+    Ok(categories.as_slice())
+}
+
+/// What loading the `tweaks.d` files found: the tweaks each one added, or why it was left out
+pub fn custom_tweaks_report() -> &'static CustomTweaksReport {
+    &loaded().custom
 }
 
 /// Get a specific tweak by ID.
 ///
-/// This is O(1) lookup from the pre-compiled HashMap.
+/// This is O(1) lookup from the pre-compiled HashMap. An alias (an earlier ID) finds the tweak
+/// under its current ID.
 pub fn get_tweak(tweak_id: &str) -> Result<Option<TweakDefinition>, Error> {
     log::trace!("Looking up tweak: {}", tweak_id);
-    let result = TWEAKS.get(tweak_id).cloned();
+    let loaded = loaded();
+    let tweak_id = loaded
+        .aliases
+        .get(tweak_id)
+        .map_or(tweak_id, String::as_str);
+    let result = loaded.tweaks.get(tweak_id).cloned();
     if result.is_none() {
         log::debug!("Tweak not found: {}", tweak_id);
     }
     Ok(result)
 }
 
+/// The current ID of the tweak once called `alias`, or None if it isn't an alias
+pub fn renamed_to(alias: &str) -> Option<&'static str> {
+    loaded().aliases.get(alias).map(String::as_str)
+}
+
 /// Filter tweaks by Windows version (u32: 10 or 11).
 ///
-/// Returns only tweaks that have registry changes applicable to the given version.
+/// Returns only tweaks that have registry changes applicable to the given version, and whose
+/// build range and editions include this machine.
 pub fn get_tweaks_for_version(version: u32) -> Result<Vec<&'static TweakDefinition>, Error> {
     log::debug!("Getting tweaks for Windows version: {}", version);
-    let total = TWEAKS.len();
+    let tweaks = &loaded().tweaks;
+    let total = tweaks.len();
 
-    // Borrow from the compiled-in map instead of deep-cloning up to 189 definitions per call.
-    let filtered: Vec<&'static TweakDefinition> = TWEAKS
+    // Borrow from the loaded map instead of deep-cloning up to 189 definitions per call.
+    let facts = system_info_service::system_facts();
+    let filtered: Vec<&'static TweakDefinition> = tweaks
         .values()
-        .filter(|tweak| tweak.applies_to_version(version))
+        .filter(|tweak| tweak.applies_to(version, facts))
         .collect();
 
     log::info!(
@@ -52,20 +535,192 @@ pub fn get_tweaks_for_version(version: u32) -> Result<Vec<&'static TweakDefiniti
     Ok(filtered)
 }
 
+/// Reloading the tweaks from their source files while a debug build runs
+#[cfg(debug_assertions)]
+mod hot_reload {
+    use super::*;
+    use crate::debug::emit_event;
+    use crate::models::tweak_fragments::FRAGMENTS_FILE;
+    use crate::state::AppState;
+    use notify::{RecursiveMode, Watcher};
+    use std::sync::mpsc;
+    use std::time::Duration;
+    use tauri::{AppHandle, Manager};
+
+    /// Event carrying a [`TweaksReload`] each time the tweaks were reloaded
+    pub const TWEAKS_RELOADED_EVENT: &str = "tweaks://reloaded";
+
+    /// The tweak YAML this build was compiled from
+    const SOURCE_TWEAKS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tweaks");
+
+    /// Wait after a change before reloading, since editors save a file in several steps
+    const SETTLE: Duration = Duration::from_millis(300);
+
+    /// What a reload did
+    #[derive(Debug, Clone, Serialize)]
+    pub struct TweaksReload {
+        /// False if a source file has errors; the tweaks in use are then left as they were
+        pub reloaded: bool,
+        pub tweak_count: usize,
+        pub category_count: usize,
+        /// Errors in the source files, or else in the `tweaks.d` files left out
+        pub errors: Vec<String>,
+    }
+
+    /// Reload the tweaks whenever a YAML file in the source or `tweaks.d` folder changes, until
+    /// the app exits
+    pub fn watch(app: AppHandle) {
+        std::thread::spawn(move || {
+            if let Err(e) = watch_folders(&app) {
+                log::warn!("Tweak hot reload is off: {}", e);
+            }
+        });
+    }
+
+    fn watch_folders(app: &AppHandle) -> notify::Result<()> {
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx)?;
+        watcher.watch(Path::new(SOURCE_TWEAKS_DIR), RecursiveMode::NonRecursive)?;
+        match backup::program_data_file(CUSTOM_TWEAKS_DIR) {
+            Ok(dir) if dir.is_dir() => watcher.watch(&dir, RecursiveMode::NonRecursive)?,
+            _ => log::debug!("No tweaks.d folder to watch"),
+        }
+        log::info!("Reloading tweaks when {} changes", SOURCE_TWEAKS_DIR);
+
+        while let Ok(event) = rx.recv() {
+            if !touches_yaml(&event) {
+                continue;
+            }
+            while rx.recv_timeout(SETTLE).is_ok() {}
+            let reload = reload();
+            // Statuses were detected against the old definitions
+            drop(app.state::<AppState>().status_cache.changing_all());
+            emit_event(TWEAKS_RELOADED_EVENT, reload);
+        }
+        Ok(())
+    }
+
+    fn touches_yaml(event: &notify::Result<notify::Event>) -> bool {
+        event.as_ref().is_ok_and(|event| {
+            !event.kind.is_access()
+                && event
+                    .paths
+                    .iter()
+                    .any(|path| path.extension().is_some_and(|e| e == "yaml" || e == "yml"))
+        })
+    }
+
+    /// Parse and validate every source file again and put the result in place of the loaded
+    /// tweaks, with `tweaks.d` read again too
+    fn reload() -> TweaksReload {
+        match parse_source_tweaks(Path::new(SOURCE_TWEAKS_DIR)) {
+            Ok((categories, tweaks)) => {
+                let new: &'static Loaded =
+                    Box::leak(Box::new(load(categories, tweaks, has_templates)));
+                *LOADED.write().unwrap_or_else(|e| e.into_inner()) = Some(new);
+                log::info!(
+                    "Reloaded {} tweaks in {} categories",
+                    new.tweaks.len(),
+                    new.categories.len()
+                );
+                TweaksReload {
+                    reloaded: true,
+                    tweak_count: new.tweaks.len(),
+                    category_count: new.categories.len(),
+                    errors: new
+                        .custom
+                        .files
+                        .iter()
+                        .flat_map(|file| file.errors.iter().cloned())
+                        .collect(),
+                }
+            }
+            Err(errors) => {
+                for error in &errors {
+                    log::warn!("Tweaks not reloaded: {}", error);
+                }
+                let current = loaded();
+                TweaksReload {
+                    reloaded: false,
+                    tweak_count: current.tweaks.len(),
+                    category_count: current.categories.len(),
+                    errors,
+                }
+            }
+        }
+    }
+
+    /// Every file in `dir`, validated against each other as build.rs does. Returns the errors if
+    /// any file has one.
+    fn parse_source_tweaks(
+        dir: &Path,
+    ) -> Result<(Vec<CategoryDefinition>, Vec<TweakDefinition>), Vec<String>> {
+        let mut files = yaml_files(dir)
+            .map_err(|e| vec![format!("Failed to read {}: {}", dir.display(), e)])?;
+        files.retain(|file| file != FRAGMENTS_FILE);
+        files.sort();
+        let fragments = match fs::read_to_string(dir.join(FRAGMENTS_FILE)) {
+            Ok(content) => Fragments::from_yaml(&content)
+                .map_err(|e| vec![format!("[{}] {}", FRAGMENTS_FILE, e)])?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Fragments::default(),
+            Err(e) => {
+                return Err(vec![format!(
+                    "[{}] Failed to read the file: {}",
+                    FRAGMENTS_FILE, e
+                )])
+            }
+        };
+
+        let mut ctx = ValidationContext::new();
+        let mut categories = Vec::new();
+        let mut tweaks = Vec::new();
+        for file in files {
+            match fs::read_to_string(dir.join(&file)) {
+                Ok(content) => {
+                    if let Some((category, parsed)) =
+                        parse_tweak_file(&mut ctx, &fragments, &file, &content)
+                    {
+                        categories.push(category);
+                        tweaks.extend(parsed);
+                    }
+                }
+                Err(e) => ctx
+                    .errors
+                    .push(format!("[{}] Failed to read the file: {}", file, e)),
+            }
+        }
+        for name in fragments.unused() {
+            ctx.warnings
+                .push(format!("[{}] No tweak includes '{}'", FRAGMENTS_FILE, name));
+        }
+        for warning in &ctx.warnings {
+            log::info!("Tweak warning: {}", warning);
+        }
+        if ctx.has_errors() {
+            return Err(ctx.errors);
+        }
+        Ok((categories, tweaks))
+    }
+}
+
+#[cfg(debug_assertions)]
+pub use hot_reload::watch;
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    /// Guards the build.rs <-> models/tweak.rs type mirror.
+    /// Guards the round trip of the embedded data.
     ///
-    /// build.rs parses the YAML with its own hand-written copy of these types and
-    /// serializes the result to tweaks.json; the runtime types here deserialize it.
-    /// The two are maintained by hand and drift silently: a field renamed on one
-    /// side only, or added to the build mirror but not to `TweakDefinition` (which
-    /// is `deny_unknown_fields`), produces a panic inside a `LazyLock` on the first
-    /// tweak lookup -- i.e. at runtime, on a user's machine, not at compile time.
+    /// build.rs serializes the parsed YAML to tweaks.bin with the same types the
+    /// runtime deserializes it with (`models/tweak_schema.rs`, which build.rs includes
+    /// by `#[path]`), so the fields can't drift apart. The encoding still can: a type
+    /// that serializes to something it doesn't deserialize from (a hand-written impl
+    /// like `WindowsBuild`'s, an untagged enum, a skipped field) produces a panic
+    /// inside a `LazyLock` on the first tweak lookup -- i.e. at runtime, on a user's
+    /// machine, not at compile time.
     ///
-    /// Touching either side runs this, so the drift surfaces here instead.
+    /// Decoding the real data here surfaces that in the tests instead.
     #[test]
     fn embedded_tweak_data_deserializes_into_the_runtime_types() {
         // Forcing the LazyLock is the whole point: this is where the .expect() lives.
@@ -100,4 +755,106 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn the_embedded_schema_describes_tweak_files() {
+        let schema = tweak_schema();
+        let properties = &schema["properties"];
+        assert!(properties["category"].is_object(), "{}", schema);
+        assert!(properties["tweaks"].is_object(), "{}", schema);
+        assert!(schema["$defs"]["TweakOption"].is_object());
+    }
+
+    fn custom_file(tweak_id: &str) -> String {
+        format!(
+            r#"
+category:
+  id: my_tweaks
+  name: "My Tweaks"
+  description: "Tweaks of my own"
+  icon: "mdi:star"
+  order: 100
+tweaks:
+  - id: {}
+    name: "Show Seconds"
+    description: "Show seconds in the taskbar clock"
+    risk_level: low
+    options:
+      - label: "Shown"
+        registry_changes:
+          - hive: HKCU
+            key: "Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\Advanced"
+            value_name: "ShowSecondsInSystemClock"
+            value_type: "REG_DWORD"
+            value: 1
+      - label: "Hidden"
+        registry_changes:
+          - hive: HKCU
+            key: "Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\Advanced"
+            value_name: "ShowSecondsInSystemClock"
+            value_type: "REG_DWORD"
+            value: 0
+"#,
+            tweak_id
+        )
+    }
+
+    #[test]
+    fn a_valid_custom_file_loads_as_custom_tweaks() {
+        let mut ctx = ValidationContext::new();
+        let (category, tweaks) =
+            load_custom_file(&mut ctx, "mine.yaml", &custom_file("show_seconds")).unwrap();
+        assert_eq!(category.id, "my_tweaks");
+        assert_eq!(tweaks.len(), 1);
+        assert!(tweaks[0].custom);
+        assert_eq!(tweaks[0].category_id, "my_tweaks");
+        assert!(!ctx.has_errors());
+
+        // The same file again: its category and tweak ID are taken
+        assert!(load_custom_file(&mut ctx, "again.yaml", &custom_file("show_seconds")).is_none());
+    }
+
+    #[test]
+    fn a_custom_file_with_errors_is_rejected_without_claiming_its_ids() {
+        let compiled_id = TWEAKS.keys().next().unwrap().clone();
+        let mut ctx = ValidationContext::new();
+        ctx.seen_tweak_ids.insert(compiled_id.clone());
+
+        assert!(load_custom_file(&mut ctx, "clash.yaml", &custom_file(&compiled_id)).is_none());
+        assert!(ctx.has_errors());
+        assert!(!ctx.seen_category_ids.contains_key("my_tweaks"));
+
+        ctx.errors.clear();
+        assert!(load_custom_file(&mut ctx, "mine.yaml", &custom_file("show_seconds")).is_some());
+        assert!(load_custom_file(&mut ctx, "broken.yaml", "tweaks: [").is_none());
+    }
+
+    #[test]
+    fn expanding_a_tweak_without_templates_keeps_its_options() {
+        let (_, tweak) = TWEAKS
+            .iter()
+            .find(|(_, tweak)| {
+                !serde_json::to_string(&tweak.options)
+                    .unwrap()
+                    .contains("${")
+            })
+            .expect("no template-free tweak compiled in");
+        let expanded = expand_tweak(tweak).unwrap();
+        assert_eq!(
+            serde_json::to_value(&expanded.options).unwrap(),
+            serde_json::to_value(&tweak.options).unwrap()
+        );
+    }
+
+    #[test]
+    fn build_lists_exactly_the_tweaks_with_templates() {
+        for (id, tweak) in TWEAKS.iter() {
+            assert_eq!(
+                TEMPLATED_TWEAKS.contains(&id.as_str()),
+                has_templates(tweak),
+                "TEMPLATED_TWEAKS is wrong about '{}'",
+                id
+            );
+        }
+    }
 }
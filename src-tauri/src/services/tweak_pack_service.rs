@@ -0,0 +1,625 @@
+//! Tweak packs: signed bundles of tweak YAML downloaded from a channel the user configures.
+//!
+//! A channel is an HTTPS URL serving three kinds of file: `manifest.json` (the pack's name,
+//! version, and each YAML file with its SHA-256), `manifest.json.sig` (the hex Ed25519 signature of
+//! the manifest's bytes), and the YAML files the manifest lists. The user sets the URL together
+//! with the hex public key the channel signs with; nothing from the channel is used unless the
+//! manifest verifies against that key and every file matches its hash.
+//!
+//! Installing a pack checks its tweaks with the build's validation (see `tweak_loader`) and stages
+//! it in `tweak_packs\staged` in the ProgramData app folder, unless the same pack is already
+//! installed or staged at that version or a later one. At the next launch `activate_staged` puts it
+//! in place of the pack in `tweak_packs\active`, whose files `tweak_loader` reads with the
+//! `tweaks.d` ones, as custom tweaks. One pack is installed at a time.
+//!
+//! The signature and hashes are checked again whenever a pack is activated or loaded, against the
+//! saved public key, and only the files its manifest lists are read: what's on disk is only trusted
+//! as far as the channel signed it. A pack whose channel key was changed since is no longer loaded.
+//! `tweak_packs` is one of the folders only Administrators and SYSTEM can write to (see
+//! `protected_dir`).
+
+use crate::error::Error;
+use crate::services::{backup, protected_dir, tweak_loader};
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Once;
+use std::time::Duration;
+
+/// Folder (in the ProgramData app folder) holding the settings and the packs
+const PACKS_DIR: &str = "tweak_packs";
+
+const SETTINGS_FILE: &str = "settings.json";
+const STAGED_DIR: &str = "staged";
+const ACTIVE_DIR: &str = "active";
+
+pub(crate) const MANIFEST_FILE: &str = "manifest.json";
+const SIGNATURE_FILE: &str = "manifest.json.sig";
+
+/// Largest manifest, signature, or YAML file downloaded
+const MAX_DOWNLOAD_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Most YAML files in a pack
+const MAX_PACK_FILES: usize = 64;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Where tweak packs come from
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TweakPackSettings {
+    /// The channel's base URL (https), or None to use no channel
+    #[serde(default)]
+    pub url: Option<String>,
+    /// The channel's Ed25519 public key, 64 hex digits
+    #[serde(default)]
+    pub public_key: Option<String>,
+}
+
+/// One YAML file of a pack
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackFile {
+    /// File name, relative to the channel URL
+    pub name: String,
+    /// Lowercase hex SHA-256 of its contents
+    pub sha256: String,
+}
+
+/// A pack's `manifest.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackManifest {
+    pub name: String,
+    /// Increases with each release of the pack
+    pub version: u64,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub files: Vec<PackFile>,
+}
+
+/// What `check_for_tweak_updates` found
+#[derive(Debug, Clone, Serialize)]
+pub struct TweakPackUpdate {
+    /// Whether the channel has a newer version than the one installed or staged
+    pub available: bool,
+    /// The pack in use, if any
+    pub installed: Option<PackManifest>,
+    /// The pack waiting for the next launch, if any
+    pub staged: Option<PackManifest>,
+    /// The channel's pack
+    pub latest: PackManifest,
+}
+
+/// The tweak packs folder. The first call creates it for Administrators and SYSTEM only if it
+/// doesn't exist (or restricts it, when elevated), as `backup` does the ProgramData app folder.
+fn packs_dir() -> Result<PathBuf, Error> {
+    static SECURED: Once = Once::new();
+    let dir = backup::program_data_file(PACKS_DIR)?;
+    SECURED.call_once(|| {
+        if let Err(e) = protected_dir::ensure(&dir) {
+            log::warn!("{}", e);
+        }
+    });
+    Ok(dir)
+}
+
+fn settings_path() -> Result<PathBuf, Error> {
+    Ok(packs_dir()?.join(SETTINGS_FILE))
+}
+
+fn write_file(path: &Path, content: &[u8]) -> Result<(), Error> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| {
+            Error::BackupFailed(format!("Failed to create {}: {}", dir.display(), e))
+        })?;
+    }
+    fs::write(path, content)
+        .map_err(|e| Error::BackupFailed(format!("Failed to write {}: {}", path.display(), e)))
+}
+
+/// The saved settings, or the defaults if there are none
+pub fn get_settings() -> Result<TweakPackSettings, Error> {
+    let path = settings_path()?;
+    match fs::read(&path) {
+        Ok(content) => serde_json::from_slice(&content).map_err(|e| {
+            Error::BackupFailed(format!(
+                "Corrupt tweak pack settings {}: {}",
+                path.display(),
+                e
+            ))
+        }),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(TweakPackSettings::default()),
+        Err(e) => Err(Error::BackupFailed(format!(
+            "Failed to read tweak pack settings: {}",
+            e
+        ))),
+    }
+}
+
+/// Save the channel's URL and public key, after checking their format
+pub fn set_settings(settings: TweakPackSettings) -> Result<TweakPackSettings, Error> {
+    let settings = TweakPackSettings {
+        url: settings
+            .url
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty()),
+        public_key: settings
+            .public_key
+            .map(|key| key.trim().to_ascii_lowercase())
+            .filter(|key| !key.is_empty()),
+    };
+    if let Some(url) = &settings.url {
+        if !url.starts_with("https://") {
+            return Err(Error::ValidationError(
+                "The tweak pack URL must use https".to_string(),
+            ));
+        }
+    }
+    if let Some(key) = &settings.public_key {
+        verifying_key(key)?;
+    }
+    let json = serde_json::to_string_pretty(&settings).map_err(|e| {
+        Error::BackupFailed(format!("Failed to serialize tweak pack settings: {}", e))
+    })?;
+    write_file(&settings_path()?, json.as_bytes())?;
+    Ok(settings)
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    let value = value.trim();
+    if value.len() % 2 != 0 || !value.is_ascii() {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&value[i..i + 2], 16).ok())
+        .collect()
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// `content` as text, if it matches `file`'s hash in the manifest
+fn checked_content(file: &PackFile, content: Vec<u8>) -> Result<String, Error> {
+    if sha256_hex(&content) != file.sha256.to_ascii_lowercase() {
+        return Err(Error::Update(format!(
+            "Tweak pack file '{}' doesn't match the manifest's hash",
+            file.name
+        )));
+    }
+    String::from_utf8(content)
+        .map_err(|_| Error::Update(format!("Tweak pack file '{}' isn't UTF-8 text", file.name)))
+}
+
+fn verifying_key(hex: &str) -> Result<VerifyingKey, Error> {
+    let bytes: [u8; 32] = decode_hex(hex)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| {
+            Error::ValidationError(
+                "The tweak pack public key must be 64 hex digits (an Ed25519 key)".to_string(),
+            )
+        })?;
+    VerifyingKey::from_bytes(&bytes)
+        .map_err(|e| Error::ValidationError(format!("Invalid tweak pack public key: {}", e)))
+}
+
+/// Check `manifest` (its raw bytes) against `signature` (hex) and `key`, then parse it
+fn verify_manifest(
+    key: &VerifyingKey,
+    manifest: &[u8],
+    signature: &str,
+) -> Result<PackManifest, Error> {
+    let signature: [u8; 64] = decode_hex(signature)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| Error::Update("The tweak pack signature is malformed".to_string()))?;
+    key.verify_strict(manifest, &Signature::from_bytes(&signature))
+        .map_err(|_| {
+            Error::Update("The tweak pack manifest doesn't match its signature".to_string())
+        })?;
+
+    let manifest: PackManifest = serde_json::from_slice(manifest)
+        .map_err(|e| Error::Update(format!("Invalid tweak pack manifest: {}", e)))?;
+    if manifest.files.is_empty() || manifest.files.len() > MAX_PACK_FILES {
+        return Err(Error::Update(format!(
+            "A tweak pack has 1 to {} files; this one has {}",
+            MAX_PACK_FILES,
+            manifest.files.len()
+        )));
+    }
+    for file in &manifest.files {
+        let plain_name = !file.name.contains(['/', '\\', ':']) && !file.name.starts_with('.');
+        let yaml = file.name.ends_with(".yaml") || file.name.ends_with(".yml");
+        if !plain_name || !yaml {
+            return Err(Error::Update(format!(
+                "Tweak pack file '{}' isn't a plain .yaml file name",
+                file.name
+            )));
+        }
+    }
+    Ok(manifest)
+}
+
+/// The channel's base URL and key, or an error if either isn't set
+fn channel() -> Result<(String, VerifyingKey), Error> {
+    let settings = get_settings()?;
+    match (settings.url, settings.public_key) {
+        (Some(url), Some(key)) => Ok((url.trim_end_matches('/').to_string(), verifying_key(&key)?)),
+        _ => Err(Error::ValidationError(
+            "Set the tweak pack URL and public key first".to_string(),
+        )),
+    }
+}
+
+fn download(agent: &ureq::Agent, url: &str) -> Result<Vec<u8>, Error> {
+    let response = agent
+        .get(url)
+        .set("User-Agent", "MagicX-Toolbox-TweakPacks")
+        .call()
+        .map_err(|e| Error::Update(format!("Failed to download {}: {}", url, e)))?;
+    let mut bytes = Vec::new();
+    response
+        .into_reader()
+        .take(MAX_DOWNLOAD_BYTES + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| Error::Update(format!("Failed to read {}: {}", url, e)))?;
+    if bytes.len() as u64 > MAX_DOWNLOAD_BYTES {
+        return Err(Error::Update(format!(
+            "{} is larger than {} bytes",
+            url, MAX_DOWNLOAD_BYTES
+        )));
+    }
+    Ok(bytes)
+}
+
+fn agent() -> ureq::Agent {
+    ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build()
+}
+
+/// Download the manifest of the channel at `url` and verify it with `key`. Returns it with its raw
+/// bytes and signature.
+fn fetch_manifest(
+    agent: &ureq::Agent,
+    url: &str,
+    key: &VerifyingKey,
+) -> Result<(PackManifest, Vec<u8>, String), Error> {
+    let raw = download(agent, &format!("{}/{}", url, MANIFEST_FILE))?;
+    let signature = download(agent, &format!("{}/{}", url, SIGNATURE_FILE))?;
+    let signature = String::from_utf8_lossy(&signature).trim().to_string();
+    let manifest = verify_manifest(key, &raw, &signature)?;
+    Ok((manifest, raw, signature))
+}
+
+/// The saved public key, to verify a pack on disk with. The settings file must be owned by
+/// Administrators or SYSTEM, as a file `tweak_loader` loads must.
+fn saved_key() -> Result<VerifyingKey, Error> {
+    let key = get_settings()?.public_key.ok_or_else(|| {
+        Error::Update("No tweak pack public key is set to verify the pack with".to_string())
+    })?;
+    if !protected_dir::owned_by_admins(&settings_path()?)? {
+        return Err(Error::Update(
+            "The tweak pack settings aren't owned by Administrators or SYSTEM (save them again as administrator)"
+                .to_string(),
+        ));
+    }
+    verifying_key(&key)
+}
+
+/// The manifest of the pack in `dir` verified against `key`, or None if there's no pack in `dir`
+fn verified_manifest(key: &VerifyingKey, dir: &Path) -> Result<Option<PackManifest>, Error> {
+    let raw = match fs::read(dir.join(MANIFEST_FILE)) {
+        Ok(raw) => raw,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(Error::Update(format!(
+                "Failed to read the tweak pack manifest in {}: {}",
+                dir.display(),
+                e
+            )))
+        }
+    };
+    let signature = fs::read_to_string(dir.join(SIGNATURE_FILE)).map_err(|e| {
+        Error::Update(format!(
+            "Failed to read the tweak pack signature in {}: {}",
+            dir.display(),
+            e
+        ))
+    })?;
+    verify_manifest(key, &raw, signature.trim()).map(Some)
+}
+
+/// A pack's YAML files, by name with their contents
+type PackContents = Vec<(String, String)>;
+
+/// The pack in `dir` verified against `key`: its manifest, and the files the manifest lists in its
+/// order, each checked against its hash. None if there's no pack in `dir`.
+fn verified_pack(
+    key: &VerifyingKey,
+    dir: &Path,
+) -> Result<Option<(PackManifest, PackContents)>, Error> {
+    let Some(manifest) = verified_manifest(key, dir)? else {
+        return Ok(None);
+    };
+    let files = manifest
+        .files
+        .iter()
+        .map(|file| {
+            let content = fs::read(dir.join(&file.name)).map_err(|e| {
+                Error::Update(format!(
+                    "Failed to read tweak pack file '{}': {}",
+                    file.name, e
+                ))
+            })?;
+            Ok((file.name.clone(), checked_content(file, content)?))
+        })
+        .collect::<Result<_, Error>>()?;
+    Ok(Some((manifest, files)))
+}
+
+/// The YAML files (name and contents) of the pack in use, for `tweak_loader`, or none if no pack
+/// is installed. The pack is verified against the saved public key first.
+pub fn active_pack_files() -> Result<PackContents, Error> {
+    let dir = packs_dir()?.join(ACTIVE_DIR);
+    if !dir.join(MANIFEST_FILE).exists() {
+        return Ok(Vec::new());
+    }
+    Ok(verified_pack(&saved_key()?, &dir)?
+        .map(|(_, files)| files)
+        .unwrap_or_default())
+}
+
+/// The installed and staged packs' manifests, verified against `key`. One that doesn't verify is
+/// logged and taken as absent.
+fn local_manifests(
+    key: &VerifyingKey,
+) -> Result<(Option<PackManifest>, Option<PackManifest>), Error> {
+    let dir = packs_dir()?;
+    let read = |name: &str| {
+        verified_manifest(key, &dir.join(name)).unwrap_or_else(|e| {
+            log::warn!("Ignoring the {} tweak pack: {}", name, e);
+            None
+        })
+    };
+    Ok((read(ACTIVE_DIR), read(STAGED_DIR)))
+}
+
+/// Whether `latest` is newer than the same pack installed or staged. The staged pack is the one
+/// that counts when there is one, since it replaces the installed pack at the next launch.
+fn is_newer(
+    latest: &PackManifest,
+    installed: Option<&PackManifest>,
+    staged: Option<&PackManifest>,
+) -> bool {
+    staged
+        .or(installed)
+        .filter(|pack| pack.name == latest.name)
+        .is_none_or(|pack| latest.version > pack.version)
+}
+
+/// Compare the channel's pack with the installed and staged ones
+pub fn check_for_updates() -> Result<TweakPackUpdate, Error> {
+    let (url, key) = channel()?;
+    let (latest, _, _) = fetch_manifest(&agent(), &url, &key)?;
+    let (installed, staged) = local_manifests(&key)?;
+    let available = is_newer(&latest, installed.as_ref(), staged.as_ref());
+    log::info!(
+        "Tweak pack '{}' version {} on the channel (update available: {})",
+        latest.name,
+        latest.version,
+        available
+    );
+    Ok(TweakPackUpdate {
+        available,
+        installed,
+        staged,
+        latest,
+    })
+}
+
+/// Download the channel's pack, verify and validate it, and stage it for the next launch.
+/// Returns its manifest.
+pub fn install() -> Result<PackManifest, Error> {
+    let (url, key) = channel()?;
+    let agent = agent();
+    let (manifest, raw_manifest, signature) = fetch_manifest(&agent, &url, &key)?;
+
+    let (installed, staged) = local_manifests(&key)?;
+    if !is_newer(&manifest, installed.as_ref(), staged.as_ref()) {
+        return Err(Error::Update(format!(
+            "Tweak pack '{}' version {} isn't newer than the one installed or staged",
+            manifest.name, manifest.version
+        )));
+    }
+
+    let mut files = Vec::with_capacity(manifest.files.len());
+    for file in &manifest.files {
+        let content = download(&agent, &format!("{}/{}", url, file.name))?;
+        files.push((file.name.clone(), checked_content(file, content)?));
+    }
+
+    let errors = tweak_loader::validate_tweak_pack(&files);
+    if !errors.is_empty() {
+        for error in &errors {
+            log::warn!("Tweak pack '{}' rejected: {}", manifest.name, error);
+        }
+        return Err(Error::ValidationError(format!(
+            "Tweak pack '{}' has {} error(s); first: {}",
+            manifest.name,
+            errors.len(),
+            errors[0]
+        )));
+    }
+
+    // Written beside the staged pack and then renamed over it, so a failed install leaves the
+    // previous one staged
+    let dir = packs_dir()?;
+    let incoming = dir.join(format!("{}.incoming", STAGED_DIR));
+    let _ = fs::remove_dir_all(&incoming);
+    write_file(&incoming.join(MANIFEST_FILE), &raw_manifest)?;
+    write_file(&incoming.join(SIGNATURE_FILE), signature.as_bytes())?;
+    for (name, content) in &files {
+        write_file(&incoming.join(name), content.as_bytes())?;
+    }
+    replace_dir(&incoming, &dir.join(STAGED_DIR))?;
+
+    log::info!(
+        "Staged tweak pack '{}' version {} ({} files) for the next launch",
+        manifest.name,
+        manifest.version,
+        files.len()
+    );
+    Ok(manifest)
+}
+
+/// Put the folder `from` in place of `to`
+fn replace_dir(from: &Path, to: &Path) -> Result<(), Error> {
+    match fs::remove_dir_all(to) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => {
+            return Err(Error::BackupFailed(format!(
+                "Failed to remove {}: {}",
+                to.display(),
+                e
+            )))
+        }
+    }
+    fs::rename(from, to).map_err(|e| {
+        Error::BackupFailed(format!(
+            "Failed to move {} to {}: {}",
+            from.display(),
+            to.display(),
+            e
+        ))
+    })
+}
+
+/// Make the staged pack, if there is one and it still verifies against the saved public key, the
+/// pack in use. Call at launch, before the tweaks are loaded.
+pub fn activate_staged() {
+    let Ok(dir) = packs_dir() else {
+        return;
+    };
+    let staged = dir.join(STAGED_DIR);
+    if !staged.join(MANIFEST_FILE).exists() {
+        return;
+    }
+    let manifest = match saved_key().and_then(|key| verified_pack(&key, &staged)) {
+        Ok(Some((manifest, _))) => manifest,
+        Ok(None) => return,
+        Err(e) => {
+            log::warn!("Not installing the staged tweak pack: {}", e);
+            return;
+        }
+    };
+    match replace_dir(&staged, &dir.join(ACTIVE_DIR)) {
+        Ok(()) => log::info!(
+            "Installed tweak pack '{}' version {}",
+            manifest.name,
+            manifest.version
+        ),
+        Err(e) => log::warn!("Failed to install the staged tweak pack: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn manifest_json(file_name: &str) -> Vec<u8> {
+        serde_json::to_vec(&serde_json::json!({
+            "name": "community",
+            "version": 3,
+            "files": [{ "name": file_name, "sha256": sha256_hex(b"tweaks: []") }],
+        }))
+        .unwrap()
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn a_manifest_is_only_read_with_a_matching_signature() {
+        let signing = SigningKey::from_bytes(&[7u8; 32]);
+        let key = verifying_key(&hex(signing.verifying_key().as_bytes())).unwrap();
+        let raw = manifest_json("extra.yaml");
+        let signature = hex(&signing.sign(&raw).to_bytes());
+
+        let manifest = verify_manifest(&key, &raw, &signature).unwrap();
+        assert_eq!(manifest.version, 3);
+
+        let mut tampered = raw.clone();
+        tampered[10] ^= 1;
+        assert!(verify_manifest(&key, &tampered, &signature).is_err());
+        assert!(verify_manifest(&key, &raw, "00").is_err());
+    }
+
+    #[test]
+    fn a_pack_on_disk_loads_only_its_listed_files_with_matching_hashes() {
+        let signing = SigningKey::from_bytes(&[5u8; 32]);
+        let dir = tempfile::tempdir().unwrap();
+        let raw = manifest_json("extra.yaml");
+        fs::write(dir.path().join(MANIFEST_FILE), &raw).unwrap();
+        fs::write(
+            dir.path().join(SIGNATURE_FILE),
+            hex(&signing.sign(&raw).to_bytes()),
+        )
+        .unwrap();
+        fs::write(dir.path().join("extra.yaml"), "tweaks: []").unwrap();
+        fs::write(dir.path().join("unlisted.yaml"), "tweaks: []").unwrap();
+
+        let (_, files) = verified_pack(&signing.verifying_key(), dir.path())
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            files,
+            [("extra.yaml".to_string(), "tweaks: []".to_string())]
+        );
+
+        let other = SigningKey::from_bytes(&[6u8; 32]).verifying_key();
+        assert!(verified_pack(&other, dir.path()).is_err());
+
+        fs::write(dir.path().join("extra.yaml"), "tweaks: [] ").unwrap();
+        assert!(verified_pack(&signing.verifying_key(), dir.path()).is_err());
+    }
+
+    #[test]
+    fn only_a_later_version_of_the_same_pack_is_newer() {
+        let pack = |name: &str, version| PackManifest {
+            name: name.to_string(),
+            version,
+            description: None,
+            files: Vec::new(),
+        };
+        let latest = pack("community", 3);
+        assert!(is_newer(&latest, None, None));
+        assert!(is_newer(&latest, Some(&pack("community", 2)), None));
+        assert!(!is_newer(&latest, Some(&pack("community", 3)), None));
+        assert!(!is_newer(&latest, Some(&pack("community", 4)), None));
+        assert!(!is_newer(
+            &latest,
+            Some(&pack("community", 2)),
+            Some(&pack("community", 3))
+        ));
+        assert!(is_newer(
+            &latest,
+            Some(&pack("community", 5)),
+            Some(&pack("other", 9))
+        ));
+        assert!(is_newer(&latest, Some(&pack("other", 9)), None));
+    }
+
+    #[test]
+    fn a_manifest_can_only_name_plain_yaml_files() {
+        let signing = SigningKey::from_bytes(&[9u8; 32]);
+        let key = signing.verifying_key();
+        for name in ["..\\evil.yaml", "sub/extra.yaml", "extra.exe", ".yaml"] {
+            let raw = manifest_json(name);
+            let signature = hex(&signing.sign(&raw).to_bytes());
+            assert!(verify_manifest(&key, &raw, &signature).is_err(), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,343 @@
+//! Tweak usage statistics: how often each tweak is applied and reverted, and how often it fails.
+//!
+//! Counting is opt-in (`set_usage_stats_enabled`, kept in the storage settings) and off by
+//! default. While it's on, every apply and revert, whichever path runs it (a batch, a deferred
+//! apply, an undo, auto-reapply), is counted in `usage_stats.json` in the ProgramData app folder,
+//! next to the storage settings. Failures are counted by error code, never by message, since
+//! messages hold paths and account names. Nothing leaves the machine: `export_stats` writes an
+//! anonymized copy (built-in tweaks only, counts and error codes, no times) for the user to share
+//! when reporting flaky tweaks.
+
+use crate::error::Error;
+use crate::models::TweakResult;
+use crate::services::{backup_service, tweak_loader};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const STATS_FILE: &str = "usage_stats.json";
+
+/// Code counted for an operation that reported failure without an error
+const FAILED_CODE: &str = "FAILED";
+
+/// Serializes the statistics' read-modify-write
+static STATS_LOCK: Mutex<()> = Mutex::new(());
+
+/// What was done to a tweak
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsageAction {
+    Apply,
+    Revert,
+}
+
+/// Counts for one tweak
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TweakUsage {
+    /// Successful applies
+    #[serde(default)]
+    pub applied: u32,
+    /// Successful reverts
+    #[serde(default)]
+    pub reverted: u32,
+    /// Applies and reverts that failed
+    #[serde(default)]
+    pub failed: u32,
+    /// Failures by error code (see `Error::code`)
+    #[serde(default)]
+    pub failures: BTreeMap<String, u32>,
+    /// When the tweak was last applied or reverted (RFC 3339)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used: Option<String>,
+}
+
+/// Counts by tweak ID
+pub type UsageStats = BTreeMap<String, TweakUsage>;
+
+/// What `get_tweak_stats` returns
+#[derive(Debug, Clone, Serialize)]
+pub struct TweakStatsReport {
+    /// Whether operations are being counted
+    pub enabled: bool,
+    pub tweaks: UsageStats,
+}
+
+/// An anonymized export
+#[derive(Debug, Clone, Serialize)]
+struct StatsExport {
+    app_version: &'static str,
+    tweaks: Vec<ExportedUsage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct ExportedUsage {
+    tweak_id: String,
+    applied: u32,
+    reverted: u32,
+    failed: u32,
+    failures: BTreeMap<String, u32>,
+}
+
+fn stats_path() -> Result<PathBuf, Error> {
+    backup_service::program_data_file(STATS_FILE)
+}
+
+fn read() -> Result<UsageStats, Error> {
+    let path = stats_path()?;
+    match fs::read(&path) {
+        Ok(content) => serde_json::from_slice(&content).map_err(|e| {
+            Error::BackupFailed(format!(
+                "Corrupt usage statistics {}: {}",
+                path.display(),
+                e
+            ))
+        }),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(UsageStats::new()),
+        Err(e) => Err(Error::BackupFailed(format!(
+            "Failed to read usage statistics: {}",
+            e
+        ))),
+    }
+}
+
+fn update<T>(change: impl FnOnce(&mut UsageStats) -> T) -> Result<T, Error> {
+    let _guard = STATS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut stats = read()?;
+    let result = change(&mut stats);
+    write(&stats_path()?, &stats)?;
+    Ok(result)
+}
+
+fn write(path: &Path, value: &impl Serialize) -> Result<(), Error> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| {
+            Error::BackupFailed(format!("Failed to create {}: {}", dir.display(), e))
+        })?;
+    }
+    let json = serde_json::to_string_pretty(value)
+        .map_err(|e| Error::BackupFailed(format!("Failed to serialize usage statistics: {}", e)))?;
+    fs::write(path, json)
+        .map_err(|e| Error::BackupFailed(format!("Failed to write {}: {}", path.display(), e)))
+}
+
+/// The error code an operation failed with, None if it succeeded. A cancelled operation is neither.
+fn failure_code(outcome: &Result<(TweakResult, bool), Error>) -> Option<Option<&'static str>> {
+    match outcome {
+        Ok((result, _)) if result.success => Some(None),
+        Ok(_) => Some(Some(FAILED_CODE)),
+        Err(Error::Cancelled(_)) => None,
+        Err(e) => Some(Some(e.code())),
+    }
+}
+
+/// Count one operation on `tweak_id` in `stats`
+fn count(
+    stats: &mut UsageStats,
+    tweak_id: &str,
+    action: UsageAction,
+    failure: Option<&str>,
+    now: String,
+) {
+    let usage = stats.entry(tweak_id.to_string()).or_default();
+    match (failure, action) {
+        (Some(code), _) => {
+            usage.failed += 1;
+            *usage.failures.entry(code.to_string()).or_default() += 1;
+        }
+        (None, UsageAction::Apply) => usage.applied += 1,
+        (None, UsageAction::Revert) => usage.reverted += 1,
+    }
+    usage.last_used = Some(now);
+}
+
+/// Count an apply or revert of `tweak_id`, if counting is on. The operation has already happened,
+/// so a failure to count it is logged rather than returned.
+pub fn record(tweak_id: &str, action: UsageAction, outcome: &Result<(TweakResult, bool), Error>) {
+    if !backup_service::get_usage_stats_enabled() {
+        return;
+    }
+    let Some(failure) = failure_code(outcome) else {
+        return;
+    };
+    let now = chrono::Local::now().to_rfc3339();
+    if let Err(e) = update(|stats| count(stats, tweak_id, action, failure, now)) {
+        log::warn!("Failed to count {:?} of '{}': {}", action, tweak_id, e);
+    }
+}
+
+/// The counts, and whether counting is on
+pub fn get_stats() -> Result<TweakStatsReport, Error> {
+    Ok(TweakStatsReport {
+        enabled: backup_service::get_usage_stats_enabled(),
+        tweaks: read()?,
+    })
+}
+
+/// Forget every count
+pub fn reset_stats() -> Result<(), Error> {
+    update(|stats| stats.clear())?;
+    log::info!("Usage statistics reset");
+    Ok(())
+}
+
+/// The counts of the built-in tweaks (`is_builtin`), without times. Custom tweaks are left out:
+/// their IDs are the user's own.
+fn anonymize(stats: &UsageStats, is_builtin: impl Fn(&str) -> bool) -> Vec<ExportedUsage> {
+    stats
+        .iter()
+        .filter(|(tweak_id, _)| is_builtin(tweak_id))
+        .map(|(tweak_id, usage)| ExportedUsage {
+            tweak_id: tweak_id.clone(),
+            applied: usage.applied,
+            reverted: usage.reverted,
+            failed: usage.failed,
+            failures: usage.failures.clone(),
+        })
+        .collect()
+}
+
+/// Write the anonymized counts to `path`. Returns the number of tweaks exported.
+pub fn export_stats(path: &Path) -> Result<usize, Error> {
+    let tweaks = anonymize(&read()?, |tweak_id| {
+        tweak_loader::get_tweak(tweak_id)
+            .ok()
+            .flatten()
+            .is_some_and(|tweak| !tweak.custom)
+    });
+    let count = tweaks.len();
+    write(
+        path,
+        &StatsExport {
+            app_version: env!("CARGO_PKG_VERSION"),
+            tweaks,
+        },
+    )?;
+    log::info!(
+        "Exported the usage statistics of {} tweaks to {}",
+        count,
+        path.display()
+    );
+    Ok(count)
+}
+
+/// Add the counts kept under a renamed tweak's earlier ID (`renamed_to` gives its current one) to
+/// the current ID's
+pub fn rename_stats<'a>(renamed_to: impl Fn(&str) -> Option<&'a str>) -> Result<(), Error> {
+    if !read()?
+        .keys()
+        .any(|tweak_id| renamed_to(tweak_id).is_some())
+    {
+        return Ok(());
+    }
+    update(|stats| move_renamed(stats, renamed_to))
+}
+
+fn move_renamed<'a>(stats: &mut UsageStats, renamed_to: impl Fn(&str) -> Option<&'a str>) {
+    let renamed: Vec<(String, &str)> = stats
+        .keys()
+        .filter_map(|old_id| renamed_to(old_id).map(|new_id| (old_id.clone(), new_id)))
+        .collect();
+    for (old_id, new_id) in renamed {
+        let Some(old) = stats.remove(&old_id) else {
+            continue;
+        };
+        let usage = stats.entry(new_id.to_string()).or_default();
+        usage.applied += old.applied;
+        usage.reverted += old.reverted;
+        usage.failed += old.failed;
+        for (code, failures) in old.failures {
+            *usage.failures.entry(code).or_default() += failures;
+        }
+        usage.last_used = usage.last_used.take().max(old.last_used);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(success: bool) -> Result<(TweakResult, bool), Error> {
+        Ok((
+            TweakResult {
+                success,
+                message: String::new(),
+                requires_reboot: false,
+                requires_logoff: false,
+                restarted_explorer: false,
+                failures: Vec::new(),
+                restore_point_sequence: None,
+                results: Vec::new(),
+                rollback: None,
+            },
+            false,
+        ))
+    }
+
+    #[test]
+    fn failures_are_counted_by_code_and_cancellations_not_at_all() {
+        assert_eq!(failure_code(&result(true)), Some(None));
+        assert_eq!(failure_code(&result(false)), Some(Some(FAILED_CODE)));
+        assert_eq!(
+            failure_code(&Err(Error::Timeout("x".into()))),
+            Some(Some("TIMEOUT"))
+        );
+        assert_eq!(failure_code(&Err(Error::Cancelled("x".into()))), None);
+
+        let mut stats = UsageStats::new();
+        count(&mut stats, "t", UsageAction::Apply, None, "1".into());
+        count(&mut stats, "t", UsageAction::Revert, None, "2".into());
+        count(
+            &mut stats,
+            "t",
+            UsageAction::Apply,
+            Some("TIMEOUT"),
+            "3".into(),
+        );
+        count(
+            &mut stats,
+            "t",
+            UsageAction::Revert,
+            Some("TIMEOUT"),
+            "4".into(),
+        );
+
+        let usage = &stats["t"];
+        assert_eq!((usage.applied, usage.reverted, usage.failed), (1, 1, 2));
+        assert_eq!(usage.failures["TIMEOUT"], 2);
+        assert_eq!(usage.last_used.as_deref(), Some("4"));
+    }
+
+    #[test]
+    fn the_export_leaves_out_custom_tweaks_and_times() {
+        let mut stats = UsageStats::new();
+        count(&mut stats, "builtin", UsageAction::Apply, None, "1".into());
+        count(&mut stats, "my_own", UsageAction::Apply, None, "1".into());
+
+        let exported = anonymize(&stats, |tweak_id| tweak_id == "builtin");
+        assert_eq!(exported.len(), 1);
+        assert_eq!(exported[0].tweak_id, "builtin");
+        let json = serde_json::to_string(&exported).unwrap();
+        assert!(!json.contains("last_used"), "{}", json);
+    }
+
+    #[test]
+    fn renaming_adds_the_old_counts_to_the_new_id() {
+        let mut stats = UsageStats::new();
+        count(
+            &mut stats,
+            "old",
+            UsageAction::Apply,
+            Some("TIMEOUT"),
+            "2".into(),
+        );
+        count(&mut stats, "new", UsageAction::Apply, None, "1".into());
+
+        move_renamed(&mut stats, |id| (id == "old").then_some("new"));
+        assert!(!stats.contains_key("old"));
+        let usage = &stats["new"];
+        assert_eq!((usage.applied, usage.failed), (1, 1));
+        assert_eq!(usage.last_used.as_deref(), Some("2"));
+    }
+}
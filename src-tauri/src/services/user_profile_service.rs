@@ -0,0 +1,307 @@
+//! Target user for HKCU registry changes.
+//!
+//! When the app is elevated with a different administrator's credentials, HKCU is that
+//! administrator's hive, not the hive of the user the tweaks are meant for. Picking a target user
+//! routes every HKCU registry change to that user's hive under HKU instead: `HKCU\X` becomes
+//! `HKU\<SID>\X`, and `HKCU\Software\Classes\X` becomes `HKU\<SID>_Classes\X`, since per-user
+//! classes live in a hive of their own.
+//!
+//! A signed-in user's hives are already loaded under HKU. Anyone else's are loaded from their
+//! profile (`NTUSER.DAT` and `UsrClass.dat`) with `RegLoadKey`, and unloaded again when the target
+//! changes or the app exits, because Windows can't sign the user in while their hive is loaded by
+//! someone else. Snapshots record the translated HKU path, so reverting reaches the same user's
+//! hive whatever the target is by then. The target lasts for the session and isn't saved.
+//!
+//! Only registry changes (values and key ACLs) are routed. Changes made through Windows APIs that
+//! act on the calling user (visual effects applied live, Group Policy, `${sid}`-style template
+//! values) still apply to the account the app runs as.
+
+use crate::error::Error;
+use crate::services::elevation::enable_privilege;
+use crate::services::{file_service, registry_acl_service, system_info_service};
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Mutex;
+use winreg::enums::{HKEY_LOCAL_MACHINE, HKEY_USERS};
+use winreg::RegKey;
+
+use windows_sys::Win32::Foundation::ERROR_SUCCESS;
+use windows_sys::Win32::System::Registry::{
+    RegLoadKeyW, RegUnLoadKeyW, HKEY_USERS as RAW_HKEY_USERS,
+};
+
+const PROFILE_LIST_KEY: &str = "SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion\\ProfileList";
+
+/// HKCU subkey that lives in the user's `_Classes` hive rather than in `NTUSER.DAT`
+const CLASSES_KEY: &str = "Software\\Classes";
+
+/// Suffix of the HKU subkey holding a user's classes hive
+const CLASSES_HIVE_SUFFIX: &str = "_Classes";
+
+/// The user's classes hive file, relative to their profile folder
+const CLASSES_HIVE_FILE: &str = "AppData\\Local\\Microsoft\\Windows\\UsrClass.dat";
+
+/// The current target, if it isn't the account the app runs as
+static TARGET: Mutex<Option<Target>> = Mutex::new(None);
+
+#[derive(Debug)]
+struct Target {
+    sid: String,
+    /// HKU subkeys the app loaded for this target, unloaded when it's released
+    loaded: Vec<String>,
+}
+
+/// A user profile on this machine
+#[derive(Debug, Clone, Serialize)]
+pub struct UserProfile {
+    pub sid: String,
+    /// The profile folder's name, which is the user name unless the account was renamed
+    pub name: String,
+    pub profile_path: String,
+    /// Whether the app runs as this user
+    pub is_current: bool,
+    /// Whether the user's hive is loaded under HKU (they're signed in, or the app loaded it)
+    pub loaded: bool,
+}
+
+/// Whether a ProfileList entry is a local or domain user account (`S-1-5-21-...`). Service
+/// accounts have profiles too, and a profile Windows failed to load leaves a `.bak` entry.
+fn is_account_sid(name: &str) -> bool {
+    name.strip_prefix("S-1-5-21-").is_some_and(|rest| {
+        !rest.is_empty() && rest.chars().all(|c| c.is_ascii_digit() || c == '-')
+    })
+}
+
+/// Where an HKCU key lives under HKU for the user with `sid`.
+pub fn user_hive_key(sid: &str, key: &str) -> String {
+    let key = key.trim_matches('\\');
+    let in_classes = key.len() >= CLASSES_KEY.len()
+        && key.is_char_boundary(CLASSES_KEY.len())
+        && key[..CLASSES_KEY.len()].eq_ignore_ascii_case(CLASSES_KEY)
+        && (key.len() == CLASSES_KEY.len() || key[CLASSES_KEY.len()..].starts_with('\\'));
+    if in_classes {
+        format!(
+            "{}{}{}",
+            sid,
+            CLASSES_HIVE_SUFFIX,
+            &key[CLASSES_KEY.len()..]
+        )
+    } else if key.is_empty() {
+        sid.to_string()
+    } else {
+        format!("{}\\{}", sid, key)
+    }
+}
+
+fn hive_loaded(subkey: &str) -> bool {
+    RegKey::predef(HKEY_USERS).open_subkey(subkey).is_ok()
+}
+
+/// User profiles on this machine, by name
+pub fn list_profiles() -> Result<Vec<UserProfile>, Error> {
+    let current = registry_acl_service::current_user_sid().ok();
+    let list = RegKey::predef(HKEY_LOCAL_MACHINE)
+        .open_subkey(PROFILE_LIST_KEY)
+        .map_err(|e| Error::RegistryOperation(format!("Failed to open profile list: {}", e)))?;
+
+    let mut profiles = Vec::new();
+    for sid in list.enum_keys().filter_map(|name| name.ok()) {
+        if !is_account_sid(&sid) {
+            continue;
+        }
+        let Ok(path) = list
+            .open_subkey(&sid)
+            .and_then(|key| key.get_value::<String, _>("ProfileImagePath"))
+        else {
+            continue;
+        };
+        let profile_path = file_service::expand_path(&path);
+        let name = Path::new(&profile_path)
+            .file_name()
+            .map_or_else(|| sid.clone(), |name| name.to_string_lossy().into_owned());
+        profiles.push(UserProfile {
+            is_current: current.as_deref() == Some(sid.as_str()),
+            loaded: hive_loaded(&sid),
+            name,
+            profile_path,
+            sid,
+        });
+    }
+    profiles.sort_by_key(|profile| profile.name.to_lowercase());
+    Ok(profiles)
+}
+
+/// SID of the user HKCU changes are routed to, or None for the account the app runs as
+pub fn target_user() -> Option<String> {
+    TARGET
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .map(|target| target.sid.clone())
+}
+
+/// Where an HKCU key should be written: the target user's HKU path, or None to use HKCU itself
+pub fn route_hkcu_key(key: &str) -> Option<String> {
+    target_user().map(|sid| user_hive_key(&sid, key))
+}
+
+fn load_hive(subkey: &str, file: &Path) -> Result<(), Error> {
+    let subkey_wide: Vec<u16> = subkey.encode_utf16().chain(Some(0)).collect();
+    let file_wide: Vec<u16> = file
+        .to_string_lossy()
+        .encode_utf16()
+        .chain(Some(0))
+        .collect();
+    // SAFETY: both strings are NUL-terminated UTF-16 buffers that outlive the call.
+    let status = unsafe { RegLoadKeyW(RAW_HKEY_USERS, subkey_wide.as_ptr(), file_wide.as_ptr()) };
+    if status != ERROR_SUCCESS {
+        return Err(Error::RegistryOperation(format!(
+            "Failed to load {} into HKU\\{}: {}",
+            file.display(),
+            subkey,
+            std::io::Error::from_raw_os_error(status as i32)
+        )));
+    }
+    log::info!("Loaded {} into HKU\\{}", file.display(), subkey);
+    Ok(())
+}
+
+fn unload_hive(subkey: &str) {
+    let subkey_wide: Vec<u16> = subkey.encode_utf16().chain(Some(0)).collect();
+    // SAFETY: `subkey_wide` is a NUL-terminated UTF-16 buffer that outlives the call.
+    let status = unsafe { RegUnLoadKeyW(RAW_HKEY_USERS, subkey_wide.as_ptr()) };
+    if status == ERROR_SUCCESS {
+        log::info!("Unloaded HKU\\{}", subkey);
+    } else {
+        log::warn!(
+            "Failed to unload HKU\\{}: {}",
+            subkey,
+            std::io::Error::from_raw_os_error(status as i32)
+        );
+    }
+}
+
+/// Make sure a user's hives are loaded under HKU, returning the ones the app had to load
+fn load_user_hives(profile: &UserProfile) -> Result<Vec<String>, Error> {
+    let mut loaded = Vec::new();
+    if hive_loaded(&profile.sid) && hive_loaded(&format!("{}{}", profile.sid, CLASSES_HIVE_SUFFIX))
+    {
+        return Ok(loaded);
+    }
+    enable_privilege("SeBackupPrivilege")?;
+    enable_privilege("SeRestorePrivilege")?;
+
+    let profile_dir = Path::new(&profile.profile_path);
+    if !hive_loaded(&profile.sid) {
+        load_hive(&profile.sid, &profile_dir.join("NTUSER.DAT"))?;
+        loaded.push(profile.sid.clone());
+    }
+    let classes = format!("{}{}", profile.sid, CLASSES_HIVE_SUFFIX);
+    if !hive_loaded(&classes) {
+        // Without it only Software\Classes changes fail; the rest of HKCU still works
+        match load_hive(&classes, &profile_dir.join(CLASSES_HIVE_FILE)) {
+            Ok(()) => loaded.push(classes),
+            Err(e) => log::warn!("{}", e),
+        }
+    }
+    Ok(loaded)
+}
+
+/// Route HKCU changes to the user with `sid`, loading their hive if they aren't signed in. None,
+/// or the SID of the account the app runs as, goes back to HKCU. Hives loaded for the previous
+/// target are unloaded.
+pub fn set_target_user(sid: Option<&str>) -> Result<Option<UserProfile>, Error> {
+    let profile = match sid {
+        Some(sid) => Some(
+            list_profiles()?
+                .into_iter()
+                .find(|profile| profile.sid.eq_ignore_ascii_case(sid))
+                .ok_or_else(|| Error::NotFound(format!("No user profile with SID {}", sid)))?,
+        ),
+        None => None,
+    }
+    .filter(|profile| !profile.is_current);
+
+    let mut target = TARGET.lock().unwrap_or_else(|e| e.into_inner());
+    if target.as_ref().map(|t| t.sid.as_str()) == profile.as_ref().map(|p| p.sid.as_str()) {
+        return Ok(profile);
+    }
+    if profile.is_some() && !system_info_service::is_running_as_admin() {
+        return Err(Error::RequiresAdmin);
+    }
+
+    if let Some(previous) = target.take() {
+        previous
+            .loaded
+            .iter()
+            .rev()
+            .for_each(|subkey| unload_hive(subkey));
+    }
+    if let Some(profile) = &profile {
+        let loaded = load_user_hives(profile)?;
+        log::info!("HKCU changes now go to {} ({})", profile.name, profile.sid);
+        *target = Some(Target {
+            sid: profile.sid.clone(),
+            loaded,
+        });
+    } else {
+        log::info!("HKCU changes now go to the current user");
+    }
+    Ok(profile)
+}
+
+/// Unload any hives loaded for the target user. Called when the app exits.
+pub fn release_target_user() {
+    if let Some(target) = TARGET.lock().unwrap_or_else(|e| e.into_inner()).take() {
+        target
+            .loaded
+            .iter()
+            .rev()
+            .for_each(|subkey| unload_hive(subkey));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SID: &str = "S-1-5-21-1004336348-1177238915-682003330-1001";
+
+    #[test]
+    fn hkcu_keys_translate_to_the_users_hive() {
+        assert_eq!(
+            user_hive_key(SID, "Software\\Microsoft\\Windows"),
+            format!("{}\\Software\\Microsoft\\Windows", SID)
+        );
+        assert_eq!(
+            user_hive_key(SID, "\\Control Panel\\Desktop\\"),
+            format!("{}\\Control Panel\\Desktop", SID)
+        );
+        assert_eq!(user_hive_key(SID, ""), SID);
+    }
+
+    #[test]
+    fn user_classes_translate_to_the_classes_hive() {
+        assert_eq!(
+            user_hive_key(SID, "Software\\Classes\\CLSID\\{x}"),
+            format!("{}_Classes\\CLSID\\{{x}}", SID)
+        );
+        assert_eq!(
+            user_hive_key(SID, "SOFTWARE\\classes"),
+            format!("{}_Classes", SID)
+        );
+        // Only the Classes key itself, not a sibling that starts the same way
+        assert_eq!(
+            user_hive_key(SID, "Software\\ClassesExtra"),
+            format!("{}\\Software\\ClassesExtra", SID)
+        );
+    }
+
+    #[test]
+    fn only_user_account_sids_are_profiles() {
+        assert!(is_account_sid(SID));
+        assert!(!is_account_sid("S-1-5-18"));
+        assert!(!is_account_sid(&format!("{}.bak", SID)));
+        assert!(!is_account_sid("S-1-5-21-"));
+    }
+}
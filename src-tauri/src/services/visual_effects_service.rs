@@ -0,0 +1,118 @@
+//! Visual effects stored as bits of `UserPreferencesMask`.
+//!
+//! Most Performance Options effects share one REG_BINARY value (HKCU `Control Panel\Desktop`
+//! `UserPreferencesMask`), one bit each, next to bits for unrelated settings. Tweak authors name
+//! the effects instead of writing the blob: the current mask is read, only the bits of the listed
+//! effects are set or cleared, and the result is written as a single registry change, so it goes
+//! through the registry apply, snapshot, and restore paths. Detection compares the bits one by
+//! one. Effects stored in a value of their own map to it in `VisualEffectsChange::to_registry_change`.
+
+use crate::error::Error;
+use crate::models::{
+    RegistryAction, RegistryChange, RegistryHive, RegistryValueType, VisualEffectsChange,
+    USER_PREFERENCES_MASK_KEY, USER_PREFERENCES_MASK_VALUE,
+};
+use crate::services::registry_service;
+
+/// Mask of a fresh install ("Let Windows choose"), used as the base when the value is missing
+const DEFAULT_PREFERENCES_MASK: [u8; 8] = [0x9E, 0x1E, 0x07, 0x80, 0x12, 0x00, 0x00, 0x00];
+
+/// The current `UserPreferencesMask`, or `None` if it isn't set
+pub fn read_preferences_mask() -> Result<Option<Vec<u8>>, Error> {
+    match registry_service::read_binary(
+        &RegistryHive::Hkcu,
+        USER_PREFERENCES_MASK_KEY,
+        USER_PREFERENCES_MASK_VALUE,
+    ) {
+        Err(Error::RegistryKeyNotFound(_)) => Ok(None),
+        result => result,
+    }
+}
+
+/// Whether `bit` of byte `byte` is set; bytes past the end of the mask count as clear
+pub fn mask_bit_is_set(mask: &[u8], (byte, bit): (usize, u8)) -> bool {
+    mask.get(byte).is_some_and(|value| value & bit != 0)
+}
+
+/// Set or clear the bit of every mask effect in `changes`, leaving all other bits as they are
+pub fn apply_mask_bits(mask: &mut Vec<u8>, changes: &[VisualEffectsChange]) {
+    for change in changes {
+        let Some((byte, bit)) = change.effect.mask_bit() else {
+            continue;
+        };
+        if mask.len() <= byte {
+            mask.resize(byte + 1, 0);
+        }
+        if change.enabled {
+            mask[byte] |= bit;
+        } else {
+            mask[byte] &= !bit;
+        }
+    }
+}
+
+/// Whether `change` writes `UserPreferencesMask`
+pub fn is_preferences_mask(change: &RegistryChange) -> bool {
+    change.hive == RegistryHive::Hkcu
+        && change.key.eq_ignore_ascii_case(USER_PREFERENCES_MASK_KEY)
+        && change
+            .value_name
+            .eq_ignore_ascii_case(USER_PREFERENCES_MASK_VALUE)
+}
+
+/// The `UserPreferencesMask` write for the mask effects in `changes`, based on the current mask;
+/// `None` if none of them is stored in the mask
+pub fn preferences_mask_change(
+    changes: &[VisualEffectsChange],
+) -> Result<Option<RegistryChange>, Error> {
+    let mask_changes: Vec<&VisualEffectsChange> = changes
+        .iter()
+        .filter(|change| change.effect.mask_bit().is_some())
+        .collect();
+    if mask_changes.is_empty() {
+        return Ok(None);
+    }
+
+    let mut mask = read_preferences_mask()?.unwrap_or_else(|| DEFAULT_PREFERENCES_MASK.to_vec());
+    apply_mask_bits(&mut mask, changes);
+
+    Ok(Some(RegistryChange {
+        hive: RegistryHive::Hkcu,
+        key: USER_PREFERENCES_MASK_KEY.to_string(),
+        value_name: USER_PREFERENCES_MASK_VALUE.to_string(),
+        action: RegistryAction::Set,
+        value_type: Some(RegistryValueType::Binary),
+        value: Some(serde_json::json!(mask)),
+        windows_versions: None,
+        conditions: None,
+        requires_reboot: false,
+        requires_logoff: false,
+        // The write covers every effect, so failures are only ignored if all of them allow it
+        skip_validation: mask_changes.iter().all(|change| change.skip_validation),
+        elevation: None,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_bits_of_listed_effects_change() {
+        let changes: Vec<VisualEffectsChange> = serde_json::from_value(serde_json::json!([
+            { "effect": "menu_animation", "enabled": false },
+            { "effect": "cursor_shadow", "enabled": true },
+            { "effect": "control_animations", "enabled": true },
+            { "effect": "transparency", "enabled": false },
+        ]))
+        .unwrap();
+
+        let mut mask = vec![0x9E, 0x12, 0x03, 0x80];
+        apply_mask_bits(&mut mask, &changes);
+
+        assert_eq!(mask, vec![0x9C, 0x32, 0x03, 0x80, 0x02]);
+        assert!(!mask_bit_is_set(&mask, (0, 0x02)));
+        assert!(mask_bit_is_set(&mask, (1, 0x20)));
+        assert!(!mask_bit_is_set(&mask, (7, 0x01)));
+    }
+}
@@ -0,0 +1,239 @@
+//! Windows Update status: pending updates, the last install, the pause, and pending reboots.
+//!
+//! Pending updates and the install history come from the Windows Update Agent (`UpdateSession`
+//! COM API). The search is offline, so it reports what Windows Update's last scan found rather
+//! than contacting the update servers. The pause is read from Windows Update's settings, and a
+//! pending reboot from the markers Component Based Servicing, Windows Update, and the Session
+//! Manager leave in the registry; `reboot_pending_reasons` is cheap enough for `get_system_info`.
+
+use crate::error::Error;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::Serialize;
+use std::cell::Cell;
+use windows::core::BSTR;
+use windows::Win32::Foundation::VARIANT_FALSE;
+use windows::Win32::System::Com::{
+    CoCreateInstance, CoInitializeEx, CLSCTX_ALL, COINIT_MULTITHREADED,
+};
+use windows::Win32::System::UpdateAgent::{
+    orcSucceeded, orcSucceededWithErrors, uoInstallation, IUpdateSearcher, IUpdateSession,
+    UpdateSession,
+};
+use winreg::enums::*;
+use winreg::RegKey;
+
+const CBS_REBOOT_PENDING_KEY: &str =
+    "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Component Based Servicing\\RebootPending";
+const WU_REBOOT_REQUIRED_KEY: &str =
+    "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\WindowsUpdate\\Auto Update\\RebootRequired";
+const SESSION_MANAGER_KEY: &str = "SYSTEM\\CurrentControlSet\\Control\\Session Manager";
+const WU_UX_SETTINGS_KEY: &str = "SOFTWARE\\Microsoft\\WindowsUpdate\\UX\\Settings";
+
+/// Updates the last scan found that aren't installed or hidden
+const PENDING_CRITERIA: &str = "IsInstalled=0 and IsHidden=0";
+
+/// History entries looked through for the last install
+const HISTORY_DEPTH: i32 = 50;
+
+/// Why Windows is waiting for a reboot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RebootReason {
+    /// Component Based Servicing has changes to finish (`RebootPending`)
+    ComponentServicing,
+    /// Windows Update installed updates that need a reboot (`RebootRequired`)
+    WindowsUpdate,
+    /// Files are queued to be replaced at boot (`PendingFileRenameOperations`)
+    FileRenames,
+}
+
+/// What `get_update_status` returns
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateStatus {
+    /// Titles of the updates the last scan found that aren't installed yet; None if the Windows
+    /// Update Agent couldn't be queried
+    pub pending_updates: Option<Vec<String>>,
+    /// When an update was last installed (RFC 3339), if the history has one
+    pub last_install: Option<String>,
+    /// Title of that update
+    pub last_install_title: Option<String>,
+    /// Whether updates are paused
+    pub paused: bool,
+    /// When the pause ends (RFC 3339), while updates are paused
+    pub paused_until: Option<String>,
+    pub reboot_pending: bool,
+    pub reboot_reasons: Vec<RebootReason>,
+}
+
+thread_local! {
+    /// Whether this thread has initialized COM (see `scheduler_service::with_task_service`)
+    static COM_READY: Cell<bool> = const { Cell::new(false) };
+}
+
+fn com_err(e: windows::core::Error) -> Error {
+    Error::CommandExecution(format!("Windows Update Agent COM error: {}", e))
+}
+
+/// The registry markers of a pending reboot that are set
+pub fn reboot_pending_reasons() -> Vec<RebootReason> {
+    let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
+    let key_exists = |path: &str| hklm.open_subkey_with_flags(path, KEY_READ).is_ok();
+
+    let mut reasons = Vec::new();
+    if key_exists(CBS_REBOOT_PENDING_KEY) {
+        reasons.push(RebootReason::ComponentServicing);
+    }
+    if key_exists(WU_REBOOT_REQUIRED_KEY) {
+        reasons.push(RebootReason::WindowsUpdate);
+    }
+    let renames = hklm
+        .open_subkey_with_flags(SESSION_MANAGER_KEY, KEY_READ)
+        .and_then(|key| key.get_value::<Vec<String>, _>("PendingFileRenameOperations"));
+    if renames.is_ok_and(|renames| renames.iter().any(|path| !path.is_empty())) {
+        reasons.push(RebootReason::FileRenames);
+    }
+    reasons
+}
+
+/// Whether Windows is waiting for a reboot
+pub fn is_reboot_pending() -> bool {
+    !reboot_pending_reasons().is_empty()
+}
+
+/// Windows Update's status. The pending updates and history are left out (and logged) if the
+/// Windows Update Agent can't be queried, e.g. while its service is disabled.
+pub fn get_update_status() -> Result<UpdateStatus, Error> {
+    let (pending_updates, last) = match create_searcher() {
+        Ok(searcher) => (
+            pending_updates(&searcher)
+                .map_err(|e| log::warn!("Failed to list pending updates: {}", e))
+                .ok(),
+            last_install(&searcher)
+                .map_err(|e| log::warn!("Failed to read the update history: {}", e))
+                .ok()
+                .flatten(),
+        ),
+        Err(e) => {
+            log::warn!("Windows Update Agent is unavailable: {}", e);
+            (None, None)
+        }
+    };
+    let (last_install, last_install_title) = last.unzip();
+
+    let paused_until = pause_expiry().filter(|until| *until > Utc::now());
+    let reboot_reasons = reboot_pending_reasons();
+    Ok(UpdateStatus {
+        pending_updates,
+        last_install,
+        last_install_title,
+        paused: paused_until.is_some(),
+        paused_until: paused_until.map(|until| until.to_rfc3339()),
+        reboot_pending: !reboot_reasons.is_empty(),
+        reboot_reasons,
+    })
+}
+
+fn create_searcher() -> Result<IUpdateSearcher, Error> {
+    // SAFETY: COM is initialized once per thread, as in scheduler_service; the session and searcher
+    // are COM objects released when dropped.
+    unsafe {
+        COM_READY.with(|ready| {
+            if !ready.get() {
+                let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+                ready.set(true);
+            }
+        });
+        let session: IUpdateSession =
+            CoCreateInstance(&UpdateSession, None, CLSCTX_ALL).map_err(com_err)?;
+        let searcher = session.CreateUpdateSearcher().map_err(com_err)?;
+        searcher.SetOnline(VARIANT_FALSE).map_err(com_err)?;
+        Ok(searcher)
+    }
+}
+
+fn pending_updates(searcher: &IUpdateSearcher) -> Result<Vec<String>, Error> {
+    // SAFETY: COM calls on a live searcher; every index is below the collection's count.
+    unsafe {
+        let updates = searcher
+            .Search(&BSTR::from(PENDING_CRITERIA))
+            .and_then(|result| result.Updates())
+            .map_err(com_err)?;
+        let count = updates.Count().map_err(com_err)?;
+        (0..count)
+            .map(|i| {
+                let title = updates.get_Item(i)?.Title()?;
+                Ok(title.to_string())
+            })
+            .collect::<windows::core::Result<_>>()
+            .map_err(com_err)
+    }
+}
+
+/// The date (RFC 3339) and title of the last update installed, if the history has one
+fn last_install(searcher: &IUpdateSearcher) -> Result<Option<(String, String)>, Error> {
+    // SAFETY: COM calls on a live searcher; every index is below the collection's count.
+    unsafe {
+        let total = searcher.GetTotalHistoryCount().map_err(com_err)?;
+        if total == 0 {
+            return Ok(None);
+        }
+        // The history is newest first
+        let history = searcher
+            .QueryHistory(0, total.min(HISTORY_DEPTH))
+            .map_err(com_err)?;
+        for i in 0..history.Count().map_err(com_err)? {
+            let entry = history.get_Item(i).map_err(com_err)?;
+            let installed = entry.Operation().map_err(com_err)? == uoInstallation
+                && matches!(
+                    entry.ResultCode().map_err(com_err)?,
+                    orcSucceeded | orcSucceededWithErrors
+                );
+            if !installed {
+                continue;
+            }
+            let Some(date) = ole_date_to_utc(entry.Date().map_err(com_err)?) else {
+                continue;
+            };
+            let title = entry.Title().map_err(com_err)?.to_string();
+            return Ok(Some((date.to_rfc3339(), title)));
+        }
+        Ok(None)
+    }
+}
+
+/// An OLE Automation date (days since 1899-12-30, the fraction being the time of day) in UTC,
+/// as the update history records it
+fn ole_date_to_utc(date: f64) -> Option<DateTime<Utc>> {
+    if !date.is_finite() || date <= 0.0 {
+        return None;
+    }
+    let epoch = NaiveDate::from_ymd_opt(1899, 12, 30)?.and_hms_opt(0, 0, 0)?;
+    let time = epoch.checked_add_signed(Duration::milliseconds((date * 86_400_000.0) as i64))?;
+    Some(time.and_utc())
+}
+
+/// When the pause set in Settings ends, if one was set
+fn pause_expiry() -> Option<DateTime<Utc>> {
+    let expiry: String = RegKey::predef(HKEY_LOCAL_MACHINE)
+        .open_subkey_with_flags(WU_UX_SETTINGS_KEY, KEY_READ)
+        .and_then(|key| key.get_value("PauseUpdatesExpiryTime"))
+        .ok()?;
+    DateTime::parse_from_rfc3339(expiry.trim())
+        .ok()
+        .map(|expiry| expiry.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ole_dates_count_days_from_the_end_of_1899() {
+        assert_eq!(
+            ole_date_to_utc(45306.5).unwrap().to_rfc3339(),
+            "2024-01-15T12:00:00+00:00"
+        );
+        assert_eq!(ole_date_to_utc(0.0), None);
+        assert_eq!(ole_date_to_utc(f64::NAN), None);
+    }
+}
@@ -1,5 +1,7 @@
-use crate::services::backup_service;
-use tauri::App;
+use crate::services::{backup_service, relaunch_service, tweak_pack_service};
+use crate::state::AppState;
+use crate::{change_watcher, deferred_runner, snapshot_monitor};
+use tauri::{App, Manager};
 
 pub fn setup(app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
     // Register the handle debug events are emitted through. Held in debug.rs rather
@@ -7,6 +9,19 @@ pub fn setup(app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
     // DEBUG_APP there. Must happen before anything that might emit.
     crate::debug::set_debug_app(app.handle().clone());
 
+    // A tweak pack installed last run takes effect now, before anything loads the tweaks
+    tweak_pack_service::activate_staged();
+
+    // Changes staged by the instance that relaunched this one elevated, for the UI to pick up
+    relaunch_service::load_resumed_operations();
+
+    // Snapshots of renamed tweaks move to the new ID first, so they aren't taken for strays
+    match backup_service::migrate_renamed_tweaks() {
+        Ok(0) => {}
+        Ok(moved) => log::info!("Moved {} snapshot(s) of renamed tweaks", moved),
+        Err(e) => log::warn!("Failed to move the snapshots of renamed tweaks: {}", e),
+    }
+
     // Validate all snapshots on startup
     // This removes stale snapshots where the tweak was externally reverted
     log::info!("Validating snapshots on startup...");
@@ -24,5 +39,54 @@ pub fn setup(app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
+    // Age limits only prune when history is written; apply them to history left since last run
+    if let Err(e) = backup_service::cleanup_old_backups() {
+        log::warn!("Failed to clean up old snapshot history: {}", e);
+    }
+
+    // Applies cut short by a crash are offered for rollback by the UI (`get_incomplete_applies`)
+    match backup_service::incomplete_apply_intents() {
+        Ok(intents) if !intents.is_empty() => {
+            log::warn!(
+                "{} apply operation(s) did not complete last run: {}",
+                intents.len(),
+                intents
+                    .iter()
+                    .map(|intent| intent.tweak_id.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+        Ok(_) => {}
+        Err(e) => log::warn!("Failed to read apply intents: {}", e),
+    }
+
+    // Capture the baseline on first run, before the UI can apply anything
+    if let Err(e) = backup_service::ensure_baseline() {
+        log::warn!("Failed to capture baseline: {}", e);
+    }
+
+    // Revalidate applied tweaks periodically and after resume from sleep
+    tauri::async_runtime::spawn(snapshot_monitor::run(app.handle().clone()));
+    // ...and as soon as something changes their keys or services
+    change_watcher::spawn(app.handle().clone());
+    // Apply what was queued for this boot or for a time that has come
+    tauri::async_runtime::spawn(deferred_runner::run(app.handle().clone()));
+
+    // Gather the hardware info now, so the dashboard's first load is served from the cache
+    let handle = app.handle().clone();
+    if let Err(e) = std::thread::Builder::new()
+        .name("system-info-warmup".into())
+        .spawn(move || {
+            handle.state::<AppState>().system_info.get();
+        })
+    {
+        log::warn!("Failed to start gathering the hardware info: {}", e);
+    }
+
+    // Pick up edits to the tweak YAML without a rebuild
+    #[cfg(debug_assertions)]
+    crate::services::tweak_loader::watch(app.handle().clone());
+
     Ok(())
 }
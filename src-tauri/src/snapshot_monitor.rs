@@ -0,0 +1,328 @@
+//! Background revalidation of applied tweaks.
+//!
+//! Snapshots are validated at startup, but a tweak changed behind the app's back later (by the
+//! user, another tool, or Windows itself) kept its old status until the next restart. This task
+//! re-detects the state of every tweak with a snapshot every `REVALIDATE_INTERVAL`, and right after
+//! the machine resumes from sleep (noticed as a tick that took far longer than it should have), and
+//! records the new status of each one that changed since the last pass in the status cache, which
+//! emits `tweak://status-changed` for it (see `status_cache`). `change_watcher` asks for a pass as
+//! soon as a registry key or service of an applied tweak changes.
+//!
+//! A tweak that no longer has the option it was applied with (Windows Update or another tool
+//! reverted it) is also reported with `tweak://external-change`. When the user turned on
+//! auto-reapply (see `backup_service::get_auto_reapply`), the option is applied again first, at
+//! most once per `REAPPLY_COOLDOWN` for a tweak so the app doesn't fight whatever keeps reverting it.
+//!
+//! Stale snapshots are still only removed at startup: deleting one while a tweak is being applied
+//! would lose its original state, and a pass can't rule that out. For the same reason a pass that
+//! overlapped a tweak operation (see `SnapshotMonitor::operation`) is thrown away, since it may
+//! have seen the tweak half-applied.
+
+use crate::commands::tweaks::apply::apply_option;
+use crate::debug::emit_event;
+use crate::error::Result;
+use crate::models::TweakStatus;
+use crate::services::{backup_service, system_info_service, tweak_loader};
+use crate::state::AppState;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime};
+use tauri::{AppHandle, Manager};
+
+/// Event carrying an [`ExternalChange`]
+pub const TWEAK_EXTERNAL_CHANGE_EVENT: &str = "tweak://external-change";
+
+/// How often applied tweaks are revalidated
+const REVALIDATE_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// How often the task wakes up to check for a resume or a requested pass
+const TICK: Duration = Duration::from_secs(5);
+
+/// A tick this much longer than `TICK` means the machine was asleep
+const RESUME_GAP: Duration = Duration::from_secs(60);
+
+/// Shortest time between two automatic re-applies of the same tweak
+const REAPPLY_COOLDOWN: Duration = Duration::from_secs(60 * 60);
+
+/// The monitor's part of `AppState`: which tweak operations are running
+#[derive(Debug, Default)]
+pub struct SnapshotMonitor {
+    /// Tweak operations (apply, revert, restore, ...) currently running
+    in_flight: AtomicUsize,
+    /// Tweak operations started since launch, so a pass can tell whether one ran while it did
+    started: AtomicU64,
+    /// A pass was asked for before the next `REVALIDATE_INTERVAL`
+    pass_requested: AtomicBool,
+}
+
+/// Marks a tweak operation as running until dropped
+pub struct OperationGuard<'a>(&'a SnapshotMonitor);
+
+impl Drop for OperationGuard<'_> {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl SnapshotMonitor {
+    /// Mark a tweak operation as running, so revalidation doesn't report what it sees meanwhile.
+    /// Commands that change tweak state hold the guard for their whole run.
+    pub fn operation(&self) -> OperationGuard<'_> {
+        self.started.fetch_add(1, Ordering::SeqCst);
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        OperationGuard(self)
+    }
+
+    /// Run a pass at the next tick (or once the running operations end) instead of waiting for
+    /// `REVALIDATE_INTERVAL`
+    pub fn request_pass(&self) {
+        self.pass_requested.store(true, Ordering::SeqCst);
+    }
+
+    /// The number of operations started so far, if none is running
+    fn quiet_since(&self) -> Option<u64> {
+        (self.in_flight.load(Ordering::SeqCst) == 0).then(|| self.started.load(Ordering::SeqCst))
+    }
+}
+
+/// A tweak found without the option it was applied with
+#[derive(Debug, Clone, Serialize)]
+pub struct ExternalChange {
+    pub tweak_id: String,
+    pub tweak_name: String,
+    /// Option the tweak was applied with
+    pub applied_option_index: usize,
+    pub applied_option_label: String,
+    /// Option it has now (None: its state matches none of them)
+    pub current_option_index: Option<usize>,
+    /// Whether auto-reapply applied the option again
+    pub reapplied: bool,
+    /// Why applying it again failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// What a pass found
+#[derive(Debug, Default)]
+struct Pass {
+    /// Statuses that differ from the last pass
+    changed: Vec<TweakStatus>,
+    /// Tweaks that lost their applied option since the last pass
+    reverted: Vec<ExternalChange>,
+}
+
+/// What a pass compares between runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Observed {
+    current_option_index: Option<usize>,
+    has_backup: bool,
+    needs_attention: bool,
+}
+
+impl From<&TweakStatus> for Observed {
+    fn from(status: &TweakStatus) -> Self {
+        Self {
+            current_option_index: status.current_option_index,
+            has_backup: status.has_backup,
+            needs_attention: status.needs_attention,
+        }
+    }
+}
+
+/// Run the monitor until the app exits. Spawn it with `tauri::async_runtime::spawn` once
+/// `AppState` is managed.
+pub async fn run(app: AppHandle) {
+    let state = app.state::<AppState>();
+    let monitor = &state.snapshot_monitor;
+    let mut observed: HashMap<String, Observed> = HashMap::new();
+    let mut reapplied: HashMap<String, Instant> = HashMap::new();
+    // The first pass only records what startup already showed
+    let mut since_pass = REVALIDATE_INTERVAL;
+
+    loop {
+        let before = SystemTime::now();
+        tokio::time::sleep(TICK).await;
+        let elapsed = before.elapsed().unwrap_or(TICK);
+        let resumed = elapsed > TICK + RESUME_GAP;
+        since_pass += elapsed;
+        if monitor.pass_requested.swap(false, Ordering::SeqCst) {
+            since_pass = REVALIDATE_INTERVAL;
+        }
+        if !resumed && since_pass < REVALIDATE_INTERVAL {
+            continue;
+        }
+        if resumed {
+            log::info!("Resumed after {}s; revalidating tweaks", elapsed.as_secs());
+        }
+
+        // While a tweak operation runs, try again on the next tick
+        let Some(started) = monitor.quiet_since() else {
+            log::debug!("Tweak operation running; revalidation postponed");
+            continue;
+        };
+        let generation = state.status_cache.generation();
+        since_pass = Duration::ZERO;
+        let previous = observed.clone();
+        let result = tauri::async_runtime::spawn_blocking(move || {
+            let mut observed = previous;
+            revalidate(&mut observed).map(|pass| (observed, pass))
+        })
+        .await;
+
+        match result {
+            Ok(Ok((next, Pass { changed, reverted }))) => {
+                if monitor.quiet_since() != Some(started) {
+                    log::debug!("Tweak operation ran during revalidation; results discarded");
+                    since_pass = REVALIDATE_INTERVAL;
+                    continue;
+                }
+                observed = next;
+                if !changed.is_empty() {
+                    log::info!("{} tweak(s) changed outside the app", changed.len());
+                }
+                state.status_cache.record(changed, generation);
+                if !reverted.is_empty() {
+                    report_reverted(&app, reverted, &mut reapplied).await;
+                }
+            }
+            Ok(Err(e)) => log::warn!("Failed to revalidate tweaks: {}", e),
+            Err(e) => log::warn!("Revalidation task failed: {}", e),
+        }
+    }
+}
+
+/// Report each reverted tweak with `tweak://external-change`, after applying it again when
+/// auto-reapply is on and the tweak wasn't re-applied within `REAPPLY_COOLDOWN`
+async fn report_reverted(
+    app: &AppHandle,
+    reverted: Vec<ExternalChange>,
+    reapplied: &mut HashMap<String, Instant>,
+) {
+    let auto_reapply = backup_service::get_auto_reapply();
+    for mut change in reverted {
+        log::warn!(
+            "Tweak '{}' no longer has option '{}' (now {:?})",
+            change.tweak_id,
+            change.applied_option_label,
+            change.current_option_index
+        );
+        let cooling_down = reapplied
+            .get(&change.tweak_id)
+            .is_some_and(|at| at.elapsed() < REAPPLY_COOLDOWN);
+        if auto_reapply && cooling_down {
+            log::warn!(
+                "Tweak '{}' was reverted again within {} minutes of being re-applied; leaving it",
+                change.tweak_id,
+                REAPPLY_COOLDOWN.as_secs() / 60
+            );
+        } else if auto_reapply {
+            reapplied.insert(change.tweak_id.clone(), Instant::now());
+            let app = app.clone();
+            let (tweak_id, option_index) = (change.tweak_id.clone(), change.applied_option_index);
+            let outcome =
+                tauri::async_runtime::spawn_blocking(move || reapply(&app, tweak_id, option_index))
+                    .await
+                    .unwrap_or_else(|e| Err(format!("Re-apply task failed: {}", e)));
+            match outcome {
+                Ok(()) => change.reapplied = true,
+                Err(e) => {
+                    log::warn!("Failed to re-apply '{}': {}", change.tweak_id, e);
+                    change.error = Some(e);
+                }
+            }
+        }
+        emit_event(TWEAK_EXTERNAL_CHANGE_EVENT, change);
+    }
+}
+
+/// Apply `option_index` of `tweak_id` again, as a tweak operation. It restores the state the user
+/// chose, so it takes no risk acknowledgement, but it still waits for a pending reboot.
+fn reapply(
+    app: &AppHandle,
+    tweak_id: String,
+    option_index: usize,
+) -> std::result::Result<(), String> {
+    let state = app.state::<AppState>();
+    let _operation = state.snapshot_monitor.operation();
+    let _stale = state.status_cache.changing([tweak_id.clone()]);
+    backup_service::ensure_no_pending_reboot(&tweak_id).map_err(|e| e.to_string())?;
+    let (result, restart_explorer) =
+        apply_option(tweak_id.clone(), option_index).map_err(|e| e.to_string())?;
+    if !result.success {
+        return Err(result.message);
+    }
+    if restart_explorer {
+        log::info!(
+            "Re-applied '{}'; part of it takes effect once Explorer restarts",
+            tweak_id
+        );
+    }
+    log::info!("Re-applied '{}' with option {}", tweak_id, option_index);
+    Ok(())
+}
+
+/// Detect the state of every tweak with a snapshot (or one that had a snapshot last pass), and
+/// return the statuses that differ from `observed` along with the tweaks among them that lost their
+/// applied option. A tweak seen for the first time is recorded without being reported.
+fn revalidate(observed: &mut HashMap<String, Observed>) -> Result<Pass> {
+    let version = system_info_service::get_windows_info()?.version_number();
+    let mut tweak_ids = backup_service::get_applied_tweaks()?;
+    let unapplied: Vec<String> = observed
+        .keys()
+        .filter(|id| !tweak_ids.contains(id))
+        .cloned()
+        .collect();
+    tweak_ids.extend(unapplied);
+
+    let mut pass = Pass::default();
+    for tweak_id in tweak_ids {
+        let Some(tweak) = tweak_loader::get_tweak(&tweak_id)? else {
+            observed.remove(&tweak_id);
+            continue;
+        };
+        let state = match backup_service::detect_tweak_state(&tweak, version) {
+            Ok(state) => state,
+            Err(e) => {
+                log::warn!("Failed to detect state for tweak {}: {}", tweak_id, e);
+                continue;
+            }
+        };
+        let snapshot = backup_service::load_snapshot(&tweak_id).ok().flatten();
+        let applied = snapshot.as_ref().map(|snapshot| {
+            (
+                snapshot.applied_option_index,
+                snapshot.applied_option_label.clone(),
+            )
+        });
+        let status = crate::commands::tweaks::query::status_from(&tweak, state, snapshot);
+
+        let now = Observed::from(&status);
+        let before = if now.has_backup {
+            observed.insert(tweak_id, now.clone())
+        } else {
+            // No snapshot any more: report it once, then stop following the tweak
+            observed.remove(&tweak_id)
+        };
+        let Some(before) = before.filter(|before| *before != now) else {
+            continue;
+        };
+        if let Some((applied_option_index, applied_option_label)) = applied {
+            let lost = before.current_option_index == Some(applied_option_index)
+                && now.current_option_index != Some(applied_option_index);
+            if lost {
+                pass.reverted.push(ExternalChange {
+                    tweak_id: tweak.id.clone(),
+                    tweak_name: tweak.name.clone(),
+                    applied_option_index,
+                    applied_option_label,
+                    current_option_index: now.current_option_index,
+                    reapplied: false,
+                    error: None,
+                });
+            }
+        }
+        pass.changed.push(status);
+    }
+    Ok(pass)
+}
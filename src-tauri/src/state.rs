@@ -0,0 +1,24 @@
+//! State managed by Tauri for the app's lifetime. Commands reach it as `State<'_, AppState>`.
+
+use crate::metrics::PerformanceMetrics;
+use crate::operations::Operations;
+use crate::risk_acks::RiskAcknowledgements;
+use crate::snapshot_monitor::SnapshotMonitor;
+use crate::status_cache::StatusCache;
+use crate::system_info_cache::SystemInfoCache;
+
+#[derive(Debug, Default)]
+pub struct AppState {
+    /// Tracks running tweak operations for the background revalidation (see `snapshot_monitor`)
+    pub snapshot_monitor: SnapshotMonitor,
+    /// Running operations that can be cancelled (see `operations`)
+    pub operations: Operations,
+    /// Last detected status of each tweak (see `status_cache`)
+    pub status_cache: StatusCache,
+    /// Acknowledgements issued for applying high and critical risk tweaks (see `risk_acks`)
+    pub risk_acks: RiskAcknowledgements,
+    /// Phase timings of the last tweak applies and reverts (see `metrics`)
+    pub metrics: PerformanceMetrics,
+    /// Hardware info last gathered for `get_system_info` (see `system_info_cache`)
+    pub system_info: SystemInfoCache,
+}
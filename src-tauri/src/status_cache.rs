@@ -0,0 +1,180 @@
+//! Cache of detected tweak statuses.
+//!
+//! Detecting a tweak's status reads every value its options declare: for the whole list that is
+//! hundreds of registry reads, service and task queries, repeated by every page that shows
+//! statuses. The cache keeps the last status detected for each tweak. A tweak operation marks the
+//! tweaks it touched as stale when it ends (every tweak when it can't tell which), and only stale
+//! or missing statuses are detected again. `refresh_tweak_statuses` with `force` re-detects all of
+//! them, for changes made outside the app that the background revalidation (`snapshot_monitor`)
+//! doesn't cover.
+//!
+//! A re-detected status that differs from the one cached is reported with `tweak://status-changed`.
+
+use crate::commands::tweaks::query::detect_status;
+use crate::debug::emit_event;
+use crate::models::{TweakDefinition, TweakStatus};
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+/// Event carrying a tweak's new status
+pub const TWEAK_STATUS_CHANGED_EVENT: &str = "tweak://status-changed";
+
+/// The cache's part of `AppState`
+#[derive(Debug, Default)]
+pub struct StatusCache {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    entries: HashMap<String, Entry>,
+    /// Bumped by every invalidation, so a detection that overlapped one isn't trusted
+    generation: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    status: TweakStatus,
+    /// False once an operation may have changed the tweak since it was detected
+    fresh: bool,
+}
+
+/// Marks tweaks stale when dropped, i.e. when the operation that changes them ends
+pub struct Invalidation<'a> {
+    cache: &'a StatusCache,
+    /// None for every tweak
+    tweak_ids: Option<Vec<String>>,
+}
+
+impl Drop for Invalidation<'_> {
+    fn drop(&mut self) {
+        let mut inner = self.cache.lock();
+        inner.generation += 1;
+        match &self.tweak_ids {
+            Some(tweak_ids) => {
+                for tweak_id in tweak_ids {
+                    if let Some(entry) = inner.entries.get_mut(tweak_id) {
+                        entry.fresh = false;
+                    }
+                }
+            }
+            None => inner
+                .entries
+                .values_mut()
+                .for_each(|entry| entry.fresh = false),
+        }
+    }
+}
+
+impl StatusCache {
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Mark `tweak_ids` stale once the returned guard drops. Commands that change tweak state hold
+    /// it for their whole run, next to `SnapshotMonitor::operation`.
+    pub fn changing(&self, tweak_ids: impl IntoIterator<Item = String>) -> Invalidation<'_> {
+        Invalidation {
+            cache: self,
+            tweak_ids: Some(tweak_ids.into_iter().collect()),
+        }
+    }
+
+    /// [`changing`](Self::changing) for an operation that may touch any tweak
+    pub fn changing_all(&self) -> Invalidation<'_> {
+        Invalidation {
+            cache: self,
+            tweak_ids: None,
+        }
+    }
+
+    /// The cached status of `tweak_id`, unless it is missing or stale
+    pub fn cached(&self, tweak_id: &str) -> Option<TweakStatus> {
+        self.lock()
+            .entries
+            .get(tweak_id)
+            .filter(|entry| entry.fresh)
+            .map(|entry| entry.status.clone())
+    }
+
+    /// The statuses of `tweaks`, detecting (in parallel) the ones that are missing or stale, or all
+    /// of them with `force`
+    pub fn statuses(
+        &self,
+        tweaks: Vec<&'static TweakDefinition>,
+        version: u32,
+        force: bool,
+    ) -> Vec<TweakStatus> {
+        let (generation, cached, stale) = {
+            let inner = self.lock();
+            let mut cached = HashMap::new();
+            let mut stale = Vec::new();
+            for tweak in tweaks {
+                match inner.entries.get(&tweak.id) {
+                    Some(entry) if entry.fresh && !force => {
+                        cached.insert(tweak.id.as_str(), entry.status.clone());
+                    }
+                    _ => stale.push(tweak),
+                }
+            }
+            (inner.generation, cached, stale)
+        };
+        log::debug!(
+            "Status cache: {} cached, {} to detect",
+            cached.len(),
+            stale.len()
+        );
+
+        let detected: Vec<TweakStatus> = stale
+            .par_iter()
+            .map(|tweak| detect_status(tweak, version))
+            .collect();
+        self.record(detected.clone(), generation);
+
+        let mut statuses: Vec<TweakStatus> = cached.into_values().collect();
+        statuses.extend(detected);
+        statuses
+    }
+
+    /// Record statuses detected after `generation`, and report each one that differs from the
+    /// status cached before. A status whose detection failed isn't kept, so the next read tries
+    /// again; one detected while an operation ended is kept stale.
+    pub fn record(&self, statuses: Vec<TweakStatus>, generation: u64) {
+        let mut changed = Vec::new();
+        {
+            let mut inner = self.lock();
+            let fresh = inner.generation == generation;
+            for status in statuses {
+                if status.error.is_some() {
+                    inner.entries.remove(&status.tweak_id);
+                    continue;
+                }
+                let entry = Entry {
+                    status: status.clone(),
+                    fresh,
+                };
+                if let Some(previous) = inner.entries.insert(status.tweak_id.clone(), entry) {
+                    if previous.status != status {
+                        changed.push(status);
+                    }
+                }
+            }
+        }
+        for status in changed {
+            log::info!(
+                "Tweak '{}' status changed (option {:?}, snapshot: {})",
+                status.tweak_id,
+                status.current_option_index,
+                status.has_backup
+            );
+            emit_event(TWEAK_STATUS_CHANGED_EVENT, status);
+        }
+    }
+
+    /// The current generation, to pass to [`record`](Self::record) with statuses detected from now
+    /// on
+    pub fn generation(&self) -> u64 {
+        self.lock().generation
+    }
+}
@@ -0,0 +1,129 @@
+//! Cache of the hardware info `get_system_info` reports.
+//!
+//! Gathering the hardware takes a dozen WMI queries over three namespaces, which is most of what
+//! made every dashboard load slow, and almost none of it changes while the app runs. The cache
+//! keeps each section with the time it was gathered and only gathers a section again once it's
+//! older than its TTL: minutes for the hardware, seconds for the network adapters, whose addresses
+//! and Wi-Fi network do change. `refresh_system_info` gathers the sections it's given right away,
+//! e.g. just the disks after one was plugged in. Setup warms the cache on a background thread so
+//! the first load is already served from it.
+
+use crate::models::{DeviceInfo, HardwareInfo, HardwareSection};
+use crate::services::system_info_service;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// How long the hardware sections are served from the cache
+const HARDWARE_TTL: Duration = Duration::from_secs(30 * 60);
+
+/// How long the network adapters are: their addresses, link speed, and Wi-Fi network change
+const NETWORK_TTL: Duration = Duration::from_secs(30);
+
+/// The cache's part of `AppState`
+#[derive(Debug, Default)]
+pub struct SystemInfoCache {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    hardware: HardwareInfo,
+    device: DeviceInfo,
+    /// When each section was last gathered; a section that failed to gather is missing
+    gathered: HashMap<HardwareSection, Instant>,
+}
+
+fn ttl(section: HardwareSection) -> Duration {
+    match section {
+        HardwareSection::Network => NETWORK_TTL,
+        _ => HARDWARE_TTL,
+    }
+}
+
+impl Inner {
+    /// Sections never gathered or older than their TTL at `now`
+    fn expired(&self, now: Instant) -> Vec<HardwareSection> {
+        HardwareSection::ALL
+            .into_iter()
+            .filter(|section| {
+                self.gathered
+                    .get(section)
+                    .is_none_or(|at| now.duration_since(*at) >= ttl(*section))
+            })
+            .collect()
+    }
+
+    fn gather(&mut self, sections: &[HardwareSection]) {
+        if sections.is_empty() {
+            return;
+        }
+        let applied = system_info_service::gather_hardware(sections)
+            .apply(&mut self.hardware, &mut self.device);
+        let now = Instant::now();
+        for section in applied {
+            self.gathered.insert(section, now);
+        }
+    }
+}
+
+impl SystemInfoCache {
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The hardware and device info, gathering the sections that expired first. The lock is held
+    /// while gathering, so concurrent callers wait for that one gather instead of repeating it.
+    pub fn get(&self) -> (HardwareInfo, DeviceInfo) {
+        let mut inner = self.lock();
+        let expired = inner.expired(Instant::now());
+        inner.gather(&expired);
+        (inner.hardware.clone(), inner.device.clone())
+    }
+
+    /// Gather `sections` (every section if empty) again, with any that expired, and return the
+    /// hardware and device info
+    pub fn refresh(&self, sections: &[HardwareSection]) -> (HardwareInfo, DeviceInfo) {
+        let mut inner = self.lock();
+        let mut wanted = if sections.is_empty() {
+            HardwareSection::ALL.to_vec()
+        } else {
+            sections.to_vec()
+        };
+        for section in inner.expired(Instant::now()) {
+            if !wanted.contains(&section) {
+                wanted.push(section);
+            }
+        }
+        inner.gather(&wanted);
+        (inner.hardware.clone(), inner.device.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sections_expire_after_their_ttl() {
+        let start = Instant::now();
+        let mut inner = Inner::default();
+        assert_eq!(inner.expired(start), HardwareSection::ALL.to_vec());
+
+        for section in HardwareSection::ALL {
+            inner.gathered.insert(section, start);
+        }
+        assert!(inner.expired(start).is_empty());
+        assert_eq!(
+            inner.expired(start + NETWORK_TTL),
+            vec![HardwareSection::Network]
+        );
+        assert_eq!(
+            inner.expired(start + HARDWARE_TTL),
+            HardwareSection::ALL.to_vec()
+        );
+
+        inner.gathered.remove(&HardwareSection::Disks);
+        assert_eq!(inner.expired(start), vec![HardwareSection::Disks]);
+    }
+}
@@ -5,6 +5,9 @@
  * to share tweak configurations across machines or backups.
  */
 
+import type { TweakTag } from "$lib/types";
+import { invoke } from "@tauri-apps/api/core";
+
 // ============================================================================
 // TEMPORARILY DISABLED
 // ============================================================================
@@ -18,6 +21,9 @@
 //
 // To restore: reinstate the Tauri commands, then replace `profileBackendUnavailable()`
 // with the original `invoke(...)` bodies (see git history for this file).
+//
+// `getBuiltinProfiles` is the exception: the templates are built from tweak tags,
+// not from the deleted backend, so it calls its command.
 // ============================================================================
 
 /** Message shown wherever a profile action is attempted while the backend is absent. */
@@ -198,10 +204,29 @@ export interface ProfileApplyResult {
   reboot_required_tweaks: string[];
 }
 
+/** A built-in profile template, generated from the tags of the available tweaks */
+export interface BuiltinProfile {
+  id: string;
+  name: string;
+  description: string;
+  /** Tags the template takes tweaks from */
+  tags: TweakTag[];
+  /** The selected tweaks (each at its first option), by category and then ID */
+  selections: TweakSelection[];
+}
+
 // ============================================================================
 // API Functions
 // ============================================================================
 
+/**
+ * Get the built-in profile templates (Gaming Desktop, Developer Laptop, VM/Test, Privacy-focused), generated from
+ * the tags of the tweaks available on this Windows version.
+ */
+export async function getBuiltinProfiles(): Promise<BuiltinProfile[]> {
+  return await invoke<BuiltinProfile[]>("get_builtin_profiles");
+}
+
 /**
  * Export a configuration profile to a file.
  *
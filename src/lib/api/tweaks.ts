@@ -1,13 +1,56 @@
 // API functions for Tauri commands
 import { invoke } from "@tauri-apps/api/core";
 import type {
+  ApplyIntent,
+  ArchiveImportReport,
+  AuditLog,
+  BackupStorageStats,
+  BaselineInfo,
+  BaselineRestoreResult,
   CategoryDefinition,
+  ChangeElevation,
+  ChangeSelector,
+  CleanupReport,
+  CustomTweaksReport,
+  DeferredApply,
+  DraftReport,
+  HardwareSection,
+  HistoryRetention,
+  InstalledSoftware,
+  JournalOperation,
+  OperationTiming,
+  PendingChange,
+  PendingOperation,
+  ProcessPrivileges,
+  ReapplyReport,
+  RegistryHive,
+  RiskAcknowledgement,
+  RestoreAllReport,
+  RestorePoint,
+  RestoreResult,
+  ScriptExportReport,
+  SnapshotDiff,
+  SnapshotGeneration,
+  SnapshotLocation,
+  SnapshotLocationInfo,
+  SnapshotVerifyReport,
+  StartupChange,
+  StartupItem,
   SystemInfo,
+  ThermalInfo,
   TweakDefinition,
+  TweakDraft,
   TweakInspection,
+  TweakNote,
   TweakResult,
+  TweakSearchFilters,
+  TweakStatsReport,
   TweakStatus,
+  TweakStatusDetail,
+  TweakTag,
+  UpdateStatus,
   TweakWithStatus,
+  UserProfile,
 } from "../types";
 
 /**
@@ -17,6 +60,65 @@ export async function getSystemInfo(): Promise<SystemInfo> {
   return await invoke<SystemInfo>("get_system_info");
 }
 
+/**
+ * Get system information after gathering the given hardware sections again
+ * (every section when omitted), bypassing the backend's hardware cache
+ */
+export async function refreshSystemInfo(sections?: HardwareSection[]): Promise<SystemInfo> {
+  return await invoke<SystemInfo>("refresh_system_info", { sections });
+}
+
+/**
+ * CPU/GPU temperatures and fan speeds, from a running LibreHardwareMonitor or OpenHardwareMonitor,
+ * else from ACPI thermal zones
+ */
+export async function getThermalInfo(): Promise<ThermalInfo> {
+  return await invoke<ThermalInfo>("get_thermal_info");
+}
+
+/**
+ * Start streaming live resource usage as `system://metrics` events (`SystemMetrics`) every `intervalMs`
+ * (default 1000, clamped to 250-60000), with CPU and GPU temperatures if `thermal`. Returns the interval used.
+ */
+export async function startMonitoring(intervalMs?: number, thermal?: boolean): Promise<number> {
+  return await invoke<number>("start_monitoring", { intervalMs, thermal });
+}
+
+/** Stop the live resource usage events. Returns false if they weren't running. */
+export async function stopMonitoring(): Promise<boolean> {
+  return await invoke<boolean>("stop_monitoring");
+}
+
+/** Programs installed on this machine (machine-wide and for the app's account), sorted by name */
+export async function getInstalledSoftware(): Promise<InstalledSoftware[]> {
+  return await invoke<InstalledSoftware[]>("get_installed_software");
+}
+
+/** Programs that start at sign-in (Run keys, Startup folders, logon tasks) with their measured impact, sorted by name */
+export async function getStartupItems(): Promise<StartupItem[]> {
+  return await invoke<StartupItem[]>("get_startup_items");
+}
+
+/** Enable or disable a startup entry, recording its prior state for undo */
+export async function setStartupItemEnabled(id: string, enabled: boolean): Promise<void> {
+  await invoke("set_startup_item_enabled", { id, enabled });
+}
+
+/** The startup changes that can be undone, newest first */
+export async function getStartupChanges(): Promise<StartupChange[]> {
+  return await invoke<StartupChange[]>("get_startup_changes");
+}
+
+/** Undo the most recent startup change. Returns it, or null if there's nothing to undo. */
+export async function undoStartupChange(): Promise<StartupChange | null> {
+  return await invoke<StartupChange | null>("undo_startup_change");
+}
+
+/** Windows Update's pending updates (as of its last scan), last install, pause, and pending reboot */
+export async function getUpdateStatus(): Promise<UpdateStatus> {
+  return await invoke<UpdateStatus>("get_update_status");
+}
+
 // ----------------------------------------------------------------------------
 // API FUNCTIONS
 // ----------------------------------------------------------------------------
@@ -31,6 +133,14 @@ export async function inspectTweak(tweakId: string): Promise<TweakInspection> {
   }
 }
 
+/**
+ * Get a tweak's status with the reason for it: every option's per-change comparison with the current state and, for a
+ * custom state, the option it is closest to
+ */
+export async function getTweakStatusDetail(tweakId: string): Promise<TweakStatusDetail> {
+  return await invoke<TweakStatusDetail>("get_tweak_status_detail", { tweakId });
+}
+
 /**
  * Get all available categories (auto-discovered from YAML files)
  */
@@ -45,6 +155,49 @@ export async function getAvailableTweaks(): Promise<TweakDefinition[]> {
   return await invoke<TweakDefinition[]>("get_available_tweaks");
 }
 
+/**
+ * Search the available tweaks: every word of `query` must appear in the name, description, or info (ignoring
+ * case), and the tweak must pass `filters`. Tweaks matching more words in their name come first.
+ */
+export async function searchTweaks(query: string, filters: TweakSearchFilters = {}): Promise<TweakDefinition[]> {
+  return await invoke<TweakDefinition[]>("search_tweaks", { query, filters });
+}
+
+/**
+ * Get the available tweaks carrying a tag, from every category, sorted by name
+ */
+export async function getTweaksByTag(tag: TweakTag): Promise<TweakDefinition[]> {
+  return await invoke<TweakDefinition[]>("get_tweaks_by_tag", { tag });
+}
+
+/**
+ * Get what loading the user's `tweaks.d` folder found: the custom tweaks each file added, or the errors that kept it out
+ */
+export async function getCustomTweaks(): Promise<CustomTweaksReport> {
+  return await invoke<CustomTweaksReport>("get_custom_tweaks");
+}
+
+/**
+ * Get the JSON Schema of the tweak YAML file format
+ */
+export async function getTweakSchema(): Promise<Record<string, unknown>> {
+  return await invoke<Record<string, unknown>>("get_tweak_schema");
+}
+
+/**
+ * Validate a custom tweak draft in its `tweaks.d` file, as it would be loaded, without saving it
+ */
+export async function validateTweakDraft(draft: TweakDraft): Promise<DraftReport> {
+  return await invoke<DraftReport>("validate_tweak_draft", { draft });
+}
+
+/**
+ * Save a custom tweak draft to its `tweaks.d` file, unless the file would have errors. It's loaded at the next launch.
+ */
+export async function saveCustomTweak(draft: TweakDraft): Promise<DraftReport> {
+  return await invoke<DraftReport>("save_custom_tweak", { draft });
+}
+
 /**
  * Get the status of a specific tweak
  */
@@ -59,6 +212,14 @@ export async function getAllTweakStatuses(): Promise<TweakStatus[]> {
   return await invoke<TweakStatus[]>("get_all_tweak_statuses");
 }
 
+/**
+ * Get statuses for all tweaks; with `force`, every one is detected again instead of reusing the cached statuses
+ * (for changes made outside the app). Each status that changed is also sent as a `tweak://status-changed` event.
+ */
+export async function refreshTweakStatuses(force = false): Promise<TweakStatus[]> {
+  return await invoke<TweakStatus[]>("refresh_tweak_statuses", { force });
+}
+
 /**
  * Get all tweaks with their status (optimized batch version)
  * Uses a single batch IPC call for statuses instead of N parallel calls
@@ -88,16 +249,41 @@ export async function getAllTweaksWithStatus(): Promise<TweakWithStatus[]> {
  * Apply a specific tweak option
  * @param tweakId - The tweak ID
  * @param optionIndex - Index of the option to apply (0 for first option, 1 for second, etc.)
+ * @param force - Apply even though the tweak is still waiting for a reboot from its last change
+ * @param operationId - Names the apply so `cancelOperation` can stop it
  */
-export async function applyTweak(tweakId: string, optionIndex: number): Promise<TweakResult> {
-  return await invoke<TweakResult>("apply_tweak", { tweakId, optionIndex });
+export async function applyTweak(
+  tweakId: string,
+  optionIndex: number,
+  force = false,
+  operationId?: string,
+  riskAck?: string,
+): Promise<TweakResult> {
+  return await invoke<TweakResult>("apply_tweak", { tweakId, optionIndex, force, operationId, riskAck });
+}
+
+/**
+ * Acknowledge the risk of a high or critical risk tweak. The token lets one apply of it through within a few
+ * minutes; without one the backend refuses it with `RISK_NOT_ACKNOWLEDGED`.
+ */
+export async function acknowledgeRisk(tweakId: string): Promise<RiskAcknowledgement> {
+  return await invoke<RiskAcknowledgement>("acknowledge_risk", { tweakId });
+}
+
+/**
+ * Cancel the apply started with `operationId`: it rolls back what it changed and fails with a `CANCELLED` error
+ * (a batch fails its remaining tweaks the same way). Returns false when it's no longer running.
+ */
+export async function cancelOperation(operationId: string): Promise<boolean> {
+  return await invoke<boolean>("cancel_operation", { operationId });
 }
 
 /**
  * Revert a specific tweak
+ * @param force - Revert even though the tweak is still waiting for a reboot
  */
-export async function revertTweak(tweakId: string): Promise<TweakResult> {
-  return await invoke<TweakResult>("revert_tweak", { tweakId });
+export async function revertTweak(tweakId: string, force = false): Promise<TweakResult> {
+  return await invoke<TweakResult>("revert_tweak", { tweakId, force });
 }
 
 /**
@@ -108,19 +294,259 @@ export async function keepCurrentState(tweakId: string): Promise<TweakResult> {
   return await invoke<TweakResult>("keep_current_state", { tweakId });
 }
 
+/**
+ * Return a tweak to one of its snapshot generations. Generation 0 is the original state (a plain revert).
+ */
+export async function restoreSnapshotGeneration(
+  tweakId: string,
+  generation: number,
+  force = false,
+): Promise<TweakResult> {
+  return await invoke<TweakResult>("restore_snapshot_generation", { tweakId, generation, force });
+}
+
+/**
+ * A tweak's snapshot history: the original state, then each kept generation, oldest first
+ */
+export async function listSnapshotGenerations(tweakId: string): Promise<SnapshotGeneration[]> {
+  return await invoke<SnapshotGeneration[]>("list_snapshot_generations", { tweakId });
+}
+
+/**
+ * How many snapshot generations are kept per tweak, and for how long
+ */
+export async function getHistoryRetention(): Promise<HistoryRetention> {
+  return await invoke<HistoryRetention>("get_history_retention");
+}
+
+/**
+ * Change the snapshot history retention; existing history is pruned to match
+ */
+export async function setHistoryRetention(retention: HistoryRetention): Promise<HistoryRetention> {
+  return await invoke<HistoryRetention>("set_history_retention", { retention });
+}
+
+/**
+ * Disk used by each applied tweak's snapshots and by the snapshots directory as a whole
+ */
+export async function getBackupStorageStats(): Promise<BackupStorageStats> {
+  return await invoke<BackupStorageStats>("get_backup_storage_stats");
+}
+
+/**
+ * Prune every tweak's snapshot history to the saved retention (count, age, and total size)
+ */
+export async function cleanupOldBackups(): Promise<CleanupReport> {
+  return await invoke<CleanupReport>("cleanup_old_backups");
+}
+
+/**
+ * Whether snapshot files are written encrypted
+ */
+export async function getSnapshotEncryption(): Promise<boolean> {
+  return await invoke<boolean>("get_snapshot_encryption");
+}
+
+/**
+ * Turn snapshot encryption on or off; existing snapshots are rewritten to match.
+ * Returns the number of files rewritten.
+ */
+export async function setSnapshotEncryption(enabled: boolean): Promise<number> {
+  return await invoke<number>("set_snapshot_encryption", { enabled });
+}
+
+/**
+ * Whether tweaks reverted outside the app are applied again automatically
+ */
+export async function getAutoReapply(): Promise<boolean> {
+  return await invoke<boolean>("get_auto_reapply");
+}
+
+/**
+ * Turn applying tweaks reverted outside the app (by Windows Update or another tool) on or off
+ */
+export async function setAutoReapply(enabled: boolean): Promise<void> {
+  await invoke("set_auto_reapply", { enabled });
+}
+
 /**
  * Apply multiple tweak options at once
  * @param operations - Array of [tweakId, optionIndex] tuples
+ * @param createRestorePoint - Create a System Restore point first (nothing is applied if that fails)
+ * @param force - Tweaks to apply even though they're still waiting for a reboot
+ * @param operationId - Names the batch so `cancelOperation` can stop it
+ * @param transactional - Stop at the first failure and roll back the tweaks applied before it
+ * @param riskAcks - Tokens from `acknowledgeRisk` for the high and critical risk tweaks
  */
-export async function batchApplyTweaks(operations: [string, number][]): Promise<TweakResult> {
-  return await invoke<TweakResult>("batch_apply_tweaks", { operations });
+export async function batchApplyTweaks(
+  operations: [string, number][],
+  createRestorePoint = false,
+  force: string[] = [],
+  operationId?: string,
+  transactional = false,
+  riskAcks: string[] = [],
+): Promise<TweakResult> {
+  return await invoke<TweakResult>("batch_apply_tweaks", {
+    operations,
+    createRestorePoint,
+    force,
+    operationId,
+    transactional,
+    riskAcks,
+  });
 }
 
 /**
  * Revert multiple tweaks at once
+ * @param force - Revert even the tweaks still waiting for a reboot
+ */
+export async function batchRevertTweaks(tweakIds: string[], force = false): Promise<TweakResult> {
+  return await invoke<TweakResult>("batch_revert_tweaks", { tweakIds, force });
+}
+
+/**
+ * Apply every applied tweak again with the option its snapshot records (after a feature update reset them).
+ * Tweaks that still have that option are skipped.
+ * @param operationId - Names the run so `cancelOperation` can stop it
+ */
+export async function reapplyAppliedTweaks(operationId?: string): Promise<ReapplyReport> {
+  return await invoke<ReapplyReport>("reapply_applied_tweaks", { operationId });
+}
+
+/**
+ * Queue an option of a tweak to be applied at `at`, or once the machine has booted again without it. A newer
+ * request for the same tweak replaces one still waiting.
+ * @param riskAck - Token from `acknowledgeRisk`, for a high or critical risk tweak
+ */
+export async function scheduleApply(
+  tweakId: string,
+  optionIndex: number,
+  at?: Date,
+  riskAck?: string,
+): Promise<DeferredApply> {
+  return await invoke<DeferredApply>("schedule_apply", { tweakId, optionIndex, at: at?.toISOString(), riskAck });
+}
+
+/**
+ * Every queued apply, waiting or finished (with its outcome), oldest first
+ */
+export async function getDeferredApplies(): Promise<DeferredApply[]> {
+  return await invoke<DeferredApply[]>("get_deferred_applies");
+}
+
+/**
+ * One queued apply, or null when it's no longer in the queue
+ */
+export async function getDeferredApply(id: string): Promise<DeferredApply | null> {
+  return await invoke<DeferredApply | null>("get_deferred_apply", { id });
+}
+
+/**
+ * Cancel a waiting deferred apply, or dismiss a finished one. Returns false when it isn't in the queue.
+ */
+export async function cancelDeferredApply(id: string): Promise<boolean> {
+  return await invoke<boolean>("cancel_deferred_apply", { id });
+}
+
+/**
+ * Revert every applied tweak to its original state, most recently applied first
+ */
+export async function restoreAllToBaseline(): Promise<RestoreAllReport> {
+  return await invoke<RestoreAllReport>("restore_all_to_baseline");
+}
+
+/**
+ * Cancel the SYSTEM / TrustedInstaller operations running now, such as a hung elevated command.
+ * Each fails with a `CANCELLED` error.
+ */
+export async function cancelElevatedOperations(): Promise<void> {
+  return await invoke<void>("cancel_elevated_operations");
+}
+
+/**
+ * Start the SYSTEM / TrustedInstaller broker ahead of the first operation that needs it.
+ * Fails with a `REQUIRES_ADMIN` error when not running as administrator.
  */
-export async function batchRevertTweaks(tweakIds: string[]): Promise<TweakResult> {
-  return await invoke<TweakResult>("batch_revert_tweaks", { tweakIds });
+export async function warmUpElevation(level: ChangeElevation): Promise<void> {
+  return await invoke<void>("warm_up_elevation", { level });
+}
+
+/**
+ * Phase timings of the last tweak applies and reverts, oldest first
+ */
+export async function getPerformanceMetrics(): Promise<OperationTiming[]> {
+  return await invoke<OperationTiming[]>("get_performance_metrics");
+}
+
+/**
+ * Read the app process's token (elevation type, integrity level, privileges) to diagnose elevation failures
+ */
+export async function getProcessPrivileges(): Promise<ProcessPrivileges> {
+  return await invoke<ProcessPrivileges>("get_process_privileges");
+}
+
+/**
+ * Restart as administrator through the UAC prompt, handing the staged changes to the new instance.
+ * Fails with a `CANCELLED` error if the prompt is declined.
+ */
+export async function relaunchElevated(pending: PendingChange[]): Promise<void> {
+  const operations: PendingOperation[] = pending.map((change) => ({
+    tweak_id: change.tweakId,
+    option_index: change.optionIndex,
+    force: change.force ?? false,
+    risk_acknowledged: change.riskAcknowledged ?? false,
+  }));
+  await invoke("relaunch_elevated", { pending: operations });
+}
+
+/**
+ * Take the staged changes handed over by the instance that relaunched this one as administrator
+ */
+export async function takeResumedOperations(): Promise<PendingOperation[]> {
+  return await invoke<PendingOperation[]>("take_resumed_operations");
+}
+
+/**
+ * Get the log of everything run as SYSTEM / TrustedInstaller, newest first, with whether its hash
+ * chain is intact
+ */
+export async function getElevationAuditLog(): Promise<AuditLog> {
+  return await invoke<AuditLog>("get_elevation_audit_log");
+}
+
+/**
+ * Create a System Restore point
+ */
+export async function createRestorePoint(description: string): Promise<RestorePoint> {
+  return await invoke<RestorePoint>("create_restore_point", { description });
+}
+
+/**
+ * List the System Restore points on this machine, oldest first
+ */
+export async function listRestorePoints(): Promise<RestorePoint[]> {
+  return await invoke<RestorePoint[]>("list_restore_points");
+}
+
+/**
+ * List the user profiles on this machine
+ */
+export async function listUserProfiles(): Promise<UserProfile[]> {
+  return await invoke<UserProfile[]>("list_user_profiles");
+}
+
+/**
+ * SID of the user HKCU tweaks apply to, or null for the account the app runs as
+ */
+export async function getTargetUser(): Promise<string | null> {
+  return await invoke<string | null>("get_target_user");
+}
+
+/**
+ * Apply HKCU tweaks to another user for the rest of the session (null: the account the app runs as)
+ */
+export async function setTargetUser(sid: string | null): Promise<UserProfile | null> {
+  return await invoke<UserProfile | null>("set_target_user", { sid });
 }
 
 /**
@@ -151,6 +577,8 @@ export interface BackupInfo {
   registry_values_count: number;
   service_snapshots_count: number;
   scheduler_snapshots_count: number;
+  /** The user's note on the tweak */
+  note: string | null;
 }
 
 /**
@@ -173,3 +601,175 @@ export async function listBackups(): Promise<string[]> {
 export async function getBackupInfo(tweakId: string): Promise<BackupInfo | null> {
   return await invoke<BackupInfo | null>("get_backup_info", { tweakId });
 }
+
+/**
+ * Export the registry values of a tweak's snapshot as a .reg file at `path`
+ */
+export async function exportSnapshotAsReg(tweakId: string, path: string): Promise<void> {
+  return await invoke<void>("export_snapshot_as_reg", { tweakId, path });
+}
+
+/**
+ * Export the applied tweaks as `apply.ps1` plus .reg files in `dir`, to reapply them without the app
+ */
+export async function exportAppliedTweaksAsScript(dir: string): Promise<ScriptExportReport> {
+  return await invoke<ScriptExportReport>("export_applied_tweaks_as_script", { dir });
+}
+
+/**
+ * Get where snapshots are stored
+ */
+export async function getSnapshotLocation(): Promise<SnapshotLocationInfo> {
+  return await invoke<SnapshotLocationInfo>("get_snapshot_location");
+}
+
+/**
+ * Store snapshots in a new location, moving the existing ones there unless `moveExisting` is false
+ */
+export async function setSnapshotLocation(
+  location: SnapshotLocation,
+  moveExisting = true,
+): Promise<SnapshotLocationInfo> {
+  return await invoke<SnapshotLocationInfo>("set_snapshot_location", { location, moveExisting });
+}
+
+/**
+ * Export every snapshot (with its file backups) to one compressed archive at `path`
+ * @returns The number of snapshots exported
+ */
+export async function exportBackupArchive(path: string): Promise<number> {
+  return await invoke<number>("export_backup_archive", { path });
+}
+
+/**
+ * Import the snapshots from a backup archive. Tweaks that already have a snapshot are skipped unless `overwrite` is set.
+ */
+export async function importBackupArchive(path: string, overwrite = false): Promise<ArchiveImportReport> {
+  return await invoke<ArchiveImportReport>("import_backup_archive", { path, overwrite });
+}
+
+/**
+ * Check every snapshot's checksum. Corrupt snapshots are moved to the `corrupt` folder.
+ */
+export async function verifySnapshots(): Promise<SnapshotVerifyReport> {
+  return await invoke<SnapshotVerifyReport>("verify_snapshots");
+}
+
+/**
+ * The baseline captured on first run, or null if there isn't one
+ */
+export async function getBaselineInfo(): Promise<BaselineInfo | null> {
+  return await invoke<BaselineInfo | null>("get_baseline_info");
+}
+
+/**
+ * Capture the baseline again from the current state, replacing the first-run one
+ */
+export async function captureBaseline(): Promise<BaselineInfo> {
+  return await invoke<BaselineInfo>("capture_baseline");
+}
+
+/**
+ * Put a registry key (and its subkeys) back to the state recorded in the baseline
+ */
+export async function restoreKeyToBaseline(hive: RegistryHive, key: string): Promise<BaselineRestoreResult> {
+  return await invoke<BaselineRestoreResult>("restore_key_to_baseline", { hive, key });
+}
+
+/**
+ * Compare a tweak's snapshot with the current state, to warn before a revert overwrites changes
+ */
+export async function diffSnapshot(tweakId: string): Promise<SnapshotDiff> {
+  return await invoke<SnapshotDiff>("diff_snapshot", { tweakId });
+}
+
+/**
+ * Get the applied tweaks still waiting for a reboot (they can't be changed again until then without forcing)
+ */
+export async function getPendingRebootTweaks(): Promise<string[]> {
+  return await invoke<string[]>("get_pending_reboot_tweaks");
+}
+
+/**
+ * Restore only the selected registry values/keys, services, and tasks of a tweak's snapshot
+ */
+export async function restoreChanges(tweakId: string, selectors: ChangeSelector[]): Promise<RestoreResult> {
+  return await invoke<RestoreResult>("restore_changes", { tweakId, selectors });
+}
+
+/**
+ * Get the applies that were cut short by a crash or power loss, oldest first
+ */
+export async function getIncompleteApplies(): Promise<ApplyIntent[]> {
+  return await invoke<ApplyIntent[]>("get_incomplete_applies");
+}
+
+/**
+ * Roll back an apply that was cut short to the state before it started
+ */
+export async function rollBackIncompleteApply(tweakId: string): Promise<TweakResult> {
+  return await invoke<TweakResult>("roll_back_incomplete_apply", { tweakId });
+}
+
+/**
+ * Keep the state an apply that was cut short left behind
+ */
+export async function dismissIncompleteApply(tweakId: string): Promise<void> {
+  await invoke("dismiss_incomplete_apply", { tweakId });
+}
+
+/**
+ * Get the recorded apply/revert/batch operations, newest first
+ */
+export async function getOperationHistory(): Promise<JournalOperation[]> {
+  return await invoke<JournalOperation[]>("get_operation_history");
+}
+
+/**
+ * Undo the newest operation that hasn't been undone yet
+ */
+export async function undoLastOperation(): Promise<TweakResult> {
+  return await invoke<TweakResult>("undo_last_operation");
+}
+
+/**
+ * Get the user's notes on tweaks, by tweak ID
+ */
+export async function getTweakNotes(): Promise<Record<string, TweakNote>> {
+  return await invoke<Record<string, TweakNote>>("get_tweak_notes");
+}
+
+/**
+ * Set the note on a tweak (an empty note removes it); returns the note kept
+ */
+export async function setTweakNote(tweakId: string, note: string): Promise<TweakNote | null> {
+  return await invoke<TweakNote | null>("set_tweak_note", { tweakId, note });
+}
+
+/**
+ * Get how often each tweak was applied, reverted, and failed, and whether counting is on
+ */
+export async function getTweakStats(): Promise<TweakStatsReport> {
+  return await invoke<TweakStatsReport>("get_tweak_stats");
+}
+
+/**
+ * Turn counting applies and reverts on or off (off until the user opts in)
+ */
+export async function setUsageStatsEnabled(enabled: boolean): Promise<void> {
+  await invoke("set_usage_stats_enabled", { enabled });
+}
+
+/**
+ * Forget every usage count
+ */
+export async function resetTweakStats(): Promise<void> {
+  await invoke("reset_tweak_stats");
+}
+
+/**
+ * Write the anonymized counts of the built-in tweaks to a file; returns the number of tweaks exported
+ */
+export async function exportTweakStats(path: string): Promise<number> {
+  return await invoke<number>("export_tweak_stats", { path });
+}
@@ -1,4 +1,5 @@
 // API functions for update-related Tauri commands
+import type { PackManifest, TweakPackSettings, TweakPackUpdate } from "$lib/types";
 import { invoke } from "@tauri-apps/api/core";
 
 /**
@@ -7,3 +8,31 @@ import { invoke } from "@tauri-apps/api/core";
 export async function installUpdate(): Promise<void> {
   return await invoke("install_update");
 }
+
+/**
+ * Get the tweak pack channel's URL and public key
+ */
+export async function getTweakPackSettings(): Promise<TweakPackSettings> {
+  return await invoke("get_tweak_pack_settings");
+}
+
+/**
+ * Set the tweak pack channel's URL and public key. Returns what was saved.
+ */
+export async function setTweakPackSettings(settings: TweakPackSettings): Promise<TweakPackSettings> {
+  return await invoke("set_tweak_pack_settings", { settings });
+}
+
+/**
+ * Check the tweak pack channel for a newer pack
+ */
+export async function checkForTweakUpdates(): Promise<TweakPackUpdate> {
+  return await invoke("check_for_tweak_updates");
+}
+
+/**
+ * Download, verify, and stage the channel's tweak pack. It takes effect at the next launch.
+ */
+export async function installTweakPack(): Promise<PackManifest> {
+  return await invoke("install_tweak_pack");
+}
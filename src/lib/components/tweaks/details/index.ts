@@ -1,6 +1,22 @@
+export { default as AppChangeItem } from "./AppChangeItem.svelte";
+export { default as AppxChangeItem } from "./AppxChangeItem.svelte";
+export { default as AudioChangeItem } from "./AudioChangeItem.svelte";
+export { default as BcdeditChangeItem } from "./BcdeditChangeItem.svelte";
+export { default as BrowserPolicyChangeItem } from "./BrowserPolicyChangeItem.svelte";
 export { default as CommandList } from "./CommandList.svelte";
+export { default as ComponentChangeItem } from "./ComponentChangeItem.svelte";
+export { default as ContextMenuChangeItem } from "./ContextMenuChangeItem.svelte";
+export { default as FeatureChangeItem } from "./FeatureChangeItem.svelte";
+export { default as FileChangeItem } from "./FileChangeItem.svelte";
 export { default as FirewallChangeItem } from "./FirewallChangeItem.svelte";
 export { default as HostsChangeItem } from "./HostsChangeItem.svelte";
+export { default as MmcssChangeItem } from "./MmcssChangeItem.svelte";
+export { default as NetAdapterChangeItem } from "./NetAdapterChangeItem.svelte";
+export { default as PolicyChangeItem } from "./PolicyChangeItem.svelte";
+export { default as PowerPlanChangeItem } from "./PowerPlanChangeItem.svelte";
+export { default as RegistryAclChangeItem } from "./RegistryAclChangeItem.svelte";
 export { default as RegistryChangeItem } from "./RegistryChangeItem.svelte";
 export { default as SchedulerChangeItem } from "./SchedulerChangeItem.svelte";
 export { default as ServiceChangeItem } from "./ServiceChangeItem.svelte";
+export { default as TimeChangeItem } from "./TimeChangeItem.svelte";
+export { default as VisualEffectsChangeItem } from "./VisualEffectsChangeItem.svelte";
@@ -59,10 +59,13 @@ export * from "./types";
 // API - explicitly export to avoid conflicts with stores
 export {
   batchApplyTweaks,
+  createRestorePoint,
   getAllTweaksWithStatus,
   getAvailableTweaks,
   getSystemInfo,
   getTweakStatus,
   getWindowsVersion,
   isAdmin,
+  listRestorePoints,
+  refreshSystemInfo,
 } from "./api";
@@ -0,0 +1,123 @@
+/**
+ * Collections Store - Svelte 5 Runes
+ *
+ * Named groups of tweaks (e.g. the ones re-applied after every feature update) with
+ * localStorage persistence. Like favorites, only tweak IDs are stored - tweak data comes
+ * from tweaksStore.
+ */
+
+import type { TweakWithStatus } from "$lib/types";
+import { PersistentStore } from "$lib/utils/persistentStore.svelte";
+import { tweaksStore } from "./tweaksData.svelte";
+
+const STORAGE_KEY = "magicx-collections";
+
+export interface TweakCollection {
+  id: string;
+  name: string;
+  tweakIds: string[];
+  createdAt: string;
+}
+
+// Persistent state
+const collectionsState = new PersistentStore<TweakCollection[]>(STORAGE_KEY, []);
+
+// === Derived Values ===
+const list = $derived(collectionsState.value);
+const count = $derived(collectionsState.value.length);
+
+function update(collectionId: string, change: (collection: TweakCollection) => TweakCollection): void {
+  collectionsState.value = collectionsState.value.map((c) => (c.id === collectionId ? change(c) : c));
+}
+
+// === Export ===
+export const collectionsStore = {
+  /** Get all collections, oldest first */
+  get list() {
+    return list;
+  },
+
+  /** Get the count of collections */
+  get count() {
+    return count;
+  },
+
+  /** Get a collection by ID */
+  get(collectionId: string): TweakCollection | undefined {
+    return collectionsState.value.find((c) => c.id === collectionId);
+  },
+
+  /** Create a collection, optionally filled with tweaks, and return it */
+  create(name: string, tweakIds: string[] = []): TweakCollection {
+    const collection: TweakCollection = {
+      id: crypto.randomUUID(),
+      name: name.trim(),
+      tweakIds: [...new Set(tweakIds)],
+      createdAt: new Date().toISOString(),
+    };
+    collectionsState.value = [...collectionsState.value, collection];
+    return collection;
+  },
+
+  /** Rename a collection */
+  rename(collectionId: string, name: string): void {
+    update(collectionId, (c) => ({ ...c, name: name.trim() }));
+  },
+
+  /** Delete a collection (the tweaks themselves are not affected) */
+  delete(collectionId: string): void {
+    collectionsState.value = collectionsState.value.filter((c) => c.id !== collectionId);
+  },
+
+  /** Check if a tweak is in a collection */
+  contains(collectionId: string, tweakId: string): boolean {
+    return this.get(collectionId)?.tweakIds.includes(tweakId) ?? false;
+  },
+
+  /** Add a tweak to a collection */
+  add(collectionId: string, tweakId: string): void {
+    update(collectionId, (c) => (c.tweakIds.includes(tweakId) ? c : { ...c, tweakIds: [...c.tweakIds, tweakId] }));
+  },
+
+  /** Remove a tweak from a collection */
+  remove(collectionId: string, tweakId: string): void {
+    update(collectionId, (c) => ({ ...c, tweakIds: c.tweakIds.filter((id) => id !== tweakId) }));
+  },
+
+  /** Toggle a tweak's membership in a collection */
+  toggle(collectionId: string, tweakId: string): boolean {
+    if (this.contains(collectionId, tweakId)) {
+      this.remove(collectionId, tweakId);
+      return false;
+    } else {
+      this.add(collectionId, tweakId);
+      return true;
+    }
+  },
+
+  /** Move tweaks saved under a renamed tweak's earlier ID to its current one */
+  renameIds(renamedTo: Map<string, string>): void {
+    if (!collectionsState.value.some((c) => c.tweakIds.some((id) => renamedTo.has(id)))) return;
+    collectionsState.value = collectionsState.value.map((c) => ({
+      ...c,
+      tweakIds: [...new Set(c.tweakIds.map((id) => renamedTo.get(id) ?? id))],
+    }));
+  },
+
+  /** Collections that contain a tweak */
+  containing(tweakId: string): TweakCollection[] {
+    return collectionsState.value.filter((c) => c.tweakIds.includes(tweakId));
+  },
+};
+
+/**
+ * The tweaks of a collection with their current status, in the order they were added.
+ * IDs of tweaks that no longer exist (or aren't loaded yet) are skipped.
+ */
+export function getCollectionTweaks(collectionId: string): TweakWithStatus[] {
+  const collection = collectionsStore.get(collectionId);
+  if (!collection) return [];
+  return collection.tweakIds
+    .map((id) => tweaksStore.getById(id))
+    .filter((tweak): tweak is TweakWithStatus => tweak !== undefined);
+}
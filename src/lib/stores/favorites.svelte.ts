@@ -62,6 +62,12 @@ export const favoritesStore = {
     }
   },
 
+  /** Move favorites saved under a renamed tweak's earlier ID to its current one */
+  renameIds(renamedTo: Map<string, string>): void {
+    if (!favoritesState.value.some((id) => renamedTo.has(id))) return;
+    favoritesState.value = [...new Set(favoritesState.value.map((id) => renamedTo.get(id) ?? id))];
+  },
+
   /** Clear all favorites */
   clear(): void {
     favoritesState.value = [];
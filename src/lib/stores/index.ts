@@ -8,7 +8,8 @@ export { isSidebarOpen, sidebarStore, sidebarWidthClass, type SidebarState } fro
 export { settingsStore } from "./settings.svelte";
 export { themeStore, type Theme } from "./theme.svelte";
 
-// Favorites
+// Favorites & collections
+export { collectionsStore, getCollectionTweaks, type TweakCollection } from "./collections.svelte";
 export { favoritesStore } from "./favorites.svelte";
 
 // Modal state
@@ -35,6 +36,8 @@ export { searchStore } from "./search.svelte";
 
 // Tweaks system (split into logical modules)
 export {
+  // Application change progress
+  appProgressStore,
   applyPendingChanges,
   applyTweak,
   categoriesStore,
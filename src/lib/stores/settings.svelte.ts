@@ -10,6 +10,8 @@ const defaultSettings: AppSettings = {
   autoInstallUpdates: false,
   checkUpdateInterval: 24, // hours
   lastUpdateCheck: null,
+  createRestorePoint: false,
+  transactionalApply: false,
 };
 
 // Persistent state
@@ -20,6 +22,8 @@ const autoCheckUpdates = $derived(settingsState.value.autoCheckUpdates);
 const autoInstallUpdates = $derived(settingsState.value.autoInstallUpdates);
 const checkUpdateInterval = $derived(settingsState.value.checkUpdateInterval);
 const lastUpdateCheck = $derived(settingsState.value.lastUpdateCheck);
+const createRestorePoint = $derived(settingsState.value.createRestorePoint ?? false);
+const transactionalApply = $derived(settingsState.value.transactionalApply ?? false);
 
 export const settingsStore = {
   get settings() {
@@ -42,6 +46,14 @@ export const settingsStore = {
     return lastUpdateCheck;
   },
 
+  get createRestorePoint() {
+    return createRestorePoint;
+  },
+
+  get transactionalApply() {
+    return transactionalApply;
+  },
+
   update(newSettings: Partial<AppSettings>) {
     settingsState.value = { ...settingsState.value, ...newSettings };
   },
@@ -61,4 +73,12 @@ export const settingsStore = {
   setLastUpdateCheck(date: string | null) {
     this.update({ lastUpdateCheck: date });
   },
+
+  setCreateRestorePoint(enabled: boolean) {
+    this.update({ createRestorePoint: enabled });
+  },
+
+  setTransactionalApply(enabled: boolean) {
+    this.update({ transactionalApply: enabled });
+  },
 };
@@ -36,6 +36,9 @@ export {
 // Loading & error stores
 export { errorStore, loadingStore } from "./tweaksLoading.svelte";
 
+// Application change progress
+export { appProgressStore, batchProgressStore, restoreAllProgressStore } from "./tweaksProgress.svelte";
+
 // Pending changes & reboot stores
 export { pendingChangesStore, pendingRebootStore } from "./tweaksPending.svelte";
 
@@ -44,10 +47,21 @@ export {
   applyPendingChanges,
   applyTweak,
   batchRevertTweaks,
+  cancelRunningOperations,
+  dismissIncompleteApplies,
+  exportBackupArchive,
   filterStore,
+  importBackupArchive,
   keepCurrentState,
+  reapplyAppliedTweaks,
+  relaunchElevated,
+  restoreAllToBaseline,
+  restoreResumedChanges,
+  restoreSnapshotGeneration,
   revertTweak,
+  rollBackIncompleteApplies,
   stageChange,
   toggleTweak,
+  undoLastOperation,
   unstageChange,
 } from "./tweaksActions.svelte";
@@ -5,11 +5,14 @@
  */
 
 import * as api from "$lib/api/tweaks";
-import type { PendingChange, TweakWithStatus } from "$lib/types";
+import { open, save } from "@tauri-apps/plugin-dialog";
+import type { PendingChange, TweakResult, TweakWithStatus } from "$lib/types";
+import { settingsStore } from "./settings.svelte";
 import { toastStore } from "./toast.svelte";
-import { tweaksStore } from "./tweaksData.svelte";
+import { systemStore, tweaksStore } from "./tweaksData.svelte";
 import { errorStore, loadingStore } from "./tweaksLoading.svelte";
 import { pendingChangesStore, pendingRebootStore } from "./tweaksPending.svelte";
+import { appProgressStore, batchProgressStore, restoreAllProgressStore } from "./tweaksProgress.svelte";
 
 // === Search and Filter State ===
 let searchQuery = $state<string>("");
@@ -59,6 +62,14 @@ export const filterStore = {
 
 // === Actions ===
 
+/** Toast text for a successful apply/revert, noting a pending reboot/sign-out or Explorer restart */
+function successMessage(verb: "Applied" | "Reverted", result: TweakResult, requiresReboot = false): string {
+  if (result.requires_reboot || requiresReboot) return `${verb} (reboot required)`;
+  if (result.requires_logoff) return `${verb} (sign out to finish)`;
+  if (result.restarted_explorer) return `${verb} (Explorer restarted)`;
+  return `${verb} successfully`;
+}
+
 /**
  * Apply a tweak with a specific option
  */
@@ -66,16 +77,18 @@ export async function applyTweak(
   tweakId: string,
   optionIndex: number,
   requiresReboot: boolean = false,
-  options?: { showToast?: boolean; tweakName?: string },
+  options?: { showToast?: boolean; tweakName?: string; riskAcknowledged?: boolean },
 ): Promise<boolean> {
   const showToast = options?.showToast ?? true;
   const tweakName = options?.tweakName ?? tweaksStore.getById(tweakId)?.definition.name;
 
   loadingStore.start(tweakId);
   errorStore.clearError(tweakId);
+  const operationId = loadingStore.beginOperation();
 
   try {
-    const result = await api.applyTweak(tweakId, optionIndex);
+    const riskAck = options?.riskAcknowledged ? (await api.acknowledgeRisk(tweakId)).token : undefined;
+    const result = await api.applyTweak(tweakId, optionIndex, false, operationId, riskAck);
 
     if (result.success) {
       // Query actual backup status (backend may skip if already at desired state)
@@ -90,11 +103,9 @@ export async function applyTweak(
       // Track if this tweak requires reboot
       if (result.requires_reboot || requiresReboot) {
         pendingRebootStore.add(tweakId);
-        if (showToast) {
-          toastStore.success("Applied (reboot required)", { tweakName });
-        }
-      } else if (showToast) {
-        toastStore.success("Applied successfully", { tweakName });
+      }
+      if (showToast) {
+        toastStore.success(successMessage("Applied", result, requiresReboot), { tweakName });
       }
 
       return true;
@@ -112,6 +123,11 @@ export async function applyTweak(
       return false;
     }
   } catch (error) {
+    // Restart as administrator with this change staged, rather than just reporting it needs that
+    if (errorCode(error) === "REQUIRES_ADMIN") {
+      await relaunchElevated({ tweakId, optionIndex });
+      return false;
+    }
     const message = error instanceof Error ? error.message : "Failed to apply tweak";
     errorStore.setError(tweakId, message);
     if (showToast) {
@@ -120,15 +136,26 @@ export async function applyTweak(
     return false;
   } finally {
     loadingStore.stop(tweakId);
+    loadingStore.endOperation(operationId);
+    appProgressStore.clear(tweakId);
   }
 }
 
+/**
+ * Cancel the running applies (each rolls back what it changed) and any other command running as SYSTEM or
+ * TrustedInstaller
+ */
+export async function cancelRunningOperations(): Promise<void> {
+  await Promise.all(loadingStore.operationIds.map((id) => api.cancelOperation(id)));
+  await api.cancelElevatedOperations();
+}
+
 /**
  * Revert a tweak to its original state
  */
 export async function revertTweak(
   tweakId: string,
-  options?: { showToast?: boolean; tweakName?: string },
+  options?: { showToast?: boolean; tweakName?: string; force?: boolean },
 ): Promise<boolean> {
   const showToast = options?.showToast ?? true;
   const tweakName = options?.tweakName ?? tweaksStore.getById(tweakId)?.definition.name;
@@ -137,7 +164,7 @@ export async function revertTweak(
   errorStore.clearError(tweakId);
 
   try {
-    const result = await api.revertTweak(tweakId);
+    const result = await api.revertTweak(tweakId, options?.force);
 
     if (result.success) {
       // Query actual status after revert to get the correct current_option_index and status_inferred
@@ -162,11 +189,9 @@ export async function revertTweak(
       // If reverting also requires reboot, add it back
       if (result.requires_reboot) {
         pendingRebootStore.add(tweakId);
-        if (showToast) {
-          toastStore.success("Reverted (reboot required)", { tweakName });
-        }
-      } else if (showToast) {
-        toastStore.success("Reverted successfully", { tweakName });
+      }
+      if (showToast) {
+        toastStore.success(successMessage("Reverted", result), { tweakName });
       }
 
       return true;
@@ -204,6 +229,7 @@ export async function revertTweak(
     return false;
   } finally {
     loadingStore.stop(tweakId);
+    appProgressStore.clear(tweakId);
   }
 }
 
@@ -247,6 +273,66 @@ export async function keepCurrentState(
   }
 }
 
+/**
+ * Return a tweak to one of its snapshot generations. Generation 0 is the original state, so it's a revert.
+ */
+export async function restoreSnapshotGeneration(
+  tweakId: string,
+  generation: number,
+  options?: { showToast?: boolean; tweakName?: string },
+): Promise<boolean> {
+  if (generation === 0) return revertTweak(tweakId, options);
+
+  const showToast = options?.showToast ?? true;
+  const tweakName = options?.tweakName ?? tweaksStore.getById(tweakId)?.definition.name;
+
+  loadingStore.start(tweakId);
+  errorStore.clearError(tweakId);
+
+  try {
+    const result = await api.restoreSnapshotGeneration(tweakId, generation);
+
+    if (result.success) {
+      // The snapshot stays; only the option the system is at changed
+      const actualStatus = await api.getTweakStatus(tweakId);
+      tweaksStore.updateStatus(tweakId, {
+        is_applied: actualStatus.is_applied,
+        current_option_index: actualStatus.current_option_index,
+        status_inferred: actualStatus.status_inferred,
+      });
+      pendingChangesStore.clear(tweakId);
+
+      if (result.requires_reboot) {
+        pendingRebootStore.add(tweakId);
+      }
+      if (showToast) {
+        toastStore.success(result.message, { tweakName });
+      }
+      return true;
+    } else {
+      const failureDetails =
+        result.failures && result.failures.length > 0
+          ? result.failures.map(([, msg]) => msg).join("; ")
+          : result.message;
+      errorStore.setError(tweakId, failureDetails);
+      if (showToast) {
+        toastStore.warning(result.message, { tweakName });
+      }
+      return false;
+    }
+  } catch (error) {
+    const message = error instanceof Error ? error.message : "Failed to restore snapshot generation";
+    errorStore.setError(tweakId, message);
+    if (showToast) {
+      toastStore.error(message, { tweakName });
+    }
+    return false;
+  } finally {
+    loadingStore.stop(tweakId);
+    appProgressStore.clear(tweakId);
+  }
+}
+
 /**
  * Toggle a tweak on/off (for is_toggle tweaks)
  */
@@ -263,6 +349,52 @@ export async function toggleTweak(
   }
 }
 
+/** The `code` of an error from the backend, if it has one */
+function errorCode(error: unknown): string | undefined {
+  return typeof error === "object" && error !== null && "code" in error ? String(error.code) : undefined;
+}
+
+/**
+ * Restart as administrator through the UAC prompt, keeping the staged changes for the new instance.
+ * `change` is staged first, so a single apply that needed admin rights is waiting there too.
+ */
+export async function relaunchElevated(change?: PendingChange): Promise<boolean> {
+  if (change) {
+    pendingChangesStore.set(change.tweakId, change);
+  }
+  try {
+    await api.relaunchElevated([...pendingChangesStore.all.values()]);
+    return true;
+  } catch (error) {
+    if (errorCode(error) === "CANCELLED") {
+      toastStore.info("Administrator rights are needed to apply these changes; they stay staged");
+    } else {
+      console.error("Failed to restart as administrator:", error);
+      toastStore.error("Failed to restart as administrator");
+    }
+    return false;
+  }
+}
+
+/**
+ * Stage the changes handed over by the instance that relaunched this one as administrator
+ */
+export async function restoreResumedChanges(): Promise<void> {
+  const operations = await api.takeResumedOperations();
+  for (const operation of operations) {
+    pendingChangesStore.set(operation.tweak_id, {
+      tweakId: operation.tweak_id,
+      optionIndex: operation.option_index,
+      force: operation.force || undefined,
+      riskAcknowledged: operation.risk_acknowledged || undefined,
+    });
+  }
+  const count = operations.length;
+  if (count > 0) {
+    toastStore.info(`Restarted as administrator: ${count} staged change${count > 1 ? "s are" : " is"} ready to apply`);
+  }
+}
+
 /**
  * Stage a change (doesn't apply yet, just marks it pending)
  */
@@ -289,6 +421,8 @@ export async function applyPendingChanges(): Promise<{ success: number; failed:
 
   // Build operations array for batch API
   const operations: [string, number][] = [];
+  // Staged with the user's consent despite a pending reboot
+  const forced: string[] = [];
   // eslint-disable-next-line svelte/prefer-svelte-reactivity -- Local variable, not reactive state
   const tweakMap = new Map<string, { change: PendingChange; tweak: TweakWithStatus }>();
 
@@ -296,6 +430,7 @@ export async function applyPendingChanges(): Promise<{ success: number; failed:
     const tweak = tweaksStore.getById(tweakId);
     if (!tweak) continue;
     operations.push([change.tweakId, change.optionIndex]);
+    if (change.force) forced.push(change.tweakId);
     tweakMap.set(tweakId, { change, tweak });
   }
 
@@ -303,25 +438,57 @@ export async function applyPendingChanges(): Promise<{ success: number; failed:
     return { success: 0, failed: 0 };
   }
 
+  // Without admin rights, restart elevated with the changes staged instead of failing them
+  const needsAdmin = [...tweakMap.values()].some(({ tweak }) => tweak.definition.requires_admin);
+  if (needsAdmin && systemStore.info?.is_admin === false) {
+    await relaunchElevated();
+    return { success: 0, failed: 0 };
+  }
+
+  const operationId = loadingStore.beginOperation();
   try {
+    // Tokens for the risky tweaks the user confirmed when staging them; the backend refuses the others
+    const riskAcks: string[] = [];
+    for (const { change, tweak } of tweakMap.values()) {
+      if (change.riskAcknowledged && isHighRisk(tweak)) {
+        riskAcks.push((await api.acknowledgeRisk(change.tweakId)).token);
+      }
+    }
+
     // Use batch API for single IPC call instead of N calls
-    const result = await api.batchApplyTweaks(operations);
+    const result = await api.batchApplyTweaks(
+      operations,
+      settingsStore.createRestorePoint,
+      forced,
+      operationId,
+      settingsStore.transactionalApply,
+      riskAcks,
+    );
+    if (result.restore_point_sequence !== undefined) {
+      console.info(`Created restore point ${result.restore_point_sequence} before applying`);
+    }
 
-    // Count successes and failures from result
-    const totalAttempted = operations.length;
-    const failedCount = result.failures?.length ?? 0;
-    const successCount = totalAttempted - failedCount;
+    // Count successes and failures from the per-tweak results
+    const failedIds = failedTweakIds(result);
+    const failedCount = failedIds.size;
+    const successCount = operations.length - failedCount;
+    for (const item of result.results ?? []) {
+      if (!item.success) {
+        console.warn(`Failed to apply ${item.tweak_id} (${item.error_code ?? "rolled back"}):`, item.message);
+      }
+    }
 
     // Clear successful pending changes
-    // eslint-disable-next-line svelte/prefer-svelte-reactivity -- Local variable, not reactive state
-    const failedIds = new Set(result.failures?.map(([id]) => id) ?? []);
     for (const tweakId of tweakMap.keys()) {
       if (!failedIds.has(tweakId)) {
         pendingChangesStore.clear(tweakId);
 
         // Track reboot requirement
         const entry = tweakMap.get(tweakId);
-        if (entry?.tweak.definition.requires_reboot) {
+        if (
+          entry?.tweak.definition.requires_reboot ||
+          entry?.tweak.definition.options[entry.change.optionIndex]?.requires_reboot
+        ) {
           pendingRebootStore.add(tweakId);
         }
 
@@ -335,7 +502,14 @@ export async function applyPendingChanges(): Promise<{ success: number; failed:
     }
 
     // Show summary toast
-    if (failedCount === 0 && successCount > 0) {
+    if (result.rollback) {
+      const incomplete = result.rollback.items.filter((item) => !item.restored).length;
+      if (incomplete > 0) {
+        toastStore.error(`${result.message} (${incomplete} could not be fully restored and keep their snapshots)`);
+      } else {
+        toastStore.warning(result.message);
+      }
+    } else if (failedCount === 0 && successCount > 0) {
       toastStore.success(`Applied ${successCount} tweak${successCount > 1 ? "s" : ""} successfully`);
     } else if (failedCount > 0 && successCount > 0) {
       toastStore.warning(`Applied ${successCount}, failed ${failedCount} tweak${failedCount > 1 ? "s" : ""}`);
@@ -346,8 +520,235 @@ export async function applyPendingChanges(): Promise<{ success: number; failed:
     return { success: successCount, failed: failedCount };
   } catch (error) {
     console.error("Batch apply failed:", error);
-    toastStore.error("Failed to apply pending changes");
+    toastStore.error(
+      settingsStore.createRestorePoint
+        ? "Failed to apply pending changes (no changes are made if the restore point can't be created)"
+        : "Failed to apply pending changes",
+    );
     return { success: 0, failed: operations.length };
+  } finally {
+    loadingStore.endOperation(operationId);
+    batchProgressStore.clear();
+  }
+}
+
+/** Whether applying the tweak takes a risk acknowledgement (high or critical risk) */
+function isHighRisk(tweak: TweakWithStatus): boolean {
+  return tweak.definition.risk_level === "high" || tweak.definition.risk_level === "critical";
+}
+
+/** Tweaks of a batch that didn't succeed, from its per-tweak results */
+function failedTweakIds(result: TweakResult): Set<string> {
+  const failed = (result.results ?? []).filter((item) => !item.success).map((item) => item.tweak_id);
+  // A transactional batch undoes the tweaks applied before its failure and never reaches the rest
+  const undone = [...(result.rollback?.items ?? []).map((item) => item.tweak_id), ...(result.rollback?.skipped ?? [])];
+  // eslint-disable-next-line svelte/prefer-svelte-reactivity -- Local variable, not reactive state
+  return new Set([...failed, ...undone]);
+}
+
+/**
+ * Revert every applied tweak to its original state in one backend pass
+ */
+export async function restoreAllToBaseline(): Promise<{ success: number; failed: number }> {
+  try {
+    const report = await api.restoreAllToBaseline();
+
+    for (const result of report.results) {
+      if (result.success) {
+        tweaksStore.updateStatus(result.tweak_id, {
+          is_applied: false,
+          current_option_index: null,
+          has_backup: false,
+        });
+        pendingRebootStore.remove(result.tweak_id);
+      } else {
+        errorStore.setError(result.tweak_id, result.failures.length > 0 ? result.failures.join("; ") : result.message);
+      }
+    }
+
+    const successCount = report.results.filter((r) => r.success).length;
+    const failedCount = report.results.length - successCount;
+
+    if (failedCount === 0 && successCount > 0) {
+      toastStore.success(`Restored ${successCount} snapshot${successCount > 1 ? "s" : ""} successfully`);
+    } else if (failedCount > 0 && successCount > 0) {
+      toastStore.warning(`Restored ${successCount}, failed ${failedCount} snapshot${failedCount > 1 ? "s" : ""}`);
+    } else if (failedCount > 0) {
+      toastStore.error(`Failed to restore ${failedCount} snapshot${failedCount > 1 ? "s" : ""}`);
+    }
+
+    return { success: successCount, failed: failedCount };
+  } catch (error) {
+    console.error("Restore all failed:", error);
+    toastStore.error("Failed to restore snapshots");
+    return { success: 0, failed: 0 };
+  } finally {
+    restoreAllProgressStore.clear();
+  }
+}
+
+/**
+ * Apply every applied tweak again with its recorded option, e.g. after a feature update reset settings
+ */
+export async function reapplyAppliedTweaks(): Promise<{ success: number; failed: number }> {
+  try {
+    const report = await api.reapplyAppliedTweaks();
+    const results = report.result.results ?? [];
+
+    for (const item of results) {
+      if (item.success && item.requires_reboot) {
+        pendingRebootStore.add(item.tweak_id);
+      } else if (!item.success) {
+        errorStore.setError(item.tweak_id, item.message);
+      }
+    }
+    await tweaksStore.refreshStatuses().catch((error) => console.warn("Failed to refresh statuses:", error));
+
+    const successCount = results.filter((item) => item.success).length;
+    const failedCount = results.length - successCount;
+    const skipped = report.already_applied.length;
+
+    if (results.length === 0) {
+      toastStore.info(`All ${skipped} applied tweak${skipped === 1 ? " is" : "s are"} still in place`);
+    } else if (failedCount === 0) {
+      toastStore.success(`Re-applied ${successCount} tweak${successCount > 1 ? "s" : ""} (${skipped} still in place)`);
+    } else if (successCount > 0) {
+      toastStore.warning(`Re-applied ${successCount}, failed ${failedCount} tweak${failedCount > 1 ? "s" : ""}`);
+    } else {
+      toastStore.error(`Failed to re-apply ${failedCount} tweak${failedCount > 1 ? "s" : ""}`);
+    }
+
+    return { success: successCount, failed: failedCount };
+  } catch (error) {
+    console.error("Re-apply failed:", error);
+    toastStore.error("Failed to re-apply applied tweaks");
+    return { success: 0, failed: 0 };
+  } finally {
+    batchProgressStore.clear();
+  }
+}
+
+/**
+ * Roll back applies that were cut short by a crash, then reload tweak statuses
+ */
+export async function rollBackIncompleteApplies(tweakIds: string[]): Promise<void> {
+  let failed = 0;
+  for (const tweakId of tweakIds) {
+    try {
+      const result = await api.rollBackIncompleteApply(tweakId);
+      if (result.success) {
+        if (result.requires_reboot) {
+          pendingRebootStore.add(tweakId);
+        }
+      } else {
+        failed++;
+        errorStore.setError(tweakId, result.message);
+      }
+    } catch (error) {
+      failed++;
+      console.error(`Rollback of ${tweakId} failed:`, error);
+    }
+  }
+  await tweaksStore.load();
+
+  if (failed === 0) {
+    toastStore.success(`Rolled back ${tweakIds.length} unfinished change${tweakIds.length === 1 ? "" : "s"}`);
+  } else {
+    toastStore.warning(`Failed to roll back ${failed} unfinished change${failed === 1 ? "" : "s"}`);
+  }
+}
+
+/**
+ * Keep the state left by applies that were cut short by a crash
+ */
+export async function dismissIncompleteApplies(tweakIds: string[]): Promise<void> {
+  for (const tweakId of tweakIds) {
+    try {
+      await api.dismissIncompleteApply(tweakId);
+    } catch (error) {
+      console.error(`Failed to dismiss incomplete apply of ${tweakId}:`, error);
+    }
+  }
+}
+
+/**
+ * Undo the last apply/revert/batch operation, then reload tweak statuses
+ * @returns Whether everything it changed was undone
+ */
+export async function undoLastOperation(): Promise<boolean> {
+  try {
+    const result = await api.undoLastOperation();
+    await tweaksStore.load();
+
+    for (const [tweakId, message] of result.failures ?? []) {
+      errorStore.setError(tweakId, message);
+    }
+    if (result.success) {
+      toastStore.success(result.message);
+    } else {
+      toastStore.warning(result.message);
+    }
+    return result.success;
+  } catch (error) {
+    const message = error instanceof Error ? error.message : String(error);
+    console.error("Undo failed:", error);
+    toastStore.error(message || "Failed to undo the last operation");
+    return false;
+  }
+}
+
+/** File dialog filter for backup archives */
+const BACKUP_ARCHIVE_FILTERS = [{ name: "MagicX Backup", extensions: ["mgxbak"] }];
+
+/**
+ * Export every snapshot to a backup archive chosen by the user
+ * @returns Whether an archive was written
+ */
+export async function exportBackupArchive(): Promise<boolean> {
+  try {
+    // eslint-disable-next-line svelte/prefer-svelte-reactivity -- Not reactive state, just for filename
+    const timestamp = new Date().toISOString().slice(0, 10);
+    const filePath = await save({ defaultPath: `magicx-backup-${timestamp}.mgxbak`, filters: BACKUP_ARCHIVE_FILTERS });
+    if (!filePath) return false;
+
+    const count = await api.exportBackupArchive(filePath);
+    toastStore.success(`Exported ${count} snapshot${count === 1 ? "" : "s"}`);
+    return true;
+  } catch (error) {
+    console.error("Backup export failed:", error);
+    toastStore.error("Failed to export backup");
+    return false;
+  }
+}
+
+/**
+ * Import the snapshots from a backup archive chosen by the user, then reload tweak statuses
+ * @returns Whether an archive was imported
+ */
+export async function importBackupArchive(): Promise<boolean> {
+  try {
+    const filePath = await open({ multiple: false, filters: BACKUP_ARCHIVE_FILTERS });
+    if (!filePath || typeof filePath !== "string") return false;
+
+    const report = await api.importBackupArchive(filePath);
+    await tweaksStore.load();
+
+    const imported = report.imported.length;
+    const skipped = report.skipped.length;
+    let message = `Imported ${imported} snapshot${imported === 1 ? "" : "s"}`;
+    if (skipped > 0) {
+      message += `, skipped ${skipped} already present`;
+    }
+    if (report.from_other_machine) {
+      toastStore.warning(`${message}. The backup was made on a different machine.`);
+    } else {
+      toastStore.success(message);
+    }
+    return true;
+  } catch (error) {
+    console.error("Backup import failed:", error);
+    toastStore.error("Failed to import backup");
+    return false;
   }
 }
 
@@ -363,14 +764,12 @@ export async function batchRevertTweaks(tweakIds: string[]): Promise<{ success:
     // Use batch API for single IPC call instead of N calls
     const result = await api.batchRevertTweaks(tweakIds);
 
-    // Count successes and failures from result
-    const totalAttempted = tweakIds.length;
-    const failedCount = result.failures?.length ?? 0;
-    const successCount = totalAttempted - failedCount;
+    // Count successes and failures from the per-tweak results
+    const failedIds = failedTweakIds(result);
+    const failedCount = failedIds.size;
+    const successCount = tweakIds.length - failedCount;
 
     // Update status for successful reverts
-    // eslint-disable-next-line svelte/prefer-svelte-reactivity -- Local variable, not reactive state
-    const failedIds = new Set(result.failures?.map(([id]) => id) ?? []);
     for (const tweakId of tweakIds) {
       if (!failedIds.has(tweakId)) {
         // Update status to reflect reverted state
@@ -398,5 +797,7 @@ export async function batchRevertTweaks(tweakIds: string[]): Promise<{ success:
     console.error("Batch revert failed:", error);
     toastStore.error("Failed to restore snapshots");
     return { success: 0, failed: tweakIds.length };
+  } finally {
+    batchProgressStore.clear();
   }
 }
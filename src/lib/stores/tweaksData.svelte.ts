@@ -6,9 +6,21 @@
  * System hardware info is cached in localStorage since it rarely changes.
  */
 
+import { browser } from "$app/environment";
 import * as api from "$lib/api/tweaks";
-import type { CachedSystemInfo, CategoryDefinition, SystemInfo, TweakStatus, TweakWithStatus } from "$lib/types";
+import type {
+  CachedSystemInfo,
+  CategoryDefinition,
+  DeferredApply,
+  ExternalChange,
+  SystemInfo,
+  TweaksReload,
+  TweakStatus,
+  TweakWithStatus,
+} from "$lib/types";
 import { PersistentStore } from "$lib/utils/persistentStore.svelte";
+import { listen } from "@tauri-apps/api/event";
+import { toastStore } from "./toast.svelte";
 
 // Storage key for cached hardware info
 const SYSTEM_INFO_CACHE_KEY = "magicx-system-info-cache";
@@ -101,6 +113,8 @@ function buildSystemInfoFromCache(cache: CachedSystemInfo, freshInfo: SystemInfo
     windows: freshInfo.windows,
     username: freshInfo.username,
     is_admin: freshInfo.is_admin,
+    power: freshInfo.power,
+    reboot_pending: freshInfo.reboot_pending,
     // Use cached static data
     hardware: cache.hardware,
     device: cache.device,
@@ -174,6 +188,18 @@ export const systemStore = {
           hardware: cached.hardware,
           device: cached.device,
           computer_name: cached.computer_name,
+          power: {
+            has_battery: false,
+            on_ac_power: null,
+            charging: false,
+            charge_percent: null,
+            design_capacity_mwh: null,
+            full_charge_capacity_mwh: null,
+            health_percent: null,
+            active_plan_guid: null,
+            active_plan_name: null,
+          },
+          reboot_pending: false,
         };
         return systemInfo;
       }
@@ -190,7 +216,7 @@ export const systemStore = {
   async refresh() {
     systemInfoRefreshing = true;
     try {
-      const freshInfo = await api.getSystemInfo();
+      const freshInfo = await api.refreshSystemInfo();
       systemInfo = freshInfo;
       updateCache(freshInfo);
       return freshInfo;
@@ -284,6 +310,17 @@ export const tweaksStore = {
     }
   },
 
+  /** Detect every tweak's status again, for changes made outside the app */
+  async refreshStatuses() {
+    // eslint-disable-next-line svelte/prefer-svelte-reactivity -- Local variable, not reactive state
+    const statuses = new Map((await api.refreshTweakStatuses(true)).map((s) => [s.tweak_id, s]));
+    tweaks = tweaks.map((t) => {
+      const status = statuses.get(t.definition.id);
+      return status ? { ...t, status } : t;
+    });
+    tweaksVersion++;
+  },
+
   /** Update a single tweak's status */
   updateStatus(tweakId: string, status: Partial<TweakStatus>) {
     tweaks = tweaks.map((t) => (t.definition.id === tweakId ? { ...t, status: { ...t.status, ...status } } : t));
@@ -382,3 +419,95 @@ export async function loadRemainingData(): Promise<void> {
 
   return remainingDataPromise;
 }
+
+/**
+ * The backend reports each tweak whose status changed since it was last detected: found by the periodic
+ * revalidation of applied tweaks (also after resume from sleep) or by a refresh, so its badge updates without a restart
+ */
+async function setupStatusListener() {
+  try {
+    await listen<TweakStatus>("tweak://status-changed", (event) => {
+      tweaksStore.updateStatus(event.payload.tweak_id, event.payload);
+    });
+  } catch (error) {
+    console.warn("Failed to set up tweak status listener:", error);
+  }
+}
+
+/**
+ * The backend reports each applied tweak that Windows Update or another tool reverted, and whether it was applied
+ * again (auto-reapply in Settings)
+ */
+async function setupExternalChangeListener() {
+  try {
+    await listen<ExternalChange>("tweak://external-change", (event) => {
+      const change = event.payload;
+      const tweakName = change.tweak_name;
+      if (change.reapplied) {
+        toastStore.info(`Reverted outside the app; re-applied "${change.applied_option_label}"`, { tweakName });
+      } else if (change.error) {
+        toastStore.error(`Reverted outside the app and couldn't be re-applied: ${change.error}`, { tweakName });
+      } else {
+        toastStore.warning(`Reverted outside the app; no longer "${change.applied_option_label}"`, { tweakName });
+      }
+    });
+  } catch (error) {
+    console.warn("Failed to set up external change listener:", error);
+  }
+}
+
+/**
+ * The backend reports each deferred apply (queued for the next boot or a set time) once it ran
+ */
+async function setupDeferredApplyListener() {
+  try {
+    await listen<DeferredApply>("tweak://deferred-apply", (event) => {
+      const entry = event.payload;
+      const tweakName = entry.tweak_name;
+      if (entry.outcome?.success) {
+        toastStore.success(`Applied "${entry.option_label}" as scheduled`, { tweakName });
+        api
+          .getTweakStatus(entry.tweak_id)
+          .then((status) => tweaksStore.updateStatus(entry.tweak_id, status))
+          .catch((err) => console.warn("Failed to refresh status after a scheduled apply:", err));
+      } else {
+        toastStore.error(`Scheduled apply failed: ${entry.outcome?.message ?? "unknown error"}`, { tweakName });
+      }
+    });
+  } catch (error) {
+    console.warn("Failed to set up deferred apply listener:", error);
+  }
+}
+
+/**
+ * Debug builds reload the tweak YAML when it's edited; load the new definitions, or show why they weren't taken
+ */
+async function setupTweaksReloadedListener() {
+  try {
+    await listen<TweaksReload>("tweaks://reloaded", (event) => {
+      const reload = event.payload;
+      if (!reload.reloaded) {
+        toastStore.error(`Tweaks not reloaded: ${reload.errors[0] ?? "unknown error"}`);
+        return;
+      }
+      Promise.all([categoriesStore.load(), tweaksStore.load()])
+        .then(() => {
+          if (reload.errors.length > 0) {
+            toastStore.warning(`Reloaded ${reload.tweak_count} tweaks; ${reload.errors[0]}`);
+          } else {
+            toastStore.info(`Reloaded ${reload.tweak_count} tweaks`);
+          }
+        })
+        .catch((err) => console.warn("Failed to load the reloaded tweaks:", err));
+    });
+  } catch (error) {
+    console.warn("Failed to set up tweaks reloaded listener:", error);
+  }
+}
+
+if (browser) {
+  setupStatusListener();
+  setupExternalChangeListener();
+  setupDeferredApplyListener();
+  setupTweaksReloadedListener();
+}
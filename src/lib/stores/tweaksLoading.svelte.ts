@@ -8,6 +8,8 @@ import { SvelteMap, SvelteSet } from "svelte/reactivity";
 
 // === Loading State ===
 const loadingTweaks = new SvelteSet<string>();
+/** IDs of the running applies, for `cancelOperation` */
+const operationIds = new SvelteSet<string>();
 
 // === Error State ===
 const errors = new SvelteMap<string, string>();
@@ -34,6 +36,23 @@ export const loadingStore = {
   stop(tweakId: string) {
     loadingTweaks.delete(tweakId);
   },
+
+  /** IDs of the applies running now */
+  get operationIds(): string[] {
+    return [...operationIds];
+  },
+
+  /** Name a new apply so it can be cancelled; pass the ID to the API call and `endOperation` it after */
+  beginOperation(): string {
+    const id = crypto.randomUUID();
+    operationIds.add(id);
+    return id;
+  },
+
+  /** Forget an apply that has finished */
+  endOperation(id: string) {
+    operationIds.delete(id);
+  },
 };
 
 export const errorStore = {
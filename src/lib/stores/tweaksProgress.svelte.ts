@@ -0,0 +1,96 @@
+/**
+ * Tweaks Progress Store - Svelte 5 Runes
+ *
+ * Tracks per-package progress of application (winget) changes, which the backend reports while a
+ * tweak is applied or reverted because a single install can take minutes, and per-tweak progress
+ * while a batch of tweaks is applied or reverted or all snapshots are restored at once.
+ */
+
+import { browser } from "$app/environment";
+import type { AppProgress, BatchProgress, RestoreAllProgress } from "$lib/types";
+import { listen } from "@tauri-apps/api/event";
+import { SvelteMap } from "svelte/reactivity";
+
+// === Progress State ===
+// Latest in-flight package per tweak
+const progress = new SvelteMap<string, AppProgress>();
+// Tweak being reverted while all snapshots are restored
+let restoreAllCurrent = $state<RestoreAllProgress | null>(null);
+// Tweak being applied or reverted in a batch
+let batchCurrent = $state<BatchProgress | null>(null);
+
+async function setupListener() {
+  try {
+    await listen<AppProgress>("app-change-progress", (event) => {
+      const payload = event.payload;
+      if (payload.stage === "started") {
+        progress.set(payload.tweakId, payload);
+      } else {
+        progress.delete(payload.tweakId);
+      }
+    });
+    await listen<RestoreAllProgress>("restore-all-progress", (event) => {
+      const payload = event.payload;
+      restoreAllCurrent = payload.stage === "started" ? payload : null;
+    });
+    await listen<BatchProgress>("tweak://batch-progress", (event) => {
+      const payload = event.payload;
+      batchCurrent = payload.stage === "started" ? payload : null;
+    });
+  } catch (error) {
+    console.warn("Failed to set up app progress listener:", error);
+  }
+}
+
+if (browser) {
+  setupListener();
+}
+
+// === Exports ===
+
+export const appProgressStore = {
+  /** The package a tweak is currently installing or uninstalling, if any */
+  get(tweakId: string): AppProgress | undefined {
+    return progress.get(tweakId);
+  },
+
+  /** Human-readable progress line, e.g. "Installing Microsoft.OneDrive (1/2)" */
+  describe(tweakId: string): string | undefined {
+    const current = progress.get(tweakId);
+    if (!current) return undefined;
+    const verb = current.action === "install" ? "Installing" : "Uninstalling";
+    const position = current.total > 1 ? ` (${current.index}/${current.total})` : "";
+    return `${verb} ${current.id}${position}`;
+  },
+
+  /** Forget any progress for a tweak (e.g. once its apply or revert call returns) */
+  clear(tweakId: string) {
+    progress.delete(tweakId);
+  },
+};
+
+export const batchProgressStore = {
+  /** Human-readable progress line, e.g. "Disable Telemetry (3/10)" */
+  get description(): string | undefined {
+    if (!batchCurrent) return undefined;
+    return `${batchCurrent.tweakName} (${batchCurrent.index}/${batchCurrent.total})`;
+  },
+
+  /** Forget the progress (once the batch call returns) */
+  clear() {
+    batchCurrent = null;
+  },
+};
+
+export const restoreAllProgressStore = {
+  /** Human-readable progress line, e.g. "Restoring Disable Telemetry (3/10)" */
+  get description(): string | undefined {
+    if (!restoreAllCurrent) return undefined;
+    return `Restoring ${restoreAllCurrent.tweakName} (${restoreAllCurrent.index}/${restoreAllCurrent.total})`;
+  },
+
+  /** Forget the progress (once the restore call returns) */
+  clear() {
+    restoreAllCurrent = null;
+  },
+};
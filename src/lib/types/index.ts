@@ -6,8 +6,8 @@ export type RiskLevel = "low" | "medium" | "high" | "critical";
 /** Permission level for tweaks (hierarchical: ti > system > admin > none) */
 export type PermissionLevel = "none" | "admin" | "system" | "ti";
 
-/** Registry hive types */
-export type RegistryHive = "HKCU" | "HKLM";
+/** Registry hive types ("ALL_USERS" targets every loaded user account hive under HKU) */
+export type RegistryHive = "HKCU" | "HKLM" | "HKCR" | "HKU" | "ALL_USERS";
 
 /** Registry value types */
 export type RegistryValueType = "REG_DWORD" | "REG_SZ" | "REG_EXPAND_SZ" | "REG_BINARY" | "REG_MULTI_SZ" | "REG_QWORD";
@@ -33,6 +33,54 @@ export type RegistryValue = number | string | string[] | number[] | null;
 /** Action type for registry operations */
 export type RegistryAction = "set" | "delete_value" | "delete_key" | "create_key";
 
+/** Tags grouping tweaks across categories, as declared for tweak files */
+export type TweakTag = "telemetry" | "privacy" | "ads" | "ai" | "gaming" | "battery" | "visuals";
+
+/** Windows edition family, as matched by `conditions.edition` */
+export type WindowsEdition = "Home" | "Pro" | "Education" | "Enterprise" | "Server";
+
+/** A Windows build (`22631`), or a build and cumulative update revision (`"22631.2428"`) */
+export type WindowsBuild = number | string;
+
+/** Hardware/edition/build guards on an option or registry change. Every guard that is set must hold. */
+export interface Conditions {
+  is_laptop?: boolean;
+  has_battery?: boolean;
+  has_ssd?: boolean;
+  edition?: WindowsEdition[];
+  min_ram_gb?: number;
+  min_build?: WindowsBuild;
+  max_build?: WindowsBuild;
+}
+
+/** Short labels for each guard in a conditions block (e.g. "laptop", "RAM >= 16 GB") */
+export function describeConditions(conditions: Conditions | null | undefined): string[] {
+  if (!conditions) return [];
+  const labels: string[] = [];
+  if (conditions.is_laptop !== undefined && conditions.is_laptop !== null) {
+    labels.push(conditions.is_laptop ? "laptop" : "desktop");
+  }
+  if (conditions.has_battery !== undefined && conditions.has_battery !== null) {
+    labels.push(conditions.has_battery ? "battery" : "no battery");
+  }
+  if (conditions.has_ssd !== undefined && conditions.has_ssd !== null) {
+    labels.push(conditions.has_ssd ? "SSD" : "no SSD");
+  }
+  if (conditions.edition && conditions.edition.length > 0) {
+    labels.push(conditions.edition.join("/"));
+  }
+  if (conditions.min_ram_gb) {
+    labels.push(`RAM >= ${conditions.min_ram_gb} GB`);
+  }
+  if (conditions.min_build !== undefined && conditions.min_build !== null) {
+    labels.push(`build >= ${conditions.min_build}`);
+  }
+  if (conditions.max_build !== undefined && conditions.max_build !== null) {
+    labels.push(`build <= ${conditions.max_build}`);
+  }
+  return labels;
+}
+
 /** Registry change within an option */
 export interface RegistryChange {
   hive: RegistryHive;
@@ -46,10 +94,24 @@ export interface RegistryChange {
   value: RegistryValue;
   /** Optional Windows version filter. If undefined/empty, applies to all versions. */
   windows_versions?: number[];
+  /** Optional hardware/edition guards. The change is skipped on machines where they don't hold. */
+  conditions?: Conditions | null;
+  /** This change only takes effect after a reboot */
+  requires_reboot?: boolean;
+  /** This change only takes effect after signing out */
+  requires_logoff?: boolean;
   /** If true, skip this change for tweak status validation and ignore failures during apply */
   skip_validation?: boolean;
+  /** Privilege this change runs at; defaults to the tweak's */
+  elevation?: ChangeElevation | null;
 }
 
+/** Privilege a single registry, service, or scheduler change runs at */
+export type ChangeElevation = "user" | "admin" | "system" | "ti";
+
+/** Interpreter an option's commands run in */
+export type CommandShell = "cmd" | "powershell" | "pwsh";
+
 /** Service change within an option */
 export interface ServiceChange {
   /** Service name (e.g., "SysMain", "DiagTrack") */
@@ -58,6 +120,8 @@ export interface ServiceChange {
   startup: ServiceStartupType;
   /** If true, skip this change for tweak status validation and ignore failures during apply */
   skip_validation?: boolean;
+  /** Privilege this change runs at; defaults to the tweak's */
+  elevation?: ChangeElevation | null;
 }
 
 /** Action for scheduled task changes */
@@ -77,6 +141,8 @@ export interface SchedulerChange {
   skip_validation?: boolean;
   /** If true, don't error if task/path not found (useful for optional tasks) */
   ignore_not_found?: boolean;
+  /** Privilege this change runs at; defaults to the tweak's */
+  elevation?: ChangeElevation | null;
 }
 
 /** Action for hosts file changes */
@@ -136,10 +202,675 @@ export interface FirewallChange {
   skip_validation?: boolean;
 }
 
+/** Action for power plan changes */
+export type PowerPlanAction = "set_active_scheme" | "set_setting_value";
+
+/** Power plan change within an option */
+export interface PowerPlanChange {
+  /** Action to perform: set_active_scheme or set_setting_value */
+  action: PowerPlanAction;
+  /** Scheme GUID or alias (SCHEME_MIN, SCHEME_MAX, SCHEME_BALANCED, SCHEME_CURRENT) */
+  scheme?: string;
+  /** Subgroup GUID (required for set_setting_value) */
+  subgroup?: string;
+  /** Setting GUID (required for set_setting_value) */
+  setting?: string;
+  /** Value index while on AC power */
+  ac_value?: number;
+  /** Value index while on battery */
+  dc_value?: number;
+  /** If true, skip this change for tweak status validation */
+  skip_validation?: boolean;
+}
+
+/** BCD action type */
+export type BcdeditAction = "set" | "delete_value";
+
+/** BCD store change within an option */
+export interface BcdeditChange {
+  /** BCD entry identifier (e.g., "{current}", "{default}"); defaults to {current} */
+  identifier?: string;
+  /** Element name (e.g., "useplatformclock") */
+  element: string;
+  /** Action to perform (default: set) */
+  action?: BcdeditAction;
+  /** Value to set (required for set) */
+  value?: string;
+  /** If true, skip this change for tweak status validation */
+  skip_validation?: boolean;
+}
+
+/** AppX action type */
+export type AppxAction = "remove" | "remove_provisioned" | "reinstall";
+
+/** AppX scope */
+export type AppxScope = "current_user" | "all_users";
+
+/** AppX (UWP) package change within an option */
+export interface AppxChange {
+  /** Package name (e.g., "Microsoft.BingNews"); `*` wildcards allowed */
+  package: string;
+  /** Action to perform */
+  action: AppxAction;
+  /** Users affected (default: current_user) */
+  scope?: AppxScope;
+  /** If true, skip this change for tweak status validation */
+  skip_validation?: boolean;
+}
+
+/** File action type */
+export type FileAction = "copy" | "delete" | "rename" | "set_attributes" | "write_content";
+
+/** File attribute flags (omitted flags are left unchanged) */
+export interface FileAttributes {
+  readonly?: boolean;
+  hidden?: boolean;
+  system?: boolean;
+}
+
+/** File or folder change within an option */
+export interface FileChange {
+  /** Absolute path; `%VAR%` environment references are expanded */
+  path: string;
+  /** Action to perform */
+  action: FileAction;
+  /** Target path (required for copy and rename) */
+  destination?: string;
+  /** Attribute flags (required for set_attributes) */
+  attributes?: FileAttributes;
+  /** Text to write (required for write_content) */
+  content?: string;
+  /** If true, don't back up the original before overwriting or deleting it */
+  skip_backup?: boolean;
+  /** If true, skip this change for tweak status validation */
+  skip_validation?: boolean;
+}
+
+/** Registry ACL action type */
+export type RegistryAclAction = "take_ownership" | "grant" | "revoke";
+
+/** Access level granted by a registry ACL change */
+export type RegistryAccess = "full_control" | "read";
+
+/** Registry key ownership/permission change within an option */
+export interface RegistryAclChange {
+  /** Registry hive */
+  hive: RegistryHive;
+  /** Registry key path (without hive) */
+  key: string;
+  /** Action to perform */
+  action: RegistryAclAction;
+  /** SID, SDDL alias (e.g. "BA"), or account name (default: Administrators; required for revoke) */
+  trustee?: string;
+  /** Access to grant (grant only; default: full_control) */
+  access?: RegistryAccess;
+  /** If true, skip this change for tweak status validation */
+  skip_validation?: boolean;
+}
+
+/** Optional feature action type */
+export type FeatureAction = "enable" | "disable";
+
+/** Windows optional feature change within an option */
+export interface FeatureChange {
+  /** Feature name as reported by Get-WindowsOptionalFeature (e.g. "Microsoft-Hyper-V") */
+  feature: string;
+  /** Action to perform */
+  action: FeatureAction;
+  /** If true, skip this change for tweak status validation */
+  skip_validation?: boolean;
+}
+
+/** Network adapter advanced property change within an option */
+export interface NetAdapterChange {
+  /** Adapter name as shown by Get-NetAdapter; `*` wildcards allowed (default: every physical adapter) */
+  adapter?: string;
+  /** Advanced property registry keyword (e.g. "*RSS", "*InterruptModeration") */
+  keyword: string;
+  /** Registry value to set */
+  value: string;
+  /** If true, skip this change for tweak status validation */
+  skip_validation?: boolean;
+}
+
+/** Group Policy scope: computer (HKLM) or user (HKCU) policies */
+export type PolicyScope = "machine" | "user";
+
+/** Policy action type */
+export type PolicyAction = "set" | "delete_value";
+
+/** Group Policy value change within an option */
+export interface PolicyChange {
+  /** Computer or user policy */
+  scope: PolicyScope;
+  /** Key under SOFTWARE\Policies (or SOFTWARE\Microsoft\Windows\CurrentVersion\Policies) */
+  key: string;
+  value_name: string;
+  /** Action to perform (default: "set") */
+  action?: PolicyAction;
+  /** Value type (required for set action) */
+  value_type?: RegistryValueType;
+  /** The value to set (required for set action) */
+  value?: RegistryValue;
+  /** If true, also record the value in the local Group Policy object's Registry.pol */
+  write_pol?: boolean;
+  /** If true, skip this change for tweak status validation */
+  skip_validation?: boolean;
+}
+
+/** Browser whose enterprise policies a browser policy change sets */
+export type Browser = "edge" | "chrome" | "firefox";
+
+/** Browser enterprise policy within an option, named as the browser documents it */
+export interface BrowserPolicyChange {
+  browser: Browser;
+  /** Policy name (e.g., "DnsOverHttpsMode") */
+  policy: string;
+  /** Computer (default) or user policy */
+  scope?: PolicyScope;
+  /** Action to perform (default: "set") */
+  action?: PolicyAction;
+  /** Boolean, integer, string, list of strings, or object (required for set action) */
+  value?: unknown;
+  /** If true, skip this change for tweak status validation */
+  skip_validation?: boolean;
+}
+
+/** Application action type */
+export type AppAction = "install" | "uninstall";
+
+/** winget application install/uninstall within an option */
+export interface AppChange {
+  /** winget package identifier (e.g., "Microsoft.OneDrive"), matched exactly */
+  id: string;
+  /** Action to perform */
+  action: AppAction;
+  /** winget source: "winget" or "msstore" (installs default to "winget") */
+  source?: string;
+  /** Exact version to install (install only; default: latest) */
+  version?: string;
+  /** If true, skip this change for tweak status validation */
+  skip_validation?: boolean;
+}
+
+/** MMCSS scheduling category */
+export type MmcssSchedulingCategory = "low" | "medium" | "high";
+
+/** MMCSS scheduled I/O priority */
+export type MmcssIoPriority = "idle" | "low" | "normal" | "high";
+
+/** Multimedia Class Scheduler task settings within an option (unset fields are left alone) */
+export interface MmcssChange {
+  /** Task key under ...\Multimedia\SystemProfile\Tasks (e.g., "Games") */
+  task: string;
+  /** Thread priority, 1-8 */
+  priority?: number | null;
+  /** GPU priority, 0-31 */
+  gpu_priority?: number | null;
+  scheduling_category?: MmcssSchedulingCategory | null;
+  sfio_priority?: MmcssIoPriority | null;
+  background_only?: boolean | null;
+  /** Timer resolution in 100-ns units (10000 = 1 ms) */
+  clock_rate?: number | null;
+  /** Processor affinity mask (0 = all processors) */
+  affinity?: number | null;
+  /** If true, skip this change for tweak status validation */
+  skip_validation?: boolean;
+}
+
+/** Context menu registration scope: current user (HKCU) or all users (HKLM) */
+export type ContextMenuScope = "user" | "machine";
+
+/** Context menu entry kind */
+export type ContextMenuKind = "verb" | "handler" | "classic_menu";
+
+/** Context menu action type */
+export type ContextMenuAction = "add" | "remove";
+
+/** Shell context menu change within an option */
+export interface ContextMenuChange {
+  action: ContextMenuAction;
+  /** Entry kind (default: "verb") */
+  kind?: ContextMenuKind;
+  /** Registration scope (default: "user") */
+  scope?: ContextMenuScope;
+  /** Class the entry belongs to (e.g., "*", "Directory", ".txt"); empty for classic_menu */
+  target?: string;
+  /** Verb or handler key name; empty for classic_menu */
+  name?: string;
+  /** Menu text (verb add) */
+  label?: string | null;
+  /** Command line the verb runs (verb add) */
+  command?: string | null;
+  /** Icon resource (verb add) */
+  icon?: string | null;
+  /** Only shown with Shift held (verb add) */
+  extended?: boolean;
+  /** Handler CLSID (handler add) */
+  clsid?: string | null;
+  /** If true, skip this change for tweak status validation */
+  skip_validation?: boolean;
+}
+
+/** Windows sound scheme */
+export type SoundScheme = "none" | "default";
+
+/** What Windows does to other sounds during a call */
+export type CommunicationsDucking = "mute_others" | "reduce_80" | "reduce_50" | "do_nothing";
+
+/** Audio settings within an option (unset fields are left alone) */
+export interface AudioChange {
+  sound_scheme?: SoundScheme | null;
+  /** Whether the startup sound plays at sign-in */
+  startup_sound?: boolean | null;
+  communications_ducking?: CommunicationsDucking | null;
+  /** Whether audio enhancements are enabled on every playback device */
+  audio_enhancements?: boolean | null;
+  /** If true, skip this change for tweak status validation */
+  skip_validation?: boolean;
+}
+
+/** A visual effect from the Performance Options dialog (plus transparency and font smoothing) */
+export type VisualEffect =
+  | "menu_animation"
+  | "combo_box_animation"
+  | "smooth_scrolling"
+  | "selection_fade"
+  | "tooltip_animation"
+  | "cursor_shadow"
+  | "window_shadows"
+  | "control_animations"
+  | "window_animations"
+  | "taskbar_animations"
+  | "icon_shadows"
+  | "transparency"
+  | "font_smoothing";
+
+/** Turn one visual effect on or off */
+export interface VisualEffectsChange {
+  effect: VisualEffect;
+  enabled: boolean;
+  /** If true, skip this change for tweak status validation */
+  skip_validation?: boolean;
+}
+
+/** Where the Windows Time service syncs the clock from */
+export type TimeSyncType = "ntp" | "nt5ds" | "all_sync" | "no_sync";
+
+/** Windows Time service settings within an option (unset fields are left alone) */
+export interface TimeChange {
+  /** NTP servers, in order of preference */
+  ntp_servers?: string[] | null;
+  sync_type?: TimeSyncType | null;
+  /** Seconds between syncs with the NTP servers */
+  sync_interval?: number | null;
+  /** If true, skip this change for tweak status validation */
+  skip_validation?: boolean;
+}
+
+/** Optional Windows component made up of services */
+export type LegacyComponent = "print_spooler" | "fax" | "xbox";
+
+/** Turn an optional component's services on or off as a bundle */
+export interface ComponentChange {
+  component: LegacyComponent;
+  /** true restores the services' default startup types; false disables and stops them (dependents first) */
+  enabled: boolean;
+  /** If true, skip this change for tweak status validation and ignore failures during apply */
+  skip_validation?: boolean;
+}
+
+/** Registry location captured by a state probe: one value, or the whole key when value_name is omitted */
+export interface RegistryProbe {
+  hive: RegistryHive;
+  key: string;
+  value_name?: string | null;
+}
+
+/** State an option's commands change, captured before apply and restored on revert (never applied) */
+export interface StateProbes {
+  registry: RegistryProbe[];
+  /** Files and folders to back up */
+  files: string[];
+  /** Services whose startup type and running state are captured */
+  services: string[];
+}
+
+/** Progress of one package while application changes are applied or reverted (`app-change-progress` event) */
+export interface AppProgress {
+  tweakId: string;
+  /** winget package identifier */
+  id: string;
+  action: AppAction;
+  /** 1-based position of this package in the batch */
+  index: number;
+  total: number;
+  stage: "started" | "finished" | "failed";
+  /** Failure reason (failed stage only) */
+  message?: string | null;
+}
+
+/** Progress of one tweak while all snapshots are restored (`restore-all-progress` event) */
+export interface RestoreAllProgress {
+  tweakId: string;
+  tweakName: string;
+  /** 1-based position of this tweak in the run */
+  index: number;
+  total: number;
+  stage: "started" | "finished" | "failed";
+}
+
+/** Progress of one tweak in a batch apply or revert (`tweak://batch-progress` event) */
+export interface BatchProgress {
+  tweakId: string;
+  tweakName: string;
+  /** 1-based position of this tweak in the batch */
+  index: number;
+  total: number;
+  stage: "started" | "finished" | "failed";
+  /** Failure reason (failed stage only) */
+  message?: string | null;
+}
+
+/** Outcome of reverting one tweak while restoring all snapshots */
+export interface TweakRestoreReport {
+  tweak_id: string;
+  tweak_name: string;
+  /** Whether everything was restored (and the snapshot released) */
+  success: boolean;
+  message: string;
+  /** What couldn't be restored; the snapshot is kept so the revert can be retried */
+  failures: string[];
+}
+
+/** Result of re-applying every applied tweak with the option its snapshot records */
+export interface ReapplyReport {
+  /** Tweaks that still had the option they were applied with, left as they were */
+  already_applied: string[];
+  /** Outcome of the tweaks applied again, as for a batch apply */
+  result: TweakResult;
+}
+
+/** Result of restoring all snapshots, one report per tweak in the order they were reverted */
+export interface RestoreAllReport {
+  success: boolean;
+  message: string;
+  requires_reboot: boolean;
+  requires_logoff: boolean;
+  restarted_explorer: boolean;
+  results: TweakRestoreReport[];
+}
+
+/** Where snapshots are stored */
+export type SnapshotLocation =
+  | { kind: "portable" }
+  | { kind: "program_data" }
+  | { kind: "app_data" }
+  | { kind: "custom"; path: string };
+
+/** The snapshot location in use */
+export interface SnapshotLocationInfo {
+  location: SnapshotLocation;
+  /** Resolved snapshots directory */
+  path: string;
+  /** False when the location was picked automatically */
+  configured: boolean;
+}
+
+/** One entry of a tweak's snapshot history */
+export interface SnapshotGeneration {
+  /** 0 for the original state, then 1, 2, ... in the order they were captured */
+  generation: number;
+  created_at: string;
+  /** Option the tweak was at in this state (for the original: null if it matched no option) */
+  option_index: number | null;
+  /** Label of that option (null for the original) */
+  option_label: string | null;
+}
+
+/** How much snapshot history is kept per tweak */
+export interface HistoryRetention {
+  /** Generations kept besides the original; 0 turns history off */
+  max_generations: number;
+  /** Generations older than this many days are dropped; null keeps them regardless of age */
+  max_age_days: number | null;
+  /** Size (MB) the history of all tweaks together is kept under, oldest generations first; null for no cap */
+  max_total_mb: number | null;
+}
+
+/** What a snapshot history cleanup removed */
+export interface CleanupReport {
+  generations_removed: number;
+  bytes_freed: number;
+}
+
+/** What an export of the applied tweaks as a script wrote */
+export interface ScriptExportReport {
+  /** Tweaks written to the script, in the order it applies them */
+  exported: string[];
+  /** Exported tweaks with changes the script can't make */
+  partial: PartialScriptExport[];
+  /** Applied tweaks left out: their definition is gone or their snapshot can't be read */
+  skipped: string[];
+}
+
+/** The changes of an exported tweak that the script leaves out, e.g. "2 hosts changes" */
+export interface PartialScriptExport {
+  tweak_id: string;
+  unsupported: string[];
+}
+
+/** Disk used by one applied tweak */
+export interface TweakStorageUsage {
+  tweak_id: string;
+  tweak_name: string;
+  /** When the tweak was first applied */
+  created_at: string;
+  /** Snapshot file, history, and file backups together */
+  size_bytes: number;
+  /** The part of size_bytes taken by kept generations, which history retention can free */
+  history_bytes: number;
+}
+
+/** Disk used by snapshots */
+export interface BackupStorageStats {
+  /** Largest first */
+  tweaks: TweakStorageUsage[];
+  /** Everything in the snapshots directory */
+  total_bytes: number;
+  /** The tweak applied longest ago */
+  oldest: TweakStorageUsage | null;
+}
+
+/** Result of importing a backup archive */
+export interface ArchiveImportReport {
+  /** Tweaks whose snapshots were imported */
+  imported: string[];
+  /** Tweaks skipped because a snapshot for them already exists */
+  skipped: string[];
+  /** The archive was made on a different machine */
+  from_other_machine: boolean;
+  /** Notes added for tweaks that had none */
+  notes_imported: number;
+}
+
+/** The user's note on a tweak */
+export interface TweakNote {
+  text: string;
+  /** When it was last changed (ISO 8601) */
+  updated_at: string;
+}
+
+/** How often a tweak was applied, reverted, and failed */
+export interface TweakUsage {
+  /** Successful applies */
+  applied: number;
+  /** Successful reverts */
+  reverted: number;
+  /** Applies and reverts that failed */
+  failed: number;
+  /** Failures by error code */
+  failures: Record<string, number>;
+  /** When it was last applied or reverted (ISO 8601) */
+  last_used?: string;
+}
+
+/** The local usage statistics */
+export interface TweakStatsReport {
+  /** Whether applies and reverts are being counted (opt-in) */
+  enabled: boolean;
+  /** Counts by tweak ID */
+  tweaks: Record<string, TweakUsage>;
+}
+
+/** When a deferred apply runs: once the machine booted again, or at a set time */
+export type DeferredTrigger =
+  | { kind: "next_boot"; /** Boot it was queued in (ISO 8601) */ queued_boot: string }
+  | { kind: "at"; /** ISO 8601 */ at: string };
+
+/** How a deferred apply went */
+export interface DeferredOutcome {
+  /** When it ran (ISO 8601) */
+  finished_at: string;
+  success: boolean;
+  message: string;
+}
+
+/** A tweak option queued to be applied at the next boot or at a set time */
+export interface DeferredApply {
+  id: string;
+  tweak_id: string;
+  tweak_name: string;
+  option_index: number;
+  option_label: string;
+  /** When it was queued (ISO 8601) */
+  queued_at: string;
+  trigger: DeferredTrigger;
+  /** null while it waits */
+  outcome: DeferredOutcome | null;
+}
+
+/** A snapshot that failed verification and was moved to the `corrupt` folder */
+export interface QuarantinedSnapshot {
+  tweak_id: string;
+  reason: string;
+  /** Where the file was moved to */
+  moved_to: string;
+}
+
+/** Result of verifySnapshots */
+export interface SnapshotVerifyReport {
+  /** Snapshots whose checksum matched */
+  valid: string[];
+  /** Snapshots without a checksum (written by an older version) */
+  unchecked: string[];
+  quarantined: QuarantinedSnapshot[];
+}
+
+/** The state of everything the tweaks reference, captured on first run */
+export interface BaselineInfo {
+  /** When it was captured (RFC 3339) */
+  created_at: string;
+  windows_version: number;
+  registry_count: number;
+  service_count: number;
+  task_count: number;
+}
+
+/** Result of restoreKeyToBaseline */
+export interface BaselineRestoreResult {
+  /** Keys and values put back, as `HIVE\key` or `HIVE\key\value` */
+  restored: string[];
+  failures: string[];
+}
+
+/** How an item's current state relates to its snapshot */
+export type DiffStatus = "matching" | "original" | "changed_externally" | "missing";
+
+export interface DiffEntry {
+  /** `HIVE\key\value`, `HIVE\key`, a service name, or `\path\task` */
+  item: string;
+  /** State in the snapshot (null: absent) */
+  original: unknown;
+  /** State now (null: absent) */
+  current: unknown;
+  status: DiffStatus;
+}
+
+/** A tweak's snapshot compared with the current state */
+export interface SnapshotDiff {
+  tweak_id: string;
+  /** Label of the option the snapshot was taken for */
+  option_label: string;
+  registry: DiffEntry[];
+  services: DiffEntry[];
+  tasks: DiffEntry[];
+  /** Entries a revert would overwrite */
+  changed_externally: number;
+  missing: number;
+}
+
+/** An apply cut short by a crash or power loss, recorded before it changed anything */
+export interface ApplyIntent {
+  tweak_id: string;
+  tweak_name: string;
+  option_index: number;
+  option_label: string;
+  /** When the apply started (RFC 3339) */
+  started_at: string;
+  /** When switching options: the option the snapshot recorded before */
+  previous_option: [number, string] | null;
+  /** When switching options: the state captured before the switch */
+  previous_state: unknown;
+}
+
+/** Picks one registry value/key, service, or task out of a snapshot for restoreChanges */
+export type ChangeSelector =
+  | { kind: "registry"; hive: string; key: string; value_name?: string | null }
+  | { kind: "service"; name: string }
+  | { kind: "task"; task_path: string; task_name: string };
+
+/** Result of restoring (part of) a snapshot */
+export interface RestoreResult {
+  success: boolean;
+  failures: string[];
+}
+
+/** What a journaled operation did */
+export type OperationKind = "apply" | "revert" | "restore_generation" | "batch_apply" | "batch_revert" | "restore_all";
+
+/** One tweak changed by a journaled operation */
+export interface JournalChange {
+  tweak_id: string;
+  tweak_name: string;
+  /** Option the tweak was applied with before (null: not applied) */
+  before: number | null;
+  /** Option it was applied with after (null: reverted) */
+  after: number | null;
+}
+
+/** An apply/revert/batch recorded in the operation journal */
+export interface JournalOperation {
+  id: number;
+  kind: OperationKind;
+  /** When it finished (RFC 3339) */
+  timestamp: string;
+  changes: JournalChange[];
+  undone: boolean;
+}
+
 /** A single option within a tweak - contains all changes for that state */
 export interface TweakOption {
   /** Display label (e.g., "Enabled", "Disabled", "4MB") */
   label: string;
+  /** Optional hardware/edition guards. The option can't be applied on machines where they don't hold. */
+  conditions?: Conditions | null;
+  /** Switching to or reverting this option needs a reboot */
+  requires_reboot?: boolean;
+  /** Switching to or reverting this option needs a sign-out */
+  requires_logoff?: boolean;
+  /** explorer.exe is restarted after this option is applied or reverted */
+  restart_explorer?: boolean;
   /** Registry modifications for this option */
   registry_changes: RegistryChange[];
   /** Service modifications for this option */
@@ -150,6 +881,38 @@ export interface TweakOption {
   hosts_changes: HostsChange[];
   /** Firewall rule modifications for this option */
   firewall_changes: FirewallChange[];
+  /** Power plan modifications for this option */
+  power_plan_changes: PowerPlanChange[];
+  /** BCD store modifications for this option */
+  bcdedit_changes: BcdeditChange[];
+  /** AppX (UWP) package modifications for this option */
+  appx_changes: AppxChange[];
+  /** File and folder modifications for this option */
+  file_changes: FileChange[];
+  /** Registry key ownership and permission modifications for this option */
+  registry_acl_changes: RegistryAclChange[];
+  /** Windows optional feature modifications for this option */
+  feature_changes: FeatureChange[];
+  /** Network adapter advanced property modifications for this option */
+  netadapter_changes: NetAdapterChange[];
+  /** Group Policy value modifications for this option */
+  policy_changes: PolicyChange[];
+  /** winget application installs/uninstalls for this option */
+  app_changes: AppChange[];
+  /** Multimedia Class Scheduler task modifications for this option */
+  mmcss_changes: MmcssChange[];
+  /** Shell context menu modifications for this option */
+  context_menu_changes: ContextMenuChange[];
+  /** Audio setting modifications for this option */
+  audio_changes: AudioChange[];
+  /** Visual effect modifications for this option */
+  visual_effects_changes: VisualEffectsChange[];
+  /** Browser enterprise policy modifications for this option */
+  browser_policy_changes: BrowserPolicyChange[];
+  /** Windows Time service modifications for this option */
+  time_changes: TimeChange[];
+  /** Optional component (service bundle) modifications for this option */
+  component_changes: ComponentChange[];
   /** Shell commands to run BEFORE applying changes */
   pre_commands: string[];
   /** PowerShell commands to run BEFORE applying changes (after pre_commands) */
@@ -158,6 +921,12 @@ export interface TweakOption {
   post_commands: string[];
   /** PowerShell commands to run AFTER applying changes (after post_commands) */
   post_powershell: string[];
+  /** Interpreter for pre/post_commands (default: cmd); pwsh also runs the PowerShell scripts in PowerShell 7 */
+  shell?: CommandShell | null;
+  /** Seconds each command may run when run as SYSTEM or TrustedInstaller (default: 30) */
+  command_timeout_secs?: number | null;
+  /** State the option's commands change, captured so revert can restore it */
+  state_probes: StateProbes;
   /**
    * If true, treat missing registry keys/values as matching this option.
    * Used for tweaks that modify registry entries which may not exist on all Windows editions.
@@ -208,6 +977,83 @@ export interface TweakDefinition {
   force_dropdown: boolean;
   /** Available options for this tweak (minimum 2) */
   options: TweakOption[];
+  /** Loaded from the user's `tweaks.d` folder rather than compiled in */
+  custom: boolean;
+  /** No longer recommended; kept so it can still be reverted */
+  deprecated: boolean;
+  /** ID of the tweak that replaces this deprecated one */
+  superseded_by: string | null;
+  /** Earlier IDs of this tweak, still resolved to it */
+  aliases: string[];
+  /** Only offered from this build on */
+  min_build: WindowsBuild | null;
+  /** Only offered up to this build */
+  max_build: WindowsBuild | null;
+  /** Only offered on these editions */
+  editions: WindowsEdition[] | null;
+  /** Groups across categories the tweak belongs to */
+  tags: TweakTag[];
+}
+
+/** One file of the user's `tweaks.d` folder */
+export interface CustomTweakFile {
+  file: string;
+  /** IDs of the tweaks loaded from it; empty if it has errors */
+  tweak_ids: string[];
+  errors: string[];
+  warnings: string[];
+}
+
+/** What loading the `tweaks.d` folder found */
+export interface CustomTweaksReport {
+  /** The folder read, or null if it couldn't be located */
+  directory: string | null;
+  files: CustomTweakFile[];
+}
+
+/** A custom tweak authored in the app, and the `tweaks.d` file it goes in */
+export interface TweakDraft {
+  /** File name in `tweaks.d`, e.g. `my_tweaks.yaml`; created if it doesn't exist */
+  file: string;
+  /** The file's category: needed for a new file, and replaces an existing file's */
+  category?: CategoryDefinition | null;
+  /** The tweak with the fields it has in YAML, written in this order */
+  tweak: Record<string, unknown>;
+  /** ID of the tweak in the file that the draft edits; omit to add a tweak */
+  original_id?: string | null;
+}
+
+/** What validating or saving a tweak draft found */
+export interface DraftReport {
+  file: string;
+  errors: string[];
+  warnings: string[];
+  /** Whether the file was written; never when there are errors */
+  saved: boolean;
+}
+
+/** A reload of the tweak YAML after an edit (`tweaks://reloaded`, debug builds only) */
+export interface TweaksReload {
+  /** False if a source file has errors; the tweaks in use are then left as they were */
+  reloaded: boolean;
+  tweak_count: number;
+  category_count: number;
+  /** Errors in the source files, or else in the `tweaks.d` files left out */
+  errors: string[];
+}
+
+/** Filters of `searchTweaks`; each one left out matches every tweak */
+export interface TweakSearchFilters {
+  /** Any of these risk levels */
+  risk_levels?: RiskLevel[];
+  /** Any of these categories */
+  category_ids?: string[];
+  /** Whether the tweak itself declares it needs a reboot */
+  requires_reboot?: boolean;
+  /** Any of these privilege levels, as the tweak declares it */
+  elevations?: ChangeElevation[];
+  /** Whether the tweak is applied by the app (has a snapshot) */
+  applied?: boolean;
 }
 
 /** Status of a tweak in the system */
@@ -241,6 +1087,23 @@ export interface TweakStatus {
   needs_attention?: boolean;
   /** Resources a partial revert could not restore (present only when needs_attention). */
   unrestorable_resources?: string[];
+  /** Indexes of options whose conditions don't hold on this machine */
+  unavailable_options?: number[];
+}
+
+/** An applied tweak found without the option it was applied with (`tweak://external-change` event) */
+export interface ExternalChange {
+  tweak_id: string;
+  tweak_name: string;
+  /** Option the tweak was applied with */
+  applied_option_index: number;
+  applied_option_label: string;
+  /** Option it has now, or null if its state matches none of them */
+  current_option_index: number | null;
+  /** Whether auto-reapply applied the option again */
+  reapplied: boolean;
+  /** Why applying it again failed */
+  error?: string;
 }
 
 /** Combined tweak info for UI display */
@@ -301,6 +1164,10 @@ export interface OptionInspection {
   service_results: ServiceMismatch[];
   scheduler_results: SchedulerMismatch[];
   all_match: boolean;
+  /** Validatable (non-`skip_validation`) changes that match the current state */
+  matched_count: number;
+  /** Validatable changes that don't */
+  mismatched_count: number;
 }
 
 export interface TweakInspection {
@@ -309,6 +1176,14 @@ export interface TweakInspection {
   matched_option_index?: number;
 }
 
+/** A tweak's status with each option's per-change comparison with the current state */
+export interface TweakStatusDetail {
+  status: TweakStatus;
+  inspection: TweakInspection;
+  /** For a custom state, the option with the fewest mismatched changes */
+  closest_option_index?: number;
+}
+
 /** Device/system information from Win32_ComputerSystem */
 export interface DeviceInfo {
   /** System manufacturer (e.g., "Dell Inc.", "ASUS") */
@@ -387,12 +1262,29 @@ export interface MotherboardInfo {
   bios_version: string;
 }
 
+/** What a network adapter connects over */
+export type NetworkMedium = "ethernet" | "wifi" | "cellular" | "bluetooth" | "other" | "unknown";
+
+/** A Wi-Fi connection */
+export interface WifiInfo {
+  ssid: string;
+  /** Signal quality, 0-100 */
+  signal_percent: number;
+}
+
 /** Network adapter information */
 export interface NetworkInfo {
   name: string;
   mac_address: string;
   ip_address: string;
   dhcp_enabled: boolean;
+  medium: NetworkMedium;
+  /** Link speed in Mbps (the faster of receive and transmit), null while it has no link */
+  link_speed_mbps: number | null;
+  /** DNS servers in use (set by hand or by DHCP), IPv4 first, in the order they're tried */
+  dns_servers: string[];
+  /** The network a connected Wi-Fi adapter is on */
+  wifi: WifiInfo | null;
 }
 
 /** Hardware information */
@@ -414,6 +1306,81 @@ export interface HardwareInfo {
   total_storage_gb: number;
 }
 
+/** A part of the hardware info gathered on its own (see `refreshSystemInfo`) */
+export type HardwareSection = "cpu" | "gpu" | "monitors" | "memory" | "motherboard" | "disks" | "network" | "device";
+
+/** Where an installed program is registered */
+export type SoftwareScope = "machine" | "machine32" | "user";
+
+/** A program from the installed software inventory */
+export interface InstalledSoftware {
+  /** Name of the program's Uninstall registry key (a product code for MSI installs) */
+  id: string;
+  name: string;
+  version: string | null;
+  publisher: string | null;
+  /** Install date (YYYY-MM-DD), if the installer recorded one */
+  install_date: string | null;
+  /** Size the installer estimated, in KB */
+  estimated_size_kb: number | null;
+  scope: SoftwareScope;
+}
+
+/** Where a startup entry is registered */
+export type StartupSource =
+  | "run_machine"
+  | "run_machine32"
+  | "run_user"
+  | "startup_folder_machine"
+  | "startup_folder_user"
+  | "logon_task";
+
+/** How much a startup entry slows sign-in, as Task Manager rates it */
+export type StartupImpact = "high" | "medium" | "low" | "not_measured";
+
+/** A program that starts at sign-in */
+export interface StartupItem {
+  /** `<source>:<name>`, the name being the Run value, the Startup folder file, or the task path */
+  id: string;
+  name: string;
+  source: StartupSource;
+  /** Command line it runs (a shortcut's target and arguments), if known */
+  command: string | null;
+  enabled: boolean;
+  impact: StartupImpact;
+  /** CPU time its processes used since they started at sign-in, when measured */
+  cpu_ms: number | null;
+  /** Bytes those processes read and wrote */
+  io_bytes: number | null;
+}
+
+/** A change to a startup entry that can be undone */
+export interface StartupChange {
+  /** The entry's ID (see StartupItem.id) */
+  id: string;
+  /** Whether the change enabled the entry */
+  enabled: boolean;
+  /** When it was made (RFC 3339) */
+  changed_at: string;
+}
+
+/** Battery and power plan status */
+export interface PowerInfo {
+  has_battery: boolean;
+  /** Whether the machine runs on AC power; null if Windows can't tell */
+  on_ac_power: boolean | null;
+  charging: boolean;
+  /** Battery charge (0-100); null without a battery */
+  charge_percent: number | null;
+  design_capacity_mwh: number | null;
+  full_charge_capacity_mwh: number | null;
+  /** Full charge capacity as a percentage of the design capacity (battery wear) */
+  health_percent: number | null;
+  active_plan_guid: string | null;
+  /** Name of the active power plan (e.g. "Balanced") */
+  active_plan_name: string | null;
+}
+
 /** System information */
 export interface SystemInfo {
   windows: WindowsInfo;
@@ -422,6 +1389,162 @@ export interface SystemInfo {
   is_admin: boolean;
   hardware: HardwareInfo;
   device: DeviceInfo;
+  /** Battery and power plan status */
+  power: PowerInfo;
+  /** Whether Windows is waiting for a reboot (servicing, updates, or queued file renames) */
+  reboot_pending: boolean;
+}
+
+/** Why Windows is waiting for a reboot */
+export type RebootReason = "component_servicing" | "windows_update" | "file_renames";
+
+/** Windows Update status, from `get_update_status` */
+export interface UpdateStatus {
+  /** Titles of the updates Windows Update's last scan found that aren't installed; null if it couldn't be queried */
+  pending_updates: string[] | null;
+  /** When an update was last installed (RFC 3339) */
+  last_install: string | null;
+  last_install_title: string | null;
+  paused: boolean;
+  /** When the pause ends (RFC 3339), while updates are paused */
+  paused_until: string | null;
+  reboot_pending: boolean;
+  reboot_reasons: RebootReason[];
+}
+
+/** Live resource usage, sent as a `system://metrics` event while monitoring runs */
+export interface SystemMetrics {
+  /** When the sample was taken (RFC 3339) */
+  timestamp: string;
+  /** CPU usage since the previous sample, 0-100 */
+  cpu_percent: number;
+  memory_used_bytes: number;
+  memory_total_bytes: number;
+  /** Physical memory in use, 0-100 */
+  memory_percent: number;
+  /** Disk busy time since the previous sample, 0-100; null if the counter can't be read */
+  disk_percent: number | null;
+  /** GPU 3D engine usage since the previous sample, 0-100; null if the counter can't be read */
+  gpu_percent: number | null;
+  /** CPU temperature in °C; null unless monitoring was started with `thermal` and it can be read */
+  cpu_temp_celsius: number | null;
+  /** GPU temperature in °C; null unless monitoring was started with `thermal` and it can be read */
+  gpu_temp_celsius: number | null;
+}
+
+/** Where temperature and fan readings come from */
+export type ThermalSource = "libre_hardware_monitor" | "open_hardware_monitor" | "acpi" | "none";
+
+/** One temperature (°C) or fan (RPM) sensor */
+export interface ThermalSensor {
+  hardware: string;
+  name: string;
+  kind: "temperature" | "fan";
+  value: number;
+}
+
+/** Temperatures and fan speeds, from `get_thermal_info` */
+export interface ThermalInfo {
+  source: ThermalSource;
+  /** CPU temperature in °C (from ACPI, the hottest thermal zone) */
+  cpu_celsius: number | null;
+  gpu_celsius: number | null;
+  sensors: ThermalSensor[];
+}
+
+/** How UAC issued a token */
+export type TokenElevationType = "default" | "full" | "limited";
+
+/** Mandatory integrity level of a token */
+export type IntegrityLevel = "untrusted" | "low" | "medium" | "medium_plus" | "high" | "system" | "protected";
+
+/** A privilege held by a token */
+export interface TokenPrivilege {
+  /** Privilege name (e.g. "SeDebugPrivilege") */
+  name: string;
+  enabled: boolean;
+  enabled_by_default: boolean;
+}
+
+/** How long one phase of a tweak apply or revert took */
+export interface PhaseTiming {
+  phase: string;
+  duration_ms: number;
+}
+
+/** Phase timings of one tweak apply or revert */
+export interface OperationTiming {
+  tweak_id: string;
+  operation: "apply" | "revert";
+  /** When it started (ISO 8601) */
+  started_at: string;
+  total_ms: number;
+  success: boolean;
+  /** In the order they ran */
+  phases: PhaseTiming[];
+}
+
+/** The app process's token, for diagnosing elevation failures */
+export interface ProcessPrivileges {
+  elevation_type: TokenElevationType;
+  is_elevated: boolean;
+  integrity_level: IntegrityLevel;
+  /** Every privilege the token holds, enabled or not */
+  privileges: TokenPrivilege[];
+  /** Privileges SYSTEM / TrustedInstaller elevation needs that the token doesn't hold */
+  missing_for_elevation: string[];
+}
+
+/** A System Restore point */
+export interface RestorePoint {
+  /** Sequence number Windows uses to identify the point */
+  sequence_number: number;
+  description: string;
+  /** Creation time (ISO 8601, UTC) */
+  created_at: string;
+  /** RestorePointType (e.g. 12 = settings modified) */
+  restore_point_type: number;
+}
+
+/** A user profile HKCU tweaks can be applied to */
+export interface UserProfile {
+  sid: string;
+  /** Profile folder name (the user name unless the account was renamed) */
+  name: string;
+  profile_path: string;
+  /** Whether the app runs as this user */
+  is_current: boolean;
+  /** Whether the user's hive is loaded (signed in, or loaded by the app) */
+  loaded: boolean;
+}
+
+/** One operation run as SYSTEM or TrustedInstaller */
+export interface AuditEntry {
+  seq: number;
+  /** When it finished (RFC 3339) */
+  timestamp: string;
+  /** "SYSTEM" or "TrustedInstaller" */
+  level: string;
+  /** What ran: the command line, or the typed operation */
+  command: string;
+  /** Exit code (0 for a non-command operation that succeeded); null when it failed without one */
+  exit_code: number | null;
+  error: string | null;
+  duration_ms: number;
+  prev_hash: string;
+  hash: string;
+}
+
+/** The elevation audit log */
+export interface AuditLog {
+  /** Newest first */
+  entries: AuditEntry[];
+  /** Whether every entry is intact and in its place, and the last one is the head recorded in the registry */
+  intact: boolean;
+  /** Line (from 1) where the hash chain first breaks */
+  broken_at_line: number | null;
+  /** How the last entry compares with the recorded head: "truncated" when entries were cut off the end */
+  head: "matches" | "truncated" | "mismatch" | "missing";
 }
 
 /**
@@ -443,8 +1566,46 @@ export interface TweakResult {
   success: boolean;
   message: string;
   requires_reboot: boolean;
+  /** A sign-out is needed (only set when no reboot is) */
+  requires_logoff?: boolean;
+  /** explorer.exe was restarted to pick up the change */
+  restarted_explorer?: boolean;
   /** List of [tweak_id, error_message] for failed operations in batch mode */
   failures?: [string, string][];
+  /** Sequence number of the restore point created before a batch apply */
+  restore_point_sequence?: number;
+  /** Outcome of each tweak in batch mode, in the order they ran */
+  results?: BatchItemResult[];
+  /** What a transactional batch apply undid after one of its tweaks failed */
+  rollback?: BatchRollbackReport;
+}
+
+/** Rollback of a transactional batch apply */
+export interface BatchRollbackReport {
+  /** Tweak whose failure stopped the batch */
+  failed_tweak_id: string;
+  /** Tweaks returned to their pre-batch state, newest first */
+  items: BatchRollbackItem[];
+  /** Tweaks the batch never reached */
+  skipped: string[];
+}
+
+/** Rollback of one tweak of a transactional batch */
+export interface BatchRollbackItem {
+  tweak_id: string;
+  /** Every value was verifiably restored (otherwise the tweak keeps its snapshot) */
+  restored: boolean;
+  failures?: string[];
+}
+
+/** Outcome of one tweak in a batch apply or revert */
+export interface BatchItemResult {
+  tweak_id: string;
+  success: boolean;
+  message: string;
+  requires_reboot: boolean;
+  /** Code of the error that stopped the tweak, e.g. "REBOOT_PENDING" */
+  error_code?: string;
 }
 
 /** Batch apply result */
@@ -461,6 +1622,26 @@ export interface PendingChange {
   tweakId: string;
   /** Option index to apply */
   optionIndex: number;
+  /** Apply even though the tweak is still waiting for a reboot from its last change */
+  force?: boolean;
+  /** The user confirmed the risk of a high or critical risk tweak (the apply then asks for a token) */
+  riskAcknowledged?: boolean;
+}
+
+/** A staged change handed to the instance relaunched as administrator */
+export interface PendingOperation {
+  tweak_id: string;
+  option_index: number;
+  force?: boolean;
+  risk_acknowledged?: boolean;
+}
+
+/** Token from `acknowledge_risk` that lets one apply change a high or critical risk tweak */
+export interface RiskAcknowledgement {
+  tweak_id: string;
+  token: string;
+  /** Seconds until the token expires */
+  expires_in_secs: number;
 }
 
 /**
@@ -503,6 +1684,10 @@ export interface AppSettings {
   checkUpdateInterval: number;
   /** Last time an update check was performed (ISO 8601) */
   lastUpdateCheck: string | null;
+  /** Whether to create a System Restore point before applying pending changes */
+  createRestorePoint: boolean;
+  /** Whether a failed tweak rolls back the rest of the pending changes applied with it */
+  transactionalApply?: boolean;
 }
 
 /** Tweak snapshot for export - captures current registry state */
@@ -546,6 +1731,39 @@ export interface UpdateCheckResult {
   error?: string;
 }
 
+/** The channel tweak packs are downloaded from */
+export interface TweakPackSettings {
+  /** The channel's base URL (https) */
+  url: string | null;
+  /** The channel's Ed25519 public key, 64 hex digits */
+  public_key: string | null;
+}
+
+/** One YAML file of a tweak pack */
+export interface PackFile {
+  name: string;
+  sha256: string;
+}
+
+/** A tweak pack's signed manifest */
+export interface PackManifest {
+  name: string;
+  /** Increases with each release of the pack */
+  version: number;
+  description: string | null;
+  files: PackFile[];
+}
+
+/** What checking the tweak pack channel found */
+export interface TweakPackUpdate {
+  /** Whether the channel has a newer version than the one installed or staged */
+  available: boolean;
+  installed: PackManifest | null;
+  /** The pack waiting for the next launch */
+  staged: PackManifest | null;
+  latest: PackManifest;
+}
+
 // ============================================================================
 // PERMISSION LEVEL HELPERS
 // ============================================================================